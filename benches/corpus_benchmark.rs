@@ -0,0 +1,172 @@
+//! Corpus benchmarks for ALICE-Text
+//!
+//! Compress / decompress / query throughput per mode over the bundled
+//! corpora in `benches/corpora` (see its README). Run one group with e.g.
+//! `cargo bench --bench corpus_benchmark -- v2_compress/syslog`.
+
+use std::io::Cursor;
+use std::time::Duration;
+
+use alice_text::{
+    compress_v3, CompressionLevel, CompressionMode, FormatV3Writer, Op, QueryEngine,
+    TunedCompressor,
+};
+use criterion::measurement::WallTime;
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion, Throughput,
+};
+
+/// A representative input and the v3 query typical for it
+struct Corpus {
+    name: &'static str,
+    text: &'static str,
+    /// (SELECT columns, WHERE column, op, value)
+    query: Option<(&'static [&'static str], &'static str, Op, &'static str)>,
+}
+
+const CORPORA: &[Corpus] = &[
+    Corpus {
+        name: "syslog",
+        text: include_str!("corpora/syslog.log"),
+        query: Some((&["timestamps"], "log_levels", Op::Eq, "ERROR")),
+    },
+    Corpus {
+        name: "access",
+        text: include_str!("corpora/access.log"),
+        query: Some((&["numbers"], "ipv4", Op::Ge, "192.168.8.0")),
+    },
+    Corpus {
+        name: "jsonl",
+        text: include_str!("corpora/events.jsonl"),
+        query: Some((&["uuids"], "log_levels", Op::Eq, "WARN")),
+    },
+    Corpus {
+        name: "dialogue",
+        text: include_str!("corpora/dialogue.txt"),
+        query: None,
+    },
+    Corpus {
+        name: "prose",
+        text: include_str!("corpora/prose.txt"),
+        query: None,
+    },
+];
+
+// `Max` (context mixing) is orders of magnitude slower and left out
+const MODES: [CompressionMode; 3] = [
+    CompressionMode::Fast,
+    CompressionMode::Balanced,
+    CompressionMode::Best,
+];
+
+const LEVELS: [CompressionLevel; 3] = [
+    CompressionLevel::Fast,
+    CompressionLevel::Balanced,
+    CompressionLevel::Best,
+];
+
+/// Short runs: 5 corpora x 3 modes per group
+fn configure(group: &mut BenchmarkGroup<'_, WallTime>) {
+    group
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(2));
+}
+
+fn v2_compress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("v2_compress");
+    configure(&mut group);
+    for corpus in CORPORA {
+        group.throughput(Throughput::Bytes(corpus.text.len() as u64));
+        for mode in MODES {
+            let id = BenchmarkId::new(corpus.name, format!("{mode:?}"));
+            group.bench_with_input(id, corpus.text, |b, text| {
+                let mut compressor = TunedCompressor::new(mode);
+                b.iter(|| compressor.compress(black_box(text)).unwrap());
+            });
+        }
+    }
+    group.finish();
+}
+
+fn v2_decompress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("v2_decompress");
+    configure(&mut group);
+    for corpus in CORPORA {
+        group.throughput(Throughput::Bytes(corpus.text.len() as u64));
+        for mode in MODES {
+            let archive = TunedCompressor::new(mode).compress(corpus.text).unwrap();
+            let id = BenchmarkId::new(corpus.name, format!("{mode:?}"));
+            group.bench_with_input(id, &archive, |b, archive| {
+                let compressor = TunedCompressor::default();
+                b.iter(|| compressor.decompress(black_box(archive)).unwrap());
+            });
+        }
+    }
+    group.finish();
+}
+
+fn v3_compress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("v3_compress");
+    configure(&mut group);
+    for corpus in CORPORA {
+        group.throughput(Throughput::Bytes(corpus.text.len() as u64));
+        for level in LEVELS {
+            let id = BenchmarkId::new(corpus.name, format!("{level:?}"));
+            group.bench_with_input(id, corpus.text, |b, text| {
+                let writer = FormatV3Writer::new(level);
+                b.iter(|| writer.compress(black_box(text)).unwrap());
+            });
+        }
+    }
+    group.finish();
+}
+
+fn v3_decompress(c: &mut Criterion) {
+    let mut group = c.benchmark_group("v3_decompress");
+    configure(&mut group);
+    for corpus in CORPORA {
+        group.throughput(Throughput::Bytes(corpus.text.len() as u64));
+        for level in LEVELS {
+            let archive = compress_v3(corpus.text, level).unwrap();
+            let id = BenchmarkId::new(corpus.name, format!("{level:?}"));
+            group.bench_with_input(id, &archive, |b, archive| {
+                b.iter(|| FormatV3Writer::decompress(black_box(archive)).unwrap());
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Open the archive and run the corpus query (partial decompression)
+fn v3_query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("v3_query");
+    configure(&mut group);
+    for corpus in CORPORA {
+        let Some((select, column, op, value)) = corpus.query else {
+            continue;
+        };
+        group.throughput(Throughput::Bytes(corpus.text.len() as u64));
+        for level in LEVELS {
+            let archive = compress_v3(corpus.text, level).unwrap();
+            let id = BenchmarkId::new(corpus.name, format!("{level:?}"));
+            group.bench_with_input(id, &archive, |b, archive| {
+                b.iter(|| {
+                    let engine = QueryEngine::from_reader(Cursor::new(black_box(archive))).unwrap();
+                    engine.query(select, column, op, value).unwrap()
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    v2_compress,
+    v2_decompress,
+    v3_compress,
+    v3_decompress,
+    v3_query
+);
+criterion_main!(benches);
@@ -0,0 +1,102 @@
+//! Message-bus sink example (feature: `bus`)
+//!
+//! Stands in for a Vector `exec`/`socket` sink or a Kafka producer/consumer
+//! pair, with stdin/stdout as the bus:
+//!
+//! ```text
+//! cargo run --example bus_sink --features bus -- produce 500 < app.log > app.frames
+//! cargo run --example bus_sink --features bus -- consume < app.frames
+//! ```
+//!
+//! `produce` reads lines, and for every batch of N lines emits the batch
+//! state followed by one `Line` frame per record. With a real bus each frame
+//! is one message (state on a compacted topic keyed by model id); `consume`
+//! keeps the models it has seen and decodes records as they arrive.
+
+use alice_text::bus::{Frame, FrameDecoder, FrameEncoder, FrameKind};
+use alice_text::{TextModel, TunedCompressor};
+use bytes::BytesMut;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("produce") => {
+            let batch_size = args.get(2).map_or(Ok(500), |n| n.parse())?;
+            produce(batch_size)
+        }
+        Some("consume") => consume(),
+        _ => {
+            eprintln!("usage: bus_sink produce [BATCH_LINES] | consume");
+            std::process::exit(2);
+        }
+    }
+}
+
+fn produce(batch_size: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let compressor = TunedCompressor::default();
+    let mut out = io::stdout().lock();
+    let mut lines = io::stdin().lock().lines();
+    let (mut records, mut bytes) = (0usize, 0usize);
+    loop {
+        let batch: Vec<String> = lines.by_ref().take(batch_size).collect::<io::Result<_>>()?;
+        if batch.is_empty() {
+            break;
+        }
+        let encoded = compressor.compress_lines(batch.iter().map(String::as_str))?;
+        let state = Frame::state(encoded.state.into());
+        let model_id = state.model_id;
+        let mut buf = BytesMut::new();
+        FrameEncoder.encode(&state, &mut buf)?;
+        for frame in encoded.frames {
+            FrameEncoder.encode(&Frame::line(model_id, frame.into()), &mut buf)?;
+        }
+        out.write_all(&buf)?;
+        records += batch.len();
+        bytes += buf.len();
+    }
+    eprintln!("{records} records -> {bytes} bytes of frames");
+    Ok(())
+}
+
+fn consume() -> Result<(), Box<dyn std::error::Error>> {
+    let mut models: HashMap<u64, TunedCompressor> = HashMap::new();
+    let mut decoder = FrameDecoder::default();
+    let mut buf = BytesMut::new();
+    let mut stdin = io::stdin().lock();
+    let mut out = io::stdout().lock();
+    let mut chunk = [0u8; 64 * 1024];
+    loop {
+        let n = stdin.read(&mut chunk)?;
+        buf.extend_from_slice(&chunk[..n]);
+        while let Some(frame) = decoder.decode(&mut buf)? {
+            match frame.kind {
+                FrameKind::State => {
+                    let model = TextModel::from_bytes(&frame.payload)?;
+                    models.insert(
+                        frame.model_id,
+                        TunedCompressor::default().with_model(&model)?,
+                    );
+                }
+                FrameKind::Line => {
+                    let reader = models
+                        .get(&frame.model_id)
+                        .ok_or_else(|| format!("model {:016x} not seen yet", frame.model_id))?;
+                    writeln!(out, "{}", reader.decompress_line(&frame.payload)?)?;
+                }
+                FrameKind::Archive => {
+                    let text = TunedCompressor::default().decompress(&frame.payload)?;
+                    out.write_all(text.as_bytes())?;
+                }
+            }
+        }
+        if n == 0 {
+            break;
+        }
+    }
+    if !buf.is_empty() {
+        return Err(format!("{} trailing bytes (truncated frame)", buf.len()).into());
+    }
+    Ok(())
+}
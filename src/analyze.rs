@@ -0,0 +1,399 @@
+//! Analyze — per-column size report and configuration advice for v3 archives
+//!
+//! Backs `alice-text analyze`. [`AnalyzeReport::build`] decompresses every
+//! column once to measure it and count its values, and checks
+//! a few alternative encodings by actually compressing them at the archive's
+//! own zstd level:
+//!
+//! - **Dictionary encoding** for string columns (distinct values + `u32` indices)
+//! - **Integer packing** for mostly-integral `numbers` (zigzag varints instead of `f64`)
+//!
+//! A suggestion is only made when the measured saving reaches
+//! [`MIN_SAVING`]. Two share-based hints point at pattern configuration
+//! instead: a large `others` column and timestamps that fell back to raw strings.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use crate::column_codec::{self, ColumnCodec};
+use crate::format_v3::{self, ColumnEntry, ColumnType};
+use crate::query_engine::{QueryEngine, QuerySource};
+use crate::{ALICETextError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+/// Smallest estimated saving (fraction of the column) worth suggesting
+pub const MIN_SAVING: f64 = 0.10;
+
+/// Share of compressed bytes in `others` above which custom patterns are suggested
+const OTHERS_SHARE_HINT: f64 = 0.25;
+
+/// Share of integral values above which integer packing is evaluated
+const INTEGER_SHARE_HINT: f64 = 0.90;
+
+/// Size and content of one column
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnReport {
+    pub name: String,
+    pub col_type: ColumnType,
+    pub rows: u32,
+    /// Serialized size before zstd
+    pub raw_size: u64,
+    pub compressed_size: u32,
+    /// Fraction of the archive's compressed column bytes
+    pub share: f64,
+    /// Number of distinct values (value columns only)
+    pub distinct: Option<usize>,
+    /// Most frequent values with their counts, most frequent first
+    pub top_values: Vec<(String, usize)>,
+}
+
+/// One configuration hint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub column: String,
+    pub message: String,
+    /// Measured size reduction of the column (0.0-1.0), if an encoding was tried
+    pub saving: Option<f64>,
+}
+
+/// Column-level compression report for one archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzeReport {
+    pub original_size: u64,
+    pub compressed_size: u64,
+    pub row_count: u64,
+    /// zstd level the columns were compressed with
+    pub zstd_level: i32,
+    /// Columns, largest compressed size first
+    pub columns: Vec<ColumnReport>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl AnalyzeReport {
+    /// Analyze every column of `engine`, keeping `top_n` top values per column
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column cannot be read or decompressed.
+    pub fn build<S: QuerySource>(engine: &QueryEngine<S>, top_n: usize) -> Result<Self> {
+        let metadata = engine.metadata();
+        let level = metadata.level().zstd_level();
+        let total = metadata.compressed_size().max(1);
+
+        let mut columns = Vec::with_capacity(metadata.columns.len());
+        let mut suggestions = Vec::new();
+        for entry in &metadata.columns {
+            let name = entry.col_type.name().to_string();
+            let bytes = engine.column_bytes(entry)?;
+            let mut measured = None;
+            let values = match entry.col_type {
+                ColumnType::Skeleton
+                | ColumnType::PlaceholderMap
+                | ColumnType::RawBytes
+                | ColumnType::Spellings => None,
+                col if is_string_column(col) => {
+                    let strings: Vec<String> = decode_column(entry, &bytes)?;
+                    measured = dictionary_saving(&strings, entry.compressed_size, level)?
+                        .map(|saving| (None, saving));
+                    Some(strings)
+                }
+                ColumnType::Numbers => {
+                    let numbers = format_v3::decode_numbers(entry, &bytes)?;
+                    measured = integer_saving(&numbers, entry.compressed_size, level)?
+                        .map(|(integers, saving)| (Some(integers), saving));
+                    Some(engine.select_column(&name)?)
+                }
+                _ => Some(engine.select_column(&name)?),
+            };
+            let (distinct, top_values) = values.map_or((None, Vec::new()), |values| {
+                let (distinct, top) = top_values(&values, top_n);
+                (Some(distinct), top)
+            });
+
+            match measured {
+                Some((Some(integers), saving)) => suggestions.push(Suggestion {
+                    message: format!(
+                        "{name} column has {:.0}% integers — integer packing would shrink it {:.0}%",
+                        integers * 100.0,
+                        saving * 100.0
+                    ),
+                    column: name.clone(),
+                    saving: Some(saving),
+                }),
+                Some((None, saving)) => suggestions.push(Suggestion {
+                    message: format!(
+                        "{name} column would shrink {:.0}% with dictionary encoding ({} distinct values in {} rows)",
+                        saving * 100.0,
+                        distinct.unwrap_or_default(),
+                        entry.row_count
+                    ),
+                    column: name.clone(),
+                    saving: Some(saving),
+                }),
+                None => {}
+            }
+            columns.push(ColumnReport {
+                name,
+                col_type: entry.col_type,
+                rows: entry.row_count,
+                raw_size: bytes.len() as u64,
+                compressed_size: entry.compressed_size,
+                share: f64::from(entry.compressed_size) / total as f64,
+                distinct,
+                top_values,
+            });
+        }
+
+        if let Some(others) = columns.iter().find(|c| c.col_type == ColumnType::Others) {
+            if others.share >= OTHERS_SHARE_HINT {
+                suggestions.push(Suggestion {
+                    column: others.name.clone(),
+                    message: format!(
+                        "others column holds {:.0}% of the archive — a custom pattern could move its values into a typed column",
+                        others.share * 100.0
+                    ),
+                    saving: None,
+                });
+            }
+        }
+        let rows_of = |col: ColumnType| {
+            columns
+                .iter()
+                .find(|c| c.col_type == col)
+                .map_or(0, |c| u64::from(c.rows))
+        };
+        let raw = rows_of(ColumnType::TimestampsRaw);
+        let parsed = rows_of(ColumnType::Timestamps);
+        if raw > 0 && raw * 10 >= raw + parsed {
+            suggestions.push(Suggestion {
+                column: ColumnType::TimestampsRaw.name().to_string(),
+                message: format!(
+                    "{raw} timestamps are stored as raw strings (format not recognized) — they cannot be range-filtered or delta-encoded"
+                ),
+                saving: None,
+            });
+        }
+
+        columns.sort_by(|a, b| b.compressed_size.cmp(&a.compressed_size));
+        suggestions.sort_by(|a, b| b.saving.unwrap_or(0.0).total_cmp(&a.saving.unwrap_or(0.0)));
+        Ok(Self {
+            original_size: metadata.header.original_length,
+            compressed_size: metadata.compressed_size(),
+            row_count: metadata.header.row_count,
+            zstd_level: level,
+            columns,
+            suggestions,
+        })
+    }
+}
+
+/// Deserialize one decompressed column block
+fn decode_column<T: ColumnCodec>(entry: &ColumnEntry, bytes: &[u8]) -> Result<T> {
+    column_codec::decode_column(bytes).map_err(|e| {
+        ALICETextError::DecompressionError(format!("{} column: {e}", entry.col_type.name()))
+    })
+}
+
+/// Columns stored as a `Vec<String>`
+const fn is_string_column(col: ColumnType) -> bool {
+    matches!(
+        col,
+        ColumnType::Emails
+            | ColumnType::URLs
+            | ColumnType::Paths
+            | ColumnType::DatesRaw
+            | ColumnType::TimesRaw
+            | ColumnType::HexValues
+            | ColumnType::Others
+            | ColumnType::TimestampsRaw
+            | ColumnType::AnsiCodes
+    )
+}
+
+/// Distinct count and the `n` most frequent values (ties by value)
+fn top_values(values: &[String], n: usize) -> (usize, Vec<(String, usize)>) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let distinct = counts.len();
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let top = counts
+        .into_iter()
+        .take(n)
+        .map(|(value, count)| (value.to_string(), count))
+        .collect();
+    (distinct, top)
+}
+
+fn zstd_size(bytes: &[u8], level: i32) -> Result<usize> {
+    zstd::stream::encode_all(Cursor::new(bytes), level)
+        .map(|c| c.len())
+        .map_err(ALICETextError::Io)
+}
+
+/// Fractional saving of `candidate` over `current` compressed bytes, if worth suggesting
+fn saving(candidate: usize, current: u32) -> Option<f64> {
+    let current = f64::from(current);
+    let saving = 1.0 - candidate as f64 / current;
+    (current > 0.0 && saving >= MIN_SAVING).then_some(saving)
+}
+
+/// Measured saving of dictionary-encoding `values` (distinct list + `u32` index per row)
+fn dictionary_saving(values: &[String], compressed: u32, level: i32) -> Result<Option<f64>> {
+    let mut ids: HashMap<&str, u32> = HashMap::new();
+    let mut dictionary: Vec<&str> = Vec::new();
+    let mut encoded = Vec::with_capacity(values.len() * 4);
+    for value in values {
+        let id = *ids.entry(value).or_insert_with(|| {
+            dictionary.push(value);
+            (dictionary.len() - 1) as u32
+        });
+        encoded.extend_from_slice(&id.to_le_bytes());
+    }
+    if dictionary.len() == values.len() {
+        return Ok(None);
+    }
+    let mut candidate = Vec::new();
+    (dictionary.len() as u64).encode(&mut candidate);
+    for value in dictionary {
+        value.encode(&mut candidate);
+    }
+    candidate.extend_from_slice(&encoded);
+    Ok(saving(zstd_size(&candidate, level)?, compressed))
+}
+
+/// Integral share of `numbers` and the measured saving of zigzag-varint packing
+///
+/// Non-integral values stay `f64` in a side list in the estimate.
+fn integer_saving(numbers: &[f64], compressed: u32, level: i32) -> Result<Option<(f64, f64)>> {
+    const MAX_EXACT: f64 = 9_007_199_254_740_992.0; // 2^53
+    let integral = numbers
+        .iter()
+        .filter(|n| n.fract() == 0.0 && n.abs() <= MAX_EXACT)
+        .count();
+    if numbers.is_empty() || (integral as f64) < numbers.len() as f64 * INTEGER_SHARE_HINT {
+        return Ok(None);
+    }
+    let mut packed = Vec::with_capacity(numbers.len() * 2);
+    let mut rest = Vec::new();
+    for &n in numbers {
+        if n.fract() == 0.0 && n.abs() <= MAX_EXACT {
+            let v = n as i64;
+            let mut zigzag = ((v << 1) ^ (v >> 63)) as u64;
+            while zigzag >= 0x80 {
+                packed.push((zigzag as u8) | 0x80);
+                zigzag >>= 7;
+            }
+            packed.push(zigzag as u8);
+        } else {
+            // 0x80 0x00 never ends a canonical varint: marks a value from `rest`
+            packed.extend_from_slice(&[0x80, 0x00]);
+            rest.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+    packed.extend_from_slice(&rest);
+    let share = integral as f64 / numbers.len() as f64;
+    Ok(saving(zstd_size(&packed, level)?, compressed).map(|saving| (share, saving)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::column_codec::encode_column;
+    use crate::{compress_v3, CompressionLevel};
+
+    #[test]
+    fn test_analyze_report() {
+        let mut text = String::new();
+        for i in 0..2000 {
+            let path = ["/api/users", "/api/orders", "/health"][i % 3];
+            let level = if i % 10 == 0 { "ERROR" } else { "INFO" };
+            text.push_str(&format!(
+                "2024-01-15 10:{:02}:{:02} {level} GET https://example.com{path} took {} ms\n",
+                (i / 60) % 60,
+                i % 60,
+                i % 500
+            ));
+        }
+        let data = compress_v3(&text, CompressionLevel::Balanced).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(data)).unwrap();
+        let report = AnalyzeReport::build(&engine, 3).unwrap();
+
+        assert_eq!(report.row_count, 2000);
+        assert!(report
+            .columns
+            .windows(2)
+            .all(|w| w[0].compressed_size >= w[1].compressed_size));
+        let share: f64 = report.columns.iter().map(|c| c.share).sum();
+        assert!((share - 1.0).abs() < 1e-9);
+
+        let levels = report
+            .columns
+            .iter()
+            .find(|c| c.col_type == ColumnType::LogLevels)
+            .unwrap();
+        assert_eq!(levels.distinct, Some(2));
+        assert_eq!(levels.top_values[0], ("INFO".to_string(), 1800));
+        let urls = report
+            .columns
+            .iter()
+            .find(|c| c.col_type == ColumnType::URLs)
+            .unwrap();
+        assert_eq!(urls.distinct, Some(3));
+        assert!(report
+            .columns
+            .iter()
+            .any(|c| c.col_type == ColumnType::Skeleton
+                && c.distinct.is_none()
+                && c.top_values.is_empty()));
+
+        // Every measured suggestion clears the threshold
+        assert!(report
+            .suggestions
+            .iter()
+            .filter_map(|s| s.saving)
+            .all(|saving| saving >= MIN_SAVING));
+    }
+
+    #[test]
+    fn test_encoding_estimates() {
+        // Few distinct high-entropy strings in random order: an index per
+        // row beats zstd back-references to the full string
+        let mut state = 1u64;
+        let values: Vec<String> = (0..5000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let key = (state >> 33) % 200;
+                format!("{:016x}", key.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+            })
+            .collect();
+        let plain = encode_column(&values);
+        let current = zstd_size(&plain, 3).unwrap() as u32;
+        assert!(dictionary_saving(&values, current, 3).unwrap().is_some());
+        // All distinct: nothing to share
+        let unique: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        assert!(dictionary_saving(&unique, 1000, 3).unwrap().is_none());
+
+        // Random request sizes and latencies
+        let numbers: Vec<f64> = (0..5000)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                ((state >> 33) % 100_000) as f64
+            })
+            .collect();
+        let current = zstd_size(&encode_column(&numbers), 3).unwrap() as u32;
+        let (share, saving) = integer_saving(&numbers, current, 3).unwrap().unwrap();
+        assert!((share - 1.0).abs() < f64::EPSILON);
+        assert!(saving >= MIN_SAVING);
+        let fractional: Vec<f64> = (0..100).map(|i| f64::from(i) + 0.5).collect();
+        assert!(integer_saving(&fractional, 1000, 3).unwrap().is_none());
+    }
+}
@@ -0,0 +1,669 @@
+//! Appender — `log` / `tracing` integration writing rotated v3 archives
+//!
+//! Feature-gated: `#[cfg(feature = "appender")]` (`tracing` layer: `#[cfg(feature = "tracing")]`)
+//!
+//! Formatted events are buffered in memory and flushed as a self-contained
+//! v3 archive (`{prefix}-{YYYYmmdd-HHMMSS}-{seq}.atxt`) whenever the batch
+//! exceeds the size limit or the time limit of the [`RotationPolicy`].
+//! Each archive is queryable with `QueryEngine` / `alice-text query`.
+//! Lines added with [`ArchiveAppender::append_line_from`] also record their
+//! source, so tailing several files into one appender keeps a `provenance`
+//! column.
+//!
+//! Installing a logger returns an [`AppenderGuard`]: it rotates batches that
+//! reach the age limit while no new events arrive, and writes the final
+//! partial batch when dropped (keep it alive in `main`).
+//!
+//! ```rust,no_run
+//! use alice_text::appender::AliceTextLogger;
+//!
+//! let _guard = AliceTextLogger::new("logs", "app").unwrap().init().unwrap();
+//! log::info!("service started");
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+#[cfg(feature = "tracing")]
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+
+use crate::columnar_encoder;
+use crate::format_v3::compress_v3;
+use crate::format_v3::FormatV3Writer;
+use crate::level::Level;
+use crate::provenance::SourceBatch;
+use crate::safe_write::SafeFileWriter;
+use crate::Result;
+
+/// Archive file extension
+const ARCHIVE_EXT: &str = "atxt";
+
+/// Event timestamp format (second precision, round-trips through the timestamp column)
+const EVENT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// When to close the current batch and start a new archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Rotate once the buffered text reaches this many bytes
+    pub max_bytes: usize,
+    /// Rotate once the oldest buffered event is this old
+    pub max_age: Duration,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: 4 * 1024 * 1024,
+            max_age: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Batching archive writer shared by [`AliceTextLogger`] and `AliceTextLayer`
+#[derive(Debug)]
+pub struct ArchiveAppender {
+    dir: PathBuf,
+    prefix: String,
+    level: Level,
+    policy: RotationPolicy,
+    batch: SourceBatch,
+    /// Some line of the current batch named its source
+    with_provenance: bool,
+    batch_started: Option<Instant>,
+    sequence: u64,
+    writer: SafeFileWriter,
+}
+
+impl ArchiveAppender {
+    /// Create an appender writing into `dir` (created if missing)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn new(dir: impl AsRef<Path>, prefix: &str) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            prefix: prefix.to_string(),
+            level: Level::Balanced,
+            policy: RotationPolicy::default(),
+            batch: SourceBatch::new(),
+            with_provenance: false,
+            batch_started: None,
+            sequence: 0,
+            writer: SafeFileWriter::new(),
+        })
+    }
+
+    /// Set the rotation policy
+    #[must_use]
+    pub fn with_rotation(mut self, policy: RotationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the archive compression level
+    #[must_use]
+    pub fn with_level(mut self, level: impl Into<Level>) -> Self {
+        self.level = level.into();
+        self
+    }
+
+    /// Set how archives are written (default: atomic rename with fsync)
+    #[must_use]
+    pub fn with_writer(mut self, writer: SafeFileWriter) -> Self {
+        self.writer = writer;
+        self
+    }
+
+    /// Bytes currently buffered (not yet written)
+    #[inline]
+    pub fn buffered_len(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// Append one line, rotating if the policy limit is reached
+    ///
+    /// Returns the path of the archive written by the rotation, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the archive write fails.
+    pub fn append_line(&mut self, line: &str) -> Result<Option<PathBuf>> {
+        self.append(None, line)
+    }
+
+    /// Append one line read from `source` (file path, host, stream id)
+    ///
+    /// The batch's archive gets a `provenance` column; lines added with
+    /// [`Self::append_line`] are attributed to the appender prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the archive write fails.
+    pub fn append_line_from(&mut self, source: &str, line: &str) -> Result<Option<PathBuf>> {
+        self.append(Some(source), line)
+    }
+
+    fn append(&mut self, source: Option<&str>, line: &str) -> Result<Option<PathBuf>> {
+        if self.batch_started.is_none() {
+            self.batch_started = Some(Instant::now());
+        }
+        self.with_provenance |= source.is_some();
+        let line = format!("{}\n", line.trim_end_matches('\n'));
+        self.batch.add(source.unwrap_or(&self.prefix), &line);
+        self.rotate_if_due()
+    }
+
+    /// Write the buffered batch if it has hit the size or age limit
+    ///
+    /// Returns the archive path, or `None` if no rotation was due.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::flush`].
+    pub fn rotate_if_due(&mut self) -> Result<Option<PathBuf>> {
+        if self.should_rotate() {
+            self.flush()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether the current batch has hit the size or age limit
+    pub fn should_rotate(&self) -> bool {
+        self.batch.len() >= self.policy.max_bytes
+            || self
+                .batch_started
+                .is_some_and(|t| t.elapsed() >= self.policy.max_age)
+    }
+
+    /// Write the buffered batch as a v3 archive
+    ///
+    /// Returns the archive path, or `None` if nothing was buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the archive write fails.
+    /// The batch is kept in memory on failure.
+    pub fn flush(&mut self) -> Result<Option<PathBuf>> {
+        if self.batch.is_empty() {
+            return Ok(None);
+        }
+
+        let compressed = if self.with_provenance {
+            FormatV3Writer::new(self.level).compress_batch(&self.batch)?
+        } else {
+            compress_v3(self.batch.text(), self.level)?
+        };
+        let path = self.dir.join(format!(
+            "{}-{}-{:04}.{ARCHIVE_EXT}",
+            self.prefix,
+            Utc::now().format("%Y%m%d-%H%M%S"),
+            self.sequence
+        ));
+        self.writer.write(&path, &compressed)?;
+
+        self.sequence += 1;
+        self.batch.clear();
+        self.with_provenance = false;
+        self.batch_started = None;
+        Ok(Some(path))
+    }
+}
+
+impl Drop for ArchiveAppender {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Keeps an installed appender's batches moving; flushes when dropped
+///
+/// Returned by [`AliceTextLogger::init`] and `AliceTextLayer::guard`. A
+/// background thread applies the age limit of the [`RotationPolicy`] while
+/// no events arrive (otherwise it is only checked on the next append), and
+/// dropping the guard writes the final partial batch. Global loggers are
+/// never dropped, so without the guard that batch would be lost at exit.
+#[must_use = "dropping the guard flushes and stops age-based rotation"]
+#[derive(Debug)]
+pub struct AppenderGuard {
+    appender: Arc<Mutex<ArchiveAppender>>,
+    stop: Option<mpsc::Sender<()>>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl AppenderGuard {
+    fn new(appender: Arc<Mutex<ArchiveAppender>>) -> Self {
+        let interval = appender.lock().map_or(Duration::from_secs(1), |a| {
+            (a.policy.max_age / 4).clamp(Duration::from_millis(10), Duration::from_secs(1))
+        });
+        let (stop, stopped) = mpsc::channel::<()>();
+        let shared = Arc::clone(&appender);
+        let ticker = std::thread::Builder::new()
+            .name("alice-appender".to_string())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    if let Ok(mut appender) = shared.lock() {
+                        let _ = appender.rotate_if_due();
+                    }
+                }
+            })
+            .ok();
+        Self {
+            appender,
+            stop: Some(stop),
+            ticker,
+        }
+    }
+
+    /// Write the buffered batch now
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the archive write fails.
+    pub fn flush(&self) -> Result<Option<PathBuf>> {
+        match self.appender.lock() {
+            Ok(mut appender) => appender.flush(),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl Drop for AppenderGuard {
+    fn drop(&mut self) {
+        // Disconnecting the channel stops the ticker
+        self.stop = None;
+        if let Some(ticker) = self.ticker.take() {
+            let _ = ticker.join();
+        }
+        let _ = self.flush();
+    }
+}
+
+// ── log ────────────────────────────────────────────────────────
+
+/// `log::Log` implementation backed by an [`ArchiveAppender`]
+///
+/// Lines are formatted as `{timestamp} {LEVEL} {target}: {message}` so the
+/// timestamp and log-level columns are extracted by the columnar encoder.
+#[derive(Debug)]
+pub struct AliceTextLogger {
+    appender: Arc<Mutex<ArchiveAppender>>,
+    max_level: log::LevelFilter,
+}
+
+impl AliceTextLogger {
+    /// Create a logger writing into `dir` with the default rotation policy
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn new(dir: impl AsRef<Path>, prefix: &str) -> Result<Self> {
+        Ok(Self::from_appender(ArchiveAppender::new(dir, prefix)?))
+    }
+
+    /// Wrap a configured appender
+    pub fn from_appender(appender: ArchiveAppender) -> Self {
+        Self {
+            appender: Arc::new(Mutex::new(appender)),
+            max_level: log::LevelFilter::Info,
+        }
+    }
+
+    /// Set the maximum level recorded (default: `Info`)
+    #[must_use]
+    pub fn with_max_level(mut self, level: log::LevelFilter) -> Self {
+        self.max_level = level;
+        self
+    }
+
+    /// Install as the global logger
+    ///
+    /// Keep the returned guard until shutdown: it rotates aged batches and
+    /// writes the last one when dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a global logger is already set.
+    pub fn init(self) -> std::result::Result<AppenderGuard, log::SetLoggerError> {
+        let appender = Arc::clone(&self.appender);
+        log::set_max_level(self.max_level);
+        log::set_boxed_logger(Box::new(self))?;
+        Ok(AppenderGuard::new(appender))
+    }
+}
+
+impl log::Log for AliceTextLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        // Encoder diagnostics are emitted while the appender is locked
+        metadata.level() <= self.max_level && metadata.target() != columnar_encoder::LOG_TARGET
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {} {}: {}",
+            Utc::now().format(EVENT_TIME_FORMAT),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if let Ok(mut appender) = self.appender.lock() {
+            let _ = appender.append_line(&line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut appender) = self.appender.lock() {
+            let _ = appender.flush();
+        }
+    }
+}
+
+// ── tracing ────────────────────────────────────────────────────
+
+/// `tracing_subscriber::Layer` backed by an [`ArchiveAppender`]
+///
+/// Events are formatted as `{timestamp} {LEVEL} {target}: {message} key=value ...`.
+/// Clones share the same appender, so a handle can be kept for explicit flushes.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone)]
+pub struct AliceTextLayer {
+    appender: Arc<Mutex<ArchiveAppender>>,
+}
+
+#[cfg(feature = "tracing")]
+impl AliceTextLayer {
+    /// Create a layer writing into `dir` with the default rotation policy
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn new(dir: impl AsRef<Path>, prefix: &str) -> Result<Self> {
+        Ok(Self::from_appender(ArchiveAppender::new(dir, prefix)?))
+    }
+
+    /// Wrap a configured appender
+    pub fn from_appender(appender: ArchiveAppender) -> Self {
+        Self {
+            appender: Arc::new(Mutex::new(appender)),
+        }
+    }
+
+    /// Guard that rotates aged batches and writes the last one when dropped
+    pub fn guard(&self) -> AppenderGuard {
+        AppenderGuard::new(Arc::clone(&self.appender))
+    }
+
+    /// Write the buffered batch now
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the archive write fails.
+    pub fn flush(&self) -> Result<Option<PathBuf>> {
+        match self.appender.lock() {
+            Ok(mut appender) => appender.flush(),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+/// Collects `message` and the remaining fields of an event
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    fields: String,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for EventVisitor {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for AliceTextLayer {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        // Encoder diagnostics (bridged by `tracing-log`) arrive while the appender is locked
+        if event.metadata().target() == columnar_encoder::LOG_TARGET {
+            return;
+        }
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+
+        let meta = event.metadata();
+        let mut line = String::with_capacity(64 + visitor.message.len() + visitor.fields.len());
+        let _ = write!(
+            line,
+            "{} {} {}: {}{}",
+            Utc::now().format(EVENT_TIME_FORMAT),
+            meta.level(),
+            meta.target(),
+            visitor.message,
+            visitor.fields
+        );
+        if let Ok(mut appender) = self.appender.lock() {
+            let _ = appender.append_line(&line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_v3::decompress_v3;
+
+    fn archives(dir: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == ARCHIVE_EXT))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    fn read_all(dir: &Path) -> String {
+        archives(dir)
+            .iter()
+            .map(|p| decompress_v3(&fs::read(p).unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_appender_size_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut appender = ArchiveAppender::new(dir.path(), "app")
+            .unwrap()
+            .with_rotation(RotationPolicy {
+                max_bytes: 200,
+                max_age: Duration::from_secs(3600),
+            });
+
+        let mut expected = String::new();
+        for i in 0..20 {
+            let line = format!("2024-01-15 10:30:{i:02} INFO request {i} served");
+            expected.push_str(&line);
+            expected.push('\n');
+            appender.append_line(&line).unwrap();
+        }
+        appender.flush().unwrap();
+
+        assert!(archives(dir.path()).len() > 1);
+        assert_eq!(read_all(dir.path()), expected);
+    }
+
+    #[test]
+    fn test_appender_age_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut appender = ArchiveAppender::new(dir.path(), "app")
+            .unwrap()
+            .with_rotation(RotationPolicy {
+                max_bytes: usize::MAX,
+                max_age: Duration::ZERO,
+            });
+
+        let written = appender
+            .append_line("2024-01-15 10:30:00 INFO ready")
+            .unwrap();
+        assert!(written.is_some());
+        assert_eq!(appender.buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_appender_flush_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut appender = ArchiveAppender::new(dir.path(), "app").unwrap();
+            appender
+                .append_line("2024-01-15 10:30:00 WARN low disk")
+                .unwrap();
+            assert!(archives(dir.path()).is_empty());
+        }
+        assert_eq!(read_all(dir.path()), "2024-01-15 10:30:00 WARN low disk\n");
+    }
+
+    #[test]
+    fn test_guard_rotates_idle_batch_and_flushes() {
+        let dir = tempfile::tempdir().unwrap();
+        let appender = ArchiveAppender::new(dir.path(), "app")
+            .unwrap()
+            .with_rotation(RotationPolicy {
+                max_bytes: usize::MAX,
+                max_age: Duration::from_millis(40),
+            });
+        let appender = Arc::new(Mutex::new(appender));
+        let guard = AppenderGuard::new(Arc::clone(&appender));
+
+        // Aged out with no further appends
+        appender
+            .lock()
+            .unwrap()
+            .append_line("2024-01-15 10:30:00 INFO idle")
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while archives(dir.path()).is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(read_all(dir.path()), "2024-01-15 10:30:00 INFO idle\n");
+
+        // The partial batch is written when the guard goes, not the appender
+        let mut appender = appender.lock().unwrap();
+        appender.policy.max_age = Duration::from_secs(3600);
+        appender
+            .append_line("2024-01-15 10:30:01 INFO last")
+            .unwrap();
+        drop(appender);
+        drop(guard);
+        assert_eq!(
+            read_all(dir.path()),
+            "2024-01-15 10:30:00 INFO idle\n2024-01-15 10:30:01 INFO last\n"
+        );
+    }
+
+    #[test]
+    fn test_appender_provenance() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut appender = ArchiveAppender::new(dir.path(), "app").unwrap();
+        appender
+            .append_line("2024-01-15 10:30:00 INFO local")
+            .unwrap();
+        appender
+            .append_line_from("/var/log/a.log", "2024-01-15 10:30:01 WARN remote")
+            .unwrap();
+        appender
+            .append_line_from("/var/log/a.log", "2024-01-15 10:30:02 INFO remote")
+            .unwrap();
+        let path = appender.flush().unwrap().unwrap();
+
+        let provenance = FormatV3Writer::read_provenance(&fs::read(path).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(provenance.describe(0).as_deref(), Some("app:1"));
+        assert_eq!(provenance.describe(2).as_deref(), Some("/var/log/a.log:2"));
+        assert_eq!(provenance.lines[2].byte_offset, 32);
+
+        // Batches without named sources stay plain
+        appender
+            .append_line("2024-01-15 10:30:03 INFO plain")
+            .unwrap();
+        let path = appender.flush().unwrap().unwrap();
+        assert!(FormatV3Writer::read_provenance(&fs::read(path).unwrap())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_logger_records() {
+        use log::Log;
+
+        let dir = tempfile::tempdir().unwrap();
+        let logger = AliceTextLogger::new(dir.path(), "svc").unwrap();
+        logger.log(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("svc::db")
+                .args(format_args!("slow query"))
+                .build(),
+        );
+        logger.log(
+            &log::Record::builder()
+                .level(log::Level::Debug)
+                .args(format_args!("filtered out"))
+                .build(),
+        );
+        logger.flush();
+
+        let text = read_all(dir.path());
+        assert_eq!(text.lines().count(), 1);
+        assert!(text.ends_with(" WARN svc::db: slow query\n"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_layer() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let layer = AliceTextLayer::new(dir.path(), "svc").unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(user = "alice", "logged in");
+        });
+        layer.flush().unwrap();
+
+        let text = read_all(dir.path());
+        assert!(text.contains(" INFO "));
+        assert!(text.ends_with(": logged in user=alice\n"));
+    }
+}
@@ -1,6 +1,14 @@
 //! Arithmetic coding module for ALICE-Text
 //!
 //! Provides entropy-optimal encoding for symbol sequences.
+//!
+//! [`FrequencyModel`] is a static model built from the data up front;
+//! [`AdaptiveModel`] learns while coding, so nothing but the coded bits is
+//! stored. [`EscapeModel`] starts empty and introduces each new byte through
+//! an escape symbol, so it needs no alphabet up front ([`encode_online`] /
+//! [`decode_online`]). [`encode_order1`] / [`decode_order1`] use one adaptive
+//! model per previous symbol — the entropy stage `TunedCompressor` applies to
+//! the placeholder-type and log-level streams in `CompressionMode::Best`.
 
 use std::collections::HashMap;
 
@@ -11,6 +19,22 @@ const HALF: u64 = WHOLE / 2;
 const QUARTER: u64 = WHOLE / 4;
 const THREE_QUARTERS: u64 = 3 * QUARTER;
 
+/// Most symbols one input byte can carry under an [`AdaptiveModel`] of two or
+/// more symbols: no count drops below 1 of at most `MAX_TOTAL + INCREMENT`,
+/// so every symbol costs at least log2(65568 / 65567) bits (~45k per bit)
+const MAX_ADAPTIVE_SYMBOLS_PER_BYTE: usize = 1 << 19;
+
+/// Output buffer for `count` symbols decoded from `input_len` bytes
+///
+/// Only as much as the input plausibly expands to is reserved up front, and
+/// a failed reservation is `None` rather than an abort.
+pub(crate) fn symbol_buffer(count: usize, input_len: usize) -> Option<Vec<u8>> {
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(count.min(input_len.saturating_mul(8).saturating_add(64)))
+        .ok()?;
+    Some(buf)
+}
+
 /// Frequency model for symbols
 #[derive(Debug, Clone)]
 pub struct FrequencyModel {
@@ -114,6 +138,196 @@ impl Default for FrequencyModel {
     }
 }
 
+/// Adaptive frequency model over the symbols `0..alphabet`
+///
+/// Starts uniform; encoder and decoder apply the same updates after every
+/// symbol, so the model never has to be transmitted.
+#[derive(Debug, Clone)]
+pub struct AdaptiveModel {
+    counts: Vec<u32>,
+    total: u32,
+}
+
+impl AdaptiveModel {
+    /// Count added per occurrence (large relative to the initial 1 so the
+    /// model adapts quickly on short streams)
+    const INCREMENT: u32 = 32;
+    /// Counts are halved when the total exceeds this
+    const MAX_TOTAL: u32 = 1 << 16;
+
+    /// Uniform model over `alphabet` symbols (clamped to 1..=256)
+    #[must_use]
+    pub fn new(alphabet: usize) -> Self {
+        let alphabet = alphabet.clamp(1, 256);
+        Self {
+            counts: vec![1; alphabet],
+            total: alphabet as u32,
+        }
+    }
+
+    /// Number of symbols
+    #[must_use]
+    pub fn alphabet(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Sum of all counts
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        self.total as u64
+    }
+
+    /// Cumulative range of a symbol: (low, high, total)
+    #[must_use]
+    pub fn get_range(&self, symbol: u8) -> Option<(u64, u64, u64)> {
+        let symbol = symbol as usize;
+        let count = *self.counts.get(symbol)?;
+        let low: u32 = self.counts[..symbol].iter().sum();
+        Some((
+            u64::from(low),
+            u64::from(low + count),
+            u64::from(self.total),
+        ))
+    }
+
+    /// Symbol whose cumulative range contains `value`
+    #[must_use]
+    pub fn get_symbol(&self, value: u64) -> Option<u8> {
+        let mut high = 0u64;
+        for (symbol, &count) in self.counts.iter().enumerate() {
+            high += u64::from(count);
+            if value < high {
+                return Some(symbol as u8);
+            }
+        }
+        None
+    }
+
+    /// Record one occurrence of `symbol`
+    pub fn update(&mut self, symbol: u8) {
+        let Some(count) = self.counts.get_mut(symbol as usize) else {
+            return;
+        };
+        *count += Self::INCREMENT;
+        self.total += Self::INCREMENT;
+        if self.total > Self::MAX_TOTAL {
+            for count in &mut self.counts {
+                *count = count.div_ceil(2);
+            }
+            self.total = self.counts.iter().sum();
+        }
+    }
+}
+
+/// Adaptive byte model that starts empty, with an escape for unseen symbols
+///
+/// Seen symbols are coded with their counts; a new symbol is coded as the
+/// escape followed by its byte value at a uniform 1/256, and is then added.
+/// The escape's count is the number of distinct symbols seen (PPM method C),
+/// so escapes get cheap while new symbols keep appearing and expensive once
+/// the alphabet has settled.
+#[derive(Debug, Clone)]
+pub struct EscapeModel {
+    counts: Vec<u32>,
+    distinct: u32,
+    total: u32,
+}
+
+impl EscapeModel {
+    const INCREMENT: u32 = 32;
+    const MAX_TOTAL: u32 = 1 << 16;
+
+    /// Empty model (every first occurrence escapes)
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; 256],
+            distinct: 0,
+            total: 0,
+        }
+    }
+
+    /// Whether `symbol` has been seen
+    #[must_use]
+    pub fn contains(&self, symbol: u8) -> bool {
+        self.counts[symbol as usize] > 0
+    }
+
+    /// Number of distinct symbols seen
+    #[must_use]
+    pub const fn distinct(&self) -> usize {
+        self.distinct as usize
+    }
+
+    /// Escape count (at least 1 so the escape is always codable)
+    const fn escape_count(&self) -> u32 {
+        if self.distinct == 0 {
+            1
+        } else {
+            self.distinct
+        }
+    }
+
+    /// Sum of symbol counts plus the escape count
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        (self.total + self.escape_count()) as u64
+    }
+
+    /// Cumulative range of a seen symbol: (low, high, total)
+    #[must_use]
+    pub fn get_range(&self, symbol: u8) -> Option<(u64, u64, u64)> {
+        let symbol = symbol as usize;
+        let count = self.counts[symbol];
+        if count == 0 {
+            return None;
+        }
+        let low: u32 = self.counts[..symbol].iter().sum();
+        Some((u64::from(low), u64::from(low + count), self.total()))
+    }
+
+    /// Range of the escape symbol (after all seen symbols)
+    #[must_use]
+    pub const fn escape_range(&self) -> (u64, u64, u64) {
+        (self.total as u64, self.total(), self.total())
+    }
+
+    /// Seen symbol at `value`, or `None` for the escape
+    #[must_use]
+    pub fn get_symbol(&self, value: u64) -> Option<u8> {
+        let mut high = 0u64;
+        for (symbol, &count) in self.counts.iter().enumerate() {
+            high += u64::from(count);
+            if value < high {
+                return Some(symbol as u8);
+            }
+        }
+        None
+    }
+
+    /// Record one occurrence of `symbol` (adding it if new)
+    pub fn update(&mut self, symbol: u8) {
+        let count = &mut self.counts[symbol as usize];
+        if *count == 0 {
+            self.distinct += 1;
+        }
+        *count += Self::INCREMENT;
+        self.total += Self::INCREMENT;
+        if self.total > Self::MAX_TOTAL {
+            for count in &mut self.counts {
+                *count = count.div_ceil(2);
+            }
+            self.total = self.counts.iter().sum();
+        }
+    }
+}
+
+impl Default for EscapeModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Arithmetic encoder
 pub struct ArithmeticEncoder {
     /// Low bound
@@ -148,16 +362,54 @@ impl ArithmeticEncoder {
     #[inline(always)]
     pub fn encode_symbol(&mut self, symbol: u8, model: &FrequencyModel) {
         if let Some((sym_low, sym_high, total)) = model.get_range(symbol) {
-            let range = (self.high - self.low + 1) as u128;
-            // Pre-compute total once to avoid repeated division setup
-            let total128 = total as u128;
+            self.encode_range(sym_low, sym_high, total);
+        }
+    }
 
-            // Use u128 to avoid overflow
-            self.high = self.low + ((range * sym_high as u128 / total128) as u64) - 1;
-            self.low += (range * sym_low as u128 / total128) as u64;
+    /// Encode a symbol with an adaptive model, then update the model
+    #[inline(always)]
+    pub fn encode_adaptive(&mut self, symbol: u8, model: &mut AdaptiveModel) {
+        if let Some((sym_low, sym_high, total)) = model.get_range(symbol) {
+            self.encode_range(sym_low, sym_high, total);
+            model.update(symbol);
+        }
+    }
 
-            self.normalize();
+    /// Encode a byte with an escape model (escape + literal if unseen), then update the model
+    pub fn encode_escaped(&mut self, symbol: u8, model: &mut EscapeModel) {
+        if let Some((sym_low, sym_high, total)) = model.get_range(symbol) {
+            self.encode_range(sym_low, sym_high, total);
+        } else {
+            let (esc_low, esc_high, total) = model.escape_range();
+            self.encode_range(esc_low, esc_high, total);
+            self.encode_range(u64::from(symbol), u64::from(symbol) + 1, 256);
         }
+        model.update(symbol);
+    }
+
+    /// Encode one bit with P(1) = `p1` / 4096 (binary coding for bitwise models)
+    #[inline(always)]
+    pub fn encode_bit(&mut self, bit: bool, p1: u16) {
+        let p1 = u64::from(p1.clamp(1, 4095));
+        if bit {
+            self.encode_range(0, p1, 4096);
+        } else {
+            self.encode_range(p1, 4096, 4096);
+        }
+    }
+
+    /// Narrow the interval to `[sym_low, sym_high)` out of `total`
+    #[inline(always)]
+    fn encode_range(&mut self, sym_low: u64, sym_high: u64, total: u64) {
+        let range = (self.high - self.low + 1) as u128;
+        // Pre-compute total once to avoid repeated division setup
+        let total128 = total as u128;
+
+        // Use u128 to avoid overflow
+        self.high = self.low + ((range * sym_high as u128 / total128) as u64) - 1;
+        self.low += (range * sym_low as u128 / total128) as u64;
+
+        self.normalize();
     }
 
     /// Encode data using a frequency model
@@ -318,32 +570,84 @@ impl ArithmeticDecoder {
             return None;
         }
 
-        let range = (self.high - self.low + 1) as u128;
-        // Pre-compute total once to avoid repeated division setup
-        let total128 = model.total() as u128;
+        // Find symbol for this value
+        let symbol = model.get_symbol(self.target(model.total()))?;
+        let (sym_low, sym_high, total) = model.get_range(symbol)?;
+        self.consume(sym_low, sym_high, total);
+
+        Some(symbol)
+    }
+
+    /// Decode a symbol with an adaptive model, then update the model
+    #[inline(always)]
+    pub fn decode_adaptive(&mut self, model: &mut AdaptiveModel) -> Option<u8> {
+        let symbol = model.get_symbol(self.target(model.total()))?;
+        let (sym_low, sym_high, total) = model.get_range(symbol)?;
+        self.consume(sym_low, sym_high, total);
+        model.update(symbol);
+
+        Some(symbol)
+    }
+
+    /// Decode a byte with an escape model, then update the model
+    pub fn decode_escaped(&mut self, model: &mut EscapeModel) -> Option<u8> {
+        let symbol = match model.get_symbol(self.target(model.total())) {
+            Some(symbol) => {
+                let (sym_low, sym_high, total) = model.get_range(symbol)?;
+                self.consume(sym_low, sym_high, total);
+                symbol
+            }
+            None => {
+                let (esc_low, esc_high, total) = model.escape_range();
+                self.consume(esc_low, esc_high, total);
+                let symbol = u8::try_from(self.target(256)).ok()?;
+                self.consume(u64::from(symbol), u64::from(symbol) + 1, 256);
+                symbol
+            }
+        };
+        model.update(symbol);
+
+        Some(symbol)
+    }
 
-        // Calculate the cumulative frequency value
+    /// Decode one bit coded with [`ArithmeticEncoder::encode_bit`]
+    #[inline(always)]
+    pub fn decode_bit(&mut self, p1: u16) -> bool {
+        let p1 = u64::from(p1.clamp(1, 4095));
+        let bit = self.target(4096) < p1;
+        if bit {
+            self.consume(0, p1, 4096);
+        } else {
+            self.consume(p1, 4096, 4096);
+        }
+        bit
+    }
+
+    /// Cumulative frequency value the current code falls on
+    #[inline(always)]
+    fn target(&self, total: u64) -> u64 {
+        let range = (self.high - self.low + 1) as u128;
         // value = ((code - low + 1) * total - 1) / range
         let code_offset = (self.code - self.low) as u128;
-        let value = ((code_offset + 1) * total128 - 1) / range;
+        (((code_offset + 1) * total as u128 - 1) / range) as u64
+    }
 
-        // Find symbol for this value
-        let symbol = model.get_symbol(value as u64)?;
-        let (sym_low, sym_high, _) = model.get_range(symbol)?;
+    /// Narrow the interval to the decoded symbol's range (mirrors the encoder)
+    #[inline(always)]
+    fn consume(&mut self, sym_low: u64, sym_high: u64, total: u64) {
+        let range = (self.high - self.low + 1) as u128;
+        let total128 = total as u128;
 
         // Update interval using u128
         self.high = self.low + ((range * sym_high as u128 / total128) as u64) - 1;
         self.low += (range * sym_low as u128 / total128) as u64;
 
-        // Normalize
         self.normalize();
-
-        Some(symbol)
     }
 
     /// Decode n symbols
     pub fn decode(&mut self, model: &FrequencyModel, count: usize) -> Vec<u8> {
-        let mut result = Vec::with_capacity(count);
+        let mut result = symbol_buffer(count, self.input.len()).unwrap_or_default();
         for _ in 0..count {
             if let Some(symbol) = self.decode_symbol(model) {
                 result.push(symbol);
@@ -380,6 +684,83 @@ impl ArithmeticDecoder {
     }
 }
 
+/// Code bytes in one pass with an [`EscapeModel`] (no model or alphabet stored)
+///
+/// The byte count is not stored; empty input gives empty output.
+#[must_use]
+pub fn encode_online(data: &[u8]) -> Vec<u8> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let mut model = EscapeModel::new();
+    let mut encoder = ArithmeticEncoder::new();
+    for &byte in data {
+        encoder.encode_escaped(byte, &mut model);
+    }
+    encoder.finish()
+}
+
+/// Inverse of [`encode_online`]
+#[must_use]
+pub fn decode_online(data: &[u8], count: usize) -> Option<Vec<u8>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    let mut model = EscapeModel::new();
+    let mut decoder = ArithmeticDecoder::new(data.to_vec());
+    (0..count)
+        .map(|_| decoder.decode_escaped(&mut model))
+        .collect()
+}
+
+/// Code `symbols` with order-1 adaptive models (one per previous symbol)
+///
+/// Output: alphabet size minus one (`u8`), then the coded bits. The symbol
+/// count is not stored; empty input gives empty output.
+#[must_use]
+pub fn encode_order1(symbols: &[u8]) -> Vec<u8> {
+    let Some(&max) = symbols.iter().max() else {
+        return Vec::new();
+    };
+    let alphabet = max as usize + 1;
+    let mut models = vec![AdaptiveModel::new(alphabet); alphabet];
+    let mut encoder = ArithmeticEncoder::new();
+    let mut context = 0;
+    for &symbol in symbols {
+        encoder.encode_adaptive(symbol, &mut models[context]);
+        context = symbol as usize;
+    }
+    let mut out = vec![max];
+    out.extend(encoder.finish());
+    out
+}
+
+/// Inverse of [`encode_order1`]; `None` if the data can't hold `count` symbols
+///
+/// A one-symbol alphabet costs no bits, so only the caller can bound `count`
+/// there; otherwise counts past what `data` could code are rejected up front.
+#[must_use]
+pub fn decode_order1(data: &[u8], count: usize) -> Option<Vec<u8>> {
+    if count == 0 {
+        return Some(Vec::new());
+    }
+    let (&max, bits) = data.split_first()?;
+    if max > 0 && count / MAX_ADAPTIVE_SYMBOLS_PER_BYTE > bits.len() + 8 {
+        return None;
+    }
+    let alphabet = max as usize + 1;
+    let mut models = vec![AdaptiveModel::new(alphabet); alphabet];
+    let mut decoder = ArithmeticDecoder::new(bits.to_vec());
+    let mut symbols = symbol_buffer(count, bits.len())?;
+    let mut context = 0;
+    for _ in 0..count {
+        let symbol = decoder.decode_adaptive(&mut models[context])?;
+        symbols.push(symbol);
+        context = symbol as usize;
+    }
+    Some(symbols)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -573,6 +954,67 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_order1_adaptive_roundtrip() {
+        // Skewed, context-dependent stream (log levels: mostly INFO, ERROR after WARN)
+        let levels: Vec<u8> = (0..2000u32)
+            .map(|i| match i % 50 {
+                10 => 3,
+                11 => 4,
+                20 => 1,
+                _ => 2,
+            })
+            .collect();
+        let encoded = encode_order1(&levels);
+        assert!(encoded.len() < levels.len() / 8, "{}", encoded.len());
+        assert_eq!(decode_order1(&encoded, levels.len()).unwrap(), levels);
+
+        for symbols in [&[][..], &[0], &[255, 0, 255, 7]] {
+            let encoded = encode_order1(symbols);
+            assert_eq!(decode_order1(&encoded, symbols.len()).unwrap(), symbols);
+        }
+        assert!(decode_order1(&[], 3).is_none());
+
+        // Counts the input cannot code fail before any work or allocation
+        let encoded = encode_order1(&[0, 1, 0, 1]);
+        assert!(decode_order1(&encoded, usize::MAX).is_none());
+        assert!(decode_order1(&encoded, 1 << 40).is_none());
+
+        let mut model = AdaptiveModel::new(3);
+        for _ in 0..5000 {
+            model.update(1);
+        }
+        let (low, high, total) = model.get_range(1).unwrap();
+        assert!(total <= 1 << 16 && high - low > total / 2);
+        assert!(model.get_range(3).is_none());
+    }
+
+    #[test]
+    fn test_online_escape_roundtrip() {
+        let mut data = b"GET /index.html 200\nGET /api 404\n".repeat(40);
+        data.extend(0..=255u8);
+        let encoded = encode_online(&data);
+        assert_eq!(decode_online(&encoded, data.len()).unwrap(), data);
+
+        // One pass, no table: beats a static model once its table is counted
+        let text = b"aaaaaaaaabaaaaaaaacaaaaaaaaaaaaaaaabaaaaaaaaaaaaaaa".repeat(20);
+        let model = FrequencyModel::from_data(&text);
+        let mut encoder = ArithmeticEncoder::new();
+        encoder.encode(&text, &model);
+        let static_size = encoder.finish().len() + 3 * 9; // (symbol, u64 count) per symbol
+        assert!(encode_online(&text).len() < static_size);
+
+        let mut model = EscapeModel::new();
+        assert_eq!(model.escape_range(), (0, 1, 1));
+        model.update(b'x');
+        assert!(model.contains(b'x') && !model.contains(b'y'));
+        assert_eq!(model.distinct(), 1);
+        assert_eq!(model.get_symbol(model.escape_range().0), None);
+
+        assert_eq!(decode_online(&[], 0).unwrap(), b"");
+        assert!(encode_online(&[]).is_empty());
+    }
+
     #[test]
     fn test_roundtrip_long_repetitive_sequence() {
         // 1000 bytes of the same character
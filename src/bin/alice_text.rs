@@ -2,15 +2,30 @@
 //!
 //! Command-line interface for ALICE-Text compression.
 
+// --- Global Allocator: mimalloc (Microsoft's high-performance allocator) ---
+#[cfg(all(feature = "mimalloc", not(target_env = "msvc")))]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+use alice_text::saved_view;
+use alice_text::stream::{decompress_stream_with, is_stream};
 use alice_text::{
-    compress_v3, ALICEText, CompressionLevel, CompressionMode, EntropyEstimator, FormatV3Metadata,
-    Op, QueryEngine, TunedCompressor,
+    classify, compress_against_with_stats, decompress_with_reference, delete_rows, embed_views,
+    merge_archives, parse_negation, resolve_reference, retention, split_archive, ALICEText,
+    ALICETextError, AnalyzeReport, BlockStrategy, ColumnCache, CompressOptions, Config,
+    ConfigLoader, DecodeOptions, EntropyEstimator, FlushPolicy, FormatV3Metadata, FormatV3Writer,
+    Level, MmapSource, Op, PatternConfig, PatternLimits, QueryEngine, QueryResult, QueryRow,
+    RedactionPolicy, SafeFileWriter, SamplingStrategy, SavedView, SourceBatch, StreamCompressor,
+    TextModel, TunedCompressor, TunedHeader, ZstdParams, DEFAULT_CACHE_SIZE, DEFAULT_MAX_LINE_LEN,
+    MODEL_EXT,
 };
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
 use std::fs;
 use std::io::{self, Cursor, Read, Write};
-use std::path::PathBuf;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{mpsc, OnceLock};
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "alice-text")]
@@ -30,6 +45,95 @@ Only surprises are transmitted, not predictions.
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Skip fsync when writing output files (faster; still replaced atomically)
+    #[arg(long, global = true)]
+    no_fsync: bool,
+
+    /// Use at most N threads for parallel work (default: one per core)
+    #[arg(long, global = true, value_name = "N")]
+    threads: Option<usize>,
+
+    /// Stable output for scripts: tab-separated, no headers or decoration
+    #[arg(long, global = true)]
+    porcelain: bool,
+
+    /// Compression defaults file (default: ~/.config/alice-text.toml if present)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+/// How output files are written (set once from `--no-fsync`)
+static OUTPUT_WRITER: OnceLock<SafeFileWriter> = OnceLock::new();
+
+/// Atomically replace `path` with `bytes` (temp file + fsync + rename)
+fn write_output(path: impl AsRef<Path>, bytes: &[u8]) -> alice_text::Result<()> {
+    OUTPUT_WRITER
+        .get_or_init(SafeFileWriter::new)
+        .write(path, bytes)
+}
+
+/// Whether `--porcelain` output was requested (set once in `main`)
+static PORCELAIN: OnceLock<bool> = OnceLock::new();
+
+fn porcelain() -> bool {
+    PORCELAIN.get().copied().unwrap_or(false)
+}
+
+/// Exit status: the input is corrupt or not an archive of the expected kind
+/// (also any failure without a more specific status)
+const EXIT_CORRUPT: u8 = 1;
+/// Exit status: invalid arguments (clap uses the same code for parse errors)
+const EXIT_USAGE: u8 = 2;
+/// Exit status: a file could not be read or written
+const EXIT_IO: u8 = 3;
+
+/// An error with an explicit exit status
+#[derive(Debug)]
+struct CliError {
+    code: u8,
+    message: String,
+}
+
+impl CliError {
+    fn corrupt(message: impl Into<String>) -> Box<Self> {
+        Box::new(Self {
+            code: EXIT_CORRUPT,
+            message: message.into(),
+        })
+    }
+
+    fn usage(message: impl Into<String>) -> Box<Self> {
+        Box::new(Self {
+            code: EXIT_USAGE,
+            message: message.into(),
+        })
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Exit status for an error that ended the command
+fn exit_code(error: &(dyn std::error::Error + 'static)) -> u8 {
+    if let Some(e) = error.downcast_ref::<CliError>() {
+        e.code
+    } else if error.is::<io::Error>() {
+        EXIT_IO
+    } else if let Some(e) = error.downcast_ref::<ALICETextError>() {
+        match e {
+            ALICETextError::Io(_) => EXIT_IO,
+            ALICETextError::InvalidQuery(_) => EXIT_USAGE,
+            _ => EXIT_CORRUPT,
+        }
+    } else {
+        EXIT_CORRUPT
+    }
 }
 
 #[derive(Subcommand)]
@@ -43,13 +147,65 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Compression level: fast, balanced, best
-        #[arg(short, long, default_value = "balanced")]
-        level: String,
+        /// Compression level: fast, balanced, best, max (context mixing, slow) or a zstd level (1-22) [default: config file, else balanced]
+        #[arg(short, long)]
+        level: Option<String>,
 
         /// Show verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Decode the output before writing and fail if it does not match the input
+        #[arg(long)]
+        verify: bool,
+
+        /// Guard against common misclassifications (version strings as IPv4, ids as numbers)
+        #[arg(long)]
+        strict_patterns: bool,
+
+        /// Kubernetes preset: pod names, namespaces, container ids, klog and CRI prefixes in their own columns
+        #[arg(long)]
+        k8s: bool,
+
+        /// Drop ANSI escape sequences (colors) instead of storing them (lossy)
+        #[arg(long)]
+        strip_ansi: bool,
+
+        /// Lines longer than BYTES are stored without pattern extraction (0: no limit)
+        #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_LINE_LEN)]
+        max_line_len: usize,
+
+        /// Zstd long-distance matching (finds repeats far apart in multi-GB inputs)
+        #[arg(long)]
+        long: bool,
+
+        /// Zstd window log (10-31); above 27 only this version or newer can decode the archive
+        #[arg(long, value_name = "LOG")]
+        window_log: Option<u32>,
+
+        /// Zstd compression threads (0: compress on the main thread)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        zstd_workers: u32,
+
+        /// Print compression statistics as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Split into ~KIB-sized blocks, each routed to columnar, dedup or raw-zstd
+        #[arg(long, value_name = "KIB")]
+        route_blocks: Option<usize>,
+
+        /// Shared model (.atxm): its pattern config and dictionary are used
+        #[arg(short, long)]
+        model: Option<PathBuf>,
+
+        /// Stream mode: append a block at least every SECS seconds (e.g. `journalctl -f | ...`)
+        #[arg(long, value_name = "SECS")]
+        flush_interval: Option<u64>,
+
+        /// Stream mode: append a block whenever BYTES of input are buffered
+        #[arg(long, value_name = "BYTES")]
+        flush_bytes: Option<usize>,
     },
 
     /// Decompress a file
@@ -60,12 +216,43 @@ enum Commands {
         /// Output file (default: stdout)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Model (.atxm) the archive was compressed with
+        #[arg(short, long)]
+        model: Option<PathBuf>,
+    },
+
+    /// Train a shareable model (.atxm) on sample files
+    Train {
+        /// Sample files (representative of what will be compressed)
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output file
+        #[arg(short, long, default_value = "model.atxm")]
+        output: PathBuf,
+
+        /// Dictionary size in KiB (0 = pattern config and stats only)
+        #[arg(long, value_name = "KIB", default_value = "64")]
+        dict_size: usize,
+
+        /// Record the strict pattern config in the model
+        #[arg(long)]
+        strict_patterns: bool,
+
+        /// Record the Kubernetes preset in the model
+        #[arg(long)]
+        k8s: bool,
     },
 
     /// Show file information
     Info {
         /// Input file (.atxt)
         input: PathBuf,
+
+        /// Print the information as JSON (v3: with the column directory)
+        #[arg(long)]
+        json: bool,
     },
 
     /// Estimate compression for a file
@@ -76,6 +263,10 @@ enum Commands {
         /// Show detailed output
         #[arg(short, long)]
         detailed: bool,
+
+        /// Print the estimate as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Verify a compressed file
@@ -101,17 +292,65 @@ enum Commands {
         #[arg(short, long)]
         select: Option<String>,
 
-        /// Filter condition: column=value
+        /// Filter condition: column=value, column in a,b; prefix with `not ` to
+        /// exclude matching lines (repeat to require several on the same line)
         #[arg(short = 'w', long = "where")]
-        filter: Option<String>,
+        filter: Vec<String>,
 
-        /// Output format: table, csv, json
+        /// Output format: table, csv, json (also applies to --stats)
         #[arg(short, long, default_value = "table")]
         format: String,
 
         /// Limit output rows
         #[arg(short, long)]
         limit: Option<usize>,
+
+        /// Also print the full original line of each match (requires --where)
+        #[arg(long)]
+        lines: bool,
+
+        /// Print the N most frequent values of each selected column, with
+        /// counts, over the lines matching --where
+        #[arg(long, value_name = "N")]
+        top: Option<usize>,
+
+        /// Run a saved view (its selection and filters; --select replaces the
+        /// selection, --where adds filters)
+        #[arg(long, value_name = "NAME")]
+        view: Option<String>,
+
+        /// List saved views (archive and INPUT.atxv sidecar)
+        #[arg(long)]
+        views: bool,
+
+        /// Keep decoded columns in this directory for later runs (size-capped)
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+
+    /// Save a named query for an archive (in the INPUT.atxv sidecar, or embedded)
+    SaveView {
+        /// Input file (.atxt, v3 format)
+        input: PathBuf,
+
+        /// View name (replaces a view of the same name)
+        name: String,
+
+        /// Columns to select (comma-separated)
+        #[arg(short, long)]
+        select: Option<String>,
+
+        /// Filter condition, as for `query --where` (repeatable)
+        #[arg(short = 'w', long = "where")]
+        filter: Vec<String>,
+
+        /// What the view is for
+        #[arg(short, long, default_value = "")]
+        description: String,
+
+        /// Store the view inside the archive (re-encodes it) instead of the sidecar
+        #[arg(long)]
+        embed: bool,
     },
 
     /// Compress file using v3 format (columnar, queryable)
@@ -123,36 +362,302 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
-        /// Compression level: fast, balanced, best
-        #[arg(short, long, default_value = "balanced")]
-        level: String,
+        /// Compression level: fast, balanced, best, max or a zstd level (1-22) [default: config file, else balanced]
+        #[arg(short, long)]
+        level: Option<String>,
 
         /// Show verbose output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Decode the output before writing and fail if it does not match the input
+        #[arg(long)]
+        verify: bool,
+
+        /// Guard against common misclassifications (version strings as IPv4, ids as numbers)
+        #[arg(long)]
+        strict_patterns: bool,
+
+        /// Kubernetes preset: pod names, namespaces, container ids, klog and CRI prefixes in their own columns
+        #[arg(long)]
+        k8s: bool,
+
+        /// Detect the source kind (syslog, JSON, access log, Kubernetes, prose) and use its pattern profile
+        #[arg(long)]
+        auto_profile: bool,
+
+        /// Drop ANSI escape sequences (colors) instead of storing them (lossy)
+        #[arg(long)]
+        strip_ansi: bool,
+
+        /// Lines longer than BYTES are stored without pattern extraction (0: no limit)
+        #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_LINE_LEN)]
+        max_line_len: usize,
+
+        /// Zstd long-distance matching (finds repeats far apart in multi-GB inputs)
+        #[arg(long)]
+        long: bool,
+
+        /// Zstd window log (10-31); above 27 only this version or newer can decode the archive
+        #[arg(long, value_name = "LOG")]
+        window_log: Option<u32>,
+
+        /// Zstd compression threads (0: compress on the main thread)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        zstd_workers: u32,
+
+        /// Number lines with stable ids starting at FIRST (kept by prune, split and join)
+        #[arg(long, value_name = "FIRST")]
+        line_ids: Option<u64>,
+    },
+
+    /// Merge several files into one v3 archive, recording each line's source
+    Merge {
+        /// Input files (lines are tagged with the path as given)
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Compression level: fast, balanced, best, max or a zstd level (1-22)
+        #[arg(short, long, default_value = "balanced")]
+        level: String,
+    },
+
+    /// Compress a file as a delta against a reference snapshot
+    Delta {
+        /// Input file (new version)
+        input: PathBuf,
+
+        /// Reference file (plain text or .atxt archive)
+        #[arg(short, long)]
+        reference: PathBuf,
+
+        /// Output file (default: input.adlt)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Compression level: fast, balanced, best, max or a zstd level (1-22)
+        #[arg(short, long, default_value = "balanced")]
+        level: String,
+    },
+
+    /// Reconstruct a file from a delta and its reference snapshot
+    Patch {
+        /// Delta file (.adlt)
+        input: PathBuf,
+
+        /// Reference file (plain text or .atxt archive)
+        #[arg(short, long)]
+        reference: PathBuf,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Preview a few representative lines without full decompression (v3)
+    Sample {
+        /// Input file (.atxt, v3 format)
+        input: PathBuf,
+
+        /// Number of lines
+        #[arg(short, long, default_value = "10")]
+        n: usize,
+
+        /// Sampling strategy: head, uniform, per-level
+        #[arg(short, long, default_value = "head")]
+        strategy: String,
+
+        /// Keep decoded columns in this directory for later runs (size-capped)
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+
+    /// Per-column size report with top values and encoding suggestions (v3)
+    Analyze {
+        /// Input file (.atxt, v3 format)
+        input: PathBuf,
+
+        /// Top values shown per column
+        #[arg(long, default_value = "5")]
+        top: usize,
+
+        /// Output format: text, json
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Browse a v3 archive interactively: scroll, filter, search, jump to a time
+    #[cfg(feature = "tui")]
+    View {
+        /// Input file (.atxt, v3 format)
+        input: PathBuf,
+
+        /// Keep decoded columns in this directory for later runs (size-capped)
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+
+    /// Remove rows (lines) from a v3 archive to enforce a retention policy
+    Prune {
+        /// Input file (.atxt, v3 format)
+        input: PathBuf,
+
+        /// Drop rows whose timestamp is older than this many days
+        #[arg(long)]
+        older_than_days: Option<u32>,
+
+        /// Drop rows containing an IPv4 address in this range (CIDR, repeatable)
+        #[arg(long)]
+        drop_ipv4: Vec<String>,
+
+        /// Output file (default: rewrite input)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Split a v3 archive into parts of at most N lines (INPUT.0.atxt, INPUT.1.atxt, ...)
+    Split {
+        /// Input file (.atxt, v3 format)
+        input: PathBuf,
+
+        /// Lines per part
+        #[arg(long, value_name = "N")]
+        lines: usize,
+    },
+
+    /// Join v3 archives, in order, into one (line ids and provenance are kept)
+    Join {
+        /// Input archives (e.g. the parts written by `split`)
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Print a shell completion script (`alice-text completions bash > alice-text.bash`)
+    Completions {
+        /// Target shell
+        shell: clap_complete::Shell,
+    },
+
+    /// Write man pages: alice-text.1 plus one per subcommand (for packagers)
+    #[command(hide = true)]
+    GenMan {
+        /// Output directory (default: print alice-text.1 to stdout)
+        #[arg(short, long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
     },
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    PORCELAIN.get_or_init(|| cli.porcelain);
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(exit_code(e.as_ref()))
+        }
+    }
+}
 
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    OUTPUT_WRITER.get_or_init(|| SafeFileWriter::new().with_fsync(!cli.no_fsync));
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .build_global()?;
+    }
+
+    let config_path = cli.config;
     match cli.command {
         Commands::Compress {
             input,
             output,
             level,
             verbose,
+            verify,
+            strict_patterns,
+            k8s,
+            strip_ansi,
+            max_line_len,
+            long,
+            window_log,
+            zstd_workers,
+            json,
+            route_blocks,
+            model,
+            flush_interval,
+            flush_bytes,
         } => {
-            compress_file(&input, output, &level, verbose)?;
+            let options = CompressOptions::new()
+                .with_verify_roundtrip(verify)
+                .with_strip_ansi(strip_ansi)
+                .with_pattern_limits(pattern_limits(max_line_len))
+                .with_zstd(
+                    ZstdParams::new()
+                        .with_long_distance_matching(long)
+                        .with_window_log(window_log)
+                        .with_workers(zstd_workers),
+                );
+            let flush = (flush_interval.is_some() || flush_bytes.is_some()).then(|| FlushPolicy {
+                interval: flush_interval.map(Duration::from_secs),
+                max_bytes: flush_bytes,
+            });
+            let config = load_config(config_path.as_ref())?;
+            if !config.redact.is_empty() {
+                return Err(CliError::usage(
+                    "The config file's redaction policy rewrites v3 columns; use compress-v3",
+                ));
+            }
+            let level = level.or_else(|| config.level.clone());
+            compress_file(
+                &input,
+                output,
+                V2Settings {
+                    level: level.as_deref().unwrap_or("balanced"),
+                    verbose,
+                    options,
+                    pattern_config: select_patterns(&config, strict_patterns, k8s),
+                    json,
+                    block_size: route_blocks.map(|kib| kib * 1024),
+                    model: model.as_ref(),
+                    flush,
+                },
+            )?;
         }
-        Commands::Decompress { input, output } => {
-            decompress_file(&input, output)?;
+        Commands::Decompress {
+            input,
+            output,
+            model,
+        } => {
+            decompress_file(&input, output, model.as_ref())?;
         }
-        Commands::Info { input } => {
-            show_info(&input)?;
+        Commands::Train {
+            inputs,
+            output,
+            dict_size,
+            strict_patterns,
+            k8s,
+        } => {
+            let config = pattern_config(strict_patterns, k8s);
+            train_model(&inputs, &output, dict_size * 1024, config)?;
         }
-        Commands::Estimate { input, detailed } => {
-            estimate_compression(&input, detailed)?;
+        Commands::Info { input, json } => {
+            show_info(&input, json)?;
+        }
+        Commands::Estimate {
+            input,
+            detailed,
+            json,
+        } => {
+            estimate_compression(&input, detailed, json)?;
         }
         Commands::Verify { input } => {
             verify_file(&input)?;
@@ -165,86 +670,337 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             filter,
             format,
             limit,
+            lines,
+            top,
+            view,
+            views,
+            cache_dir,
         } => {
+            if views {
+                list_views(&input)?;
+                return Ok(());
+            }
+            // A saved view supplies defaults for --select and --where
+            let (select, filter) = match view {
+                Some(name) => {
+                    let view = find_view(&input, &name)?;
+                    let select = select.or_else(|| Some(view.select.join(",")));
+                    (select, view.filters.into_iter().chain(filter).collect())
+                }
+                None => (select, filter),
+            };
             query_file(
                 &input,
-                columns,
-                stats,
-                select.as_ref(),
-                filter.as_ref(),
-                &format,
-                limit,
+                QuerySettings {
+                    show_columns: columns,
+                    show_stats: stats,
+                    select: select.as_ref(),
+                    filters: &filter,
+                    format: &format,
+                    limit,
+                    with_lines: lines,
+                    top,
+                    cache_dir: cache_dir.as_ref(),
+                },
             )?;
         }
+        Commands::SaveView {
+            input,
+            name,
+            select,
+            filter,
+            description,
+            embed,
+        } => {
+            let columns: Vec<&str> = select
+                .as_deref()
+                .map(|s| s.split(',').map(str::trim).collect())
+                .unwrap_or_default();
+            let view = filter.iter().fold(
+                SavedView::new(name).describe(description).select(&columns),
+                |v, f| v.filter(f),
+            );
+            save_view(&input, view, embed)?;
+        }
         Commands::CompressV3 {
             input,
             output,
             level,
             verbose,
+            verify,
+            strict_patterns,
+            k8s,
+            auto_profile,
+            strip_ansi,
+            max_line_len,
+            long,
+            window_log,
+            zstd_workers,
+            line_ids,
+        } => {
+            if auto_profile && (strict_patterns || k8s) {
+                return Err(CliError::usage("--auto-profile picks the pattern profile itself; drop --strict-patterns / --k8s"));
+            }
+            let options = CompressOptions::new()
+                .with_verify_roundtrip(verify)
+                .with_strip_ansi(strip_ansi)
+                .with_pattern_limits(pattern_limits(max_line_len))
+                .with_zstd(
+                    ZstdParams::new()
+                        .with_long_distance_matching(long)
+                        .with_window_log(window_log)
+                        .with_workers(zstd_workers),
+                );
+            let config = load_config(config_path.as_ref())?;
+            let level = level.or_else(|| config.level.clone());
+            let patterns = if auto_profile {
+                None
+            } else {
+                select_patterns(&config, strict_patterns, k8s)
+            };
+            compress_file_v3(
+                &input,
+                output,
+                V3Settings {
+                    level: level.as_deref().unwrap_or("balanced"),
+                    verbose,
+                    options,
+                    pattern_config: patterns,
+                    first_line_id: line_ids,
+                    redact: &config.redact,
+                },
+            )?;
+        }
+        Commands::Delta {
+            input,
+            reference,
+            output,
+            level,
         } => {
-            compress_file_v3(&input, output, &level, verbose)?;
+            delta_file(&input, &reference, output, &level)?;
+        }
+        Commands::Patch {
+            input,
+            reference,
+            output,
+        } => {
+            patch_file(&input, &reference, output)?;
+        }
+        Commands::Sample {
+            input,
+            n,
+            strategy,
+            cache_dir,
+        } => {
+            sample_file(&input, n, &strategy, cache_dir.as_ref())?;
+        }
+        Commands::Analyze { input, top, format } => {
+            analyze_file(&input, top, &format)?;
+        }
+        #[cfg(feature = "tui")]
+        Commands::View { input, cache_dir } => {
+            let engine = open_engine(&input, cache_dir.as_ref())?;
+            let title = input.display().to_string();
+            alice_text::viewer::run(alice_text::viewer::Viewer::new(engine, title)?)?;
+        }
+        Commands::Prune {
+            input,
+            older_than_days,
+            drop_ipv4,
+            output,
+        } => {
+            prune_file(&input, older_than_days, &drop_ipv4, output)?;
+        }
+
+        Commands::Merge {
+            inputs,
+            output,
+            level,
+        } => {
+            merge_files(&inputs, &output, &level)?;
+        }
+
+        Commands::Split { input, lines } => {
+            split_file(&input, lines)?;
+        }
+
+        Commands::Join { inputs, output } => {
+            join_files(&inputs, &output)?;
+        }
+        Commands::Completions { shell } => {
+            let mut cmd = Cli::command();
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(shell, &mut cmd, name, &mut io::stdout());
+        }
+        Commands::GenMan { out_dir } => {
+            let cmd = Cli::command();
+            match out_dir {
+                Some(dir) => {
+                    fs::create_dir_all(&dir)?;
+                    clap_mangen::generate_to(cmd, &dir)?;
+                }
+                None => clap_mangen::Man::new(cmd).render(&mut io::stdout())?,
+            }
         }
     }
 
     Ok(())
 }
 
+fn pattern_limits(max_line_len: usize) -> PatternLimits {
+    PatternLimits::new().with_max_line_len((max_line_len > 0).then_some(max_line_len))
+}
+
+/// Input bytes `--auto-profile` classifies
+const AUTO_PROFILE_SAMPLE: usize = 64 * 1024;
+
+/// `--config PATH`, else the default config file if present
+fn load_config(path: Option<&PathBuf>) -> Result<Config, Box<dyn std::error::Error>> {
+    let loader = match path {
+        Some(path) => ConfigLoader::new().with_path(path),
+        None => ConfigLoader::new(),
+    };
+    loader.load().map_err(|e| match e {
+        ALICETextError::Io(e) => e.into(),
+        e => CliError::usage(e.to_string()).into(),
+    })
+}
+
+/// Pattern config from the flags, else from the config file (`None`: classify the input)
+fn select_patterns(config: &Config, strict: bool, k8s: bool) -> Option<PatternConfig> {
+    if strict || k8s {
+        Some(pattern_config(strict, k8s))
+    } else {
+        config.pattern_config()
+    }
+}
+
+fn pattern_config(strict: bool, k8s: bool) -> PatternConfig {
+    let config = if strict {
+        PatternConfig::strict()
+    } else {
+        PatternConfig::default()
+    };
+    if k8s {
+        config.with_kubernetes()
+    } else {
+        config
+    }
+}
+
+/// Compression level from `--level` (a preset name or a zstd level);
+/// unknown values fall back to balanced
+fn parse_level(level: &str) -> Level {
+    level.parse().unwrap_or_else(|_| {
+        eprintln!("Unknown level: {level}. Using balanced.");
+        Level::Balanced
+    })
+}
+
+/// Compressor settings for `compress` (v2)
+struct V2Settings<'a> {
+    level: &'a str,
+    verbose: bool,
+    options: CompressOptions,
+    pattern_config: Option<PatternConfig>,
+    /// Print statistics as JSON
+    json: bool,
+    /// Split into blocks of this many bytes, each routed to its best encoding
+    block_size: Option<usize>,
+    /// Shared model: its pattern config and dictionary are used
+    model: Option<&'a PathBuf>,
+    /// Stream mode: append a block whenever the policy says so
+    flush: Option<FlushPolicy>,
+}
+
 fn compress_file(
     input: &PathBuf,
     output: Option<PathBuf>,
-    level: &str,
-    verbose: bool,
+    settings: V2Settings,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Read input
-    let text = if input.to_string_lossy() == "-" {
-        let mut buffer = String::new();
-        io::stdin().read_to_string(&mut buffer)?;
+    let V2Settings {
+        level,
+        verbose,
+        options,
+        pattern_config,
+        json,
+        block_size,
+        model,
+        flush,
+    } = settings;
+    let compression_level = parse_level(level);
+
+    // Configure TunedCompressor v2 (a model's pattern config wins)
+    let configure = |pattern_config| -> Result<TunedCompressor, Box<dyn std::error::Error>> {
+        let mut compressor = TunedCompressor::new(compression_level)
+            .with_options(options)
+            .with_pattern_config(pattern_config)?;
+        if let Some(model) = model {
+            compressor = compressor.with_model(&TextModel::load(model)?)?;
+        }
+        if let Some(block_size) = block_size {
+            compressor = compressor.with_block_routing(block_size);
+        }
+        Ok(compressor)
+    };
+
+    let output_path = output.unwrap_or_else(|| {
+        let mut p = input.clone();
+        p.set_extension("atxt");
+        p
+    });
+    if let Some(policy) = flush {
+        // Stream input cannot be classified ahead: no config means the defaults
+        let compressor = configure(pattern_config.unwrap_or_default())?;
+        return stream_compress(input, &output_path, compressor, policy, verbose);
+    }
+
+    // Read input (raw bytes: UTF-16LE logs are transcoded by the compressor)
+    let bytes = if input.to_string_lossy() == "-" {
+        let mut buffer = Vec::new();
+        io::stdin().read_to_end(&mut buffer)?;
         buffer
     } else {
-        fs::read_to_string(input)?
+        fs::read(input)?
     };
 
-    let original_size = text.len();
+    // No config given: classify the start of the input
+    let mut compressor = configure(pattern_config.unwrap_or_else(|| {
+        let sample = &bytes[..bytes.len().min(AUTO_PROFILE_SAMPLE)];
+        classify(&String::from_utf8_lossy(sample))
+            .kind
+            .pattern_config()
+    }))?;
 
-    // Parse compression level
-    let compression_mode = match level.to_lowercase().as_str() {
-        "fast" => CompressionMode::Fast,
-        "balanced" => CompressionMode::Balanced,
-        "best" => CompressionMode::Best,
-        _ => {
-            eprintln!("Unknown level: {level}. Using balanced.");
-            CompressionMode::Balanced
-        }
-    };
+    let original_size = bytes.len();
 
     // Compress using TunedCompressor v2
     let start = Instant::now();
-    let mut compressor = TunedCompressor::new(compression_mode);
-    let compressed = compressor.compress(&text)?;
+    let compressed = compressor.compress_bytes(&bytes)?;
     let elapsed = start.elapsed();
 
     let compressed_size = compressed.len();
 
     // Write output
-    let output_path = output.unwrap_or_else(|| {
-        let mut p = input.clone();
-        p.set_extension("atxt");
-        p
-    });
-
-    fs::write(&output_path, &compressed)?;
+    write_output(&output_path, &compressed)?;
 
     // Report
     #[allow(clippy::cast_precision_loss)]
     let ratio = compressed_size as f64 / original_size as f64 * 100.0;
     let savings = 100.0 - ratio;
 
-    if verbose {
+    if json {
+        if let Some(stats) = compressor.last_stats() {
+            println!("{}", serde_json::to_string_pretty(stats)?);
+        }
+    } else if porcelain() {
+        print_size_summary(input, &output_path, original_size, compressed_size);
+    } else if verbose {
         println!("ALICE-Text Compression (v2)");
         println!("===========================");
         println!("Input:      {}", input.display());
         println!("Output:     {}", output_path.display());
-        println!("Level:      {compression_mode:?}");
+        println!("Level:      {compression_level:?}");
         println!();
         println!("Original:   {original_size} bytes");
         println!("Compressed: {compressed_size} bytes");
@@ -257,6 +1013,23 @@ fn compress_file(
             println!("Statistics:");
             println!("  Patterns:   {}", stats.pattern_count);
             println!("  Skeleton:   {} tokens", stats.skeleton_size);
+            println!(
+                "  Exceptions: {:.1}% of input in value columns",
+                stats.exception_rate * 100.0
+            );
+            if let Some(routing) = &stats.routing {
+                println!("  Blocks:     {}", routing.blocks.len());
+                for strategy in BlockStrategy::ALL {
+                    let count = routing.count(strategy);
+                    if count > 0 {
+                        println!(
+                            "    {:<10} {count} ({} bytes)",
+                            strategy.name(),
+                            routing.bytes(strategy)
+                        );
+                    }
+                }
+            }
         }
     } else {
         println!(
@@ -271,118 +1044,485 @@ fn compress_file(
     Ok(())
 }
 
+/// `--porcelain` compress summary: input, output, original and compressed bytes
+fn print_size_summary(input: &Path, output: &Path, original: usize, compressed: usize) {
+    println!(
+        "{}\t{}\t{original}\t{compressed}",
+        input.display(),
+        output.display()
+    );
+}
+
+fn train_model(
+    inputs: &[PathBuf],
+    output: &PathBuf,
+    dictionary_size: usize,
+    pattern_config: PatternConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let texts = inputs
+        .iter()
+        .map(|path| fs::read(path).map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+        .collect::<io::Result<Vec<_>>>()?;
+    let samples: Vec<&str> = texts.iter().map(String::as_str).collect();
+
+    let model = TextModel::train(&samples, pattern_config, dictionary_size)?;
+    write_output(output, &model.to_bytes()?)?;
+
+    if output.extension().is_none_or(|ext| ext != MODEL_EXT) {
+        eprintln!("Warning: model files conventionally use .{MODEL_EXT}");
+    }
+    println!(
+        "{} samples -> {} (model {:016x}, {} byte dictionary, {} pattern matches)",
+        inputs.len(),
+        output.display(),
+        model.id()?,
+        model.dictionary.len(),
+        model.pattern_stats.total_matches
+    );
+    Ok(())
+}
+
+/// Stream output file: `flush` (called after every block) also fsyncs
+struct SyncedFile {
+    file: fs::File,
+    fsync: bool,
+}
+
+impl Write for SyncedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.fsync {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+/// Compress never-ending input block by block (`--flush-interval` / `--flush-bytes`)
+fn stream_compress(
+    input: &PathBuf,
+    output_path: &PathBuf,
+    compressor: TunedCompressor,
+    policy: FlushPolicy,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut source: Box<dyn Read + Send> = if input.to_string_lossy() == "-" {
+        Box::new(io::stdin())
+    } else {
+        Box::new(fs::File::open(input)?)
+    };
+
+    // Read on a thread so the interval fires even while the input is idle
+    let (tx, rx) = mpsc::sync_channel::<io::Result<Vec<u8>>>(16);
+    std::thread::spawn(move || {
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let chunk = match source.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => Ok(buf[..n].to_vec()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            if tx.send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let file = SyncedFile {
+        file: fs::File::create(output_path)?,
+        fsync: OUTPUT_WRITER
+            .get_or_init(SafeFileWriter::new)
+            .fsync_enabled(),
+    };
+    let mut stream = StreamCompressor::new(file, compressor)?.with_policy(policy);
+    let mut total = 0usize;
+    loop {
+        let wait = stream.time_to_flush().unwrap_or(Duration::from_secs(3600));
+        let flushed = match rx.recv_timeout(wait) {
+            Ok(chunk) => {
+                let chunk = chunk?;
+                total += chunk.len();
+                stream.push(&chunk)?
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => stream.poll()?,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        if verbose && flushed {
+            eprintln!(
+                "Block {} flushed ({total} bytes read)",
+                stream.blocks_written()
+            );
+        }
+    }
+    let blocks = stream.blocks_written() + u64::from(stream.buffered_len() > 0);
+    stream.finish()?;
+
+    println!(
+        "Streamed {total} bytes in {blocks} blocks to {}",
+        output_path.display()
+    );
+    Ok(())
+}
+
 fn decompress_file(
     input: &PathBuf,
     output: Option<PathBuf>,
+    model: Option<&PathBuf>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Read compressed data
     let compressed = fs::read(input)?;
+    let mut tuned = TunedCompressor::default();
+    if let Some(model) = model {
+        tuned = tuned.with_model(&TextModel::load(model)?)?;
+    }
 
-    // Decompress
-    let alice = ALICEText::default();
-    let text = alice.decompress(&compressed)?;
+    // Decompress to the original bytes (line endings / UTF-16LE restored)
+    let bytes = if is_stream(&compressed) {
+        decompress_stream_with(&tuned, &compressed, DecodeOptions::strict()).or_else(|_| {
+            eprintln!("Warning: last stream block is incomplete; decoding the complete blocks");
+            decompress_stream_with(&tuned, &compressed, DecodeOptions::lenient())
+        })?
+    } else {
+        match compressed.get(8) {
+            Some(&version) if version >= 3 => FormatV3Writer::decompress_bytes(&compressed)?,
+            Some(2) => tuned.decompress_bytes(&compressed)?,
+            _ => ALICEText::default().decompress(&compressed)?.into_bytes(),
+        }
+    };
 
     // Write output
     if let Some(output_path) = output {
-        fs::write(&output_path, &text)?;
+        write_output(&output_path, &bytes)?;
         println!("Decompressed to: {}", output_path.display());
     } else {
-        io::stdout().write_all(text.as_bytes())?;
+        io::stdout().write_all(&bytes)?;
     }
 
     Ok(())
 }
 
-fn show_info(input: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+/// One `alice-text info` field: text label, JSON key, value and text unit
+struct InfoField {
+    label: &'static str,
+    key: &'static str,
+    value: serde_json::Value,
+    unit: &'static str,
+}
+
+/// `alice-text info` output, printed as text or (`--json`) one JSON object
+#[derive(Default)]
+struct FileInfo {
+    fields: Vec<InfoField>,
+    /// v3 column directory
+    columns: Vec<serde_json::Value>,
+    /// Why the body failed to decode, if it did
+    error: Option<String>,
+}
+
+impl FileInfo {
+    fn add(&mut self, label: &'static str, key: &'static str, value: impl Into<serde_json::Value>) {
+        self.add_unit(label, key, value, "");
+    }
+
+    fn add_unit(
+        &mut self,
+        label: &'static str,
+        key: &'static str,
+        value: impl Into<serde_json::Value>,
+        unit: &'static str,
+    ) {
+        self.fields.push(InfoField {
+            label,
+            key,
+            value: value.into(),
+            unit,
+        });
+    }
+
+    /// Print, then fail (as corrupt) if the body did not decode
+    fn finish(self, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.print(json)?;
+        match self.error {
+            Some(error) => Err(CliError::corrupt(error)),
+            None => Ok(()),
+        }
+    }
+
+    /// Compressed size as a percentage of `original` (one decimal)
+    #[allow(clippy::cast_precision_loss)]
+    fn add_ratio(&mut self, compressed: usize, original: u64) {
+        if original > 0 {
+            let ratio = compressed as f64 / original as f64 * 100.0;
+            self.add_unit("Ratio", "ratio_percent", (ratio * 10.0).round() / 10.0, "%");
+        }
+    }
+
+    fn add_status<T, E: std::fmt::Display>(&mut self, decoded: Result<T, E>) {
+        self.add("Valid", "valid", decoded.is_ok());
+        if let Err(e) = decoded {
+            self.add("Error", "error", e.to_string());
+            self.error = Some(e.to_string());
+        }
+    }
+
+    fn print(&self, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if json {
+            let mut object: serde_json::Map<String, serde_json::Value> = self
+                .fields
+                .iter()
+                .map(|f| (f.key.to_string(), f.value.clone()))
+                .collect();
+            if !self.columns.is_empty() {
+                object.insert("columns".to_string(), self.columns.clone().into());
+            }
+            println!("{}", serde_json::to_string_pretty(&object)?);
+            return Ok(());
+        }
+
+        let text = |value: &serde_json::Value| match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Bool(true) => "yes".to_string(),
+            serde_json::Value::Bool(false) => "no".to_string(),
+            serde_json::Value::Object(map) => map
+                .iter()
+                .map(|(k, v)| format!("{k}={}", v.as_str().unwrap_or_default()))
+                .collect::<Vec<_>>()
+                .join(" "),
+            value => value.to_string(),
+        };
+        if porcelain() {
+            for field in &self.fields {
+                println!("{}\t{}", field.key, text(&field.value));
+            }
+            for column in &self.columns {
+                let number = |key: &str| column[key].as_u64().unwrap_or_default();
+                println!(
+                    "column\t{}\t{}\t{}\t{}",
+                    column["name"].as_str().unwrap_or_default(),
+                    number("row_count"),
+                    number("compressed_size"),
+                    number("offset")
+                );
+            }
+            return Ok(());
+        }
+
+        println!("ALICE-Text File Information");
+        println!("===========================");
+        for field in &self.fields {
+            let value = text(&field.value);
+            println!("{:<17}{value}{}", format!("{}:", field.label), field.unit);
+        }
+        if !self.columns.is_empty() {
+            println!();
+            println!("Columns:");
+            for column in &self.columns {
+                let number = |key: &str| column[key].as_u64().unwrap_or_default();
+                let encoding = match column["encoding"].as_str() {
+                    Some(encoding) if encoding != "plain" => format!("  ({encoding})"),
+                    _ => String::new(),
+                };
+                println!(
+                    "  {:15} {:>7} rows  {:>9} bytes  @ {}{encoding}",
+                    column["name"].as_str().unwrap_or_default(),
+                    number("row_count"),
+                    number("compressed_size"),
+                    number("offset")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn show_info(input: &PathBuf, json: bool) -> Result<(), Box<dyn std::error::Error>> {
     let compressed = fs::read(input)?;
 
-    // Read header manually for info
-    if compressed.len() < 34 {
-        eprintln!("Error: File too small");
-        return Ok(());
+    let mut info = FileInfo::default();
+    info.add("File", "file", input.display().to_string());
+    info.add_unit(
+        "Compressed Size",
+        "compressed_size",
+        compressed.len(),
+        " bytes",
+    );
+
+    if is_stream(&compressed) {
+        info.add("Format", "format", "Block stream (Tuned blocks)");
+        let decoded = decompress_stream_with(
+            &TunedCompressor::default(),
+            &compressed,
+            DecodeOptions::strict(),
+        );
+        if let Ok(text) = &decoded {
+            info.add_unit("Original Size", "original_size", text.len(), " bytes");
+            info.add_ratio(compressed.len(), text.len() as u64);
+        }
+        info.add_status(decoded);
+        return info.finish(json);
     }
 
     // Check magic
-    if &compressed[0..8] != b"ALICETXT" {
-        eprintln!("Error: Invalid ALICE-Text file (bad magic)");
-        return Ok(());
+    if compressed.len() < 10 || &compressed[0..8] != b"ALICETXT" {
+        return Err(CliError::corrupt("Invalid ALICE-Text file (bad magic)"));
     }
-
     let version = (compressed[8], compressed[9]);
+    info.add("Version", "version", format!("{}.{}", version.0, version.1));
 
-    println!("ALICE-Text File Information");
-    println!("===========================");
-    println!("File:            {}", input.display());
-    println!("Compressed Size: {} bytes", compressed.len());
-    println!("Version:         {}.{}", version.0, version.1);
-
-    if version.0 >= 2 {
-        // v2 format (TunedCompressor)
-        let original_length = u64::from_le_bytes(compressed[10..18].try_into().unwrap_or([0u8; 8]));
-        let mode = match compressed[18] {
-            0 => "Fast",
-            1 => "Balanced",
-            2 => "Best",
-            _ => "Unknown",
-        };
-        let pattern_count = u32::from_le_bytes(compressed[22..26].try_into().unwrap_or([0u8; 4]));
-        let skeleton_length = u32::from_le_bytes(compressed[26..30].try_into().unwrap_or([0u8; 4]));
+    match version.0 {
+        3.. => v3_info(&mut info, &compressed)?,
+        2 => v2_info(&mut info, &compressed)?,
+        _ => v1_info(&mut info, &compressed)?,
+    }
+    info.finish(json)
+}
 
-        println!("Format:          Tuned (Zstd + Columnar)");
-        println!("Compression:     {mode}");
-        println!("Original Size:   {original_length} bytes");
-        println!("Pattern Count:   {pattern_count}");
-        println!("Skeleton Tokens: {skeleton_length}");
+/// Header fields of a v3 (columnar) archive, with its column directory
+fn v3_info(info: &mut FileInfo, compressed: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = FormatV3Metadata::read_from(&mut io::Cursor::new(compressed))?;
+    let header = &metadata.header;
+    let level = format!("{:?}", metadata.level());
+
+    info.add("Format", "format", "Columnar v3 (queryable)");
+    info.add("Compression", "compression", level);
+    info.add_unit(
+        "Original Size",
+        "original_size",
+        header.original_length,
+        " bytes",
+    );
+    info.add_ratio(compressed.len(), header.original_length);
+    info.add("Lines", "row_count", header.row_count);
+    info.add("Column Count", "column_count", metadata.columns.len());
+    if let Some(kind) = metadata.source_kind() {
+        info.add("Source Kind", "source_kind", kind.name());
+    }
+    if let Some(value_bytes) = metadata.value_bytes() {
+        info.add_unit("Value Bytes", "value_bytes", value_bytes, " bytes");
+    }
+    if let Some(producer) = metadata.producer() {
+        info.add(
+            "Written By",
+            "producer_version",
+            format!("alice-text {}", producer.crate_version),
+        );
+        info.add("Zstd", "zstd_version", producer.zstd_version);
+        info.add("Pattern Set", "pattern_set", producer.pattern_set);
+        info.add(
+            "Settings",
+            "settings",
+            serde_json::to_value(&producer.settings)?,
+        );
+    }
+    if !metadata.extensions.is_empty() {
+        let tags: Vec<String> = metadata
+            .extensions
+            .iter()
+            .map(|e| format!("0x{:04x}", e.tag))
+            .collect();
+        info.add("Extensions", "extensions", tags.join(", "));
+    }
+    info.columns = metadata
+        .columns
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "name": c.col_type.name(),
+                "offset": c.offset,
+                "compressed_size": c.compressed_size,
+                "row_count": c.row_count,
+                "encoding": c.encoding.name(),
+            })
+        })
+        .collect();
+    info.add_status(FormatV3Writer::decompress_bytes(compressed));
+    Ok(())
+}
 
-        #[allow(clippy::cast_precision_loss)]
-        let ratio = compressed.len() as f64 / original_length as f64 * 100.0;
-        println!("Ratio:           {ratio:.1}%");
+/// Header fields of a v2 (Tuned) archive
+fn v2_info(info: &mut FileInfo, compressed: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    if compressed.len() < 30 {
+        return Err(CliError::corrupt("File too small for a v2 header"));
+    }
+    let original_length = u64::from_le_bytes(compressed[10..18].try_into().unwrap_or([0u8; 8]));
+    let mode = match compressed[18] {
+        0 => "Fast",
+        1 => "Balanced",
+        2 => "Best",
+        3 => "Max",
+        _ => "Unknown",
+    };
+    let pattern_count = u32::from_le_bytes(compressed[22..26].try_into().unwrap_or([0u8; 4]));
+    let skeleton_length = u32::from_le_bytes(compressed[26..30].try_into().unwrap_or([0u8; 4]));
 
-        // Verify decompression
-        let compressor = TunedCompressor::default();
-        match compressor.decompress(&compressed) {
-            Ok(_) => println!("Status:          Valid (decompression OK)"),
-            Err(e) => println!("Status:          Invalid ({e})"),
-        }
+    if compressed[19] & TunedHeader::FLAG_STORED != 0 {
+        info.add("Format", "format", "Tuned (stored, uncompressed)");
     } else {
-        // v1 format (legacy)
-        let mode = match compressed[10] {
-            0 => "Pattern",
-            1 => "N-gram",
-            _ => "Unknown",
-        };
-        let original_length = u32::from_le_bytes(compressed[14..18].try_into().unwrap_or([0u8; 4]));
-        let token_count = u32::from_le_bytes(compressed[18..22].try_into().unwrap_or([0u8; 4]));
-        let exception_count = u32::from_le_bytes(compressed[22..26].try_into().unwrap_or([0u8; 4]));
-
-        println!("Format:          Legacy (LZMA)");
-        println!("Mode:            {mode}");
-        println!("Original Size:   {original_length} bytes");
-        println!("Token Count:     {token_count}");
-        println!("Exception Count: {exception_count}");
-
-        #[allow(clippy::cast_precision_loss)]
-        let ratio = compressed.len() as f64 / f64::from(original_length) * 100.0;
-        println!("Ratio:           {ratio:.1}%");
-
-        // Verify decompression
-        let alice = ALICEText::default();
-        match alice.decompress(&compressed) {
-            Ok(_) => println!("Status:          Valid (decompression OK)"),
-            Err(e) => println!("Status:          Invalid ({e})"),
-        }
+        info.add("Format", "format", "Tuned (Zstd + Columnar)");
     }
+    info.add("Compression", "compression", mode);
+    info.add_unit("Original Size", "original_size", original_length, " bytes");
+    info.add_ratio(compressed.len(), original_length);
+    info.add("Pattern Count", "pattern_count", pattern_count);
+    info.add("Skeleton Tokens", "skeleton_tokens", skeleton_length);
+    if let Ok(Some(id)) = TunedCompressor::read_model_id(compressed) {
+        info.add_unit(
+            "Model",
+            "model_id",
+            format!("{id:016x}"),
+            " (needs --model to decompress)",
+        );
+    }
+    info.add_status(TunedCompressor::default().decompress(compressed));
+    Ok(())
+}
 
+/// Header fields of a v1 (legacy LZMA) archive
+fn v1_info(info: &mut FileInfo, compressed: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    if compressed.len() < 26 {
+        return Err(CliError::corrupt("File too small for a v1 header"));
+    }
+    let mode = match compressed[10] {
+        0 => "Pattern",
+        1 => "N-gram",
+        _ => "Unknown",
+    };
+    let original_length = u32::from_le_bytes(compressed[14..18].try_into().unwrap_or([0u8; 4]));
+    let token_count = u32::from_le_bytes(compressed[18..22].try_into().unwrap_or([0u8; 4]));
+    let exception_count = u32::from_le_bytes(compressed[22..26].try_into().unwrap_or([0u8; 4]));
+
+    info.add("Format", "format", "Legacy (LZMA)");
+    info.add("Mode", "mode", mode);
+    info.add_unit("Original Size", "original_size", original_length, " bytes");
+    info.add_ratio(compressed.len(), u64::from(original_length));
+    info.add("Token Count", "token_count", token_count);
+    info.add("Exception Count", "exception_count", exception_count);
+    info.add_status(ALICEText::default().decompress(compressed));
     Ok(())
 }
 
-fn estimate_compression(input: &PathBuf, detailed: bool) -> Result<(), Box<dyn std::error::Error>> {
+fn estimate_compression(
+    input: &PathBuf,
+    detailed: bool,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
     let text = fs::read_to_string(input)?;
     let original_size = text.len();
 
     let estimator = EntropyEstimator::new();
     let estimate = estimator.estimate(&text);
 
+    if json {
+        println!("{}", serde_json::to_string_pretty(&estimate)?);
+        return Ok(());
+    }
+
     println!("Compression Estimate for: {}", input.display());
     println!("==========================");
     println!("Original Size:    {original_size} bytes");
@@ -406,10 +1546,16 @@ fn estimate_compression(input: &PathBuf, detailed: bool) -> Result<(), Box<dyn s
             "  Shannon Entropy:   {:.2} bits/byte",
             estimate.shannon_entropy
         );
+        println!(
+            "  Context Entropy:   {:.2} bits/byte (order 3)",
+            estimate.context_entropy
+        );
         println!(
             "  Pattern Coverage:  {:.1}%",
             estimate.pattern_coverage * 100.0
         );
+        println!("  Line Templates:    {}", estimate.template_count);
+        println!("  Sampled Bytes:     {}", estimate.sampled_bytes);
         println!("  Repetition Score:  {:.2}", estimate.repetition_score);
         println!("  Unique Bytes:      {}", estimate.unique_bytes);
     }
@@ -422,47 +1568,74 @@ fn verify_file(input: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
 
     let alice = ALICEText::default();
 
+    if porcelain() {
+        let text = alice.decompress(&compressed).inspect_err(|_| {
+            println!("failed\t{}", input.display());
+        })?;
+        println!("ok\t{}\t{}", input.display(), text.len());
+        return Ok(());
+    }
+
     print!("Verifying {}... ", input.display());
     io::stdout().flush()?;
 
     match alice.decompress(&compressed) {
         Ok(text) => {
             println!("OK ({} bytes decompressed)", text.len());
+            Ok(())
         }
         Err(e) => {
             println!("FAILED");
-            eprintln!("Error: {e}");
-            std::process::exit(1);
+            Err(e.into())
         }
     }
+}
 
-    Ok(())
+/// Writer settings for `compress --v3`
+struct V3Settings<'a> {
+    level: &'a str,
+    verbose: bool,
+    options: CompressOptions,
+    /// `None`: classify the start of the input
+    pattern_config: Option<PatternConfig>,
+    first_line_id: Option<u64>,
+    redact: &'a RedactionPolicy,
 }
 
 fn compress_file_v3(
     input: &PathBuf,
     output: Option<PathBuf>,
-    level: &str,
-    verbose: bool,
+    settings: V3Settings,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let V3Settings {
+        level,
+        verbose,
+        options,
+        pattern_config,
+        first_line_id,
+        redact,
+    } = settings;
     // Read input
-    let text = fs::read_to_string(input)?;
-    let original_size = text.len();
+    let bytes = fs::read(input)?;
+    let original_size = bytes.len();
 
-    // Parse compression level
-    let compression_level = match level.to_lowercase().as_str() {
-        "fast" => CompressionLevel::Fast,
-        "balanced" => CompressionLevel::Balanced,
-        "best" => CompressionLevel::Best,
-        _ => {
-            eprintln!("Unknown level: {level}. Using balanced.");
-            CompressionLevel::Balanced
-        }
-    };
+    let compression_level = parse_level(level);
 
     // Compress using v3 format
     let start = Instant::now();
-    let compressed = compress_v3(&text, compression_level)?;
+    let mut writer = FormatV3Writer::new(compression_level).with_options(options);
+    if let Some(first) = first_line_id {
+        writer = writer.with_line_ids(first);
+    }
+    let writer = match pattern_config {
+        Some(config) => writer.with_pattern_config(config)?,
+        // No config given: classify the start of the file
+        None => {
+            let sample = &bytes[..bytes.len().min(AUTO_PROFILE_SAMPLE)];
+            writer.with_detected_profile(&String::from_utf8_lossy(sample))?
+        }
+    };
+    let compressed = redact.apply(writer.compress_bytes(&bytes)?)?;
     let elapsed = start.elapsed();
 
     let compressed_size = compressed.len();
@@ -474,14 +1647,16 @@ fn compress_file_v3(
         p
     });
 
-    fs::write(&output_path, &compressed)?;
+    write_output(&output_path, &compressed)?;
 
     // Report
     #[allow(clippy::cast_precision_loss)]
     let ratio = compressed_size as f64 / original_size as f64 * 100.0;
     let savings = 100.0 - ratio;
 
-    if verbose {
+    if porcelain() {
+        print_size_summary(input, &output_path, original_size, compressed_size);
+    } else if verbose {
         // Get metadata for detailed info
         let mut cursor = Cursor::new(&compressed);
         let metadata = FormatV3Metadata::read_from(&mut cursor)?;
@@ -491,6 +1666,9 @@ fn compress_file_v3(
         println!("Input:      {}", input.display());
         println!("Output:     {}", output_path.display());
         println!("Level:      {compression_level:?}");
+        if let Some(kind) = metadata.source_kind() {
+            println!("Source:     {kind}");
+        }
         println!();
         println!("Original:   {original_size} bytes");
         println!("Compressed: {compressed_size} bytes");
@@ -522,37 +1700,374 @@ fn compress_file_v3(
     Ok(())
 }
 
-fn query_file(
+fn merge_files(
+    inputs: &[PathBuf],
+    output: &PathBuf,
+    level: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut batch = SourceBatch::new();
+    for input in inputs {
+        let reader = io::BufReader::new(fs::File::open(input)?);
+        batch.add_reader(&input.display().to_string(), reader)?;
+    }
+
+    let compression_level = parse_level(level);
+
+    let compressed = FormatV3Writer::new(compression_level).compress_batch(&batch)?;
+    write_output(output, &compressed)?;
+
+    println!(
+        "{} files ({} lines, {} bytes) -> {} ({} bytes) [v3 queryable, provenance]",
+        inputs.len(),
+        batch.provenance().len(),
+        batch.len(),
+        output.display(),
+        compressed.len()
+    );
+
+    Ok(())
+}
+
+fn split_file(input: &PathBuf, max_lines: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let data = fs::read(input)?;
+    let parts = split_archive(&data, max_lines)?;
+    for (i, part) in parts.iter().enumerate() {
+        let path = input.with_extension(format!("{i}.atxt"));
+        write_output(&path, part)?;
+        println!("{} ({} bytes)", path.display(), part.len());
+    }
+    println!("{} -> {} parts", input.display(), parts.len());
+    Ok(())
+}
+
+/// Saved views of `input`: the archive's, overridden by its sidecar
+fn load_views(input: &PathBuf) -> Result<Vec<SavedView>, Box<dyn std::error::Error>> {
+    Ok(open_engine(input, None)?.views()?)
+}
+
+fn find_view(input: &PathBuf, name: &str) -> Result<SavedView, Box<dyn std::error::Error>> {
+    load_views(input)?
+        .into_iter()
+        .find(|v| v.name == name)
+        .ok_or_else(|| {
+            CliError::usage(format!(
+                "No saved view named {name:?} (list them with --views)"
+            ))
+            .into()
+        })
+}
+
+fn list_views(input: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let views = load_views(input)?;
+    if views.is_empty() {
+        println!("No saved views");
+    }
+    for view in views {
+        println!("{}", view.name);
+        if !view.description.is_empty() {
+            println!("  {}", view.description);
+        }
+        if !view.select.is_empty() {
+            println!("  select: {}", view.select.join(","));
+        }
+        for filter in &view.filters {
+            println!("  where:  {filter}");
+        }
+    }
+    Ok(())
+}
+
+fn save_view(
     input: &PathBuf,
-    show_columns: bool,
-    show_stats: bool,
-    select: Option<&String>,
-    filter: Option<&String>,
+    view: SavedView,
+    embed: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = view.name.clone();
+    if embed {
+        let data = fs::read(input)?;
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&data))?;
+        let views = saved_view::merge(metadata.saved_views()?, &[view]);
+        write_output(input, &embed_views(&data, &views)?)?;
+        println!("Saved view {name:?} in {}", input.display());
+    } else {
+        let sidecar = saved_view::sidecar_path(input);
+        let existing = if sidecar.exists() {
+            saved_view::read_sidecar(&sidecar)?
+        } else {
+            Vec::new()
+        };
+        saved_view::write_sidecar(&sidecar, &saved_view::merge(existing, &[view]))?;
+        println!("Saved view {name:?} in {}", sidecar.display());
+    }
+    Ok(())
+}
+
+fn join_files(inputs: &[PathBuf], output: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let parts = inputs.iter().map(fs::read).collect::<Result<Vec<_>, _>>()?;
+    let refs: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+    let joined = merge_archives(&refs)?;
+    write_output(output, &joined)?;
+
+    println!(
+        "{} archives -> {} ({} bytes)",
+        inputs.len(),
+        output.display(),
+        joined.len()
+    );
+    Ok(())
+}
+
+fn delta_file(
+    input: &PathBuf,
+    reference: &PathBuf,
+    output: Option<PathBuf>,
+    level: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(input)?;
+    let reference_text = resolve_reference(&fs::read(reference)?)?;
+
+    let compression_level = parse_level(level);
+
+    let (delta, stats) = compress_against_with_stats(&reference_text, &text, compression_level)?;
+
+    let output_path = output.unwrap_or_else(|| {
+        let mut p = input.clone();
+        p.set_extension("adlt");
+        p
+    });
+    write_output(&output_path, &delta)?;
+
+    println!(
+        "{} -> {} ({} bytes, {} lines copied, {} lines inserted)",
+        input.display(),
+        output_path.display(),
+        stats.delta_size,
+        stats.copied_lines,
+        stats.inserted_lines
+    );
+
+    Ok(())
+}
+
+fn patch_file(
+    input: &PathBuf,
+    reference: &PathBuf,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let delta = fs::read(input)?;
+    let reference_text = resolve_reference(&fs::read(reference)?)?;
+    let text = decompress_with_reference(&reference_text, &delta)?;
+
+    if let Some(output_path) = output {
+        write_output(&output_path, text.as_bytes())?;
+        println!("Patched to: {}", output_path.display());
+    } else {
+        io::stdout().write_all(text.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn analyze_file(
+    input: &PathBuf,
+    top: usize,
     format: &str,
-    limit: Option<usize>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Check file format version (read header only)
-    let data = fs::read(input)?;
-    if data.len() < 10 {
-        eprintln!("Error: File too small");
+    let engine = QueryEngine::open(input)?;
+    let report = AnalyzeReport::build(&engine, top)?;
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report)?);
         return Ok(());
     }
 
-    if &data[0..8] != b"ALICETXT" {
-        eprintln!("Error: Invalid ALICE-Text file");
-        return Ok(());
+    println!("Column Analysis: {}", input.display());
+    println!("================");
+    println!(
+        "Original size:   {} bytes, compressed {} bytes ({:.1}%), {} rows, zstd level {}",
+        report.original_size,
+        report.compressed_size,
+        report.compressed_size as f64 / report.original_size.max(1) as f64 * 100.0,
+        report.row_count,
+        report.zstd_level
+    );
+    println!();
+    println!(
+        "  {:15} {:>8} {:>10} {:>10} {:>6} {:>6} {:>8}",
+        "column", "rows", "raw", "compressed", "ratio", "share", "distinct"
+    );
+    for col in report.columns.iter().filter(|c| c.rows > 0) {
+        println!(
+            "  {:15} {:>8} {:>10} {:>10} {:>5.1}% {:>5.1}% {:>8}",
+            col.name,
+            col.rows,
+            col.raw_size,
+            col.compressed_size,
+            f64::from(col.compressed_size) / col.raw_size.max(1) as f64 * 100.0,
+            col.share * 100.0,
+            col.distinct
+                .map_or_else(|| "-".to_string(), |d| d.to_string())
+        );
+    }
+
+    let with_values: Vec<_> = report
+        .columns
+        .iter()
+        .filter(|c| !c.top_values.is_empty())
+        .collect();
+    if !with_values.is_empty() {
+        println!();
+        println!("Top values:");
+        for col in with_values {
+            let values: Vec<String> = col
+                .top_values
+                .iter()
+                .map(|(value, count)| format!("{value} ({count})"))
+                .collect();
+            println!("  {:15} {}", col.name, values.join(", "));
+        }
+    }
+
+    println!();
+    if report.suggestions.is_empty() {
+        println!("No suggestions: every column is already stored compactly.");
+    } else {
+        println!("Suggestions:");
+        for suggestion in &report.suggestions {
+            println!("  - {}", suggestion.message);
+        }
+    }
+    Ok(())
+}
+
+/// Open a v3 archive, with the on-disk column cache if a directory is given
+fn open_engine(
+    input: &PathBuf,
+    cache_dir: Option<&PathBuf>,
+) -> Result<QueryEngine<MmapSource>, Box<dyn std::error::Error>> {
+    let mut engine = QueryEngine::open(input)?;
+    let sidecar = saved_view::sidecar_path(input);
+    if sidecar.exists() {
+        engine = engine.with_views(saved_view::read_sidecar(sidecar)?);
+    }
+    Ok(match cache_dir {
+        Some(dir) => engine.with_cache(ColumnCache::open(dir, DEFAULT_CACHE_SIZE)?),
+        None => engine,
+    })
+}
+
+fn sample_file(
+    input: &PathBuf,
+    n: usize,
+    strategy: &str,
+    cache_dir: Option<&PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let strategy = SamplingStrategy::from_name(strategy).ok_or_else(|| {
+        format!("Unknown sampling strategy: {strategy}. Use head, uniform or per-level.")
+    })?;
+    let engine = open_engine(input, cache_dir)?;
+    for line in engine.sample(n, strategy)? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn prune_file(
+    input: &PathBuf,
+    older_than_days: Option<u32>,
+    drop_ipv4: &[String],
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let too_old = older_than_days.map(|days| {
+        retention::older_than(chrono::Utc::now().timestamp_millis() - i64::from(days) * 86_400_000)
+    });
+    let ranges = drop_ipv4
+        .iter()
+        .map(|cidr| {
+            let (addr, prefix) = cidr.split_once('/').unwrap_or((cidr, "32"));
+            Ok(retention::ipv4_in_range(addr.parse()?, prefix.parse()?))
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+    if too_old.is_none() && ranges.is_empty() {
+        return Err(CliError::usage(
+            "Nothing to prune: pass --older-than-days and/or --drop-ipv4",
+        ));
+    }
+
+    let data = fs::read(input)?;
+    let (pruned, stats) = delete_rows(&data, |row| {
+        too_old.as_ref().is_some_and(|is_old| is_old(row))
+            || ranges.iter().any(|in_range| in_range(row))
+    })?;
+
+    let output_path = output.unwrap_or_else(|| input.clone());
+    write_output(&output_path, &pruned)?;
+
+    println!(
+        "{} -> {} ({} of {} rows removed, {} -> {} bytes)",
+        input.display(),
+        output_path.display(),
+        stats.rows_removed,
+        stats.rows_before,
+        data.len(),
+        pruned.len()
+    );
+
+    Ok(())
+}
+
+/// What `query` prints and how
+struct QuerySettings<'a> {
+    /// List the columns instead of querying
+    show_columns: bool,
+    /// Print file statistics instead of querying
+    show_stats: bool,
+    select: Option<&'a String>,
+    /// `column<op>value` filters, `not ...` ones excluding lines
+    filters: &'a [String],
+    format: &'a str,
+    limit: Option<usize>,
+    /// Print the matching lines with the selected values
+    with_lines: bool,
+    /// Most frequent values per column instead of rows
+    top: Option<usize>,
+    cache_dir: Option<&'a PathBuf>,
+}
+
+fn query_file(input: &PathBuf, settings: QuerySettings) -> Result<(), Box<dyn std::error::Error>> {
+    let QuerySettings {
+        show_columns,
+        show_stats,
+        select,
+        filters,
+        format,
+        limit,
+        with_lines,
+        top,
+        cache_dir,
+    } = settings;
+    // Check file format version (read header only)
+    let data = fs::read(input)?;
+    if data.len() < 10 || &data[0..8] != b"ALICETXT" {
+        return Err(CliError::corrupt("Invalid ALICE-Text file (bad magic)"));
     }
 
     if data[8] < 3 {
-        eprintln!("Error: Query requires v3 format. Use 'alice-text compress-v3' to create queryable files.");
-        return Ok(());
+        return Err(CliError::corrupt(
+            "Query requires v3 format. Use 'alice-text compress-v3' to create queryable files.",
+        ));
     }
 
     // Use memory-mapped file for zero-copy access
-    let engine = QueryEngine::open(input)?;
+    let engine = open_engine(input, cache_dir)?;
 
     // Show columns only
     if show_columns {
+        if porcelain() {
+            for col in engine.columns() {
+                println!("{col}");
+            }
+            return Ok(());
+        }
         println!("Available columns:");
         for col in engine.columns() {
             println!("  {col}");
@@ -563,11 +2078,21 @@ fn query_file(
     // Show stats only
     if show_stats {
         let stats = engine.stats();
+        if format == "json" {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+            return Ok(());
+        }
         println!("File Statistics");
         println!("===============");
         println!("Original size:     {} bytes", stats.original_size);
         println!("Compressed size:   {} bytes", stats.compressed_size);
         println!("Compression ratio: {:.1}%", stats.compression_ratio * 100.0);
+        if let Some(rate) = stats.exception_rate {
+            println!("Exception rate:    {:.1}%", rate * 100.0);
+        }
+        if let Some(kind) = stats.source_kind {
+            println!("Source kind:       {kind}");
+        }
         println!("Row count:         {}", stats.row_count);
         println!("Column count:      {}", stats.column_count);
         println!();
@@ -578,6 +2103,22 @@ fn query_file(
                 col.name, col.row_count, col.compressed_size
             );
         }
+        if !stats.summaries.is_empty() {
+            println!();
+            println!("Summaries:");
+            for summary in &stats.summaries {
+                println!(
+                    "  {:15} min {}  max {}  p50 {}  p99 {}  ({} values, {} null)",
+                    summary.col_type.name(),
+                    summary.format_value(summary.min),
+                    summary.format_value(summary.max),
+                    summary.format_value(summary.p50),
+                    summary.format_value(summary.p99),
+                    summary.count,
+                    summary.null_count
+                );
+            }
+        }
         return Ok(());
     }
 
@@ -587,18 +2128,57 @@ fn query_file(
         |s| s.split(',').map(str::trim).collect(),
     );
 
-    // Parse filter
-    let (filter_col, filter_op, filter_val) = if let Some(f) = filter {
-        parse_filter(f)?
+    // Parse filters; `not ...` ones exclude lines
+    let mut required = Vec::new();
+    let mut excluded = Vec::new();
+    for filter in filters {
+        let (negated, expr) = parse_negation(filter);
+        let parsed = parse_filter(expr)?;
+        if negated {
+            excluded.push(parsed);
+        } else {
+            required.push(parsed);
+        }
+    }
+
+    if let Some(k) = top {
+        let rows = if filters.is_empty() {
+            None
+        } else {
+            Some(engine.filter_except(&required, &excluded)?)
+        };
+        let counts = select_cols
+            .iter()
+            .map(|&col| Ok((col, engine.top_k(col, k, rows.as_ref())?)))
+            .collect::<alice_text::Result<Vec<_>>>()?;
+        print_top_values(&counts, format)?;
+        return Ok(());
+    }
+
+    // Filters, however many, combine on line numbers: one match per line
+    let matches = if filters.is_empty() {
+        None
     } else {
-        (None, None, None)
+        Some(engine.query_at(&select_cols, &engine.filter_except(&required, &excluded)?)?)
     };
 
+    if with_lines {
+        let matches =
+            matches.ok_or_else(|| CliError::usage("--lines requires a --where filter"))?;
+        print_line_matches(&matches, &select_cols, format, limit)?;
+        return Ok(());
+    }
+
     // Execute query
-    let result = if let (Some(col), Some(op), Some(val)) = (filter_col, filter_op, filter_val) {
-        engine.query(&select_cols, col, op, val)?
-    } else {
-        engine.select_columns(&select_cols)?
+    let result = match matches {
+        None => engine.select_columns(&select_cols)?,
+        Some(matches) => QueryResult {
+            columns: select_cols.iter().map(ToString::to_string).collect(),
+            rows: matches
+                .into_iter()
+                .map(|m| QueryRow { values: m.values })
+                .collect(),
+        },
     };
 
     // Apply limit
@@ -611,14 +2191,14 @@ fn query_file(
     // Output
     match format {
         "csv" => {
-            println!("{}", result.columns.join(","));
+            println!("{}", csv_record(&result.columns));
             for row in rows {
                 let values: Vec<&str> = result
                     .columns
                     .iter()
                     .map(|c| row.values.get(c).map_or("", std::string::String::as_str))
                     .collect();
-                println!("{}", values.join(","));
+                println!("{}", csv_record(&values));
             }
         }
         "json" => {
@@ -635,9 +2215,11 @@ fn query_file(
             println!("]");
         }
         _ => {
-            // Table format
-            println!("{}", result.columns.join("\t"));
-            println!("{}", "-".repeat(result.columns.len() * 20));
+            // Table format (rows only with --porcelain)
+            if !porcelain() {
+                println!("{}", result.columns.join("\t"));
+                println!("{}", "-".repeat(result.columns.len() * 20));
+            }
             for row in rows {
                 let values: Vec<&str> = result
                     .columns
@@ -646,38 +2228,134 @@ fn query_file(
                     .collect();
                 println!("{}", values.join("\t"));
             }
-            println!();
-            println!("({} rows)", result.len());
+            if !porcelain() {
+                println!();
+                println!("({} rows)", result.len());
+            }
         }
     }
 
     Ok(())
 }
 
-type FilterResult<'a> =
-    Result<(Option<&'a str>, Option<Op>, Option<&'a str>), Box<dyn std::error::Error>>;
-
-fn parse_filter(filter: &str) -> FilterResult<'_> {
-    // Parse: column=value, column!=value, column>=value, column<=value, column>value, column<value, column~value
-    // Order matters: check multi-char operators first
-    if let Some((col, val)) = filter.split_once("!=") {
-        Ok((Some(col.trim()), Some(Op::Ne), Some(val.trim())))
-    } else if let Some((col, val)) = filter.split_once(">=") {
-        Ok((Some(col.trim()), Some(Op::Ge), Some(val.trim())))
-    } else if let Some((col, val)) = filter.split_once("<=") {
-        Ok((Some(col.trim()), Some(Op::Le), Some(val.trim())))
-    } else if let Some((col, val)) = filter.split_once('>') {
-        Ok((Some(col.trim()), Some(Op::Gt), Some(val.trim())))
-    } else if let Some((col, val)) = filter.split_once('<') {
-        Ok((Some(col.trim()), Some(Op::Lt), Some(val.trim())))
-    } else if let Some((col, val)) = filter.split_once('~') {
-        Ok((Some(col.trim()), Some(Op::Contains), Some(val.trim())))
-    } else if let Some((col, val)) = filter.split_once('=') {
-        Ok((Some(col.trim()), Some(Op::Eq), Some(val.trim())))
-    } else {
-        Err(
-            format!("Invalid filter format: {filter}. Use column=value, column>=value, etc.")
-                .into(),
-        )
+/// Output for `query --lines`: selected values followed by the original line
+fn print_line_matches(
+    matches: &[alice_text::LineMatch],
+    columns: &[&str],
+    format: &str,
+    limit: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let shown = &matches[..limit.unwrap_or(usize::MAX).min(matches.len())];
+    let values = |m: &alice_text::LineMatch, sep: &str| -> String {
+        columns
+            .iter()
+            .map(|c| m.values.get(*c).map_or("", String::as_str))
+            .collect::<Vec<_>>()
+            .join(sep)
+    };
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(shown)?),
+        "csv" => {
+            let header = ["line_number"].iter().chain(columns).chain(&["line"]);
+            println!("{}", csv_record(header));
+            for m in shown {
+                let number = (m.line_number + 1).to_string();
+                let values = columns
+                    .iter()
+                    .map(|c| m.values.get(*c).map_or("", String::as_str));
+                let record = std::iter::once(number.as_str())
+                    .chain(values)
+                    .chain([m.line.as_str()]);
+                println!("{}", csv_record(record));
+            }
+        }
+        _ => {
+            if !porcelain() {
+                println!("line\t{}\ttext", columns.join("\t"));
+                println!("{}", "-".repeat((columns.len() + 2) * 20));
+            }
+            for m in shown {
+                println!("{}\t{}\t{}", m.line_number + 1, values(m, "\t"), m.line);
+            }
+            if !porcelain() {
+                println!();
+                println!("({} rows)", matches.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// One CSV record: fields holding a comma, quote or line break are quoted,
+/// with quotes doubled (RFC 4180)
+fn csv_record<S: AsRef<str>>(fields: impl IntoIterator<Item = S>) -> String {
+    let quote = |field: &str| {
+        if field.contains([',', '"', '\r', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+    fields
+        .into_iter()
+        .map(|field| quote(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Output for `query --top`: each column's most frequent values with counts
+fn print_top_values(
+    counts: &[(&str, Vec<(String, usize)>)],
+    format: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rows = counts.iter().flat_map(|(column, top)| {
+        top.iter()
+            .map(move |(value, count)| (*column, value, count))
+    });
+
+    match format {
+        "json" => {
+            let rows: Vec<_> = rows
+                .map(|(column, value, count)| {
+                    serde_json::json!({"column": column, "value": value, "count": count})
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+        }
+        "csv" => {
+            println!("column,value,count");
+            for (column, value, count) in rows {
+                println!(
+                    "{}",
+                    csv_record([column, value.as_str(), &count.to_string()])
+                );
+            }
+        }
+        _ if porcelain() => {
+            for (column, value, count) in rows {
+                println!("{column}\t{value}\t{count}");
+            }
+        }
+        _ => {
+            for (column, top) in counts {
+                println!("{column}");
+                println!("{}", "-".repeat(40));
+                for (value, count) in top {
+                    println!("{count:>10}  {value}");
+                }
+                println!();
+            }
+        }
     }
+    Ok(())
+}
+
+fn parse_filter(filter: &str) -> Result<(&str, Op, &str), Box<dyn std::error::Error>> {
+    alice_text::parse_filter(filter).ok_or_else(|| {
+        CliError::usage(format!(
+            "Invalid filter format: {filter}. Use column=value, column>=value, etc."
+        ))
+        .into()
+    })
 }
@@ -0,0 +1,381 @@
+//! ALICE-Text HTTP service
+//!
+//! Small compress/decompress/query service backed by a directory of v3 archives.
+//!
+//! ## Endpoints
+//!
+//! ```text
+//! GET  /health                         liveness probe
+//! GET  /archives                       list archive names
+//! POST /archives/{name}?level=balanced compress request body into {name}.atxt
+//! GET  /archives/{name}                full decompression (text/plain)
+//! GET  /archives/{name}/stats          header-only file statistics
+//! GET  /archives/{name}/query          ?select=a,b&where=col=value&limit=N&lines=true
+//! GET  /archives/{name}/sample         ?n=20&strategy=head|uniform|per-level
+//! ```
+
+// --- Global Allocator: mimalloc (Microsoft's high-performance allocator) ---
+#[cfg(all(feature = "mimalloc", not(target_env = "msvc")))]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+use alice_text::{
+    compress_v3, parse_filter, write_atomic, FileStats, Level, QueryEngine, SamplingStrategy,
+    TunedPatternLearner,
+};
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use clap::Parser;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Archive file extension
+const ARCHIVE_EXT: &str = "atxt";
+
+/// Default request body limit in MiB (axum's own default is 2 MB)
+const DEFAULT_MAX_BODY_MIB: usize = 256;
+
+#[derive(Parser)]
+#[command(name = "alice-textd")]
+#[command(author = "Moroya Sakamoto")]
+#[command(version = "1.0.0")]
+#[command(about = "ALICE-Text HTTP compress/query service")]
+struct Cli {
+    /// Archive directory (backing store)
+    #[arg(short, long, default_value = ".")]
+    dir: PathBuf,
+
+    /// Listen address
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    bind: String,
+
+    /// Threads shared by all queries for parallel column reads (default: one per core)
+    #[arg(long, value_name = "N")]
+    query_threads: Option<usize>,
+
+    /// Largest accepted request body (compress uploads), in MiB
+    #[arg(long, value_name = "MIB", default_value_t = DEFAULT_MAX_BODY_MIB)]
+    max_body_mib: usize,
+}
+
+/// Shared service state
+struct AppState {
+    dir: PathBuf,
+    /// Bounded pool for query column reads (`None`: rayon's global pool)
+    query_pool: Option<Arc<ThreadPool>>,
+}
+
+/// Service error mapped to an HTTP status + JSON body
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(name: &str) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: format!("Archive not found: {name}"),
+        }
+    }
+
+    fn internal(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<alice_text::ALICETextError> for ApiError {
+    fn from(e: alice_text::ALICETextError) -> Self {
+        match e {
+            alice_text::ALICETextError::InvalidQuery(_) => Self::bad_request(e.to_string()),
+            _ => Self::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        Self::internal(e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Deserialize)]
+struct CompressParams {
+    level: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    select: Option<String>,
+    #[serde(rename = "where")]
+    filter: Option<String>,
+    limit: Option<usize>,
+    /// Include the reconstructed line of each match (needs `where`)
+    #[serde(default)]
+    lines: bool,
+}
+
+#[derive(Deserialize)]
+struct SampleParams {
+    n: Option<usize>,
+    strategy: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    fs::create_dir_all(&cli.dir)?;
+    TunedPatternLearner::warm_up();
+
+    let query_pool = match cli.query_threads {
+        Some(n) => Some(Arc::new(
+            ThreadPoolBuilder::new()
+                .num_threads(n.max(1))
+                .thread_name(|i| format!("alice-query-{i}"))
+                .build()?,
+        )),
+        None => None,
+    };
+    let state = Arc::new(AppState {
+        dir: cli.dir,
+        query_pool,
+    });
+    let app = Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/archives", get(list_archives))
+        .route("/archives/{name}", get(read_archive).post(write_archive))
+        .route("/archives/{name}/stats", get(archive_stats))
+        .route("/archives/{name}/query", get(query_archive))
+        .route("/archives/{name}/sample", get(sample_archive))
+        .layer(DefaultBodyLimit::max(
+            cli.max_body_mib.saturating_mul(1024 * 1024),
+        ))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&cli.bind).await?;
+    eprintln!("alice-textd listening on {}", listener.local_addr()?);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Resolve an archive name to a path inside the backing directory
+///
+/// Names are restricted to `[A-Za-z0-9._-]` and may not start with `.`,
+/// so requests cannot escape the archive directory.
+fn archive_path(state: &AppState, name: &str) -> ApiResult<PathBuf> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'));
+    if !valid {
+        return Err(ApiError::bad_request(format!(
+            "Invalid archive name: {name}"
+        )));
+    }
+    Ok(state.dir.join(format!("{name}.{ARCHIVE_EXT}")))
+}
+
+fn existing_archive(state: &AppState, name: &str) -> ApiResult<PathBuf> {
+    let path = archive_path(state, name)?;
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(ApiError::not_found(name))
+    }
+}
+
+/// Run blocking (CPU/disk bound) work off the async executor
+async fn blocking<T, F>(f: F) -> ApiResult<T>
+where
+    F: FnOnce() -> ApiResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+async fn list_archives(State(state): State<Arc<AppState>>) -> ApiResult<Json<Value>> {
+    blocking(move || {
+        let mut names: Vec<String> = fs::read_dir(&state.dir)?
+            .filter_map(std::result::Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == ARCHIVE_EXT))
+            .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .collect();
+        names.sort_unstable();
+        Ok(Json(json!({ "archives": names })))
+    })
+    .await
+}
+
+async fn write_archive(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(params): Query<CompressParams>,
+    body: String,
+) -> ApiResult<(StatusCode, Json<Value>)> {
+    let level = params
+        .level
+        .as_deref()
+        .map(str::parse::<Level>)
+        .transpose()
+        .map_err(|e| ApiError::bad_request(e.to_string()))?
+        .unwrap_or_default();
+    let path = archive_path(&state, &name)?;
+
+    blocking(move || {
+        let compressed = compress_v3(&body, level)?;
+        write_atomic(&path, &compressed)?;
+
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = if body.is_empty() {
+            0.0
+        } else {
+            compressed.len() as f64 / body.len() as f64
+        };
+        Ok((
+            StatusCode::CREATED,
+            Json(json!({
+                "name": name,
+                "original_size": body.len(),
+                "compressed_size": compressed.len(),
+                "compression_ratio": ratio,
+            })),
+        ))
+    })
+    .await
+}
+
+async fn read_archive(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> ApiResult<String> {
+    let path = existing_archive(&state, &name)?;
+    blocking(move || Ok(QueryEngine::open(&path)?.decompress_all()?)).await
+}
+
+async fn archive_stats(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<FileStats>> {
+    let path = existing_archive(&state, &name)?;
+    blocking(move || Ok(Json(QueryEngine::open(&path)?.stats()))).await
+}
+
+async fn query_archive(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(params): Query<QueryParams>,
+) -> ApiResult<Json<Value>> {
+    let path = existing_archive(&state, &name)?;
+    let pool = state.query_pool.clone();
+    blocking(move || {
+        let mut engine = QueryEngine::open(&path)?;
+        if let Some(pool) = pool {
+            engine = engine.with_thread_pool(pool);
+        }
+
+        let select = params
+            .select
+            .unwrap_or_else(|| "log_levels,ipv4,timestamps".to_string());
+        let select_cols: Vec<&str> = select.split(',').map(str::trim).collect();
+
+        if params.lines {
+            let expr = params
+                .filter
+                .as_deref()
+                .ok_or_else(|| ApiError::bad_request("lines=true requires a where filter"))?;
+            let (col, op, val) = parse_filter(expr)
+                .ok_or_else(|| ApiError::bad_request(format!("Invalid filter: {expr}")))?;
+            let matches = engine.query_rows(&select_cols, &[(col, op, val)])?;
+            let total = matches.len();
+            let rows: Vec<_> = matches
+                .into_iter()
+                .take(params.limit.unwrap_or(usize::MAX))
+                .collect();
+            return Ok(Json(json!({
+                "columns": select_cols,
+                "total": total,
+                "rows": rows,
+            })));
+        }
+
+        // One match per line, as the CLI reports filtered queries
+        let rows: Vec<HashMap<String, String>> = match params.filter.as_deref() {
+            Some(expr) => {
+                let (col, op, val) = parse_filter(expr)
+                    .ok_or_else(|| ApiError::bad_request(format!("Invalid filter: {expr}")))?;
+                engine
+                    .query_rows(&select_cols, &[(col, op, val)])
+                    .map(|matches| matches.into_iter().map(|m| m.values).collect())
+            }
+            None => engine
+                .select_columns(&select_cols)
+                .map(|result| result.rows.into_iter().map(|r| r.values).collect()),
+        }?;
+
+        let total = rows.len();
+        let rows: Vec<_> = rows
+            .into_iter()
+            .take(params.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Ok(Json(json!({
+            "columns": select_cols,
+            "total": total,
+            "rows": rows,
+        })))
+    })
+    .await
+}
+
+async fn sample_archive(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(params): Query<SampleParams>,
+) -> ApiResult<Json<Value>> {
+    let strategy = match params.strategy.as_deref() {
+        None => SamplingStrategy::Head,
+        Some(s) => SamplingStrategy::from_name(s)
+            .ok_or_else(|| ApiError::bad_request(format!("Unknown strategy: {s}")))?,
+    };
+    let path = existing_archive(&state, &name)?;
+    blocking(move || {
+        let lines = QueryEngine::open(&path)?.sample(params.n.unwrap_or(20), strategy)?;
+        Ok(Json(json!({ "lines": lines })))
+    })
+    .await
+}
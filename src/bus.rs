@@ -0,0 +1,369 @@
+//! Bus — versioned frame format for ALICE-Text blocks on message buses
+//!
+//! Feature-gated: `#[cfg(feature = "bus")]`
+//!
+//! Kafka, Vector, NATS and friends move opaque byte messages; producers and
+//! consumers written in different languages still need to agree on what a
+//! message holds and which model decodes it. Every message is one frame:
+//!
+//! ```text
+//! MAGIC "ATXF" (4) | VERSION (2) | KIND u8 | FLAGS u8 | MODEL_ID u64 | LEN u32 | PAYLOAD (LEN) | CHECKSUM u64
+//! ```
+//!
+//! All integers are little-endian; the header is [`HEADER_SIZE`] bytes.
+//!
+//! | Kind | Payload |
+//! |------|---------|
+//! | 0 `Archive` | a complete `.atxt` archive (v2 or v3) |
+//! | 1 `Line` | one frame of a `LineBatch` (`TunedCompressor::compress_lines`) |
+//! | 2 `State` | an `.atxm` model (`LineBatch::state`, `TextModel::to_bytes`) |
+//!
+//! `MODEL_ID` is the `TextModel::id` the payload needs for decoding
+//! (0 = none), so a consumer can fetch or cache the right model before it
+//! touches the payload. `FLAGS` is reserved and must be 0. `CHECKSUM` is
+//! FNV-1a over everything before it. A different major version is
+//! rejected; readers of the same major ignore unknown flags.
+//!
+//! `LEN` makes the frame self-delimiting, so the same codec works for
+//! one-frame-per-message buses and for byte streams (TCP, files):
+//! [`FrameDecoder::decode`] follows the `tokio_util::codec::Decoder`
+//! contract (`Ok(None)` until a whole frame is buffered).
+//!
+//! ```rust
+//! use alice_text::bus::{Frame, FrameDecoder, FrameEncoder};
+//! use bytes::BytesMut;
+//!
+//! let mut buf = BytesMut::new();
+//! FrameEncoder.encode(&Frame::state(vec![1, 2, 3].into()), &mut buf).unwrap();
+//! let frame = FrameDecoder::default().decode(&mut buf).unwrap().unwrap();
+//! assert_eq!(&frame.payload[..], &[1, 2, 3]);
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::tuned_compressor::TunedCompressor;
+use crate::{fnv1a, to_u32, ALICETextError, Result, FNV_OFFSET};
+
+/// Magic bytes for bus frames
+pub const FRAME_MAGIC: &[u8; 4] = b"ATXF";
+
+/// Frame format version
+pub const FRAME_VERSION: (u8, u8) = (1, 0);
+
+/// Fixed header size (magic, version, kind, flags, model id, length)
+pub const HEADER_SIZE: usize = 4 + 2 + 1 + 1 + 8 + 4;
+
+/// Trailing checksum size
+pub const CHECKSUM_SIZE: usize = 8;
+
+/// Default upper bound for a frame payload (guards buffering on stream transports)
+pub const DEFAULT_MAX_PAYLOAD: usize = 16 * 1024 * 1024;
+
+/// What a frame payload holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameKind {
+    /// A complete `.atxt` archive
+    Archive = 0,
+    /// One `LineBatch` frame (needs the batch's model)
+    Line = 1,
+    /// An `.atxm` model / `LineBatch` state
+    State = 2,
+}
+
+impl FrameKind {
+    #[must_use]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Archive),
+            1 => Some(Self::Line),
+            2 => Some(Self::State),
+            _ => None,
+        }
+    }
+}
+
+/// One message on the bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    /// Model the payload needs for decoding (0 = none)
+    pub model_id: u64,
+    pub payload: Bytes,
+}
+
+impl Frame {
+    /// Wrap an `.atxt` archive; the model id is read from v2 archives
+    #[must_use]
+    pub fn archive(data: Bytes) -> Self {
+        let model_id = if data.get(8) == Some(&2) {
+            TunedCompressor::read_model_id(&data)
+                .ok()
+                .flatten()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        Self {
+            kind: FrameKind::Archive,
+            model_id,
+            payload: data,
+        }
+    }
+
+    /// Wrap one `LineBatch` frame written with model `model_id`
+    #[must_use]
+    pub const fn line(model_id: u64, data: Bytes) -> Self {
+        Self {
+            kind: FrameKind::Line,
+            model_id,
+            payload: data,
+        }
+    }
+
+    /// Wrap an `.atxm` model (the id is the model's own)
+    #[must_use]
+    pub fn state(data: Bytes) -> Self {
+        // Model header: magic (8) + version (2) + id (8)
+        let model_id = data
+            .get(10..18)
+            .and_then(|id| id.try_into().ok())
+            .map_or(0, u64::from_le_bytes);
+        Self {
+            kind: FrameKind::State,
+            model_id,
+            payload: data,
+        }
+    }
+
+    /// Encoded size of this frame
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        HEADER_SIZE + self.payload.len() + CHECKSUM_SIZE
+    }
+}
+
+/// Writes [`Frame`]s
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameEncoder;
+
+impl FrameEncoder {
+    /// Append `frame` to `dst`
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if the payload is 4 GiB or larger (nothing is written).
+    pub fn encode<B: BufMut>(&self, frame: &Frame, dst: &mut B) -> Result<()> {
+        let len = to_u32(frame.payload.len(), "frame payload size")?;
+        let mut header = [0u8; HEADER_SIZE];
+        header[0..4].copy_from_slice(FRAME_MAGIC);
+        header[4] = FRAME_VERSION.0;
+        header[5] = FRAME_VERSION.1;
+        header[6] = frame.kind as u8;
+        header[7] = 0;
+        header[8..16].copy_from_slice(&frame.model_id.to_le_bytes());
+        header[16..20].copy_from_slice(&len.to_le_bytes());
+
+        let checksum = fnv1a(&frame.payload, fnv1a(&header, FNV_OFFSET));
+        dst.put_slice(&header);
+        dst.put_slice(&frame.payload);
+        dst.put_u64_le(checksum);
+        Ok(())
+    }
+
+    /// Encode `frame` into a new buffer (one bus message)
+    ///
+    /// # Errors
+    ///
+    /// See [`Self::encode`].
+    pub fn to_bytes(&self, frame: &Frame) -> Result<Bytes> {
+        let mut buf = BytesMut::with_capacity(frame.encoded_len());
+        self.encode(frame, &mut buf)?;
+        Ok(buf.freeze())
+    }
+}
+
+/// Reads [`Frame`]s from a buffer that may hold partial or several frames
+#[derive(Debug, Clone, Copy)]
+pub struct FrameDecoder {
+    max_payload: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self {
+            max_payload: DEFAULT_MAX_PAYLOAD,
+        }
+    }
+}
+
+impl FrameDecoder {
+    /// Reject frames whose payload is larger than `max_payload`
+    #[must_use]
+    pub const fn with_max_payload(mut self, max_payload: usize) -> Self {
+        self.max_payload = max_payload;
+        self
+    }
+
+    /// Take the next complete frame off the front of `src`
+    ///
+    /// Returns `Ok(None)` (consuming nothing) while the frame is incomplete.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on bad magic, an unsupported major version, an
+    /// unknown kind, a payload over the limit, or a checksum mismatch. The
+    /// stream cannot be resynchronized after an error.
+    pub fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>> {
+        if src.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+        let header: [u8; HEADER_SIZE] = src[..HEADER_SIZE].try_into().unwrap_or_default();
+        if &header[0..4] != FRAME_MAGIC {
+            return Err(ALICETextError::DecompressionError(
+                "Invalid frame magic: expected ATXF".to_string(),
+            ));
+        }
+        if header[4] != FRAME_VERSION.0 {
+            return Err(ALICETextError::InvalidVersion(header[4], header[5]));
+        }
+        let kind = FrameKind::from_u8(header[6]).ok_or_else(|| {
+            ALICETextError::DecompressionError(format!("Unknown frame kind: {}", header[6]))
+        })?;
+        let model_id = u64::from_le_bytes(header[8..16].try_into().unwrap_or_default());
+        let len = u32::from_le_bytes(header[16..20].try_into().unwrap_or_default()) as usize;
+        if len > self.max_payload {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Frame payload of {len} bytes exceeds limit of {}",
+                self.max_payload
+            )));
+        }
+        if src.len() < HEADER_SIZE + len + CHECKSUM_SIZE {
+            src.reserve(HEADER_SIZE + len + CHECKSUM_SIZE - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_SIZE);
+        let payload = src.split_to(len).freeze();
+        let checksum = src.get_u64_le();
+        if checksum != fnv1a(&payload, fnv1a(&header, FNV_OFFSET)) {
+            return Err(ALICETextError::VerificationFailed(
+                "Frame checksum mismatch".to_string(),
+            ));
+        }
+        Ok(Some(Frame {
+            kind,
+            model_id,
+            payload,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TextModel, TunedCompressor};
+
+    #[test]
+    fn test_frame_stream_roundtrip() {
+        let log: String = (0..300)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:30:{:02} INFO request {i} from 10.0.0.{}\n",
+                    i % 60,
+                    i % 250
+                )
+            })
+            .collect();
+        let archive = TunedCompressor::default().compress(&log).unwrap();
+        let records: Vec<String> = log.lines().map(str::to_string).collect();
+        let batch = TunedCompressor::default()
+            .compress_lines(records.iter().map(String::as_str))
+            .unwrap();
+        let model = batch.model().unwrap();
+        let id = model.id().unwrap();
+
+        // Producer: state first, then records, then an archive, all on one stream
+        let mut stream = BytesMut::new();
+        let state = Frame::state(batch.state.clone().into());
+        assert_eq!(state.model_id, id);
+        FrameEncoder.encode(&state, &mut stream).unwrap();
+        for frame in &batch.frames[..3] {
+            FrameEncoder
+                .encode(&Frame::line(id, frame.clone().into()), &mut stream)
+                .unwrap();
+        }
+        FrameEncoder
+            .encode(&Frame::archive(archive.clone().into()), &mut stream)
+            .unwrap();
+
+        // Consumer: bytes arrive in small pieces
+        let wire = stream.freeze();
+        let mut decoder = FrameDecoder::default();
+        let mut buf = BytesMut::new();
+        let mut frames = Vec::new();
+        for chunk in wire.chunks(7) {
+            buf.extend_from_slice(chunk);
+            while let Some(frame) = decoder.decode(&mut buf).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(frames.len(), 5);
+
+        let reader = TunedCompressor::default()
+            .with_model(&TextModel::from_bytes(&frames[0].payload).unwrap())
+            .unwrap();
+        for (frame, record) in frames[1..4].iter().zip(&records) {
+            assert_eq!(frame.kind, FrameKind::Line);
+            assert_eq!(frame.model_id, id);
+            assert_eq!(reader.decompress_line(&frame.payload).unwrap(), *record);
+        }
+        assert_eq!(frames[4].kind, FrameKind::Archive);
+        assert_eq!(frames[4].model_id, 0);
+        assert_eq!(
+            TunedCompressor::default()
+                .decompress(&frames[4].payload)
+                .unwrap(),
+            log
+        );
+    }
+
+    #[test]
+    fn test_frame_validation() {
+        let frame = Frame::line(42, Bytes::from_static(b"payload"));
+        let wire = FrameEncoder.to_bytes(&frame).unwrap();
+        assert_eq!(wire.len(), frame.encoded_len());
+        assert_eq!(
+            FrameDecoder::default()
+                .decode(&mut BytesMut::from(&wire[..]))
+                .unwrap(),
+            Some(frame)
+        );
+
+        let decode = |bytes: &[u8]| FrameDecoder::default().decode(&mut BytesMut::from(bytes));
+        let mut corrupt = wire.to_vec();
+        corrupt[HEADER_SIZE] ^= 1;
+        assert!(matches!(
+            decode(&corrupt),
+            Err(ALICETextError::VerificationFailed(_))
+        ));
+        let mut newer = wire.to_vec();
+        newer[4] = FRAME_VERSION.0 + 1;
+        assert!(matches!(
+            decode(&newer),
+            Err(ALICETextError::InvalidVersion(..))
+        ));
+        let mut kind = wire.to_vec();
+        kind[6] = 9;
+        assert!(decode(&kind).is_err());
+        assert!(decode(b"ALICETXT-not-a-frame-at-all").is_err());
+        assert!(FrameDecoder::default()
+            .with_max_payload(4)
+            .decode(&mut BytesMut::from(&wire[..]))
+            .is_err());
+    }
+}
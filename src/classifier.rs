@@ -0,0 +1,347 @@
+//! Source classifier — pick a pattern profile from the text itself
+//!
+//! Mixed log directories hold syslog, JSON, web access logs, container logs
+//! and plain prose side by side. [`classify`] looks at the first
+//! [`SAMPLE_LINES`] non-empty lines of a block of text, recognizes each line
+//! by its shape (no regexes) and reports the kind most lines share;
+//! [`SourceKind::pattern_config`] maps the kind to the profile it compresses
+//! best with.
+//!
+//! [`FormatV3Writer::with_detected_profile`] applies the profile and records
+//! the kind in the archive ([`HeaderExtension::SOURCE_KIND`]), readable as
+//! [`FormatV3Metadata::source_kind`].
+//!
+//! | Kind | Recognized by | Profile |
+//! |------|---------------|---------|
+//! | [`SourceKind::Syslog`] | `<pri>`, `Jan 15 10:30:45 host prog[pid]:` or RFC 5424 header | default |
+//! | [`SourceKind::Json`] | `{ … }` per line | default |
+//! | [`SourceKind::AccessLog`] | `"GET /path HTTP/1.1" 200` | default |
+//! | [`SourceKind::Kubernetes`] | CRI prefix or klog header | [`PatternConfig::kubernetes`] |
+//! | [`SourceKind::Prose`] | long lines of words, almost no digits | [`PatternConfig::prose`] |
+//! | [`SourceKind::Plain`] | anything else, or no kind reaching [`MIN_SHARE`] | default |
+//!
+//! [`FormatV3Writer::with_detected_profile`]: crate::FormatV3Writer::with_detected_profile
+//! [`HeaderExtension::SOURCE_KIND`]: crate::format_v3::HeaderExtension::SOURCE_KIND
+//! [`FormatV3Metadata::source_kind`]: crate::format_v3::FormatV3Metadata::source_kind
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::tuned_pattern_learner::PatternConfig;
+
+/// Lines sampled from the start of a block
+pub const SAMPLE_LINES: usize = 256;
+
+/// Share of sampled lines a kind needs to be chosen over [`SourceKind::Plain`]
+pub const MIN_SHARE: f64 = 0.6;
+
+/// Words a line needs before it can count as prose
+const PROSE_MIN_WORDS: usize = 8;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// What a block of text looks like
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum SourceKind {
+    /// Line-oriented text with no recognized shape
+    #[default]
+    Plain = 0,
+    Syslog = 1,
+    /// One JSON object per line
+    Json = 2,
+    /// Common / combined web access log
+    AccessLog = 3,
+    /// CRI runtime output or klog
+    Kubernetes = 4,
+    /// Natural-language text (documents, dialogue)
+    Prose = 5,
+}
+
+impl SourceKind {
+    pub const ALL: [Self; 6] = [
+        Self::Plain,
+        Self::Syslog,
+        Self::Json,
+        Self::AccessLog,
+        Self::Kubernetes,
+        Self::Prose,
+    ];
+
+    #[must_use]
+    pub const fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::Plain),
+            1 => Some(Self::Syslog),
+            2 => Some(Self::Json),
+            3 => Some(Self::AccessLog),
+            4 => Some(Self::Kubernetes),
+            5 => Some(Self::Prose),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::Syslog => "syslog",
+            Self::Json => "json",
+            Self::AccessLog => "access-log",
+            Self::Kubernetes => "kubernetes",
+            Self::Prose => "prose",
+        }
+    }
+
+    /// Pattern profile for this kind
+    #[must_use]
+    pub fn pattern_config(self) -> PatternConfig {
+        match self {
+            Self::Kubernetes => PatternConfig::kubernetes(),
+            Self::Prose => PatternConfig::prose(),
+            Self::Plain | Self::Syslog | Self::Json | Self::AccessLog => PatternConfig::default(),
+        }
+    }
+}
+
+impl fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Result of [`classify`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Classification {
+    pub kind: SourceKind,
+    /// Share of sampled lines recognized as `kind` (0 for an empty sample)
+    pub share: f64,
+    /// Non-empty lines sampled
+    pub lines: usize,
+}
+
+/// Classify a block of text by its first [`SAMPLE_LINES`] non-empty lines
+#[must_use]
+pub fn classify(text: &str) -> Classification {
+    let mut counts = [0usize; SourceKind::ALL.len()];
+    let mut lines = 0;
+    for line in text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(SAMPLE_LINES)
+    {
+        counts[line_kind(line) as usize] += 1;
+        lines += 1;
+    }
+    // Most lines among the recognized kinds; ties go to the earlier kind
+    let (kind, count) = SourceKind::ALL[1..]
+        .iter()
+        .map(|&kind| (kind, counts[kind as usize]))
+        .fold((SourceKind::Plain, 0), |best, next| {
+            if next.1 > best.1 {
+                next
+            } else {
+                best
+            }
+        });
+    let share = if lines == 0 {
+        0.0
+    } else {
+        count as f64 / lines as f64
+    };
+    if share >= MIN_SHARE {
+        Classification { kind, share, lines }
+    } else {
+        Classification {
+            kind: SourceKind::Plain,
+            share: counts[0] as f64 / lines.max(1) as f64,
+            lines,
+        }
+    }
+}
+
+/// Kind of a single non-empty line
+fn line_kind(line: &str) -> SourceKind {
+    let trimmed = line.trim();
+    if is_cri(line) || is_klog(line) {
+        SourceKind::Kubernetes
+    } else if trimmed.starts_with('{') && trimmed.ends_with('}') {
+        SourceKind::Json
+    } else if is_access_log(line) {
+        SourceKind::AccessLog
+    } else if is_syslog(line) {
+        SourceKind::Syslog
+    } else if is_prose(trimmed) {
+        SourceKind::Prose
+    } else {
+        SourceKind::Plain
+    }
+}
+
+/// `2024-01-15T10:30:45.123456789Z stdout F `
+fn is_cri(line: &str) -> bool {
+    let b = line.as_bytes();
+    b.len() > 20
+        && b[..4].iter().all(u8::is_ascii_digit)
+        && b[4] == b'-'
+        && b[10] == b'T'
+        && line.find("Z std").is_some_and(|at| {
+            let rest = &line[at + 2..];
+            (rest.starts_with("stdout ") || rest.starts_with("stderr "))
+                && matches!(rest.as_bytes().get(7..9), Some(b"F " | b"P "))
+        })
+}
+
+/// `I0115 10:30:45.123456    1 main.go:42]`
+fn is_klog(line: &str) -> bool {
+    let b = line.as_bytes();
+    b.len() > 22
+        && matches!(b[0], b'I' | b'W' | b'E' | b'F')
+        && b[1..5].iter().all(u8::is_ascii_digit)
+        && b[5] == b' '
+        && is_clock(&b[6..14])
+        && b[14] == b'.'
+        && line.contains("] ")
+}
+
+/// `… "GET /index.html HTTP/1.1" 200 …`
+fn is_access_log(line: &str) -> bool {
+    line.find(" HTTP/").is_some_and(|at| {
+        let rest = &line.as_bytes()[at + 6..];
+        let Some(quote) = rest.iter().position(|&c| c == b'"') else {
+            return false;
+        };
+        let status = rest.get(quote + 1..quote + 5).unwrap_or_default();
+        status.len() == 4 && status[0] == b' ' && status[1..].iter().all(u8::is_ascii_digit)
+    })
+}
+
+/// `<34>Jan 15 10:30:45 host prog[42]: …`, `Jan  5 …` or `<34>1 2024-01-15T…`
+fn is_syslog(line: &str) -> bool {
+    let mut rest = line;
+    if let Some(after) = line.strip_prefix('<') {
+        let Some(end) = after.find('>') else {
+            return false;
+        };
+        if end == 0 || end > 3 || !after[..end].bytes().all(|b| b.is_ascii_digit()) {
+            return false;
+        }
+        rest = &after[end + 1..];
+        // RFC 5424: version, then an ISO timestamp
+        if rest.starts_with("1 ") && rest.as_bytes().get(2).is_some_and(u8::is_ascii_digit) {
+            return true;
+        }
+    }
+    let b = rest.as_bytes();
+    b.len() > 16
+        && MONTHS.iter().any(|m| rest.starts_with(m))
+        && b[3] == b' '
+        && (b[4] == b' ' || b[4].is_ascii_digit())
+        && b[5].is_ascii_digit()
+        && b[6] == b' '
+        && is_clock(&b[7..15])
+        && b[15] == b' '
+        && rest[16..].contains(": ")
+}
+
+/// `hh:mm:ss`
+fn is_clock(b: &[u8]) -> bool {
+    b.len() == 8
+        && b.iter().enumerate().all(|(i, c)| {
+            if i % 3 == 2 {
+                *c == b':'
+            } else {
+                c.is_ascii_digit()
+            }
+        })
+}
+
+/// Mostly letters across enough words, with at most a few digits
+fn is_prose(line: &str) -> bool {
+    if line.split_whitespace().count() < PROSE_MIN_WORDS {
+        return false;
+    }
+    let (mut letters, mut digits, mut other) = (0usize, 0usize, 0usize);
+    for c in line.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_alphabetic() {
+            letters += 1;
+        } else if c.is_ascii_digit() {
+            digits += 1;
+        } else if !matches!(
+            c,
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"' | '-' | '(' | ')'
+        ) {
+            other += 1;
+        }
+    }
+    let total = letters + digits + other;
+    letters * 10 >= total * 9 && digits * 50 <= total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_v3::{FormatV3Metadata, FormatV3Writer};
+    use crate::CompressionLevel;
+
+    #[test]
+    fn test_classify_kinds() {
+        let syslog = "<34>Jan 15 10:30:45 web-01 sshd[8842]: Accepted publickey for deploy\n\
+                      Jan  5 10:30:46 web-01 CRON[77]: (root) CMD (run-parts)\n";
+        let json = "{\"level\":\"info\",\"msg\":\"started\"}\n{\"level\":\"warn\",\"ms\":12}\n";
+        let access =
+            "10.0.0.1 - - [15/Jan/2024:10:30:45 +0000] \"GET /index.html HTTP/1.1\" 200 512\n";
+        let k8s = "2024-01-15T10:30:45.123456789Z stdout F started\n\
+                   I0115 10:30:45.123456       1 main.go:42] ready\n";
+        let prose = "It was a bright cold day in April, and the clocks were striking thirteen.\n";
+        let plain = "2024-01-15 10:30:45 INFO User logged in from 192.168.1.100\n";
+
+        for (text, kind) in [
+            (syslog, SourceKind::Syslog),
+            (json, SourceKind::Json),
+            (access, SourceKind::AccessLog),
+            (k8s, SourceKind::Kubernetes),
+            (prose, SourceKind::Prose),
+            (plain, SourceKind::Plain),
+        ] {
+            let found = classify(text);
+            assert_eq!(found.kind, kind, "{text}");
+            assert!((found.share - 1.0).abs() < f64::EPSILON);
+        }
+
+        // No kind reaches MIN_SHARE: plain
+        let mixed = format!("{json}{access}{prose}{plain}");
+        assert_eq!(classify(&mixed).kind, SourceKind::Plain);
+        assert_eq!(classify("").lines, 0);
+        for kind in SourceKind::ALL {
+            assert_eq!(SourceKind::from_u8(kind as u8), Some(kind));
+        }
+    }
+
+    #[test]
+    fn test_detected_profile_recorded() {
+        let text = "2024-01-15T10:30:45.123456789Z stderr F pod web-7fb96c846b-x2k9p restarted\n"
+            .repeat(20);
+        let writer = FormatV3Writer::new(CompressionLevel::Balanced)
+            .with_detected_profile(&text)
+            .unwrap();
+        let archive = writer.compress(&text).unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&archive)).unwrap();
+        assert_eq!(metadata.source_kind(), Some(SourceKind::Kubernetes));
+        assert_eq!(metadata.pattern_config, PatternConfig::kubernetes());
+        assert_eq!(FormatV3Writer::decompress(&archive).unwrap(), text);
+
+        // Archives written without detection carry no kind
+        let plain = FormatV3Writer::new(CompressionLevel::Balanced)
+            .compress(&text)
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&plain)).unwrap();
+        assert_eq!(metadata.source_kind(), None);
+    }
+}
@@ -0,0 +1,350 @@
+//! Column cache — on-disk cache of decoded v3 column blocks
+//!
+//! Interactive sessions (a TUI log viewer, repeated `alice-text query`
+//! runs) decompress the same columns over and over, and an in-memory cache
+//! dies with the process. [`ColumnCache`] keeps the Zstd-decoded bytes of
+//! each column block in a directory, keyed by archive content hash, column
+//! and row group, so the next process skips decompression. Attach it with
+//! `QueryEngine::with_cache`.
+//!
+//! One file per block: `{file_hash:016x}-{column:02}-{row_group}.atxc`,
+//! holding `MAGIC "ATXC" | FNV-1a u64 | bytes`. Entries that fail the check
+//! are dropped and treated as misses. Writes go through a temp file and a
+//! rename, so concurrent readers never see a partial entry.
+//!
+//! Size cap: after every insert the least recently used entries (by file
+//! mtime, refreshed on hit) are deleted until the directory is back under
+//! the cap. Blocks larger than the cap are not cached.
+//!
+//! v3 archives currently hold a single row group (0); the key carries it so
+//! archives split into row groups can share the cache layout.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use crate::format_v3::ColumnType;
+use crate::safe_write::SafeFileWriter;
+use crate::Result;
+
+/// Cache entry file extension
+pub const CACHE_EXT: &str = "atxc";
+
+/// Default size cap (256 MiB)
+pub const DEFAULT_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+
+const ENTRY_MAGIC: &[u8; 4] = b"ATXC";
+const ENTRY_HEADER: usize = 4 + 8;
+
+// ── FNV-1a (file-local) ───────────────────────────────────────
+#[inline(always)]
+fn fnv1a(data: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in data {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+/// Identifies one decoded column block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// FNV-1a of the whole archive (see [`ColumnCache::file_hash`])
+    pub file_hash: u64,
+    pub column: ColumnType,
+    pub row_group: u32,
+}
+
+impl CacheKey {
+    fn file_name(&self) -> String {
+        format!(
+            "{:016x}-{:02}-{}.{CACHE_EXT}",
+            self.file_hash, self.column as u8, self.row_group
+        )
+    }
+}
+
+/// Size-capped directory of decoded column blocks, shared across processes
+#[derive(Debug)]
+pub struct ColumnCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ColumnCache {
+    /// Use (and create if needed) `dir`, holding at most `max_bytes` of entries
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    /// Per-user default location: `$XDG_CACHE_HOME/alice-text`, else `~/.cache/alice-text`
+    #[must_use]
+    pub fn default_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .map(|dir| dir.join("alice-text"))
+    }
+
+    /// Content hash used as [`CacheKey::file_hash`]
+    #[must_use]
+    pub fn file_hash(data: &[u8]) -> u64 {
+        fnv1a(data)
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Lookups served from disk since this cache was opened
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Lookups that found no (valid) entry since this cache was opened
+    #[must_use]
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Decoded bytes for `key`, if cached and intact
+    #[must_use]
+    pub fn get(&self, key: &CacheKey) -> Option<Vec<u8>> {
+        let path = self.dir.join(key.file_name());
+        let entry = fs::read(&path).ok().and_then(|mut entry| {
+            let valid = entry.len() >= ENTRY_HEADER
+                && &entry[..4] == ENTRY_MAGIC
+                && entry[4..ENTRY_HEADER] == fnv1a(&entry[ENTRY_HEADER..]).to_le_bytes();
+            if valid {
+                entry.drain(..ENTRY_HEADER);
+                Some(entry)
+            } else {
+                let _ = fs::remove_file(&path);
+                None
+            }
+        });
+        if entry.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            // Refresh recency for eviction; a read-only cache still serves hits
+            if let Ok(file) = fs::File::options().write(true).open(&path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        entry
+    }
+
+    /// Store decoded bytes for `key`, then evict down to the size cap
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be written or old entries cannot be listed.
+    pub fn put(&self, key: &CacheKey, data: &[u8]) -> Result<()> {
+        if (ENTRY_HEADER + data.len()) as u64 > self.max_bytes {
+            return Ok(());
+        }
+        let name = key.file_name();
+        let mut entry = Vec::with_capacity(ENTRY_HEADER + data.len());
+        entry.extend_from_slice(ENTRY_MAGIC);
+        entry.extend_from_slice(&fnv1a(data).to_le_bytes());
+        entry.extend_from_slice(data);
+        // A lost entry is just a cache miss: atomic, but no fsync
+        SafeFileWriter::fast().write(self.dir.join(&name), &entry)?;
+        self.evict()
+    }
+
+    /// Total size of all entries
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be listed.
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|(_, size, _)| size).sum())
+    }
+
+    /// Delete all entries
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be listed or an entry cannot be removed.
+    pub fn clear(&self) -> Result<()> {
+        for (path, _, _) in self.entries()? {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Delete least recently used entries until the total fits the cap
+    fn evict(&self) -> Result<()> {
+        let mut entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        for (path, size, _) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            // Another process may have evicted it already
+            if fs::remove_file(&path).is_ok() {
+                total -= size;
+            }
+        }
+        Ok(())
+    }
+
+    /// (path, size, mtime) of every entry
+    fn entries(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut entries = Vec::new();
+        for dirent in fs::read_dir(&self.dir)? {
+            let dirent = dirent?;
+            let path = dirent.path();
+            if path.extension().is_none_or(|ext| ext != CACHE_EXT) {
+                continue;
+            }
+            if let Ok(meta) = dirent.metadata() {
+                let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                entries.push((path, meta.len(), modified));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress_v3, CompressionLevel, Op, QueryEngine};
+    use std::io::Cursor;
+
+    fn key(column: ColumnType) -> CacheKey {
+        CacheKey {
+            file_hash: 0xABCD,
+            column,
+            row_group: 0,
+        }
+    }
+
+    #[test]
+    fn test_cache_entries_and_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ColumnCache::open(dir.path(), 3 * (ENTRY_HEADER as u64 + 100)).unwrap();
+
+        assert_eq!(cache.get(&key(ColumnType::IPv4)), None);
+        cache.put(&key(ColumnType::IPv4), &[1; 100]).unwrap();
+        assert_eq!(cache.get(&key(ColumnType::IPv4)), Some(vec![1; 100]));
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // Oldest entries go first once the cap is exceeded
+        let old = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        for (i, column) in [ColumnType::Numbers, ColumnType::Emails, ColumnType::URLs]
+            .into_iter()
+            .enumerate()
+        {
+            cache.put(&key(column), &[i as u8; 100]).unwrap();
+            if column == ColumnType::Numbers {
+                let path = dir.path().join(key(column).file_name());
+                let file = fs::File::options().write(true).open(path).unwrap();
+                file.set_modified(old).unwrap();
+            }
+        }
+        assert_eq!(cache.get(&key(ColumnType::Numbers)), None);
+        assert!(cache.get(&key(ColumnType::URLs)).is_some());
+        assert!(cache.size().unwrap() <= 3 * (ENTRY_HEADER as u64 + 100));
+
+        // Corrupt entries are misses, oversized blocks are skipped
+        let path = dir.path().join(key(ColumnType::URLs).file_name());
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[ENTRY_HEADER] ^= 0xFF;
+        fs::write(&path, bytes).unwrap();
+        assert_eq!(cache.get(&key(ColumnType::URLs)), None);
+        assert!(!path.exists());
+        cache.put(&key(ColumnType::Paths), &[0; 1000]).unwrap();
+        assert_eq!(cache.get(&key(ColumnType::Paths)), None);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.size().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_query_cache_survives_restart() {
+        let text: String = (0..500)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:30:{:02} {} request from 10.0.0.{}\n",
+                    i % 60,
+                    ["INFO", "WARN", "ERROR"][i % 3],
+                    i % 250
+                )
+            })
+            .collect();
+        let data = compress_v3(&text, CompressionLevel::Balanced).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let open = || {
+            QueryEngine::from_reader(Cursor::new(&data))
+                .unwrap()
+                .with_cache(ColumnCache::open(dir.path(), DEFAULT_CACHE_SIZE).unwrap())
+        };
+
+        let first = open();
+        let expected = first
+            .query(&["ipv4"], "log_levels", Op::Eq, "ERROR")
+            .unwrap();
+        assert_eq!(first.cache().unwrap().hits(), 0);
+
+        // A new engine (new process) reads the decoded columns from disk
+        let second = open();
+        let result = second
+            .query(&["ipv4"], "log_levels", Op::Eq, "ERROR")
+            .unwrap();
+        assert_eq!(result.column_values("ipv4"), expected.column_values("ipv4"));
+        // log_levels, ipv4, skeleton and placeholder map (to line values up by row)
+        assert_eq!(second.cache().unwrap().hits(), 4);
+        assert_eq!(second.cache().unwrap().misses(), 0);
+        assert_eq!(
+            second
+                .sample(3, crate::SamplingStrategy::Head)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // A different archive does not hit
+        let other = compress_v3(
+            &text.replace("10.0.0.", "10.9.9."),
+            CompressionLevel::Balanced,
+        )
+        .unwrap();
+        let third = QueryEngine::from_reader(Cursor::new(&other))
+            .unwrap()
+            .with_cache(ColumnCache::open(dir.path(), DEFAULT_CACHE_SIZE).unwrap());
+        let result = third
+            .query(&["ipv4"], "log_levels", Op::Eq, "ERROR")
+            .unwrap();
+        assert!(result.column_values("ipv4")[0].starts_with("10.9.9."));
+        assert_eq!(third.cache().unwrap().hits(), 0);
+    }
+}
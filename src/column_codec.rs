@@ -0,0 +1,1391 @@
+//! Column codec — fixed little-endian encodings for v3 column payloads
+//!
+//! Every v3 column block is Zstd over the bytes described here. The layout
+//! is spelled out (rather than delegated to a serialization crate) so an
+//! archive can be decoded from this page alone, independent of any library
+//! version. It matches what bincode 1.x produced with default options, so
+//! archives written before this codec existed decode unchanged.
+//!
+//! | Type | Encoding |
+//! |------|----------|
+//! | `u8` `u16` `u32` `u64` `i32` `i64` `u128` | fixed width, little-endian |
+//! | `f64` | IEEE 754 bits as `u64` |
+//! | `String` | `LEN u64` + UTF-8 bytes |
+//! | `Vec<T>` | `LEN u64` + `LEN` × `T` |
+//! | `Option<T>` | `0u8`, or `1u8` + `T` |
+//! | `(A, B)`, structs | fields in declaration order, no padding |
+//! | enums | variant index as `u32` + the variant's fields |
+//!
+//! Per column:
+//!
+//! | Column | Rust type | Layout |
+//! |--------|-----------|--------|
+//! | `skeleton` | `Vec<SkeletonToken>` | `Text(String)` = 0, `Ref(u32)` = 1 |
+//! | `skeleton` (interned) | see `format_v3` | `literals: Vec<String>`, `tokens: Vec<Literal(u32) = 0 \| Ref(u32) = 1>` |
+//! | `placeholder_map` | `Vec<(u8, u32)>` | packed layout in `format_v3` when flagged |
+//! | `timestamps` | `TimestampColumn` | `base: Option<String>`, `base_ms: Option<i64>`, `deltas: Vec<i64>`, `raw: Vec<String>`, `cached_format_idx: Option<None = 0 \| Naive(u64) = 1 \| Tz(u64) = 2>`, `last_ms: i64`, `base_offset_secs: Option<i32>` |
+//! | `ipv4`, `date_days`, `time_ms` | `Vec<u32>` | |
+//! | `ipv6`, `uuids` | `Vec<u128>` | |
+//! | `log_levels` | `Vec<u8>` | |
+//! | `http_methods` | `Vec<u8>` | index into `GET HEAD POST PUT DELETE CONNECT OPTIONS TRACE PATCH` |
+//! | `http_statuses` | `Vec<u16>` | |
+//! | `pids`, `tids` | `Vec<u32>` | |
+//! | `durations` | `DurationColumn` | `nanos: Vec<u64>`, `formats: Vec<u32>` (one byte per component: `(unit + 1) << 4 \| fraction digits`) |
+//! | `hosts` | `HostColumn` | `labels: Vec<String>`, `nodes: Vec<(parent + 1 u32, label u32)>` (suffix tree, TLD at the root), `hosts: Vec<u32>` node ids |
+//! | `byte_sizes` | `ByteSizeColumn` | `bytes: Vec<u64>`, `formats: Vec<u8>` (`(unit + 1) << 4 \| fraction digits`) |
+//! | `numbers` | `Vec<f64>` | |
+//! | `mac_addrs` | `Vec<u64>` | 48-bit address; bit 48 `-` separators, bit 49 upper case |
+//! | `geo_points` | `Vec<GeoPoint>` | `lat: f32`, `lon: f32`, `format: u8` |
+//! | string columns | `Vec<String>` | `emails`, `urls`, `paths`, `dates_raw`, `times_raw`, `hex_values`, `others`, `timestamps_raw`, `ansi_codes` |
+//! | `epochs`, `uptimes` | `NumericTimeColumn` | `deltas: Vec<i64>`, `formats: Vec<u8>` |
+//! | `raw_bytes` | `Vec<RawSegment>` | `ordinal: u64`, `bytes: Vec<u8>` |
+//! | `provenance` | `ProvenanceColumn` | `sources: Vec<String>`, `lines: Vec<(source u32, byte_offset u64, line_number u64)>` |
+//! | `line_ids` | `LineIdColumn` | `deltas: Vec<u64>` (first id, then the gap to each next id; gaps are non-zero) |
+//! | `plugins` | `Vec<Vec<u8>>` | each column plugin's encoded bytes, in schema order |
+//! | `spellings` | `Vec<(u32, String)>` | placeholder index (ascending) and original text of typed values restored differently |
+//! | `cri_prefixes` | `CriColumn` | `deltas: Vec<i64>` (ns), `formats: Vec<u8>` |
+//! | `klog_headers` | `KlogColumn` | `files: Vec<String>`, `headers: Vec<(severity u8, when u64, gap u8, pid u32, file u32, line u32)>` |
+//! | `container_ids` | `Vec<ContainerId>` | `runtime: u8`, `id: [u8; 32]` (raw bytes, no length) |
+//! | `k8s_namespaces` | `NamespaceColumn` | `names: Vec<String>`, `values: Vec<(key u8, name u32)>` |
+//! | `k8s_pods` | `PodColumn` | `prefixes: Vec<String>`, `pods: Vec<(prefix u32, suffix u32)>` |
+//!
+//! The v3 header records outside the column blocks use the same rules:
+//!
+//! | Record | Rust type | Layout |
+//! |--------|-----------|--------|
+//! | column summaries | `Vec<ColumnSummary>` | `col_type` (variant `u32` = its column tag), `count u64`, `null_count u64`, `min`, `max`, `p50`, `p90`, `p99` (`f64`) |
+//! | pattern config | `PatternConfig` | `priority: Vec<PatternType>`, `disabled: Vec<PatternType>` (variant `u32` = the pattern tag), `guards: Vec<(PatternType, not_preceded_by String, not_followed_by String)>`, `exclusions: Vec<(PatternType, regex String)>` |
+//! | `PLUGIN_SCHEMA` extension | `Vec<PluginSchema>` | `id: String`, `count: u64`, `stats: Vec<(String, f64)>` |
+//!
+//! v1/v2 payloads and the dialogue, delta and model files are not covered
+//! here; they are still serialized with bincode 1.x.
+//!
+//! Decoding rejects truncated input, trailing bytes, invalid UTF-8, unknown
+//! enum variants and option tags other than 0/1.
+//!
+//! A `Vec` of fixed-width values is its length followed by a plain
+//! little-endian array, so [`column_slice`] reads such a column in place
+//! (`ipv4`, `numbers`, `http_statuses`, ...) instead of decoding it.
+//!
+//! ## Alternative Encodings
+//!
+//! The integer columns (`ipv4`, `ipv6`, `uuids`, `log_levels`,
+//! `http_methods`, `http_statuses`, `pids`, `tids`, `date_days`, `time_ms`)
+//! and `numbers` (as the `u64` bits of each `f64`) may be stored in another
+//! [`ColumnEncoding`]; the archive records which one per column and
+//! [`decode_values`] dispatches on it:
+//!
+//! | Encoding | Layout |
+//! |----------|--------|
+//! | `Plain` (0) | `Vec<T>` as above |
+//! | `Dict` (1) | [`DedupColumn`]: `values: Vec<T>` (distinct, first-seen order), `width: u8` (1, 2 or 4), `indices: LEN u64` + `LEN` × `width`-byte LE positions into `values` |
+//! | `Rle` (2) | [`RunColumn`]: `values: Vec<T>`, `lengths: Vec<u32>` (one run each, non-zero) |
+//! | `Delta` (3) | `Vec<T>` of wrapping differences from the previous value (the first from 0) |
+//! | `Shuffle` (4) | `LEN u64`, then the values' bytes by plane: byte 0 of every value, byte 1 of every value, ... |
+//!
+//! Filters on `Dict` and `Rle` columns test each distinct value or run once
+//! and select rows by index.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use crate::column_plugin::PluginSchema;
+use crate::columnar_encoder::{
+    ByteSizeColumn, CachedFormatType, DurationColumn, GeoPoint, HostColumn, NumericTimeColumn,
+    SkeletonToken, TimestampColumn,
+};
+use crate::format_v3::{ColumnSummary, ColumnType};
+use crate::kubernetes::{
+    ContainerId, CriColumn, Dictionary, KlogColumn, KlogHeader, NamespaceColumn, PodColumn,
+};
+use crate::line_index::LineIdColumn;
+use crate::provenance::{LineOrigin, ProvenanceColumn};
+use crate::source_profile::RawSegment;
+use crate::tuned_pattern_learner::{PatternConfig, PatternExclusion, PatternGuard, PatternType};
+use crate::{ALICETextError, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A value with a documented column encoding
+pub trait ColumnCodec {
+    /// Append the encoding of `self` to `out`
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Read one value from the front of `input`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `input` is truncated or malformed.
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self>
+    where
+        Self: Sized;
+}
+
+/// Cursor over encoded column bytes
+#[derive(Debug)]
+pub struct ColumnReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ColumnReader<'a> {
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Bytes not read yet
+    #[must_use]
+    pub const fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.remaining() {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Column data truncated at byte {}",
+                self.pos
+            )));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    /// Length prefix; every element takes at least one byte, which bounds it
+    fn len(&mut self) -> Result<usize> {
+        let len = u64::from_le_bytes(self.array()?);
+        usize::try_from(len)
+            .ok()
+            .filter(|&len| len <= self.remaining())
+            .ok_or_else(|| {
+                ALICETextError::DecompressionError(format!(
+                    "Column length {len} exceeds remaining {} bytes",
+                    self.remaining()
+                ))
+            })
+    }
+
+    /// Enum variant index
+    fn variant(&mut self) -> Result<u32> {
+        u32::decode(self)
+    }
+}
+
+/// Encode `value` as a column block (before Zstd)
+#[must_use]
+pub fn encode_column<T: ColumnCodec + ?Sized>(value: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.encode(&mut out);
+    out
+}
+
+/// Decode a whole column block (after Zstd)
+///
+/// # Errors
+///
+/// Returns an error if the bytes are malformed or not fully consumed.
+pub fn decode_column<T: ColumnCodec>(bytes: &[u8]) -> Result<T> {
+    let mut reader = ColumnReader::new(bytes);
+    let value = T::decode(&mut reader)?;
+    if reader.remaining() != 0 {
+        return Err(ALICETextError::DecompressionError(format!(
+            "{} trailing bytes after column data",
+            reader.remaining()
+        )));
+    }
+    Ok(value)
+}
+
+/// View a `Vec<T>` column block of fixed-width values (after Zstd) in place
+///
+/// The values follow the length prefix as a plain little-endian array, so on
+/// little-endian targets this is a bounds-checked cast when the array is
+/// aligned for `T`; otherwise the values are decoded into a new vector.
+///
+/// # Errors
+///
+/// Returns an error if the length prefix does not match the block size.
+pub fn column_slice<T: ColumnCodec + bytemuck::Pod>(bytes: &[u8]) -> Result<Cow<'_, [T]>> {
+    let mut reader = ColumnReader::new(bytes);
+    let len = u64::decode(&mut reader)?;
+    let values = &bytes[bytes.len() - reader.remaining()..];
+    let expected = usize::try_from(len)
+        .ok()
+        .and_then(|len| len.checked_mul(std::mem::size_of::<T>()));
+    if expected != Some(values.len()) {
+        return Err(ALICETextError::DecompressionError(format!(
+            "Column length {len} does not match {} value bytes",
+            values.len()
+        )));
+    }
+    if cfg!(target_endian = "little") {
+        if let Ok(values) = bytemuck::try_cast_slice(values) {
+            return Ok(Cow::Borrowed(values));
+        }
+    }
+    decode_column(bytes).map(Cow::Owned)
+}
+
+fn unknown_variant(what: &str, variant: u32) -> ALICETextError {
+    ALICETextError::DecompressionError(format!("Unknown {what} variant {variant}"))
+}
+
+macro_rules! fixed_le {
+    ($($t:ty),*) => {$(
+        impl ColumnCodec for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+                Ok(<$t>::from_le_bytes(input.array()?))
+            }
+        }
+    )*};
+}
+
+fixed_le!(u8, u16, u32, u64, i32, i64, u128, f32, f64);
+
+impl ColumnCodec for str {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl ColumnCodec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_str().encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        let len = input.len()?;
+        String::from_utf8(input.take(len)?.to_vec())
+            .map_err(|e| ALICETextError::DecompressionError(format!("Column string: {e}")))
+    }
+}
+
+impl<T: ColumnCodec> ColumnCodec for [T] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u64).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+impl<T: ColumnCodec> ColumnCodec for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        let len = input.len()?;
+        (0..len).map(|_| T::decode(input)).collect()
+    }
+}
+
+impl<T: ColumnCodec> ColumnCodec for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            None => out.push(0),
+            Some(value) => {
+                out.push(1);
+                value.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => T::decode(input).map(Some),
+            tag => Err(ALICETextError::DecompressionError(format!(
+                "Invalid option tag {tag}"
+            ))),
+        }
+    }
+}
+
+impl<A: ColumnCodec, B: ColumnCodec> ColumnCodec for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl ColumnCodec for SkeletonToken {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Text(text) => {
+                0u32.encode(out);
+                text.encode(out);
+            }
+            Self::Ref(idx) => {
+                1u32.encode(out);
+                idx.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        match input.variant()? {
+            0 => String::decode(input).map(Self::Text),
+            1 => u32::decode(input).map(Self::Ref),
+            v => Err(unknown_variant("skeleton token", v)),
+        }
+    }
+}
+
+impl ColumnCodec for CachedFormatType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match *self {
+            Self::None => 0u32.encode(out),
+            Self::Naive(idx) => {
+                1u32.encode(out);
+                (idx as u64).encode(out);
+            }
+            Self::Tz(idx) => {
+                2u32.encode(out);
+                (idx as u64).encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        let variant = input.variant()?;
+        let index = |input: &mut ColumnReader<'_>| -> Result<usize> {
+            let idx = u64::decode(input)?;
+            usize::try_from(idx).map_err(|_| {
+                ALICETextError::DecompressionError(format!("Format index {idx} out of range"))
+            })
+        };
+        match variant {
+            0 => Ok(Self::None),
+            1 => index(input).map(Self::Naive),
+            2 => index(input).map(Self::Tz),
+            v => Err(unknown_variant("timestamp format", v)),
+        }
+    }
+}
+
+impl ColumnCodec for TimestampColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.base.encode(out);
+        self.base_ms.encode(out);
+        self.deltas.encode(out);
+        self.raw.encode(out);
+        self.cached_format_idx.encode(out);
+        self.last_ms.encode(out);
+        self.base_offset_secs.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            base: ColumnCodec::decode(input)?,
+            base_ms: ColumnCodec::decode(input)?,
+            deltas: ColumnCodec::decode(input)?,
+            raw: ColumnCodec::decode(input)?,
+            cached_format_idx: ColumnCodec::decode(input)?,
+            last_ms: ColumnCodec::decode(input)?,
+            base_offset_secs: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for NumericTimeColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.deltas.encode(out);
+        self.formats.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            deltas: ColumnCodec::decode(input)?,
+            formats: ColumnCodec::decode(input)?,
+            ..Self::default()
+        })
+    }
+}
+
+impl ColumnCodec for DurationColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.nanos.encode(out);
+        self.formats.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            nanos: ColumnCodec::decode(input)?,
+            formats: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for ByteSizeColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.bytes.encode(out);
+        self.formats.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            bytes: ColumnCodec::decode(input)?,
+            formats: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for HostColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.labels.encode(out);
+        self.nodes.encode(out);
+        self.hosts.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self::from_parts(
+            ColumnCodec::decode(input)?,
+            ColumnCodec::decode(input)?,
+            ColumnCodec::decode(input)?,
+        ))
+    }
+}
+
+impl ColumnCodec for GeoPoint {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.lat.encode(out);
+        self.lon.encode(out);
+        self.format.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            lat: ColumnCodec::decode(input)?,
+            lon: ColumnCodec::decode(input)?,
+            format: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for Dictionary {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.entries.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self::from_entries(ColumnCodec::decode(input)?))
+    }
+}
+
+impl ColumnCodec for CriColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.deltas.encode(out);
+        self.formats.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            deltas: ColumnCodec::decode(input)?,
+            formats: ColumnCodec::decode(input)?,
+            ..Self::default()
+        })
+    }
+}
+
+impl ColumnCodec for KlogHeader {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.severity.encode(out);
+        self.when.encode(out);
+        self.gap.encode(out);
+        self.pid.encode(out);
+        self.file.encode(out);
+        self.line.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            severity: ColumnCodec::decode(input)?,
+            when: ColumnCodec::decode(input)?,
+            gap: ColumnCodec::decode(input)?,
+            pid: ColumnCodec::decode(input)?,
+            file: ColumnCodec::decode(input)?,
+            line: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for KlogColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.files.encode(out);
+        self.headers.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            files: ColumnCodec::decode(input)?,
+            headers: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for ContainerId {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.runtime.encode(out);
+        out.extend_from_slice(&self.id);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            runtime: ColumnCodec::decode(input)?,
+            id: input.array()?,
+        })
+    }
+}
+
+impl ColumnCodec for NamespaceColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.names.encode(out);
+        self.values.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            names: ColumnCodec::decode(input)?,
+            values: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for PodColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.prefixes.encode(out);
+        self.pods.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            prefixes: ColumnCodec::decode(input)?,
+            pods: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for RawSegment {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.ordinal.encode(out);
+        self.bytes.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            ordinal: ColumnCodec::decode(input)?,
+            bytes: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for ColumnType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u32::from(*self as u8).encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        let variant = input.variant()?;
+        u8::try_from(variant)
+            .ok()
+            .and_then(Self::from_u8)
+            .ok_or_else(|| unknown_variant("column type", variant))
+    }
+}
+
+impl ColumnCodec for ColumnSummary {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.col_type.encode(out);
+        self.count.encode(out);
+        self.null_count.encode(out);
+        self.min.encode(out);
+        self.max.encode(out);
+        self.p50.encode(out);
+        self.p90.encode(out);
+        self.p99.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            col_type: ColumnCodec::decode(input)?,
+            count: ColumnCodec::decode(input)?,
+            null_count: ColumnCodec::decode(input)?,
+            min: ColumnCodec::decode(input)?,
+            max: ColumnCodec::decode(input)?,
+            p50: ColumnCodec::decode(input)?,
+            p90: ColumnCodec::decode(input)?,
+            p99: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for PatternType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u32::from(*self as u8).encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        // `from_u8` maps unknown tags to `Custom` (12); only 12 itself may
+        let variant = input.variant()?;
+        u8::try_from(variant)
+            .ok()
+            .map(Self::from_u8)
+            .filter(|pattern| u32::from(*pattern as u8) == variant)
+            .ok_or_else(|| unknown_variant("pattern type", variant))
+    }
+}
+
+impl ColumnCodec for PatternGuard {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.pattern_type.encode(out);
+        self.not_preceded_by.encode(out);
+        self.not_followed_by.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            pattern_type: ColumnCodec::decode(input)?,
+            not_preceded_by: ColumnCodec::decode(input)?,
+            not_followed_by: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for PatternExclusion {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.pattern_type.encode(out);
+        self.regex.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            pattern_type: ColumnCodec::decode(input)?,
+            regex: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for PatternConfig {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.priority.encode(out);
+        self.disabled.encode(out);
+        self.guards.encode(out);
+        self.exclusions.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            priority: ColumnCodec::decode(input)?,
+            disabled: ColumnCodec::decode(input)?,
+            guards: ColumnCodec::decode(input)?,
+            exclusions: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for PluginSchema {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.id.encode(out);
+        self.count.encode(out);
+        self.stats.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            id: ColumnCodec::decode(input)?,
+            count: ColumnCodec::decode(input)?,
+            stats: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for LineOrigin {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.source.encode(out);
+        self.byte_offset.encode(out);
+        self.line_number.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            source: ColumnCodec::decode(input)?,
+            byte_offset: ColumnCodec::decode(input)?,
+            line_number: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for ProvenanceColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.sources.encode(out);
+        self.lines.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            sources: ColumnCodec::decode(input)?,
+            lines: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+impl ColumnCodec for LineIdColumn {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.deltas().encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        let deltas: Vec<u64> = ColumnCodec::decode(input)?;
+        Self::from_deltas(&deltas)
+    }
+}
+
+/// Layout of an integer column's block (see module docs)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum ColumnEncoding {
+    #[default]
+    Plain = 0,
+    Dict = 1,
+    Rle = 2,
+    Delta = 3,
+    Shuffle = 4,
+}
+
+impl ColumnEncoding {
+    /// Every encoding, in tag order
+    pub const ALL: [Self; 5] = [
+        Self::Plain,
+        Self::Dict,
+        Self::Rle,
+        Self::Delta,
+        Self::Shuffle,
+    ];
+
+    #[must_use]
+    pub const fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Plain),
+            1 => Some(Self::Dict),
+            2 => Some(Self::Rle),
+            3 => Some(Self::Delta),
+            4 => Some(Self::Shuffle),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::Dict => "dict",
+            Self::Rle => "rle",
+            Self::Delta => "delta",
+            Self::Shuffle => "shuffle",
+        }
+    }
+}
+
+/// Fixed-width integer values the alternative encodings apply to
+pub trait EncodableValue: ColumnCodec + Copy + Eq + Hash + Default {
+    #[must_use]
+    fn wrapping_sub(self, other: Self) -> Self;
+    #[must_use]
+    fn wrapping_add(self, other: Self) -> Self;
+}
+
+macro_rules! encodable {
+    ($($t:ty),*) => {$(
+        impl EncodableValue for $t {
+            fn wrapping_sub(self, other: Self) -> Self {
+                <$t>::wrapping_sub(self, other)
+            }
+
+            fn wrapping_add(self, other: Self) -> Self {
+                <$t>::wrapping_add(self, other)
+            }
+        }
+    )*};
+}
+
+encodable!(u8, u16, u32, u64, u128);
+
+/// Encode `values` as `encoding`
+#[must_use]
+pub fn encode_values<T: EncodableValue>(values: &[T], encoding: ColumnEncoding) -> Vec<u8> {
+    match encoding {
+        ColumnEncoding::Plain => encode_column(values),
+        ColumnEncoding::Dict => encode_column(&DedupColumn::new(values)),
+        ColumnEncoding::Rle => encode_column(&RunColumn::new(values)),
+        ColumnEncoding::Delta => {
+            let mut previous = T::default();
+            let deltas: Vec<T> = values
+                .iter()
+                .map(|&value| {
+                    let delta = value.wrapping_sub(previous);
+                    previous = value;
+                    delta
+                })
+                .collect();
+            encode_column(&deltas)
+        }
+        ColumnEncoding::Shuffle => {
+            let mut out = encode_column(values);
+            let planes = transpose(&out[8..], values.len(), std::mem::size_of::<T>());
+            out.truncate(8);
+            out.extend_from_slice(&planes);
+            out
+        }
+    }
+}
+
+/// Transpose a `rows` × `cols` byte matrix (row-major in and out)
+fn transpose(bytes: &[u8], rows: usize, cols: usize) -> Vec<u8> {
+    let mut out = vec![0u8; bytes.len()];
+    for (r, row) in bytes.chunks_exact(cols).enumerate() {
+        for (c, &byte) in row.iter().enumerate() {
+            out[c * rows + r] = byte;
+        }
+    }
+    out
+}
+
+/// Decode a block of `rows` values stored as `encoding`
+///
+/// # Errors
+///
+/// Returns an error if the block is malformed or (other than `Plain`, which
+/// older archives wrote without a checked row count) holds a different
+/// number of rows.
+pub fn decode_values<T: EncodableValue>(
+    bytes: &[u8],
+    encoding: ColumnEncoding,
+    rows: usize,
+) -> Result<Vec<T>> {
+    let values = match encoding {
+        ColumnEncoding::Plain => return decode_column(bytes),
+        ColumnEncoding::Dict => decode_column::<DedupColumn<T>>(bytes)?.expand(),
+        ColumnEncoding::Rle => decode_runs::<T>(bytes, rows)?.expand(),
+        ColumnEncoding::Delta => {
+            let mut previous = T::default();
+            let mut values: Vec<T> = decode_column(bytes)?;
+            for value in &mut values {
+                previous = previous.wrapping_add(*value);
+                *value = previous;
+            }
+            values
+        }
+        ColumnEncoding::Shuffle => {
+            let width = std::mem::size_of::<T>();
+            let len = u64::decode(&mut ColumnReader::new(bytes))?;
+            let planes = &bytes[8..];
+            if usize::try_from(len)
+                .ok()
+                .and_then(|len| len.checked_mul(width))
+                != Some(planes.len())
+            {
+                return Err(ALICETextError::DecompressionError(format!(
+                    "Shuffled column length {len} does not match {} value bytes",
+                    planes.len()
+                )));
+            }
+            let mut plain = bytes[..8].to_vec();
+            plain.extend_from_slice(&transpose(planes, width, planes.len() / width));
+            decode_column(&plain)?
+        }
+    };
+    check_rows(values.len(), rows)?;
+    Ok(values)
+}
+
+/// Decode an `Rle` block, checking its runs cover `rows` rows before anything
+/// is expanded
+///
+/// # Errors
+///
+/// Returns an error if the block is malformed or covers a different number
+/// of rows.
+pub fn decode_runs<T: ColumnCodec>(bytes: &[u8], rows: usize) -> Result<RunColumn<T>> {
+    let column: RunColumn<T> = decode_column(bytes)?;
+    check_rows(column.len(), rows)?;
+    Ok(column)
+}
+
+fn check_rows(found: usize, rows: usize) -> Result<()> {
+    if found == rows {
+        Ok(())
+    } else {
+        Err(ALICETextError::DecompressionError(format!(
+            "Encoded column holds {found} rows, directory says {rows}"
+        )))
+    }
+}
+
+/// A column stored as runs of equal values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunColumn<T> {
+    /// Value of each run
+    pub values: Vec<T>,
+    /// Rows in each run (non-zero)
+    pub lengths: Vec<u32>,
+}
+
+impl<T> RunColumn<T> {
+    /// Rows the runs cover
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lengths.iter().map(|&len| len as usize).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lengths.is_empty()
+    }
+
+    /// Rows of the runs at `positions` in [`Self::values`]
+    #[must_use]
+    pub fn rows_at(&self, positions: &[usize]) -> Vec<usize> {
+        let mut starts = Vec::with_capacity(self.lengths.len());
+        let mut row = 0;
+        for &len in &self.lengths {
+            starts.push(row);
+            row += len as usize;
+        }
+        let mut positions = positions.to_vec();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
+            .into_iter()
+            .flat_map(|run| starts[run]..starts[run] + self.lengths[run] as usize)
+            .collect()
+    }
+}
+
+impl<T: Copy + Eq> RunColumn<T> {
+    #[must_use]
+    pub fn new(column: &[T]) -> Self {
+        let mut runs = Self {
+            values: Vec::new(),
+            lengths: Vec::new(),
+        };
+        for &value in column {
+            match (runs.values.last(), runs.lengths.last_mut()) {
+                (Some(&last), Some(len)) if last == value && *len < u32::MAX => *len += 1,
+                _ => {
+                    runs.values.push(value);
+                    runs.lengths.push(1);
+                }
+            }
+        }
+        runs
+    }
+
+    /// The column's rows
+    #[must_use]
+    pub fn expand(&self) -> Vec<T> {
+        self.values
+            .iter()
+            .zip(&self.lengths)
+            .flat_map(|(&value, &len)| std::iter::repeat_n(value, len as usize))
+            .collect()
+    }
+}
+
+impl<T: ColumnCodec> ColumnCodec for RunColumn<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.values.encode(out);
+        self.lengths.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        let values: Vec<T> = ColumnCodec::decode(input)?;
+        let lengths: Vec<u32> = ColumnCodec::decode(input)?;
+        if lengths.len() != values.len() || lengths.contains(&0) {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Invalid runs: {} values, {} lengths",
+                values.len(),
+                lengths.len()
+            )));
+        }
+        Ok(Self { values, lengths })
+    }
+}
+
+/// A column stored as its distinct values plus one index per row
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DedupColumn<T> {
+    /// Distinct values, in order of first appearance
+    pub values: Vec<T>,
+    /// Position in `values` of each row's value
+    pub indices: Vec<u32>,
+}
+
+impl<T: Copy + Eq + Hash> DedupColumn<T> {
+    #[must_use]
+    pub fn new(column: &[T]) -> Self {
+        let mut positions = HashMap::new();
+        let mut values = Vec::new();
+        let indices = column
+            .iter()
+            .map(|&value| {
+                *positions.entry(value).or_insert_with(|| {
+                    values.push(value);
+                    (values.len() - 1) as u32
+                })
+            })
+            .collect();
+        Self { values, indices }
+    }
+
+    /// The column's rows
+    #[must_use]
+    pub fn expand(&self) -> Vec<T> {
+        self.indices
+            .iter()
+            .map(|&i| self.values[i as usize])
+            .collect()
+    }
+
+    /// Rows whose value is at one of `positions` in [`Self::values`]
+    #[must_use]
+    pub fn rows_at(&self, positions: &[usize]) -> Vec<usize> {
+        let mut wanted = vec![false; self.values.len()];
+        for &position in positions {
+            wanted[position] = true;
+        }
+        self.indices
+            .iter()
+            .enumerate()
+            .filter(|&(_, &i)| wanted[i as usize])
+            .map(|(row, _)| row)
+            .collect()
+    }
+
+    /// Bytes per index for `distinct` values
+    const fn index_width(distinct: usize) -> u8 {
+        if distinct <= 1 << 8 {
+            1
+        } else if distinct <= 1 << 16 {
+            2
+        } else {
+            4
+        }
+    }
+}
+
+impl<T: ColumnCodec + Copy + Eq + Hash> ColumnCodec for DedupColumn<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.values.encode(out);
+        let width = Self::index_width(self.values.len());
+        out.push(width);
+        (self.indices.len() as u64).encode(out);
+        for index in &self.indices {
+            out.extend_from_slice(&index.to_le_bytes()[..usize::from(width)]);
+        }
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        let values: Vec<T> = ColumnCodec::decode(input)?;
+        let width = usize::from(u8::decode(input)?);
+        if !matches!(width, 1 | 2 | 4) {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Invalid dedup index width {width}"
+            )));
+        }
+        let len = input.len()?;
+        let indices = (0..len)
+            .map(|_| {
+                let mut index = [0u8; 4];
+                index[..width].copy_from_slice(input.take(width)?);
+                let index = u32::from_le_bytes(index);
+                if index as usize >= values.len() {
+                    return Err(ALICETextError::DecompressionError(format!(
+                        "Dedup index {index} out of {} values",
+                        values.len()
+                    )));
+                }
+                Ok(index)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { values, indices })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_bytes() {
+        let tokens = vec![SkeletonToken::Text("a\n".into()), SkeletonToken::Ref(7)];
+        #[rustfmt::skip]
+        let golden: &[u8] = &[
+            2, 0, 0, 0, 0, 0, 0, 0,             // LEN
+            0, 0, 0, 0,                         // Text
+            2, 0, 0, 0, 0, 0, 0, 0, b'a', b'\n',
+            1, 0, 0, 0,                         // Ref
+            7, 0, 0, 0,
+        ];
+        assert_eq!(encode_column(&tokens), golden);
+        assert_eq!(decode_column::<Vec<SkeletonToken>>(golden).unwrap(), tokens);
+
+        let timestamps = TimestampColumn {
+            base: Some("x".into()),
+            base_ms: None,
+            deltas: vec![-1],
+            raw: Vec::new(),
+            cached_format_idx: Some(CachedFormatType::Tz(3)),
+            last_ms: 258,
+            base_offset_secs: Some(-3600),
+        };
+        #[rustfmt::skip]
+        let golden: &[u8] = &[
+            1, 1, 0, 0, 0, 0, 0, 0, 0, b'x',    // base
+            0,                                  // base_ms
+            1, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            0, 0, 0, 0, 0, 0, 0, 0,             // raw
+            1, 2, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0,
+            2, 1, 0, 0, 0, 0, 0, 0,             // last_ms
+            1, 0xF0, 0xF1, 0xFF, 0xFF,          // base_offset_secs
+        ];
+        assert_eq!(encode_column(&timestamps), golden);
+        let decoded: TimestampColumn = decode_column(golden).unwrap();
+        assert_eq!(encode_column(&decoded), golden);
+
+        let numbers = vec![1.5f64];
+        assert_eq!(
+            encode_column(&numbers),
+            [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xF8, 0x3F]
+        );
+        let map = vec![(2u8, 0x0102_0304u32)];
+        assert_eq!(encode_column(&map), [1, 0, 0, 0, 0, 0, 0, 0, 2, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_matches_legacy_bincode() {
+        let strings = vec!["GET /".to_string(), "日本".to_string()];
+        let provenance = ProvenanceColumn {
+            sources: vec!["a.log".into()],
+            lines: vec![LineOrigin {
+                source: 0,
+                byte_offset: 42,
+                line_number: 2,
+            }],
+        };
+        let segments = vec![RawSegment {
+            ordinal: 3,
+            bytes: vec![0xFF, 0xFE],
+        }];
+        let epochs = NumericTimeColumn {
+            deltas: vec![1_700_000_000, 5],
+            formats: vec![0, 1],
+            ..NumericTimeColumn::default()
+        };
+        assert_eq!(
+            encode_column(&strings),
+            bincode::serialize(&strings).unwrap()
+        );
+        assert_eq!(
+            encode_column(&provenance),
+            bincode::serialize(&provenance).unwrap()
+        );
+        assert_eq!(
+            encode_column(&segments),
+            bincode::serialize(&segments).unwrap()
+        );
+        assert_eq!(encode_column(&epochs), bincode::serialize(&epochs).unwrap());
+        let points = vec![GeoPoint::parse("35.6895, 139.6917").unwrap()];
+        assert_eq!(encode_column(&points), bincode::serialize(&points).unwrap());
+        let mut klog = KlogColumn::default();
+        klog.add("I0115 10:30:45.123456   12345 main.go:42]")
+            .unwrap();
+        assert_eq!(encode_column(&klog), bincode::serialize(&klog).unwrap());
+        let ids = vec![ContainerId::parse(&format!("docker://{}", "ab".repeat(32))).unwrap()];
+        assert_eq!(encode_column(&ids), bincode::serialize(&ids).unwrap());
+        assert_eq!(
+            encode_column(&vec![u128::MAX, 1]),
+            bincode::serialize(&vec![u128::MAX, 1]).unwrap()
+        );
+        assert_eq!(
+            decode_column::<ProvenanceColumn>(&encode_column(&provenance)).unwrap(),
+            provenance
+        );
+    }
+
+    #[test]
+    fn test_header_records_match_legacy_bincode() {
+        let summaries = vec![ColumnSummary {
+            col_type: ColumnType::Numbers,
+            count: 3,
+            null_count: 1,
+            min: -1.5,
+            max: 9.0,
+            p50: 2.0,
+            p90: 8.0,
+            p99: 9.0,
+        }];
+        let config = PatternConfig::strict()
+            .with_kubernetes()
+            .disable(PatternType::Hex)
+            .exclude(PatternType::Number, "^0+$");
+        let schema = vec![PluginSchema {
+            id: "trace".into(),
+            count: 2,
+            stats: vec![("bytes".into(), 12.5)],
+        }];
+        assert_eq!(
+            encode_column(&summaries),
+            bincode::serialize(&summaries).unwrap()
+        );
+        assert_eq!(encode_column(&config), bincode::serialize(&config).unwrap());
+        assert_eq!(encode_column(&schema), bincode::serialize(&schema).unwrap());
+        assert_eq!(
+            decode_column::<Vec<ColumnSummary>>(&encode_column(&summaries)).unwrap(),
+            summaries
+        );
+        assert_eq!(
+            decode_column::<PatternConfig>(&encode_column(&config)).unwrap(),
+            config
+        );
+        assert_eq!(
+            decode_column::<Vec<PluginSchema>>(&encode_column(&schema)).unwrap(),
+            schema
+        );
+
+        // Unknown tags are refused, `Custom` itself is not
+        assert!(decode_column::<PatternType>(&99u32.to_le_bytes()).is_err());
+        assert_eq!(
+            decode_column::<PatternType>(&12u32.to_le_bytes()).unwrap(),
+            PatternType::Custom
+        );
+        assert!(decode_column::<ColumnType>(&200u32.to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed() {
+        let strings = encode_column(&vec!["abc".to_string()]);
+        assert!(decode_column::<Vec<String>>(&strings[..strings.len() - 1]).is_err());
+        let mut trailing = strings.clone();
+        trailing.push(0);
+        assert!(decode_column::<Vec<String>>(&trailing).is_err());
+        let mut invalid = strings;
+        invalid[16] = 0xFF;
+        assert!(decode_column::<Vec<String>>(&invalid).is_err());
+        // Huge length prefix must not allocate
+        assert!(decode_column::<Vec<u8>>(&[0xFF; 8]).is_err());
+        assert!(decode_column::<Option<u8>>(&[2, 0]).is_err());
+        assert!(
+            decode_column::<Vec<SkeletonToken>>(&[1, 0, 0, 0, 0, 0, 0, 0, 9, 0, 0, 0]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_column_slice_in_place() {
+        let addrs: Vec<u32> = vec![1, 0x0A00_0001, u32::MAX, 7];
+        let encoded = encode_column(&addrs);
+
+        // Aligned block: borrowed as is
+        let mut words = vec![0u64; encoded.len() / 8];
+        bytemuck::cast_slice_mut(&mut words).copy_from_slice(&encoded);
+        let block: &[u8] = bytemuck::cast_slice(&words);
+        let view = column_slice::<u32>(block).unwrap();
+        assert_eq!(*view, addrs[..]);
+        if cfg!(target_endian = "little") {
+            assert!(matches!(view, Cow::Borrowed(_)));
+        }
+
+        // Misaligned block: decoded
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&encoded);
+        let view = column_slice::<u32>(&shifted[1..]).unwrap();
+        assert_eq!(*view, addrs[..]);
+
+        assert!(column_slice::<u32>(&encoded[..encoded.len() - 1]).is_err());
+        assert!(column_slice::<u16>(&encoded).is_err());
+        assert!(column_slice::<u32>(&[0xFF; 8]).is_err());
+        assert!(column_slice::<u32>(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn test_dedup_column() {
+        let addrs = [7u32, 9, 7, 7];
+        let column = DedupColumn::new(&addrs);
+        assert_eq!(column.values, [7, 9]);
+        assert_eq!(column.indices, [0, 1, 0, 0]);
+        assert_eq!(column.expand(), addrs);
+        assert_eq!(column.rows_at(&[0]), [0, 2, 3]);
+        #[rustfmt::skip]
+        let golden: &[u8] = &[
+            2, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 9, 0, 0, 0,     // values
+            1,                                                  // width
+            4, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,                 // indices
+        ];
+        assert_eq!(encode_column(&column), golden);
+        assert_eq!(decode_column::<DedupColumn<u32>>(golden).unwrap(), column);
+
+        let mut bad = golden.to_vec();
+        bad[16] = 3;
+        assert!(decode_column::<DedupColumn<u32>>(&bad).is_err());
+        let mut bad = golden.to_vec();
+        bad[25] = 2;
+        assert!(decode_column::<DedupColumn<u32>>(&bad).is_err());
+
+        let wide: Vec<u128> = (0..300).collect();
+        let column = DedupColumn::new(&wide);
+        assert_eq!(encode_column(&column)[300 * 16 + 8], 2);
+    }
+
+    #[test]
+    fn test_encodings_round_trip() {
+        let pids = [7u32, 7, 7, 9, 9, 3, u32::MAX, 0];
+        for encoding in ColumnEncoding::ALL {
+            assert_eq!(ColumnEncoding::from_u8(encoding as u8), Some(encoding));
+            let bytes = encode_values(&pids, encoding);
+            assert_eq!(decode_values::<u32>(&bytes, encoding, 8).unwrap(), pids);
+            if encoding != ColumnEncoding::Plain {
+                assert!(decode_values::<u32>(&bytes, encoding, 9).is_err());
+            }
+        }
+        assert_eq!(ColumnEncoding::from_u8(5), None);
+
+        let runs = RunColumn::new(&pids);
+        assert_eq!(runs.values, [7, 9, 3, u32::MAX, 0]);
+        assert_eq!(runs.lengths, [3, 2, 1, 1, 1]);
+        assert_eq!(runs.rows_at(&[1, 0]), [0, 1, 2, 3, 4]);
+        // Runs covering more rows than the directory lists are rejected unexpanded
+        let huge = RunColumn {
+            values: vec![1u8],
+            lengths: vec![u32::MAX],
+        };
+        assert!(decode_runs::<u8>(&encode_column(&huge), 1).is_err());
+        let zero = [
+            1, 0, 0, 0, 0, 0, 0, 0, 5, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert!(decode_column::<RunColumn<u8>>(&zero).is_err());
+
+        let deltas = encode_values(&[5u8, 3, 250], ColumnEncoding::Delta);
+        assert_eq!(deltas[8..], [5, 254, 247]);
+        let planes = encode_values(&[0x0102u16, 0x0304], ColumnEncoding::Shuffle);
+        assert_eq!(planes[8..], [2, 4, 1, 3]);
+        assert!(decode_values::<u16>(&planes[..11], ColumnEncoding::Shuffle, 2).is_err());
+    }
+}
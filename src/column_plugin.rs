@@ -0,0 +1,389 @@
+//! Column plugins — domain-specific value columns without forking
+//!
+//! A [`ColumnPlugin`] finds its own values in the text (MAC addresses, geo
+//! coordinates, ticket ids...) and chooses how a column of them is stored.
+//! Register plugins with [`ColumnarEncoder::with_plugin`] or
+//! [`FormatV3Writer::with_plugin`]; their values are claimed before the
+//! built-in patterns run, in registration order.
+//!
+//! In format v3 all plugin columns share one [`ColumnType::Plugins`] column
+//! (a Bincode list of each plugin's encoded bytes). The critical
+//! [`HeaderExtension::PLUGIN_SCHEMA`] record lists, per plugin, its
+//! [`id`](ColumnPlugin::id), value count and compress-time
+//! [`stats`](ColumnPlugin::stats). Decoding needs the same plugins
+//! ([`FormatV3Writer::decompress_with_plugins`]); readers without them
+//! fail instead of dropping values.
+//!
+//! Format v2 has no plugin column; `TunedCompressor` does not take plugins.
+//!
+//! [`ColumnarEncoder::with_plugin`]: crate::ColumnarEncoder::with_plugin
+//! [`FormatV3Writer::with_plugin`]: crate::FormatV3Writer::with_plugin
+//! [`FormatV3Writer::decompress_with_plugins`]: crate::FormatV3Writer::decompress_with_plugins
+//! [`ColumnType::Plugins`]: crate::ColumnType::Plugins
+//! [`HeaderExtension::PLUGIN_SCHEMA`]: crate::HeaderExtension::PLUGIN_SCHEMA
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::coverage::Coverage;
+use crate::{ALICETextError, Result};
+
+/// Most plugins one encoder can hold (placeholder tags `0x80..=0xBF`)
+pub const MAX_PLUGINS: usize = 64;
+
+/// Placeholder map tag of the first plugin column
+pub(crate) const PLUGIN_TAG_BASE: u8 = 0x80;
+
+/// A custom value column
+pub trait ColumnPlugin: Send + Sync {
+    /// Stable identifier recorded in archives (e.g. `"mac-address"`)
+    fn id(&self) -> &str;
+
+    /// Byte ranges of values in `text`, ascending
+    ///
+    /// Ranges that are empty, overlap an earlier claim, cross a newline or
+    /// do not fall on character boundaries are ignored.
+    fn detect(&self, text: &str) -> Vec<Range<usize>>;
+
+    /// Encode this plugin's values, in text order
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value cannot be represented.
+    fn encode(&self, values: &[String]) -> Result<Vec<u8>>;
+
+    /// Inverse of [`Self::encode`]; must return exactly `count` values
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `data` is corrupt.
+    fn decode(&self, data: &[u8], count: usize) -> Result<Vec<String>>;
+
+    /// Named compress-time statistics (min/max, distinct count...) stored in
+    /// the schema record; none by default
+    fn stats(&self, _values: &[String]) -> Vec<(String, f64)> {
+        Vec::new()
+    }
+}
+
+/// Values one plugin took from the text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginColumn {
+    pub id: String,
+    pub values: Vec<String>,
+}
+
+/// Schema record entry for one plugin column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginSchema {
+    pub id: String,
+    pub count: u64,
+    pub stats: Vec<(String, f64)>,
+}
+
+/// Check a registration against `plugins` (id clash, capacity)
+pub(crate) fn check_registration(
+    plugins: &[Arc<dyn ColumnPlugin>],
+    plugin: &dyn ColumnPlugin,
+) -> Result<()> {
+    if plugins.len() >= MAX_PLUGINS {
+        return Err(ALICETextError::LimitExceeded {
+            field: "column plugins",
+            value: plugins.len() as u64 + 1,
+            limit: MAX_PLUGINS as u64,
+        });
+    }
+    if plugins.iter().any(|p| p.id() == plugin.id()) {
+        return Err(ALICETextError::EncodingError(format!(
+            "Column plugin {:?} registered twice",
+            plugin.id()
+        )));
+    }
+    Ok(())
+}
+
+/// Claim plugin values in `text[from..to]`: `(start, end, slot)` in claim order
+pub(crate) fn detect(
+    plugins: &[Arc<dyn ColumnPlugin>],
+    text: &str,
+    (from, to): (usize, usize),
+    coverage: &mut Coverage,
+) -> Vec<(usize, usize, usize)> {
+    let segment = &text[from..to];
+    let mut found = Vec::new();
+    for (slot, plugin) in plugins.iter().enumerate() {
+        for range in plugin.detect(segment) {
+            let valid = range.start < range.end
+                && segment.is_char_boundary(range.start)
+                && segment.is_char_boundary(range.end)
+                && range.end <= segment.len()
+                && !segment[range.clone()].contains('\n');
+            if valid && coverage.claim(from + range.start, from + range.end) {
+                found.push((from + range.start, from + range.end, slot));
+            }
+        }
+    }
+    found
+}
+
+/// Encode every plugin column: (schema, encoded bytes per column)
+pub(crate) fn encode_columns(
+    plugins: &[Arc<dyn ColumnPlugin>],
+    columns: &[PluginColumn],
+) -> Result<(Vec<PluginSchema>, Vec<Vec<u8>>)> {
+    let mut schema = Vec::with_capacity(columns.len());
+    let mut encoded = Vec::with_capacity(columns.len());
+    for column in columns {
+        let plugin = find(plugins, &column.id)?;
+        schema.push(PluginSchema {
+            id: column.id.clone(),
+            count: column.values.len() as u64,
+            stats: plugin.stats(&column.values),
+        });
+        encoded.push(plugin.encode(&column.values)?);
+    }
+    Ok((schema, encoded))
+}
+
+/// Inverse of [`encode_columns`]
+pub(crate) fn decode_columns(
+    plugins: &[Arc<dyn ColumnPlugin>],
+    schema: &[PluginSchema],
+    encoded: &[Vec<u8>],
+) -> Result<Vec<PluginColumn>> {
+    if schema.len() != encoded.len() {
+        return Err(ALICETextError::DecompressionError(format!(
+            "Plugin schema lists {} columns, archive has {}",
+            schema.len(),
+            encoded.len()
+        )));
+    }
+    schema
+        .iter()
+        .zip(encoded)
+        .map(|(entry, data)| {
+            let values = find(plugins, &entry.id)?.decode(data, entry.count as usize)?;
+            if values.len() as u64 != entry.count {
+                return Err(ALICETextError::DecompressionError(format!(
+                    "Column plugin {:?} decoded {} values, expected {}",
+                    entry.id,
+                    values.len(),
+                    entry.count
+                )));
+            }
+            Ok(PluginColumn {
+                id: entry.id.clone(),
+                values,
+            })
+        })
+        .collect()
+}
+
+fn find<'a>(plugins: &'a [Arc<dyn ColumnPlugin>], id: &str) -> Result<&'a dyn ColumnPlugin> {
+    plugins
+        .iter()
+        .find(|p| p.id() == id)
+        .map(AsRef::as_ref)
+        .ok_or_else(|| {
+            ALICETextError::DecompressionError(format!(
+                "Archive needs column plugin {id:?}, which is not registered"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_v3::{DecodeOptions, FormatV3Editor, FormatV3Metadata, FormatV3Writer};
+    use crate::{
+        delete_rows, delete_rows_with_plugins, embed_views, embed_views_with_plugins,
+        merge_archives_with_plugins, split_archive, split_archive_with_plugins, ColumnarEncoder,
+        CompressionLevel, SavedView,
+    };
+
+    /// Lower-case MAC addresses, six bytes each
+    struct MacPlugin;
+
+    fn format_mac(bytes: &[u8]) -> String {
+        bytes
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    impl ColumnPlugin for MacPlugin {
+        fn id(&self) -> &str {
+            "mac-address"
+        }
+
+        fn detect(&self, text: &str) -> Vec<Range<usize>> {
+            let bytes = text.as_bytes();
+            let mut found = Vec::new();
+            let mut i = 0;
+            while i + 17 <= bytes.len() {
+                let is_mac = (0..17).all(|k| match k % 3 {
+                    2 => bytes[i + k] == b':',
+                    _ => matches!(bytes[i + k], b'0'..=b'9' | b'a'..=b'f'),
+                });
+                if is_mac {
+                    found.push(i..i + 17);
+                    i += 17;
+                } else {
+                    i += 1;
+                }
+            }
+            found
+        }
+
+        fn encode(&self, values: &[String]) -> Result<Vec<u8>> {
+            let mut out = Vec::with_capacity(values.len() * 6);
+            for value in values {
+                let bytes: Vec<u8> = value
+                    .split(':')
+                    .filter_map(|b| u8::from_str_radix(b, 16).ok())
+                    .collect();
+                if format_mac(&bytes) != *value {
+                    return Err(ALICETextError::EncodingError(format!("Not a MAC: {value}")));
+                }
+                out.extend_from_slice(&bytes);
+            }
+            Ok(out)
+        }
+
+        fn decode(&self, data: &[u8], count: usize) -> Result<Vec<String>> {
+            if data.len() != count * 6 {
+                return Err(ALICETextError::DecompressionError(
+                    "MAC column size".to_string(),
+                ));
+            }
+            Ok(data.chunks(6).map(format_mac).collect())
+        }
+
+        fn stats(&self, values: &[String]) -> Vec<(String, f64)> {
+            let mut distinct = values.to_vec();
+            distinct.sort_unstable();
+            distinct.dedup();
+            vec![("distinct".to_string(), distinct.len() as f64)]
+        }
+    }
+
+    fn sample() -> String {
+        (0..50)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:30:{:02} INFO dhcp lease 10.0.0.{i} to 00:1a:2b:3c:4d:{:02x}\n",
+                    i % 60,
+                    i % 8
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plugin_column_roundtrip() {
+        let text = sample();
+        let payload = ColumnarEncoder::new()
+            .with_plugin(MacPlugin)
+            .unwrap()
+            .encode(&text);
+        assert_eq!(payload.plugin_columns[0].values.len(), 50);
+        assert_eq!(payload.restore(), text);
+
+        let writer = FormatV3Writer::new(CompressionLevel::Balanced)
+            .with_plugin(MacPlugin)
+            .unwrap();
+        let archive = writer.compress(&text).unwrap();
+        let plugins: Vec<Arc<dyn ColumnPlugin>> = vec![Arc::new(MacPlugin)];
+        assert_eq!(
+            FormatV3Writer::decompress_with_plugins(&archive, &plugins).unwrap(),
+            text
+        );
+
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&archive)).unwrap();
+        let schema = metadata.plugin_schema().unwrap();
+        assert_eq!(schema.len(), 1);
+        assert_eq!(schema[0].id, "mac-address");
+        assert_eq!(schema[0].count, 50);
+        assert_eq!(schema[0].stats, [("distinct".to_string(), 8.0)]);
+
+        // Without the plugin the archive is refused, not decoded lossily
+        assert!(FormatV3Writer::decompress(&archive).is_err());
+        assert!(FormatV3Writer::decompress_with(&archive, DecodeOptions::lenient()).is_err());
+    }
+
+    #[test]
+    fn test_plugin_archive_rewrites() {
+        let text = sample();
+        let archive = FormatV3Writer::new(CompressionLevel::Balanced)
+            .with_plugin(MacPlugin)
+            .unwrap()
+            .compress(&text)
+            .unwrap();
+        let plugins: Vec<Arc<dyn ColumnPlugin>> = vec![Arc::new(MacPlugin)];
+        let decode = |data: &[u8]| FormatV3Writer::decompress_with_plugins(data, &plugins).unwrap();
+
+        #[cfg(feature = "query")]
+        {
+            let engine = crate::QueryEngine::from_slice(&archive).unwrap();
+            assert!(engine.lines(&[1]).is_err());
+            let engine = engine.with_plugins(&plugins);
+            assert_eq!(engine.decompress_all().unwrap(), text);
+            assert_eq!(
+                engine.lines(&[1]).unwrap(),
+                ["2024-01-15 10:30:01 INFO dhcp lease 10.0.0.1 to 00:1a:2b:3c:4d:01"]
+            );
+        }
+
+        // Re-encoded archives keep their plugin column
+        let (pruned, _) =
+            delete_rows_with_plugins(&archive, &plugins, |row| row.line_number() < 10).unwrap();
+        assert_eq!(
+            decode(&pruned),
+            text.split_inclusive('\n').skip(10).collect::<String>()
+        );
+        assert!(FormatV3Writer::decompress(&pruned).is_err());
+        assert!(delete_rows(&archive, |_| false).is_err());
+
+        let parts = split_archive_with_plugins(&archive, 20, &plugins).unwrap();
+        let refs: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        assert_eq!(
+            decode(&merge_archives_with_plugins(&refs, &plugins).unwrap()),
+            text
+        );
+        assert!(split_archive(&archive, 20).is_err());
+
+        let compacted = FormatV3Editor::new(archive.clone())
+            .unwrap()
+            .with_plugins(&plugins)
+            .compact()
+            .unwrap();
+        assert_eq!(decode(&compacted), text);
+
+        let viewed =
+            embed_views_with_plugins(&archive, &[SavedView::new("everything")], &plugins).unwrap();
+        assert_eq!(decode(&viewed), text);
+        assert!(embed_views(&archive, &[]).is_err());
+    }
+
+    #[test]
+    fn test_plugin_registration() {
+        let encoder = ColumnarEncoder::new().with_plugin(MacPlugin).unwrap();
+        assert!(encoder.with_plugin(MacPlugin).is_err());
+
+        // No plugin values: a plain archive any reader can decode
+        let writer = FormatV3Writer::new(CompressionLevel::Balanced)
+            .with_plugin(MacPlugin)
+            .unwrap();
+        let archive = writer.compress("no hardware addresses here\n").unwrap();
+        assert_eq!(
+            FormatV3Writer::decompress(&archive).unwrap(),
+            "no hardware addresses here\n"
+        );
+    }
+}
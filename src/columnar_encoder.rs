@@ -5,11 +5,21 @@
 //! - Type-specific encodings (IP as u32, `LogLevel` as u8, etc.)
 //! - Delta encoding for timestamps (massive compression gains)
 
-use crate::tuned_pattern_learner::{PatternType, TunedPatternLearner};
+use crate::column_plugin::{self, ColumnPlugin, PluginColumn, PLUGIN_TAG_BASE};
+use crate::coverage::Coverage;
+use crate::format_v3::ColumnType;
+use crate::kubernetes::{ContainerId, Dictionary, KubernetesColumns};
+use crate::source_profile::RawSegment;
+use crate::tuned_pattern_learner::{
+    build_skeleton, PatternConfig, PatternLimits, PatternType, TunedPatternLearner,
+};
+use crate::ALICETextError;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt::{self, Write as _};
 use std::net::Ipv4Addr;
+use std::sync::Arc;
 
 /// Log level encoded as u8
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -164,8 +174,15 @@ impl TimestampColumn {
 
     /// Add a timestamp, using delta encoding if possible
     /// Returns (`is_delta`, index) where index is into deltas or raw array
+    ///
+    /// Only timestamps that [`Self::get_delta`] restores byte for byte are
+    /// delta-encoded (fractional seconds, for one, are not).
     pub fn add(&mut self, text: &str) -> (bool, usize) {
-        if let Some(ts_ms) = self.parse_timestamp(text) {
+        let base_offset_secs = self.base_offset_secs;
+        if let Some(ts_ms) = self
+            .parse_timestamp(text)
+            .filter(|&ts_ms| self.restores_exactly(text, ts_ms))
+        {
             let delta_idx = self.deltas.len();
             if self.base_ms.is_none() {
                 // First timestamp: store as base
@@ -181,7 +198,8 @@ impl TimestampColumn {
             }
             (true, delta_idx)
         } else {
-            // Can't parse: store as raw string
+            // Can't parse (or would not restore): store as raw string
+            self.base_offset_secs = base_offset_secs;
             let raw_idx = self.raw.len();
             self.raw.push(text.to_string());
             (false, raw_idx)
@@ -205,10 +223,18 @@ impl TimestampColumn {
     /// Get delta-encoded timestamp by index (O(1) with precomputed prefix sums)
     #[must_use]
     pub fn get_delta(&self, delta_idx: usize, prefix_sums: &[i64]) -> Option<String> {
-        use chrono::FixedOffset;
+        self.format_ms(self.base.as_ref()?, *prefix_sums.get(delta_idx)?)
+    }
 
-        let base_str = self.base.as_ref()?;
-        let total_ms = *prefix_sums.get(delta_idx)?;
+    /// Whether `text`, parsed to `ts_ms`, comes back unchanged from [`Self::get_delta`]
+    fn restores_exactly(&self, text: &str, ts_ms: i64) -> bool {
+        let base_str = self.base.as_deref().unwrap_or(text);
+        self.format_ms(base_str, ts_ms).as_deref() == Some(text)
+    }
+
+    /// Format milliseconds in the style of the base timestamp
+    fn format_ms(&self, base_str: &str, total_ms: i64) -> Option<String> {
+        use chrono::FixedOffset;
 
         // Reconstruct DateTime from milliseconds
         let dt_utc = chrono::DateTime::from_timestamp_millis(total_ms)?;
@@ -267,8 +293,451 @@ impl TimestampColumn {
     }
 }
 
+/// Format flag: epoch value was written in milliseconds (13 integer digits)
+const EPOCH_MILLIS: u8 = 0x10;
+
+/// Numeric timestamps (Unix epoch or uptime seconds) with delta encoding
+///
+/// Values are fixed-point integers (µs for epochs, ns for uptimes) stored
+/// as deltas; each keeps a format byte (unit, fraction digits, padding) so
+/// the exact original text is reconstructed:
+/// - Before: "1705312245.120" (14 bytes) × N
+/// - After: [1705312245120000, 1000, 250000, ...] + one format byte each
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NumericTimeColumn {
+    /// `Delta[0]` is the first value, `Delta[n] = Value[n] - Value[n-1]`
+    pub deltas: Vec<i64>,
+    /// Per-value representation (see `parse_epoch` / `parse_uptime`)
+    pub formats: Vec<u8>,
+    /// Last value (for O(1) delta calculation while encoding)
+    #[serde(skip)]
+    pub(crate) last: i64,
+}
+
+impl NumericTimeColumn {
+    fn push(&mut self, (value, format): (i64, u8)) -> usize {
+        let idx = self.deltas.len();
+        self.deltas.push(value - self.last);
+        self.formats.push(format);
+        self.last = value;
+        idx
+    }
+
+    /// Add Unix epoch seconds/millis; `None` if the text can't be reproduced exactly
+    pub fn add_epoch(&mut self, text: &str) -> Option<usize> {
+        parse_epoch(text).map(|v| self.push(v))
+    }
+
+    /// Add a bracketed uptime (`[ 1234.5678]`); `None` if the text can't be reproduced exactly
+    pub fn add_uptime(&mut self, text: &str) -> Option<usize> {
+        parse_uptime(text).map(|v| self.push(v))
+    }
+
+    /// Precompute absolute values (prefix sums of the deltas)
+    #[must_use]
+    pub fn prepare_for_read(&self) -> Vec<i64> {
+        self.deltas
+            .iter()
+            .scan(0i64, |sum, delta| {
+                *sum += delta;
+                Some(*sum)
+            })
+            .collect()
+    }
+
+    /// Reconstruct an epoch value by index
+    #[must_use]
+    pub fn get_epoch(&self, idx: usize, prefix_sums: &[i64]) -> Option<String> {
+        Some(format_epoch(
+            *prefix_sums.get(idx)?,
+            *self.formats.get(idx)?,
+        ))
+    }
+
+    /// Reconstruct an uptime value by index
+    #[must_use]
+    pub fn get_uptime(&self, idx: usize, prefix_sums: &[i64]) -> Option<String> {
+        Some(format_uptime(
+            *prefix_sums.get(idx)?,
+            *self.formats.get(idx)?,
+        ))
+    }
+
+    /// Number of values stored
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Check if empty
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+}
+
+/// Latencies and durations (`123ms`, `4.5s`, `2m30s`) as nanoseconds
+///
+/// Each value keeps a format word so the exact text is reconstructed: one
+/// byte per component (up to four, low byte first), `(unit + 1) << 4 |
+/// fraction digits`, with units `ns us µs ms s m h`. Only the last
+/// component may have a fraction.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DurationColumn {
+    pub nanos: Vec<u64>,
+    pub formats: Vec<u32>,
+}
+
+impl DurationColumn {
+    /// Add a duration; `None` if the text can't be reproduced exactly
+    pub fn add(&mut self, text: &str) -> Option<usize> {
+        let (nanos, format) = parse_duration(text)?;
+        self.nanos.push(nanos);
+        self.formats.push(format);
+        Some(self.nanos.len() - 1)
+    }
+
+    /// Reconstruct a duration by index
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<String> {
+        format_duration(*self.nanos.get(idx)?, *self.formats.get(idx)?)
+    }
+
+    /// Number of values stored
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nanos.len()
+    }
+
+    /// Check if empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nanos.is_empty()
+    }
+}
+
+/// Duration units (index = unit code in a format word) and their length in ns
+const DURATION_UNITS: [(&str, u64); 7] = [
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60_000_000_000),
+    ("h", 3_600_000_000_000),
+];
+
+/// Parse a duration (`1h2m3.5s`) to ns plus its format word
+pub(crate) fn parse_duration(s: &str) -> Option<(u64, u32)> {
+    let mut rest = s;
+    let mut nanos = 0u64;
+    let mut format = 0u32;
+    for component in 0..4 {
+        let len = rest
+            .bytes()
+            .take_while(|b| b.is_ascii_digit() || *b == b'.')
+            .count();
+        let (number, tail) = rest.split_at(len);
+        // Longest unit first (`ms` before `m`)
+        let (unit, &(name, scale)) = DURATION_UNITS
+            .iter()
+            .enumerate()
+            .filter(|(_, (name, _))| tail.starts_with(name))
+            .max_by_key(|(_, (name, _))| name.len())?;
+        let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+        if int.is_empty() || frac.len() > 9 || (frac.is_empty() && number.ends_with('.')) {
+            return None;
+        }
+        let frac_ns = if frac.is_empty() {
+            0
+        } else {
+            let pow = 10u128.pow(frac.len() as u32);
+            let scaled = frac.parse::<u128>().ok()? * u128::from(scale);
+            if scaled % pow != 0 {
+                // Finer than a nanosecond
+                return None;
+            }
+            u64::try_from(scaled / pow).ok()?
+        };
+        let value = int.parse::<u64>().ok()?.checked_mul(scale)?;
+        nanos = nanos.checked_add(value)?.checked_add(frac_ns)?;
+        format |= ((unit as u32 + 1) << 4 | frac.len() as u32) << (8 * component);
+        rest = &tail[name.len()..];
+        if rest.is_empty() {
+            return (format_duration(nanos, format)? == s).then_some((nanos, format));
+        }
+    }
+    None
+}
+
+/// Format ns back to the original duration representation
+pub(crate) fn format_duration(nanos: u64, format: u32) -> Option<String> {
+    let components: Vec<u32> = (0..4)
+        .map(|i| (format >> (8 * i)) & 0xFF)
+        .take_while(|&b| b != 0)
+        .collect();
+    let mut out = String::new();
+    let mut rest = nanos;
+    for (i, &component) in components.iter().enumerate() {
+        let &(name, scale) = DURATION_UNITS.get((component >> 4) as usize - 1)?;
+        let digits = component & 0x0F;
+        let int = rest / scale;
+        rest -= int * scale;
+        if i + 1 == components.len() && digits > 0 {
+            let frac = u128::from(rest) * 10u128.pow(digits) / u128::from(scale);
+            let _ = write!(out, "{int}.{frac:0width$}{name}", width = digits as usize);
+        } else {
+            let _ = write!(out, "{int}{name}");
+        }
+    }
+    Some(out)
+}
+
+/// Sizes (`300KB`, `1.5GiB`, `512B`) normalized to bytes
+///
+/// Each value keeps a format byte, `(unit + 1) << 4 | fraction digits`, with
+/// units `B kB KB MB GB TB PB KiB MiB GiB TiB PiB` (`K`..`P` decimal, `Ki`..`Pi`
+/// binary). Fractions must come out to a whole number of bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteSizeColumn {
+    pub bytes: Vec<u64>,
+    pub formats: Vec<u8>,
+}
+
+impl ByteSizeColumn {
+    /// Add a size; `None` if the text can't be reproduced exactly
+    pub fn add(&mut self, text: &str) -> Option<usize> {
+        let (bytes, format) = parse_byte_size(text)?;
+        self.bytes.push(bytes);
+        self.formats.push(format);
+        Some(self.bytes.len() - 1)
+    }
+
+    /// Reconstruct a size by index
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<String> {
+        format_byte_size(*self.bytes.get(idx)?, *self.formats.get(idx)?)
+    }
+
+    /// Number of values stored
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Check if empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Size units (index = unit code in a format byte) and their length in bytes
+const BYTE_SIZE_UNITS: [(&str, u64); 12] = [
+    ("B", 1),
+    ("kB", 1_000),
+    ("KB", 1_000),
+    ("MB", 1_000_000),
+    ("GB", 1_000_000_000),
+    ("TB", 1_000_000_000_000),
+    ("PB", 1_000_000_000_000_000),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+    ("PiB", 1 << 50),
+];
+
+/// Parse a size (`1.5GiB`) to bytes plus its format byte
+pub(crate) fn parse_byte_size(s: &str) -> Option<(u64, u8)> {
+    let len = s
+        .bytes()
+        .take_while(|b| b.is_ascii_digit() || *b == b'.')
+        .count();
+    let (number, name) = s.split_at(len);
+    let (unit, &(_, scale)) = BYTE_SIZE_UNITS
+        .iter()
+        .enumerate()
+        .find(|(_, (unit, _))| *unit == name)?;
+    let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+    if int.is_empty() || frac.len() > 9 || (frac.is_empty() && number.ends_with('.')) {
+        return None;
+    }
+    let frac_bytes = if frac.is_empty() {
+        0
+    } else {
+        let pow = 10u128.pow(frac.len() as u32);
+        let scaled = frac.parse::<u128>().ok()? * u128::from(scale);
+        if scaled % pow != 0 {
+            // Not a whole number of bytes
+            return None;
+        }
+        u64::try_from(scaled / pow).ok()?
+    };
+    let bytes = int
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(scale)?
+        .checked_add(frac_bytes)?;
+    let format = (unit as u8 + 1) << 4 | frac.len() as u8;
+    (format_byte_size(bytes, format)? == s).then_some((bytes, format))
+}
+
+/// Format bytes back to the original size representation
+pub(crate) fn format_byte_size(bytes: u64, format: u8) -> Option<String> {
+    let &(name, scale) = BYTE_SIZE_UNITS.get(usize::from(format >> 4).checked_sub(1)?)?;
+    let digits = u32::from(format & 0x0F);
+    let int = bytes / scale;
+    if digits == 0 {
+        return (int * scale == bytes).then(|| format!("{int}{name}"));
+    }
+    let frac = u128::from(bytes - int * scale) * 10u128.pow(digits) / u128::from(scale);
+    Some(format!(
+        "{int}.{frac:0width$}{name}",
+        width = digits as usize
+    ))
+}
+
+/// Hostnames as paths in a label suffix tree
+///
+/// `web-01.prod.example.com` and `web-02.prod.example.com` share the
+/// `com` → `example` → `prod` nodes; each host is one node id. Labels are
+/// stored once and keep their case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostColumn {
+    pub labels: Dictionary,
+    /// Tree nodes as (parent node + 1, or 0 under the root; label id)
+    pub nodes: Vec<(u32, u32)>,
+    /// Node of each host, in text order
+    pub hosts: Vec<u32>,
+    #[serde(skip)]
+    node_ids: HashMap<(u32, u32), u32>,
+}
+
+impl HostColumn {
+    /// Column over decoded parts
+    #[must_use]
+    pub fn from_parts(labels: Dictionary, nodes: Vec<(u32, u32)>, hosts: Vec<u32>) -> Self {
+        Self {
+            labels,
+            nodes,
+            hosts,
+            node_ids: HashMap::new(),
+        }
+    }
+
+    /// Add a dotted hostname
+    pub fn add(&mut self, text: &str) -> Option<usize> {
+        if text.split('.').any(str::is_empty) {
+            return None;
+        }
+        if self.node_ids.len() != self.nodes.len() {
+            self.node_ids = (0..).zip(&self.nodes).map(|(id, &n)| (n, id)).collect();
+        }
+        let mut parent = 0;
+        for label in text.rsplit('.') {
+            let key = (parent, self.labels.intern(label));
+            let id = match self.node_ids.get(&key) {
+                Some(&id) => id,
+                None => {
+                    let id = self.nodes.len() as u32;
+                    self.nodes.push(key);
+                    self.node_ids.insert(key, id);
+                    id
+                }
+            };
+            parent = id + 1;
+        }
+        self.hosts.push(parent - 1);
+        Some(self.hosts.len() - 1)
+    }
+
+    /// Reconstruct a hostname by index
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<String> {
+        let mut node = *self.hosts.get(idx)? + 1;
+        let mut labels = Vec::new();
+        // A well-formed tree has no path longer than its node count
+        while node != 0 && labels.len() <= self.nodes.len() {
+            let &(parent, label) = self.nodes.get(node as usize - 1)?;
+            labels.push(self.labels.get(label)?);
+            node = parent;
+        }
+        (node == 0).then(|| labels.join("."))
+    }
+
+    /// Number of values stored
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.hosts.len()
+    }
+
+    /// Check if empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+}
+
+/// Latitude/longitude pair as two `f32` plus its text format
+///
+/// Only pairs whose `f32` values print back to the exact input are stored
+/// this way (about five decimals near ±180°); others go to `others`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f32,
+    pub lon: f32,
+    /// Bits 0-2: latitude decimals, bits 3-5: longitude decimals,
+    /// bit 6: space after the comma
+    pub format: u8,
+}
+
+impl GeoPoint {
+    /// Parse `lat,lon` or `lat, lon`; `None` unless it restores exactly
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let (lat, rest) = s.split_once(',')?;
+        let (space, lon) = rest
+            .strip_prefix(' ')
+            .map_or((false, rest), |lon| (true, lon));
+        let decimals = |v: &str| {
+            v.split_once('.')
+                .map(|(_, d)| d.len())
+                .filter(|&d| d <= 7)
+                .map(|d| d as u8)
+        };
+        let point = Self {
+            lat: lat.parse().ok()?,
+            lon: lon.parse().ok()?,
+            format: decimals(lat)? | decimals(lon)? << 3 | u8::from(space) << 6,
+        };
+        (point.to_string() == s).then_some(point)
+    }
+}
+
+impl fmt::Display for GeoPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lat_decimals = usize::from(self.format & 0x07);
+        let lon_decimals = usize::from(self.format >> 3 & 0x07);
+        let space = if self.format & 0x40 != 0 { " " } else { "" };
+        write!(
+            f,
+            "{:.lat_decimals$},{space}{:.lon_decimals$}",
+            self.lat, self.lon
+        )
+    }
+}
+
+/// Precomputed absolute values of the delta-encoded columns
+pub(crate) struct PrefixSums {
+    timestamps: Vec<i64>,
+    epochs: Vec<i64>,
+    uptimes: Vec<i64>,
+    cri_prefixes: Vec<i64>,
+}
+
 /// Skeleton token for binary representation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SkeletonToken {
     /// Literal text segment
     Text(String),
@@ -276,6 +745,85 @@ pub enum SkeletonToken {
     Ref(u32),
 }
 
+/// Segment of one skeleton line
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum LinePiece<'a> {
+    Text(&'a str),
+    Ref(u32),
+}
+
+/// How [`ColumnarPayload::push_match`] stored a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fallback {
+    /// In its typed column
+    None,
+    /// In its typed column, with the spelling it would not restore recorded in
+    /// [`ColumnarPayload::spellings`] (case, zero padding, long IPv6 form, ...)
+    Spelling,
+    /// As raw text: a valid value the typed column would not restore byte for byte
+    Raw,
+    /// As raw text: the matched text does not parse as its type
+    Unparseable,
+}
+
+/// Check that decoded [`ColumnarPayload::spellings`] are in ascending
+/// placeholder order (lookups binary-search them)
+pub(crate) fn check_spellings(spellings: Vec<(u32, String)>) -> crate::Result<Vec<(u32, String)>> {
+    if spellings.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+        return Err(ALICETextError::DecompressionError(
+            "Spellings are not in ascending placeholder order".to_string(),
+        ));
+    }
+    Ok(spellings)
+}
+
+/// `log` target of encoder diagnostics (never recorded by the crate's own appenders)
+pub(crate) const LOG_TARGET: &str = "alice_text::encode";
+
+/// Raw-text fallbacks per pattern type during one encode, logged once at the end
+#[derive(Debug, Default)]
+struct FallbackLog {
+    /// (pattern, raw, unparseable), in order of first fallback
+    counts: Vec<(PatternType, usize, usize)>,
+}
+
+impl FallbackLog {
+    fn record(&mut self, pattern_type: PatternType, fallback: Fallback) {
+        if matches!(fallback, Fallback::None | Fallback::Spelling) {
+            return;
+        }
+        let index = match self.counts.iter().position(|c| c.0 == pattern_type) {
+            Some(index) => index,
+            None => {
+                self.counts.push((pattern_type, 0, 0));
+                self.counts.len() - 1
+            }
+        };
+        match fallback {
+            Fallback::Raw => self.counts[index].1 += 1,
+            _ => self.counts[index].2 += 1,
+        }
+    }
+
+    /// Warn about unparseable values; other raw-text fallbacks are routine (info)
+    fn emit(&self) {
+        for &(pattern_type, raw, unparseable) in &self.counts {
+            if unparseable > 0 {
+                log::warn!(
+                    target: LOG_TARGET,
+                    "{unparseable} {pattern_type:?} value(s) did not parse; stored as raw text"
+                );
+            }
+            if raw > 0 {
+                log::info!(
+                    target: LOG_TARGET,
+                    "{raw} {pattern_type:?} value(s) would not restore exactly from their column; stored as raw text"
+                );
+            }
+        }
+    }
+}
+
 /// Columnar payload - Struct of Arrays layout
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnarPayload {
@@ -333,6 +881,156 @@ pub struct ColumnarPayload {
 
     /// Other/custom patterns
     pub others: Vec<String>,
+
+    /// Unix epoch seconds/millis (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub epochs: NumericTimeColumn,
+
+    /// dmesg-style uptimes (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub uptimes: NumericTimeColumn,
+
+    /// ANSI escape sequences (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub ansi_codes: Vec<String>,
+
+    /// MAC addresses: 48-bit value plus format flags (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub mac_addrs: Vec<u64>,
+
+    /// Latitude/longitude pairs (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub geo_points: Vec<GeoPoint>,
+
+    /// Kubernetes metadata columns (v2: column section, v3: own columns)
+    #[serde(skip)]
+    pub k8s: KubernetesColumns,
+
+    /// HTTP methods as indices into [`HTTP_METHODS`] (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub http_methods: Vec<u8>,
+
+    /// HTTP status codes (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub http_statuses: Vec<u16>,
+
+    /// Durations / latencies (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub durations: DurationColumn,
+
+    /// Byte sizes (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub byte_sizes: ByteSizeColumn,
+
+    /// Hostnames (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub hosts: HostColumn,
+
+    /// Process ids (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub pids: Vec<u32>,
+
+    /// Thread ids (v2: column section, v3: own column)
+    #[serde(skip)]
+    pub tids: Vec<u32>,
+
+    /// Invalid UTF-8 runs behind `U+FFFD` placeholders (v2: column section, v3: own column)
+    ///
+    /// Not referenced by the skeleton; filled in by the compressor.
+    #[serde(skip)]
+    pub raw_bytes: Vec<RawSegment>,
+
+    /// Values taken by column plugins, one column per registered plugin
+    /// (v3 only: placeholder tags `0x80 + slot`)
+    #[serde(skip)]
+    pub plugin_columns: Vec<PluginColumn>,
+
+    /// Original text of typed values their column restores differently, as
+    /// `(placeholder index, text)` in ascending index order (v2: column
+    /// section, v3: own column); the typed column keeps the parsed value
+    #[serde(skip)]
+    pub spellings: Vec<(u32, String)>,
+}
+
+/// First 8 bytes of a v2.1 columnar payload
+///
+/// v2.0 readers ignore the header and take the payload as a bare Bincode
+/// [`ColumnarPayload`]; read as its skeleton length this marker runs them out
+/// of input, so they refuse v2.1 archives instead of decoding them without
+/// the column section.
+pub(crate) const V2_SECTION_MARKER: u64 = u64::MAX;
+
+/// Tag of a v2.1 column section record
+///
+/// Every tag carries values the skeleton refers to, so a reader that does
+/// not know one rejects the archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u16)]
+enum V2Column {
+    Epochs = 1,
+    Uptimes = 2,
+    Ansi = 3,
+    RawBytes = 4,
+    Mac = 5,
+    Geo = 6,
+    Kubernetes = 7,
+    HttpMethods = 8,
+    HttpStatuses = 9,
+    Durations = 10,
+    ByteSizes = 11,
+    Hosts = 12,
+    Pids = 13,
+    Tids = 14,
+    Spellings = 15,
+}
+
+impl V2Column {
+    const ALL: [Self; 15] = [
+        Self::Epochs,
+        Self::Uptimes,
+        Self::Ansi,
+        Self::RawBytes,
+        Self::Mac,
+        Self::Geo,
+        Self::Kubernetes,
+        Self::HttpMethods,
+        Self::HttpStatuses,
+        Self::Durations,
+        Self::ByteSizes,
+        Self::Hosts,
+        Self::Pids,
+        Self::Tids,
+        Self::Spellings,
+    ];
+
+    fn from_u16(tag: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| *c as u16 == tag)
+    }
+}
+
+/// v2.1 payload being assembled: records are appended after the payload
+struct V2Section(Vec<u8>);
+
+impl V2Section {
+    /// Append one record, unless the column is empty
+    fn put<T: Serialize + ?Sized>(
+        &mut self,
+        column: V2Column,
+        value: &T,
+        empty: bool,
+    ) -> crate::Result<()> {
+        if empty {
+            return Ok(());
+        }
+        let value = bincode::serialize(value)
+            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        self.0.extend_from_slice(&(column as u16).to_le_bytes());
+        self.0.extend_from_slice(
+            &crate::to_u32(value.len(), "v2 column section record")?.to_le_bytes(),
+        );
+        self.0.extend_from_slice(&value);
+        Ok(())
+    }
 }
 
 impl ColumnarPayload {
@@ -359,46 +1057,221 @@ impl ColumnarPayload {
             times: Vec::new(),
             hex_values: Vec::new(),
             others: Vec::new(),
+            epochs: NumericTimeColumn::default(),
+            uptimes: NumericTimeColumn::default(),
+            ansi_codes: Vec::new(),
+            mac_addrs: Vec::new(),
+            geo_points: Vec::new(),
+            k8s: KubernetesColumns::default(),
+            http_methods: Vec::new(),
+            http_statuses: Vec::new(),
+            durations: DurationColumn::default(),
+            byte_sizes: ByteSizeColumn::default(),
+            hosts: HostColumn::default(),
+            pids: Vec::new(),
+            tids: Vec::new(),
+            raw_bytes: Vec::new(),
+            plugin_columns: Vec::new(),
+            spellings: Vec::new(),
+        }
+    }
+
+    /// Serialize for v2.1: [`V2_SECTION_MARKER`], the payload, then the
+    /// column section — one record per non-empty column added since v2.0
+    /// (tag `u16` LE, length `u32` LE, Bincode value; see [`V2Column`])
+    pub(crate) fn to_v2_bytes(&self) -> crate::Result<Vec<u8>> {
+        if self.plugin_columns.iter().any(|c| !c.values.is_empty()) {
+            return Err(ALICETextError::EncodingError(
+                "Column plugin values need format v3".to_string(),
+            ));
         }
+        let mut bytes = V2_SECTION_MARKER.to_le_bytes().to_vec();
+        bincode::serialize_into(&mut bytes, self)
+            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        let mut section = V2Section(bytes);
+        section.put(V2Column::Epochs, &self.epochs, self.epochs.is_empty())?;
+        section.put(V2Column::Uptimes, &self.uptimes, self.uptimes.is_empty())?;
+        section.put(V2Column::Ansi, &self.ansi_codes, self.ansi_codes.is_empty())?;
+        section.put(
+            V2Column::RawBytes,
+            &self.raw_bytes,
+            self.raw_bytes.is_empty(),
+        )?;
+        section.put(V2Column::Mac, &self.mac_addrs, self.mac_addrs.is_empty())?;
+        section.put(V2Column::Geo, &self.geo_points, self.geo_points.is_empty())?;
+        section.put(V2Column::Kubernetes, &self.k8s, self.k8s.is_empty())?;
+        section.put(
+            V2Column::HttpMethods,
+            &self.http_methods,
+            self.http_methods.is_empty(),
+        )?;
+        section.put(
+            V2Column::HttpStatuses,
+            &self.http_statuses,
+            self.http_statuses.is_empty(),
+        )?;
+        section.put(
+            V2Column::Durations,
+            &self.durations,
+            self.durations.is_empty(),
+        )?;
+        section.put(
+            V2Column::ByteSizes,
+            &self.byte_sizes,
+            self.byte_sizes.is_empty(),
+        )?;
+        section.put(V2Column::Hosts, &self.hosts, self.hosts.is_empty())?;
+        section.put(V2Column::Pids, &self.pids, self.pids.is_empty())?;
+        section.put(V2Column::Tids, &self.tids, self.tids.is_empty())?;
+        section.put(
+            V2Column::Spellings,
+            &self.spellings,
+            self.spellings.is_empty(),
+        )?;
+        Ok(section.0)
+    }
+
+    /// Inverse of [`Self::to_v2_bytes`]; also reads the v2.0 layout (bare
+    /// payload, optionally followed by the positional trailers unreleased
+    /// 2.0 builds wrote)
+    pub(crate) fn from_v2_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        let Some(rest) = bytes.strip_prefix(&V2_SECTION_MARKER.to_le_bytes()) else {
+            return Self::from_v2_trailers(bytes);
+        };
+        let mut rest = rest;
+        let mut payload: Self = bincode::deserialize_from(&mut rest)
+            .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        let corrupt = || ALICETextError::DecompressionError("v2 column section truncated".into());
+        while !rest.is_empty() {
+            let (tag, tail) = rest.split_first_chunk::<2>().ok_or_else(corrupt)?;
+            let (len, tail) = tail.split_first_chunk::<4>().ok_or_else(corrupt)?;
+            let (value, tail) = tail
+                .split_at_checked(u32::from_le_bytes(*len) as usize)
+                .ok_or_else(corrupt)?;
+            rest = tail;
+            let tag = u16::from_le_bytes(*tag);
+            let column = V2Column::from_u16(tag).ok_or_else(|| {
+                ALICETextError::DecompressionError(format!(
+                    "Unknown v2 column section tag {tag:#06x}"
+                ))
+            })?;
+            payload.take_v2_column(column, value)?;
+        }
+        Ok(payload)
+    }
+
+    /// Decode one column section record into its field
+    fn take_v2_column(&mut self, column: V2Column, value: &[u8]) -> crate::Result<()> {
+        fn de<T: serde::de::DeserializeOwned>(value: &[u8]) -> crate::Result<T> {
+            bincode::deserialize(value)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))
+        }
+        match column {
+            V2Column::Epochs => self.epochs = de(value)?,
+            V2Column::Uptimes => self.uptimes = de(value)?,
+            V2Column::Ansi => self.ansi_codes = de(value)?,
+            V2Column::RawBytes => self.raw_bytes = de(value)?,
+            V2Column::Mac => self.mac_addrs = de(value)?,
+            V2Column::Geo => self.geo_points = de(value)?,
+            V2Column::Kubernetes => self.k8s = de(value)?,
+            V2Column::HttpMethods => self.http_methods = de(value)?,
+            V2Column::HttpStatuses => self.http_statuses = de(value)?,
+            V2Column::Durations => self.durations = de(value)?,
+            V2Column::ByteSizes => self.byte_sizes = de(value)?,
+            V2Column::Hosts => self.hosts = de(value)?,
+            V2Column::Pids => self.pids = de(value)?,
+            V2Column::Tids => self.tids = de(value)?,
+            V2Column::Spellings => self.spellings = check_spellings(de(value)?)?,
+        }
+        Ok(())
+    }
+
+    /// v2.0 layout: the payload, then up to eight positional trailers
+    /// (epochs / uptimes / ANSI / raw bytes, MAC / geo, Kubernetes, HTTP,
+    /// durations, byte sizes, hostnames, process / thread ids)
+    fn from_v2_trailers(bytes: &[u8]) -> crate::Result<Self> {
+        let mut rest = bytes;
+        let mut payload: Self = bincode::deserialize_from(&mut rest)
+            .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        if !rest.is_empty() {
+            (
+                payload.epochs,
+                payload.uptimes,
+                payload.ansi_codes,
+                payload.raw_bytes,
+            ) = bincode::deserialize_from(&mut rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        if !rest.is_empty() {
+            (payload.mac_addrs, payload.geo_points) = bincode::deserialize_from(&mut rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        if !rest.is_empty() {
+            payload.k8s = bincode::deserialize_from(&mut rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        if !rest.is_empty() {
+            (payload.http_methods, payload.http_statuses) = bincode::deserialize_from(&mut rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        if !rest.is_empty() {
+            payload.durations = bincode::deserialize_from(&mut rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        if !rest.is_empty() {
+            payload.byte_sizes = bincode::deserialize_from(&mut rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        if !rest.is_empty() {
+            payload.hosts = bincode::deserialize_from(&mut rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        if !rest.is_empty() {
+            (payload.pids, payload.tids) = bincode::deserialize(rest)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        }
+        Ok(payload)
     }
 
     /// Parse skeleton string into binary tokens
-    fn parse_skeleton(skeleton: &str) -> Vec<SkeletonToken> {
+    ///
+    /// `{N}` is a placeholder and `{{` a literal `{`; any other `{` is kept
+    /// as text.
+    pub(crate) fn parse_skeleton(skeleton: &str) -> Vec<SkeletonToken> {
         let mut tokens = Vec::new();
         let mut current_text = String::new();
-        let mut chars = skeleton.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == '{' {
-                // Collect digits
-                let mut num_str = String::new();
-                while let Some(&next_c) = chars.peek() {
-                    if next_c == '}' {
-                        chars.next();
-                        break;
-                    }
-                    if next_c.is_ascii_digit() {
-                        num_str.push(chars.next().unwrap());
-                    } else {
-                        break;
-                    }
-                }
+        let mut rest = skeleton;
+
+        while let Some(pos) = rest.find('{') {
+            current_text.push_str(&rest[..pos]);
+            let after = &rest[pos + 1..];
+            if let Some(after) = after.strip_prefix('{') {
+                current_text.push('{');
+                rest = after;
+                continue;
+            }
 
-                if let Ok(idx) = num_str.parse::<u32>() {
+            let digits = after.bytes().take_while(u8::is_ascii_digit).count();
+            match (
+                after[..digits].parse::<u32>(),
+                after[digits..].strip_prefix('}'),
+            ) {
+                (Ok(idx), Some(tail)) => {
                     // Save accumulated text
                     if !current_text.is_empty() {
                         tokens.push(SkeletonToken::Text(std::mem::take(&mut current_text)));
                     }
                     tokens.push(SkeletonToken::Ref(idx));
-                } else {
+                    rest = tail;
+                }
+                _ => {
                     // Not a valid placeholder, keep as text
                     current_text.push('{');
-                    current_text.push_str(&num_str);
+                    rest = after;
                 }
-            } else {
-                current_text.push(c);
             }
         }
+        current_text.push_str(rest);
 
         // Push remaining text
         if !current_text.is_empty() {
@@ -409,7 +1282,16 @@ impl ColumnarPayload {
     }
 
     /// Add a match to the appropriate column
+    ///
+    /// Values a typed column cannot restore byte for byte are stored as raw
+    /// text (see [`Fallback`]).
     pub fn add_match(&mut self, pattern_type: PatternType, text: &str) {
+        self.push_match(pattern_type, text);
+    }
+
+    /// Add a match, reporting whether it went to its typed column
+    pub(crate) fn push_match(&mut self, pattern_type: PatternType, text: &str) -> Fallback {
+        let mut fallback = Fallback::None;
         let (col_type, col_idx) = match pattern_type {
             PatternType::Timestamp => {
                 // Use delta encoding for timestamps
@@ -417,29 +1299,59 @@ impl ColumnarPayload {
                 if is_delta {
                     (0u8, idx as u32) // Delta-encoded: col_type 0
                 } else {
+                    fallback = Fallback::Raw;
                     (13u8, idx as u32) // Raw string: col_type 13
                 }
             }
-            PatternType::IPv4 => {
-                let ip_u32 = parse_ipv4(text).unwrap_or(0);
-                self.ipv4_addrs.push(ip_u32);
-                (1u8, (self.ipv4_addrs.len() - 1) as u32)
-            }
-            PatternType::LogLevel => {
-                let level = LogLevel::parse_level(text);
-                self.log_levels.push(level as u8);
-                (2u8, (self.log_levels.len() - 1) as u32)
-            }
-            PatternType::Number => {
-                let num = text.parse::<f64>().unwrap_or(0.0);
-                self.numbers.push(num);
-                (3u8, (self.numbers.len() - 1) as u32)
-            }
-            PatternType::UUID => {
-                let uuid = parse_uuid(text).unwrap_or(0);
-                self.uuids.push(uuid);
-                (4u8, (self.uuids.len() - 1) as u32)
-            }
+            PatternType::IPv4 => match parse_ipv4(text) {
+                // Zero-padded octets keep their spelling
+                Some(ip) => {
+                    fallback = self.keep_spelling(text, &format_ipv4(ip));
+                    self.ipv4_addrs.push(ip);
+                    (1u8, (self.ipv4_addrs.len() - 1) as u32)
+                }
+                None => {
+                    fallback = Fallback::Unparseable;
+                    self.push_other(text)
+                }
+            },
+            PatternType::LogLevel => match LogLevel::parse_level(text) {
+                LogLevel::Unknown => {
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+                // Lower case and `WARNING` keep their spelling
+                level => {
+                    fallback = self.keep_spelling(text, level.to_str());
+                    self.log_levels.push(level as u8);
+                    (2u8, (self.log_levels.len() - 1) as u32)
+                }
+            },
+            PatternType::Number => match text.parse::<f64>() {
+                // Leading / trailing zeros, exponents, more digits than f64
+                // holds keep their spelling
+                Ok(num) => {
+                    fallback = self.keep_spelling(text, &format_number(num));
+                    self.numbers.push(num);
+                    (3u8, (self.numbers.len() - 1) as u32)
+                }
+                Err(_) => {
+                    fallback = Fallback::Unparseable;
+                    self.push_other(text)
+                }
+            },
+            PatternType::UUID => match parse_uuid(text) {
+                // Upper case, missing dashes keep their spelling
+                Some(uuid) => {
+                    fallback = self.keep_spelling(text, &format_uuid(uuid));
+                    self.uuids.push(uuid);
+                    (4u8, (self.uuids.len() - 1) as u32)
+                }
+                None => {
+                    fallback = Fallback::Unparseable;
+                    self.push_other(text)
+                }
+            },
             PatternType::Email => {
                 self.emails.push(text.to_string());
                 (5u8, (self.emails.len() - 1) as u32)
@@ -453,25 +1365,36 @@ impl ColumnarPayload {
                 (7u8, (self.paths.len() - 1) as u32)
             }
             PatternType::Date => {
-                // Try to parse as epoch days
-                if let Some(days) = parse_date_to_days(text) {
-                    self.date_days.push(days);
-                    (8u8, (self.date_days.len() - 1) as u32)
-                } else {
-                    // Fallback to raw string
-                    self.dates.push(text.to_string());
-                    (14u8, (self.dates.len() - 1) as u32)
+                // Try to parse as epoch days (other layouts than `YYYY-MM-DD`
+                // keep their spelling)
+                match parse_date_to_days(text) {
+                    Some(days) => {
+                        fallback = self.keep_spelling(text, &format_date_from_days(days));
+                        self.date_days.push(days);
+                        (8u8, (self.date_days.len() - 1) as u32)
+                    }
+                    None => {
+                        // Fallback to raw string
+                        fallback = Fallback::Unparseable;
+                        self.dates.push(text.to_string());
+                        (14u8, (self.dates.len() - 1) as u32)
+                    }
                 }
             }
             PatternType::Time => {
                 // Try to parse as milliseconds from midnight
-                if let Some(ms) = parse_time_to_ms(text) {
-                    self.time_ms.push(ms);
-                    (9u8, (self.time_ms.len() - 1) as u32)
-                } else {
-                    // Fallback to raw string
-                    self.times.push(text.to_string());
-                    (15u8, (self.times.len() - 1) as u32)
+                match parse_time_to_ms(text) {
+                    Some(ms) => {
+                        fallback = self.keep_spelling(text, &format_time_from_ms(ms));
+                        self.time_ms.push(ms);
+                        (9u8, (self.time_ms.len() - 1) as u32)
+                    }
+                    None => {
+                        // Fallback to raw string
+                        fallback = Fallback::Unparseable;
+                        self.times.push(text.to_string());
+                        (15u8, (self.times.len() - 1) as u32)
+                    }
                 }
             }
             PatternType::Hex => {
@@ -480,33 +1403,221 @@ impl ColumnarPayload {
             }
             PatternType::IPv6 => {
                 // Parse IPv6 to u128
-                if let Some(ip) = parse_ipv6(text) {
-                    self.ipv6_addrs.push(ip);
-                    (12u8, (self.ipv6_addrs.len() - 1) as u32)
+                match parse_ipv6(text) {
+                    // Upper case and uncompressed zero runs keep their spelling
+                    Some(ip) => {
+                        fallback = self.keep_spelling(text, &format_ipv6(ip));
+                        self.ipv6_addrs.push(ip);
+                        (12u8, (self.ipv6_addrs.len() - 1) as u32)
+                    }
+                    None => {
+                        fallback = Fallback::Unparseable;
+                        self.push_other(text)
+                    }
+                }
+            }
+            PatternType::Epoch => {
+                if let Some(idx) = self.epochs.add_epoch(text) {
+                    (16u8, idx as u32)
+                } else {
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::Uptime => {
+                if let Some(idx) = self.uptimes.add_uptime(text) {
+                    (17u8, idx as u32)
                 } else {
-                    // Fallback to string
-                    self.others.push(text.to_string());
-                    (11u8, (self.others.len() - 1) as u32)
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
                 }
             }
-            PatternType::Custom => {
-                self.others.push(text.to_string());
-                (11u8, (self.others.len() - 1) as u32)
+            PatternType::Ansi => {
+                self.ansi_codes.push(text.to_string());
+                (18u8, (self.ansi_codes.len() - 1) as u32)
+            }
+            PatternType::Mac => {
+                if let Some(mac) = parse_mac(text) {
+                    self.mac_addrs.push(mac);
+                    (19u8, (self.mac_addrs.len() - 1) as u32)
+                } else {
+                    // Mixed-case hex
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::Geo => {
+                if let Some(point) = GeoPoint::parse(text) {
+                    self.geo_points.push(point);
+                    (20u8, (self.geo_points.len() - 1) as u32)
+                } else {
+                    // More precision than f32 holds
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::Cri | PatternType::Klog | PatternType::Namespace | PatternType::Pod => {
+                let (tag, added) = match pattern_type {
+                    PatternType::Cri => (21u8, self.k8s.cri_prefixes.add(text)),
+                    PatternType::Klog => (22u8, self.k8s.klog_headers.add(text)),
+                    PatternType::Namespace => (24u8, self.k8s.namespaces.add(text)),
+                    _ => (25u8, self.k8s.pods.add(text)),
+                };
+                if let Some(idx) = added {
+                    (tag, idx as u32)
+                } else {
+                    // Would not restore exactly
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::ContainerId => {
+                if let Some(id) = ContainerId::parse(text) {
+                    self.k8s.container_ids.push(id);
+                    (23u8, (self.k8s.container_ids.len() - 1) as u32)
+                } else {
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
             }
+            PatternType::HttpMethod => {
+                if let Some(method) = HTTP_METHODS.iter().position(|&m| m == text) {
+                    self.http_methods.push(method as u8);
+                    (26u8, (self.http_methods.len() - 1) as u32)
+                } else {
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::HttpStatus => {
+                match text.parse::<u16>() {
+                    // Three digits without a leading zero restore exactly
+                    Ok(status) if (100..600).contains(&status) => {
+                        self.http_statuses.push(status);
+                        (27u8, (self.http_statuses.len() - 1) as u32)
+                    }
+                    _ => {
+                        fallback = Fallback::Raw;
+                        self.push_other(text)
+                    }
+                }
+            }
+            PatternType::Duration => {
+                if let Some(idx) = self.durations.add(text) {
+                    (28u8, idx as u32)
+                } else {
+                    // Leading zeros, sub-ns fractions, non-canonical components
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::ByteSize => {
+                if let Some(idx) = self.byte_sizes.add(text) {
+                    (29u8, idx as u32)
+                } else {
+                    // Leading zeros, fractions of a byte
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::Hostname => {
+                if let Some(idx) = self.hosts.add(text) {
+                    (30u8, idx as u32)
+                } else {
+                    fallback = Fallback::Raw;
+                    self.push_other(text)
+                }
+            }
+            PatternType::Pid | PatternType::Tid => {
+                let (column, tag) = if pattern_type == PatternType::Pid {
+                    (&mut self.pids, 31u8)
+                } else {
+                    (&mut self.tids, 32u8)
+                };
+                match text.parse::<u32>() {
+                    // No leading zeros: restores exactly
+                    Ok(id) if id.to_string() == text => {
+                        column.push(id);
+                        (tag, (column.len() - 1) as u32)
+                    }
+                    _ => {
+                        fallback = Fallback::Raw;
+                        self.push_other(text)
+                    }
+                }
+            }
+            PatternType::Custom => self.push_other(text),
         };
 
         self.placeholder_map.push((col_type, col_idx));
+        fallback
+    }
+
+    /// Record `text` as the spelling of the placeholder being added unless
+    /// its typed column restores it as `canonical` already
+    fn keep_spelling(&mut self, text: &str, canonical: &str) -> Fallback {
+        if text == canonical {
+            return Fallback::None;
+        }
+        self.spellings
+            .push((self.placeholder_map.len() as u32, text.to_string()));
+        Fallback::Spelling
+    }
+
+    /// Store a value in the `others` column
+    fn push_other(&mut self, text: &str) -> (u8, u32) {
+        self.others.push(text.to_string());
+        (11u8, (self.others.len() - 1) as u32)
+    }
+
+    /// Add a value taken by the plugin in column `slot`
+    pub(crate) fn add_plugin_value(&mut self, slot: usize, text: &str) {
+        let values = &mut self.plugin_columns[slot].values;
+        values.push(text.to_string());
+        self.placeholder_map
+            .push((PLUGIN_TAG_BASE + slot as u8, (values.len() - 1) as u32));
+    }
+
+    /// Precompute prefix sums of every delta-encoded column for O(1) lookup
+    pub(crate) fn prepare_for_read(&self) -> PrefixSums {
+        PrefixSums {
+            timestamps: self.timestamps.prepare_for_read(),
+            epochs: self.epochs.prepare_for_read(),
+            uptimes: self.uptimes.prepare_for_read(),
+            cri_prefixes: self.k8s.cri_prefixes.prepare_for_read(),
+        }
     }
 
     /// Get value for placeholder N (optimized with precomputed prefix sums)
-    fn get_value_fast(&self, placeholder_idx: usize, ts_prefix_sums: &[i64]) -> Option<String> {
-        let (col_type, col_idx) = self.placeholder_map.get(placeholder_idx)?;
-        let idx = *col_idx as usize;
+    pub(crate) fn get_value_fast(
+        &self,
+        placeholder_idx: usize,
+        prefix_sums: &PrefixSums,
+    ) -> Option<String> {
+        let entry = *self.placeholder_map.get(placeholder_idx)?;
+        self.value_for(placeholder_idx, entry, prefix_sums)
+    }
+
+    /// Value of placeholder N given its `(col_type, col_idx)` map entry, for
+    /// callers that hold the placeholder map apart from the value columns
+    pub(crate) fn value_for(
+        &self,
+        placeholder_idx: usize,
+        (col_type, col_idx): (u8, u32),
+        prefix_sums: &PrefixSums,
+    ) -> Option<String> {
+        let idx = col_idx as usize;
+        if let Ok(i) = self
+            .spellings
+            .binary_search_by_key(&placeholder_idx, |&(at, _)| at as usize)
+        {
+            return Some(self.spellings[i].1.clone());
+        }
 
         Some(match col_type {
             0 => {
                 // Delta-encoded timestamp: O(1) lookup
-                self.timestamps.get_delta(idx, ts_prefix_sums)?
+                self.timestamps.get_delta(idx, &prefix_sums.timestamps)?
             }
             1 => {
                 let ip = *self.ipv4_addrs.get(idx)?;
@@ -556,15 +1667,66 @@ impl ColumnarPayload {
                 // Raw time string (fallback)
                 self.times.get(idx)?.clone()
             }
+            16 => self.epochs.get_epoch(idx, &prefix_sums.epochs)?,
+            17 => self.uptimes.get_uptime(idx, &prefix_sums.uptimes)?,
+            18 => self.ansi_codes.get(idx)?.clone(),
+            19 => format_mac(*self.mac_addrs.get(idx)?),
+            20 => self.geo_points.get(idx)?.to_string(),
+            21 => self.k8s.cri_prefixes.get(idx, &prefix_sums.cri_prefixes)?,
+            22 => self.k8s.klog_headers.get(idx)?,
+            23 => self.k8s.container_ids.get(idx)?.to_string(),
+            24 => self.k8s.namespaces.get(idx)?,
+            25 => self.k8s.pods.get(idx)?,
+            26 => (*HTTP_METHODS.get(usize::from(*self.http_methods.get(idx)?))?).to_string(),
+            27 => self.http_statuses.get(idx)?.to_string(),
+            28 => self.durations.get(idx)?,
+            29 => self.byte_sizes.get(idx)?,
+            30 => self.hosts.get(idx)?,
+            31 => self.pids.get(idx)?.to_string(),
+            32 => self.tids.get(idx)?.to_string(),
+            tag if tag >= PLUGIN_TAG_BASE => self
+                .plugin_columns
+                .get(usize::from(tag - PLUGIN_TAG_BASE))?
+                .values
+                .get(idx)?
+                .clone(),
             _ => return None,
         })
     }
 
+    /// Column a placeholder's value is stored in
+    pub(crate) fn placeholder_column(&self, placeholder_idx: usize) -> Option<ColumnType> {
+        let (col_type, _) = self.placeholder_map.get(placeholder_idx)?;
+        ColumnType::from_placeholder_tag(*col_type)
+    }
+
+    /// Split skeleton tokens into lines (each keeps its trailing `\n`)
+    pub(crate) fn split_lines(tokens: &[SkeletonToken]) -> Vec<Vec<LinePiece<'_>>> {
+        let mut lines = Vec::new();
+        let mut current = Vec::new();
+        for token in tokens {
+            match token {
+                SkeletonToken::Text(text) => {
+                    for piece in text.split_inclusive('\n') {
+                        current.push(LinePiece::Text(piece));
+                        if piece.ends_with('\n') {
+                            lines.push(std::mem::take(&mut current));
+                        }
+                    }
+                }
+                SkeletonToken::Ref(idx) => current.push(LinePiece::Ref(*idx)),
+            }
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+        lines
+    }
+
     /// Get value for placeholder N
     #[must_use]
     pub fn get_value(&self, placeholder_idx: usize) -> Option<String> {
-        let ts_prefix_sums = self.timestamps.prepare_for_read();
-        self.get_value_fast(placeholder_idx, &ts_prefix_sums)
+        self.get_value_fast(placeholder_idx, &self.prepare_for_read())
     }
 
     /// Restore original text from skeleton tokens and columns
@@ -572,8 +1734,8 @@ impl ColumnarPayload {
     /// Uses pre-parsed binary tokens for O(N) performance with zero parsing overhead.
     #[must_use]
     pub fn restore(&self) -> String {
-        // Pre-compute timestamp prefix sums once for O(1) lookup
-        let ts_prefix_sums = self.timestamps.prepare_for_read();
+        // Pre-compute delta prefix sums once for O(1) lookup
+        let prefix_sums = self.prepare_for_read();
 
         // Estimate capacity from tokens
         let estimated_size: usize = self
@@ -596,7 +1758,7 @@ impl ColumnarPayload {
                     result.push_str(text);
                 }
                 SkeletonToken::Ref(idx) => {
-                    if let Some(value) = self.get_value_fast(*idx as usize, &ts_prefix_sums) {
+                    if let Some(value) = self.get_value_fast(*idx as usize, &prefix_sums) {
                         result.push_str(&value);
                     }
                 }
@@ -606,6 +1768,18 @@ impl ColumnarPayload {
         result
     }
 
+    /// Bytes of literal skeleton text, i.e. everything not held in a value column
+    #[must_use]
+    pub fn literal_bytes(&self) -> usize {
+        self.skeleton_tokens
+            .iter()
+            .map(|t| match t {
+                SkeletonToken::Text(s) => s.len(),
+                SkeletonToken::Ref(_) => 0,
+            })
+            .sum()
+    }
+
     /// Get compression statistics
     #[must_use]
     pub fn stats(&self) -> HashMap<&'static str, usize> {
@@ -625,6 +1799,23 @@ impl ColumnarPayload {
         stats.insert("times_raw", self.times.len());
         stats.insert("hex", self.hex_values.len());
         stats.insert("others", self.others.len());
+        stats.insert("epochs", self.epochs.len());
+        stats.insert("uptimes", self.uptimes.len());
+        stats.insert("ansi_codes", self.ansi_codes.len());
+        stats.insert("mac_addrs", self.mac_addrs.len());
+        stats.insert("geo_points", self.geo_points.len());
+        stats.insert("cri_prefixes", self.k8s.cri_prefixes.len());
+        stats.insert("klog_headers", self.k8s.klog_headers.headers.len());
+        stats.insert("container_ids", self.k8s.container_ids.len());
+        stats.insert("k8s_namespaces", self.k8s.namespaces.values.len());
+        stats.insert("k8s_pods", self.k8s.pods.pods.len());
+        stats.insert("http_methods", self.http_methods.len());
+        stats.insert("http_statuses", self.http_statuses.len());
+        stats.insert("durations", self.durations.len());
+        stats.insert("byte_sizes", self.byte_sizes.len());
+        stats.insert("hosts", self.hosts.len());
+        stats.insert("pids", self.pids.len());
+        stats.insert("tids", self.tids.len());
         stats
     }
 }
@@ -678,6 +1869,51 @@ fn format_uuid(uuid: u128) -> String {
     )
 }
 
+/// HTTP methods in `http_methods` index order
+pub const HTTP_METHODS: [&str; 9] = [
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// MAC address flag: `-` separators
+const MAC_DASH: u64 = 1 << 48;
+/// MAC address flag: upper-case hex digits
+const MAC_UPPER: u64 = 1 << 49;
+
+/// Parse a MAC address to its 48-bit value plus format flags
+/// (`None` for mixed-case hex, which would not restore exactly)
+fn parse_mac(s: &str) -> Option<u64> {
+    let dash = s.contains('-');
+    let mut mac = 0u64;
+    for octet in s.split(if dash { '-' } else { ':' }) {
+        mac = mac << 8 | u64::from(u8::from_str_radix(octet, 16).ok()?);
+    }
+    if dash {
+        mac |= MAC_DASH;
+    }
+    if s.bytes().any(|b| b.is_ascii_uppercase()) {
+        mac |= MAC_UPPER;
+    }
+    (format_mac(mac) == s).then_some(mac)
+}
+
+/// Format a packed MAC address back to its original text
+pub(crate) fn format_mac(mac: u64) -> String {
+    let separator = if mac & MAC_DASH == 0 { ':' } else { '-' };
+    let mut out = String::with_capacity(17);
+    for shift in (0..6).rev() {
+        let octet = (mac >> (shift * 8)) & 0xFF;
+        if shift < 5 {
+            out.push(separator);
+        }
+        let _ = if mac & MAC_UPPER == 0 {
+            write!(out, "{octet:02x}")
+        } else {
+            write!(out, "{octet:02X}")
+        };
+    }
+    out
+}
+
 /// Format number, preserving integer appearance when possible
 fn format_number(n: f64) -> String {
     if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
@@ -771,9 +2007,87 @@ fn format_time_from_ms(ms: u32) -> String {
     }
 }
 
+/// Parse Unix epoch seconds (10 digits) or millis (13 digits) to (µs, format)
+///
+/// Format byte: `EPOCH_MILLIS` flag | fraction digits.
+fn parse_epoch(s: &str) -> Option<(i64, u8)> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    let (flag, unit_digits) = match int.len() {
+        10 => (0, 6),
+        13 => (EPOCH_MILLIS, 3),
+        _ => return None,
+    };
+    if frac.len() > unit_digits as usize
+        || int.starts_with('0')
+        || s.ends_with('.')
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let scale = frac.len() as u32;
+    let frac_value = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<i64>().ok()?
+    };
+    let value = int.parse::<i64>().ok()? * 10i64.pow(unit_digits)
+        + frac_value * 10i64.pow(unit_digits - scale);
+    Some((value, flag | scale as u8))
+}
+
+/// Format µs back to the original epoch representation
+fn format_epoch(value: i64, format: u8) -> String {
+    let unit_digits = if format & EPOCH_MILLIS == 0 { 6 } else { 3 };
+    let unit = 10i64.pow(unit_digits);
+    let scale = u32::from(format & 0x0f).min(unit_digits);
+    let int = value.div_euclid(unit);
+    if scale == 0 {
+        return int.to_string();
+    }
+    let frac = value.rem_euclid(unit) / 10i64.pow(unit_digits - scale);
+    format!("{int}.{frac:0width$}", width = scale as usize)
+}
+
+/// Parse a bracketed uptime (`[ 1234.5678]`) to (ns, format)
+///
+/// Format byte: padding spaces (high nibble) | fraction digits (low nibble).
+fn parse_uptime(s: &str) -> Option<(i64, u8)> {
+    let inner = s.strip_prefix('[')?.strip_suffix(']')?;
+    let digits = inner.trim_start_matches(' ');
+    let pad = inner.len() - digits.len();
+    let (int, frac) = digits.split_once('.')?;
+    if pad > 0x0f
+        || int.is_empty()
+        || frac.is_empty()
+        || frac.len() > 9
+        || (int.len() > 1 && int.starts_with('0'))
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let scale = frac.len() as u32;
+    let value = int
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(1_000_000_000)?
+        .checked_add(frac.parse::<i64>().ok()? * 10i64.pow(9 - scale))?;
+    Some((value, ((pad as u8) << 4) | scale as u8))
+}
+
+/// Format ns back to the original uptime representation
+fn format_uptime(value: i64, format: u8) -> String {
+    let pad = usize::from(format >> 4);
+    let scale = u32::from(format & 0x0f).clamp(1, 9);
+    let int = value.div_euclid(1_000_000_000);
+    let frac = value.rem_euclid(1_000_000_000) / 10i64.pow(9 - scale);
+    format!("[{:pad$}{int}.{frac:0width$}]", "", width = scale as usize)
+}
+
 /// Columnar Encoder
 pub struct ColumnarEncoder {
     learner: TunedPatternLearner,
+    /// Claim their values before the built-in patterns, in order
+    plugins: Vec<Arc<dyn ColumnPlugin>>,
 }
 
 impl ColumnarEncoder {
@@ -781,22 +2095,142 @@ impl ColumnarEncoder {
     pub fn new() -> Self {
         Self {
             learner: TunedPatternLearner::new(),
+            plugins: Vec::new(),
         }
     }
 
+    /// Register a column plugin (see [`crate::column_plugin`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a plugin with the same id is registered or
+    /// [`MAX_PLUGINS`](crate::column_plugin::MAX_PLUGINS) are.
+    pub fn with_plugin(mut self, plugin: impl ColumnPlugin + 'static) -> crate::Result<Self> {
+        column_plugin::check_registration(&self.plugins, &plugin)?;
+        self.plugins.push(Arc::new(plugin));
+        Ok(self)
+    }
+
+    /// Register already shared plugins (e.g. a decoder's registry), in order
+    pub(crate) fn with_shared_plugins(
+        mut self,
+        plugins: &[Arc<dyn ColumnPlugin>],
+    ) -> crate::Result<Self> {
+        for plugin in plugins {
+            column_plugin::check_registration(&self.plugins, plugin.as_ref())?;
+            self.plugins.push(Arc::clone(plugin));
+        }
+        Ok(self)
+    }
+
+    /// Registered column plugins, in claim order
+    #[must_use]
+    pub fn plugins(&self) -> &[Arc<dyn ColumnPlugin>] {
+        &self.plugins
+    }
+
+    /// Same plugins, new pattern configuration
+    pub(crate) fn reconfigured(&self, config: PatternConfig) -> crate::Result<Self> {
+        Ok(Self {
+            learner: TunedPatternLearner::with_config(config)?,
+            plugins: self.plugins.clone(),
+        })
+    }
+
+    /// Create an encoder with a custom pattern configuration
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exclusion regex is invalid.
+    pub fn with_config(config: PatternConfig) -> crate::Result<Self> {
+        Ok(Self {
+            learner: TunedPatternLearner::with_config(config)?,
+            plugins: Vec::new(),
+        })
+    }
+
+    /// Effective pattern configuration
+    #[must_use]
+    pub fn pattern_config(&self) -> &PatternConfig {
+        self.learner.config()
+    }
+
     /// Encode text into columnar payload
     #[must_use]
     pub fn encode(&self, text: &str) -> ColumnarPayload {
-        let (skeleton, matches) = self.learner.extract_skeleton(text);
+        self.encode_limited(text, PatternLimits::default())
+    }
+
+    /// Encode text, leaving lines rejected by `limits` in the skeleton
+    #[must_use]
+    pub fn encode_limited(&self, text: &str, limits: PatternLimits) -> ColumnarPayload {
+        if !self.plugins.is_empty() {
+            return self.encode_with_plugins(text, limits);
+        }
+        let (skeleton, matches) = self.learner.extract_skeleton_limited(text, limits);
         let mut payload = ColumnarPayload::new(&skeleton);
 
+        let mut fallbacks = FallbackLog::default();
         for m in matches {
-            payload.add_match(m.pattern_type, &m.matched_text);
+            fallbacks.record(
+                m.pattern_type,
+                payload.push_match(m.pattern_type, &m.matched_text),
+            );
         }
+        fallbacks.emit();
 
         payload
     }
 
+    /// Plugins claim their values first; built-in patterns fill the gaps
+    fn encode_with_plugins(&self, text: &str, limits: PatternLimits) -> ColumnarPayload {
+        /// Who took a span: a plugin slot or a built-in pattern
+        enum Owner {
+            Plugin(usize),
+            Pattern(PatternType),
+        }
+
+        let mut coverage = Coverage::new();
+        let mut spans: Vec<(usize, usize, Owner)> = Vec::new();
+        for segment in limits.segments(text) {
+            let found = column_plugin::detect(&self.plugins, text, segment, &mut coverage);
+            spans.extend(
+                found
+                    .into_iter()
+                    .map(|(s, e, slot)| (s, e, Owner::Plugin(slot))),
+            );
+        }
+        let builtin = self.learner.find_unclaimed(text, limits, &mut coverage);
+        spans.extend(
+            builtin
+                .iter()
+                .map(|m| (m.start, m.end, Owner::Pattern(m.pattern_type))),
+        );
+        spans.sort_unstable_by_key(|&(start, _, _)| start);
+
+        let skeleton = build_skeleton(text, spans.iter().map(|&(s, e, _)| (s, e)));
+        let mut payload = ColumnarPayload::new(&skeleton);
+        payload.plugin_columns = self
+            .plugins
+            .iter()
+            .map(|p| PluginColumn {
+                id: p.id().to_string(),
+                values: Vec::new(),
+            })
+            .collect();
+        let mut fallbacks = FallbackLog::default();
+        for (start, end, owner) in spans {
+            match owner {
+                Owner::Plugin(slot) => payload.add_plugin_value(slot, &text[start..end]),
+                Owner::Pattern(pattern) => {
+                    fallbacks.record(pattern, payload.push_match(pattern, &text[start..end]));
+                }
+            }
+        }
+        fallbacks.emit();
+        payload
+    }
+
     /// Decode columnar payload back to text
     #[must_use]
     pub fn decode(&self, payload: &ColumnarPayload) -> String {
@@ -813,6 +2247,7 @@ impl Default for ColumnarEncoder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tuned_pattern_learner::OwnedMatch;
 
     #[test]
     fn test_ipv4_encoding() {
@@ -838,6 +2273,25 @@ mod tests {
         assert_eq!(text, restored);
     }
 
+    #[test]
+    fn test_literal_braces_roundtrip() {
+        // Placeholder-looking text must not be read back as a reference
+        let text = "fmt!(\"{0} {} {name:>8}\", 42) {\"a\": {}} {{x}} {7 {";
+        let no_numbers = PatternConfig::new().disable(PatternType::Number);
+        for encoder in [
+            ColumnarEncoder::new(),
+            ColumnarEncoder::with_config(no_numbers).unwrap(),
+        ] {
+            assert_eq!(encoder.decode(&encoder.encode(text)), text);
+        }
+
+        let learner = TunedPatternLearner::new();
+        let (skeleton, matches) = learner.extract_skeleton("{1} at 10.0.0.1");
+        assert_eq!(skeleton, "{{{0}} at {1}");
+        let owned: Vec<OwnedMatch> = matches.into_iter().map(OwnedMatch::from).collect();
+        assert_eq!(learner.restore_text(&skeleton, &owned), "{1} at 10.0.0.1");
+    }
+
     #[test]
     fn test_columnar_multiline() {
         let encoder = ColumnarEncoder::new();
@@ -896,6 +2350,59 @@ mod tests {
         assert_eq!(uuid_str, formatted);
     }
 
+    #[test]
+    fn test_raw_fallbacks_are_lossless() {
+        let encoder = ColumnarEncoder::new();
+        for text in [
+            "ip 010.0.0.1 x",
+            "n 007 x",
+            "n 1.50 x",
+            "n 12345678901234567890123 x",
+            "id 550E8400-E29B-41D4-A716-446655440000 x",
+            "d 15-01-2024 x",
+            "t 10:30:45.1 x",
+            "v6 2001:0db8:0000:0000:0000:0000:0000:0001 x",
+            "2024-01-15 10:30:45 warning a\n2024-01-15 10:30:46 WARNING b",
+            "2024-01-15 10:30:45.123 INFO a\n2024-01-15 10:30:46.5 INFO b",
+            "2024-01-15T10:30:45Z a\n2024-01-15T10:30:45+09:00 b\n2024-01-15 10:30:45 c",
+        ] {
+            let payload = encoder.encode(text);
+            assert_eq!(payload.restore(), text);
+            let v2 = ColumnarPayload::from_v2_bytes(&payload.to_v2_bytes().unwrap()).unwrap();
+            assert_eq!(v2.restore(), text);
+        }
+
+        let mut payload = ColumnarPayload::new("");
+        assert_eq!(
+            payload.push_match(PatternType::IPv4, "999.0.0.1"),
+            Fallback::Unparseable
+        );
+        assert_eq!(
+            payload.push_match(PatternType::Number, "007"),
+            Fallback::Spelling
+        );
+        assert_eq!(
+            payload.push_match(PatternType::IPv4, "10.0.0.1"),
+            Fallback::None
+        );
+        assert_eq!(
+            payload.push_match(PatternType::LogLevel, "warning"),
+            Fallback::Spelling
+        );
+        // Non-canonical spellings keep their typed value for filters
+        assert_eq!(payload.ipv4_addrs, [0x0A00_0001]);
+        assert_eq!(payload.numbers, [7.0]);
+        assert_eq!(payload.log_levels, [LogLevel::Warn as u8]);
+        assert_eq!(payload.others, ["999.0.0.1"]);
+        assert_eq!(
+            payload.spellings,
+            [(1, "007".to_string()), (3, "warning".to_string())]
+        );
+        assert_eq!(payload.get_value(3).as_deref(), Some("warning"));
+
+        assert!(check_spellings(vec![(2, "a".into()), (1, "b".into())]).is_err());
+    }
+
     #[test]
     fn test_uuid_parse_invalid() {
         assert!(parse_uuid("not-a-uuid").is_none());
@@ -984,6 +2491,158 @@ mod tests {
         assert_eq!(ts.raw[0], "not-a-timestamp");
     }
 
+    #[test]
+    fn test_epoch_and_uptime_exact_roundtrip() {
+        let text = "[    0.000000] boot\n\
+                    [   12.500100] eth0 up at 1705312245 (1705312245120 ms, 1705312245.120)\n\
+                    [ 1234.5678] done at 1705312246.5 id=17053122451\n";
+        let encoder = ColumnarEncoder::new();
+        let payload = encoder.encode(text);
+
+        assert_eq!(payload.epochs.len(), 4);
+        assert_eq!(payload.uptimes.len(), 3);
+        // Deltas in µs between consecutive epochs
+        assert_eq!(payload.epochs.deltas[1..], [120_000, 0, 1_380_000]);
+        assert_eq!(encoder.decode(&payload), text);
+    }
+
+    #[test]
+    fn test_epoch_unrepresentable_fallback() {
+        let mut col = NumericTimeColumn::default();
+        assert!(col.add_epoch("1705312245123.4567").is_none());
+        assert!(col.add_uptime("[007.5]").is_none());
+        assert!(col.is_empty());
+
+        let payload = ColumnarEncoder::new().encode("at 1705312245123.4567 [007.5]");
+        assert_eq!(payload.restore(), "at 1705312245123.4567 [007.5]");
+    }
+
+    #[test]
+    fn test_mac_and_geo_exact_roundtrip() {
+        let text = "dhcp 00:1a:2b:3c:4d:5e AA-BB-CC-DD-EE-0F at 35.6895, 139.6917\n\
+                    mixed 00:1A:2b:3c:4d:5e at -33.8688,151.2093 fine 12.3456789, 139.6917123\n";
+        let payload = ColumnarEncoder::new().encode(text);
+
+        assert_eq!(
+            payload.mac_addrs,
+            [0x001a_2b3c_4d5e, 0xaabb_ccdd_ee0f | MAC_DASH | MAC_UPPER]
+        );
+        assert_eq!(payload.geo_points.len(), 2);
+        assert_eq!(payload.geo_points[1].to_string(), "-33.8688,151.2093");
+        // Mixed case and more precision than f32 holds stay exact via `others`
+        assert_eq!(
+            payload.others,
+            ["00:1A:2b:3c:4d:5e", "12.3456789, 139.6917123"]
+        );
+        assert_eq!(payload.restore(), text);
+
+        let v2 = ColumnarPayload::from_v2_bytes(&payload.to_v2_bytes().unwrap()).unwrap();
+        assert_eq!(v2.restore(), text);
+    }
+
+    #[test]
+    fn test_http_columns_roundtrip() {
+        let text = "10.0.0.1 \"GET /a HTTP/1.1\" 200 512\n10.0.0.2 \"POST /b HTTP/2.0\" 503 0\n";
+        let payload = ColumnarEncoder::new().encode(text);
+
+        assert_eq!(payload.http_methods, [0, 2]);
+        assert_eq!(payload.http_statuses, [200, 503]);
+        // Response sizes are still numbers
+        assert_eq!(payload.numbers, [512.0, 0.0]);
+        assert_eq!(payload.restore(), text);
+
+        let v2 = ColumnarPayload::from_v2_bytes(&payload.to_v2_bytes().unwrap()).unwrap();
+        assert_eq!(v2.restore(), text);
+    }
+
+    #[test]
+    fn test_duration_exact_roundtrip() {
+        for text in ["123ms", "4.5s", "2m30s", "1h2m3.250s", "7µs", "0.5ns"] {
+            let Some((nanos, format)) = parse_duration(text) else {
+                assert_eq!(text, "0.5ns", "finer than 1 ns is not representable");
+                continue;
+            };
+            assert_eq!(format_duration(nanos, format).unwrap(), text);
+        }
+        assert_eq!(parse_duration("2m30s").unwrap().0, 150_000_000_000);
+        // Not reproducible: leading zero, fraction before the last component
+        assert!(parse_duration("05s").is_none());
+        assert!(parse_duration("1.5m30s").is_none());
+
+        let text = "GET /a took 123ms\nGET /b took 2m30s (timeout 05s)\n";
+        let payload = ColumnarEncoder::new().encode(text);
+        assert_eq!(payload.durations.nanos, [123_000_000, 150_000_000_000]);
+        assert_eq!(payload.others, ["05s"]);
+        assert_eq!(payload.restore(), text);
+        let v2 = ColumnarPayload::from_v2_bytes(&payload.to_v2_bytes().unwrap()).unwrap();
+        assert_eq!(v2.restore(), text);
+    }
+
+    #[test]
+    fn test_byte_size_exact_roundtrip() {
+        for text in ["300KB", "1.5GiB", "512B", "4kB", "2.25MiB", "1.000TB"] {
+            let (bytes, format) = parse_byte_size(text).unwrap();
+            assert_eq!(format_byte_size(bytes, format).unwrap(), text);
+        }
+        assert_eq!(parse_byte_size("1.5GiB").unwrap().0, 1_610_612_736);
+        // Not reproducible: leading zero, fraction of a byte
+        assert!(parse_byte_size("01MB").is_none());
+        assert!(parse_byte_size("1.5B").is_none());
+        assert!(parse_byte_size("1.0001KiB").is_none());
+
+        let text = "flushed 300KB to disk\ncompacted 1.5GiB (was 0.5B)\n";
+        let payload = ColumnarEncoder::new().encode(text);
+        assert_eq!(payload.byte_sizes.bytes, [300_000, 1_610_612_736]);
+        assert_eq!(payload.others, ["0.5B"]);
+        assert_eq!(payload.restore(), text);
+        let v2 = ColumnarPayload::from_v2_bytes(&payload.to_v2_bytes().unwrap()).unwrap();
+        assert_eq!(v2.restore(), text);
+    }
+
+    #[test]
+    fn test_v2_column_section() {
+        let text = "boot 1705312245 on aa:bb:cc:dd:ee:ff took 123ms\n";
+        let payload = ColumnarEncoder::new().encode(text);
+        let bytes = payload.to_v2_bytes().unwrap();
+        assert_eq!(
+            ColumnarPayload::from_v2_bytes(&bytes).unwrap().restore(),
+            text
+        );
+
+        // A 2.0 reader (bare Bincode payload) refuses the section layout
+        assert!(bincode::deserialize::<ColumnarPayload>(&bytes).is_err());
+
+        // Unknown and truncated records are rejected, not skipped
+        let mut unknown = bytes.clone();
+        unknown.extend_from_slice(&0x7fffu16.to_le_bytes());
+        unknown.extend_from_slice(&0u32.to_le_bytes());
+        assert!(ColumnarPayload::from_v2_bytes(&unknown).is_err());
+        assert!(ColumnarPayload::from_v2_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_hostname_suffix_tree() {
+        let text = "\
+connect web-01.prod.example.com:443 ok
+connect web-02.prod.example.com:443 ok
+connect db.staging.example.com:5432 refused
+rotate app.log, keep 1.2.3 and com.example.Foo
+";
+        let payload = ColumnarEncoder::new().encode(text);
+        assert_eq!(payload.hosts.len(), 3);
+        // com, example, prod, web-01, web-02, staging, db
+        assert_eq!(payload.hosts.nodes.len(), 7);
+        assert_eq!(payload.hosts.labels.entries.len(), 7);
+        assert_eq!(
+            payload.hosts.get(2).as_deref(),
+            Some("db.staging.example.com")
+        );
+        assert_eq!(payload.restore(), text);
+        let v2 = ColumnarPayload::from_v2_bytes(&payload.to_v2_bytes().unwrap()).unwrap();
+        assert_eq!(v2.hosts.get(1).as_deref(), Some("web-02.prod.example.com"));
+        assert_eq!(v2.restore(), text);
+    }
+
     #[test]
     fn test_timestamp_column_empty() {
         let ts = TimestampColumn::default();
@@ -0,0 +1,334 @@
+//! Configuration file — fleet-wide compression defaults
+//!
+//! An optional TOML file supplies the defaults an invocation would otherwise
+//! spell out: compression level, pattern profile, extra pattern rules and a
+//! redaction policy. [`ConfigLoader`] finds it (`--config PATH`, else
+//! `$XDG_CONFIG_HOME/alice-text.toml`, else `~/.config/alice-text.toml`);
+//! a missing default file is an empty [`Config`]. Flags given on the
+//! command line win over the file.
+//!
+//! ```toml
+//! level = "best"
+//! profile = "strict"          # default, strict, kubernetes, prose, auto
+//!
+//! [patterns]
+//! disabled = ["Hex"]
+//! exclusions = [{ pattern_type = "Email", regex = "@example\\.com$" }]
+//!
+//! [redact]
+//! columns = ["emails", "urls"]
+//! replacement = "[redacted]"
+//! ```
+//!
+//! ```rust,ignore
+//! use alice_text::{ConfigLoader, FormatV3Writer};
+//!
+//! let config = ConfigLoader::new().load()?;
+//! let archive = FormatV3Writer::new(config.level()?.unwrap_or_default())
+//!     .with_pattern_config(config.pattern_config().unwrap_or_default())?
+//!     .compress(&text)?;
+//! let archive = config.redact.apply(archive)?;
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::format_v3::{ColumnType, FormatV3Editor};
+use crate::level::Level;
+use crate::tuned_pattern_learner::PatternConfig;
+use crate::{ALICETextError, Result};
+
+/// File name looked up in the user's config directory
+pub const CONFIG_FILE: &str = "alice-text.toml";
+
+/// Value written over redacted fields unless the policy names another
+pub const DEFAULT_REPLACEMENT: &str = "[redacted]";
+
+/// Pattern profile a config selects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    #[default]
+    Default,
+    /// [`PatternConfig::strict`]
+    Strict,
+    /// [`PatternConfig::kubernetes`]
+    Kubernetes,
+    /// [`PatternConfig::prose`]
+    Prose,
+    /// Classify each input and use its kind's profile
+    Auto,
+}
+
+/// Columns whose values are overwritten after compression (v3 archives)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionPolicy {
+    /// String-valued column names (`emails`, `urls`, `paths`, `hex_values`, ...)
+    pub columns: Vec<String>,
+    pub replacement: String,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+            replacement: DEFAULT_REPLACEMENT.to_string(),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Column types of [`Self::columns`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming an unknown or non-editable column.
+    pub fn column_types(&self) -> Result<Vec<ColumnType>> {
+        self.columns
+            .iter()
+            .map(|name| {
+                (0..=u8::MAX)
+                    .filter_map(ColumnType::from_u8)
+                    .find(|c| c.name() == name && FormatV3Editor::is_editable(*c))
+                    .ok_or_else(|| {
+                        ALICETextError::EncodingError(format!(
+                            "Cannot redact column {name:?}: not a string-valued v3 column"
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Overwrite every value of the policy's columns in a v3 archive
+    ///
+    /// The archive is re-encoded afterwards ([`FormatV3Editor::compact`]), so
+    /// no block left behind by the edits still holds the original values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column cannot be redacted, the data is not a
+    /// v3 archive or re-encoding fails.
+    pub fn apply(&self, archive: Vec<u8>) -> Result<Vec<u8>> {
+        if self.is_empty() {
+            return Ok(archive);
+        }
+        let mut editor = FormatV3Editor::new(archive)?;
+        for col_type in self.column_types()? {
+            editor.rewrite_column(col_type, |_, _| Some(self.replacement.clone()))?;
+        }
+        editor.compact()
+    }
+}
+
+/// Compression defaults read from a config file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Compression level: `fast`, `balanced`, `best`, `max` or a zstd level
+    /// (`"15"`), see [`Level`]
+    pub level: Option<String>,
+    pub profile: Option<Profile>,
+    /// Rules layered on the profile's pattern config
+    pub patterns: PatternConfig,
+    pub redact: RedactionPolicy,
+}
+
+impl Config {
+    /// Parse and validate TOML
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on a syntax error, an unknown key, level or profile,
+    /// or a column the redaction policy cannot rewrite.
+    pub fn parse(toml: &str) -> Result<Self> {
+        let config: Self = toml::from_str(toml)
+            .map_err(|e| ALICETextError::EncodingError(format!("Config: {e}")))?;
+        config
+            .level()
+            .map_err(|e| ALICETextError::EncodingError(format!("Config: {e}")))?;
+        config.redact.column_types()?;
+        Ok(config)
+    }
+
+    /// Level for either writer, if the config sets one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the level does not parse (checked by [`Self::parse`]).
+    pub fn level(&self) -> Result<Option<Level>> {
+        self.level.as_deref().map(str::parse).transpose()
+    }
+
+    /// The profile's pattern config with [`Self::patterns`] layered on top;
+    /// `None` for [`Profile::Auto`] (classify the input instead)
+    #[must_use]
+    pub fn pattern_config(&self) -> Option<PatternConfig> {
+        let mut config = match self.profile.unwrap_or_default() {
+            Profile::Default => PatternConfig::default(),
+            Profile::Strict => PatternConfig::strict(),
+            Profile::Kubernetes => PatternConfig::kubernetes(),
+            Profile::Prose => PatternConfig::prose(),
+            Profile::Auto => return None,
+        };
+        let extra = self.patterns.clone();
+        if !extra.priority.is_empty() {
+            config.priority.retain(|p| !extra.priority.contains(p));
+            config.priority.splice(0..0, extra.priority);
+        }
+        config.disabled.extend(extra.disabled);
+        config.guards.extend(extra.guards);
+        config.exclusions.extend(extra.exclusions);
+        Some(config)
+    }
+}
+
+/// Locates and reads the config file
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    path: Option<PathBuf>,
+}
+
+impl ConfigLoader {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read this file instead of the default one (it must exist)
+    #[must_use]
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// `$XDG_CONFIG_HOME/alice-text.toml`, else `$HOME/.config/alice-text.toml`
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        let non_empty = |key| std::env::var_os(key).filter(|v| !v.is_empty());
+        non_empty("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    /// Load the config: the explicit path, else the default file if present,
+    /// else an empty config
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an explicit file is missing, or a file cannot be
+    /// read or is invalid.
+    pub fn load(&self) -> Result<Config> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => match Self::default_path() {
+                Some(path) if path.is_file() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+        let toml = std::fs::read_to_string(&path)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+        Config::parse(&toml).map_err(|e| match e {
+            ALICETextError::EncodingError(msg) => {
+                ALICETextError::EncodingError(format!("{}: {msg}", path.display()))
+            }
+            e => e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatV3Writer, TunedPatternType};
+    use std::io::Read;
+
+    const TOML: &str = r#"
+level = "best"
+profile = "kubernetes"
+
+[patterns]
+disabled = ["Hex"]
+exclusions = [{ pattern_type = "Email", regex = "@internal$" }]
+
+[redact]
+columns = ["emails"]
+"#;
+
+    #[test]
+    fn test_parse_and_layer_patterns() {
+        let config = Config::parse(TOML).unwrap();
+        assert_eq!(config.level().unwrap(), Some(Level::Best));
+        let patterns = config.pattern_config().unwrap();
+        assert_eq!(patterns.priority, PatternConfig::kubernetes().priority);
+        assert_eq!(patterns.disabled, [TunedPatternType::Hex]);
+        assert_eq!(patterns.exclusions.len(), 1);
+        assert_eq!(config.redact.replacement, DEFAULT_REPLACEMENT);
+
+        let auto = Config::parse("profile = \"auto\"").unwrap();
+        assert_eq!(auto.pattern_config(), None);
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_invalid_config_rejected() {
+        assert!(Config::parse("level = \"turbo\"").is_err());
+        let exact = Config::parse("level = \"15\"").unwrap();
+        assert_eq!(exact.level().unwrap(), Some(Level::Zstd(15)));
+        assert!(Config::parse("profile = \"fancy\"").is_err());
+        assert!(Config::parse("levle = \"best\"").is_err());
+        assert!(Config::parse("[redact]\ncolumns = [\"ipv4\"]").is_err());
+    }
+
+    #[test]
+    fn test_loader_and_redaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        assert!(ConfigLoader::new().with_path(&path).load().is_err());
+        std::fs::write(&path, TOML).unwrap();
+        let config = ConfigLoader::new().with_path(&path).load().unwrap();
+
+        let log = "login alice@example.com ok\nlogin bob@example.com denied\n";
+        let archive = FormatV3Writer::new(Level::Fast).compress(log).unwrap();
+        let redacted = config.redact.apply(archive).unwrap();
+        assert_eq!(
+            FormatV3Writer::decompress(&redacted).unwrap(),
+            "login [redacted] ok\nlogin [redacted] denied\n"
+        );
+        // A replacement too long for the old slot: no block of the output,
+        // listed or orphaned, still holds the originals
+        let policy = RedactionPolicy {
+            columns: vec!["emails".to_string()],
+            replacement: "[removed under retention policy 7]".to_string(),
+        };
+        let archive = FormatV3Writer::new(Level::Fast).compress(log).unwrap();
+        let redacted = policy.apply(archive).unwrap();
+        let blocks: Vec<u8> = (0..redacted.len())
+            .filter(|&i| redacted[i..].starts_with(&[0x28, 0xB5, 0x2F, 0xFD]))
+            .filter_map(|i| {
+                let mut block = Vec::new();
+                zstd::stream::read::Decoder::new(&redacted[i..])
+                    .ok()?
+                    .single_frame()
+                    .read_to_end(&mut block)
+                    .ok()?;
+                Some(block)
+            })
+            .flatten()
+            .collect();
+        assert!(!blocks.is_empty());
+        for value in [&b"alice@example.com"[..], b"bob@example.com"] {
+            assert!(!blocks.windows(value.len()).any(|w| w == value));
+        }
+    }
+}
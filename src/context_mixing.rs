@@ -0,0 +1,389 @@
+//! Context-mixing model for skeleton text (`CompressionMode::Max`)
+//!
+//! Bitwise prediction in the style of PAQ: for every bit, the contexts of
+//! order 0..=N (hashes of the last N bytes plus the bits of the current byte
+//! seen so far) each look up an adaptive probability; a logistic mixer
+//! weights them by how well they have predicted recently, and the mixed
+//! probability drives the binary arithmetic coder.
+//!
+//! Skeleton text is a handful of line templates repeated with small
+//! variations, so the high orders predict almost every bit once a template
+//! has been seen. Coding runs at a few MB/s in both directions, so a `Max`
+//! archive takes several times as long as a `Best` one to decompress —
+//! meant for cold archives where ratio matters more than speed.
+//!
+//! All arithmetic is integer, so encoder and decoder agree on every platform.
+
+use crate::arithmetic_coder::{symbol_buffer, ArithmeticDecoder, ArithmeticEncoder};
+
+/// Slots per order table (2^TABLE_BITS)
+const TABLE_BITS: u32 = 18;
+/// Probabilities are 12-bit (0..4096) at the mixer and coder
+const PROB_BITS: u32 = 12;
+/// Counter adaptation limit (adaptive rate 1/(n+1.5) until n reaches it)
+const COUNT_LIMIT: u32 = 255;
+/// Most bytes one coded byte can carry: the coder clamps every bit to at
+/// most 4095/4096, so each costs at least log2(4096/4095) bits (~2839 per bit)
+const MAX_BYTES_PER_INPUT_BYTE: usize = 4096;
+
+/// Logistic function on a stretched probability (`d` in 1/256 units), 12-bit result
+fn squash(d: i32) -> i32 {
+    const T: [i32; 33] = [
+        1, 2, 3, 6, 10, 16, 27, 45, 73, 120, 194, 310, 488, 747, 1101, 1546, 2047, 2549, 2994,
+        3348, 3607, 3785, 3901, 3975, 4022, 4050, 4068, 4079, 4085, 4089, 4092, 4093, 4094,
+    ];
+    if d > 2047 {
+        return 4095;
+    }
+    if d < -2047 {
+        return 1;
+    }
+    let w = d & 127;
+    let i = ((d >> 7) + 16) as usize;
+    (T[i] * (128 - w) + T[i + 1] * w + 64) >> 7
+}
+
+/// Inverse of [`squash`], tabulated for all 12-bit probabilities
+fn stretch_table() -> Vec<i16> {
+    let mut table = vec![2047i16; 1 << PROB_BITS];
+    let mut next = 0usize;
+    for d in -2047..=2047 {
+        let p = squash(d) as usize;
+        for slot in table.iter_mut().take(p + 1).skip(next) {
+            *slot = d as i16;
+        }
+        next = next.max(p + 1);
+    }
+    table
+}
+
+/// Adaptive probability per context slot: `probability << 10 | count`
+///
+/// The rate is 1/(n+1.5) for the n-th update, so fresh slots learn fast and
+/// settled ones average over up to [`COUNT_LIMIT`] observations.
+#[derive(Debug, Clone)]
+struct StateMap {
+    slots: Vec<u32>,
+}
+
+impl StateMap {
+    fn new(size: usize) -> Self {
+        Self {
+            slots: vec![1 << 31; size],
+        }
+    }
+
+    /// 12-bit probability of a 1
+    #[inline]
+    fn p(&self, slot: usize) -> usize {
+        (self.slots[slot] >> 20) as usize
+    }
+
+    #[inline]
+    fn update(&mut self, slot: usize, bit: bool, rates: &[u32]) {
+        let entry = &mut self.slots[slot];
+        let count = *entry & 1023;
+        let p = i64::from(*entry >> 10);
+        let goal = if bit { (1i64 << 22) - 1 } else { 0 };
+        let p = p + (((goal - p) * i64::from(rates[count as usize])) >> 16);
+        *entry = ((p as u32) << 10) | (count + 1).min(COUNT_LIMIT);
+    }
+}
+
+/// Order-N bitwise context-mixing predictor
+///
+/// Inputs: one hashed context per order `0..=N`, plus a match model that
+/// follows the most recent earlier occurrence of the last [`MATCH_MIN`]
+/// bytes and predicts the bit that came next there. The mixer keeps one
+/// weight set per match-length bucket.
+#[derive(Debug, Clone)]
+pub struct ContextMixer {
+    /// One table per order
+    tables: Vec<StateMap>,
+    /// Match model: probability per (match length bucket, expected bit)
+    match_map: StateMap,
+    /// Mixer weight sets (16 fractional bits), `inputs` weights each
+    weights: Vec<i32>,
+    /// Context hash per order for the current byte
+    hashes: Vec<u32>,
+    /// Slot per input for the current bit (orders, then the match model)
+    slots: Vec<usize>,
+    /// Stretched prediction per input for the current bit
+    inputs: Vec<i32>,
+    /// Weight set for the current bit
+    weight_set: usize,
+    stretch: Vec<i16>,
+    /// Adaptation rate per count: 2^17 / (2n + 3)
+    rates: Vec<u32>,
+    /// Everything coded so far (the match model's search space)
+    buffer: Vec<u8>,
+    /// Last position after each hashed `MATCH_MIN`-byte context
+    match_index: Vec<u32>,
+    /// Position in `buffer` of the predicted next byte (valid if `match_len > 0`)
+    match_ptr: usize,
+    match_len: usize,
+    /// Bits of the current byte with a leading 1 (1..=255)
+    partial: u32,
+    /// Mixed prediction for the next bit (12-bit)
+    prediction: i32,
+}
+
+/// Bytes of context the match model needs to find a match
+const MATCH_MIN: usize = 6;
+/// Match lengths are bucketed up to this for the match model and weight sets
+const MATCH_BUCKETS: usize = 16;
+
+impl ContextMixer {
+    /// Highest context order used by `CompressionMode::Max`
+    pub const DEFAULT_ORDER: usize = 5;
+    /// Highest supported order (contexts hash at most 8 history bytes)
+    pub const MAX_ORDER: usize = 8;
+
+    /// Predictor over orders `0..=order` (clamped to [`Self::MAX_ORDER`])
+    #[must_use]
+    pub fn new(order: usize) -> Self {
+        let orders = order.min(Self::MAX_ORDER) + 1;
+        let inputs = orders + 1;
+        let mut mixer = Self {
+            tables: vec![StateMap::new(1 << TABLE_BITS); orders],
+            match_map: StateMap::new(MATCH_BUCKETS * 2),
+            weights: vec![(1 << 16) / inputs as i32; inputs * MATCH_BUCKETS],
+            hashes: vec![0; orders],
+            slots: vec![0; inputs],
+            inputs: vec![0; inputs],
+            weight_set: 0,
+            stretch: stretch_table(),
+            rates: (0..=COUNT_LIMIT).map(|n| (1 << 17) / (2 * n + 3)).collect(),
+            buffer: Vec::new(),
+            match_index: vec![0; 1 << TABLE_BITS],
+            match_ptr: 0,
+            match_len: 0,
+            partial: 1,
+            prediction: 2048,
+        };
+        mixer.start_byte();
+        mixer.predict();
+        mixer
+    }
+
+    /// Highest context order
+    #[must_use]
+    pub fn order(&self) -> usize {
+        self.tables.len() - 1
+    }
+
+    /// Probability (out of 4096) that the next bit is 1
+    #[must_use]
+    pub const fn p1(&self) -> u16 {
+        self.prediction as u16
+    }
+
+    /// Learn the actual bit and predict the next one
+    pub fn update(&mut self, bit: bool) {
+        let err = if bit { 1 << 12 } else { 0 } - self.prediction;
+        let n = self.inputs.len();
+        let weights = &mut self.weights[self.weight_set * n..][..n];
+        for (weight, &input) in weights.iter_mut().zip(&self.inputs) {
+            *weight += (input * err) >> 12;
+        }
+
+        for (table, &slot) in self.tables.iter_mut().zip(&self.slots) {
+            table.update(slot, bit, &self.rates);
+        }
+        self.match_map.update(self.slots[n - 1], bit, &self.rates);
+
+        self.partial = (self.partial << 1) | u32::from(bit);
+        if self.partial >= 256 {
+            self.buffer.push(self.partial as u8);
+            self.partial = 1;
+            self.start_byte();
+        }
+        self.predict();
+    }
+
+    /// Hash the last bytes for each order and advance the match model
+    fn start_byte(&mut self) {
+        let len = self.buffer.len();
+        let mut history = 0u64;
+        for &byte in &self.buffer[len.saturating_sub(8)..] {
+            history = (history << 8) | u64::from(byte);
+        }
+        for (order, hash) in self.hashes.iter_mut().enumerate() {
+            let mask = if order == 8 {
+                u64::MAX
+            } else {
+                (1u64 << (8 * order)) - 1
+            };
+            let context = (history & mask) ^ ((order as u64) << 60);
+            *hash = (context.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as u32;
+        }
+
+        if len < MATCH_MIN {
+            return;
+        }
+        if self.match_len > 0 && self.buffer[self.match_ptr] == self.buffer[len - 1] {
+            self.match_len += 1;
+            self.match_ptr += 1;
+        } else {
+            self.match_len = 0;
+        }
+        let key = ((history & 0xFFFF_FFFF_FFFF).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            >> (64 - TABLE_BITS)) as usize;
+        if self.match_len == 0 {
+            let candidate = self.match_index[key] as usize;
+            if candidate > 0 {
+                let matched = (1..=candidate.min(MATCH_BUCKETS))
+                    .take_while(|&k| self.buffer[candidate - k] == self.buffer[len - k])
+                    .count();
+                if matched >= MATCH_MIN {
+                    self.match_len = matched;
+                    self.match_ptr = candidate;
+                }
+            }
+        }
+        self.match_index[key] = len as u32;
+    }
+
+    fn predict(&mut self) {
+        let shift = 32 - TABLE_BITS;
+        let n = self.inputs.len();
+        for (order, table) in self.tables.iter().enumerate() {
+            let slot = (self.hashes[order].wrapping_add(self.partial.wrapping_mul(0x2F0B_3A49)))
+                .wrapping_mul(0x9E37_79B1)
+                >> shift;
+            self.slots[order] = slot as usize;
+            self.inputs[order] = i32::from(self.stretch[table.p(slot as usize)]);
+        }
+
+        // Match model: expected bit while the current byte still agrees
+        let bucket = if self.match_len > 0 {
+            let expected = u32::from(self.buffer[self.match_ptr]) | 0x100;
+            let bits = 32 - self.partial.leading_zeros() - 1;
+            if expected >> (8 - bits) == self.partial {
+                let expected_bit = (expected >> (7 - bits)) & 1;
+                let bucket = self.match_len.min(MATCH_BUCKETS - 1);
+                self.slots[n - 1] = bucket * 2 + expected_bit as usize;
+                bucket
+            } else {
+                0
+            }
+        } else {
+            0
+        };
+        if bucket == 0 {
+            self.slots[n - 1] = 0;
+            self.inputs[n - 1] = 0;
+        } else {
+            self.inputs[n - 1] = i32::from(self.stretch[self.match_map.p(self.slots[n - 1])]);
+        }
+
+        self.weight_set = bucket;
+        let weights = &self.weights[bucket * n..][..n];
+        let dot: i64 = weights
+            .iter()
+            .zip(&self.inputs)
+            .map(|(&w, &x)| i64::from(w) * i64::from(x))
+            .sum();
+        self.prediction = squash((dot >> 16).clamp(-2047, 2047) as i32).clamp(1, 4095);
+    }
+}
+
+/// Code bytes with a [`ContextMixer`] of the given order
+///
+/// Output: the order (`u8`), then the coded bits. The byte count is not
+/// stored; empty input gives empty output.
+#[must_use]
+pub fn encode(data: &[u8], order: usize) -> Vec<u8> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let mut mixer = ContextMixer::new(order);
+    let mut encoder = ArithmeticEncoder::new();
+    for &byte in data {
+        for i in (0..8).rev() {
+            let bit = (byte >> i) & 1 == 1;
+            encoder.encode_bit(bit, mixer.p1());
+            mixer.update(bit);
+        }
+    }
+    let mut out = vec![mixer.order() as u8];
+    out.extend(encoder.finish());
+    out
+}
+
+/// Inverse of [`encode`]; `None` if the data can't hold `len` bytes
+#[must_use]
+pub fn decode(data: &[u8], len: usize) -> Option<Vec<u8>> {
+    if len == 0 {
+        return Some(Vec::new());
+    }
+    let (&order, bits) = data.split_first()?;
+    if len / MAX_BYTES_PER_INPUT_BYTE > bits.len() + 8 {
+        return None;
+    }
+    let mut mixer = ContextMixer::new(order as usize);
+    let mut decoder = ArithmeticDecoder::new(bits.to_vec());
+    let mut out = symbol_buffer(len, bits.len())?;
+    for _ in 0..len {
+        let mut byte = 0u8;
+        for _ in 0..8 {
+            let bit = decoder.decode_bit(mixer.p1());
+            mixer.update(bit);
+            byte = (byte << 1) | u8::from(bit);
+        }
+        out.push(byte);
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_mixing_roundtrip() {
+        let mut skeleton = Vec::new();
+        for i in 0..300 {
+            let template: &[u8] = match i % 3 {
+                0 => b"\xFF \xFF request from \xFF took \xFFms\n",
+                1 => b"\xFF \xFF cache miss for key \xFF\n",
+                _ => b"\xFF \xFF worker \xFF finished job \xFF in \xFFs\n",
+            };
+            skeleton.extend_from_slice(template);
+        }
+        let encoded = encode(&skeleton, ContextMixer::DEFAULT_ORDER);
+        assert_eq!(encoded[0] as usize, ContextMixer::DEFAULT_ORDER);
+        assert_eq!(decode(&encoded, skeleton.len()).unwrap(), skeleton);
+
+        // Repeated templates: far below Zstd on the same bytes
+        let zstd = zstd::stream::encode_all(skeleton.as_slice(), 19).unwrap();
+        assert!(
+            encoded.len() < zstd.len(),
+            "{} vs {}",
+            encoded.len(),
+            zstd.len()
+        );
+
+        let binary: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        for order in [0, 2, ContextMixer::MAX_ORDER] {
+            assert_eq!(
+                decode(&encode(&binary, order), binary.len()).unwrap(),
+                binary
+            );
+        }
+        assert!(encode(&[], 3).is_empty());
+        assert!(decode(&[], 1).is_none());
+        // Lengths the input cannot code fail before any work or allocation
+        assert!(decode(&encoded, usize::MAX).is_none());
+    }
+
+    #[test]
+    fn test_squash_stretch_inverse() {
+        let stretch = stretch_table();
+        for p in [1, 100, 2048, 3000, 4095] {
+            let d = i32::from(stretch[p]);
+            assert!((squash(d) - p as i32).abs() <= 40, "{p}: {}", squash(d));
+        }
+        assert!(squash(-2047) < 10 && squash(2047) > 4085);
+    }
+}
@@ -0,0 +1,106 @@
+//! Coverage — non-overlapping byte ranges claimed by pattern matches
+//!
+//! Both pattern learners let the first (highest-priority) match claim a
+//! region and drop later matches that overlap it. [`Coverage`] keeps the
+//! claimed ranges in a `BTreeMap` keyed by start instead of one flag per
+//! input byte, so memory follows the match count rather than the input size
+//! and each check is `O(log n)`.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::BTreeMap;
+
+/// Sorted, non-overlapping `start..end` ranges
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// start -> end
+    ranges: BTreeMap<usize, usize>,
+}
+
+impl Coverage {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `start..end` intersects a claimed range (empty ranges never do)
+    #[must_use]
+    pub fn overlaps(&self, start: usize, end: usize) -> bool {
+        // Ranges are disjoint, so only the last one starting before `end`
+        // can reach past `start`
+        start < end
+            && self
+                .ranges
+                .range(..end)
+                .next_back()
+                .is_some_and(|(_, &e)| e > start)
+    }
+
+    /// Claim `start..end` unless it overlaps; returns whether it was free
+    ///
+    /// Empty ranges are free but not recorded.
+    pub fn claim(&mut self, start: usize, end: usize) -> bool {
+        if self.overlaps(start, end) {
+            return false;
+        }
+        if start < end {
+            self.ranges.insert(start, end);
+        }
+        true
+    }
+
+    /// Number of claimed ranges
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Total bytes claimed
+    #[must_use]
+    pub fn covered_bytes(&self) -> usize {
+        self.ranges.iter().map(|(s, e)| e - s).sum()
+    }
+
+    /// Claimed ranges in order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.ranges.iter().map(|(&s, &e)| (s, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_and_overlap() {
+        let mut coverage = Coverage::new();
+        assert!(coverage.claim(10, 20));
+        assert!(coverage.claim(30, 40));
+        // Out-of-order claims between existing ranges
+        assert!(coverage.claim(20, 30));
+        assert!(coverage.claim(0, 10));
+
+        assert!(!coverage.claim(5, 6));
+        assert!(!coverage.claim(19, 21));
+        assert!(!coverage.claim(0, 100));
+        assert!(coverage.overlaps(39, 40));
+        assert!(!coverage.overlaps(40, 41));
+
+        // Empty ranges are always free and never stored
+        assert!(coverage.claim(15, 15));
+        assert_eq!(coverage.len(), 4);
+        assert_eq!(coverage.covered_bytes(), 40);
+        assert_eq!(
+            coverage.iter().collect::<Vec<_>>(),
+            [(0, 10), (10, 20), (20, 30), (30, 40)]
+        );
+    }
+}
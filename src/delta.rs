@@ -0,0 +1,429 @@
+//! Delta — differential compression against a reference snapshot
+//!
+//! Stores a new text as line-level edits relative to a reference text:
+//! runs of lines found in the reference become `Copy` ops, everything
+//! else is carried as `Insert`. The op list is Bincode + Zstd encoded.
+//!
+//! ```text
+//! MAGIC "ALICEDLT" (8) | VERSION (2) | REF_HASH u64 | REF_LEN u64 | ORIGINAL_LEN u64 | ZSTD(BINCODE(ops))
+//! ```
+//!
+//! The reference is identified by FNV-1a hash + length, so reconstructing
+//! with the wrong snapshot fails instead of producing garbage. The
+//! reference may itself be an `.atxt` archive (see [`resolve_reference`]).
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format_v3::DecodeLimits;
+use crate::level::Level;
+use crate::zstd_params;
+use crate::{fnv1a, to_u32, ALICETextError, Result, ALICE_TEXT_MAGIC, FNV_OFFSET};
+
+/// Magic bytes for delta archives
+pub const DELTA_MAGIC: &[u8; 8] = b"ALICEDLT";
+
+/// Delta format version
+pub const DELTA_VERSION: (u8, u8) = (1, 0);
+
+/// Fixed header size (magic + version + ref hash + ref len + original len)
+const HEADER_SIZE: usize = 8 + 2 + 8 + 8 + 8;
+
+/// Candidate reference positions examined per line (bounds worst-case time)
+const MAX_CANDIDATES: usize = 64;
+
+/// Shorter matches are cheaper to insert than to reference
+const MIN_COPY_BYTES: usize = 16;
+
+/// Edit operation (line granularity)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum DeltaOp {
+    /// Copy `count` reference lines starting at line `start`
+    Copy { start: u32, count: u32 },
+    /// Literal text (one or more complete lines)
+    Insert(String),
+}
+
+/// Delta encoding statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeltaStats {
+    /// Lines taken from the reference
+    pub copied_lines: usize,
+    /// Lines stored literally
+    pub inserted_lines: usize,
+    /// Encoded delta size in bytes
+    pub delta_size: usize,
+}
+
+/// Split into lines, keeping terminators so reconstruction is byte-exact
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Build the op list for `new_text` against `reference`
+fn diff(reference: &str, new_text: &str) -> Result<(Vec<DeltaOp>, DeltaStats)> {
+    let ref_lines = lines(reference);
+    let new_lines = lines(new_text);
+    // Copy ops address reference lines as u32; every cast below is bounded by this
+    to_u32(ref_lines.len(), "delta reference line count")?;
+
+    let mut index: HashMap<&str, Vec<u32>> = HashMap::new();
+    for (i, line) in ref_lines.iter().enumerate() {
+        index.entry(line).or_default().push(i as u32);
+    }
+
+    let mut ops: Vec<DeltaOp> = Vec::new();
+    let mut stats = DeltaStats::default();
+    let mut pending = String::new();
+    let mut i = 0;
+
+    while i < new_lines.len() {
+        // Longest reference run starting at a candidate position
+        let mut best: Option<(usize, usize, usize)> = None; // (start, count, bytes)
+        if let Some(candidates) = index.get(new_lines[i]) {
+            for &c in candidates.iter().take(MAX_CANDIDATES) {
+                let c = c as usize;
+                let mut count = 0;
+                let mut bytes = 0;
+                while c + count < ref_lines.len()
+                    && i + count < new_lines.len()
+                    && ref_lines[c + count] == new_lines[i + count]
+                {
+                    bytes += new_lines[i + count].len();
+                    count += 1;
+                }
+                if best.is_none_or(|(_, _, b)| bytes > b) {
+                    best = Some((c, count, bytes));
+                }
+            }
+        }
+
+        match best {
+            Some((start, count, bytes)) if bytes >= MIN_COPY_BYTES => {
+                if !pending.is_empty() {
+                    ops.push(DeltaOp::Insert(std::mem::take(&mut pending)));
+                }
+                // Extend the previous copy when the runs are contiguous
+                match ops.last_mut() {
+                    Some(DeltaOp::Copy {
+                        start: prev_start,
+                        count: prev_count,
+                    }) if (*prev_start + *prev_count) as usize == start => {
+                        *prev_count += count as u32;
+                    }
+                    _ => ops.push(DeltaOp::Copy {
+                        start: start as u32,
+                        count: count as u32,
+                    }),
+                }
+                stats.copied_lines += count;
+                i += count;
+            }
+            _ => {
+                pending.push_str(new_lines[i]);
+                stats.inserted_lines += 1;
+                i += 1;
+            }
+        }
+    }
+    if !pending.is_empty() {
+        ops.push(DeltaOp::Insert(pending));
+    }
+
+    Ok((ops, stats))
+}
+
+/// Compress `new_text` as edits relative to `reference`
+///
+/// # Errors
+///
+/// Returns an error if Bincode serialization or Zstd compression fails, or
+/// the reference has more than `u32::MAX` lines.
+pub fn compress_against(
+    reference: &str,
+    new_text: &str,
+    level: impl Into<Level>,
+) -> Result<Vec<u8>> {
+    compress_against_with_stats(reference, new_text, level).map(|(data, _)| data)
+}
+
+/// [`compress_against`] that also reports copy/insert statistics
+///
+/// # Errors
+///
+/// Returns an error if Bincode serialization or Zstd compression fails.
+pub fn compress_against_with_stats(
+    reference: &str,
+    new_text: &str,
+    level: impl Into<Level>,
+) -> Result<(Vec<u8>, DeltaStats)> {
+    let (ops, mut stats) = diff(reference, new_text)?;
+
+    let serialized = bincode::serialize(&ops)
+        .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+    let compressed = zstd::encode_all(serialized.as_slice(), level.into().zstd_level())
+        .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")))?;
+
+    let mut output = Vec::with_capacity(HEADER_SIZE + compressed.len());
+    output.extend_from_slice(DELTA_MAGIC);
+    output.push(DELTA_VERSION.0);
+    output.push(DELTA_VERSION.1);
+    output.extend_from_slice(&fnv1a(reference.as_bytes(), FNV_OFFSET).to_le_bytes());
+    output.extend_from_slice(&(reference.len() as u64).to_le_bytes());
+    output.extend_from_slice(&(new_text.len() as u64).to_le_bytes());
+    output.extend_from_slice(&compressed);
+
+    stats.delta_size = output.len();
+    Ok((output, stats))
+}
+
+/// Reconstruct the text from a delta and the reference it was built against
+///
+/// # Errors
+///
+/// Returns an error if the data is not a delta archive, the reference does not
+/// match the one used for compression, or decoding fails.
+pub fn decompress_with_reference(reference: &str, data: &[u8]) -> Result<String> {
+    decompress_with_reference_limited(reference, data, DecodeLimits::new())
+}
+
+/// [`decompress_with_reference`] under caller-chosen output ceilings
+///
+/// Both the decoded op list and the declared output length are held to
+/// [`DecodeLimits::max_total_bytes`].
+///
+/// # Errors
+///
+/// As [`decompress_with_reference`], plus [`ALICETextError::LimitExceeded`]
+/// once a ceiling is passed.
+pub fn decompress_with_reference_limited(
+    reference: &str,
+    data: &[u8],
+    limits: DecodeLimits,
+) -> Result<String> {
+    if data.len() < HEADER_SIZE {
+        return Err(ALICETextError::DecompressionError(
+            "Data too short".to_string(),
+        ));
+    }
+    if &data[0..8] != DELTA_MAGIC {
+        return Err(ALICETextError::InvalidMagic);
+    }
+    if data[8] != DELTA_VERSION.0 {
+        return Err(ALICETextError::InvalidVersion(data[8], data[9]));
+    }
+
+    let read_u64 = |at: usize| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&data[at..at + 8]);
+        u64::from_le_bytes(buf)
+    };
+    let ref_hash = read_u64(10);
+    let ref_len = read_u64(18);
+    let original_len = read_u64(26);
+    limits.check_total(original_len)?;
+    let original_len = original_len as usize;
+
+    if ref_len != reference.len() as u64 || ref_hash != fnv1a(reference.as_bytes(), FNV_OFFSET) {
+        return Err(ALICETextError::DecompressionError(
+            "Reference mismatch: delta was built against a different snapshot".to_string(),
+        ));
+    }
+
+    let decompressed =
+        zstd_params::decompress(&data[HEADER_SIZE..], &[], 0, limits.max_total_bytes)?;
+    let ops: Vec<DeltaOp> = bincode::deserialize(&decompressed)
+        .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+
+    let ref_lines = lines(reference);
+    // The header length is untrusted: reserve no more than the ops can plausibly produce
+    let mut output = String::with_capacity(original_len.min(reference.len() + decompressed.len()));
+    let overrun = |len: usize| {
+        ALICETextError::DecompressionError(format!(
+            "Length mismatch: output passes the declared {original_len} bytes at {len}"
+        ))
+    };
+    for op in &ops {
+        match op {
+            DeltaOp::Copy { start, count } => {
+                let start = *start as usize;
+                let end = start.checked_add(*count as usize);
+                let run = end
+                    .and_then(|end| ref_lines.get(start..end))
+                    .ok_or_else(|| {
+                        ALICETextError::DecompressionError(format!(
+                            "Copy out of range: {count} lines from line {start}"
+                        ))
+                    })?;
+                for line in run {
+                    if output.len() + line.len() > original_len {
+                        return Err(overrun(output.len() + line.len()));
+                    }
+                    output.push_str(line);
+                }
+            }
+            DeltaOp::Insert(text) => {
+                if output.len() + text.len() > original_len {
+                    return Err(overrun(output.len() + text.len()));
+                }
+                output.push_str(text);
+            }
+        }
+    }
+
+    if output.len() != original_len {
+        return Err(ALICETextError::DecompressionError(format!(
+            "Length mismatch: expected {original_len}, got {}",
+            output.len()
+        )));
+    }
+    Ok(output)
+}
+
+/// Turn reference bytes into text, decompressing `.atxt` archives (v1/v2/v3)
+///
+/// # Errors
+///
+/// Returns an error if an archive fails to decompress or plain input is not UTF-8.
+pub fn resolve_reference(data: &[u8]) -> Result<String> {
+    if data.len() >= 10 && &data[0..8] == ALICE_TEXT_MAGIC {
+        return if data[8] >= 3 {
+            crate::decompress_v3(data)
+        } else {
+            crate::decompress(data)
+        };
+    }
+    String::from_utf8(data.to_vec())
+        .map_err(|e| ALICETextError::DecompressionError(format!("Reference is not UTF-8: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_v3::CompressionLevel;
+
+    fn config(version: u32, port: u32) -> String {
+        let mut text = String::new();
+        for i in 0..200 {
+            text.push_str(&format!("service.worker_{i}.enabled = true\n"));
+        }
+        text.push_str(&format!("service.version = {version}\n"));
+        text.push_str(&format!("service.port = {port}\n"));
+        for i in 0..200 {
+            text.push_str(&format!("service.limit_{i} = {}\n", i * 10));
+        }
+        text
+    }
+
+    #[test]
+    fn test_delta_roundtrip() {
+        let reference = config(1, 8080);
+        let new_text = config(2, 8080);
+
+        let (delta, stats) =
+            compress_against_with_stats(&reference, &new_text, CompressionLevel::Balanced).unwrap();
+        assert_eq!(stats.inserted_lines, 1);
+        assert_eq!(stats.copied_lines, 401);
+        assert!(delta.len() < 100, "delta is {} bytes", delta.len());
+
+        let restored = decompress_with_reference(&reference, &delta).unwrap();
+        assert_eq!(restored, new_text);
+    }
+
+    #[test]
+    fn test_delta_unrelated_and_empty() {
+        for (reference, new_text) in [
+            ("", "brand new text\nwithout trailing newline"),
+            ("old line that is long enough\n", ""),
+            ("a\nb\nc\n", "a\nb\nc\n"),
+        ] {
+            let delta = compress_against(reference, new_text, CompressionLevel::Fast).unwrap();
+            assert_eq!(
+                decompress_with_reference(reference, &delta).unwrap(),
+                new_text
+            );
+        }
+    }
+
+    #[test]
+    fn test_delta_wrong_reference() {
+        let reference = config(1, 8080);
+        let delta = compress_against(&reference, &config(2, 9090), CompressionLevel::Fast).unwrap();
+
+        let result = decompress_with_reference(&config(1, 8081), &delta);
+        assert!(matches!(
+            result,
+            Err(ALICETextError::DecompressionError(msg)) if msg.contains("Reference mismatch")
+        ));
+    }
+
+    #[test]
+    fn test_delta_crafted_ops_rejected() {
+        let reference = config(1, 8080);
+        let craft = |ops: &[DeltaOp], original_len: u64| {
+            let mut data = Vec::new();
+            data.extend_from_slice(DELTA_MAGIC);
+            data.extend_from_slice(&[DELTA_VERSION.0, DELTA_VERSION.1]);
+            data.extend_from_slice(&fnv1a(reference.as_bytes(), FNV_OFFSET).to_le_bytes());
+            data.extend_from_slice(&(reference.len() as u64).to_le_bytes());
+            data.extend_from_slice(&original_len.to_le_bytes());
+            let encoded = bincode::serialize(ops).unwrap();
+            data.extend_from_slice(&zstd::encode_all(&encoded[..], 1).unwrap());
+            decompress_with_reference(&reference, &data)
+        };
+
+        // `start + count` past u32::MAX, and copies past the declared length
+        let wrapping = DeltaOp::Copy {
+            start: u32::MAX,
+            count: 2,
+        };
+        assert!(craft(&[wrapping], 10).is_err());
+        let all = DeltaOp::Copy {
+            start: 0,
+            count: lines(&reference).len() as u32,
+        };
+        let repeated = vec![all.clone(); 1000];
+        assert!(matches!(
+            craft(&repeated, reference.len() as u64),
+            Err(ALICETextError::DecompressionError(msg)) if msg.contains("Length mismatch")
+        ));
+        assert!(craft(&[DeltaOp::Insert("x".repeat(64))], u64::MAX).is_err());
+        assert_eq!(
+            craft(&[all.clone(), all], 2 * reference.len() as u64).unwrap(),
+            reference.repeat(2)
+        );
+    }
+
+    #[test]
+    fn test_delta_decode_limits() {
+        let reference = config(1, 8080);
+        let new_text = format!("{reference}{}", "fresh line\n".repeat(4096));
+        let delta = compress_against(&reference, &new_text, CompressionLevel::Fast).unwrap();
+
+        let roomy = DecodeLimits::new().with_max_total_bytes(1 << 20);
+        assert_eq!(
+            decompress_with_reference_limited(&reference, &delta, roomy).unwrap(),
+            new_text
+        );
+        let tight = DecodeLimits::new().with_max_total_bytes(1 << 10);
+        assert!(matches!(
+            decompress_with_reference_limited(&reference, &delta, tight),
+            Err(ALICETextError::LimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_resolve_reference_archive() {
+        let reference = config(1, 8080);
+        let v2 = crate::compress_tuned(&reference, crate::CompressionMode::Fast).unwrap();
+        let v3 = crate::compress_v3(&reference, CompressionLevel::Fast).unwrap();
+
+        assert_eq!(resolve_reference(&v2).unwrap(), reference);
+        assert_eq!(resolve_reference(&v3).unwrap(), reference);
+        assert_eq!(resolve_reference(reference.as_bytes()).unwrap(), reference);
+    }
+}
@@ -1,35 +1,65 @@
 //! Dialogue — game dialogue compression and localization
 //!
 //! Provides compressed storage for game dialogue text with:
-//! - Speaker dictionary (name deduplication)
+//! - Speaker dictionary (name deduplication, up to [`MAX_SPEAKERS`] names)
 //! - Ruby/furigana annotations for CJK text
 //! - O(1) dialogue lookup (contiguous ID) or O(log n) (sparse ID)
+//! - Validating table builder with a duplicate-ID policy
+//! - Inline markup (`<color=#f00>`, `[pause=500]`, `<icon=sword/>`) parsed
+//!   into text/tag segments that render back to the exact source string
 //! - Delta-based localization (store only differences from base locale)
+//! - Case-insensitive / regex search per locale, optionally trigram-indexed
+//! - Stable per-entry content hashes, a Merkle table root and line diffs
 //! - Bincode + Zstd compressed wire format
 //!
+//! Localization archives (type `0x03`, since 1.1) store the base table and
+//! each locale delta as separately compressed sections behind a directory,
+//! so one locale can be loaded or replaced without touching the others:
+//!
+//! ```text
+//! MAGIC "ALICEDLG" (8) | VERSION (2) | TYPE 0x03 | MODE | COUNT u32 LE
+//! | { LOCALE u16 LE | KIND u8 (0 base, 1 delta) | LEN u32 LE } x COUNT
+//! | SECTIONS (zstd(bincode), directory order, base first)
+//! ```
+//!
+//! Type `0x02` (one blob for the whole table, 1.0) is still read.
+//!
 //! License: BSL 1.1
 //! Author: Moroya Sakamoto
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::sync::OnceLock;
 
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::format_v3::DecodeLimits;
+use crate::zstd_params;
+use crate::{fnv1a, FNV_OFFSET};
+
 /// Magic bytes for dialogue archive format
 pub const DIALOGUE_MAGIC: &[u8; 8] = b"ALICEDLG";
 
 /// Dialogue format version
-pub const DIALOGUE_VERSION: (u8, u8) = (1, 0);
+pub const DIALOGUE_VERSION: (u8, u8) = (1, 1);
 
-// ── FNV-1a (file-local) ───────────────────────────────────────
-#[inline(always)]
-fn fnv1a(data: &[u8]) -> u64 {
-    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
-    for &b in data {
-        h ^= b as u64;
-        h = h.wrapping_mul(0x0000_0100_0000_01b3);
-    }
-    h
-}
+/// Container types (byte 10)
+const TYPE_TABLE: u8 = 0x01;
+const TYPE_LOCALIZATION_BLOB: u8 = 0x02;
+const TYPE_LOCALIZATION: u8 = 0x03;
+
+/// Section kinds in a localization directory
+const SECTION_BASE: u8 = 0;
+const SECTION_DELTA: u8 = 1;
+
+/// Directory entry size (locale + kind + length)
+const DIRECTORY_ENTRY_SIZE: usize = 2 + 1 + 4;
+
+/// Speakers a [`SpeakerDictionary`] can hold (`DialogueEntry::speaker` is a `u16`)
+pub const MAX_SPEAKERS: usize = u16::MAX as usize + 1;
 
 // ── Locale ─────────────────────────────────────────────────────
 
@@ -78,6 +108,218 @@ pub struct DialogueEntry {
     pub ruby: Option<Vec<RubyAnnotation>>,
 }
 
+impl DialogueEntry {
+    /// Hash of everything that affects how the line is spoken
+    /// (id, speaker, text and ruby readings)
+    ///
+    /// Stable across releases (FNV-1a over a fixed layout), so it can key
+    /// caches of derived assets such as voice clips or glyph atlases.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        let mut buf = Vec::with_capacity(6 + self.text.len());
+        buf.extend_from_slice(&self.id.to_le_bytes());
+        buf.extend_from_slice(&self.speaker.to_le_bytes());
+        buf.extend_from_slice(self.text.as_bytes());
+        for ruby in self.ruby.iter().flatten() {
+            buf.push(0);
+            buf.extend_from_slice(&ruby.base_start.to_le_bytes());
+            buf.extend_from_slice(&ruby.base_len.to_le_bytes());
+            buf.extend_from_slice(ruby.ruby_text.as_bytes());
+        }
+        fnv1a(&buf, FNV_OFFSET)
+    }
+
+    /// The text split into plain runs and markup tags
+    #[must_use]
+    pub fn segments(&self) -> SegmentedText {
+        SegmentedText::parse(&self.text)
+    }
+}
+
+// ── Markup Segments ────────────────────────────────────────────
+
+/// Delimiters of an inline markup tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TagStyle {
+    /// `<name=value>`
+    Angle,
+    /// `[name=value]`
+    Square,
+}
+
+impl TagStyle {
+    const fn delimiters(self) -> (char, char) {
+        match self {
+            Self::Angle => ('<', '>'),
+            Self::Square => ('[', ']'),
+        }
+    }
+}
+
+/// Inline markup tag: `<name>`, `<name=value>`, `</name>` or `<name/>`
+///
+/// Names are ASCII alphanumerics, `_` and `-`; the value runs to the
+/// closing delimiter (no newline or delimiter inside).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MarkupTag {
+    pub style: TagStyle,
+    pub name: String,
+    pub value: Option<String>,
+    /// `</name>`
+    pub closing: bool,
+    /// `<name/>`
+    pub self_closing: bool,
+}
+
+impl MarkupTag {
+    /// Parse a tag at the start of `s`, returning it and its byte length
+    fn parse_prefix(s: &str) -> Option<(Self, usize)> {
+        let style = match s.chars().next()? {
+            '<' => TagStyle::Angle,
+            '[' => TagStyle::Square,
+            _ => return None,
+        };
+        let (open, close) = style.delimiters();
+        let end = s[1..].find([open, close, '\n'])? + 1;
+        if !s[end..].starts_with(close) {
+            return None;
+        }
+        let body = &s[1..end];
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(rest) => (true, rest),
+            None => (false, body),
+        };
+        let (self_closing, body) = match body.strip_suffix('/') {
+            Some(rest) if !closing => (true, rest),
+            _ => (false, body),
+        };
+        let (name, value) = match body.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (body, None),
+        };
+        let valid_name = !name.is_empty()
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+        if !valid_name || (closing && value.is_some()) {
+            return None;
+        }
+        let tag = Self {
+            style,
+            name: name.to_string(),
+            value,
+            closing,
+            self_closing,
+        };
+        Some((tag, end + 1))
+    }
+}
+
+impl fmt::Display for MarkupTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (open, close) = self.style.delimiters();
+        write!(f, "{open}")?;
+        if self.closing {
+            f.write_str("/")?;
+        }
+        f.write_str(&self.name)?;
+        if let Some(value) = &self.value {
+            write!(f, "={value}")?;
+        }
+        if self.self_closing {
+            f.write_str("/")?;
+        }
+        write!(f, "{close}")
+    }
+}
+
+/// One run of a segmented dialogue line
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MarkupSegment {
+    Text(String),
+    Tag(MarkupTag),
+}
+
+/// Dialogue text as plain runs and tags, in order
+///
+/// `SegmentedText::parse(s).to_string() == s` for every input: brackets
+/// that do not form a valid tag stay in the text runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SegmentedText {
+    pub segments: Vec<MarkupSegment>,
+}
+
+impl SegmentedText {
+    /// Split `text` into text runs and markup tags
+    #[must_use]
+    pub fn parse(text: &str) -> Self {
+        let mut segments = Vec::new();
+        let mut run_start = 0;
+        let mut pos = 0;
+        while let Some(offset) = text[pos..].find(['<', '[']) {
+            let at = pos + offset;
+            match MarkupTag::parse_prefix(&text[at..]) {
+                Some((tag, len)) => {
+                    if run_start < at {
+                        segments.push(MarkupSegment::Text(text[run_start..at].to_string()));
+                    }
+                    segments.push(MarkupSegment::Tag(tag));
+                    pos = at + len;
+                    run_start = pos;
+                }
+                None => pos = at + 1,
+            }
+        }
+        if run_start < text.len() {
+            segments.push(MarkupSegment::Text(text[run_start..].to_string()));
+        }
+        Self { segments }
+    }
+
+    /// Text with all tags removed (what the player reads)
+    #[must_use]
+    pub fn plain_text(&self) -> String {
+        self.segments
+            .iter()
+            .filter_map(|s| match s {
+                MarkupSegment::Text(text) => Some(text.as_str()),
+                MarkupSegment::Tag(_) => None,
+            })
+            .collect()
+    }
+
+    /// Tags in order
+    pub fn tags(&self) -> impl Iterator<Item = &MarkupTag> {
+        self.segments.iter().filter_map(|s| match s {
+            MarkupSegment::Tag(tag) => Some(tag),
+            MarkupSegment::Text(_) => None,
+        })
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+/// Renders the original markup string
+impl fmt::Display for SegmentedText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                MarkupSegment::Text(text) => f.write_str(text)?,
+                MarkupSegment::Tag(tag) => write!(f, "{tag}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
 // ── Speaker Dictionary ─────────────────────────────────────────
 
 /// Speaker name dictionary with deduplication
@@ -99,14 +341,19 @@ impl SpeakerDictionary {
 
     /// Insert a speaker name, returning its index.
     /// Deduplicates: returns existing index if already present.
-    pub fn insert(&mut self, name: &str) -> u16 {
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if a new name would need an index past `u16::MAX`
+    /// (check [`Self::remaining`] before bulk imports).
+    pub fn insert(&mut self, name: &str) -> crate::Result<u16> {
         if let Some(&idx) = self.index.get(name) {
-            return idx;
+            return Ok(idx);
         }
-        let idx = self.names.len() as u16;
+        let idx = crate::to_u16(self.names.len(), "speaker count")?;
         self.names.push(name.to_string());
         self.index.insert(name.to_string(), idx);
-        idx
+        Ok(idx)
     }
 
     /// Look up speaker name by index
@@ -128,12 +375,43 @@ impl SpeakerDictionary {
         self.names.is_empty()
     }
 
+    /// Maximum number of speakers ([`MAX_SPEAKERS`])
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        MAX_SPEAKERS
+    }
+
+    /// New names that can still be inserted
+    #[must_use]
+    pub const fn remaining(&self) -> usize {
+        MAX_SPEAKERS.saturating_sub(self.names.len())
+    }
+
+    /// Whether no new name can be inserted
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.remaining() == 0
+    }
+
     /// Rebuild the index after deserialization
-    pub fn rebuild_index(&mut self) {
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if the dictionary holds more than
+    /// [`MAX_SPEAKERS`] names (corrupt or foreign data).
+    pub fn rebuild_index(&mut self) -> crate::Result<()> {
+        if self.names.len() > MAX_SPEAKERS {
+            return Err(crate::ALICETextError::LimitExceeded {
+                field: "speaker count",
+                value: self.names.len() as u64,
+                limit: MAX_SPEAKERS as u64,
+            });
+        }
         self.index.clear();
-        for (i, name) in self.names.iter().enumerate() {
-            self.index.insert(name.clone(), i as u16);
+        for (i, name) in (0..=u16::MAX).zip(&self.names) {
+            self.index.insert(name.clone(), i);
         }
+        Ok(())
     }
 }
 
@@ -156,6 +434,9 @@ pub struct DialogueTable {
     contiguous: bool,
     /// Content hash
     pub content_hash: u64,
+    /// Parsed markup per entry, built on first [`DialogueTable::segments`]
+    #[serde(skip)]
+    segments: OnceLock<Vec<SegmentedText>>,
 }
 
 impl DialogueTable {
@@ -166,17 +447,27 @@ impl DialogueTable {
             entries: Vec::new(),
             contiguous: true,
             content_hash: 0,
+            segments: OnceLock::new(),
         }
     }
 
+    /// Start a validating builder (any insert order, duplicate-ID policy)
+    #[must_use]
+    pub fn builder() -> DialogueTableBuilder {
+        DialogueTableBuilder::new()
+    }
+
     /// Add a dialogue entry
+    ///
+    /// Entries must arrive in ascending, unique id order for sparse lookups
+    /// to work; use [`Self::builder`] when the input order is not guaranteed.
     pub fn add(&mut self, entry: DialogueEntry) {
         // Check contiguity
-        let expected_id = self.entries.len() as u32;
-        if entry.id != expected_id {
+        if entry.id as usize != self.entries.len() {
             self.contiguous = false;
         }
         self.entries.push(entry);
+        self.segments = OnceLock::new();
         self.update_hash();
     }
 
@@ -194,6 +485,23 @@ impl DialogueTable {
         }
     }
 
+    /// Parsed markup of dialogue `id`
+    ///
+    /// Every entry is parsed once, on the first call after loading or
+    /// adding, so engines can render segments without re-parsing.
+    #[must_use]
+    pub fn segments(&self, id: u32) -> Option<&SegmentedText> {
+        let index = if self.contiguous {
+            id as usize
+        } else {
+            self.entries.binary_search_by_key(&id, |e| e.id).ok()?
+        };
+        self.entries.get(index)?;
+        self.segments
+            .get_or_init(|| self.entries.iter().map(DialogueEntry::segments).collect())
+            .get(index)
+    }
+
     /// Number of entries
     #[must_use]
     pub const fn len(&self) -> usize {
@@ -211,6 +519,44 @@ impl DialogueTable {
         self.contiguous
     }
 
+    /// Check the lookup invariants: ids ascending and unique, speakers in
+    /// the dictionary, ruby spans inside their text
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDialogue` with the first violation found.
+    pub fn validate(&self) -> crate::Result<()> {
+        for pair in self.entries.windows(2) {
+            if pair[1].id <= pair[0].id {
+                return Err(if pair[1].id == pair[0].id {
+                    DialogueViolation::DuplicateId(pair[1].id)
+                } else {
+                    DialogueViolation::Unsorted(pair[1].id)
+                }
+                .into());
+            }
+        }
+        for entry in &self.entries {
+            if self.speakers.get(entry.speaker).is_none() {
+                return Err(DialogueViolation::UnknownSpeaker {
+                    id: entry.id,
+                    speaker: entry.speaker,
+                }
+                .into());
+            }
+            let chars = entry.text.chars().count();
+            if entry
+                .ruby
+                .iter()
+                .flatten()
+                .any(|r| usize::from(r.base_start) + usize::from(r.base_len) > chars)
+            {
+                return Err(DialogueViolation::RubyOutOfRange(entry.id).into());
+            }
+        }
+        Ok(())
+    }
+
     /// Collect all unique characters across all dialogue text.
     /// Useful for font atlas preloading.
     #[must_use]
@@ -226,13 +572,238 @@ impl DialogueTable {
         self.entries.iter()
     }
 
+    /// [`DialogueEntry::content_hash`] of dialogue `id`
+    #[must_use]
+    pub fn entry_hash(&self, id: u32) -> Option<u64> {
+        self.get(id).map(DialogueEntry::content_hash)
+    }
+
+    /// `(id, content hash)` of every entry, by id
+    #[must_use]
+    pub fn entry_hashes(&self) -> Vec<(u32, u64)> {
+        let mut hashes: Vec<(u32, u64)> = self
+            .entries
+            .iter()
+            .map(|e| (e.id, e.content_hash()))
+            .collect();
+        hashes.sort_unstable_by_key(|&(id, _)| id);
+        hashes
+    }
+
+    /// Merkle root over the entry hashes in id order
+    ///
+    /// Equal roots mean every line (id, speaker index, text, ruby) is
+    /// unchanged; speaker names are not covered.
+    #[must_use]
+    pub fn merkle_root(&self) -> u64 {
+        let leaves: Vec<u64> = self.entry_hashes().into_iter().map(|(_, h)| h).collect();
+        merkle_root(&leaves)
+    }
+
+    /// Lines added, removed or changed since `previous`
+    #[must_use]
+    pub fn diff(&self, previous: &Self) -> TableDiff {
+        let mut diff = TableDiff::default();
+        if self.merkle_root() == previous.merkle_root() {
+            return diff;
+        }
+        let old: HashMap<u32, u64> = previous.entry_hashes().into_iter().collect();
+        let new = self.entry_hashes();
+        for &(id, hash) in &new {
+            match old.get(&id) {
+                None => diff.added.push(id),
+                Some(&h) if h != hash => diff.changed.push(id),
+                Some(_) => {}
+            }
+        }
+        let new: HashMap<u32, u64> = new.into_iter().collect();
+        diff.removed = previous
+            .entry_hashes()
+            .into_iter()
+            .filter(|(id, _)| !new.contains_key(id))
+            .map(|(id, _)| id)
+            .collect();
+        diff
+    }
+
     fn update_hash(&mut self) {
         let mut buf = Vec::new();
         for entry in &self.entries {
             buf.extend_from_slice(&entry.id.to_le_bytes());
             buf.extend_from_slice(entry.text.as_bytes());
         }
-        self.content_hash = fnv1a(&buf);
+        self.content_hash = fnv1a(&buf, FNV_OFFSET);
+    }
+}
+
+// ── Content Addressing ─────────────────────────────────────────
+
+/// Dialogue ids that differ between two versions of a table (each by id)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableDiff {
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
+    /// Same id, different content hash
+    pub changed: Vec<u32>,
+}
+
+impl TableDiff {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Binary Merkle root of `leaves` (FNV-1a, odd node promoted unchanged)
+///
+/// Inner nodes hash `0x01 | left | right` so they cannot collide with a
+/// leaf; the empty tree hashes to FNV-1a of nothing.
+#[must_use]
+pub fn merkle_root(leaves: &[u64]) -> u64 {
+    if leaves.is_empty() {
+        return fnv1a(&[], FNV_OFFSET);
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut buf = [0u8; 17];
+                    buf[0] = 0x01;
+                    buf[1..9].copy_from_slice(&left.to_le_bytes());
+                    buf[9..].copy_from_slice(&right.to_le_bytes());
+                    fnv1a(&buf, FNV_OFFSET)
+                }
+                [single] => *single,
+                _ => unreachable!("chunks(2)"),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+// ── Dialogue Table Builder ─────────────────────────────────────
+
+/// What [`DialogueTableBuilder::build`] does with a repeated dialogue id
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Fail with [`DialogueViolation::DuplicateId`]
+    #[default]
+    Reject,
+    /// The entry added last wins
+    Overwrite,
+    /// The entry added first wins
+    KeepFirst,
+}
+
+/// A dialogue table invariant broken by the input
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DialogueViolation {
+    #[error("duplicate dialogue id {0}")]
+    DuplicateId(u32),
+    #[error("dialogue id {0} out of order")]
+    Unsorted(u32),
+    #[error("dialogue {id}: speaker {speaker} is not in the dictionary")]
+    UnknownSpeaker { id: u32, speaker: u16 },
+    #[error("dialogue {0}: ruby annotation past the end of the text")]
+    RubyOutOfRange(u32),
+}
+
+/// Collects entries in any order, then sorts, resolves duplicate ids and
+/// validates into a [`DialogueTable`]
+#[derive(Debug, Clone, Default)]
+pub struct DialogueTableBuilder {
+    speakers: SpeakerDictionary,
+    entries: Vec<DialogueEntry>,
+    duplicates: DuplicatePolicy,
+}
+
+impl DialogueTableBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the duplicate-id policy (default: reject)
+    #[must_use]
+    pub const fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Start from an existing speaker dictionary
+    #[must_use]
+    pub fn with_speakers(mut self, speakers: SpeakerDictionary) -> Self {
+        self.speakers = speakers;
+        self
+    }
+
+    /// Register a speaker name, returning its index
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` past [`MAX_SPEAKERS`] names.
+    pub fn speaker(&mut self, name: &str) -> crate::Result<u16> {
+        self.speakers.insert(name)
+    }
+
+    /// Queue an entry (checked in [`Self::build`])
+    pub fn add(&mut self, entry: DialogueEntry) -> &mut Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Entries queued so far
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sort by id, apply the duplicate policy and validate
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidDialogue` for a duplicate id (under
+    /// [`DuplicatePolicy::Reject`]), an unknown speaker or a ruby span past
+    /// the end of its text.
+    pub fn build(self) -> crate::Result<DialogueTable> {
+        let mut entries = self.entries;
+        // Stable: equal ids stay in insertion order
+        entries.sort_by_key(|e| e.id);
+        match self.duplicates {
+            DuplicatePolicy::Reject => {
+                if let Some(pair) = entries.windows(2).find(|p| p[0].id == p[1].id) {
+                    return Err(DialogueViolation::DuplicateId(pair[0].id).into());
+                }
+            }
+            DuplicatePolicy::KeepFirst => entries.dedup_by_key(|e| e.id),
+            DuplicatePolicy::Overwrite => entries.dedup_by(|later, kept| {
+                if later.id == kept.id {
+                    std::mem::swap(later, kept);
+                    true
+                } else {
+                    false
+                }
+            }),
+        }
+
+        let contiguous = (0u32..).zip(&entries).all(|(i, e)| e.id == i);
+        let mut table = DialogueTable {
+            speakers: self.speakers,
+            entries,
+            contiguous,
+            content_hash: 0,
+            segments: OnceLock::new(),
+        };
+        table.validate()?;
+        table.update_hash();
+        Ok(table)
     }
 }
 
@@ -298,7 +869,7 @@ impl DeltaTable {
                 buf.extend_from_slice(e.text.as_bytes());
             }
         }
-        self.content_hash = fnv1a(&buf);
+        self.content_hash = fnv1a(&buf, FNV_OFFSET);
     }
 }
 
@@ -313,6 +884,9 @@ pub struct LocalizationTable {
     pub base_table: DialogueTable,
     /// Per-locale delta tables
     pub locale_deltas: HashMap<LocaleId, DeltaTable>,
+    /// Search indexes from [`LocalizationTable::build_search_index`]
+    #[serde(skip)]
+    search_indexes: HashMap<LocaleId, SearchIndex>,
 }
 
 impl LocalizationTable {
@@ -322,6 +896,7 @@ impl LocalizationTable {
             base_locale,
             base_table: DialogueTable::new(),
             locale_deltas: HashMap::new(),
+            search_indexes: HashMap::new(),
         }
     }
 
@@ -372,6 +947,200 @@ impl LocalizationTable {
     }
 }
 
+// ── Search ─────────────────────────────────────────────────────
+
+/// What [`LocalizationTable::search`] looks for
+#[derive(Debug, Clone, Copy)]
+pub enum SearchQuery<'a> {
+    /// Case-insensitive substring (both sides lowercase-folded)
+    Substring(&'a str),
+    /// Regex over the original text
+    Regex(&'a Regex),
+}
+
+/// One match: dialogue id and byte span in that entry's text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub id: u32,
+    pub span: Range<usize>,
+}
+
+/// A line lowercase-folded for case-insensitive matching
+#[derive(Debug, Clone)]
+struct FoldedLine {
+    id: u32,
+    folded: String,
+    /// Original byte offset per folded byte (plus end), only when folding
+    /// changed a character's length
+    offsets: Option<Vec<usize>>,
+}
+
+impl FoldedLine {
+    fn new(entry: &DialogueEntry) -> Self {
+        let folded = entry.text.to_lowercase();
+        let resized = |c: char| c.to_lowercase().map(char::len_utf8).sum::<usize>() != c.len_utf8();
+        let offsets = entry.text.chars().any(resized).then(|| {
+            let mut offsets = Vec::with_capacity(folded.len() + 1);
+            for (at, c) in entry.text.char_indices() {
+                let len: usize = c.to_lowercase().map(char::len_utf8).sum();
+                offsets.extend(std::iter::repeat_n(at, len));
+            }
+            offsets.push(entry.text.len());
+            offsets
+        });
+        Self {
+            id: entry.id,
+            folded,
+            offsets,
+        }
+    }
+
+    /// Map a folded byte span back to the original text
+    fn original_span(&self, start: usize, end: usize) -> Range<usize> {
+        match &self.offsets {
+            Some(offsets) => offsets[start]..offsets[end],
+            None => start..end,
+        }
+    }
+
+    fn find(&self, needle: &str, hits: &mut Vec<SearchHit>) {
+        for (at, m) in self.folded.match_indices(needle) {
+            hits.push(SearchHit {
+                id: self.id,
+                span: self.original_span(at, at + m.len()),
+            });
+        }
+    }
+}
+
+/// Lowercase-folded trigram index over one locale's effective lines
+#[derive(Debug, Clone)]
+struct SearchIndex {
+    /// Content hashes of the base and delta tables it was built from
+    built_from: (u64, u64),
+    lines: Vec<FoldedLine>,
+    /// Folded byte trigram -> line indices (ascending)
+    trigrams: HashMap<[u8; 3], Vec<u32>>,
+}
+
+impl SearchIndex {
+    fn build(lines: Vec<FoldedLine>, built_from: (u64, u64)) -> Self {
+        let mut trigrams: HashMap<[u8; 3], Vec<u32>> = HashMap::new();
+        for (i, line) in (0u32..).zip(&lines) {
+            for gram in line.folded.as_bytes().windows(3) {
+                let postings = trigrams.entry([gram[0], gram[1], gram[2]]).or_default();
+                if postings.last() != Some(&i) {
+                    postings.push(i);
+                }
+            }
+        }
+        Self {
+            built_from,
+            lines,
+            trigrams,
+        }
+    }
+
+    /// Lines containing every trigram of `needle` (all lines if shorter)
+    fn candidates(&self, needle: &str) -> Vec<u32> {
+        if needle.len() < 3 {
+            return (0..).zip(&self.lines).map(|(i, _)| i).collect();
+        }
+        let mut lists: Vec<&[u32]> = Vec::new();
+        for gram in needle.as_bytes().windows(3) {
+            match self.trigrams.get(gram) {
+                Some(postings) => lists.push(postings),
+                None => return Vec::new(),
+            }
+        }
+        lists.sort_by_key(|l| l.len());
+        let mut result = lists[0].to_vec();
+        for list in &lists[1..] {
+            result.retain(|i| list.binary_search(i).is_ok());
+        }
+        result
+    }
+}
+
+impl LocalizationTable {
+    /// Entries as seen in `locale` (delta overrides, then base), by id
+    #[must_use]
+    pub fn entries_for(&self, locale: LocaleId) -> Vec<&DialogueEntry> {
+        let delta = (locale != self.base_locale)
+            .then(|| self.locale_deltas.get(&locale))
+            .flatten();
+        let mut entries: Vec<&DialogueEntry> = self
+            .base_table
+            .iter()
+            .filter(|e| delta.is_none_or(|d| !d.entries.contains_key(&e.id)))
+            .chain(delta.into_iter().flat_map(|d| d.entries.values()))
+            .collect();
+        entries.sort_by_key(|e| e.id);
+        entries
+    }
+
+    /// Build (or rebuild) the search index for `locale`
+    ///
+    /// [`Self::search`] uses it while the base and delta tables are
+    /// unchanged and scans the lines otherwise.
+    pub fn build_search_index(&mut self, locale: LocaleId) {
+        let lines = self
+            .entries_for(locale)
+            .into_iter()
+            .map(FoldedLine::new)
+            .collect();
+        let index = SearchIndex::build(lines, self.content_hashes(locale));
+        self.search_indexes.insert(locale, index);
+    }
+
+    /// Find `query` in every line of `locale` (ids ascending)
+    #[must_use]
+    pub fn search(&self, locale: LocaleId, query: SearchQuery<'_>) -> Vec<SearchHit> {
+        let mut hits = Vec::new();
+        match query {
+            SearchQuery::Substring(needle) => {
+                let needle = needle.to_lowercase();
+                if needle.is_empty() {
+                    return hits;
+                }
+                let index = self
+                    .search_indexes
+                    .get(&locale)
+                    .filter(|index| index.built_from == self.content_hashes(locale));
+                match index {
+                    Some(index) => {
+                        for i in index.candidates(&needle) {
+                            index.lines[i as usize].find(&needle, &mut hits);
+                        }
+                    }
+                    None => {
+                        for entry in self.entries_for(locale) {
+                            FoldedLine::new(entry).find(&needle, &mut hits);
+                        }
+                    }
+                }
+            }
+            SearchQuery::Regex(regex) => {
+                for entry in self.entries_for(locale) {
+                    hits.extend(regex.find_iter(&entry.text).map(|m| SearchHit {
+                        id: entry.id,
+                        span: m.range(),
+                    }));
+                }
+            }
+        }
+        hits
+    }
+
+    fn content_hashes(&self, locale: LocaleId) -> (u64, u64) {
+        let delta = (locale != self.base_locale)
+            .then(|| self.locale_deltas.get(&locale))
+            .flatten()
+            .map_or(0, |d| d.content_hash);
+        (self.base_table.content_hash, delta)
+    }
+}
+
 // ── Compression ────────────────────────────────────────────────
 
 /// Compression mode for dialogue data
@@ -398,12 +1167,28 @@ impl DialogueCompressionMode {
 /// Dialogue compressor (Bincode + Zstd)
 pub struct DialogueCompressor {
     mode: DialogueCompressionMode,
+    limits: DecodeLimits,
 }
 
+const _: () = crate::assert_send_sync::<DialogueCompressor>();
+
 impl DialogueCompressor {
     #[must_use]
     pub const fn new(mode: DialogueCompressionMode) -> Self {
-        Self { mode }
+        Self {
+            mode,
+            limits: DecodeLimits::new(),
+        }
+    }
+
+    /// Set the output ceilings for decoding (default: [`DecodeLimits::new`])
+    ///
+    /// Every decoded table, blob and locale section counts toward
+    /// [`DecodeLimits::max_total_bytes`].
+    #[must_use]
+    pub const fn with_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
     /// Compress a dialogue table to bytes
@@ -418,11 +1203,13 @@ impl DialogueCompressor {
         output.extend_from_slice(DIALOGUE_MAGIC);
         output.push(DIALOGUE_VERSION.0);
         output.push(DIALOGUE_VERSION.1);
-        output.push(0x01); // type: single table
+        output.push(TYPE_TABLE);
         output.push(self.mode as u8);
         let compressed = zstd::encode_all(serialized.as_slice(), self.mode.zstd_level())
             .map_err(|e| crate::ALICETextError::EncodingError(e.to_string()))?;
-        output.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        output.extend_from_slice(
+            &crate::to_u32(compressed.len(), "dialogue payload size")?.to_le_bytes(),
+        );
         output.extend_from_slice(&compressed);
         Ok(output)
     }
@@ -431,7 +1218,8 @@ impl DialogueCompressor {
     ///
     /// # Errors
     ///
-    /// Returns an error if the data is too short, magic bytes are invalid, or decompression fails.
+    /// Returns an error if the data is too short, magic bytes are invalid,
+    /// decompression fails, or the table decodes past the decode limits.
     pub fn decompress_table(&self, data: &[u8]) -> crate::Result<DialogueTable> {
         if data.len() < 16 {
             return Err(crate::ALICETextError::DecompressionError(
@@ -441,7 +1229,7 @@ impl DialogueCompressor {
         if &data[0..8] != DIALOGUE_MAGIC {
             return Err(crate::ALICETextError::InvalidMagic);
         }
-        if data[10] != 0x01 {
+        if data[10] != TYPE_TABLE {
             return Err(crate::ALICETextError::DecompressionError(
                 "Not a single dialogue table".to_string(),
             ));
@@ -452,67 +1240,257 @@ impl DialogueCompressor {
                 "Truncated data".to_string(),
             ));
         }
-        let decompressed = zstd::decode_all(&data[16..16 + compressed_len])
-            .map_err(|e| crate::ALICETextError::DecompressionError(e.to_string()))?;
+        let decompressed = zstd_params::decompress(
+            &data[16..16 + compressed_len],
+            &[],
+            0,
+            self.limits.max_total_bytes,
+        )?;
         let mut table: DialogueTable = bincode::deserialize(&decompressed)
             .map_err(|e| crate::ALICETextError::DecompressionError(e.to_string()))?;
-        table.speakers.rebuild_index();
+        table.speakers.rebuild_index()?;
         Ok(table)
     }
 
     /// Compress a localization table (multi-locale) to bytes
     ///
+    /// The base table and every locale delta become separate sections
+    /// (deltas in locale id order).
+    ///
     /// # Errors
     ///
     /// Returns an error if serialization or Zstd compression fails.
     pub fn compress_localization(&self, table: &LocalizationTable) -> crate::Result<Vec<u8>> {
-        let serialized = bincode::serialize(table)
-            .map_err(|e| crate::ALICETextError::EncodingError(e.to_string()))?;
-        let mut output = Vec::with_capacity(serialized.len() + 12);
-        output.extend_from_slice(DIALOGUE_MAGIC);
-        output.push(DIALOGUE_VERSION.0);
-        output.push(DIALOGUE_VERSION.1);
-        output.push(0x02); // type: localization table
-        output.push(self.mode as u8);
-        let compressed = zstd::encode_all(serialized.as_slice(), self.mode.zstd_level())
-            .map_err(|e| crate::ALICETextError::EncodingError(e.to_string()))?;
-        output.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
-        output.extend_from_slice(&compressed);
-        Ok(output)
+        let mut deltas: Vec<&DeltaTable> = table.locale_deltas.values().collect();
+        deltas.sort_by_key(|d| d.locale.0);
+
+        let mut sections = Vec::with_capacity(1 + deltas.len());
+        sections.push(LocaleSection {
+            locale: table.base_locale,
+            kind: SECTION_BASE,
+            bytes: Cow::Owned(self.compress_section(&table.base_table)?),
+        });
+        for delta in deltas {
+            sections.push(LocaleSection {
+                locale: delta.locale,
+                kind: SECTION_DELTA,
+                bytes: Cow::Owned(self.compress_section(delta)?),
+            });
+        }
+        write_sections(self.mode as u8, &sections)
     }
 
-    /// Decompress a localization table from bytes
+    /// Decompress a localization table from bytes (sectioned or 1.0 blob)
     ///
     /// # Errors
     ///
     /// Returns an error if the data is too short, magic bytes are invalid, or decompression fails.
     pub fn decompress_localization(&self, data: &[u8]) -> crate::Result<LocalizationTable> {
-        if data.len() < 16 {
-            return Err(crate::ALICETextError::DecompressionError(
-                "Data too short".to_string(),
-            ));
+        self.decompress_locales(data, None)
+    }
+
+    /// Decompress the base table and only the listed locale deltas
+    ///
+    /// Other sections are skipped without being decompressed (a 1.0 blob
+    /// is decoded whole, then filtered).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is too short, magic bytes are invalid, or decompression fails.
+    pub fn decompress_localization_subset(
+        &self,
+        data: &[u8],
+        locales: &[LocaleId],
+    ) -> crate::Result<LocalizationTable> {
+        self.decompress_locales(data, Some(locales))
+    }
+
+    /// Locales stored in a localization archive (base first), read from
+    /// the directory without decompressing anything
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header or directory is invalid.
+    pub fn localization_locales(&self, data: &[u8]) -> crate::Result<Vec<LocaleId>> {
+        if localization_type(data)? == TYPE_LOCALIZATION_BLOB {
+            return Ok(self.decompress_localization(data)?.available_locales());
         }
-        if &data[0..8] != DIALOGUE_MAGIC {
-            return Err(crate::ALICETextError::InvalidMagic);
+        Ok(read_sections(data)?.iter().map(|s| s.locale).collect())
+    }
+
+    /// Replace (or add) one locale delta, copying every other section
+    /// byte for byte
+    ///
+    /// A 1.0 blob is converted to the sectioned layout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is invalid, `delta` targets the base
+    /// locale, or compression fails.
+    pub fn update_locale(&self, data: &[u8], delta: &DeltaTable) -> crate::Result<Vec<u8>> {
+        if localization_type(data)? == TYPE_LOCALIZATION_BLOB {
+            let mut table = self.decompress_localization(data)?;
+            if delta.locale == table.base_locale {
+                return Err(base_locale_delta());
+            }
+            table.locale_deltas.insert(delta.locale, delta.clone());
+            return self.compress_localization(&table);
         }
-        if data[10] != 0x02 {
-            return Err(crate::ALICETextError::DecompressionError(
-                "Not a localization table".to_string(),
-            ));
+
+        let mut sections = read_sections(data)?;
+        if sections[0].locale == delta.locale {
+            return Err(base_locale_delta());
         }
-        let compressed_len = u32::from_le_bytes([data[12], data[13], data[14], data[15]]) as usize;
-        if data.len() < 16 + compressed_len {
-            return Err(crate::ALICETextError::DecompressionError(
-                "Truncated data".to_string(),
-            ));
+        let section = LocaleSection {
+            locale: delta.locale,
+            kind: SECTION_DELTA,
+            bytes: Cow::Owned(self.compress_section(delta)?),
+        };
+        match sections.iter().position(|s| s.locale == delta.locale) {
+            Some(i) => sections[i] = section,
+            None => {
+                let at = sections
+                    .partition_point(|s| s.kind == SECTION_BASE || s.locale.0 < delta.locale.0);
+                sections.insert(at, section);
+            }
+        }
+        write_sections(data[11], &sections)
+    }
+
+    fn decompress_locales(
+        &self,
+        data: &[u8],
+        locales: Option<&[LocaleId]>,
+    ) -> crate::Result<LocalizationTable> {
+        let wanted = |locale: &LocaleId| locales.is_none_or(|l| l.contains(locale));
+        if localization_type(data)? == TYPE_LOCALIZATION_BLOB {
+            let compressed_len =
+                u32::from_le_bytes([data[12], data[13], data[14], data[15]]) as usize;
+            let blob = data.get(16..16 + compressed_len).ok_or_else(|| {
+                crate::ALICETextError::DecompressionError("Truncated data".to_string())
+            })?;
+            let mut table: LocalizationTable = self.decompress_section(blob, &mut 0)?;
+            table.base_table.speakers.rebuild_index()?;
+            table.locale_deltas.retain(|locale, _| wanted(locale));
+            return Ok(table);
+        }
+
+        let sections = read_sections(data)?;
+        let base = &sections[0];
+        let mut table = LocalizationTable::new(base.locale);
+        let mut decoded = 0;
+        table.base_table = self.decompress_section(&base.bytes, &mut decoded)?;
+        table.base_table.speakers.rebuild_index()?;
+        for section in &sections[1..] {
+            if wanted(&section.locale) {
+                let delta: DeltaTable = self.decompress_section(&section.bytes, &mut decoded)?;
+                table.locale_deltas.insert(section.locale, delta);
+            }
         }
-        let decompressed = zstd::decode_all(&data[16..16 + compressed_len])
-            .map_err(|e| crate::ALICETextError::DecompressionError(e.to_string()))?;
-        let mut table: LocalizationTable = bincode::deserialize(&decompressed)
-            .map_err(|e| crate::ALICETextError::DecompressionError(e.to_string()))?;
-        table.base_table.speakers.rebuild_index();
         Ok(table)
     }
+
+    fn compress_section<T: Serialize>(&self, value: &T) -> crate::Result<Vec<u8>> {
+        let serialized = bincode::serialize(value)
+            .map_err(|e| crate::ALICETextError::EncodingError(e.to_string()))?;
+        zstd::encode_all(serialized.as_slice(), self.mode.zstd_level())
+            .map_err(|e| crate::ALICETextError::EncodingError(e.to_string()))
+    }
+
+    /// Decode one section, adding its size to the running `decoded` total
+    fn decompress_section<T: serde::de::DeserializeOwned>(
+        &self,
+        bytes: &[u8],
+        decoded: &mut u64,
+    ) -> crate::Result<T> {
+        let remaining = self.limits.max_total_bytes.saturating_sub(*decoded);
+        let decompressed = zstd_params::decompress(bytes, &[], 0, remaining)?;
+        *decoded += decompressed.len() as u64;
+        bincode::deserialize(&decompressed)
+            .map_err(|e| crate::ALICETextError::DecompressionError(e.to_string()))
+    }
+}
+
+/// One compressed section of a localization archive
+struct LocaleSection<'a> {
+    locale: LocaleId,
+    kind: u8,
+    bytes: Cow<'a, [u8]>,
+}
+
+fn base_locale_delta() -> crate::ALICETextError {
+    crate::ALICETextError::EncodingError("Delta targets the base locale".to_string())
+}
+
+/// Check magic and type of a localization archive, returning the type
+fn localization_type(data: &[u8]) -> crate::Result<u8> {
+    if data.len() < 16 {
+        return Err(crate::ALICETextError::DecompressionError(
+            "Data too short".to_string(),
+        ));
+    }
+    if &data[0..8] != DIALOGUE_MAGIC {
+        return Err(crate::ALICETextError::InvalidMagic);
+    }
+    match data[10] {
+        t @ (TYPE_LOCALIZATION_BLOB | TYPE_LOCALIZATION) => Ok(t),
+        _ => Err(crate::ALICETextError::DecompressionError(
+            "Not a localization table".to_string(),
+        )),
+    }
+}
+
+fn read_sections(data: &[u8]) -> crate::Result<Vec<LocaleSection<'_>>> {
+    let truncated = || crate::ALICETextError::DecompressionError("Truncated data".to_string());
+    let count = u32::from_le_bytes([data[12], data[13], data[14], data[15]]) as usize;
+    let directory = count
+        .checked_mul(DIRECTORY_ENTRY_SIZE)
+        .and_then(|len| data.get(16..16 + len))
+        .ok_or_else(truncated)?;
+    let mut offset = 16 + directory.len();
+    let mut sections = Vec::with_capacity(count);
+    for entry in directory.chunks_exact(DIRECTORY_ENTRY_SIZE) {
+        let len = u32::from_le_bytes([entry[3], entry[4], entry[5], entry[6]]) as usize;
+        let bytes = data.get(offset..offset + len).ok_or_else(truncated)?;
+        sections.push(LocaleSection {
+            locale: LocaleId(u16::from_le_bytes([entry[0], entry[1]])),
+            kind: entry[2],
+            bytes: Cow::Borrowed(bytes),
+        });
+        offset += len;
+    }
+    if sections.first().is_none_or(|s| s.kind != SECTION_BASE)
+        || sections[1..].iter().any(|s| s.kind != SECTION_DELTA)
+    {
+        return Err(crate::ALICETextError::DecompressionError(
+            "Localization directory must start with the base section".to_string(),
+        ));
+    }
+    Ok(sections)
+}
+
+fn write_sections(mode: u8, sections: &[LocaleSection]) -> crate::Result<Vec<u8>> {
+    let body: usize = sections.iter().map(|s| s.bytes.len()).sum();
+    let mut output = Vec::with_capacity(16 + sections.len() * DIRECTORY_ENTRY_SIZE + body);
+    output.extend_from_slice(DIALOGUE_MAGIC);
+    output.push(DIALOGUE_VERSION.0);
+    output.push(DIALOGUE_VERSION.1);
+    output.push(TYPE_LOCALIZATION);
+    output.push(mode);
+    output.extend_from_slice(
+        &crate::to_u32(sections.len(), "localization section count")?.to_le_bytes(),
+    );
+    for section in sections {
+        output.extend_from_slice(&section.locale.0.to_le_bytes());
+        output.push(section.kind);
+        output.extend_from_slice(
+            &crate::to_u32(section.bytes.len(), "dialogue payload size")?.to_le_bytes(),
+        );
+    }
+    for section in sections {
+        output.extend_from_slice(&section.bytes);
+    }
+    Ok(output)
 }
 
 impl Default for DialogueCompressor {
@@ -525,6 +1503,8 @@ impl Default for DialogueCompressor {
 mod tests {
     use super::*;
 
+    const PINNED_ENTRY_HASH: u64 = 17_774_915_237_794_061_878;
+
     fn make_entry(id: u32, speaker: u16, text: &str) -> DialogueEntry {
         DialogueEntry {
             id,
@@ -537,25 +1517,105 @@ mod tests {
     #[test]
     fn test_speaker_dictionary_insert_dedup() {
         let mut dict = SpeakerDictionary::new();
-        let i1 = dict.insert("Alice");
-        let i2 = dict.insert("Bob");
-        let i3 = dict.insert("Alice"); // duplicate
+        let i1 = dict.insert("Alice").unwrap();
+        let i2 = dict.insert("Bob").unwrap();
+        let i3 = dict.insert("Alice").unwrap(); // duplicate
         assert_eq!(i1, 0);
         assert_eq!(i2, 1);
         assert_eq!(i3, 0); // same as first
         assert_eq!(dict.len(), 2);
     }
 
+    #[test]
+    fn test_entry_content_hash() {
+        let entry = make_entry(1, 0, "今日は");
+        assert_eq!(
+            entry.content_hash(),
+            make_entry(1, 0, "今日は").content_hash()
+        );
+        assert_ne!(
+            entry.content_hash(),
+            make_entry(1, 1, "今日は").content_hash()
+        );
+        assert_ne!(
+            entry.content_hash(),
+            make_entry(2, 0, "今日は").content_hash()
+        );
+
+        let mut with_reading = entry.clone();
+        with_reading.ruby = Some(vec![RubyAnnotation {
+            base_start: 0,
+            base_len: 3,
+            ruby_text: "こんにちは".to_string(),
+        }]);
+        assert_ne!(entry.content_hash(), with_reading.content_hash());
+
+        // Pinned: asset caches key on this value across releases
+        assert_eq!(entry.content_hash(), PINNED_ENTRY_HASH);
+    }
+
+    #[test]
+    fn test_merkle_root_and_diff() {
+        let mut v1 = DialogueTable::new();
+        for (id, text) in ["a", "b", "c", "d", "e"].iter().enumerate() {
+            v1.add(make_entry(id as u32, 0, text));
+        }
+        let mut v2 = DialogueTable::new();
+        for (id, text) in ["a", "B", "c", "e"].iter().enumerate() {
+            v2.add(make_entry(id as u32, 0, text));
+        }
+        v2.add(make_entry(9, 0, "new"));
+
+        assert_eq!(v1.merkle_root(), v1.clone().merkle_root());
+        assert_ne!(v1.merkle_root(), v2.merkle_root());
+        assert_eq!(v1.entry_hash(2), Some(make_entry(2, 0, "c").content_hash()));
+        assert!(v1.diff(&v1).is_empty());
+        assert_eq!(
+            v2.diff(&v1),
+            TableDiff {
+                added: vec![9],
+                removed: vec![4],
+                changed: vec![1, 3],
+            }
+        );
+
+        // Order of leaves matters; an odd leaf is promoted
+        assert_ne!(merkle_root(&[1, 2]), merkle_root(&[2, 1]));
+        assert_eq!(merkle_root(&[7]), 7);
+        assert_ne!(merkle_root(&[1, 2, 3]), merkle_root(&[1, 2]));
+    }
+
     #[test]
     fn test_speaker_dictionary_get() {
         let mut dict = SpeakerDictionary::new();
-        dict.insert("Alice");
-        dict.insert("Bob");
+        dict.insert("Alice").unwrap();
+        dict.insert("Bob").unwrap();
         assert_eq!(dict.get(0), Some("Alice"));
         assert_eq!(dict.get(1), Some("Bob"));
         assert_eq!(dict.get(2), None);
     }
 
+    #[test]
+    fn test_speaker_dictionary_limit() {
+        let mut dict = SpeakerDictionary::new();
+        assert_eq!(dict.remaining(), MAX_SPEAKERS);
+        for i in 0..=u16::MAX {
+            assert_eq!(dict.insert(&format!("npc{i}")).unwrap(), i);
+        }
+        assert!(dict.is_full());
+        assert_eq!(dict.len(), dict.capacity());
+        assert!(matches!(
+            dict.insert("one too many"),
+            Err(crate::ALICETextError::LimitExceeded { limit: 65535, .. })
+        ));
+        // Existing names still resolve
+        assert_eq!(dict.insert("npc7").unwrap(), 7);
+
+        // A deserialized dictionary past the limit is rejected
+        dict.names.push("foreign".to_string());
+        assert!(dict.rebuild_index().is_err());
+    }
+
     #[test]
     fn test_ruby_annotation() {
         let ruby = RubyAnnotation {
@@ -591,6 +1651,135 @@ mod tests {
         assert_eq!(found.unwrap().text, "Sparse");
     }
 
+    #[test]
+    fn test_markup_segments_roundtrip() {
+        let line = "<color=#ff0000>Danger!</color>[pause=500] Take the <icon=sword/> \
+                    and run. <b>勇者</b>よ、[wait]急げ";
+        let parsed = SegmentedText::parse(line);
+        assert_eq!(parsed.to_string(), line);
+        assert_eq!(
+            parsed.plain_text(),
+            "Danger! Take the  and run. 勇者よ、急げ"
+        );
+        let tags: Vec<_> = parsed.tags().map(|t| t.name.as_str()).collect();
+        assert_eq!(tags, ["color", "color", "pause", "icon", "b", "b", "wait"]);
+        assert_eq!(
+            parsed.segments[0],
+            MarkupSegment::Tag(MarkupTag {
+                style: TagStyle::Angle,
+                name: "color".to_string(),
+                value: Some("#ff0000".to_string()),
+                closing: false,
+                self_closing: false,
+            })
+        );
+        assert!(
+            matches!(&parsed.segments[3], MarkupSegment::Tag(t) if t.value.as_deref() == Some("500"))
+        );
+
+        // Anything that is not a well-formed tag stays text, losslessly
+        for text in [
+            "",
+            "a < b > c",
+            "[[x]]",
+            "<>",
+            "</x=1>",
+            "<a b>",
+            "x [unterminated",
+            "<a=b/>",
+            "</a/>",
+            "[line\nbreak]",
+        ] {
+            assert_eq!(SegmentedText::parse(text).to_string(), text);
+        }
+        assert_eq!(SegmentedText::parse("a < b > c").len(), 1);
+    }
+
+    #[test]
+    fn test_table_segments_cached() {
+        let mut table = DialogueTable::new();
+        table.add(make_entry(0, 0, "[pause=1]Hi"));
+        table.add(make_entry(1, 0, "plain"));
+        assert_eq!(table.segments(0).unwrap().len(), 2);
+        assert_eq!(table.segments(1).unwrap().plain_text(), "plain");
+        assert!(table.segments(2).is_none());
+
+        // Adding invalidates the cache
+        table.add(make_entry(7, 0, "<b>late</b>"));
+        assert_eq!(table.segments(7).unwrap().tags().count(), 2);
+        assert_eq!(table.segments(1).unwrap().plain_text(), "plain");
+    }
+
+    #[test]
+    fn test_builder_sorts_and_applies_duplicate_policy() {
+        let build = |policy| {
+            let mut builder = DialogueTable::builder().with_duplicate_policy(policy);
+            let alice = builder.speaker("Alice").unwrap();
+            builder
+                .add(make_entry(2, alice, "two"))
+                .add(make_entry(0, alice, "zero"))
+                .add(make_entry(1, alice, "one"))
+                .add(make_entry(0, alice, "zero again"));
+            builder.build()
+        };
+
+        assert!(matches!(
+            build(DuplicatePolicy::Reject),
+            Err(crate::ALICETextError::InvalidDialogue(
+                DialogueViolation::DuplicateId(0)
+            ))
+        ));
+        let table = build(DuplicatePolicy::KeepFirst).unwrap();
+        assert!(table.is_contiguous());
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get(0).unwrap().text, "zero");
+        assert_eq!(table.get(2).unwrap().text, "two");
+        let table = build(DuplicatePolicy::Overwrite).unwrap();
+        assert_eq!(table.get(0).unwrap().text, "zero again");
+        assert!(table.validate().is_ok());
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let mut builder = DialogueTableBuilder::new();
+        builder.speaker("Alice").unwrap();
+        builder.add(make_entry(9, 1, "who?"));
+        assert!(matches!(
+            builder.build(),
+            Err(crate::ALICETextError::InvalidDialogue(
+                DialogueViolation::UnknownSpeaker { id: 9, speaker: 1 }
+            ))
+        ));
+
+        let mut builder = DialogueTableBuilder::new();
+        let speaker = builder.speaker("Guide").unwrap();
+        let mut entry = make_entry(3, speaker, "東京");
+        entry.ruby = Some(vec![RubyAnnotation {
+            base_start: 1,
+            base_len: 2,
+            ruby_text: "きょう".to_string(),
+        }]);
+        builder.add(entry);
+        assert!(matches!(
+            builder.build(),
+            Err(crate::ALICETextError::InvalidDialogue(
+                DialogueViolation::RubyOutOfRange(3)
+            ))
+        ));
+
+        // Unsorted `add` is caught by `validate`
+        let mut table = DialogueTable::new();
+        table.speakers.insert("Alice").unwrap();
+        table.add(make_entry(5, 0, "later"));
+        table.add(make_entry(1, 0, "earlier"));
+        assert!(matches!(
+            table.validate(),
+            Err(crate::ALICETextError::InvalidDialogue(
+                DialogueViolation::Unsorted(1)
+            ))
+        ));
+    }
+
     #[test]
     fn test_unique_chars() {
         let mut table = DialogueTable::new();
@@ -649,7 +1838,7 @@ mod tests {
     #[test]
     fn test_compress_decompress_table_roundtrip() {
         let mut table = DialogueTable::new();
-        let speaker = table.speakers.insert("Alice");
+        let speaker = table.speakers.insert("Alice").unwrap();
         table.add(DialogueEntry {
             id: 0,
             speaker,
@@ -677,7 +1866,7 @@ mod tests {
     #[test]
     fn test_compress_decompress_localization_roundtrip() {
         let mut loc = LocalizationTable::new(LocaleId::JA);
-        let speaker = loc.base_table.speakers.insert("NPC");
+        let speaker = loc.base_table.speakers.insert("NPC").unwrap();
         loc.base_table.add(make_entry(0, speaker, "こんにちは"));
         loc.add_delta(LocaleId::EN, make_entry(0, 0, "Hello"));
 
@@ -690,4 +1879,168 @@ mod tests {
         );
         assert_eq!(decompressed.get(LocaleId::EN, 0).unwrap().text, "Hello");
     }
+
+    #[test]
+    fn test_decode_limits() {
+        let mut table = DialogueTable::new();
+        let speaker = table.speakers.insert("Narrator").unwrap();
+        for id in 0..256 {
+            table.add(make_entry(id, speaker, &"long line ".repeat(64)));
+        }
+        let mut loc = LocalizationTable::new(LocaleId::JA);
+        loc.base_table = table.clone();
+        loc.add_delta(LocaleId::EN, make_entry(0, 0, &"delta ".repeat(64)));
+
+        let compressor = DialogueCompressor::default();
+        let single = compressor.compress_table(&table).unwrap();
+        let multi = compressor.compress_localization(&loc).unwrap();
+        assert_eq!(compressor.decompress_table(&single).unwrap().len(), 256);
+        assert!(compressor.decompress_localization(&multi).is_ok());
+
+        let limited = DialogueCompressor::default()
+            .with_decode_limits(DecodeLimits::new().with_max_total_bytes(1 << 12));
+        assert!(matches!(
+            limited.decompress_table(&single),
+            Err(crate::ALICETextError::LimitExceeded { .. })
+        ));
+        assert!(matches!(
+            limited.decompress_localization(&multi),
+            Err(crate::ALICETextError::LimitExceeded { .. })
+        ));
+    }
+
+    fn three_locales() -> LocalizationTable {
+        let mut loc = LocalizationTable::new(LocaleId::JA);
+        let speaker = loc.base_table.speakers.insert("NPC").unwrap();
+        loc.base_table.add(make_entry(0, speaker, "こんにちは"));
+        loc.base_table.add(make_entry(1, speaker, "さようなら"));
+        loc.add_delta(LocaleId::FR, make_entry(0, 0, "Bonjour"));
+        loc.add_delta(LocaleId::EN, make_entry(0, 0, "Hello"));
+        loc.add_delta(LocaleId::EN, make_entry(1, 0, "Goodbye"));
+        loc
+    }
+
+    #[test]
+    fn test_localization_sections_and_subset() {
+        let compressor = DialogueCompressor::default();
+        let data = compressor.compress_localization(&three_locales()).unwrap();
+        assert_eq!(data[10], TYPE_LOCALIZATION);
+        assert_eq!(
+            compressor.localization_locales(&data).unwrap(),
+            [LocaleId::JA, LocaleId::EN, LocaleId::FR]
+        );
+
+        let subset = compressor
+            .decompress_localization_subset(&data, &[LocaleId::FR])
+            .unwrap();
+        assert_eq!(subset.locale_deltas.len(), 1);
+        assert_eq!(subset.get(LocaleId::FR, 0).unwrap().text, "Bonjour");
+        // EN not loaded: falls back to the base text
+        assert_eq!(subset.get(LocaleId::EN, 1).unwrap().text, "さようなら");
+        assert_eq!(subset.base_table.speakers.get(0), Some("NPC"));
+
+        assert!(compressor
+            .decompress_localization(&data[..data.len() - 1])
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_locale_keeps_other_sections() {
+        let compressor = DialogueCompressor::default();
+        let data = compressor.compress_localization(&three_locales()).unwrap();
+        let before = read_sections(&data).unwrap();
+
+        let mut de = DeltaTable::new(LocaleId::DE);
+        de.add(make_entry(0, 0, "Hallo"));
+        let data = compressor.update_locale(&data, &de).unwrap();
+        let mut fr = DeltaTable::new(LocaleId::FR);
+        fr.add(make_entry(0, 0, "Salut"));
+        let data = compressor.update_locale(&data, &fr).unwrap();
+
+        let after = read_sections(&data).unwrap();
+        let locales: Vec<_> = after.iter().map(|s| s.locale).collect();
+        assert_eq!(
+            locales,
+            [LocaleId::JA, LocaleId::EN, LocaleId::FR, LocaleId::DE]
+        );
+        // Untouched sections are copied, not re-encoded
+        assert_eq!(after[0].bytes, before[0].bytes);
+        assert_eq!(after[1].bytes, before[1].bytes);
+        assert_ne!(after[2].bytes, before[2].bytes);
+
+        let table = compressor.decompress_localization(&data).unwrap();
+        assert_eq!(table.get(LocaleId::FR, 0).unwrap().text, "Salut");
+        assert_eq!(table.get(LocaleId::DE, 0).unwrap().text, "Hallo");
+        assert_eq!(table.get(LocaleId::EN, 1).unwrap().text, "Goodbye");
+
+        let ja = DeltaTable::new(LocaleId::JA);
+        assert!(compressor.update_locale(&data, &ja).is_err());
+    }
+
+    #[test]
+    fn test_legacy_localization_blob() {
+        // 1.0 layout: the whole table as one zstd(bincode) blob, type 0x02
+        let loc = three_locales();
+        let compressed = zstd::encode_all(bincode::serialize(&loc).unwrap().as_slice(), 3).unwrap();
+        let mut data = DIALOGUE_MAGIC.to_vec();
+        data.extend_from_slice(&[1, 0, TYPE_LOCALIZATION_BLOB, 1]);
+        data.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        data.extend_from_slice(&compressed);
+
+        let compressor = DialogueCompressor::default();
+        let table = compressor.decompress_localization(&data).unwrap();
+        assert_eq!(table.get(LocaleId::EN, 0).unwrap().text, "Hello");
+        let subset = compressor
+            .decompress_localization_subset(&data, &[LocaleId::EN])
+            .unwrap();
+        assert_eq!(subset.locale_deltas.len(), 1);
+
+        // Updating converts to the sectioned layout
+        let mut ko = DeltaTable::new(LocaleId::KO);
+        ko.add(make_entry(0, 0, "안녕하세요"));
+        let data = compressor.update_locale(&data, &ko).unwrap();
+        assert_eq!(data[10], TYPE_LOCALIZATION);
+        assert_eq!(compressor.localization_locales(&data).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_search_across_locales() {
+        let mut loc = three_locales();
+        loc.base_table.add(make_entry(2, 0, "İstanbul HELLO"));
+        loc.add_delta(LocaleId::EN, make_entry(2, 0, "Hello, hello!"));
+
+        let find = |loc: &LocalizationTable, locale, needle| {
+            loc.search(locale, SearchQuery::Substring(needle))
+                .into_iter()
+                .map(|h| (h.id, h.span))
+                .collect::<Vec<_>>()
+        };
+        let check = |loc: &LocalizationTable| {
+            assert_eq!(
+                find(loc, LocaleId::EN, "HELLO"),
+                [(0, 0..5), (2, 0..5), (2, 7..12)]
+            );
+            // FR falls back to the base line for ids it does not override
+            assert_eq!(find(loc, LocaleId::FR, "bon"), [(0, 0..3)]);
+            assert_eq!(find(loc, LocaleId::FR, "hello"), [(2, 10..15)]);
+            // `İ` folds to two characters: the span still covers the original
+            assert_eq!(find(loc, LocaleId::JA, "i̇stan"), [(2, 0..6)]);
+            assert_eq!(find(loc, LocaleId::JA, "なら"), [(1, 9..15)]);
+            assert!(find(loc, LocaleId::EN, "missing").is_empty());
+        };
+        check(&loc);
+        loc.build_search_index(LocaleId::EN);
+        loc.build_search_index(LocaleId::FR);
+        loc.build_search_index(LocaleId::JA);
+        check(&loc);
+
+        // A stale index is ignored
+        loc.add_delta(LocaleId::EN, make_entry(1, 0, "hello again"));
+        assert_eq!(find(&loc, LocaleId::EN, "hello").len(), 4);
+
+        let regex = Regex::new(r"[Hh]ello\b").unwrap();
+        let hits = loc.search(LocaleId::EN, SearchQuery::Regex(&regex));
+        assert_eq!(hits.len(), 4);
+        assert_eq!(hits[0], SearchHit { id: 0, span: 0..5 });
+    }
 }
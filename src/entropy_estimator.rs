@@ -1,12 +1,24 @@
 //! Entropy estimation module for ALICE-Text
 //!
 //! Estimates compression efficiency without full compression.
-
+//!
+//! Byte-level Shannon entropy ignores context and badly over-estimates
+//! structured logs, so the size estimate is template-aware instead: pattern
+//! and skeleton extraction run on a line-aligned sample (at most
+//! `SAMPLE_CHUNKS × SAMPLE_CHUNK` bytes spread over the input), the sample's
+//! columnar payload is compressed like `TunedCompressor` does, and the result
+//! is scaled to the full size. An order-3 context-model entropy (blended
+//! down to order 0) is reported alongside.
+
+use crate::columnar_encoder::{ColumnarEncoder, ColumnarPayload, LinePiece};
+use crate::level::Level;
 use crate::pattern_learner::PatternLearner;
-use std::collections::HashMap;
+use crate::tuned_compressor::{encode_dedup, zstd_encode, BlockStrategy, STORED_THRESHOLD};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 /// Entropy estimation result
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntropyEstimate {
     /// Shannon entropy (bits per byte)
     pub shannon_entropy: f64,
@@ -24,6 +36,15 @@ pub struct EntropyEstimate {
     pub unique_bytes: usize,
     /// Repetition score (higher = more repetitive)
     pub repetition_score: f64,
+    /// Adaptive order-3 context-model entropy of the sample (bits per byte)
+    #[serde(default)]
+    pub context_entropy: f64,
+    /// Distinct line templates (skeleton lines) in the sample
+    #[serde(default)]
+    pub template_count: usize,
+    /// Bytes actually analyzed for the size estimate
+    #[serde(default)]
+    pub sampled_bytes: usize,
 }
 
 impl EntropyEstimate {
@@ -56,8 +77,16 @@ pub struct EntropyEstimator {
 }
 
 impl EntropyEstimator {
-    /// Header overhead: magic (8) + version (2) + header (32) = 42 bytes
-    const HEADER_OVERHEAD: usize = 42;
+    /// Header overhead: magic (8) + version (2) + tuned header (24) = 34 bytes
+    const HEADER_OVERHEAD: usize = 34;
+    /// Number of evenly spaced sample chunks
+    const SAMPLE_CHUNKS: usize = 16;
+    /// Sample chunk size in bytes (shortened to whole lines)
+    const SAMPLE_CHUNK: usize = 16 * 1024;
+    /// Sample chunk size for block routing (16 chunks, 64 KiB per block)
+    const ROUTING_CHUNK: usize = 4 * 1024;
+    /// Context order of the context-model entropy
+    const CONTEXT_ORDER: usize = 3;
 
     /// Create a new estimator
     #[must_use]
@@ -84,6 +113,9 @@ impl EntropyEstimator {
                 pattern_coverage: 0.0,
                 unique_bytes: 0,
                 repetition_score: 0.0,
+                context_entropy: 0.0,
+                template_count: 0,
+                sampled_bytes: 0,
             };
         }
 
@@ -96,17 +128,16 @@ impl EntropyEstimator {
         // Calculate repetition score
         let (repetition_score, unique_bytes) = self.calculate_repetition(bytes);
 
-        // Estimate compression ratio
-        let estimated_ratio = self.estimate_ratio(
-            original_size,
-            shannon_entropy,
-            pattern_coverage,
-            repetition_score,
-        );
-
-        // Calculate estimated size
-        let estimated_size =
-            (original_size as f64 * estimated_ratio).ceil() as usize + self.header_overhead;
+        // Template-aware size of a representative sample, scaled up
+        let sample = Self::sample(text, Self::SAMPLE_CHUNK);
+        let context_entropy = self.context_entropy(sample.as_bytes(), Self::CONTEXT_ORDER);
+        let (sample_body, template_count) = Self::template_estimate(&sample, Level::default());
+        let body = if sample.len() == original_size {
+            sample_body
+        } else {
+            (sample_body as f64 * original_size as f64 / sample.len() as f64).ceil() as usize
+        };
+        let estimated_size = body.min(original_size) + self.header_overhead;
 
         let inv_original = 1.0 / original_size as f64;
         let actual_ratio = estimated_size as f64 * inv_original;
@@ -121,7 +152,118 @@ impl EntropyEstimator {
             pattern_coverage,
             unique_bytes,
             repetition_score,
+            context_entropy,
+            template_count,
+            sampled_bytes: sample.len(),
+        }
+    }
+
+    /// Pick the cheapest [`BlockStrategy`] for one block of a routed archive
+    ///
+    /// Each candidate encoding runs on a 64 KiB line-aligned sample of the
+    /// block; dedup is only tried when at least a quarter of the sampled
+    /// lines are repeats, stored wins when nothing beats the sample size.
+    #[must_use]
+    pub fn route_block(&self, block: &str, level: impl Into<Level>) -> BlockStrategy {
+        let level = level.into();
+        if block.len() < STORED_THRESHOLD {
+            return BlockStrategy::Stored;
+        }
+        let sample = Self::sample(block, Self::ROUTING_CHUNK);
+        let size = |encoded: crate::Result<Vec<u8>>| encoded.map_or(usize::MAX, |e| e.len());
+
+        let (lines, unique) =
+            sample
+                .split_inclusive('\n')
+                .fold((0usize, HashSet::new()), |(n, mut seen), line| {
+                    seen.insert(line);
+                    (n + 1, seen)
+                });
+        let dedup = if unique.len() * 4 <= lines * 3 {
+            size(encode_dedup(&sample, level))
+        } else {
+            usize::MAX
+        };
+
+        [
+            (
+                BlockStrategy::Columnar,
+                Self::template_estimate(&sample, level).0,
+            ),
+            (
+                BlockStrategy::RawZstd,
+                size(zstd_encode(sample.as_bytes(), level)),
+            ),
+            (BlockStrategy::Dedup, dedup),
+        ]
+        .into_iter()
+        .fold((BlockStrategy::Stored, sample.len()), |best, candidate| {
+            if candidate.1 < best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+        .0
+    }
+
+    /// Line-aligned sample: the whole text if small, else evenly spaced chunks
+    fn sample(text: &str, chunk_size: usize) -> std::borrow::Cow<'_, str> {
+        let budget = Self::SAMPLE_CHUNKS * chunk_size;
+        if text.len() <= budget {
+            return std::borrow::Cow::Borrowed(text);
+        }
+
+        let floor_boundary = |mut i: usize| {
+            while !text.is_char_boundary(i) {
+                i -= 1;
+            }
+            i
+        };
+        let stride = text.len() / Self::SAMPLE_CHUNKS;
+        let mut sample = String::with_capacity(budget);
+        for chunk in 0..Self::SAMPLE_CHUNKS {
+            let mut start = floor_boundary(chunk * stride);
+            if start > 0 {
+                match text[start..].find('\n') {
+                    Some(nl) => start += nl + 1,
+                    None => break,
+                }
+            }
+            let mut end = floor_boundary((start + chunk_size).min(text.len()));
+            if let Some(nl) = text[start..end].rfind('\n') {
+                end = start + nl + 1;
+            }
+            sample.push_str(&text[start..end]);
+        }
+        std::borrow::Cow::Owned(sample)
+    }
+
+    /// Compressed body size of `sample` through the columnar pipeline, and
+    /// its number of distinct line templates
+    fn template_estimate(sample: &str, level: Level) -> (usize, usize) {
+        if sample.len() < STORED_THRESHOLD {
+            return (sample.len(), 0);
         }
+        let payload = ColumnarEncoder::new().encode(sample);
+        let templates: HashSet<Vec<Option<&str>>> =
+            ColumnarPayload::split_lines(&payload.skeleton_tokens)
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|piece| match piece {
+                            LinePiece::Text(text) => Some(*text),
+                            LinePiece::Ref(_) => None,
+                        })
+                        .collect()
+                })
+                .collect();
+
+        let body = payload
+            .to_v2_bytes()
+            .and_then(|serialized| zstd_encode(&serialized, level))
+            .map_or(sample.len(), |compressed| compressed.len());
+        (body, templates.len())
     }
 
     /// Calculate Shannon entropy in bits per byte
@@ -192,49 +334,63 @@ impl EntropyEstimator {
         (combined_score, unique_bytes)
     }
 
-    /// Estimate compression ratio based on various factors
-    #[allow(clippy::unused_self)]
-    fn estimate_ratio(
-        &self,
-        original_size: usize,
-        shannon_entropy: f64,
-        pattern_coverage: f64,
-        repetition_score: f64,
-    ) -> f64 {
-        // Base ratio from Shannon entropy (theoretical minimum)
-        // 8 bits per byte, so ratio = entropy * (1/8)
-        const RCP_8: f64 = 1.0 / 8.0;
-        let entropy_ratio = shannon_entropy * RCP_8;
-
-        // Pattern bonus (patterns compress well)
-        let pattern_factor = pattern_coverage.mul_add(-0.3, 1.0);
-
-        // Repetition bonus
-        let repetition_factor = repetition_score.mul_add(-0.2, 1.0);
-
-        // LZMA typically achieves better than theoretical
-        // but has overhead for small files
-        let lzma_efficiency = if original_size < 100 {
-            1.5 // Overhead dominates for small files
-        } else if original_size < 1000 {
-            0.9 // Some overhead
-        } else {
-            0.7 // Good compression for larger files
-        };
-
-        // Combined estimate
-        let estimated = entropy_ratio * pattern_factor * repetition_factor * lzma_efficiency;
-
-        // Clamp to reasonable range
-        estimated.clamp(0.05, 1.5)
-    }
-
     /// Quick entropy check without full estimation
     #[must_use]
     pub fn quick_entropy(&self, data: &[u8]) -> f64 {
         self.calculate_shannon_entropy(data)
     }
 
+    /// Adaptive order-`order` context-model entropy in bits per byte
+    ///
+    /// Each byte is coded with a PPM-style blend: the order-`k` estimate
+    /// falls back to order `k - 1` with weight proportional to the number of
+    /// distinct symbols seen in the context, down to an adaptive order-0 model.
+    /// This is a code length a real coder could achieve, so unlike Shannon
+    /// entropy it never rewards unseen structure.
+    #[must_use]
+    #[allow(clippy::unused_self)]
+    pub fn context_entropy(&self, data: &[u8], order: usize) -> f64 {
+        if data.is_empty() {
+            return 0.0;
+        }
+        let order = order.min(4);
+        // (context, symbol) -> count; context -> (total, distinct symbols)
+        let mut counts: HashMap<(u64, u8), u32> = HashMap::new();
+        let mut totals: HashMap<u64, (u32, u32)> = HashMap::new();
+        let mut order0 = [0u32; 256];
+        let mut bits = 0.0;
+
+        for (i, &symbol) in data.iter().enumerate() {
+            let mut p = (f64::from(order0[symbol as usize]) + 0.5) / (i as f64 + 128.0);
+            let contexts = (1..=order.min(i)).map(|k| {
+                let packed = data[i - k..i]
+                    .iter()
+                    .fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+                ((k as u64) << 32) | packed
+            });
+            for context in contexts.clone() {
+                if let Some(&(total, distinct)) = totals.get(&context) {
+                    let seen = f64::from(*counts.get(&(context, symbol)).unwrap_or(&0));
+                    let escape = f64::from(distinct.max(1));
+                    p = escape.mul_add(p, seen) / (f64::from(total) + escape);
+                }
+            }
+            bits -= p.log2();
+
+            order0[symbol as usize] += 1;
+            for context in contexts {
+                let count = counts.entry((context, symbol)).or_insert(0);
+                let entry = totals.entry(context).or_insert((0, 0));
+                if *count == 0 {
+                    entry.1 += 1;
+                }
+                *count += 1;
+                entry.0 += 1;
+            }
+        }
+        bits / data.len() as f64
+    }
+
     /// Check if data is likely compressible
     #[must_use]
     pub fn is_compressible(&self, text: &str) -> bool {
@@ -270,6 +426,55 @@ mod tests {
         assert!(estimate2.shannon_entropy > estimate.shannon_entropy);
     }
 
+    #[test]
+    fn test_context_entropy_below_shannon() {
+        let estimator = EntropyEstimator::new();
+        let text = "abcdefgh".repeat(200);
+        let shannon = estimator.quick_entropy(text.as_bytes());
+        let context = estimator.context_entropy(text.as_bytes(), 2);
+        assert!((shannon - 3.0).abs() < 1e-9);
+        assert!(
+            context < 0.5,
+            "order-2 model should learn the cycle: {context}"
+        );
+        assert_eq!(estimator.context_entropy(b"", 3), 0.0);
+    }
+
+    #[test]
+    fn test_template_aware_estimate_tracks_actual() {
+        use crate::tuned_compressor::TunedCompressor;
+
+        let mut logs = String::new();
+        for i in 0..3000u32 {
+            logs.push_str(&format!(
+                "2024-01-15 10:{:02}:{:02} {} request from 10.0.{}.{} took {}ms\n",
+                (i / 60) % 60,
+                i % 60,
+                ["INFO", "WARN", "ERROR"][(i % 3) as usize],
+                i % 7,
+                (i * 13) % 250,
+                (i * 37) % 900
+            ));
+            if i % 10 == 0 {
+                logs.push_str("2024-01-15 10:00:00 INFO worker restarted\n");
+            }
+        }
+        let estimate = EntropyEstimator::new().estimate(&logs);
+        let actual = TunedCompressor::new(Level::default())
+            .compress(&logs)
+            .unwrap()
+            .len();
+        let error = (estimate.estimated_size as f64 - actual as f64).abs() / actual as f64;
+        assert!(
+            error < 0.1,
+            "estimate {} vs actual {actual}",
+            estimate.estimated_size
+        );
+        // Levels and values are placeholders: two line shapes
+        assert_eq!(estimate.template_count, 2);
+        assert!(estimate.context_entropy < estimate.shannon_entropy);
+    }
+
     #[test]
     fn test_pattern_coverage() {
         let estimator = EntropyEstimator::new();
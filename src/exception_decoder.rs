@@ -2,15 +2,22 @@
 //!
 //! Decodes compressed data back to original text.
 
-use crate::exception_encoder::ExceptionHeader;
+use crate::exception_encoder::{ExceptionHeader, PAYLOAD_DIRECT, PAYLOAD_NGRAM, PAYLOAD_PATTERN};
+use crate::format_v3::DecodeLimits;
+use crate::ngram_model::{NGramEncoding, NGramModel};
 use crate::pattern_learner::{PatternLearner, PatternMatch};
 use crate::{ALICETextError, Result, ALICE_TEXT_MAGIC, ALICE_TEXT_VERSION};
-use lzma_rs::lzma_decompress;
+use lzma_rs::decompress::Options as LzmaOptions;
+use lzma_rs::lzma_decompress_with_options;
 
 /// Exception decoder
 pub struct ExceptionDecoder {
     /// Pattern learner for restoration
     pattern_learner: PatternLearner,
+    /// Primed n-gram model, for archives encoded with one
+    ngram_model: Option<NGramModel>,
+    /// Output ceilings for the LZMA payload
+    limits: DecodeLimits,
 }
 
 impl ExceptionDecoder {
@@ -19,14 +26,32 @@ impl ExceptionDecoder {
     pub fn new() -> Self {
         Self {
             pattern_learner: PatternLearner::new(),
+            ngram_model: None,
+            limits: DecodeLimits::new(),
         }
     }
 
+    /// Set the output ceilings for decoding (default: [`DecodeLimits::new`])
+    #[must_use]
+    pub const fn with_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Decode n-gram archives primed with `model` (see
+    /// [`ExceptionEncoder::with_ngram_model`](crate::ExceptionEncoder::with_ngram_model))
+    #[must_use]
+    pub fn with_ngram_model(mut self, model: NGramModel) -> Self {
+        self.ngram_model = Some(model);
+        self
+    }
+
     /// Decode bytes to text
     ///
     /// # Errors
     ///
-    /// Returns an error if the data is too short, magic/version is invalid, or decompression fails.
+    /// Returns an error if the data is too short, magic/version is invalid,
+    /// decompression fails, or the payload decodes past the decode limits.
     pub fn decode_from_bytes(&self, data: &[u8]) -> Result<String> {
         // Minimum size: magic (8) + version (2) + header (32) = 42
         if data.len() < 42 {
@@ -40,14 +65,15 @@ impl ExceptionDecoder {
             return Err(ALICETextError::InvalidMagic);
         }
 
-        // Verify version
+        // Verify version (v1 payload layouts are not skippable: refuse newer minors)
         let version = (data[8], data[9]);
-        if version.0 > ALICE_TEXT_VERSION.0 {
+        if version > ALICE_TEXT_VERSION {
             return Err(ALICETextError::InvalidVersion(version.0, version.1));
         }
 
         // Parse header
         let header = ExceptionHeader::from_bytes(&data[10..42])?;
+        self.limits.check_total(u64::from(header.original_length))?;
 
         // Get compressed data
         let compressed_data = &data[42..];
@@ -55,16 +81,16 @@ impl ExceptionDecoder {
         // Decompress
         let decompressed = self.decompress_lzma(compressed_data)?;
 
-        // Check if pattern mode was used (pattern_db_length field used as flag)
-        let use_pattern_mode = header.pattern_db_length == 1;
-
-        // Decode based on mode
-        if use_pattern_mode {
-            self.decode_pattern(&decompressed)
-        } else {
+        // Payload layout (pattern_db_length field used as flag)
+        match header.pattern_db_length {
+            PAYLOAD_PATTERN => self.decode_pattern(&decompressed),
+            PAYLOAD_NGRAM => self.decode_ngram(&decompressed, &header),
             // Direct decompression - data is just the original text
-            String::from_utf8(decompressed)
-                .map_err(|e| ALICETextError::DecompressionError(format!("UTF-8 error: {e}")))
+            PAYLOAD_DIRECT => String::from_utf8(decompressed)
+                .map_err(|e| ALICETextError::DecompressionError(format!("UTF-8 error: {e}"))),
+            layout => Err(ALICETextError::DecompressionError(format!(
+                "Unknown v1 payload layout {layout}"
+            ))),
         }
     }
 
@@ -81,6 +107,28 @@ impl ExceptionDecoder {
         Ok(text)
     }
 
+    /// Decode n-gram exceptions by replaying the model's predictions
+    fn decode_ngram(&self, data: &[u8], header: &ExceptionHeader) -> Result<String> {
+        let encoding = NGramEncoding::from_bytes(data)?;
+        let mut model = match (&self.ngram_model, header.model_fingerprint) {
+            (_, 0) => NGramModel::new(usize::from(encoding.order)),
+            (Some(model), fp) if model.fingerprint() == fp => model.clone(),
+            (_, fp) => {
+                return Err(ALICETextError::DecompressionError(format!(
+                    "N-gram archive needs its primed model (fingerprint {fp:016x})"
+                )))
+            }
+        };
+        if model.order() != usize::from(encoding.order) {
+            return Err(ALICETextError::DecompressionError(format!(
+                "N-gram order mismatch: archive {}, model {}",
+                encoding.order,
+                model.order()
+            )));
+        }
+        encoding.decode(&mut model, header.original_length as usize)
+    }
+
     /// Parse binary payload format
     #[allow(clippy::unused_self)]
     fn parse_binary_payload(&self, data: &[u8]) -> Result<(Vec<PatternMatch>, String)> {
@@ -177,14 +225,30 @@ impl ExceptionDecoder {
         Ok((pattern_matches, processed_text))
     }
 
-    /// Decompress LZMA data
-    #[allow(clippy::unused_self)]
+    /// Decompress LZMA data, stopping at [`DecodeLimits::max_total_bytes`]
     fn decompress_lzma(&self, data: &[u8]) -> Result<Vec<u8>> {
-        let mut decompressed = Vec::new();
-        lzma_decompress(&mut std::io::Cursor::new(data), &mut decompressed).map_err(|e| {
+        let mut output = BoundedWriter {
+            buf: Vec::new(),
+            limit: self.limits.max_total_bytes,
+        };
+        // The dictionary never needs to outgrow the output it may produce
+        let options = LzmaOptions {
+            memlimit: Some(usize::try_from(self.limits.max_total_bytes).unwrap_or(usize::MAX)),
+            ..LzmaOptions::default()
+        };
+        let result =
+            lzma_decompress_with_options(&mut std::io::Cursor::new(data), &mut output, &options);
+        if output.buf.len() as u64 > output.limit {
+            return Err(ALICETextError::LimitExceeded {
+                field: "decoded LZMA payload",
+                value: output.buf.len() as u64,
+                limit: output.limit,
+            });
+        }
+        result.map_err(|e| {
             ALICETextError::DecompressionError(format!("LZMA decompression failed: {e}"))
         })?;
-        Ok(decompressed)
+        Ok(output.buf)
     }
 
     /// Get header from compressed data
@@ -225,7 +289,7 @@ impl ExceptionDecoder {
 
         // Verify version
         let version = (data[8], data[9]);
-        if version.0 > ALICE_TEXT_VERSION.0 {
+        if version > ALICE_TEXT_VERSION {
             return Ok(false);
         }
 
@@ -236,6 +300,34 @@ impl ExceptionDecoder {
     }
 }
 
+/// `Vec` sink that refuses writes once it holds more than `limit` bytes
+///
+/// The first byte past the limit is kept so the caller can tell an overrun
+/// from a decoder error.
+struct BoundedWriter {
+    buf: Vec<u8>,
+    limit: u64,
+}
+
+impl std::io::Write for BoundedWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let room = self
+            .limit
+            .saturating_add(1)
+            .saturating_sub(self.buf.len() as u64);
+        if room == 0 {
+            return Err(std::io::Error::other("decode limit exceeded"));
+        }
+        let take = data.len().min(usize::try_from(room).unwrap_or(usize::MAX));
+        self.buf.extend_from_slice(&data[..take]);
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 impl Default for ExceptionDecoder {
     fn default() -> Self {
         Self::new()
@@ -271,6 +363,42 @@ mod tests {
         assert_eq!(text, decompressed);
     }
 
+    #[test]
+    fn test_decode_primed_ngram() {
+        use crate::exception_encoder::PAYLOAD_NGRAM;
+        use crate::tokenizer::WhitespaceTokenizer;
+
+        // Latency follows from the item, so only the item id is a surprise
+        let line = |i: usize| format!("GET /api/items/{} 200 {}ms\n", i % 13, i % 13 * 7);
+        let corpus: String = (0..500).map(line).collect();
+        let text: String = (0..40).map(|i| line(i * 3)).collect();
+        let mut model = NGramModel::new(5);
+        model.train(&corpus, &WhitespaceTokenizer);
+
+        let encoder = ExceptionEncoder::new(EncodingMode::NGram).with_ngram_model(model.clone());
+        let encoded = encoder.encode(&text).unwrap();
+        assert!(encoded.exception_count * 6 < encoded.token_count);
+        let bytes = encoder.to_bytes(&encoded).unwrap();
+        let header = ExceptionDecoder::new().read_header(&bytes).unwrap();
+        assert_eq!(header.pattern_db_length, PAYLOAD_NGRAM);
+        assert_eq!(header.model_fingerprint, model.fingerprint());
+
+        let primed = ExceptionDecoder::new().with_ngram_model(model);
+        assert_eq!(primed.decode_from_bytes(&bytes).unwrap(), text);
+        assert!(ExceptionDecoder::new().decode_from_bytes(&bytes).is_err());
+
+        // Payload layouts and minors this build does not know are refused
+        let mut unknown = bytes.clone();
+        unknown[10 + 16..10 + 20].copy_from_slice(&(PAYLOAD_NGRAM + 1).to_le_bytes());
+        assert!(primed.decode_from_bytes(&unknown).is_err());
+        let mut newer = bytes;
+        newer[9] = ALICE_TEXT_VERSION.1 + 1;
+        assert!(matches!(
+            primed.decode_from_bytes(&newer),
+            Err(ALICETextError::InvalidVersion(1, _))
+        ));
+    }
+
     #[test]
     fn test_verify_valid_data() {
         let encoder = ExceptionEncoder::new(EncodingMode::Pattern);
@@ -348,6 +476,29 @@ mod tests {
         assert_eq!(text, decompressed);
     }
 
+    #[test]
+    fn test_decode_limits() {
+        let text = "ERROR disk full on /dev/sda1\n".repeat(4096);
+        let encoder = ExceptionEncoder::new(EncodingMode::Pattern);
+        let encoded = encoder.encode_to_bytes(&text).unwrap();
+        assert_eq!(
+            ExceptionDecoder::new().decode_from_bytes(&encoded).unwrap(),
+            text
+        );
+
+        let limited = ExceptionDecoder::new()
+            .with_decode_limits(DecodeLimits::new().with_max_total_bytes(1 << 10));
+        assert!(matches!(
+            limited.decode_from_bytes(&encoded),
+            Err(ALICETextError::LimitExceeded { .. })
+        ));
+
+        // A header understating the length is still stopped by the payload bound
+        let mut lying = encoded;
+        lying[14..18].copy_from_slice(&16u32.to_le_bytes());
+        assert!(limited.decode_from_bytes(&lying).is_err());
+    }
+
     #[test]
     fn test_decode_data_too_short() {
         let decoder = ExceptionDecoder::new();
@@ -2,10 +2,19 @@
 //!
 //! Encodes text by storing only the "exceptions" - tokens that differ from predictions.
 
+use crate::ngram_model::{segment, NGramEncoding, NGramModel};
 use crate::pattern_learner::{PatternDatabase, PatternLearner, PatternMatch};
-use crate::{ALICETextError, Result, ALICE_TEXT_MAGIC, ALICE_TEXT_VERSION};
+use crate::tokenizer::{Tokenizer, WhitespaceTokenizer};
+use crate::{to_u16, to_u32, ALICETextError, Result, ALICE_TEXT_MAGIC, ALICE_TEXT_VERSION};
 use lzma_rs::lzma_compress;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// `ExceptionHeader::pattern_db_length` values: how the payload is laid out
+pub(crate) const PAYLOAD_DIRECT: u32 = 0;
+pub(crate) const PAYLOAD_PATTERN: u32 = 1;
+/// [`NGramEncoding`] exceptions (since v1.1; 1.0 readers misread it as direct)
+pub(crate) const PAYLOAD_NGRAM: u32 = 2;
 
 /// Encoding mode for compression
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -13,7 +22,8 @@ pub enum EncodingMode {
     /// Pattern-based encoding (lightweight, good for structured logs)
     #[default]
     Pattern,
-    /// N-gram based encoding (better compression, more CPU)
+    /// N-gram token prediction: only mispredicted tokens are stored
+    /// (better on repetitive prose and logs, more CPU)
     NGram,
 }
 
@@ -32,6 +42,9 @@ pub struct ExceptionHeader {
     pub pattern_db_length: u32,
     /// Compressed data length
     pub compressed_length: u32,
+    /// [`NGramModel::fingerprint`] of the model an n-gram payload was
+    /// primed with (0 = untrained)
+    pub model_fingerprint: u64,
 }
 
 impl ExceptionHeader {
@@ -48,6 +61,7 @@ impl ExceptionHeader {
             exception_count: 0,
             pattern_db_length: 0,
             compressed_length: 0,
+            model_fingerprint: 0,
         }
     }
 
@@ -80,8 +94,8 @@ impl ExceptionHeader {
         // Compressed length (4 bytes)
         bytes[20..24].copy_from_slice(&self.compressed_length.to_le_bytes());
 
-        // Reserved (8 bytes)
-        bytes[24..32].copy_from_slice(&[0; 8]);
+        // Model fingerprint (8 bytes, zero when unused)
+        bytes[24..32].copy_from_slice(&self.model_fingerprint.to_le_bytes());
 
         bytes
     }
@@ -116,6 +130,7 @@ impl ExceptionHeader {
             exception_count: u32::from_le_bytes(bytes[12..16].try_into().map_err(|_| to_err())?),
             pattern_db_length: u32::from_le_bytes(bytes[16..20].try_into().map_err(|_| to_err())?),
             compressed_length: u32::from_le_bytes(bytes[20..24].try_into().map_err(|_| to_err())?),
+            model_fingerprint: u64::from_le_bytes(bytes[24..32].try_into().map_err(|_| to_err())?),
         })
     }
 }
@@ -133,6 +148,8 @@ pub struct EncodedText {
     pub processed_text: String,
     /// Original text (for direct compression mode)
     pub original_text: String,
+    /// Mispredicted segments (n-gram mode)
+    pub ngram: Option<NGramEncoding>,
     /// Token count
     pub token_count: usize,
     /// Exception count
@@ -145,6 +162,10 @@ pub struct ExceptionEncoder {
     mode: EncodingMode,
     /// Pattern learner
     pattern_learner: PatternLearner,
+    /// Splits text for `token_count` / `exception_count`
+    tokenizer: Arc<dyn Tokenizer>,
+    /// Starting state of the n-gram predictor
+    ngram_model: NGramModel,
 }
 
 impl ExceptionEncoder {
@@ -154,9 +175,26 @@ impl ExceptionEncoder {
         Self {
             mode,
             pattern_learner: PatternLearner::new(),
+            tokenizer: Arc::new(WhitespaceTokenizer),
+            ngram_model: NGramModel::default(),
         }
     }
 
+    /// Start n-gram prediction from `model` (e.g. one primed with
+    /// [`NGramModel::train`]); decoders need the same model
+    #[must_use]
+    pub fn with_ngram_model(mut self, model: NGramModel) -> Self {
+        self.ngram_model = model;
+        self
+    }
+
+    /// Count tokens with `tokenizer` instead of splitting on whitespace
+    #[must_use]
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
     /// Encode text to `EncodedText` structure
     ///
     /// # Errors
@@ -170,7 +208,6 @@ impl ExceptionEncoder {
     }
 
     /// Pattern-based encoding
-    #[allow(clippy::unnecessary_wraps)]
     fn encode_pattern(&self, text: &str) -> Result<EncodedText> {
         // Learn patterns
         let pattern_db = self.pattern_learner.learn(text);
@@ -178,14 +215,14 @@ impl ExceptionEncoder {
         // Replace patterns with placeholders
         let (processed_text, pattern_matches) = self.pattern_learner.replace_patterns(text);
 
-        // Count tokens (simple whitespace tokenization for stats)
-        let token_count = text.split_whitespace().count();
+        // Count tokens (stats only; does not affect the payload)
+        let token_count = self.tokenizer.count(text);
         let exception_count = pattern_matches.len();
 
         let mut header = ExceptionHeader::new(self.mode);
-        header.original_length = text.len() as u32;
-        header.token_count = token_count as u32;
-        header.exception_count = exception_count as u32;
+        header.original_length = to_u32(text.len(), "v1 original length")?;
+        header.token_count = to_u32(token_count, "v1 token count")?;
+        header.exception_count = to_u32(exception_count, "v1 exception count")?;
 
         Ok(EncodedText {
             header,
@@ -193,20 +230,25 @@ impl ExceptionEncoder {
             pattern_matches,
             processed_text,
             original_text: text.to_string(),
+            ngram: None,
             token_count,
             exception_count,
         })
     }
 
-    /// N-gram based encoding (simplified - stores compressed text)
-    #[allow(clippy::unnecessary_wraps)]
+    /// N-gram based encoding: segments (tokens and the gaps between them)
+    /// the model predicts are dropped, the rest become exceptions
     fn encode_ngram(&self, text: &str) -> Result<EncodedText> {
-        let token_count = text.split_whitespace().count();
+        let segments = segment(text, self.tokenizer.as_ref());
+        let ngram = NGramEncoding::encode(&mut self.ngram_model.clone(), &segments);
+        let token_count = segments.len();
+        let exception_count = ngram.exceptions.len();
 
         let mut header = ExceptionHeader::new(self.mode);
-        header.original_length = text.len() as u32;
-        header.token_count = token_count as u32;
-        header.exception_count = token_count as u32; // All tokens are exceptions in simplified mode
+        header.original_length = to_u32(text.len(), "v1 original length")?;
+        header.token_count = to_u32(token_count, "v1 token count")?;
+        header.exception_count = to_u32(exception_count, "v1 exception count")?;
+        header.model_fingerprint = self.ngram_model.fingerprint();
 
         Ok(EncodedText {
             header,
@@ -214,8 +256,9 @@ impl ExceptionEncoder {
             pattern_matches: Vec::new(),
             processed_text: text.to_string(),
             original_text: text.to_string(),
+            ngram: Some(ngram),
             token_count,
-            exception_count: token_count,
+            exception_count,
         })
     }
 
@@ -250,30 +293,45 @@ impl ExceptionEncoder {
 
         // Try pattern-based approach: store pattern values separately
         // This can help when patterns are highly repetitive
-        let (payload, use_pattern_mode) =
-            if self.mode == EncodingMode::Pattern && !encoded.pattern_matches.is_empty() {
-                // Calculate overhead of pattern storage vs direct LZMA
-                let pattern_payload = self.create_payload(encoded)?;
-                let direct_compressed = self.compress_lzma(text_bytes)?;
-                let pattern_compressed = self.compress_lzma(&pattern_payload)?;
-
-                // Use pattern mode only if it results in smaller output
-                if pattern_compressed.len() < direct_compressed.len() {
-                    (pattern_compressed, true)
-                } else {
-                    (direct_compressed, false)
-                }
+        // Matches that overflow the payload's u16/u32 fields force direct mode
+        let (payload, payload_mode) = if self.mode == EncodingMode::Pattern
+            && !encoded.pattern_matches.is_empty()
+            && Self::payload_fits(encoded)
+        {
+            // Calculate overhead of pattern storage vs direct LZMA
+            let pattern_payload = self.create_payload(encoded)?;
+            let direct_compressed = self.compress_lzma(text_bytes)?;
+            let pattern_compressed = self.compress_lzma(&pattern_payload)?;
+
+            // Use pattern mode only if it results in smaller output
+            if pattern_compressed.len() < direct_compressed.len() {
+                (pattern_compressed, PAYLOAD_PATTERN)
             } else {
-                // Direct compression
-                (self.compress_lzma(text_bytes)?, false)
-            };
+                (direct_compressed, PAYLOAD_DIRECT)
+            }
+        } else if let (EncodingMode::NGram, Some(ngram)) = (self.mode, &encoded.ngram) {
+            // Same rule: exceptions only when they beat plain LZMA
+            let ngram_compressed = self.compress_lzma(&ngram.to_bytes()?)?;
+            let direct_compressed = self.compress_lzma(text_bytes)?;
+            if ngram_compressed.len() < direct_compressed.len() {
+                (ngram_compressed, PAYLOAD_NGRAM)
+            } else {
+                (direct_compressed, PAYLOAD_DIRECT)
+            }
+        } else {
+            // Direct compression
+            (self.compress_lzma(text_bytes)?, PAYLOAD_DIRECT)
+        };
 
         // Update header with sizes and mode flag
         let mut header = encoded.header.clone();
-        header.compressed_length = payload.len() as u32;
+        header.compressed_length = to_u32(payload.len(), "v1 compressed length")?;
 
-        // Use pattern_db_length field as mode flag (0 = direct, 1 = pattern)
-        header.pattern_db_length = u32::from(use_pattern_mode);
+        // Use pattern_db_length field as payload flag (see `PAYLOAD_*`)
+        header.pattern_db_length = payload_mode;
+        if payload_mode != PAYLOAD_NGRAM {
+            header.model_fingerprint = 0;
+        }
 
         // Write header
         result.extend_from_slice(&header.to_bytes());
@@ -284,8 +342,16 @@ impl ExceptionEncoder {
         Ok(result)
     }
 
+    /// Whether every pattern match fits the fixed-width payload fields
+    fn payload_fits(encoded: &EncodedText) -> bool {
+        u32::try_from(encoded.pattern_matches.len()).is_ok()
+            && u32::try_from(encoded.processed_text.len()).is_ok()
+            && encoded.pattern_matches.iter().all(|mat| {
+                u32::try_from(mat.end).is_ok() && u16::try_from(mat.matched_text.len()).is_ok()
+            })
+    }
+
     /// Create payload for compression
-    #[allow(clippy::unnecessary_wraps)]
     #[allow(clippy::unused_self)]
     fn create_payload(&self, encoded: &EncodedText) -> Result<Vec<u8>> {
         // Use a compact binary format:
@@ -306,7 +372,7 @@ impl ExceptionEncoder {
         let mut payload = Vec::new();
 
         // Write match count
-        let match_count = encoded.pattern_matches.len() as u32;
+        let match_count = to_u32(encoded.pattern_matches.len(), "v1 match count")?;
         payload.extend_from_slice(&match_count.to_le_bytes());
 
         // Write matches
@@ -330,18 +396,20 @@ impl ExceptionEncoder {
             payload.push(pt);
 
             // Start and end positions
-            payload.extend_from_slice(&(mat.start as u32).to_le_bytes());
-            payload.extend_from_slice(&(mat.end as u32).to_le_bytes());
+            payload.extend_from_slice(&to_u32(mat.start, "v1 match offset")?.to_le_bytes());
+            payload.extend_from_slice(&to_u32(mat.end, "v1 match offset")?.to_le_bytes());
 
             // Matched text
             let text_bytes = mat.matched_text.as_bytes();
-            payload.extend_from_slice(&(text_bytes.len() as u16).to_le_bytes());
+            payload.extend_from_slice(&to_u16(text_bytes.len(), "v1 match length")?.to_le_bytes());
             payload.extend_from_slice(text_bytes);
         }
 
         // Write processed text
         let text_bytes = encoded.processed_text.as_bytes();
-        payload.extend_from_slice(&(text_bytes.len() as u32).to_le_bytes());
+        payload.extend_from_slice(
+            &to_u32(text_bytes.len(), "v1 processed text length")?.to_le_bytes(),
+        );
         payload.extend_from_slice(text_bytes);
 
         Ok(payload)
@@ -395,6 +463,24 @@ mod tests {
         assert!(encoded.header.original_length > 0);
     }
 
+    #[test]
+    fn test_match_longer_than_u16() {
+        // A 70 KB URL does not fit the payload's u16 match length: direct mode
+        let url = format!("https://example.com/{}", "a".repeat(70_000));
+        let text = format!("2024-01-15 10:30:00 INFO fetch {url}\n").repeat(3);
+        let encoded = ExceptionEncoder::new(EncodingMode::Pattern)
+            .encode(&text)
+            .unwrap();
+        assert!(encoded
+            .pattern_matches
+            .iter()
+            .any(|m| m.matched_text.len() > usize::from(u16::MAX)));
+        assert!(!ExceptionEncoder::payload_fits(&encoded));
+
+        let bytes = crate::compress(&text, EncodingMode::Pattern).unwrap();
+        assert_eq!(crate::decompress(&bytes).unwrap(), text);
+    }
+
     #[test]
     fn test_encode_to_bytes() {
         let encoder = ExceptionEncoder::new(EncodingMode::Pattern);
@@ -410,6 +496,19 @@ mod tests {
         assert_eq!(bytes[9], ALICE_TEXT_VERSION.1);
     }
 
+    #[test]
+    fn test_tokenizer_counts() {
+        let text = "ログイン成功 user=alice";
+        let default = ExceptionEncoder::new(EncodingMode::Pattern);
+        assert_eq!(default.encode(text).unwrap().token_count, 2);
+
+        let words = ExceptionEncoder::new(EncodingMode::Pattern)
+            .with_tokenizer(crate::tokenizer::UnicodeWordTokenizer);
+        let encoded = words.encode(text).unwrap();
+        assert_eq!(encoded.token_count, 5);
+        assert_eq!(encoded.header.token_count, 5);
+    }
+
     #[test]
     fn test_ngram_encoding() {
         let encoder = ExceptionEncoder::new(EncodingMode::NGram);
@@ -107,16 +107,19 @@ pub unsafe extern "C" fn alice_text_compress(
         Err(_) => return empty,
     };
     match alice.compress(text_str) {
-        Ok(mut data) => {
-            let len = data.len() as u32;
-            let ptr = data.as_mut_ptr();
-            std::mem::forget(data);
-            AliceTextCompressedData { data: ptr, len }
-        }
+        Ok(data) => into_c_data(data).unwrap_or(empty),
         Err(_) => empty,
     }
 }
 
+/// Hand `data` to C (`None` if its length does not fit the `u32` field)
+fn into_c_data(data: Vec<u8>) -> Option<AliceTextCompressedData> {
+    let len = u32::try_from(data.len()).ok()?;
+    // Exact capacity, as `alice_text_data_free` rebuilds the Vec with `len`
+    let data = Box::into_raw(data.into_boxed_slice()).cast::<u8>();
+    Some(AliceTextCompressedData { data, len })
+}
+
 /// Decompress data back to a UTF-8 string.
 /// Caller must free the returned string with `alice_text_string_free`.
 ///
@@ -143,7 +146,7 @@ pub unsafe extern "C" fn alice_text_decompress(
     }
 }
 
-/// Compress with a specified mode (0=Fast, 1=Balanced, 2=Best).
+/// Compress with a specified mode (0=Fast, 1=Balanced, 2=Best, 3=Max).
 /// Caller must free with `alice_text_data_free`.
 ///
 /// # Safety
@@ -169,15 +172,11 @@ pub unsafe extern "C" fn alice_text_compress_tuned(
     let compression_mode = match mode {
         0 => CompressionMode::Fast,
         2 => CompressionMode::Best,
+        3 => CompressionMode::Max,
         _ => CompressionMode::Balanced,
     };
     match crate::compress_tuned(text_str, compression_mode) {
-        Ok(mut data) => {
-            let len = data.len() as u32;
-            let ptr = data.as_mut_ptr();
-            std::mem::forget(data);
-            AliceTextCompressedData { data: ptr, len }
-        }
+        Ok(data) => into_c_data(data).unwrap_or(empty),
         Err(_) => empty,
     }
 }
@@ -315,7 +314,9 @@ pub unsafe extern "C" fn alice_text_dialogue_add(
         Ok(s) => s,
         Err(_) => return 0,
     };
-    let speaker_idx = table.speakers.insert(speaker_str);
+    let Ok(speaker_idx) = table.speakers.insert(speaker_str) else {
+        return 0;
+    };
     table.add(DialogueEntry {
         id,
         speaker: speaker_idx,
@@ -360,7 +361,7 @@ pub unsafe extern "C" fn alice_text_dialogue_count(handle: AliceDialogueTableHan
         return 0;
     }
     let table = unsafe { &*handle };
-    table.len() as u32
+    u32::try_from(table.len()).unwrap_or(u32::MAX)
 }
 
 /// Get unique character count across all dialogue (useful for font atlas).
@@ -564,7 +565,7 @@ mod tests {
     #[test]
     fn test_compress_modes() {
         let text = CString::new("Mode test data for compression").unwrap();
-        for mode in [0u8, 1, 2] {
+        for mode in [0u8, 1, 2, 3] {
             let compressed = unsafe { alice_text_compress_tuned(text.as_ptr(), mode) };
             assert!(!compressed.data.is_null());
             unsafe { alice_text_data_free(compressed.data, compressed.len) };
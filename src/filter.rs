@@ -0,0 +1,214 @@
+//! Filter expressions — operators and the `column op value` syntax
+//!
+//! Shared by the query engine, saved views and retention, so archives can
+//! carry and prune by filters without the query feature. [`parse_filter`]
+//! splits `log_levels=ERROR` or `ipv4 within 10.0.0.0/8` into a column, an
+//! [`Op`] and a value; [`parse_relative_time`] resolves `now-1h` style
+//! bounds.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use crate::columnar_encoder::parse_duration;
+use crate::{ALICETextError, Result};
+use chrono::NaiveDateTime;
+
+/// Comparison operators
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    StartsWith,
+    EndsWith,
+    /// Equal to one of a comma-separated list (`ERROR,FATAL`)
+    In,
+    /// Equal to none of a comma-separated list
+    NotIn,
+    /// Address inside a CIDR block (`10.0.0.0/8`, `2001:db8::/32`);
+    /// `ipv4` / `ipv6` columns only
+    InCidr,
+    /// Inside an inclusive range `low..high` (`now-2h..now-1h`)
+    Between,
+}
+
+impl Op {
+    /// Values to compare against: the list items for [`Op::In`] /
+    /// [`Op::NotIn`], both bounds for [`Op::Between`], otherwise `value` itself
+    pub fn operands(self, value: &str) -> impl Iterator<Item = &str> {
+        let separator = match self {
+            Self::In | Self::NotIn => Some(","),
+            Self::Between => Some(".."),
+            _ => None,
+        };
+        let mut items = separator.map(|sep| value.split(sep).map(str::trim));
+        let mut whole = Some(value);
+        std::iter::from_fn(move || match &mut items {
+            Some(items) => items.next(),
+            None => whole.take(),
+        })
+    }
+}
+/// Parse a filter expression: `column=value`, `column!=value`, `column>=value`,
+/// `column<=value`, `column>value`, `column<value`, `column~value`,
+/// `column^=value` (starts with), `column$=value` (ends with),
+/// `column in a,b`, `column not in a,b`, `column within 10.0.0.0/8` and
+/// `column between low..high`
+///
+/// Returns `(column, op, value)` with surrounding whitespace trimmed, or `None`
+/// if no operator is present.
+#[must_use]
+pub fn parse_filter(filter: &str) -> Option<(&str, Op, &str)> {
+    // `in` only counts after a bare column name, so `messages=log in` is an Eq
+    const KEYWORDS: [(&str, Op); 4] = [
+        (" not in ", Op::NotIn),
+        (" in ", Op::In),
+        (" within ", Op::InCidr),
+        (" between ", Op::Between),
+    ];
+    let lower = filter.to_ascii_lowercase();
+    let keyword = KEYWORDS.iter().find_map(|&(token, op)| {
+        let at = lower.find(token)?;
+        let col = filter[..at].trim();
+        let bare = !col.is_empty() && col.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+        bare.then(|| (col, op, filter[at + token.len()..].trim()))
+    });
+    if keyword.is_some() {
+        return keyword;
+    }
+
+    // Order matters: check multi-char operators first
+    const OPERATORS: [(&str, Op); 9] = [
+        ("!=", Op::Ne),
+        ("^=", Op::StartsWith),
+        ("$=", Op::EndsWith),
+        (">=", Op::Ge),
+        ("<=", Op::Le),
+        (">", Op::Gt),
+        ("<", Op::Lt),
+        ("~", Op::Contains),
+        ("=", Op::Eq),
+    ];
+
+    OPERATORS.iter().find_map(|&(token, op)| {
+        filter
+            .split_once(token)
+            .map(|(col, val)| (col.trim(), op, val.trim()))
+    })
+}
+
+/// Split a leading `not ` or `!` off a filter expression
+///
+/// Returns whether the expression was negated and the rest, for
+/// `QueryEngine::filter_except`: `not log_levels=ERROR` keeps the lines
+/// without an ERROR level.
+#[must_use]
+pub fn parse_negation(filter: &str) -> (bool, &str) {
+    let filter = filter.trim_start();
+    if filter
+        .get(..4)
+        .is_some_and(|word| word.eq_ignore_ascii_case("not "))
+    {
+        return (true, filter[4..].trim_start());
+    }
+    match filter.strip_prefix('!') {
+        Some(rest) if !rest.starts_with('=') => (true, rest.trim_start()),
+        _ => (false, filter),
+    }
+}
+
+/// Parse a time relative to `now_ms`: `now`, `now-1h`, `now+30m`, `-15m`,
+/// `-7d` (units `ms`, `s`, `m`, `h`, `d`, combined as in `1h30m`)
+///
+/// Returns `None` if `s` is not a relative expression.
+#[must_use]
+pub fn parse_relative_time(s: &str, now_ms: i64) -> Option<i64> {
+    let s = s.trim();
+    let offset = s.strip_prefix("now").unwrap_or(s);
+    if offset.is_empty() {
+        return (s == "now").then_some(now_ms);
+    }
+    let (sign, span) = match offset.as_bytes()[0] {
+        b'-' => (-1, &offset[1..]),
+        b'+' if offset.len() < s.len() => (1, &offset[1..]),
+        _ => return None,
+    };
+    let (days, rest) = match span.split_once('d') {
+        Some((days, rest)) => (days.parse::<i64>().ok()?, rest),
+        None => (0, span),
+    };
+    let nanos = if rest.is_empty() {
+        0
+    } else {
+        parse_duration(rest)?.0
+    };
+    let span_ms = days
+        .checked_mul(86_400_000)?
+        .checked_add(i64::try_from(nanos / 1_000_000).ok()?)?;
+    now_ms.checked_add(sign * span_ms)
+}
+
+/// Parse a timestamp string to Unix milliseconds (i64)
+/// Supports multiple formats: "YYYY-MM-DD HH:MM:SS", "YYYY-MM-DDTHH:MM:SS", etc.
+pub(crate) fn parse_timestamp_ms(s: &str) -> Result<i64> {
+    // Try common formats
+    let formats = [
+        "%Y-%m-%d %H:%M:%S",    // 2024-01-15 10:30:45
+        "%Y-%m-%dT%H:%M:%S",    // 2024-01-15T10:30:45
+        "%Y-%m-%d %H:%M:%S%.f", // 2024-01-15 10:30:45.123
+        "%Y-%m-%dT%H:%M:%S%.f", // 2024-01-15T10:30:45.123
+        "%Y-%m-%d",             // 2024-01-15 (assumes 00:00:00)
+    ];
+
+    for fmt in &formats {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Ok(dt.and_utc().timestamp_millis());
+        }
+    }
+
+    // Try date-only format with time defaulting to 00:00:00
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let dt = date.and_hms_opt(0, 0, 0).unwrap();
+        return Ok(dt.and_utc().timestamp_millis());
+    }
+
+    Err(ALICETextError::InvalidQuery(format!(
+        "Invalid timestamp format: {s}. Expected YYYY-MM-DD HH:MM:SS"
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter() {
+        let (col, op, val) = parse_filter("log_levels = ERROR").unwrap();
+        assert_eq!(col, "log_levels");
+        assert!(matches!(op, Op::Eq));
+        assert_eq!(val, "ERROR");
+
+        let (col, op, val) = parse_filter("timestamps>=2024-01-15 10:30:46").unwrap();
+        assert_eq!(col, "timestamps");
+        assert!(matches!(op, Op::Ge));
+        assert_eq!(val, "2024-01-15 10:30:46");
+
+        assert!(matches!(
+            parse_filter("ipv4!=10.0.0.1"),
+            Some((_, Op::Ne, _))
+        ));
+        assert!(matches!(
+            parse_filter("urls~example"),
+            Some((_, Op::Contains, _))
+        ));
+        assert!(matches!(
+            parse_filter("ipv4^=10."),
+            Some(("ipv4", Op::StartsWith, "10."))
+        ));
+        assert!(parse_filter("no operator").is_none());
+    }
+}
@@ -7,6 +7,7 @@
 //! - Character set extraction from compressed text
 //! - Text shaping with ALICE-Font parameters
 //! - Dialogue table charset extraction for atlas preloading
+//! - Charset deltas between scenes/tables/locales for incremental atlas updates
 //!
 //! License: BSL 1.1
 //! Author: Moroya Sakamoto
@@ -15,7 +16,9 @@ use alice_font::atlas::SdfAtlas;
 use alice_font::param::MetaFontParams;
 use alice_font::shaper::{ShapedLine, TextShaper};
 
-use crate::dialogue::{DialogueTable, LocalizationTable};
+use std::collections::HashMap;
+
+use crate::dialogue::{DialogueTable, LocaleId, LocalizationTable};
 
 // ── FNV-1a (file-local) ───────────────────────────────────────
 #[inline(always)]
@@ -52,6 +55,28 @@ impl CharacterSet {
         }
     }
 
+    /// Create from several texts (e.g. every line of a scene)
+    pub fn from_texts<'a>(texts: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut chars: Vec<char> = texts.into_iter().flat_map(str::chars).collect();
+        chars.sort_unstable();
+        chars.dedup();
+        Self::from_sorted_chars(chars)
+    }
+
+    /// Wrap sorted unique characters, hashing their UTF-8 encoding
+    fn from_sorted_chars(chars: Vec<char>) -> Self {
+        let mut buf = Vec::new();
+        for ch in &chars {
+            let mut b = [0u8; 4];
+            let s = ch.encode_utf8(&mut b);
+            buf.extend_from_slice(s.as_bytes());
+        }
+        Self {
+            content_hash: fnv1a(&buf),
+            chars,
+        }
+    }
+
     /// Create from compressed ALICE-Text data (decompress first)
     pub fn from_compressed(data: &[u8]) -> crate::Result<Self> {
         let alice = crate::ALICEText::default();
@@ -84,15 +109,62 @@ impl CharacterSet {
         chars.extend_from_slice(&other.chars);
         chars.sort_unstable();
         chars.dedup();
-        let mut buf = Vec::new();
-        for ch in &chars {
-            let mut b = [0u8; 4];
-            let s = ch.encode_utf8(&mut b);
-            buf.extend_from_slice(s.as_bytes());
+        Self::from_sorted_chars(chars)
+    }
+
+    /// Characters `next` needs that `self` lacks, and those it no longer needs
+    pub fn diff(&self, next: &Self) -> CharsetDelta {
+        let mut delta = CharsetDelta::default();
+        if self.content_hash == next.content_hash && self.chars == next.chars {
+            return delta;
         }
-        Self {
-            chars,
-            content_hash: fnv1a(&buf),
+        let (mut old, mut new) = (self.chars.iter().peekable(), next.chars.iter().peekable());
+        loop {
+            match (old.peek(), new.peek()) {
+                (Some(&&a), Some(&&b)) if a == b => {
+                    old.next();
+                    new.next();
+                }
+                (Some(&&a), Some(&&b)) if a < b => {
+                    delta.removed.push(a);
+                    old.next();
+                }
+                (_, Some(&&b)) => {
+                    delta.added.push(b);
+                    new.next();
+                }
+                (Some(&&a), None) => {
+                    delta.removed.push(a);
+                    old.next();
+                }
+                (None, None) => break,
+            }
+        }
+        delta
+    }
+}
+
+// ── Charset Delta ──────────────────────────────────────────────
+
+/// Incremental atlas update between two character sets (both sorted)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharsetDelta {
+    /// Characters newly required
+    pub added: Vec<char>,
+    /// Characters no longer needed (candidates for eviction)
+    pub removed: Vec<char>,
+}
+
+impl CharsetDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Preload the newly required characters; eviction of `removed` is left
+    /// to the caller's atlas policy
+    pub fn preload_atlas(&self, atlas: &mut SdfAtlas) {
+        if !self.added.is_empty() {
+            atlas.preload(&self.added);
         }
     }
 }
@@ -195,32 +267,63 @@ pub fn shape_text_with_config(text: &str, config: &FontPipelineConfig) -> Shaped
 
 /// Extract character set from a dialogue table (for atlas preloading)
 pub fn dialogue_charset(table: &DialogueTable) -> CharacterSet {
-    let chars = table.unique_chars();
-    let mut buf = Vec::new();
-    for ch in &chars {
-        let mut b = [0u8; 4];
-        let s = ch.encode_utf8(&mut b);
-        buf.extend_from_slice(s.as_bytes());
-    }
-    CharacterSet {
-        chars,
-        content_hash: fnv1a(&buf),
-    }
+    CharacterSet::from_sorted_chars(table.unique_chars())
 }
 
 /// Extract character set from all locales in a localization table
 pub fn localization_charset(table: &LocalizationTable) -> CharacterSet {
-    let chars = table.all_unique_chars();
-    let mut buf = Vec::new();
-    for ch in &chars {
-        let mut b = [0u8; 4];
-        let s = ch.encode_utf8(&mut b);
-        buf.extend_from_slice(s.as_bytes());
-    }
-    CharacterSet {
-        chars,
-        content_hash: fnv1a(&buf),
-    }
+    CharacterSet::from_sorted_chars(table.all_unique_chars())
+}
+
+/// Character set of one locale (base entries with that locale's overrides)
+pub fn locale_charset(table: &LocalizationTable, locale: LocaleId) -> CharacterSet {
+    CharacterSet::from_texts(
+        table
+            .base_table
+            .iter()
+            .filter_map(|base| table.get(locale, base.id))
+            .map(|entry| entry.text.as_str()),
+    )
+}
+
+/// Charset delta between two versions of a dialogue table
+pub fn dialogue_charset_delta(old: &DialogueTable, new: &DialogueTable) -> CharsetDelta {
+    dialogue_charset(old).diff(&dialogue_charset(new))
+}
+
+/// Charset delta between two scenes' text sets
+pub fn scene_charset_delta<'a>(
+    old: impl IntoIterator<Item = &'a str>,
+    new: impl IntoIterator<Item = &'a str>,
+) -> CharsetDelta {
+    CharacterSet::from_texts(old).diff(&CharacterSet::from_texts(new))
+}
+
+/// Per-locale charset deltas between two versions of a localization table
+///
+/// Covers every locale present in either version; unchanged locales are
+/// omitted, and a dropped locale reports all its characters as removed.
+pub fn localization_charset_deltas(
+    old: &LocalizationTable,
+    new: &LocalizationTable,
+) -> HashMap<LocaleId, CharsetDelta> {
+    let mut locales = old.available_locales();
+    locales.extend(new.available_locales());
+    locales.sort_by_key(|l| l.0);
+    locales.dedup();
+
+    let charset = |table: &LocalizationTable, locale: LocaleId| {
+        if table.available_locales().contains(&locale) {
+            locale_charset(table, locale)
+        } else {
+            CharacterSet::from_sorted_chars(Vec::new())
+        }
+    };
+    locales
+        .into_iter()
+        .map(|locale| (locale, charset(old, locale).diff(&charset(new, locale))))
+        .filter(|(_, delta)| !delta.is_empty())
+        .collect()
 }
 
 #[cfg(test)]
@@ -336,4 +439,38 @@ mod tests {
         assert!(cs.contains('x'));
         assert_eq!(cs.len(), 6); // a,b,c,x,y,z
     }
+
+    #[test]
+    fn test_charset_diff() {
+        let delta = scene_charset_delta(["abc", "d"], ["bcd", "xy"]);
+        assert_eq!(delta.added, vec!['x', 'y']);
+        assert_eq!(delta.removed, vec!['a']);
+        assert!(CharacterSet::from_text("abc")
+            .diff(&CharacterSet::from_text("cab"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_localization_charset_deltas() {
+        let entry = |id, text: &str| DialogueEntry {
+            id,
+            speaker: 0,
+            text: text.to_string(),
+            ruby: None,
+        };
+        let mut old = LocalizationTable::new(LocaleId::JA);
+        old.base_table.add(entry(0, "あい"));
+        old.add_delta(LocaleId::EN, entry(0, "hi"));
+
+        let mut new = old.clone();
+        new.add_delta(LocaleId::EN, entry(0, "ho"));
+        new.add_delta(LocaleId::FR, entry(0, "é"));
+
+        let deltas = localization_charset_deltas(&old, &new);
+        assert!(!deltas.contains_key(&LocaleId::JA));
+        assert_eq!(deltas[&LocaleId::EN].added, vec!['o']);
+        assert_eq!(deltas[&LocaleId::EN].removed, vec!['i']);
+        assert_eq!(deltas[&LocaleId::FR].added, vec!['é']);
+        assert_eq!(locale_charset(&new, LocaleId::EN).chars, vec!['h', 'o']);
+    }
 }
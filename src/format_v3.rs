@@ -17,26 +17,398 @@
 //! ├─────────────────────────────────────────┤
 //! │ Column Directory (variable)             │
 //! ├─────────────────────────────────────────┤
-//! │ Column 0 Data (Zstd)                    │
+//! │ Column Summaries (Bincode, optional)    │
 //! ├─────────────────────────────────────────┤
-//! │ Column 1 Data (Zstd)                    │
+//! │ Pattern Config (Bincode, optional)      │
+//! ├─────────────────────────────────────────┤
+//! │ Header Extensions (TLV, optional)       │
+//! ├─────────────────────────────────────────┤
+//! │ Hot Columns (Zstd)                      │
 //! ├─────────────────────────────────────────┤
 //! │ ...                                     │
 //! ├─────────────────────────────────────────┤
-//! │ Skeleton Data (Zstd)                    │
+//! │ Cold Columns, Skeleton (Zstd)           │
 //! └─────────────────────────────────────────┘
 //! ```
+//!
+//! ## Column Order
+//!
+//! Column offsets are absolute, so readers accept any order. The writer
+//! groups the data by [`ColumnType::layout_tier`]: the most queried columns
+//! (`timestamps`, `log_levels`, `numbers`) first, then the other typed
+//! filter columns, string columns, `others`, and finally the skeleton and
+//! the placeholder map. Filters then touch one contiguous stretch of a
+//! memory map, and [`FormatV3Metadata::prefix_len`] tells how short a
+//! ranged read of an archive in object storage can be.
+//!
+//! ## Header Extensions
+//!
+//! New metadata goes into a TLV area instead of new header fields. When
+//! [`FormatV3Header::FLAG_EXTENSIONS`] is set, `extension_size` bytes of
+//! records follow the pattern config:
+//!
+//! ```text
+//! TAG u16 LE | LEN u32 LE | VALUE (LEN bytes)
+//! ```
+//!
+//! Column offsets are absolute, so readers that predate the area skip it
+//! without knowing it exists, and archives without the flag read as having
+//! no extensions. Readers skip unknown tags, except tags with
+//! [`HeaderExtension::CRITICAL`] set: those mark data a reader must understand
+//! to decode correctly (e.g. encryption) and make it refuse the archive.
+//!
+//! The writer always emits [`HeaderExtension::VALUE_BYTES`] (input bytes held
+//! in value columns, `u64` LE) so the exception rate is readable from the
+//! header; records with that tag passed to the writer are replaced.
+//!
+//! Archives with [column plugin](crate::column_plugin) values carry the
+//! critical [`HeaderExtension::PLUGIN_SCHEMA`] record (plugin ids, counts
+//! and stats) next to the [`ColumnType::Plugins`] column.
+//!
+//! [Saved views](crate::saved_view) (named queries) travel in the optional
+//! [`HeaderExtension::SAVED_VIEWS`] record, and the
+//! [producer](crate::producer) (versions and settings of the writing build)
+//! in [`HeaderExtension::PRODUCER`], which is replaced on every write.
+//!
+//! Integer columns (`ipv4`, `log_levels`, `pids`, ...) and `numbers` are
+//! written in the [`ColumnEncoding`] (plain, dictionary, run-length, delta
+//! or byte shuffle) whose Zstd output is smallest on a sample of the
+//! column. The critical
+//! [`HeaderExtension::COLUMN_ENCODINGS`] record lists the columns not stored
+//! plain, and readers put the tag into [`ColumnEntry::encoding`].
+//!
+//! Columns compressed with a [`ZstdParams`](crate::ZstdParams) window
+//! beyond the decoder default carry the critical
+//! [`HeaderExtension::ZSTD_WINDOW`] record (the window log, one byte);
+//! readers check it and put it into [`ColumnEntry::window_log`].
+//!
+//! Every writer also emits [`HeaderExtension::CHECKSUM`]: FNV-1a 64 of the
+//! column data, from the end of the extension area to the end of the last
+//! column block. Readers verify it when
+//! asked to ([`DecodeOptions::verify_checksum`], part of
+//! [`DecodeProfile::Untrusted`]).
+//!
+//! ## Skeleton Literals
+//!
+//! Log lines repeat the same literal segments (`"User "`, `" logged in from "`)
+//! thousands of times. With [`FormatV3Header::FLAG_INTERNED_SKELETON`] set,
+//! the skeleton column holds each distinct literal once in a string table,
+//! and the token stream refers to it by `u32` index. Readers resolve the
+//! table back into [`SkeletonToken::Text`], so the in-memory payload is the
+//! same either way; archives without the flag store the tokens inline.
+//!
+//! ## Placeholder Map
+//!
+//! With [`FormatV3Header::FLAG_PACKED_PLACEHOLDERS`] set, the placeholder map
+//! is two streams instead of `(u8, u32)` pairs:
+//!
+//! ```text
+//! COUNT varint | RUNS varint | RUNS × (TAG u8 | LEN varint) | COUNT × DELTA zigzag varint
+//! ```
+//!
+//! Tags are run-length encoded. Each `DELTA` is the column index minus the
+//! previous index of the same tag minus one, so values taken in order (the
+//! common case) cost one `0x00` byte each.
+//!
+//! ## Spellings
+//!
+//! Typed columns hold the parsed value (`010.0.0.1` as an IPv4 address,
+//! `WARNING` as the `WARN` level). A value not written the way its column
+//! formats it also gets an entry in the [`ColumnType::Spellings`] column,
+//! keyed by placeholder index, and the archive sets
+//! [`FormatV3Header::FLAG_SPELLINGS`]: filters see the typed value, decoding
+//! restores the exact text.
 
-use crate::columnar_encoder::{ColumnarEncoder, ColumnarPayload, TimestampColumn};
-use crate::{ALICETextError, Result, ALICE_TEXT_MAGIC};
+use crate::classifier::{classify, SourceKind};
+use crate::column_codec::{
+    decode_column, decode_values, encode_column, encode_values, ColumnCodec, ColumnEncoding,
+    ColumnReader, EncodableValue,
+};
+use crate::column_plugin::{self, ColumnPlugin, PluginSchema, PLUGIN_TAG_BASE};
+use crate::columnar_encoder::{
+    check_spellings, format_mac, ByteSizeColumn, ColumnarEncoder, ColumnarPayload, DurationColumn,
+    GeoPoint, HostColumn, LinePiece, NumericTimeColumn, SkeletonToken, TimestampColumn,
+};
+use crate::kubernetes::{
+    ContainerId, CriColumn, KlogColumn, KubernetesColumns, NamespaceColumn, PodColumn,
+};
+use crate::level::Level;
+use crate::line_index::LineIdColumn;
+use crate::producer::Producer;
+use crate::provenance::{ProvenanceColumn, SourceBatch};
+use crate::saved_view::{self, SavedView};
+use crate::source_profile::{RawSegment, SourceProfile};
+use crate::tuned_pattern_learner::{PatternConfig, PatternType};
+use crate::zstd_params;
+use crate::{
+    fnv1a, telemetry, to_u16, to_u32, verify_roundtrip, ALICETextError, CompressOptions, Result,
+    ALICE_TEXT_MAGIC, FNV_OFFSET,
+};
 use serde::{Deserialize, Serialize};
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
 
 /// Format v3 version
-pub const FORMAT_V3_VERSION: (u8, u8) = (3, 0);
+///
+/// 3.1 marks the interned skeleton and packed placeholder map
+/// ([`FormatV3Header::FLAG_INTERNED_SKELETON`] /
+/// [`FormatV3Header::FLAG_PACKED_PLACEHOLDERS`]); readers refuse header flags
+/// they do not know, so these column layouts are never misread.
+pub const FORMAT_V3_VERSION: (u8, u8) = (3, 1);
+
+/// `log` target for decode diagnostics
+const LOG_TARGET: &str = "alice_text::decode";
+
+/// How tolerant v3 readers are of archives from other writers
+///
+/// Strict mode (the default) rejects unknown column types, bytes after the
+/// last column and a minor version newer than [`FORMAT_V3_VERSION`]. Lenient
+/// mode is opt-in: it skips unknown columns, ignores trailing bytes and
+/// accepts any 3.x archive; [`FormatV3Writer::decompress_with_report`]
+/// additionally leaves value columns that fail to decode empty instead of
+/// failing, and reports every column it left out.
+///
+/// Both modes apply [`DecodeLimits`]; checksum verification and the
+/// extension allow-list are off unless set (see [`DecodeProfile::Untrusted`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub strict: bool,
+    /// Output-size ceilings for Zstd decoding
+    pub limits: DecodeLimits,
+    /// Require [`HeaderExtension::CHECKSUM`] and check it against the column data
+    pub verify_checksum: bool,
+    /// Reject header extension tags this reader does not know, critical or not
+    pub known_extensions_only: bool,
+}
+
+impl DecodeOptions {
+    #[must_use]
+    pub const fn strict() -> Self {
+        Self {
+            strict: true,
+            limits: DecodeLimits::new(),
+            verify_checksum: false,
+            known_extensions_only: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn lenient() -> Self {
+        Self {
+            strict: false,
+            limits: DecodeLimits::new(),
+            verify_checksum: false,
+            known_extensions_only: false,
+        }
+    }
+
+    /// Require and verify the column data checksum
+    #[must_use]
+    pub const fn with_verify_checksum(mut self, verify: bool) -> Self {
+        self.verify_checksum = verify;
+        self
+    }
+
+    /// Reject unknown header extension tags
+    #[must_use]
+    pub const fn with_known_extensions_only(mut self, known_only: bool) -> Self {
+        self.known_extensions_only = known_only;
+        self
+    }
+
+    /// Set the output-size ceilings
+    #[must_use]
+    pub const fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self::strict()
+    }
+}
+
+/// Columns lenient decoding left out of the text
+/// (see [`FormatV3Writer::decompress_with_report`])
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DroppedColumns {
+    /// Column type bytes this reader does not know
+    pub unknown: Vec<u8>,
+    /// Known columns that failed to decode; their values are missing
+    pub damaged: Vec<ColumnType>,
+}
+
+impl DroppedColumns {
+    /// Whether nothing was left out
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.unknown.is_empty() && self.damaged.is_empty()
+    }
+}
+
+/// Decode presets by where the archive comes from
+///
+/// `Untrusted` is for services that accept archives from the internet: strict
+/// parsing, tight [`DecodeLimits`] (256 MiB archive and column, 1 GiB decoded),
+/// a verified [`HeaderExtension::CHECKSUM`] and no unknown extensions. Read
+/// such archives into a bounded buffer
+/// ([`QueryEngine::from_reader_with`](crate::QueryEngine::from_reader_with))
+/// rather than memory-mapping them: a mapped file can change under the reader
+/// after it was checked.
+///
+/// ```rust,ignore
+/// let options = DecodeProfile::Untrusted.options();
+/// let text = FormatV3Writer::decompress_with(&upload, options)?;
+/// ```
+///
+/// v1/v2 archives have no checksum or extensions; only the limits carry
+/// over ([`Self::limits`] into
+/// [`TunedCompressor::with_decode_limits`](crate::TunedCompressor::with_decode_limits) /
+/// [`ALICEText::with_decode_limits`](crate::ALICEText::with_decode_limits)).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DecodeProfile {
+    /// Local archives: strict parsing and the default limits
+    #[default]
+    Trusted,
+    /// Archives from untrusted sources
+    Untrusted,
+}
+
+impl DecodeProfile {
+    /// Decode options of this preset (v3)
+    #[must_use]
+    pub const fn options(self) -> DecodeOptions {
+        match self {
+            Self::Trusted => DecodeOptions::strict(),
+            Self::Untrusted => DecodeOptions::strict()
+                .with_limits(self.limits())
+                .with_verify_checksum(true)
+                .with_known_extensions_only(true),
+        }
+    }
+
+    /// Decode limits of this preset (any format version)
+    #[must_use]
+    pub const fn limits(self) -> DecodeLimits {
+        match self {
+            Self::Trusted => DecodeLimits::new(),
+            Self::Untrusted => DecodeLimits {
+                max_column_bytes: 256 << 20,
+                max_total_bytes: 1 << 30,
+                max_archive_bytes: 256 << 20,
+            },
+        }
+    }
+}
+
+impl From<DecodeProfile> for DecodeOptions {
+    fn from(profile: DecodeProfile) -> Self {
+        profile.options()
+    }
+}
+
+/// Decompression bomb protection: how many bytes Zstd may produce
+///
+/// A column of a few kilobytes can claim to expand to gigabytes. Decoders
+/// stop as soon as one v3 column block passes `max_column_bytes` or
+/// everything decoded for one call (a whole v2 archive) passes
+/// `max_total_bytes`, and fail with [`ALICETextError::LimitExceeded`].
+/// Archives larger than `max_archive_bytes` are refused before anything is
+/// read past the header (no ceiling by default).
+/// v2 decoders take theirs from [`TunedCompressor::with_decode_limits`](crate::TunedCompressor::with_decode_limits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    pub max_column_bytes: u64,
+    pub max_total_bytes: u64,
+    pub max_archive_bytes: u64,
+}
+
+impl DecodeLimits {
+    /// Default ceiling for one column block (1 GiB)
+    pub const DEFAULT_MAX_COLUMN_BYTES: u64 = 1 << 30;
+
+    /// Default ceiling for one decode call (4 GiB)
+    pub const DEFAULT_MAX_TOTAL_BYTES: u64 = 4 << 30;
+
+    /// Default ceilings
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_column_bytes: Self::DEFAULT_MAX_COLUMN_BYTES,
+            max_total_bytes: Self::DEFAULT_MAX_TOTAL_BYTES,
+            max_archive_bytes: u64::MAX,
+        }
+    }
+
+    /// No ceilings (trusted input only)
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_column_bytes: u64::MAX,
+            max_total_bytes: u64::MAX,
+            max_archive_bytes: u64::MAX,
+        }
+    }
+
+    /// Set the per-column ceiling
+    #[must_use]
+    pub const fn with_max_column_bytes(mut self, bytes: u64) -> Self {
+        self.max_column_bytes = bytes;
+        self
+    }
+
+    /// Set the per-call ceiling
+    #[must_use]
+    pub const fn with_max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = bytes;
+        self
+    }
+
+    /// Set the ceiling on the archive's own size
+    #[must_use]
+    pub const fn with_max_archive_bytes(mut self, bytes: u64) -> Self {
+        self.max_archive_bytes = bytes;
+        self
+    }
+
+    /// Fail if an archive of `size` bytes passes [`Self::max_archive_bytes`]
+    pub(crate) fn check_archive(&self, size: u64) -> Result<()> {
+        if size > self.max_archive_bytes {
+            return Err(ALICETextError::LimitExceeded {
+                field: "archive size",
+                value: size,
+                limit: self.max_archive_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fail once `total` decoded bytes pass [`Self::max_total_bytes`]
+    pub(crate) fn check_total(&self, total: u64) -> Result<()> {
+        if total > self.max_total_bytes {
+            return Err(ALICETextError::LimitExceeded {
+                field: "decoded size",
+                value: total,
+                limit: self.max_total_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Column types for directory
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum ColumnType {
     Skeleton = 0,
@@ -57,6 +429,33 @@ pub enum ColumnType {
     Others = 15,
     PlaceholderMap = 16,
     TimestampsRaw = 17,
+    Epochs = 18,
+    Uptimes = 19,
+    AnsiCodes = 20,
+    RawBytes = 21,
+    Provenance = 22,
+    /// Every plugin column's encoded bytes (see [`crate::column_plugin`])
+    Plugins = 23,
+    MacAddrs = 24,
+    GeoPoints = 25,
+    /// Kubernetes columns (see [`crate::kubernetes`])
+    CriPrefixes = 26,
+    KlogHeaders = 27,
+    ContainerIds = 28,
+    K8sNamespaces = 29,
+    K8sPods = 30,
+    HttpMethods = 31,
+    HttpStatuses = 32,
+    Durations = 33,
+    ByteSizes = 34,
+    Hosts = 35,
+    Pids = 36,
+    Tids = 37,
+    /// Stable per-line ids (see [`crate::line_index`])
+    LineIds = 38,
+    /// Original text of typed values their column restores differently
+    /// (see [`FormatV3Header::FLAG_SPELLINGS`])
+    Spellings = 39,
 }
 
 impl ColumnType {
@@ -81,6 +480,70 @@ impl ColumnType {
             15 => Some(Self::Others),
             16 => Some(Self::PlaceholderMap),
             17 => Some(Self::TimestampsRaw),
+            18 => Some(Self::Epochs),
+            19 => Some(Self::Uptimes),
+            20 => Some(Self::AnsiCodes),
+            21 => Some(Self::RawBytes),
+            22 => Some(Self::Provenance),
+            23 => Some(Self::Plugins),
+            24 => Some(Self::MacAddrs),
+            25 => Some(Self::GeoPoints),
+            26 => Some(Self::CriPrefixes),
+            27 => Some(Self::KlogHeaders),
+            28 => Some(Self::ContainerIds),
+            29 => Some(Self::K8sNamespaces),
+            30 => Some(Self::K8sPods),
+            31 => Some(Self::HttpMethods),
+            32 => Some(Self::HttpStatuses),
+            33 => Some(Self::Durations),
+            34 => Some(Self::ByteSizes),
+            35 => Some(Self::Hosts),
+            36 => Some(Self::Pids),
+            37 => Some(Self::Tids),
+            38 => Some(Self::LineIds),
+            39 => Some(Self::Spellings),
+            _ => None,
+        }
+    }
+
+    /// Column holding values of a skeleton placeholder tag (`ColumnarPayload::placeholder_map`)
+    #[must_use]
+    pub const fn from_placeholder_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Self::Timestamps),
+            1 => Some(Self::IPv4),
+            2 => Some(Self::LogLevels),
+            3 => Some(Self::Numbers),
+            4 => Some(Self::UUIDs),
+            5 => Some(Self::Emails),
+            6 => Some(Self::URLs),
+            7 => Some(Self::Paths),
+            8 => Some(Self::DateDays),
+            9 => Some(Self::TimeMs),
+            10 => Some(Self::HexValues),
+            11 => Some(Self::Others),
+            12 => Some(Self::IPv6),
+            13 => Some(Self::TimestampsRaw),
+            14 => Some(Self::DatesRaw),
+            15 => Some(Self::TimesRaw),
+            16 => Some(Self::Epochs),
+            17 => Some(Self::Uptimes),
+            18 => Some(Self::AnsiCodes),
+            19 => Some(Self::MacAddrs),
+            20 => Some(Self::GeoPoints),
+            21 => Some(Self::CriPrefixes),
+            22 => Some(Self::KlogHeaders),
+            23 => Some(Self::ContainerIds),
+            24 => Some(Self::K8sNamespaces),
+            25 => Some(Self::K8sPods),
+            26 => Some(Self::HttpMethods),
+            27 => Some(Self::HttpStatuses),
+            28 => Some(Self::Durations),
+            29 => Some(Self::ByteSizes),
+            30 => Some(Self::Hosts),
+            31 => Some(Self::Pids),
+            32 => Some(Self::Tids),
+            PLUGIN_TAG_BASE..=u8::MAX => Some(Self::Plugins),
             _ => None,
         }
     }
@@ -106,10 +569,358 @@ impl ColumnType {
             Self::Others => "others",
             Self::PlaceholderMap => "placeholder_map",
             Self::TimestampsRaw => "timestamps_raw",
+            Self::Epochs => "epochs",
+            Self::Uptimes => "uptimes",
+            Self::AnsiCodes => "ansi_codes",
+            Self::RawBytes => "raw_bytes",
+            Self::Provenance => "provenance",
+            Self::Plugins => "plugins",
+            Self::MacAddrs => "mac_addrs",
+            Self::GeoPoints => "geo_points",
+            Self::CriPrefixes => "cri_prefixes",
+            Self::KlogHeaders => "klog_headers",
+            Self::ContainerIds => "container_ids",
+            Self::K8sNamespaces => "k8s_namespaces",
+            Self::K8sPods => "k8s_pods",
+            Self::HttpMethods => "http_methods",
+            Self::HttpStatuses => "http_statuses",
+            Self::Durations => "durations",
+            Self::ByteSizes => "byte_sizes",
+            Self::Hosts => "hosts",
+            Self::Pids => "pids",
+            Self::Tids => "tids",
+            Self::LineIds => "line_ids",
+            Self::Spellings => "spellings",
+        }
+    }
+
+    /// Where the writer places the column's data, from 0 (front of the data
+    /// area) to 4 (end); see the module docs on column order
+    #[must_use]
+    pub const fn layout_tier(&self) -> u8 {
+        match self {
+            Self::Timestamps | Self::LogLevels | Self::Numbers => 0,
+            Self::IPv4
+            | Self::IPv6
+            | Self::HttpMethods
+            | Self::HttpStatuses
+            | Self::Durations
+            | Self::ByteSizes
+            | Self::Hosts
+            | Self::Pids
+            | Self::Tids
+            | Self::LineIds
+            | Self::Provenance => 1,
+            Self::Others | Self::RawBytes | Self::Plugins | Self::Spellings => 3,
+            Self::Skeleton | Self::PlaceholderMap => 4,
+            _ => 2,
+        }
+    }
+
+    /// Whether the column holds one entry per archive line instead of one per value
+    #[must_use]
+    pub const fn is_per_line(&self) -> bool {
+        matches!(self, Self::Provenance | Self::LineIds)
+    }
+
+    /// Main column a pattern's matches are stored in (raw-string fallbacks aside)
+    #[must_use]
+    pub const fn for_pattern(pattern: PatternType) -> Self {
+        match pattern {
+            PatternType::Timestamp => Self::Timestamps,
+            PatternType::Date => Self::DateDays,
+            PatternType::Time => Self::TimeMs,
+            PatternType::IPv4 => Self::IPv4,
+            PatternType::IPv6 => Self::IPv6,
+            PatternType::UUID => Self::UUIDs,
+            PatternType::LogLevel => Self::LogLevels,
+            PatternType::Path => Self::Paths,
+            PatternType::URL => Self::URLs,
+            PatternType::Number => Self::Numbers,
+            PatternType::Hex => Self::HexValues,
+            PatternType::Email => Self::Emails,
+            PatternType::Custom => Self::Others,
+            PatternType::Epoch => Self::Epochs,
+            PatternType::Uptime => Self::Uptimes,
+            PatternType::Ansi => Self::AnsiCodes,
+            PatternType::Mac => Self::MacAddrs,
+            PatternType::Geo => Self::GeoPoints,
+            PatternType::Cri => Self::CriPrefixes,
+            PatternType::Klog => Self::KlogHeaders,
+            PatternType::ContainerId => Self::ContainerIds,
+            PatternType::Namespace => Self::K8sNamespaces,
+            PatternType::Pod => Self::K8sPods,
+            PatternType::HttpMethod => Self::HttpMethods,
+            PatternType::HttpStatus => Self::HttpStatuses,
+            PatternType::Duration => Self::Durations,
+            PatternType::ByteSize => Self::ByteSizes,
+            PatternType::Hostname => Self::Hosts,
+            PatternType::Pid => Self::Pids,
+            PatternType::Tid => Self::Tids,
+        }
+    }
+}
+
+/// Skeleton token with its literal interned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InternedToken {
+    /// Index into [`InternedSkeleton::literals`]
+    Literal(u32),
+    /// Reference to placeholder index
+    Ref(u32),
+}
+
+/// Skeleton column layout under [`FormatV3Header::FLAG_INTERNED_SKELETON`]
+#[derive(Debug, Default)]
+struct InternedSkeleton {
+    /// Distinct literal segments, in order of first use
+    literals: Vec<String>,
+    tokens: Vec<InternedToken>,
+}
+
+impl InternedSkeleton {
+    fn intern(tokens: &[SkeletonToken]) -> Self {
+        let mut ids: HashMap<&str, u32> = HashMap::new();
+        let mut skeleton = Self::default();
+        for token in tokens {
+            skeleton.tokens.push(match token {
+                SkeletonToken::Text(text) => {
+                    InternedToken::Literal(*ids.entry(text).or_insert_with(|| {
+                        skeleton.literals.push(text.clone());
+                        skeleton.literals.len() as u32 - 1
+                    }))
+                }
+                SkeletonToken::Ref(idx) => InternedToken::Ref(*idx),
+            });
+        }
+        skeleton
+    }
+
+    fn resolve(self) -> Result<Vec<SkeletonToken>> {
+        self.tokens
+            .into_iter()
+            .map(|token| match token {
+                InternedToken::Literal(id) => self
+                    .literals
+                    .get(id as usize)
+                    .map(|text| SkeletonToken::Text(text.clone()))
+                    .ok_or_else(|| {
+                        ALICETextError::DecompressionError(format!(
+                            "Skeleton literal {id} out of range ({} literals)",
+                            self.literals.len()
+                        ))
+                    }),
+                InternedToken::Ref(idx) => Ok(SkeletonToken::Ref(idx)),
+            })
+            .collect()
+    }
+}
+
+impl ColumnCodec for InternedToken {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let (variant, value) = match *self {
+            Self::Literal(id) => (0u32, id),
+            Self::Ref(idx) => (1u32, idx),
+        };
+        variant.encode(out);
+        value.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        match u32::decode(input)? {
+            0 => u32::decode(input).map(Self::Literal),
+            1 => u32::decode(input).map(Self::Ref),
+            v => Err(ALICETextError::DecompressionError(format!(
+                "Unknown skeleton token variant {v}"
+            ))),
+        }
+    }
+}
+
+impl ColumnCodec for InternedSkeleton {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.literals.encode(out);
+        self.tokens.encode(out);
+    }
+
+    fn decode(input: &mut ColumnReader<'_>) -> Result<Self> {
+        Ok(Self {
+            literals: ColumnCodec::decode(input)?,
+            tokens: ColumnCodec::decode(input)?,
+        })
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn get_varint(bytes: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or_else(|| {
+            ALICETextError::DecompressionError("Truncated placeholder map".to_string())
+        })?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(ALICETextError::DecompressionError(
+        "Placeholder map varint too long".to_string(),
+    ))
+}
+
+/// Rows of a column the encoding candidates are compared on
+const ENCODING_SAMPLE: usize = 8192;
+
+/// Highest Zstd level the candidates are compared at (keeps `Best` cheap)
+const ENCODING_ZSTD_LEVEL: i32 = 3;
+
+/// `values` in the [`ColumnEncoding`] whose Zstd output is smallest on a
+/// sample of the column (`Plain` on ties)
+fn encode_auto<T: EncodableValue>(values: &[T], zstd_level: i32) -> (Vec<u8>, ColumnEncoding) {
+    let sample = &values[..values.len().min(ENCODING_SAMPLE)];
+    let level = zstd_level.min(ENCODING_ZSTD_LEVEL);
+    let size = |encoding| {
+        zstd::stream::encode_all(Cursor::new(encode_values(sample, encoding)), level)
+            .map_or(usize::MAX, |compressed| compressed.len())
+    };
+    let encoding = ColumnEncoding::ALL
+        .into_iter()
+        .min_by_key(|&encoding| size(encoding))
+        .unwrap_or_default();
+    (encode_values(values, encoding), encoding)
+}
+
+/// Set [`ColumnEntry::encoding`] from a [`HeaderExtension::COLUMN_ENCODINGS`] record
+fn apply_encodings(columns: &mut [ColumnEntry], data: &[u8]) -> Result<()> {
+    if !data.len().is_multiple_of(2) {
+        return Err(ALICETextError::DecompressionError(
+            "Truncated column encodings record".to_string(),
+        ));
+    }
+    for pair in data.chunks_exact(2) {
+        let encoding = ColumnEncoding::from_u8(pair[1]).ok_or_else(|| {
+            ALICETextError::DecompressionError(format!("Unknown column encoding {}", pair[1]))
+        })?;
+        for entry in columns.iter_mut().filter(|e| e.col_type as u8 == pair[0]) {
+            entry.encoding = encoding;
+        }
+    }
+    Ok(())
+}
+
+/// FNV-1a 64 of the `len` bytes of column data at the reader's position
+fn data_checksum<R: Read>(reader: &mut R, len: u64) -> Result<u64> {
+    let mut data = reader.take(len);
+    let mut hash = FNV_OFFSET;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        match data.read(&mut buf)? {
+            0 => return Ok(hash),
+            n => hash = fnv1a(&buf[..n], hash),
         }
     }
 }
 
+/// Set [`ColumnEntry::window_log`] from a [`HeaderExtension::ZSTD_WINDOW`] record
+fn apply_window_log(columns: &mut [ColumnEntry], data: &[u8]) -> Result<()> {
+    let [window_log] = *data else {
+        return Err(ALICETextError::DecompressionError(
+            "Malformed zstd window record".to_string(),
+        ));
+    };
+    zstd_params::check_window_log(window_log)?;
+    for entry in columns {
+        entry.window_log = window_log;
+    }
+    Ok(())
+}
+
+/// Decode an integer column block in its entry's encoding
+fn decode_entry<T: EncodableValue>(entry: &ColumnEntry, bytes: &[u8]) -> Result<Vec<T>> {
+    decode_values(bytes, entry.encoding, entry.row_count as usize)
+}
+
+/// Decode the `numbers` column block (the values' bits in its entry's encoding)
+pub(crate) fn decode_numbers(entry: &ColumnEntry, bytes: &[u8]) -> Result<Vec<f64>> {
+    let bits: Vec<u64> = decode_entry(entry, bytes)?;
+    Ok(bits.into_iter().map(f64::from_bits).collect())
+}
+
+/// Placeholder map in the packed layout (see module docs)
+fn pack_placeholder_map(map: &[(u8, u32)]) -> Vec<u8> {
+    let mut runs: Vec<(u8, u64)> = Vec::new();
+    for &(tag, _) in map {
+        match runs.last_mut() {
+            Some((last, len)) if *last == tag => *len += 1,
+            _ => runs.push((tag, 1)),
+        }
+    }
+    let mut out = Vec::with_capacity(map.len() + runs.len() * 2 + 8);
+    put_varint(&mut out, map.len() as u64);
+    put_varint(&mut out, runs.len() as u64);
+    for (tag, len) in runs {
+        out.push(tag);
+        put_varint(&mut out, len);
+    }
+    let mut previous = [-1i64; 256];
+    for &(tag, idx) in map {
+        let delta = i64::from(idx) - previous[usize::from(tag)] - 1;
+        previous[usize::from(tag)] = i64::from(idx);
+        put_varint(&mut out, ((delta << 1) ^ (delta >> 63)) as u64);
+    }
+    out
+}
+
+fn unpack_placeholder_map(bytes: &[u8]) -> Result<Vec<(u8, u32)>> {
+    let invalid =
+        |what: &str| ALICETextError::DecompressionError(format!("Placeholder map: {what}"));
+    let mut pos = 0;
+    let count = get_varint(bytes, &mut pos)? as usize;
+    let run_count = get_varint(bytes, &mut pos)?;
+    // Every entry takes at least one byte, which bounds the allocation
+    if count > bytes.len() {
+        return Err(invalid("entry count exceeds column size"));
+    }
+    let mut tags = Vec::with_capacity(count);
+    for _ in 0..run_count {
+        let tag = *bytes
+            .get(pos)
+            .ok_or_else(|| invalid("truncated tag runs"))?;
+        pos += 1;
+        let end = usize::try_from(get_varint(bytes, &mut pos)?)
+            .ok()
+            .and_then(|len| tags.len().checked_add(len))
+            .filter(|&end| end <= count)
+            .ok_or_else(|| invalid("tag runs exceed entry count"))?;
+        tags.resize(end, tag);
+    }
+    if tags.len() != count {
+        return Err(invalid("tag runs do not cover every entry"));
+    }
+    let mut previous = [-1i64; 256];
+    let mut map = Vec::with_capacity(count);
+    for tag in tags {
+        let zigzag = get_varint(bytes, &mut pos)?;
+        let delta = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        let idx = previous[usize::from(tag)]
+            .checked_add(1)
+            .and_then(|next| next.checked_add(delta))
+            .and_then(|idx| u32::try_from(idx).ok())
+            .ok_or_else(|| invalid("column index out of range"))?;
+        previous[usize::from(tag)] = i64::from(idx);
+        map.push((tag, idx));
+    }
+    Ok(map)
+}
+
 /// Column directory entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnEntry {
@@ -123,6 +934,14 @@ pub struct ColumnEntry {
     pub uncompressed_size: u32,
     /// Number of rows/items
     pub row_count: u32,
+    /// Block layout of an integer column (from
+    /// [`HeaderExtension::COLUMN_ENCODINGS`]; not part of the entry bytes)
+    #[serde(default)]
+    pub encoding: ColumnEncoding,
+    /// Zstd window log the block needs (from [`HeaderExtension::ZSTD_WINDOW`];
+    /// 0 = decoder default; not part of the entry bytes)
+    #[serde(default)]
+    pub window_log: u8,
 }
 
 impl ColumnEntry {
@@ -158,8 +977,123 @@ impl ColumnEntry {
             compressed_size: u32::from_le_bytes(bytes[9..13].try_into().map_err(|_| to_err())?),
             uncompressed_size: u32::from_le_bytes(bytes[13..17].try_into().map_err(|_| to_err())?),
             row_count: u32::from_le_bytes(bytes[17..21].try_into().map_err(|_| to_err())?),
+            encoding: ColumnEncoding::Plain,
+            window_log: 0,
+        })
+    }
+}
+
+/// Values sampled for quantile estimation (strided beyond this)
+const QUANTILE_SAMPLE: usize = 65_536;
+
+/// Compress-time summary of a numeric, timestamp or IPv4 column
+///
+/// Values are stored as `f64`: Unix milliseconds for timestamps, the
+/// big-endian `u32` for IPv4 (both exact). Quantiles are exact up to
+/// 65 536 values and estimated from a strided sample beyond that.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSummary {
+    pub col_type: ColumnType,
+    /// Non-null values
+    pub count: u64,
+    /// Values that could not be typed (unparsed timestamps, NaN)
+    pub null_count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl ColumnSummary {
+    /// Summarize a column; `None` when it has no non-null values
+    #[must_use]
+    pub fn from_values(col_type: ColumnType, values: &[f64], null_count: u64) -> Option<Self> {
+        let step = values.len().div_ceil(QUANTILE_SAMPLE).max(1);
+        let mut sample: Vec<f64> = values
+            .iter()
+            .step_by(step)
+            .copied()
+            .filter(|v| !v.is_nan())
+            .collect();
+        sample.sort_unstable_by(f64::total_cmp);
+
+        let non_null = values.iter().filter(|v| !v.is_nan());
+        let (min, max) = non_null
+            .clone()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| {
+                (lo.min(v), hi.max(v))
+            });
+        let count = non_null.count() as u64;
+        if count == 0 {
+            return None;
+        }
+
+        let quantile = |q: f64| {
+            if sample.is_empty() {
+                return min;
+            }
+            let idx = ((sample.len() - 1) as f64 * q).round() as usize;
+            sample[idx]
+        };
+        Some(Self {
+            col_type,
+            count,
+            null_count: null_count + (values.len() as u64 - count),
+            min,
+            max,
+            p50: quantile(0.5),
+            p90: quantile(0.9),
+            p99: quantile(0.99),
         })
     }
+
+    /// Render a value in the column's native form (timestamp, dotted IPv4, number)
+    #[must_use]
+    pub fn format_value(&self, value: f64) -> String {
+        match self.col_type {
+            ColumnType::Timestamps => chrono::DateTime::from_timestamp_millis(value as i64)
+                .map_or_else(
+                    || value.to_string(),
+                    |dt| dt.format("%Y-%m-%d %H:%M:%S").to_string(),
+                ),
+            ColumnType::IPv4 => std::net::Ipv4Addr::from(value as u32).to_string(),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Summaries for the numeric, timestamp and IPv4 columns of a payload
+    fn from_payload(payload: &ColumnarPayload) -> Vec<Self> {
+        let timestamps: Vec<f64> = payload
+            .timestamps
+            .prepare_for_read()
+            .into_iter()
+            .map(|ms| ms as f64)
+            .collect();
+        let ipv4: Vec<f64> = payload.ipv4_addrs.iter().map(|&ip| f64::from(ip)).collect();
+        let durations: Vec<f64> = payload
+            .durations
+            .nanos
+            .iter()
+            .map(|&ns| ns as f64)
+            .collect();
+        let byte_sizes: Vec<f64> = payload.byte_sizes.bytes.iter().map(|&b| b as f64).collect();
+
+        [
+            Self::from_values(
+                ColumnType::Timestamps,
+                &timestamps,
+                payload.timestamps.raw.len() as u64,
+            ),
+            Self::from_values(ColumnType::IPv4, &ipv4, 0),
+            Self::from_values(ColumnType::Numbers, &payload.numbers, 0),
+            Self::from_values(ColumnType::Durations, &durations, 0),
+            Self::from_values(ColumnType::ByteSizes, &byte_sizes, 0),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
 }
 
 /// Format v3 header
@@ -167,20 +1101,79 @@ impl ColumnEntry {
 pub struct FormatV3Header {
     /// Original text length
     pub original_length: u64,
-    /// Compression level (0=fast, 1=balanced, 2=best)
+    /// Nearest compression preset (0=fast, 1=balanced, 2=best, see
+    /// `Level::v3_level`): `Level::Max` and `Level::Zstd(n)` have no code of
+    /// their own, the exact level is in the producer record
+    /// ([`FormatV3Metadata::level`])
     pub compression_level: u8,
     /// Number of columns in directory
     pub column_count: u16,
     /// Total row count (for log lines)
     pub row_count: u64,
-    /// Reserved for future use
-    pub reserved: [u8; 13],
+    /// Size of the column summary block after the directory (0 = none)
+    pub summary_size: u32,
+    /// Size of the pattern config block after the summaries (0 = default config)
+    pub config_size: u32,
+    /// Source profile flags (`SourceProfile::FLAG_CRLF`, `FLAG_UTF16LE`),
+    /// [`Self::FLAG_EXTENSIONS`], [`Self::FLAG_INTERNED_SKELETON`],
+    /// [`Self::FLAG_PACKED_PLACEHOLDERS`] and [`Self::FLAG_SPELLINGS`]
+    pub flags: u8,
+    /// Size of the header extension area after the pattern config
+    /// (only meaningful with [`Self::FLAG_EXTENSIONS`])
+    pub extension_size: u32,
 }
 
 impl FormatV3Header {
-    /// Header size: 8 + 1 + 2 + 8 + 13 = 32 bytes
+    /// Header size: 8 + 1 + 2 + 8 + 4 + 4 + 1 + 4 = 32 bytes
     pub const SIZE: usize = 32;
 
+    /// `flags` bit: a header extension area of `extension_size` bytes is present
+    pub const FLAG_EXTENSIONS: u8 = 0x80;
+
+    /// `flags` bit: the skeleton column interns its literals (see module docs)
+    pub const FLAG_INTERNED_SKELETON: u8 = 0x40;
+
+    /// `flags` bit: the placeholder map uses the packed layout (see module docs)
+    pub const FLAG_PACKED_PLACEHOLDERS: u8 = 0x20;
+
+    /// `flags` bit: a [`ColumnType::Spellings`] column overrides how some
+    /// typed values are spelled; readers that would skip the column refuse
+    /// the archive instead of restoring the canonical forms
+    pub const FLAG_SPELLINGS: u8 = 0x10;
+
+    /// Every flag this reader understands; flags change how columns are laid
+    /// out, so archives with any other bit set are refused
+    pub const KNOWN_FLAGS: u8 = SourceProfile::FLAG_CRLF
+        | SourceProfile::FLAG_UTF16LE
+        | Self::FLAG_EXTENSIONS
+        | Self::FLAG_INTERNED_SKELETON
+        | Self::FLAG_PACKED_PLACEHOLDERS
+        | Self::FLAG_SPELLINGS;
+
+    /// Whether the archive carries a header extension area
+    #[must_use]
+    pub const fn has_extensions(&self) -> bool {
+        self.flags & Self::FLAG_EXTENSIONS != 0
+    }
+
+    /// Whether the skeleton column stores literals in a string table
+    #[must_use]
+    pub const fn has_interned_skeleton(&self) -> bool {
+        self.flags & Self::FLAG_INTERNED_SKELETON != 0
+    }
+
+    /// Whether the placeholder map uses the packed layout
+    #[must_use]
+    pub const fn has_packed_placeholders(&self) -> bool {
+        self.flags & Self::FLAG_PACKED_PLACEHOLDERS != 0
+    }
+
+    /// Line endings / input encoding to restore on decode
+    #[must_use]
+    pub const fn source_profile(&self) -> SourceProfile {
+        SourceProfile::from_flags(self.flags)
+    }
+
     #[must_use]
     pub fn to_bytes(&self) -> [u8; Self::SIZE] {
         let mut bytes = [0u8; Self::SIZE];
@@ -188,7 +1181,10 @@ impl FormatV3Header {
         bytes[8] = self.compression_level;
         bytes[9..11].copy_from_slice(&self.column_count.to_le_bytes());
         bytes[11..19].copy_from_slice(&self.row_count.to_le_bytes());
-        bytes[19..32].copy_from_slice(&self.reserved);
+        bytes[19..23].copy_from_slice(&self.summary_size.to_le_bytes());
+        bytes[23..27].copy_from_slice(&self.config_size.to_le_bytes());
+        bytes[27] = self.flags;
+        bytes[28..32].copy_from_slice(&self.extension_size.to_le_bytes());
         bytes
     }
 
@@ -207,53 +1203,394 @@ impl FormatV3Header {
             compression_level: bytes[8],
             column_count: u16::from_le_bytes(bytes[9..11].try_into().map_err(|_| to_err())?),
             row_count: u64::from_le_bytes(bytes[11..19].try_into().map_err(|_| to_err())?),
-            reserved: bytes[19..32].try_into().map_err(|_| to_err())?,
+            summary_size: u32::from_le_bytes(bytes[19..23].try_into().map_err(|_| to_err())?),
+            config_size: u32::from_le_bytes(bytes[23..27].try_into().map_err(|_| to_err())?),
+            flags: bytes[27],
+            extension_size: u32::from_le_bytes(bytes[28..32].try_into().map_err(|_| to_err())?),
         })
     }
 }
 
-/// Format v3 file metadata (header + column directory)
-#[derive(Debug, Clone)]
-pub struct FormatV3Metadata {
-    pub header: FormatV3Header,
-    pub columns: Vec<ColumnEntry>,
+/// One record of the header extension area
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderExtension {
+    pub tag: u16,
+    pub data: Vec<u8>,
 }
 
-impl FormatV3Metadata {
-    /// Read metadata from file (header only read - no data decompression)
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if magic/version is invalid or reading the header or column directory fails.
-    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Self> {
-        // Read magic
-        let mut magic = [0u8; 8];
-        reader.read_exact(&mut magic)?;
-        if &magic != ALICE_TEXT_MAGIC {
-            return Err(ALICETextError::InvalidMagic);
-        }
+impl HeaderExtension {
+    /// Tag bit: readers that do not know the tag must reject the archive
+    pub const CRITICAL: u16 = 0x8000;
 
-        // Read version
-        let mut version = [0u8; 2];
-        reader.read_exact(&mut version)?;
-        if version[0] != 3 {
-            return Err(ALICETextError::InvalidVersion(version[0], version[1]));
-        }
+    /// Input bytes captured into value columns (`u64` LE), written by every writer
+    pub const VALUE_BYTES: u16 = 0x0100;
+
+    /// Column plugin schema (Bincode `Vec<PluginSchema>`), written with the
+    /// [`ColumnType::Plugins`] column
+    pub const PLUGIN_SCHEMA: u16 = Self::CRITICAL | 0x0101;
+
+    /// Source kind chosen by [`FormatV3Writer::with_detected_profile`]
+    /// (one [`SourceKind`] byte)
+    pub const SOURCE_KIND: u16 = 0x0102;
+
+    /// Saved views (JSON `Vec<SavedView>`, the sidecar's shape), written by
+    /// [`FormatV3Writer::with_views`]
+    pub const SAVED_VIEWS: u16 = 0x0103;
+
+    /// Producing build and writer settings (JSON [`Producer`]), written by
+    /// every writer
+    pub const PRODUCER: u16 = 0x0104;
+
+    /// Columns not stored plain: `(ColumnType u8, ColumnEncoding u8)` pairs
+    pub const COLUMN_ENCODINGS: u16 = Self::CRITICAL | 0x0105;
+
+    /// Zstd window log (one byte) beyond the decoder default, written when
+    /// [`ZstdParams::required_window_log`](crate::ZstdParams::required_window_log)
+    /// is set
+    pub const ZSTD_WINDOW: u16 = Self::CRITICAL | 0x0106;
+
+    /// FNV-1a 64 (`u64` LE) of every byte after the extension area, written
+    /// by every writer
+    pub const CHECKSUM: u16 = 0x0107;
+
+    /// Every tag this reader understands
+    pub const KNOWN: &'static [u16] = &[
+        Self::VALUE_BYTES,
+        Self::PLUGIN_SCHEMA,
+        Self::SOURCE_KIND,
+        Self::SAVED_VIEWS,
+        Self::PRODUCER,
+        Self::COLUMN_ENCODINGS,
+        Self::ZSTD_WINDOW,
+        Self::CHECKSUM,
+    ];
+
+    /// Record header size: tag (2) + length (4)
+    const RECORD_HEADER: usize = 6;
+
+    /// Critical tags this reader understands
+    const KNOWN_CRITICAL: &'static [u16] = &[
+        Self::PLUGIN_SCHEMA,
+        Self::COLUMN_ENCODINGS,
+        Self::ZSTD_WINDOW,
+    ];
+
+    #[must_use]
+    pub const fn new(tag: u16, data: Vec<u8>) -> Self {
+        Self { tag, data }
+    }
+
+    #[must_use]
+    pub const fn is_critical(&self) -> bool {
+        self.tag & Self::CRITICAL != 0
+    }
+
+    /// Encode records back to back
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if a record's data is 4 GiB or larger.
+    pub fn encode_all(extensions: &[Self]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for ext in extensions {
+            out.extend_from_slice(&ext.tag.to_le_bytes());
+            out.extend_from_slice(
+                &to_u32(ext.data.len(), "v3 extension record size")?.to_le_bytes(),
+            );
+            out.extend_from_slice(&ext.data);
+        }
+        Ok(out)
+    }
+
+    /// Parse an extension area, rejecting unknown critical tags
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a record is truncated or a critical tag is not supported.
+    pub fn decode_all(mut bytes: &[u8]) -> Result<Vec<Self>> {
+        let truncated =
+            || ALICETextError::DecompressionError("Truncated header extension".to_string());
+        let mut extensions = Vec::new();
+        while !bytes.is_empty() {
+            if bytes.len() < Self::RECORD_HEADER {
+                return Err(truncated());
+            }
+            let tag = u16::from_le_bytes([bytes[0], bytes[1]]);
+            let len = u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]) as usize;
+            let data = bytes
+                .get(Self::RECORD_HEADER..Self::RECORD_HEADER + len)
+                .ok_or_else(truncated)?;
+            let ext = Self::new(tag, data.to_vec());
+            if ext.is_critical() && !Self::KNOWN_CRITICAL.contains(&tag) {
+                return Err(ALICETextError::DecompressionError(format!(
+                    "Archive needs header extension {tag:#06x}, which this reader does not support"
+                )));
+            }
+            extensions.push(ext);
+            bytes = &bytes[Self::RECORD_HEADER + len..];
+        }
+        Ok(extensions)
+    }
+}
+
+/// Format v3 file metadata (header + column directory + summaries)
+#[derive(Debug, Clone)]
+pub struct FormatV3Metadata {
+    pub header: FormatV3Header,
+    pub columns: Vec<ColumnEntry>,
+    /// Compress-time column summaries (empty for archives written without them)
+    pub summaries: Vec<ColumnSummary>,
+    /// Pattern configuration the archive was encoded with
+    pub pattern_config: PatternConfig,
+    /// Header extension records, in file order
+    pub extensions: Vec<HeaderExtension>,
+    /// Directory entries with a column type this reader does not know (skipped)
+    pub unknown_columns: Vec<u8>,
+    /// Ceilings column reads of this archive apply (from the [`DecodeOptions`]
+    /// it was read with)
+    pub limits: DecodeLimits,
+}
+
+impl FormatV3Metadata {
+    /// Read metadata from file (header only read - no data decompression)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if magic/version is invalid or reading the header or column directory fails.
+    pub fn read_from<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        Self::read_from_with(reader, DecodeOptions::default())
+    }
+
+    /// [`Self::read_from`] with explicit strictness
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if magic/version is invalid or reading the header or
+    /// column directory fails; in strict mode also on an unknown column type,
+    /// a minor version mismatch or trailing bytes.
+    pub fn read_from_with<R: Read + Seek>(reader: &mut R, options: DecodeOptions) -> Result<Self> {
+        // Archive size, before trusting any size the header claims
+        let start = reader.stream_position()?;
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        options.limits.check_archive(len.saturating_sub(start))?;
+
+        // Read magic
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != ALICE_TEXT_MAGIC {
+            return Err(ALICETextError::InvalidMagic);
+        }
+
+        // Read version
+        let mut version = [0u8; 2];
+        reader.read_exact(&mut version)?;
+        if version[0] != FORMAT_V3_VERSION.0 || (options.strict && version[1] > FORMAT_V3_VERSION.1)
+        {
+            return Err(ALICETextError::InvalidVersion(version[0], version[1]));
+        }
 
         // Read header
         let mut header_bytes = [0u8; FormatV3Header::SIZE];
         reader.read_exact(&mut header_bytes)?;
         let header = FormatV3Header::from_bytes(&header_bytes)?;
+        let unknown = header.flags & !FormatV3Header::KNOWN_FLAGS;
+        if unknown != 0 {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Unknown v3 header flags {unknown:#04x}"
+            )));
+        }
+        let claimed = u64::from(header.column_count) * ColumnEntry::SIZE as u64
+            + u64::from(header.summary_size)
+            + u64::from(header.config_size)
+            + u64::from(header.extension_size);
+        if reader.stream_position()? + claimed > len {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Archive truncated: header areas need {claimed} more bytes"
+            )));
+        }
 
         // Read column directory
         let mut columns = Vec::with_capacity(header.column_count as usize);
+        let mut unknown_columns = Vec::new();
+        let mut data_end = 0u64;
         for _ in 0..header.column_count {
             let mut entry_bytes = [0u8; ColumnEntry::SIZE];
             reader.read_exact(&mut entry_bytes)?;
+            let offset = u64::from_le_bytes(entry_bytes[1..9].try_into().unwrap_or_default());
+            let size = u32::from_le_bytes(entry_bytes[9..13].try_into().unwrap_or_default());
+            let end = offset.checked_add(u64::from(size)).ok_or_else(|| {
+                ALICETextError::DecompressionError(format!(
+                    "Column at offset {offset} runs past the end of the address space"
+                ))
+            })?;
+            data_end = data_end.max(end);
+            if ColumnType::from_u8(entry_bytes[0]).is_none() && !options.strict {
+                unknown_columns.push(entry_bytes[0]);
+                continue;
+            }
             columns.push(ColumnEntry::from_bytes(&entry_bytes)?);
         }
 
-        Ok(Self { header, columns })
+        // Read column summaries
+        let summaries = if header.summary_size > 0 {
+            let mut summary_bytes = vec![0u8; header.summary_size as usize];
+            reader.read_exact(&mut summary_bytes)?;
+            decode_column(&summary_bytes)?
+        } else {
+            Vec::new()
+        };
+
+        // Read pattern config
+        let pattern_config = if header.config_size > 0 {
+            let mut config_bytes = vec![0u8; header.config_size as usize];
+            reader.read_exact(&mut config_bytes)?;
+            decode_column(&config_bytes)?
+        } else {
+            PatternConfig::default()
+        };
+
+        // Read header extensions
+        let extensions = if header.has_extensions() {
+            let mut extension_bytes = vec![0u8; header.extension_size as usize];
+            reader.read_exact(&mut extension_bytes)?;
+            HeaderExtension::decode_all(&extension_bytes)?
+        } else {
+            Vec::new()
+        };
+        if let Some(ext) = extensions
+            .iter()
+            .find(|e| e.tag == HeaderExtension::COLUMN_ENCODINGS)
+        {
+            apply_encodings(&mut columns, &ext.data)?;
+        }
+        if let Some(ext) = extensions
+            .iter()
+            .find(|e| e.tag == HeaderExtension::ZSTD_WINDOW)
+        {
+            apply_window_log(&mut columns, &ext.data)?;
+        }
+        if options.known_extensions_only {
+            if let Some(ext) = extensions
+                .iter()
+                .find(|e| !HeaderExtension::KNOWN.contains(&e.tag))
+            {
+                return Err(ALICETextError::DecompressionError(format!(
+                    "Unknown header extension {:#06x}",
+                    ext.tag
+                )));
+            }
+        }
+        if options.verify_checksum {
+            let expected = extensions
+                .iter()
+                .find(|e| e.tag == HeaderExtension::CHECKSUM)
+                .and_then(|e| <[u8; 8]>::try_from(e.data.as_slice()).ok())
+                .map(u64::from_le_bytes)
+                .ok_or_else(|| {
+                    ALICETextError::DecompressionError("Archive has no checksum".to_string())
+                })?;
+            let position = reader.stream_position()?;
+            let actual = data_checksum(reader, data_end.saturating_sub(position))?;
+            reader.seek(SeekFrom::Start(position))?;
+            if actual != expected {
+                return Err(ALICETextError::DecompressionError(format!(
+                    "Checksum mismatch: {actual:016x} != {expected:016x}"
+                )));
+            }
+        }
+
+        if options.strict {
+            let position = reader.stream_position()?;
+            let len = reader.seek(SeekFrom::End(0))?;
+            reader.seek(SeekFrom::Start(position))?;
+            let data_end = data_end.max(position);
+            if len != data_end {
+                return Err(ALICETextError::DecompressionError(if len > data_end {
+                    format!("{} trailing bytes after column data", len - data_end)
+                } else {
+                    format!("Archive truncated: {} of {data_end} bytes", len)
+                }));
+            }
+        }
+
+        Ok(Self {
+            header,
+            columns,
+            summaries,
+            pattern_config,
+            extensions,
+            unknown_columns,
+            limits: options.limits,
+        })
+    }
+
+    /// Value of the first header extension with `tag`
+    #[must_use]
+    pub fn extension(&self, tag: u16) -> Option<&[u8]> {
+        self.extensions
+            .iter()
+            .find(|e| e.tag == tag)
+            .map(|e| e.data.as_slice())
+    }
+
+    /// Input bytes held in value columns (`None` for archives written
+    /// before [`HeaderExtension::VALUE_BYTES`] existed)
+    #[must_use]
+    pub fn value_bytes(&self) -> Option<u64> {
+        self.extension(HeaderExtension::VALUE_BYTES)
+            .and_then(|data| data.try_into().ok())
+            .map(u64::from_le_bytes)
+    }
+
+    /// Source kind the writer detected (`None` unless written with
+    /// [`FormatV3Writer::with_detected_profile`])
+    #[must_use]
+    pub fn source_kind(&self) -> Option<SourceKind> {
+        match self.extension(HeaderExtension::SOURCE_KIND)? {
+            &[kind] => SourceKind::from_u8(kind),
+            _ => None,
+        }
+    }
+
+    /// Build and settings that wrote the archive (`None` for archives
+    /// written before [`HeaderExtension::PRODUCER`] existed, or an
+    /// unreadable record)
+    #[must_use]
+    pub fn producer(&self) -> Option<Producer> {
+        self.extension(HeaderExtension::PRODUCER)
+            .and_then(|data| Producer::from_extension(data).ok())
+    }
+
+    /// Level the archive was written at: the exact one from the
+    /// [`Producer`] settings, else the header's preset
+    #[must_use]
+    pub fn level(&self) -> Level {
+        self.producer()
+            .and_then(|p| p.settings.get("level").and_then(|l| l.parse().ok()))
+            .unwrap_or_else(|| CompressionLevel::from_u8(self.header.compression_level).into())
+    }
+
+    /// Saved views stored in the archive (empty if none)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a view record does not deserialize.
+    pub fn saved_views(&self) -> Result<Vec<SavedView>> {
+        saved_view::from_metadata(self)
+    }
+
+    /// Column plugins the archive was written with (empty if none)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema record does not deserialize.
+    pub fn plugin_schema(&self) -> Result<Vec<PluginSchema>> {
+        self.extension(HeaderExtension::PLUGIN_SCHEMA)
+            .map_or(Ok(Vec::new()), |data| {
+                decode_column(data)
+                    .map_err(|e| ALICETextError::DecompressionError(format!("Plugin schema: {e}")))
+            })
     }
 
     /// Get column entry by type
@@ -262,6 +1599,12 @@ impl FormatV3Metadata {
         self.columns.iter().find(|c| c.col_type == col_type)
     }
 
+    /// Get column summary by type
+    #[must_use]
+    pub fn get_summary(&self, col_type: ColumnType) -> Option<&ColumnSummary> {
+        self.summaries.iter().find(|s| s.col_type == col_type)
+    }
+
     /// Get all column names
     #[must_use]
     pub fn column_names(&self) -> Vec<&'static str> {
@@ -273,6 +1616,184 @@ impl FormatV3Metadata {
     pub fn compressed_size(&self) -> u64 {
         self.columns.iter().map(|c| c.compressed_size as u64).sum()
     }
+
+    /// Bytes from the start of the archive that hold the metadata and every
+    /// column in `columns` (e.g. the length of a ranged object-storage read)
+    ///
+    /// Read such a prefix with [`DecodeOptions::lenient`]: strict decoding
+    /// rejects it as truncated.
+    #[must_use]
+    pub fn prefix_len(&self, columns: &[ColumnType]) -> u64 {
+        let header = &self.header;
+        let extensions = if header.has_extensions() {
+            header.extension_size
+        } else {
+            0
+        };
+        let metadata_len = (8 + 2 + FormatV3Header::SIZE) as u64
+            + u64::from(header.column_count) * ColumnEntry::SIZE as u64
+            + u64::from(header.summary_size)
+            + u64::from(header.config_size)
+            + u64::from(extensions);
+        self.columns
+            .iter()
+            .filter(|c| columns.contains(&c.col_type))
+            .map(|c| c.offset + u64::from(c.compressed_size))
+            .fold(metadata_len, u64::max)
+    }
+
+    /// Whether `other` can be merged with or queried alongside this archive
+    ///
+    /// True when no column is [`SchemaDiff::typed_differently`]; columns
+    /// present on one side only just read as empty on the other.
+    #[must_use]
+    pub fn is_compatible_with(&self, other: &Self) -> bool {
+        self.schema_diff(other).is_compatible()
+    }
+
+    /// Column and pattern-configuration differences from `self` to `other`
+    #[must_use]
+    pub fn schema_diff(&self, other: &Self) -> SchemaDiff {
+        let has_values =
+            |meta: &Self, col: ColumnType| meta.get_column(col).is_some_and(|c| c.row_count > 0);
+        let value_columns = |meta: &Self| -> Vec<ColumnType> {
+            meta.columns
+                .iter()
+                .map(|c| c.col_type)
+                .filter(|&col| {
+                    !matches!(col, ColumnType::Skeleton | ColumnType::PlaceholderMap)
+                        && has_values(meta, col)
+                })
+                .collect()
+        };
+        let (ours, theirs) = (value_columns(self), value_columns(other));
+        let mut diff = SchemaDiff {
+            missing: theirs
+                .iter()
+                .copied()
+                .filter(|c| !ours.contains(c))
+                .collect(),
+            extra: ours
+                .iter()
+                .copied()
+                .filter(|c| !theirs.contains(c))
+                .collect(),
+            typed_differently: Vec::new(),
+        };
+
+        let (a, b) = (&self.pattern_config, &other.pattern_config);
+        let position =
+            |config: &PatternConfig, p: PatternType| config.priority.iter().position(|&q| q == p);
+        for pattern in (0..=PatternType::Tid.as_u8()).map(PatternType::from_u8) {
+            let mut push = |reason: String| {
+                diff.typed_differently.push(TypeDifference {
+                    column: ColumnType::for_pattern(pattern),
+                    pattern,
+                    reason,
+                });
+            };
+            match (a.disabled.contains(&pattern), b.disabled.contains(&pattern)) {
+                (true, false) => push("disabled in this archive only".to_string()),
+                (false, true) => push("disabled in the other archive only".to_string()),
+                _ => {}
+            }
+            let guards = |config: &PatternConfig| -> Vec<(String, String)> {
+                let mut guards: Vec<_> = config
+                    .guards
+                    .iter()
+                    .filter(|g| g.pattern_type == pattern)
+                    .map(|g| (g.not_preceded_by.clone(), g.not_followed_by.clone()))
+                    .collect();
+                guards.sort();
+                guards
+            };
+            if guards(a) != guards(b) {
+                push("different guards".to_string());
+            }
+            let exclusions = |config: &PatternConfig| -> Vec<String> {
+                let mut regexes: Vec<_> = config
+                    .exclusions
+                    .iter()
+                    .filter(|e| e.pattern_type == pattern)
+                    .map(|e| e.regex.clone())
+                    .collect();
+                regexes.sort();
+                regexes
+            };
+            if exclusions(a) != exclusions(b) {
+                push("different exclusions".to_string());
+            }
+            if a.priority != b.priority && position(a, pattern) != position(b, pattern) {
+                push("different priority".to_string());
+            }
+        }
+
+        // Same pattern, but parsed on one side and kept as raw strings on the other
+        for (parsed, raw) in [
+            (ColumnType::Timestamps, ColumnType::TimestampsRaw),
+            (ColumnType::DateDays, ColumnType::DatesRaw),
+            (ColumnType::TimeMs, ColumnType::TimesRaw),
+        ] {
+            let only = |meta: &Self, col: ColumnType, alt: ColumnType| {
+                has_values(meta, col) && !has_values(meta, alt)
+            };
+            let reason = if only(self, parsed, raw) && only(other, raw, parsed) {
+                "parsed in this archive, raw strings in the other"
+            } else if only(self, raw, parsed) && only(other, parsed, raw) {
+                "raw strings in this archive, parsed in the other"
+            } else {
+                continue;
+            };
+            let pattern = match parsed {
+                ColumnType::Timestamps => PatternType::Timestamp,
+                ColumnType::DateDays => PatternType::Date,
+                _ => PatternType::Time,
+            };
+            diff.typed_differently.push(TypeDifference {
+                column: parsed,
+                pattern,
+                reason: reason.to_string(),
+            });
+        }
+        diff
+    }
+}
+
+/// Differences between two archives' columns ([`FormatV3Metadata::schema_diff`])
+///
+/// Only columns holding values count; skeleton and placeholder map are
+/// structural and always present.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaDiff {
+    /// Columns with values in the other archive but not in this one
+    pub missing: Vec<ColumnType>,
+    /// Columns with values in this archive but not in the other
+    pub extra: Vec<ColumnType>,
+    /// Columns whose values were extracted under different rules, so the same
+    /// text may land in different columns (or stay in the skeleton)
+    pub typed_differently: Vec<TypeDifference>,
+}
+
+impl SchemaDiff {
+    /// No typed differences (missing/extra columns are allowed)
+    #[must_use]
+    pub fn is_compatible(&self) -> bool {
+        self.typed_differently.is_empty()
+    }
+
+    /// Both archives have exactly the same value columns and rules
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.typed_differently.is_empty()
+    }
+}
+
+/// One column extracted differently in two archives
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeDifference {
+    pub column: ColumnType,
+    pub pattern: PatternType,
+    pub reason: String,
 }
 
 /// Compression level
@@ -286,225 +1807,624 @@ pub enum CompressionLevel {
 }
 
 impl CompressionLevel {
-    const fn zstd_level(self) -> i32 {
-        match self {
-            Self::Fast => 3,
-            Self::Balanced => 10,
-            Self::Best => 19,
+    /// Level stored in a header byte (unknown values fall back to the default)
+    pub(crate) const fn from_u8(v: u8) -> Self {
+        match v {
+            0 => Self::Fast,
+            2 => Self::Best,
+            _ => Self::Balanced,
         }
     }
+
+    /// Zstd level of this preset (see [`Level`])
+    #[must_use]
+    pub fn zstd_level(self) -> i32 {
+        Level::from(self).zstd_level()
+    }
 }
 
 /// Format v3 writer
 pub struct FormatV3Writer {
     encoder: ColumnarEncoder,
-    level: CompressionLevel,
+    level: Level,
+    options: CompressOptions,
+    extensions: Vec<HeaderExtension>,
+    first_line_id: Option<u64>,
 }
 
+const _: () = crate::assert_send_sync::<FormatV3Writer>();
+
 impl FormatV3Writer {
+    /// Writer at `level` (a [`Level`], or a [`CompressionLevel`] preset)
     #[must_use]
-    pub fn new(level: CompressionLevel) -> Self {
+    pub fn new(level: impl Into<Level>) -> Self {
         Self {
             encoder: ColumnarEncoder::new(),
-            level,
+            level: level.into(),
+            options: CompressOptions::default(),
+            extensions: Vec::new(),
+            first_line_id: None,
+        }
+    }
+
+    /// Writer reproducing an archive's level, pattern configuration and header
+    /// extensions, with `plugins` registered for its plugin column
+    pub(crate) fn matching(
+        metadata: &FormatV3Metadata,
+        plugins: &[Arc<dyn ColumnPlugin>],
+    ) -> Result<Self> {
+        Ok(metadata.extensions.iter().cloned().fold(
+            Self::new(metadata.level())
+                .with_pattern_config(metadata.pattern_config.clone())?
+                .with_plugins(plugins)?,
+            Self::with_extension,
+        ))
+    }
+
+    /// Append a header extension record (written in call order)
+    #[must_use]
+    pub fn with_extension(mut self, extension: HeaderExtension) -> Self {
+        self.extensions.push(extension);
+        self
+    }
+
+    /// Store named queries in the archive ([`HeaderExtension::SAVED_VIEWS`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a view has no name, shares a name with another,
+    /// or has a filter that does not parse.
+    pub fn with_views(self, views: &[SavedView]) -> Result<Self> {
+        Ok(self.with_extension(saved_view::to_extension(views)?))
+    }
+
+    /// Set compression options
+    #[must_use]
+    pub const fn with_options(mut self, options: CompressOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Use a custom pattern configuration (recorded in the archive)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exclusion regex is invalid.
+    pub fn with_pattern_config(mut self, config: PatternConfig) -> Result<Self> {
+        self.encoder = self.encoder.reconfigured(config)?;
+        Ok(self)
+    }
+
+    /// Use the pattern profile [`classify`] picks for `sample` and record
+    /// the detected [`SourceKind`] in the archive
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::with_pattern_config`].
+    pub fn with_detected_profile(self, sample: &str) -> Result<Self> {
+        let kind = classify(sample).kind;
+        Ok(self
+            .with_pattern_config(kind.pattern_config())?
+            .with_extension(HeaderExtension::new(
+                HeaderExtension::SOURCE_KIND,
+                vec![kind as u8],
+            )))
+    }
+
+    /// Number lines with stable ids `first, first + 1, ...` in the `line_ids` column
+    ///
+    /// Ids stay with their lines through pruning, compaction, splitting and
+    /// merging (see [`crate::line_index`]).
+    #[must_use]
+    pub const fn with_line_ids(mut self, first: u64) -> Self {
+        self.first_line_id = Some(first);
+        self
+    }
+
+    /// [`Producer`] record for this writer's settings
+    fn producer(&self) -> Producer {
+        let limits = self.options.pattern_limits;
+        let mut settings = std::collections::BTreeMap::new();
+        let mut set = |key: &str, value: String| settings.insert(key.to_string(), value);
+        set("level", self.level.to_string());
+        set("zstd_level", self.level.zstd_level().to_string());
+        set(
+            "verify_roundtrip",
+            self.options.verify_roundtrip.to_string(),
+        );
+        set("strip_ansi", self.options.strip_ansi.to_string());
+        let zstd = self.options.zstd;
+        set(
+            "long_distance_matching",
+            zstd.long_distance_matching.to_string(),
+        );
+        set(
+            "window_log",
+            zstd.window_log
+                .map_or("auto".to_string(), |log| log.to_string()),
+        );
+        set("zstd_workers", zstd.workers.to_string());
+        set(
+            "max_line_len",
+            limits
+                .max_line_len
+                .map_or("none".to_string(), |n| n.to_string()),
+        );
+        set("skip_binary", limits.skip_binary.to_string());
+        let patterns = if self.encoder.pattern_config().is_default() {
+            "default"
+        } else {
+            "custom"
+        };
+        set("pattern_config", patterns.to_string());
+        if let Some(first) = self.first_line_id {
+            set("first_line_id", first.to_string());
         }
+        Producer::current(FORMAT_V3_VERSION, settings)
+    }
+
+    /// Register a column plugin (see [`crate::column_plugin`])
+    ///
+    /// Archives with plugin values decode only through
+    /// [`Self::decompress_with_plugins`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`ColumnarEncoder::with_plugin`].
+    pub fn with_plugin(mut self, plugin: impl ColumnPlugin + 'static) -> Result<Self> {
+        self.encoder = self.encoder.with_plugin(plugin)?;
+        Ok(self)
+    }
+
+    /// Register a shared plugin registry (the one passed to
+    /// [`Self::decompress_with_plugins`]), in order
+    ///
+    /// # Errors
+    ///
+    /// Same as [`ColumnarEncoder::with_plugin`].
+    pub fn with_plugins(mut self, plugins: &[Arc<dyn ColumnPlugin>]) -> Result<Self> {
+        self.encoder = self.encoder.with_shared_plugins(plugins)?;
+        Ok(self)
     }
 
     /// Compress text to v3 format
     ///
     /// # Errors
     ///
-    /// Returns an error if Bincode serialization or Zstd compression of any column fails.
+    /// Returns an error if Bincode serialization or Zstd compression of any column fails, or
+    /// (with `verify_roundtrip`) if the output does not decode back to `text`.
     pub fn compress(&self, text: &str) -> Result<Vec<u8>> {
-        let original_length = text.len() as u64;
-        let payload = self.encoder.encode(text);
+        let text = self.options.prepare(text);
+        let (normalized, profile) = SourceProfile::normalize(&text);
+        self.compress_normalized(&normalized, profile, &[], None, None)
+    }
+
+    /// Compress a merged [`SourceBatch`], storing its line origins in the `provenance` column
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::compress`].
+    pub fn compress_batch(&self, batch: &SourceBatch) -> Result<Vec<u8>> {
+        let text = self.options.prepare(batch.text());
+        let (normalized, profile) = SourceProfile::normalize(&text);
+        self.compress_normalized(&normalized, profile, &[], Some(batch.provenance()), None)
+    }
+
+    /// Compress arbitrary bytes: UTF-8 (invalid runs kept verbatim), or UTF-16LE with BOM
+    ///
+    /// [`Self::decompress_bytes`] returns the exact input bytes (see [`SourceProfile`]).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::compress`].
+    pub fn compress_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let (text, profile, raw) = SourceProfile::decode(bytes);
+        match self.options.prepare(&text) {
+            Cow::Borrowed(text) => self.compress_normalized(text, profile, &raw, None, None),
+            // Stripping moved the placeholders; the (lossy) output keeps U+FFFD
+            Cow::Owned(text) => self.compress_normalized(&text, profile, &[], None, None),
+        }
+    }
+
+    /// Compress LF-only UTF-8 text, recording `profile` in the header flags,
+    /// `raw` (invalid UTF-8 runs behind placeholders) in the `raw_bytes` column,
+    /// the line origins (one per line) in the `provenance` column and the line
+    /// ids in the `line_ids` column (numbered afresh with [`Self::with_line_ids`]
+    /// when `line_ids` is `None`)
+    pub(crate) fn compress_normalized(
+        &self,
+        text: &str,
+        profile: SourceProfile,
+        raw: &[RawSegment],
+        provenance: Option<&ProvenanceColumn>,
+        line_ids: Option<&LineIdColumn>,
+    ) -> Result<Vec<u8>> {
+        let timer = telemetry::Timer::start();
+        let original_length = profile.restored_len(text, raw) as u64;
+        self.options.zstd.validate()?;
 
         // Count rows (log lines)
         let row_count = text.lines().count() as u64;
+        if let Some(provenance) = provenance {
+            if provenance.len() as u64 != row_count {
+                return Err(ALICETextError::EncodingError(format!(
+                    "Provenance covers {} lines, text has {row_count}",
+                    provenance.len()
+                )));
+            }
+        }
+        let numbered;
+        let line_ids = match (line_ids, self.first_line_id) {
+            (None, Some(first)) => {
+                numbered = LineIdColumn::sequential(first, row_count as usize)?;
+                Some(&numbered)
+            }
+            (line_ids, _) => line_ids,
+        };
+        if let Some(line_ids) = line_ids {
+            if line_ids.len() as u64 != row_count {
+                return Err(ALICETextError::EncodingError(format!(
+                    "Line ids cover {} lines, text has {row_count}",
+                    line_ids.len()
+                )));
+            }
+        }
+
+        let payload = self
+            .encoder
+            .encode_limited(text, self.options.pattern_limits);
+        let value_bytes = text.len().saturating_sub(payload.literal_bytes()) as u64;
 
         // Prepare columns for individual compression
         let mut column_data: Vec<(ColumnType, Vec<u8>, u32)> = Vec::new();
 
         // Helper to compress and add column
-        let zstd_level = self.level.zstd_level();
-        let mut add_column = |col_type: ColumnType, data: &[u8], count: u32| -> Result<()> {
+        let (zstd_level, zstd) = (self.level.zstd_level(), self.options.zstd);
+        let mut add_column = |col_type: ColumnType, data: &[u8], count: usize| -> Result<()> {
+            // Row counts are u32 (placeholder map entries index columns as u32)
+            let count = to_u32(count, "v3 column row count")?;
             if !data.is_empty()
                 || col_type == ColumnType::Skeleton
                 || col_type == ColumnType::PlaceholderMap
             {
-                let compressed = zstd::stream::encode_all(Cursor::new(data), zstd_level)
-                    .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")))?;
+                let compressed = zstd.compress(data, zstd_level)?;
                 column_data.push((col_type, compressed, count));
             }
             Ok(())
         };
 
         // Serialize each column separately
-        // 1. Skeleton tokens
-        let skeleton_bytes = bincode::serialize(&payload.skeleton_tokens)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        // 1. Skeleton tokens (literals interned)
+        let skeleton_bytes = encode_column(&InternedSkeleton::intern(&payload.skeleton_tokens));
         add_column(
             ColumnType::Skeleton,
             &skeleton_bytes,
-            payload.skeleton_tokens.len() as u32,
+            payload.skeleton_tokens.len(),
         )?;
 
-        // 2. Placeholder map
-        let placeholder_bytes = bincode::serialize(&payload.placeholder_map)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        // 2. Placeholder map (packed)
+        let placeholder_bytes = pack_placeholder_map(&payload.placeholder_map);
         add_column(
             ColumnType::PlaceholderMap,
             &placeholder_bytes,
-            payload.placeholder_map.len() as u32,
+            payload.placeholder_map.len(),
         )?;
 
         // 3. Timestamps (delta-encoded)
-        let ts_bytes = bincode::serialize(&payload.timestamps)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        let ts_bytes = encode_column(&payload.timestamps);
         add_column(
             ColumnType::Timestamps,
             &ts_bytes,
-            payload.timestamps.deltas.len() as u32,
+            payload.timestamps.deltas.len(),
         )?;
 
+        // Integer and number columns: encoding chosen per column (see `encode_auto`)
+        let mut encodings = HashMap::new();
+
         // 4. IPv4
-        let ipv4_bytes = bincode::serialize(&payload.ipv4_addrs)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::IPv4,
-            &ipv4_bytes,
-            payload.ipv4_addrs.len() as u32,
-        )?;
+        let (ipv4_bytes, encoding) = encode_auto(&payload.ipv4_addrs, zstd_level);
+        encodings.insert(ColumnType::IPv4, encoding);
+        add_column(ColumnType::IPv4, &ipv4_bytes, payload.ipv4_addrs.len())?;
 
         // 5. IPv6
-        let ipv6_bytes = bincode::serialize(&payload.ipv6_addrs)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::IPv6,
-            &ipv6_bytes,
-            payload.ipv6_addrs.len() as u32,
-        )?;
+        let (ipv6_bytes, encoding) = encode_auto(&payload.ipv6_addrs, zstd_level);
+        encodings.insert(ColumnType::IPv6, encoding);
+        add_column(ColumnType::IPv6, &ipv6_bytes, payload.ipv6_addrs.len())?;
 
         // 6. Log levels
-        let log_bytes = bincode::serialize(&payload.log_levels)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::LogLevels,
-            &log_bytes,
-            payload.log_levels.len() as u32,
-        )?;
+        let (log_bytes, encoding) = encode_auto(&payload.log_levels, zstd_level);
+        encodings.insert(ColumnType::LogLevels, encoding);
+        add_column(ColumnType::LogLevels, &log_bytes, payload.log_levels.len())?;
 
-        // 7. Numbers
-        let num_bytes = bincode::serialize(&payload.numbers)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::Numbers,
-            &num_bytes,
-            payload.numbers.len() as u32,
-        )?;
+        // 7. Numbers (encoded as their bits)
+        let bits: Vec<u64> = payload.numbers.iter().map(|n| n.to_bits()).collect();
+        let (num_bytes, encoding) = encode_auto(&bits, zstd_level);
+        encodings.insert(ColumnType::Numbers, encoding);
+        add_column(ColumnType::Numbers, &num_bytes, payload.numbers.len())?;
 
         // 8. UUIDs
-        let uuid_bytes = bincode::serialize(&payload.uuids)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(ColumnType::UUIDs, &uuid_bytes, payload.uuids.len() as u32)?;
+        let (uuid_bytes, encoding) = encode_auto(&payload.uuids, zstd_level);
+        encodings.insert(ColumnType::UUIDs, encoding);
+        add_column(ColumnType::UUIDs, &uuid_bytes, payload.uuids.len())?;
 
         // 9. Emails
-        let email_bytes = bincode::serialize(&payload.emails)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::Emails,
-            &email_bytes,
-            payload.emails.len() as u32,
-        )?;
+        let email_bytes = encode_column(&payload.emails);
+        add_column(ColumnType::Emails, &email_bytes, payload.emails.len())?;
 
         // 10. URLs
-        let url_bytes = bincode::serialize(&payload.urls)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(ColumnType::URLs, &url_bytes, payload.urls.len() as u32)?;
+        let url_bytes = encode_column(&payload.urls);
+        add_column(ColumnType::URLs, &url_bytes, payload.urls.len())?;
 
         // 11. Paths
-        let path_bytes = bincode::serialize(&payload.paths)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(ColumnType::Paths, &path_bytes, payload.paths.len() as u32)?;
+        let path_bytes = encode_column(&payload.paths);
+        add_column(ColumnType::Paths, &path_bytes, payload.paths.len())?;
 
         // 12. Date days
-        let date_days_bytes = bincode::serialize(&payload.date_days)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        let (date_days_bytes, encoding) = encode_auto(&payload.date_days, zstd_level);
+        encodings.insert(ColumnType::DateDays, encoding);
         add_column(
             ColumnType::DateDays,
             &date_days_bytes,
-            payload.date_days.len() as u32,
+            payload.date_days.len(),
         )?;
 
         // 13. Dates raw
-        let dates_raw_bytes = bincode::serialize(&payload.dates)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::DatesRaw,
-            &dates_raw_bytes,
-            payload.dates.len() as u32,
-        )?;
+        let dates_raw_bytes = encode_column(&payload.dates);
+        add_column(ColumnType::DatesRaw, &dates_raw_bytes, payload.dates.len())?;
 
         // 14. Time ms
-        let time_ms_bytes = bincode::serialize(&payload.time_ms)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::TimeMs,
-            &time_ms_bytes,
-            payload.time_ms.len() as u32,
-        )?;
+        let (time_ms_bytes, encoding) = encode_auto(&payload.time_ms, zstd_level);
+        encodings.insert(ColumnType::TimeMs, encoding);
+        add_column(ColumnType::TimeMs, &time_ms_bytes, payload.time_ms.len())?;
 
         // 15. Times raw
-        let times_raw_bytes = bincode::serialize(&payload.times)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::TimesRaw,
-            &times_raw_bytes,
-            payload.times.len() as u32,
-        )?;
+        let times_raw_bytes = encode_column(&payload.times);
+        add_column(ColumnType::TimesRaw, &times_raw_bytes, payload.times.len())?;
 
         // 16. Hex values
-        let hex_bytes = bincode::serialize(&payload.hex_values)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::HexValues,
-            &hex_bytes,
-            payload.hex_values.len() as u32,
-        )?;
+        let hex_bytes = encode_column(&payload.hex_values);
+        add_column(ColumnType::HexValues, &hex_bytes, payload.hex_values.len())?;
 
         // 17. Others
-        let others_bytes = bincode::serialize(&payload.others)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
-        add_column(
-            ColumnType::Others,
-            &others_bytes,
-            payload.others.len() as u32,
-        )?;
+        let others_bytes = encode_column(&payload.others);
+        add_column(ColumnType::Others, &others_bytes, payload.others.len())?;
 
         // 18. Timestamps raw
-        let ts_raw_bytes = bincode::serialize(&payload.timestamps.raw)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        let ts_raw_bytes = encode_column(&payload.timestamps.raw);
         add_column(
             ColumnType::TimestampsRaw,
             &ts_raw_bytes,
-            payload.timestamps.raw.len() as u32,
+            payload.timestamps.raw.len(),
         )?;
 
-        // Calculate offsets
-        let header_start = 8 + 2; // Magic + Version
-        let directory_start = header_start + FormatV3Header::SIZE;
-        let data_start = directory_start + column_data.len() * ColumnEntry::SIZE;
+        // 19. Epochs (delta-encoded)
+        if !payload.epochs.is_empty() {
+            let epoch_bytes = encode_column(&payload.epochs);
+            add_column(ColumnType::Epochs, &epoch_bytes, payload.epochs.len())?;
+        }
 
-        let mut current_offset = data_start as u64;
-        let mut entries: Vec<ColumnEntry> = Vec::new();
+        // 20. Uptimes (delta-encoded)
+        if !payload.uptimes.is_empty() {
+            let uptime_bytes = encode_column(&payload.uptimes);
+            add_column(ColumnType::Uptimes, &uptime_bytes, payload.uptimes.len())?;
+        }
 
-        for (col_type, compressed, count) in &column_data {
-            entries.push(ColumnEntry {
+        // 21. ANSI escape sequences
+        if !payload.ansi_codes.is_empty() {
+            let ansi_bytes = encode_column(&payload.ansi_codes);
+            add_column(ColumnType::AnsiCodes, &ansi_bytes, payload.ansi_codes.len())?;
+        }
+
+        // 22. MAC addresses (48 bits + format flags)
+        if !payload.mac_addrs.is_empty() {
+            let mac_bytes = encode_column(&payload.mac_addrs);
+            add_column(ColumnType::MacAddrs, &mac_bytes, payload.mac_addrs.len())?;
+        }
+
+        // 23. Latitude/longitude pairs (f32 x 2 + format)
+        if !payload.geo_points.is_empty() {
+            let geo_bytes = encode_column(&payload.geo_points);
+            add_column(ColumnType::GeoPoints, &geo_bytes, payload.geo_points.len())?;
+        }
+
+        // 24-28. Kubernetes metadata
+        let k8s = &payload.k8s;
+        if !k8s.cri_prefixes.is_empty() {
+            let cri_bytes = encode_column(&k8s.cri_prefixes);
+            add_column(ColumnType::CriPrefixes, &cri_bytes, k8s.cri_prefixes.len())?;
+        }
+        if !k8s.klog_headers.headers.is_empty() {
+            let klog_bytes = encode_column(&k8s.klog_headers);
+            let count = k8s.klog_headers.headers.len();
+            add_column(ColumnType::KlogHeaders, &klog_bytes, count)?;
+        }
+        if !k8s.container_ids.is_empty() {
+            let id_bytes = encode_column(&k8s.container_ids);
+            add_column(ColumnType::ContainerIds, &id_bytes, k8s.container_ids.len())?;
+        }
+        if !k8s.namespaces.values.is_empty() {
+            let ns_bytes = encode_column(&k8s.namespaces);
+            let count = k8s.namespaces.values.len();
+            add_column(ColumnType::K8sNamespaces, &ns_bytes, count)?;
+        }
+        if !k8s.pods.pods.is_empty() {
+            let pod_bytes = encode_column(&k8s.pods);
+            add_column(ColumnType::K8sPods, &pod_bytes, k8s.pods.pods.len())?;
+        }
+
+        // 29-30. HTTP methods (enum index) and status codes (u16)
+        if !payload.http_methods.is_empty() {
+            let (method_bytes, encoding) = encode_auto(&payload.http_methods, zstd_level);
+            encodings.insert(ColumnType::HttpMethods, encoding);
+            let count = payload.http_methods.len();
+            add_column(ColumnType::HttpMethods, &method_bytes, count)?;
+        }
+        if !payload.http_statuses.is_empty() {
+            let (status_bytes, encoding) = encode_auto(&payload.http_statuses, zstd_level);
+            encodings.insert(ColumnType::HttpStatuses, encoding);
+            let count = payload.http_statuses.len();
+            add_column(ColumnType::HttpStatuses, &status_bytes, count)?;
+        }
+
+        // 31. Durations (ns + format word)
+        if !payload.durations.is_empty() {
+            let duration_bytes = encode_column(&payload.durations);
+            add_column(
+                ColumnType::Durations,
+                &duration_bytes,
+                payload.durations.len(),
+            )?;
+        }
+
+        // 32. Byte sizes (bytes + format byte)
+        if !payload.byte_sizes.is_empty() {
+            let size_bytes = encode_column(&payload.byte_sizes);
+            add_column(ColumnType::ByteSizes, &size_bytes, payload.byte_sizes.len())?;
+        }
+
+        // 33. Hostnames (label suffix tree)
+        if !payload.hosts.is_empty() {
+            let host_bytes = encode_column(&payload.hosts);
+            add_column(ColumnType::Hosts, &host_bytes, payload.hosts.len())?;
+        }
+
+        // 34-35. Process and thread ids (u32)
+        if !payload.pids.is_empty() {
+            let (pid_bytes, encoding) = encode_auto(&payload.pids, zstd_level);
+            encodings.insert(ColumnType::Pids, encoding);
+            add_column(ColumnType::Pids, &pid_bytes, payload.pids.len())?;
+        }
+        if !payload.tids.is_empty() {
+            let (tid_bytes, encoding) = encode_auto(&payload.tids, zstd_level);
+            encodings.insert(ColumnType::Tids, encoding);
+            add_column(ColumnType::Tids, &tid_bytes, payload.tids.len())?;
+        }
+
+        // 36. Invalid UTF-8 runs (binary-safe input)
+        if !raw.is_empty() {
+            let raw_bytes = encode_column(raw);
+            add_column(ColumnType::RawBytes, &raw_bytes, raw.len())?;
+        }
+
+        // 37. Line provenance (merged sources)
+        if let Some(provenance) = provenance.filter(|p| !p.is_empty()) {
+            let provenance_bytes = encode_column(provenance);
+            add_column(ColumnType::Provenance, &provenance_bytes, provenance.len())?;
+        }
+
+        // 38. Line ids (delta-encoded)
+        if let Some(line_ids) = line_ids.filter(|ids| !ids.is_empty()) {
+            let line_id_bytes = encode_column(line_ids);
+            add_column(ColumnType::LineIds, &line_id_bytes, line_ids.len())?;
+        }
+
+        // 39. Spellings of typed values that do not restore canonically
+        if !payload.spellings.is_empty() {
+            let spelling_bytes = encode_column(&payload.spellings);
+            add_column(
+                ColumnType::Spellings,
+                &spelling_bytes,
+                payload.spellings.len(),
+            )?;
+        }
+
+        // 40. Column plugins (schema goes in a header extension)
+        let plugin_schema = if payload.plugin_columns.iter().any(|c| !c.values.is_empty()) {
+            let (schema, encoded) =
+                column_plugin::encode_columns(self.encoder.plugins(), &payload.plugin_columns)?;
+            add_column(ColumnType::Plugins, &encode_column(&encoded), encoded.len())?;
+            Some(encode_column(&schema))
+        } else {
+            None
+        };
+
+        // Column summaries (header-readable min/max/quantiles)
+        let summaries = ColumnSummary::from_payload(&payload);
+        let summary_bytes = if summaries.is_empty() {
+            Vec::new()
+        } else {
+            encode_column(&summaries)
+        };
+
+        // Pattern config (only when not the default)
+        let config = self.encoder.pattern_config();
+        let config_bytes = if config.is_default() {
+            Vec::new()
+        } else {
+            encode_column(config)
+        };
+
+        // Column encodings other than plain, in column type order
+        let mut encoded: Vec<[u8; 2]> = encodings
+            .iter()
+            .filter(|(_, &encoding)| encoding != ColumnEncoding::Plain)
+            .map(|(&col_type, &encoding)| [col_type as u8, encoding as u8])
+            .collect();
+        encoded.sort_unstable();
+
+        // Hot query columns first, text structure last (stable within a tier)
+        column_data.sort_by_key(|(col_type, _, _)| col_type.layout_tier());
+        let checksum = column_data
+            .iter()
+            .fold(FNV_OFFSET, |hash, (_, compressed, _)| {
+                fnv1a(compressed, hash)
+            });
+
+        // Fresh value-bytes, producer, plugin, encoding, window and checksum records first (carried-over ones may be stale)
+        let fresh = [
+            HeaderExtension::VALUE_BYTES,
+            HeaderExtension::PRODUCER,
+            HeaderExtension::PLUGIN_SCHEMA,
+            HeaderExtension::COLUMN_ENCODINGS,
+            HeaderExtension::ZSTD_WINDOW,
+            HeaderExtension::CHECKSUM,
+        ];
+        let window_log = self.options.zstd.required_window_log();
+        let extensions: Vec<HeaderExtension> = [
+            HeaderExtension::new(
+                HeaderExtension::VALUE_BYTES,
+                value_bytes.to_le_bytes().to_vec(),
+            ),
+            self.producer().to_extension()?,
+            HeaderExtension::new(HeaderExtension::CHECKSUM, checksum.to_le_bytes().to_vec()),
+        ]
+        .into_iter()
+        .chain(plugin_schema.map(|data| HeaderExtension::new(HeaderExtension::PLUGIN_SCHEMA, data)))
+        .chain(
+            (!encoded.is_empty())
+                .then(|| HeaderExtension::new(HeaderExtension::COLUMN_ENCODINGS, encoded.concat())),
+        )
+        .chain(window_log.map(|log| HeaderExtension::new(HeaderExtension::ZSTD_WINDOW, vec![log])))
+        .chain(
+            self.extensions
+                .iter()
+                .filter(|e| !fresh.contains(&e.tag))
+                .cloned(),
+        )
+        .collect();
+        let extension_bytes = HeaderExtension::encode_all(&extensions)?;
+
+        // Calculate offsets
+        let header_start = 8 + 2; // Magic + Version
+        let directory_start = header_start + FormatV3Header::SIZE;
+        let data_start = directory_start
+            + column_data.len() * ColumnEntry::SIZE
+            + summary_bytes.len()
+            + config_bytes.len()
+            + extension_bytes.len();
+
+        let mut current_offset = data_start as u64;
+        let mut entries: Vec<ColumnEntry> = Vec::new();
+
+        for (col_type, compressed, count) in &column_data {
+            entries.push(ColumnEntry {
                 col_type: *col_type,
                 offset: current_offset,
-                compressed_size: compressed.len() as u32,
+                compressed_size: to_u32(compressed.len(), "v3 column size")?,
                 uncompressed_size: 0, // We don't track this for simplicity
                 row_count: *count,
+                encoding: encodings.get(col_type).copied().unwrap_or_default(),
+                window_log: window_log.unwrap_or(0),
             });
             current_offset += compressed.len() as u64;
         }
@@ -523,10 +2443,21 @@ impl FormatV3Writer {
         // Write header
         let header = FormatV3Header {
             original_length,
-            compression_level: self.level as u8,
-            column_count: entries.len() as u16,
+            compression_level: self.level.v3_level() as u8,
+            column_count: to_u16(entries.len(), "v3 column count")?,
             row_count,
-            reserved: [0u8; 13],
+            summary_size: to_u32(summary_bytes.len(), "v3 summary size")?,
+            config_size: to_u32(config_bytes.len(), "v3 pattern config size")?,
+            flags: profile.flags()
+                | FormatV3Header::FLAG_INTERNED_SKELETON
+                | FormatV3Header::FLAG_PACKED_PLACEHOLDERS
+                | FormatV3Header::FLAG_EXTENSIONS
+                | if payload.spellings.is_empty() {
+                    0
+                } else {
+                    FormatV3Header::FLAG_SPELLINGS
+                },
+            extension_size: to_u32(extension_bytes.len(), "v3 extension size")?,
         };
         output.extend_from_slice(&header.to_bytes());
 
@@ -535,11 +2466,24 @@ impl FormatV3Writer {
             output.extend_from_slice(&entry.to_bytes());
         }
 
+        // Write column summaries, pattern config and header extensions
+        output.extend_from_slice(&summary_bytes);
+        output.extend_from_slice(&config_bytes);
+        output.extend_from_slice(&extension_bytes);
+
         // Write column data
         for (_, compressed, _) in column_data {
             output.extend_from_slice(&compressed);
         }
 
+        if self.options.verify_roundtrip {
+            let decoded = Self::decode_payload(&output, self.encoder.plugins())
+                .map(|payload| payload.restore())
+                .map_err(|e| ALICETextError::VerificationFailed(e.to_string()))?;
+            verify_roundtrip(text, &decoded)?;
+        }
+
+        telemetry::record_compress(&timer, "v3", original_length as usize, output.len());
         Ok(output)
     }
 
@@ -549,13 +2493,163 @@ impl FormatV3Writer {
     ///
     /// Returns an error if metadata reading or column decompression fails.
     pub fn decompress(data: &[u8]) -> Result<String> {
+        let timer = telemetry::Timer::start();
+        let text = Self::decode(data)?;
+        telemetry::record_decompress(&timer, "v3", data.len(), text.len());
+        Ok(text)
+    }
+
+    /// Decompress to the exact original input bytes (invalid UTF-8 spliced back,
+    /// UTF-16LE archives re-encoded)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if metadata reading or column decompression fails.
+    pub fn decompress_bytes(data: &[u8]) -> Result<Vec<u8>> {
+        let timer = telemetry::Timer::start();
+        let (text, profile, raw) = Self::decode_normalized(data, &[])?;
+        let bytes = profile.encode(text, &raw);
+        telemetry::record_decompress(&timer, "v3", data.len(), bytes.len());
+        Ok(bytes)
+    }
+
+    /// Decompress an archive written with column plugins
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decoding fails or a plugin listed in the
+    /// archive's schema is not among `plugins`.
+    pub fn decompress_with_plugins(
+        data: &[u8],
+        plugins: &[Arc<dyn ColumnPlugin>],
+    ) -> Result<String> {
+        let timer = telemetry::Timer::start();
+        let (text, profile, _) = Self::decode_normalized(data, plugins)?;
+        let text = profile.restore_text(text);
+        telemetry::record_decompress(&timer, "v3", data.len(), text.len());
+        Ok(text)
+    }
+
+    fn decode(data: &[u8]) -> Result<String> {
+        let (text, profile, _) = Self::decode_normalized(data, &[])?;
+        Ok(profile.restore_text(text))
+    }
+
+    /// Decompress with explicit [`DecodeOptions`]
+    ///
+    /// In lenient mode columns can be left out of the text; use
+    /// [`Self::decompress_with_report`] to learn which.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::decompress_with_report`].
+    pub fn decompress_with(data: &[u8], options: DecodeOptions) -> Result<String> {
+        Self::decompress_with_report(data, options).map(|(text, _)| text)
+    }
+
+    /// Decompress with explicit [`DecodeOptions`], reporting the columns
+    /// lenient mode left out
+    ///
+    /// Lenient mode also recovers what it can from a damaged archive: value
+    /// columns that fail to decompress or deserialize are left empty, so the
+    /// affected values are missing from the text. Skeleton, placeholder map and
+    /// the plugin column must still decode: an archive needing a column plugin
+    /// fails here as it does in strict mode (see
+    /// [`Self::decompress_with_plugins`]). Strict mode never drops a column, so its report is
+    /// always empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if metadata reading fails (see
+    /// [`FormatV3Metadata::read_from_with`]), a structural column cannot be
+    /// decoded, or in strict mode any column fails.
+    pub fn decompress_with_report(
+        data: &[u8],
+        options: DecodeOptions,
+    ) -> Result<(String, DroppedColumns)> {
+        let timer = telemetry::Timer::start();
+        let mut cursor = Cursor::new(data);
+        let metadata = FormatV3Metadata::read_from_with(&mut cursor, options)?;
+        let mut dropped = DroppedColumns {
+            unknown: metadata.unknown_columns.clone(),
+            damaged: Vec::new(),
+        };
+        let payload = match Self::read_all_columns(&mut cursor, &metadata, &[]) {
+            Ok(payload) => payload,
+            Err(e) if options.strict => return Err(e),
+            Err(_) => {
+                dropped.damaged = metadata
+                    .columns
+                    .iter()
+                    .map(|entry| entry.col_type)
+                    .filter(|&col| {
+                        !matches!(
+                            col,
+                            ColumnType::Skeleton | ColumnType::PlaceholderMap | ColumnType::Plugins
+                        ) && Self::read_payload(&mut cursor, &metadata, |c| c == col).is_err()
+                    })
+                    .collect();
+                Self::read_payload(&mut cursor, &metadata, |c| !dropped.damaged.contains(&c))?
+            }
+        };
+        if !dropped.is_empty() {
+            log::warn!(
+                target: LOG_TARGET,
+                "lenient decode left out columns: unknown types {:?}, damaged {:?}",
+                dropped.unknown,
+                dropped.damaged
+            );
+        }
+        let text = metadata
+            .header
+            .source_profile()
+            .restore_text(payload.restore());
+        telemetry::record_decompress(&timer, "v3", data.len(), text.len());
+        Ok((text, dropped))
+    }
+
+    /// Decode to the LF-only text that was encoded, plus what's needed to restore the input
+    pub(crate) fn decode_normalized(
+        data: &[u8],
+        plugins: &[Arc<dyn ColumnPlugin>],
+    ) -> Result<(String, SourceProfile, Vec<RawSegment>)> {
+        let mut cursor = Cursor::new(data);
+        let metadata = FormatV3Metadata::read_from(&mut cursor)?;
+        let mut payload = Self::read_all_columns(&mut cursor, &metadata, plugins)?;
+        let raw = std::mem::take(&mut payload.raw_bytes);
+        Ok((payload.restore(), metadata.header.source_profile(), raw))
+    }
+
+    /// Read every column back into a full payload
+    pub(crate) fn decode_payload(
+        data: &[u8],
+        plugins: &[Arc<dyn ColumnPlugin>],
+    ) -> Result<ColumnarPayload> {
         let mut cursor = Cursor::new(data);
         let metadata = FormatV3Metadata::read_from(&mut cursor)?;
+        Self::read_all_columns(&mut cursor, &metadata, plugins)
+    }
 
-        // Read all columns and reconstruct payload
-        let payload = Self::read_all_columns(&mut cursor, &metadata)?;
+    /// Read the line provenance column (`None` for single-source archives)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if metadata reading or column decompression fails.
+    pub fn read_provenance(data: &[u8]) -> Result<Option<ProvenanceColumn>> {
+        let mut cursor = Cursor::new(data);
+        let metadata = FormatV3Metadata::read_from(&mut cursor)?;
+        Ok(Self::read_columns(&mut cursor, &metadata, &[ColumnType::Provenance])?.provenance)
+    }
 
-        Ok(payload.restore())
+    /// Read the line id column (`None` unless written with [`Self::with_line_ids`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if metadata reading or column decompression fails.
+    pub fn read_line_ids(data: &[u8]) -> Result<Option<LineIdColumn>> {
+        let mut cursor = Cursor::new(data);
+        let metadata = FormatV3Metadata::read_from(&mut cursor)?;
+        Ok(Self::read_columns(&mut cursor, &metadata, &[ColumnType::LineIds])?.line_ids)
     }
 
     /// Read specific columns only (selective decompression)
@@ -567,69 +2661,171 @@ impl FormatV3Writer {
         reader: &mut R,
         metadata: &FormatV3Metadata,
         column_types: &[ColumnType],
+    ) -> Result<PartialPayload> {
+        Self::read_columns_with(metadata, column_types, |entry| {
+            Self::read_column_bytes(reader, entry, metadata.limits.max_column_bytes)
+        })
+    }
+
+    /// Like [`Self::read_columns`], plus a [`RowMap`] of the columns in `rows`
+    ///
+    /// Also decompresses the skeleton and the placeholder map.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if seeking, reading, or Zstd/Bincode decompression of any column fails.
+    pub fn read_columns_by_row<R: Read + Seek>(
+        reader: &mut R,
+        metadata: &FormatV3Metadata,
+        column_types: &[ColumnType],
+    ) -> Result<PartialPayload> {
+        Self::read_columns_by_row_with(metadata, column_types, |entry| {
+            Self::read_column_bytes(reader, entry, metadata.limits.max_column_bytes)
+        })
+    }
+
+    /// [`Self::read_columns_by_row`] over any column source
+    pub(crate) fn read_columns_by_row_with(
+        metadata: &FormatV3Metadata,
+        column_types: &[ColumnType],
+        mut fetch: impl FnMut(&ColumnEntry) -> Result<Vec<u8>>,
+    ) -> Result<PartialPayload> {
+        let structure = Self::read_payload_with(
+            metadata,
+            &[],
+            |c| matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap),
+            &mut fetch,
+        )?;
+        let mut partial = Self::read_columns_with(metadata, column_types, fetch)?;
+        partial.rows = Some(RowMap::build(&structure, column_types));
+        Ok(partial)
+    }
+
+    /// Seek to a column block and Zstd-decompress it (`column_codec` bytes,
+    /// at most `max_bytes`)
+    pub(crate) fn read_column_bytes<R: Read + Seek>(
+        reader: &mut R,
+        entry: &ColumnEntry,
+        max_bytes: u64,
+    ) -> Result<Vec<u8>> {
+        // Lenient metadata does not check the directory against the stream
+        // length; do it before sizing the buffer from it
+        let len = reader.seek(SeekFrom::End(0))?;
+        let end = entry
+            .offset
+            .saturating_add(u64::from(entry.compressed_size));
+        if end > len {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Column {:?} runs past the end of the archive ({end} > {len} bytes)",
+                entry.col_type
+            )));
+        }
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        let mut compressed = vec![0u8; entry.compressed_size as usize];
+        reader.read_exact(&mut compressed)?;
+        zstd_params::decompress(&compressed, &[], entry.window_log, max_bytes)
+    }
+
+    /// [`Self::read_columns`] with decompressed column bytes supplied by `fetch`
+    /// (e.g. from a cache)
+    pub(crate) fn read_columns_with(
+        metadata: &FormatV3Metadata,
+        column_types: &[ColumnType],
+        mut fetch: impl FnMut(&ColumnEntry) -> Result<Vec<u8>>,
     ) -> Result<PartialPayload> {
         let mut partial = PartialPayload::default();
+        let mut total = 0u64;
 
         for col_type in column_types {
             if let Some(entry) = metadata.get_column(*col_type) {
-                reader.seek(SeekFrom::Start(entry.offset))?;
-                let mut compressed = vec![0u8; entry.compressed_size as usize];
-                reader.read_exact(&mut compressed)?;
-
-                let decompressed = zstd::stream::decode_all(Cursor::new(&compressed))
-                    .map_err(|e| ALICETextError::DecompressionError(format!("Zstd error: {e}")))?;
+                let decompressed = fetch(entry)?;
+                total += decompressed.len() as u64;
+                metadata.limits.check_total(total)?;
 
                 match col_type {
                     ColumnType::LogLevels => {
-                        partial.log_levels =
-                            Some(bincode::deserialize(&decompressed).map_err(|e| {
-                                ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                            })?);
+                        partial.log_levels = Some(decode_entry(entry, &decompressed)?);
                     }
                     ColumnType::Timestamps => {
-                        partial.timestamps =
-                            Some(bincode::deserialize(&decompressed).map_err(|e| {
-                                ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                            })?);
+                        partial.timestamps = Some(decode_column(&decompressed)?);
                     }
                     ColumnType::IPv4 => {
-                        partial.ipv4_addrs =
-                            Some(bincode::deserialize(&decompressed).map_err(|e| {
-                                ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                            })?);
+                        partial.ipv4_addrs = Some(decode_entry(entry, &decompressed)?);
                     }
                     ColumnType::IPv6 => {
-                        partial.ipv6_addrs =
-                            Some(bincode::deserialize(&decompressed).map_err(|e| {
-                                ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                            })?);
+                        partial.ipv6_addrs = Some(decode_entry(entry, &decompressed)?);
                     }
                     ColumnType::Numbers => {
-                        partial.numbers =
-                            Some(bincode::deserialize(&decompressed).map_err(|e| {
-                                ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                            })?);
+                        partial.numbers = Some(decode_numbers(entry, &decompressed)?);
                     }
                     ColumnType::UUIDs => {
-                        partial.uuids = Some(bincode::deserialize(&decompressed).map_err(|e| {
-                            ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                        })?);
+                        partial.uuids = Some(decode_entry(entry, &decompressed)?);
                     }
                     ColumnType::Emails => {
-                        partial.emails =
-                            Some(bincode::deserialize(&decompressed).map_err(|e| {
-                                ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                            })?);
+                        partial.emails = Some(decode_column(&decompressed)?);
                     }
                     ColumnType::URLs => {
-                        partial.urls = Some(bincode::deserialize(&decompressed).map_err(|e| {
-                            ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                        })?);
+                        partial.urls = Some(decode_column(&decompressed)?);
                     }
                     ColumnType::Paths => {
-                        partial.paths = Some(bincode::deserialize(&decompressed).map_err(|e| {
-                            ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                        })?);
+                        partial.paths = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::Epochs => {
+                        partial.epochs = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::Uptimes => {
+                        partial.uptimes = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::AnsiCodes => {
+                        partial.ansi_codes = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::MacAddrs => {
+                        partial.mac_addrs = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::GeoPoints => {
+                        partial.geo_points = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::CriPrefixes => {
+                        partial.cri_prefixes = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::KlogHeaders => {
+                        partial.klog_headers = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::ContainerIds => {
+                        partial.container_ids = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::K8sNamespaces => {
+                        partial.k8s_namespaces = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::K8sPods => {
+                        partial.k8s_pods = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::HttpMethods => {
+                        partial.http_methods = Some(decode_entry(entry, &decompressed)?);
+                    }
+                    ColumnType::HttpStatuses => {
+                        partial.http_statuses = Some(decode_entry(entry, &decompressed)?);
+                    }
+                    ColumnType::Durations => {
+                        partial.durations = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::ByteSizes => {
+                        partial.byte_sizes = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::Hosts => {
+                        partial.hosts = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::Pids => {
+                        partial.pids = Some(decode_entry(entry, &decompressed)?);
+                    }
+                    ColumnType::Tids => {
+                        partial.tids = Some(decode_entry(entry, &decompressed)?);
+                    }
+                    ColumnType::Provenance => {
+                        partial.provenance = Some(decode_column(&decompressed)?);
+                    }
+                    ColumnType::LineIds => {
+                        partial.line_ids = Some(decode_column(&decompressed)?);
                     }
                     _ => {}
                 }
@@ -643,6 +2839,34 @@ impl FormatV3Writer {
     fn read_all_columns<R: Read + Seek>(
         reader: &mut R,
         metadata: &FormatV3Metadata,
+        plugins: &[Arc<dyn ColumnPlugin>],
+    ) -> Result<ColumnarPayload> {
+        Self::read_payload_with(
+            metadata,
+            plugins,
+            |_| true,
+            |entry| Self::read_column_bytes(reader, entry, metadata.limits.max_column_bytes),
+        )
+    }
+
+    /// Reconstruct a payload from the columns accepted by `wanted` (others left empty)
+    pub(crate) fn read_payload<R: Read + Seek>(
+        reader: &mut R,
+        metadata: &FormatV3Metadata,
+        wanted: impl Fn(ColumnType) -> bool,
+    ) -> Result<ColumnarPayload> {
+        Self::read_payload_with(metadata, &[], wanted, |entry| {
+            Self::read_column_bytes(reader, entry, metadata.limits.max_column_bytes)
+        })
+    }
+
+    /// [`Self::read_payload`] with decompressed column bytes supplied by `fetch`
+    /// and `plugins` to decode the plugin column with
+    pub(crate) fn read_payload_with(
+        metadata: &FormatV3Metadata,
+        plugins: &[Arc<dyn ColumnPlugin>],
+        wanted: impl Fn(ColumnType) -> bool,
+        mut fetch: impl FnMut(&ColumnEntry) -> Result<Vec<u8>>,
     ) -> Result<ColumnarPayload> {
         let mut skeleton_tokens = Vec::new();
         let mut placeholder_map = Vec::new();
@@ -662,105 +2886,157 @@ impl FormatV3Writer {
         let mut hex_values = Vec::new();
         let mut others = Vec::new();
         let mut timestamps_raw = Vec::new();
+        let mut epochs = NumericTimeColumn::default();
+        let mut uptimes = NumericTimeColumn::default();
+        let mut ansi_codes = Vec::new();
+        let mut mac_addrs = Vec::new();
+        let mut geo_points = Vec::new();
+        let mut k8s = KubernetesColumns::default();
+        let mut http_methods = Vec::new();
+        let mut http_statuses = Vec::new();
+        let mut durations = DurationColumn::default();
+        let mut byte_sizes = ByteSizeColumn::default();
+        let mut hosts = HostColumn::default();
+        let mut pids = Vec::new();
+        let mut tids = Vec::new();
+        let mut raw_bytes = Vec::new();
+        let mut plugin_columns = Vec::new();
+        let mut spellings = Vec::new();
 
-        for entry in &metadata.columns {
-            reader.seek(SeekFrom::Start(entry.offset))?;
-            let mut compressed = vec![0u8; entry.compressed_size as usize];
-            reader.read_exact(&mut compressed)?;
-
-            let decompressed = zstd::stream::decode_all(Cursor::new(&compressed))
-                .map_err(|e| ALICETextError::DecompressionError(format!("Zstd error: {e}")))?;
+        let mut total = 0u64;
+        for entry in metadata.columns.iter().filter(|e| wanted(e.col_type)) {
+            let decompressed = fetch(entry)?;
+            total += decompressed.len() as u64;
+            metadata.limits.check_total(total)?;
 
             match entry.col_type {
+                ColumnType::Skeleton if metadata.header.has_interned_skeleton() => {
+                    skeleton_tokens =
+                        decode_column::<InternedSkeleton>(&decompressed)?.resolve()?;
+                }
                 ColumnType::Skeleton => {
-                    skeleton_tokens = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    skeleton_tokens = decode_column(&decompressed)?;
+                }
+                ColumnType::PlaceholderMap if metadata.header.has_packed_placeholders() => {
+                    placeholder_map = unpack_placeholder_map(&decompressed)?;
                 }
                 ColumnType::PlaceholderMap => {
-                    placeholder_map = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    placeholder_map = decode_column(&decompressed)?;
                 }
                 ColumnType::Timestamps => {
-                    timestamps = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    timestamps = decode_column(&decompressed)?;
                 }
                 ColumnType::TimestampsRaw => {
-                    timestamps_raw = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    timestamps_raw = decode_column(&decompressed)?;
                 }
                 ColumnType::IPv4 => {
-                    ipv4_addrs = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    ipv4_addrs = decode_entry(entry, &decompressed)?;
                 }
                 ColumnType::IPv6 => {
-                    ipv6_addrs = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    ipv6_addrs = decode_entry(entry, &decompressed)?;
                 }
                 ColumnType::LogLevels => {
-                    log_levels = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    log_levels = decode_entry(entry, &decompressed)?;
                 }
                 ColumnType::Numbers => {
-                    numbers = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    numbers = decode_numbers(entry, &decompressed)?;
                 }
                 ColumnType::UUIDs => {
-                    uuids = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    uuids = decode_entry(entry, &decompressed)?;
                 }
                 ColumnType::Emails => {
-                    emails = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    emails = decode_column(&decompressed)?;
                 }
                 ColumnType::URLs => {
-                    urls = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    urls = decode_column(&decompressed)?;
                 }
                 ColumnType::Paths => {
-                    paths = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    paths = decode_column(&decompressed)?;
                 }
                 ColumnType::DateDays => {
-                    date_days = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    date_days = decode_entry(entry, &decompressed)?;
                 }
                 ColumnType::DatesRaw => {
-                    dates = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    dates = decode_column(&decompressed)?;
                 }
                 ColumnType::TimeMs => {
-                    time_ms = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    time_ms = decode_entry(entry, &decompressed)?;
                 }
                 ColumnType::TimesRaw => {
-                    times = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    times = decode_column(&decompressed)?;
                 }
                 ColumnType::HexValues => {
-                    hex_values = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    hex_values = decode_column(&decompressed)?;
                 }
                 ColumnType::Others => {
-                    others = bincode::deserialize(&decompressed).map_err(|e| {
-                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
-                    })?;
+                    others = decode_column(&decompressed)?;
+                }
+                ColumnType::Epochs => {
+                    epochs = decode_column(&decompressed)?;
+                }
+                ColumnType::Uptimes => {
+                    uptimes = decode_column(&decompressed)?;
+                }
+                ColumnType::AnsiCodes => {
+                    ansi_codes = decode_column(&decompressed)?;
+                }
+                ColumnType::Spellings => {
+                    spellings = check_spellings(decode_column(&decompressed)?)?;
+                }
+                ColumnType::MacAddrs => {
+                    mac_addrs = decode_column(&decompressed)?;
+                }
+                ColumnType::GeoPoints => {
+                    geo_points = decode_column(&decompressed)?;
+                }
+                ColumnType::CriPrefixes => {
+                    k8s.cri_prefixes = decode_column(&decompressed)?;
+                }
+                ColumnType::KlogHeaders => {
+                    k8s.klog_headers = decode_column(&decompressed)?;
+                }
+                ColumnType::ContainerIds => {
+                    k8s.container_ids = decode_column(&decompressed)?;
+                }
+                ColumnType::K8sNamespaces => {
+                    k8s.namespaces = decode_column(&decompressed)?;
+                }
+                ColumnType::K8sPods => {
+                    k8s.pods = decode_column(&decompressed)?;
+                }
+                ColumnType::HttpMethods => {
+                    http_methods = decode_entry(entry, &decompressed)?;
+                }
+                ColumnType::HttpStatuses => {
+                    http_statuses = decode_entry(entry, &decompressed)?;
+                }
+                ColumnType::Durations => {
+                    durations = decode_column(&decompressed)?;
+                }
+                ColumnType::ByteSizes => {
+                    byte_sizes = decode_column(&decompressed)?;
+                }
+                ColumnType::Hosts => {
+                    hosts = decode_column(&decompressed)?;
+                }
+                ColumnType::Pids => {
+                    pids = decode_entry(entry, &decompressed)?;
+                }
+                ColumnType::Tids => {
+                    tids = decode_entry(entry, &decompressed)?;
+                }
+                ColumnType::RawBytes => {
+                    raw_bytes = decode_column(&decompressed)?;
+                }
+                // Per-line metadata, not part of the text (see `read_provenance`)
+                ColumnType::Provenance | ColumnType::LineIds => {}
+                ColumnType::Plugins => {
+                    let encoded: Vec<Vec<u8>> = decode_column(&decompressed)?;
+                    plugin_columns = column_plugin::decode_columns(
+                        plugins,
+                        &metadata.plugin_schema()?,
+                        &encoded,
+                    )?;
                 }
             }
         }
@@ -786,74 +3062,652 @@ impl FormatV3Writer {
             times,
             hex_values,
             others,
+            epochs,
+            uptimes,
+            ansi_codes,
+            mac_addrs,
+            geo_points,
+            k8s,
+            http_methods,
+            http_statuses,
+            durations,
+            byte_sizes,
+            hosts,
+            pids,
+            tids,
+            raw_bytes,
+            plugin_columns,
+            spellings,
+        })
+    }
+}
+
+impl Default for FormatV3Writer {
+    fn default() -> Self {
+        Self::new(CompressionLevel::Balanced)
+    }
+}
+
+/// Convenience function to compress text with v3 format
+///
+/// # Errors
+///
+/// Returns an error if Bincode serialization or Zstd compression fails.
+pub fn compress_v3(text: &str, level: impl Into<Level>) -> Result<Vec<u8>> {
+    FormatV3Writer::new(level).compress(text)
+}
+
+/// Convenience function to decompress v3 format
+///
+/// # Errors
+///
+/// Returns an error if decompression fails.
+pub fn decompress_v3(data: &[u8]) -> Result<String> {
+    FormatV3Writer::decompress(data)
+}
+
+/// Partial payload for selective column reads
+#[derive(Debug, Default)]
+pub struct PartialPayload {
+    pub timestamps: Option<TimestampColumn>,
+    pub ipv4_addrs: Option<Vec<u32>>,
+    pub ipv6_addrs: Option<Vec<u128>>,
+    pub log_levels: Option<Vec<u8>>,
+    pub numbers: Option<Vec<f64>>,
+    pub uuids: Option<Vec<u128>>,
+    pub emails: Option<Vec<String>>,
+    pub urls: Option<Vec<String>>,
+    pub paths: Option<Vec<String>>,
+    pub epochs: Option<NumericTimeColumn>,
+    pub uptimes: Option<NumericTimeColumn>,
+    pub ansi_codes: Option<Vec<String>>,
+    pub mac_addrs: Option<Vec<u64>>,
+    pub geo_points: Option<Vec<GeoPoint>>,
+    pub cri_prefixes: Option<CriColumn>,
+    pub klog_headers: Option<KlogColumn>,
+    pub container_ids: Option<Vec<ContainerId>>,
+    pub k8s_namespaces: Option<NamespaceColumn>,
+    pub k8s_pods: Option<PodColumn>,
+    pub http_methods: Option<Vec<u8>>,
+    pub http_statuses: Option<Vec<u16>>,
+    pub durations: Option<DurationColumn>,
+    pub byte_sizes: Option<ByteSizeColumn>,
+    pub hosts: Option<HostColumn>,
+    pub pids: Option<Vec<u32>>,
+    pub tids: Option<Vec<u32>>,
+    pub provenance: Option<ProvenanceColumn>,
+    pub line_ids: Option<LineIdColumn>,
+    /// Row of every value read (only from [`FormatV3Writer::read_columns_by_row`])
+    pub rows: Option<RowMap>,
+}
+
+/// Which values of each column every row (line) refers to
+///
+/// Value columns are flat: `timestamps[i]` is the `i`-th timestamp in the
+/// text, which is on row `i` only if every line has exactly one. A `RowMap`
+/// lists, per row, the `(column, value index)` pairs its placeholders point
+/// at, so values of different columns can be lined up by row. Per-line
+/// columns (`provenance`, `line_ids`) map row `i` to value `i`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowMap {
+    /// Row `r` owns `entries[starts[r]..starts[r + 1]]`
+    starts: Vec<usize>,
+    entries: Vec<(ColumnType, u32)>,
+}
+
+impl RowMap {
+    /// Map the values of `columns` to the rows of `structure` (skeleton and placeholder map)
+    pub(crate) fn build(structure: &ColumnarPayload, columns: &[ColumnType]) -> Self {
+        let lines = ColumnarPayload::split_lines(&structure.skeleton_tokens);
+        let mut starts = Vec::with_capacity(lines.len() + 1);
+        let mut entries = Vec::new();
+        for (row, pieces) in lines.iter().enumerate() {
+            starts.push(entries.len());
+            for &col_type in columns.iter().filter(|c| c.is_per_line()) {
+                entries.push((col_type, row as u32));
+            }
+            for piece in pieces {
+                if let LinePiece::Ref(idx) = *piece {
+                    let idx = idx as usize;
+                    if let Some(col_type) = structure
+                        .placeholder_column(idx)
+                        .filter(|c| columns.contains(c))
+                    {
+                        entries.push((col_type, structure.placeholder_map[idx].1));
+                    }
+                }
+            }
+        }
+        starts.push(entries.len());
+        Self { starts, entries }
+    }
+
+    /// Number of rows
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.starts.len().saturating_sub(1)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `(column, value index)` pairs on row `row`, in text order
+    #[must_use]
+    pub fn row(&self, row: usize) -> &[(ColumnType, u32)] {
+        match (self.starts.get(row), self.starts.get(row + 1)) {
+            (Some(&start), Some(&end)) => &self.entries[start..end],
+            _ => &[],
+        }
+    }
+
+    /// Value indices of `column` on row `row`, in text order
+    pub fn values(&self, row: usize, column: ColumnType) -> impl Iterator<Item = usize> + '_ {
+        self.row(row)
+            .iter()
+            .filter(move |(c, _)| *c == column)
+            .map(|&(_, idx)| idx as usize)
+    }
+
+    /// Row of every value of `column` (`None` for values no row refers to),
+    /// for a column of `len` values
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a row refers to a value at or past `len` (a
+    /// placeholder map that does not match the column).
+    pub fn value_rows(&self, column: ColumnType, len: usize) -> Result<Vec<Option<usize>>> {
+        let mut rows = Vec::new();
+        for row in 0..self.len() {
+            for idx in self.values(row, column) {
+                if idx >= len {
+                    return Err(ALICETextError::DecompressionError(format!(
+                        "Row {row} refers to {} value {idx}, column has {len}",
+                        column.name()
+                    )));
+                }
+                if rows.len() <= idx {
+                    rows.resize(idx + 1, None);
+                }
+                rows[idx] = Some(row);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+impl PartialPayload {
+    /// Get log level values as strings
+    #[must_use]
+    pub fn log_level_strings(&self) -> Option<Vec<String>> {
+        self.log_levels.as_ref().map(|levels| {
+            levels
+                .iter()
+                .map(|&l| {
+                    match l {
+                        0 => "TRACE",
+                        1 => "DEBUG",
+                        2 => "INFO",
+                        3 => "WARN",
+                        4 => "ERROR",
+                        5 => "FATAL",
+                        6 => "CRITICAL",
+                        _ => "UNKNOWN",
+                    }
+                    .to_string()
+                })
+                .collect()
+        })
+    }
+
+    /// Get IPv4 addresses as strings
+    #[must_use]
+    pub fn ipv4_strings(&self) -> Option<Vec<String>> {
+        self.ipv4_addrs.as_ref().map(|addrs| {
+            addrs
+                .iter()
+                .map(|&ip| std::net::Ipv4Addr::from(ip).to_string())
+                .collect()
+        })
+    }
+
+    /// Get timestamps as strings
+    #[must_use]
+    pub fn timestamp_strings(&self) -> Option<Vec<String>> {
+        self.timestamps.as_ref().map(|ts| {
+            let prefix_sums = ts.prepare_for_read();
+            (0..ts.deltas.len())
+                .filter_map(|i| ts.get_delta(i, &prefix_sums))
+                .collect()
         })
     }
-}
 
-impl Default for FormatV3Writer {
-    fn default() -> Self {
-        Self::new(CompressionLevel::Balanced)
+    /// Get epoch values as strings (original representation)
+    #[must_use]
+    pub fn epoch_strings(&self) -> Option<Vec<String>> {
+        self.epochs.as_ref().map(|col| {
+            let prefix_sums = col.prepare_for_read();
+            (0..col.len())
+                .filter_map(|i| col.get_epoch(i, &prefix_sums))
+                .collect()
+        })
+    }
+
+    /// Get MAC addresses as strings (original representation)
+    #[must_use]
+    pub fn mac_strings(&self) -> Option<Vec<String>> {
+        self.mac_addrs
+            .as_ref()
+            .map(|macs| macs.iter().map(|&mac| format_mac(mac)).collect())
+    }
+
+    /// Get latitude/longitude pairs as strings (original representation)
+    #[must_use]
+    pub fn geo_strings(&self) -> Option<Vec<String>> {
+        self.geo_points
+            .as_ref()
+            .map(|points| points.iter().map(ToString::to_string).collect())
+    }
+
+    /// Get uptime values as strings (original representation)
+    #[must_use]
+    pub fn uptime_strings(&self) -> Option<Vec<String>> {
+        self.uptimes.as_ref().map(|col| {
+            let prefix_sums = col.prepare_for_read();
+            (0..col.len())
+                .filter_map(|i| col.get_uptime(i, &prefix_sums))
+                .collect()
+        })
+    }
+}
+
+/// In-place editor for string-valued columns of a v3 archive
+///
+/// Rewrites a single column without recompressing the rest of the file
+/// (e.g. erasing an email address). A re-encoded column that still fits in
+/// its old slot is written over it; otherwise it is appended and the
+/// directory entry is repointed. Either way the bytes of the old block that
+/// are not reused are zeroed, so erased values cannot be recovered from the
+/// file. Header `original_length` (in the input's encoding and line endings)
+/// and the value-bytes record are kept in sync.
+/// Columns of a type this reader does not know are left as they are.
+///
+/// ```rust,ignore
+/// let mut editor = FormatV3Editor::open("server.atxt")?;
+/// editor.rewrite_column(ColumnType::Emails, |_, v| (v == "bob@example.com").then(|| "[redacted]".into()))?;
+/// editor.write_in_place("server.atxt")?;
+/// ```
+pub struct FormatV3Editor {
+    data: Vec<u8>,
+    metadata: FormatV3Metadata,
+    /// Byte ranges (offset, len) changed since load, for `write_in_place`
+    dirty: Vec<(usize, usize)>,
+    /// Plugins for [`Self::compact`] to decode and re-encode the plugin column with
+    plugins: Vec<Arc<dyn ColumnPlugin>>,
+}
+
+impl FormatV3Editor {
+    /// Byte offset of the header (after magic + version)
+    const HEADER_START: usize = 8 + 2;
+
+    /// Load an archive from memory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a valid v3 archive.
+    pub fn new(data: Vec<u8>) -> Result<Self> {
+        let metadata =
+            FormatV3Metadata::read_from_with(&mut Cursor::new(&data), DecodeOptions::lenient())?;
+        // Edits slice columns out of `data`; a directory pointing past it is corrupt
+        if let Some(entry) = metadata.columns.iter().find(|entry| {
+            entry
+                .offset
+                .saturating_add(u64::from(entry.compressed_size))
+                > data.len() as u64
+        }) {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Column {:?} runs past the end of the archive",
+                entry.col_type
+            )));
+        }
+        Ok(Self {
+            data,
+            metadata,
+            dirty: Vec::new(),
+            plugins: Vec::new(),
+        })
+    }
+
+    /// Plugins the archive was written with, for [`Self::compact`]
+    #[must_use]
+    pub fn with_plugins(mut self, plugins: &[Arc<dyn ColumnPlugin>]) -> Self {
+        self.plugins = plugins.to_vec();
+        self
+    }
+
+    /// Load an archive from disk
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid v3 archive.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(std::fs::read(path)?)
+    }
+
+    /// Current metadata (reflects edits)
+    #[must_use]
+    pub const fn metadata(&self) -> &FormatV3Metadata {
+        &self.metadata
+    }
+
+    /// Whether a column stores its values verbatim (and is therefore editable)
+    #[must_use]
+    pub const fn is_editable(col_type: ColumnType) -> bool {
+        matches!(
+            col_type,
+            ColumnType::Emails
+                | ColumnType::URLs
+                | ColumnType::Paths
+                | ColumnType::DatesRaw
+                | ColumnType::TimesRaw
+                | ColumnType::HexValues
+                | ColumnType::Others
+                | ColumnType::TimestampsRaw
+                | ColumnType::AnsiCodes
+        )
+    }
+
+    /// Read the values of an editable column
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column is not editable or decompression fails.
+    pub fn read_strings(&self, col_type: ColumnType) -> Result<Vec<String>> {
+        Self::check_editable(col_type)?;
+        let Some(entry) = self.metadata.get_column(col_type) else {
+            return Ok(Vec::new());
+        };
+        let compressed = usize::try_from(entry.offset)
+            .ok()
+            .and_then(|start| {
+                self.data
+                    .get(start..)?
+                    .get(..entry.compressed_size as usize)
+            })
+            .ok_or_else(|| ALICETextError::DecompressionError("Column out of range".to_string()))?;
+        let decompressed = zstd_params::decompress(
+            compressed,
+            &[],
+            entry.window_log,
+            self.metadata.limits.max_column_bytes,
+        )?;
+        decode_column(&decompressed)
+    }
+
+    /// Replace values at specific rows of a column
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column is not editable, a row is out of range,
+    /// or re-encoding fails.
+    pub fn set_values(&mut self, col_type: ColumnType, edits: &[(usize, &str)]) -> Result<()> {
+        let mut values = self.read_strings(col_type)?;
+        let row_count = values.len();
+        let mut len_delta = LenDelta::default();
+        for &(row, value) in edits {
+            let slot = values.get_mut(row).ok_or_else(|| {
+                ALICETextError::EncodingError(format!(
+                    "Row {row} out of range for column {} ({} rows)",
+                    col_type.name(),
+                    row_count
+                ))
+            })?;
+            len_delta.add(self.metadata.header.source_profile(), slot, value);
+            *slot = value.to_string();
+        }
+        self.store_column(col_type, &values, len_delta)
+    }
+
+    /// Rewrite a column with a closure: `Some(new)` replaces the value at that row
+    ///
+    /// Returns the number of rows changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column is not editable or re-encoding fails.
+    pub fn rewrite_column<F>(&mut self, col_type: ColumnType, mut f: F) -> Result<usize>
+    where
+        F: FnMut(usize, &str) -> Option<String>,
+    {
+        let mut values = self.read_strings(col_type)?;
+        let profile = self.metadata.header.source_profile();
+        let mut len_delta = LenDelta::default();
+        let mut changed = 0;
+        for (row, value) in values.iter_mut().enumerate() {
+            if let Some(new_value) = f(row, value) {
+                len_delta.add(profile, value, &new_value);
+                *value = new_value;
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            self.store_column(col_type, &values, len_delta)?;
+        }
+        Ok(changed)
+    }
+
+    /// Edited archive bytes
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consume the editor, returning the edited archive bytes
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Write the whole edited archive to `path` (copy-on-write: temp file + fsync + rename)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing or renaming fails.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        crate::write_atomic(path, &self.data)
+    }
+
+    /// Patch only the changed byte ranges into the file the archive was loaded from
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or written.
+    pub fn write_in_place<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        for &(offset, len) in &self.dirty {
+            file.seek(SeekFrom::Start(offset as u64))?;
+            file.write_all(&self.data[offset..offset + len])?;
+        }
+        file.set_len(self.data.len() as u64)?;
+        file.sync_data()?;
+        self.dirty.clear();
+        Ok(())
+    }
+
+    /// Re-encode the whole archive (drops slack left by in-place edits)
+    ///
+    /// Header extensions, provenance and line ids are carried over unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decompression or compression fails, or the archive
+    /// needs a column plugin not given to [`Self::with_plugins`].
+    pub fn compact(&self) -> Result<Vec<u8>> {
+        let (text, profile, raw) = FormatV3Writer::decode_normalized(&self.data, &self.plugins)?;
+        let provenance = FormatV3Writer::read_provenance(&self.data)?;
+        let line_ids = FormatV3Writer::read_line_ids(&self.data)?;
+        FormatV3Writer::matching(&self.metadata, &self.plugins)?.compress_normalized(
+            &text,
+            profile,
+            &raw,
+            provenance.as_ref(),
+            line_ids.as_ref(),
+        )
     }
-}
 
-/// Partial payload for selective column reads
-#[derive(Debug, Default)]
-pub struct PartialPayload {
-    pub timestamps: Option<TimestampColumn>,
-    pub ipv4_addrs: Option<Vec<u32>>,
-    pub ipv6_addrs: Option<Vec<u128>>,
-    pub log_levels: Option<Vec<u8>>,
-    pub numbers: Option<Vec<f64>>,
-    pub uuids: Option<Vec<u128>>,
-    pub emails: Option<Vec<String>>,
-    pub urls: Option<Vec<String>>,
-    pub paths: Option<Vec<String>>,
-}
+    /// Level the archive was written at
+    fn level(&self) -> Level {
+        self.metadata.level()
+    }
 
-impl PartialPayload {
-    /// Get log level values as strings
-    #[must_use]
-    pub fn log_level_strings(&self) -> Option<Vec<String>> {
-        self.log_levels.as_ref().map(|levels| {
-            levels
-                .iter()
-                .map(|&l| {
-                    match l {
-                        0 => "TRACE",
-                        1 => "DEBUG",
-                        2 => "INFO",
-                        3 => "WARN",
-                        4 => "ERROR",
-                        5 => "FATAL",
-                        6 => "CRITICAL",
-                        _ => "UNKNOWN",
-                    }
-                    .to_string()
-                })
-                .collect()
-        })
+    fn check_editable(col_type: ColumnType) -> Result<()> {
+        if Self::is_editable(col_type) {
+            Ok(())
+        } else {
+            Err(ALICETextError::EncodingError(format!(
+                "Column {} is not string-valued and cannot be edited",
+                col_type.name()
+            )))
+        }
     }
 
-    /// Get IPv4 addresses as strings
-    #[must_use]
-    pub fn ipv4_strings(&self) -> Option<Vec<String>> {
-        self.ipv4_addrs.as_ref().map(|addrs| {
-            addrs
-                .iter()
-                .map(|&ip| std::net::Ipv4Addr::from(ip).to_string())
-                .collect()
-        })
+    /// Re-encode a column and patch data, directory entry and header
+    fn store_column(
+        &mut self,
+        col_type: ColumnType,
+        values: &[String],
+        len_delta: LenDelta,
+    ) -> Result<()> {
+        let Some(index) = self
+            .metadata
+            .columns
+            .iter()
+            .position(|c| c.col_type == col_type)
+        else {
+            // Absent columns have no rows, so any edit already failed the range check
+            return Ok(());
+        };
+
+        let level = self.level();
+        let row_count = to_u32(values.len(), "v3 column row count")?;
+        let serialized = encode_column(values);
+        let compressed = zstd::stream::encode_all(Cursor::new(&serialized), level.zstd_level())
+            .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")))?;
+        let compressed_size = to_u32(compressed.len(), "v3 column size")?;
+
+        // Column data: overwrite the old slot if it fits, otherwise append;
+        // then zero what is left of the old block (its slack, or all of it)
+        let entry = &mut self.metadata.columns[index];
+        let (old_offset, old_size) = (entry.offset as usize, entry.compressed_size as usize);
+        let offset = if compressed.len() <= old_size {
+            old_offset
+        } else {
+            self.data.len()
+        };
+        let end = offset + compressed.len();
+        if end > self.data.len() {
+            self.data.resize(end, 0);
+        }
+        self.data[offset..end].copy_from_slice(&compressed);
+        self.dirty.push((offset, compressed.len()));
+        let stale = if offset == old_offset {
+            end..old_offset + old_size
+        } else {
+            old_offset..old_offset + old_size
+        };
+        if !stale.is_empty() {
+            self.data[stale.clone()].fill(0);
+            self.dirty.push((stale.start, stale.len()));
+        }
+
+        entry.offset = offset as u64;
+        entry.compressed_size = compressed_size;
+        entry.row_count = row_count;
+
+        // Directory entry (its slot differs from `index` when unknown columns were skipped)
+        let entry_bytes = entry.to_bytes();
+        let directory_start = Self::HEADER_START + FormatV3Header::SIZE;
+        let slot = (0..usize::from(self.metadata.header.column_count))
+            .find(|&i| self.data[directory_start + i * ColumnEntry::SIZE] == col_type as u8)
+            .unwrap_or(index);
+        let entry_start = directory_start + slot * ColumnEntry::SIZE;
+        self.data[entry_start..entry_start + ColumnEntry::SIZE].copy_from_slice(&entry_bytes);
+        self.dirty.push((entry_start, ColumnEntry::SIZE));
+
+        // Header (original length changes with the substituted values)
+        let header = &mut self.metadata.header;
+        header.original_length = header
+            .original_length
+            .saturating_add_signed(len_delta.source);
+        let header_bytes = header.to_bytes();
+        self.data[Self::HEADER_START..Self::HEADER_START + FormatV3Header::SIZE]
+            .copy_from_slice(&header_bytes);
+        self.dirty.push((Self::HEADER_START, FormatV3Header::SIZE));
+
+        if let Some(value_bytes) = self.metadata.value_bytes() {
+            let value_bytes = value_bytes.saturating_add_signed(len_delta.text);
+            self.patch_extension(HeaderExtension::VALUE_BYTES, value_bytes);
+        }
+        self.refresh_checksum();
+        Ok(())
     }
 
-    /// Get timestamps as strings
-    #[must_use]
-    pub fn timestamp_strings(&self) -> Option<Vec<String>> {
-        self.timestamps.as_ref().map(|ts| {
-            let prefix_sums = ts.prepare_for_read();
-            (0..ts.deltas.len())
-                .filter_map(|i| ts.get_delta(i, &prefix_sums))
-                .collect()
-        })
+    /// Recompute the [`HeaderExtension::CHECKSUM`] record in place (if the archive has one)
+    fn refresh_checksum(&mut self) {
+        let header = &self.metadata.header;
+        let data_start = Self::HEADER_START
+            + FormatV3Header::SIZE
+            + usize::from(header.column_count) * ColumnEntry::SIZE
+            + header.summary_size as usize
+            + header.config_size as usize
+            + header.extension_size as usize;
+        let data_end = self
+            .metadata
+            .columns
+            .iter()
+            .map(|entry| entry.offset as usize + entry.compressed_size as usize)
+            .fold(data_start, usize::max)
+            .min(self.data.len());
+        let checksum = fnv1a(&self.data[data_start..data_end], FNV_OFFSET);
+        self.patch_extension(HeaderExtension::CHECKSUM, checksum);
+    }
+
+    /// Overwrite the 8-byte `tag` record in place (if the archive has one)
+    fn patch_extension(&mut self, tag: u16, value: u64) {
+        let header = &self.metadata.header;
+        let mut position = Self::HEADER_START
+            + FormatV3Header::SIZE
+            + usize::from(header.column_count) * ColumnEntry::SIZE
+            + header.summary_size as usize
+            + header.config_size as usize;
+        for ext in &mut self.metadata.extensions {
+            let start = position + HeaderExtension::RECORD_HEADER;
+            if ext.tag == tag && ext.data.len() == 8 {
+                ext.data = value.to_le_bytes().to_vec();
+                self.data[start..start + 8].copy_from_slice(&ext.data);
+                self.dirty.push((start, 8));
+            }
+            position = start + ext.data.len();
+        }
+    }
+}
+
+/// Length change of an edit: in the original input (its encoding and line
+/// endings, for `original_length`) and in the normalized text (value bytes)
+#[derive(Debug, Clone, Copy, Default)]
+struct LenDelta {
+    source: i64,
+    text: i64,
+}
+
+impl LenDelta {
+    /// Account for `old` being replaced by `new`
+    fn add(&mut self, profile: SourceProfile, old: &str, new: &str) {
+        // A BOM, if counted, is counted on both sides
+        self.source +=
+            profile.restored_len(new, &[]) as i64 - profile.restored_len(old, &[]) as i64;
+        self.text += new.len() as i64 - old.len() as i64;
     }
 }
 
@@ -925,21 +3779,93 @@ mod tests {
         assert!(levels.contains(&"WARN".to_string()));
     }
 
+    #[test]
+    fn test_hot_columns_first() {
+        let text = "2024-01-15 10:30:45 INFO user=alice@example.com 10.0.0.1 took 12 ms\n\
+                    2024-01-15 10:30:46 ERROR user=bob@example.com 10.0.0.2 took 340 ms\n";
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(text)
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap();
+
+        // Data follows directory order, tiers ascending, skeleton at the end
+        let tiers: Vec<u8> = metadata
+            .columns
+            .iter()
+            .map(|c| c.col_type.layout_tier())
+            .collect();
+        assert!(tiers.windows(2).all(|w| w[0] <= w[1]));
+        assert!(metadata
+            .columns
+            .windows(2)
+            .all(|w| w[0].offset + u64::from(w[0].compressed_size) == w[1].offset));
+        assert_eq!(metadata.columns[0].col_type, ColumnType::Timestamps);
+        assert_eq!(
+            metadata.prefix_len(&[ColumnType::Skeleton, ColumnType::PlaceholderMap]),
+            compressed.len() as u64
+        );
+
+        // A prefix holding the hot columns is enough to query them
+        let hot = [ColumnType::Timestamps, ColumnType::LogLevels];
+        let prefix = &compressed[..metadata.prefix_len(&hot) as usize];
+        assert!(prefix.len() < compressed.len());
+        let partial = FormatV3Writer::read_columns(
+            &mut Cursor::new(prefix),
+            &FormatV3Metadata::read_from_with(&mut Cursor::new(prefix), DecodeOptions::lenient())
+                .unwrap(),
+            &hot,
+        )
+        .unwrap();
+        assert_eq!(
+            partial.log_level_strings().unwrap(),
+            ["INFO".to_string(), "ERROR".to_string()]
+        );
+        assert!(metadata.prefix_len(&[]) < metadata.columns[0].offset + 1);
+    }
+
     #[test]
     fn test_column_type_from_u8_all_values() {
-        for i in 0..=17u8 {
+        for i in 0..=39u8 {
             assert!(
                 ColumnType::from_u8(i).is_some(),
                 "ColumnType::from_u8({i}) should be Some"
             );
         }
-        assert!(ColumnType::from_u8(18).is_none());
+        assert!(ColumnType::from_u8(40).is_none());
         assert!(ColumnType::from_u8(255).is_none());
     }
 
+    #[test]
+    fn test_spellings_column() {
+        let text = "2024-01-15 10:30:45 WARNING disk at 007%\n2024-01-15 10:30:46 WARN ok 7\n";
+        let archive = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&archive)).unwrap();
+        assert_ne!(metadata.header.flags & FormatV3Header::FLAG_SPELLINGS, 0);
+        assert_eq!(
+            metadata
+                .get_column(ColumnType::Spellings)
+                .unwrap()
+                .row_count,
+            2
+        );
+        assert_eq!(
+            metadata
+                .get_column(ColumnType::LogLevels)
+                .unwrap()
+                .row_count,
+            2
+        );
+        assert_eq!(decompress_v3(&archive).unwrap(), text);
+
+        let plain = compress_v3("2024-01-15 10:30:46 WARN ok 7\n", CompressionLevel::Fast).unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&plain)).unwrap();
+        assert_eq!(metadata.header.flags & FormatV3Header::FLAG_SPELLINGS, 0);
+        assert!(metadata.get_column(ColumnType::Spellings).is_none());
+    }
+
     #[test]
     fn test_column_type_name_not_empty() {
-        for i in 0..=17u8 {
+        for i in 0..=38u8 {
             if let Some(ct) = ColumnType::from_u8(i) {
                 assert!(
                     !ct.name().is_empty(),
@@ -957,6 +3883,8 @@ mod tests {
             compressed_size: 100,
             uncompressed_size: 500,
             row_count: 50,
+            encoding: ColumnEncoding::Dict,
+            window_log: 0,
         };
         let bytes = entry.to_bytes();
         let restored = ColumnEntry::from_bytes(&bytes).unwrap();
@@ -980,7 +3908,10 @@ mod tests {
             compression_level: 2,
             column_count: 15,
             row_count: 1000,
-            reserved: [0u8; 13],
+            summary_size: 48,
+            config_size: 64,
+            flags: SourceProfile::FLAG_CRLF,
+            extension_size: 0,
         };
         let bytes = header.to_bytes();
         let restored = FormatV3Header::from_bytes(&bytes).unwrap();
@@ -988,6 +3919,282 @@ mod tests {
         assert_eq!(restored.compression_level, 2);
         assert_eq!(restored.column_count, 15);
         assert_eq!(restored.row_count, 1000);
+        assert_eq!(restored.summary_size, 48);
+        assert_eq!(restored.config_size, 64);
+        assert!(restored.source_profile().crlf);
+        assert!(!restored.has_extensions());
+    }
+
+    #[test]
+    fn test_interned_skeleton() {
+        let text: String = (0..500)
+            .map(|i| {
+                format!(
+                    "User u{i} logged in from 10.0.{}.{} after {i} ms\n",
+                    i % 7,
+                    i % 250
+                )
+            })
+            .collect();
+        let data = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&data)).unwrap();
+        assert!(metadata.header.has_interned_skeleton());
+        assert_eq!(FormatV3Writer::decompress(&data).unwrap(), text);
+
+        // Raw skeleton shrinks; every repeat of a literal is one u32 reference
+        let tokens = ColumnarEncoder::new().encode(&text).skeleton_tokens;
+        let interned = InternedSkeleton::intern(&tokens);
+        assert!(interned.literals.len() < 10);
+        let inline = encode_column(&tokens);
+        assert!(encode_column(&interned).len() * 3 < inline.len() * 2);
+        assert_eq!(interned.resolve().unwrap(), tokens);
+
+        // Archives written before interning keep decoding: swap in an inline skeleton
+        let mut legacy = data.clone();
+        let compressed = zstd::stream::encode_all(Cursor::new(&inline), 1).unwrap();
+        let slot = (0..usize::from(metadata.header.column_count))
+            .map(|i| 10 + FormatV3Header::SIZE + i * ColumnEntry::SIZE)
+            .find(|&at| legacy[at] == ColumnType::Skeleton as u8)
+            .unwrap();
+        legacy[slot + 1..slot + 9].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        legacy[slot + 9..slot + 13].copy_from_slice(&(compressed.len() as u32).to_le_bytes());
+        legacy[10 + 27] &= !FormatV3Header::FLAG_INTERNED_SKELETON;
+        legacy.extend_from_slice(&compressed);
+        assert_eq!(FormatV3Writer::decompress(&legacy).unwrap(), text);
+
+        let corrupt = InternedSkeleton {
+            literals: Vec::new(),
+            tokens: vec![InternedToken::Literal(3)],
+        };
+        assert!(corrupt.resolve().is_err());
+    }
+
+    #[test]
+    fn test_packed_placeholder_map() {
+        let text: String = (0..500)
+            .map(|i| format!("2024-01-15 10:30:00 INFO user{i}@example.com took {i} ms\n"))
+            .collect();
+        let map = ColumnarEncoder::new().encode(&text).placeholder_map;
+        let packed = pack_placeholder_map(&map);
+        // Deltas are all zero and tags repeat per line, which zstd folds away
+        let pairs = encode_column(&map);
+        let zstd_len = |b: &[u8]| zstd::stream::encode_all(Cursor::new(b), 3).unwrap().len();
+        assert!(packed.len() < pairs.len());
+        assert!(zstd_len(&packed) * 20 < zstd_len(&pairs));
+        assert_eq!(unpack_placeholder_map(&packed).unwrap(), map);
+
+        let data = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&data)).unwrap();
+        assert!(metadata.header.has_packed_placeholders());
+        assert_eq!(FormatV3Writer::decompress(&data).unwrap(), text);
+
+        // Out-of-order and repeated indices, large gaps, empty map
+        let map = vec![(3, 5), (3, 2), (0, u32::MAX), (3, 2), (0, 0), (7, 0)];
+        assert_eq!(
+            unpack_placeholder_map(&pack_placeholder_map(&map)).unwrap(),
+            map
+        );
+        assert!(unpack_placeholder_map(&pack_placeholder_map(&[]))
+            .unwrap()
+            .is_empty());
+
+        let packed = pack_placeholder_map(&map);
+        assert!(unpack_placeholder_map(&packed[..packed.len() - 1]).is_err());
+        assert!(unpack_placeholder_map(&[0xFF; 11]).is_err());
+        assert!(unpack_placeholder_map(&[2, 1, 0, 1, 0, 0]).is_err());
+        // A run length that overflows the running total
+        let mut overflow = vec![1, 2, 0, 1, 0];
+        put_varint(&mut overflow, u64::MAX);
+        overflow.push(0);
+        assert!(unpack_placeholder_map(&overflow).is_err());
+
+        // Header flags this reader does not know are refused
+        let mut unknown = data;
+        unknown[10 + 27] |= 0x01;
+        assert!(FormatV3Writer::decompress(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_strict_and_lenient_decoding() {
+        let text = "2024-01-15 10:30:00 INFO GET /index.html from 10.0.0.1 in 12 ms\n".repeat(5);
+        let data = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+        for options in [DecodeOptions::strict(), DecodeOptions::lenient()] {
+            assert_eq!(
+                FormatV3Writer::decompress_with(&data, options).unwrap(),
+                text
+            );
+        }
+        let directory = 10 + FormatV3Header::SIZE;
+        let slot = |data: &[u8], col: ColumnType| {
+            (0..)
+                .map(|i| directory + i * ColumnEntry::SIZE)
+                .find(|&at| data[at] == col as u8)
+                .unwrap()
+        };
+
+        // Unknown column type (from a newer writer): refused unless lenient,
+        // which reports it
+        let mut newer = data.clone();
+        let hex = slot(&newer, ColumnType::HexValues);
+        newer[hex] = 200;
+        let strict = DecodeOptions::strict();
+        let lenient = DecodeOptions::lenient();
+        assert_eq!(DecodeOptions::default(), strict);
+        assert!(FormatV3Metadata::read_from(&mut Cursor::new(&newer)).is_err());
+        assert!(FormatV3Writer::decompress(&newer).is_err());
+        let metadata = FormatV3Metadata::read_from_with(&mut Cursor::new(&newer), lenient).unwrap();
+        assert_eq!(metadata.unknown_columns, [200]);
+        let (decoded, dropped) = FormatV3Writer::decompress_with_report(&newer, lenient).unwrap();
+        assert_eq!(decoded, text);
+        assert_eq!(dropped.unknown, [200]);
+        assert!(dropped.damaged.is_empty());
+
+        // Trailing bytes and minor version
+        let mut trailing = data.clone();
+        trailing.extend_from_slice(b"junk");
+        assert!(FormatV3Writer::decompress(&trailing).is_err());
+        assert_eq!(
+            FormatV3Writer::decompress_with(&trailing, lenient).unwrap(),
+            text
+        );
+        let mut minor = data.clone();
+        minor[9] = 7;
+        assert!(matches!(
+            FormatV3Writer::decompress(&minor),
+            Err(ALICETextError::InvalidVersion(3, 7))
+        ));
+        assert_eq!(
+            FormatV3Writer::decompress_with(&minor, lenient).unwrap(),
+            text
+        );
+
+        // Damaged value column: lenient recovers the rest of the line and
+        // names the column
+        let mut damaged = data.clone();
+        let entry = metadata.get_column(ColumnType::IPv4).unwrap();
+        damaged[entry.offset as usize..][..entry.compressed_size as usize].fill(0xFF);
+        assert!(FormatV3Writer::decompress(&damaged).is_err());
+        assert!(FormatV3Writer::decompress_with(&damaged, strict).is_err());
+        let (recovered, dropped) =
+            FormatV3Writer::decompress_with_report(&damaged, lenient).unwrap();
+        assert_eq!(recovered, text.replace("10.0.0.1", ""));
+        assert_eq!(dropped.damaged, [ColumnType::IPv4]);
+
+        // A column whose end overflows u64 is refused, not wrapped
+        let mut wrapped = data.clone();
+        let at = slot(&wrapped, ColumnType::IPv4);
+        wrapped[at + 1..at + 9].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(FormatV3Metadata::read_from_with(&mut Cursor::new(&wrapped), lenient).is_err());
+
+        // Editing after a skipped column patches the right directory slot
+        let mut editor = FormatV3Editor::new(newer).unwrap();
+        editor
+            .set_values(ColumnType::Paths, &[(0, "/index.htm")])
+            .unwrap();
+        let edited = editor.into_bytes();
+        assert_eq!(
+            edited[slot(&edited, ColumnType::Paths)],
+            ColumnType::Paths as u8
+        );
+        assert!(FormatV3Writer::decompress_with(&edited, lenient)
+            .unwrap()
+            .starts_with("2024-01-15 10:30:00 INFO GET /index.htm from"));
+    }
+
+    #[test]
+    fn test_header_extensions() {
+        let text = "2024-01-15 10:30:00 INFO started on 10.0.0.1\n".repeat(5);
+        let plain = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+        let extended = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_extension(HeaderExtension::new(0x0001, b"row-groups".to_vec()))
+            .with_extension(HeaderExtension::new(0x0002, Vec::new()))
+            .compress(&text)
+            .unwrap();
+
+        // Every archive carries the value-bytes, producer and checksum records; user records follow them
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&plain)).unwrap();
+        assert!(metadata.header.has_extensions());
+        assert_eq!(metadata.extensions.len(), 3);
+        assert!(metadata.value_bytes().is_some());
+        assert!(metadata.producer().is_some());
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&extended)).unwrap();
+        assert!(metadata.header.has_extensions());
+        let producer_len = metadata.extension(HeaderExtension::PRODUCER).unwrap().len();
+        assert_eq!(
+            metadata.header.extension_size as usize,
+            6 + 8 + 6 + producer_len + 6 + 8 + 6 + 10 + 6
+        );
+        assert_eq!(metadata.extension(0x0001), Some(&b"row-groups"[..]));
+        assert_eq!(metadata.extension(0x0002), Some(&[][..]));
+        assert_eq!(metadata.extension(0x0003), None);
+        assert!(!metadata.header.source_profile().crlf);
+
+        // Column offsets account for the area; decoding and compaction are unaffected
+        assert_eq!(FormatV3Writer::decompress(&extended).unwrap(), text);
+        let compacted = FormatV3Editor::new(extended).unwrap().compact().unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compacted)).unwrap();
+        assert_eq!(metadata.extensions.len(), 5);
+
+        // Unknown critical tags are refused, truncated records are errors
+        let critical = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_extension(HeaderExtension::new(
+                HeaderExtension::CRITICAL | 0x0001,
+                vec![1],
+            ))
+            .compress(&text)
+            .unwrap();
+        assert!(FormatV3Metadata::read_from(&mut Cursor::new(&critical)).is_err());
+        assert!(HeaderExtension::decode_all(&[1, 0, 9, 0, 0, 0, 1]).is_err());
+        assert!(HeaderExtension::decode_all(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn test_forged_sizes_checked_before_allocating() {
+        let text = "2024-01-15 10:30:00 INFO started on 10.0.0.1\n".repeat(5);
+        let archive = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+        let header_start = FormatV3Editor::HEADER_START;
+        let directory = header_start + FormatV3Header::SIZE;
+
+        // Header areas (summaries, config, extensions) claiming ~4 GiB
+        for field in [19, 23, 28] {
+            let mut forged = archive.clone();
+            forged[header_start + field..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+            for options in [DecodeOptions::default(), DecodeOptions::lenient()] {
+                let err = FormatV3Metadata::read_from_with(&mut Cursor::new(&forged), options)
+                    .unwrap_err();
+                assert!(err.to_string().contains("header areas need"), "{err}");
+            }
+            assert!(FormatV3Editor::new(forged).is_err());
+        }
+
+        // A column block claiming ~4 GiB: lenient metadata accepts the
+        // directory, the column read refuses it
+        let mut forged = archive;
+        forged[directory + 9..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let metadata =
+            FormatV3Metadata::read_from_with(&mut Cursor::new(&forged), DecodeOptions::lenient())
+                .unwrap();
+        let err = FormatV3Writer::read_column_bytes(
+            &mut Cursor::new(&forged),
+            &metadata.columns[0],
+            u64::MAX,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("past the end of the archive"),
+            "{err}"
+        );
+        assert!(FormatV3Editor::new(forged).is_err());
     }
 
     #[test]
@@ -1043,4 +4250,656 @@ mod tests {
         let result = FormatV3Writer::decompress(&data);
         assert!(result.is_err());
     }
+
+    const EDIT_LOG: &str = "2024-01-15 10:30:45 INFO login user=alice@example.com from 10.0.0.1\n\
+                            2024-01-15 10:30:46 INFO login user=bob@example.com from 10.0.0.2\n\
+                            2024-01-15 10:30:47 ERROR denied user=alice@example.com from 10.0.0.3\n";
+
+    #[test]
+    fn test_editor_rewrite_column() {
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(EDIT_LOG)
+            .unwrap();
+        let mut editor = FormatV3Editor::new(compressed).unwrap();
+
+        let changed = editor
+            .rewrite_column(ColumnType::Emails, |_, v| {
+                (v == "alice@example.com").then(|| "[redacted]".to_string())
+            })
+            .unwrap();
+        assert_eq!(changed, 2);
+
+        let expected = EDIT_LOG.replace("alice@example.com", "[redacted]");
+        assert_eq!(
+            editor.metadata().header.original_length,
+            expected.len() as u64
+        );
+        let edited = editor.into_bytes();
+        assert_eq!(FormatV3Writer::decompress(&edited).unwrap(), expected);
+
+        // Untouched columns remain queryable
+        #[cfg(feature = "query")]
+        {
+            let engine = crate::QueryEngine::from_reader(Cursor::new(&edited)).unwrap();
+            let result = engine
+                .query(&["emails"], "log_levels", crate::Op::Eq, "ERROR")
+                .unwrap();
+            assert_eq!(result.rows[0].values["emails"], "[redacted]");
+        }
+    }
+
+    #[test]
+    fn test_editor_set_values_grow_and_compact() {
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(EDIT_LOG)
+            .unwrap();
+        let original_len = compressed.len();
+        let mut editor = FormatV3Editor::new(compressed).unwrap();
+
+        // Longer, incompressible value forces the column to be appended
+        let long = "x".repeat(10) + &"0123456789abcdef".repeat(8) + "@example.com";
+        editor
+            .set_values(ColumnType::Emails, &[(1, &long)])
+            .unwrap();
+        assert!(editor.as_bytes().len() > original_len);
+
+        let expected = EDIT_LOG.replace("bob@example.com", &long);
+        assert_eq!(
+            FormatV3Writer::decompress(editor.as_bytes()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            FormatV3Writer::decompress(&editor.compact().unwrap()).unwrap(),
+            expected
+        );
+
+        assert!(editor
+            .set_values(ColumnType::Emails, &[(3, "c@example.com")])
+            .is_err());
+        assert!(editor.set_values(ColumnType::IPv4, &[(0, "x")]).is_err());
+        assert!(editor.read_strings(ColumnType::URLs).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_editor_write_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("editor.atxt");
+        let compressed = FormatV3Writer::new(CompressionLevel::Balanced)
+            .compress(EDIT_LOG)
+            .unwrap();
+        std::fs::write(&path, &compressed).unwrap();
+
+        let mut editor = FormatV3Editor::open(&path).unwrap();
+        editor
+            .set_values(ColumnType::Emails, &[(1, "b@example.com")])
+            .unwrap();
+        editor.write_in_place(&path).unwrap();
+
+        let on_disk = std::fs::read(&path).unwrap();
+        assert_eq!(on_disk, editor.as_bytes());
+        assert_eq!(
+            FormatV3Writer::decompress(&on_disk).unwrap(),
+            EDIT_LOG.replace("bob@example.com", "b@example.com")
+        );
+    }
+
+    /// Every zstd frame that can be (even partially) decoded out of `bytes`
+    fn scan_zstd_frames(bytes: &[u8]) -> Vec<u8> {
+        const MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+        let mut decoded = Vec::new();
+        for start in 0..bytes.len().saturating_sub(3) {
+            if bytes[start..start + 4] == MAGIC {
+                if let Ok(decoder) = zstd::stream::read::Decoder::new(&bytes[start..]) {
+                    let _ = decoder.single_frame().read_to_end(&mut decoded);
+                }
+            }
+        }
+        decoded
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+    }
+
+    #[test]
+    fn test_editor_erases_old_values() {
+        let compressed = FormatV3Writer::new(CompressionLevel::Balanced)
+            .compress(EDIT_LOG)
+            .unwrap();
+        assert!(contains(&scan_zstd_frames(&compressed), "bob@example.com"));
+
+        // Shorter value (rewritten in the old slot) and longer one (appended)
+        let long = "x".repeat(10) + &"0123456789abcdef".repeat(8) + "@example.com";
+        for replacement in ["b@example.com", long.as_str()] {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("erase.atxt");
+            std::fs::write(&path, &compressed).unwrap();
+
+            let mut editor = FormatV3Editor::open(&path).unwrap();
+            editor
+                .set_values(ColumnType::Emails, &[(1, replacement)])
+                .unwrap();
+            editor.write_in_place(&path).unwrap();
+            let on_disk = std::fs::read(&path).unwrap();
+            let saved = editor.into_bytes();
+            assert_eq!(on_disk, saved);
+
+            assert!(!contains(&saved, "bob@example.com"));
+            let frames = scan_zstd_frames(&saved);
+            assert!(contains(&frames, replacement));
+            assert!(!contains(&frames, "bob@example.com"));
+            assert_eq!(
+                FormatV3Writer::decompress(&saved).unwrap(),
+                EDIT_LOG.replace("bob@example.com", replacement)
+            );
+        }
+    }
+
+    #[test]
+    fn test_editor_lengths_in_source_encoding() {
+        let crlf = EDIT_LOG.replace('\n', "\r\n");
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(crlf.encode_utf16().flat_map(u16::to_le_bytes));
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress_bytes(&utf16)
+            .unwrap();
+        let mut editor = FormatV3Editor::new(compressed).unwrap();
+        let value_bytes = editor.metadata().value_bytes().unwrap();
+
+        editor
+            .rewrite_column(ColumnType::Emails, |_, v| {
+                (v == "alice@example.com").then(|| "[redacted]".to_string())
+            })
+            .unwrap();
+        let restored = FormatV3Writer::decompress_bytes(editor.as_bytes()).unwrap();
+        assert_eq!(
+            editor.metadata().header.original_length,
+            restored.len() as u64
+        );
+        let shrink = 2 * ("alice@example.com".len() - "[redacted]".len()) as u64;
+        assert_eq!(editor.metadata().value_bytes(), Some(value_bytes - shrink));
+        assert_eq!(
+            FormatV3Metadata::read_from(&mut Cursor::new(editor.as_bytes()))
+                .unwrap()
+                .value_bytes(),
+            Some(value_bytes - shrink)
+        );
+    }
+
+    #[test]
+    fn test_column_summaries() {
+        let mut text = String::new();
+        for i in 0..100 {
+            text.push_str(&format!(
+                "2024-01-15 10:{:02}:00 INFO request took {} ms from 10.0.0.{}\n",
+                i % 60,
+                i + 1,
+                i % 4 + 1
+            ));
+        }
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+
+        // Header-only read
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap();
+        assert!(metadata.header.summary_size > 0);
+
+        let numbers = metadata.get_summary(ColumnType::Numbers).unwrap();
+        assert_eq!(numbers.min, 1.0);
+        assert_eq!(numbers.max, 100.0);
+        assert!((49.0..=52.0).contains(&numbers.p50));
+        assert!((98.0..=100.0).contains(&numbers.p99));
+
+        let ipv4 = metadata.get_summary(ColumnType::IPv4).unwrap();
+        assert_eq!(ipv4.format_value(ipv4.min), "10.0.0.1");
+        assert_eq!(ipv4.format_value(ipv4.max), "10.0.0.4");
+
+        let timestamps = metadata.get_summary(ColumnType::Timestamps).unwrap();
+        assert_eq!(timestamps.count, 100);
+        assert_eq!(timestamps.null_count, 0);
+        assert_eq!(
+            timestamps.format_value(timestamps.max),
+            "2024-01-15 10:59:00"
+        );
+
+        // Summary block does not disturb column access
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+    }
+
+    #[test]
+    fn test_column_summary_from_values() {
+        assert!(ColumnSummary::from_values(ColumnType::Numbers, &[], 3).is_none());
+        assert!(ColumnSummary::from_values(ColumnType::Numbers, &[f64::NAN], 0).is_none());
+
+        let values: Vec<f64> = (0..=200_000).map(f64::from).collect();
+        let summary = ColumnSummary::from_values(ColumnType::Numbers, &values, 2).unwrap();
+        assert_eq!(summary.count, 200_001);
+        assert_eq!(summary.null_count, 2);
+        assert_eq!((summary.min, summary.max), (0.0, 200_000.0));
+        assert!((summary.p90 - 180_000.0).abs() < 1_000.0);
+    }
+
+    #[test]
+    fn test_pattern_config_recorded() {
+        let text = "release 1.2.3.4.5 build a1b2c3 from 10.0.0.1 took 42 ms\n".repeat(10);
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_pattern_config(PatternConfig::strict())
+            .unwrap()
+            .compress(&text)
+            .unwrap();
+
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap();
+        assert!(metadata.header.config_size > 0);
+        assert_eq!(metadata.pattern_config, PatternConfig::strict());
+        assert_eq!(metadata.get_column(ColumnType::IPv4).unwrap().row_count, 10);
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+
+        // Re-encoding keeps the config
+        let compacted = FormatV3Editor::new(compressed).unwrap().compact().unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compacted)).unwrap();
+        assert_eq!(metadata.pattern_config, PatternConfig::strict());
+    }
+
+    #[test]
+    fn test_schema_diff() {
+        let metadata = |text: &str, config: PatternConfig| {
+            let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+                .with_pattern_config(config)
+                .unwrap()
+                .compress(text)
+                .unwrap();
+            FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap()
+        };
+        let web = metadata(
+            "2024-01-15 10:30:00 INFO GET /index.html from 10.0.0.1\n",
+            PatternConfig::default(),
+        );
+        let mail = metadata(
+            "2024-01-15 10:30:01 WARN bounce for ops@example.com from 10.0.0.2\n",
+            PatternConfig::default(),
+        );
+        assert!(web.schema_diff(&web).is_empty());
+
+        // Different columns only: still compatible
+        let diff = web.schema_diff(&mail);
+        assert!(diff.is_compatible() && web.is_compatible_with(&mail));
+        assert_eq!(diff.missing, [ColumnType::Emails]);
+        assert!(diff.extra.contains(&ColumnType::Paths));
+        assert_eq!(mail.schema_diff(&web).missing, diff.extra);
+
+        // Different extraction rules for IPv4 and numbers
+        let strict = metadata(
+            "2024-01-15 10:30:02 INFO GET /index.html from 10.0.0.3\n",
+            PatternConfig::strict(),
+        );
+        let diff = web.schema_diff(&strict);
+        assert!(!web.is_compatible_with(&strict));
+        let columns: Vec<ColumnType> = diff.typed_differently.iter().map(|d| d.column).collect();
+        assert_eq!(columns, [ColumnType::IPv4, ColumnType::Numbers]);
+        assert_eq!(diff.typed_differently[0].reason, "different guards");
+
+        // Timestamps parsed on one side, raw (invalid date) on the other
+        let raw = metadata(
+            "2024-13-45 10:30:03 INFO GET /index.html from 10.0.0.4\n",
+            PatternConfig::default(),
+        );
+        let diff = web.schema_diff(&raw);
+        assert!(web.is_compatible_with(&mail) && !web.is_compatible_with(&raw));
+        assert_eq!(diff.typed_differently[0].column, ColumnType::Timestamps);
+        assert_eq!(
+            diff.typed_differently[0].reason,
+            "parsed in this archive, raw strings in the other"
+        );
+    }
+
+    #[test]
+    fn test_mac_and_geo_columns() {
+        let text = "lease 00:1a:2b:3c:4d:5e at 35.6895, 139.6917\nlease 00:1a:2b:3c:4d:5f at 35.6896, 139.6918\n";
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(text)
+            .unwrap();
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+
+        let mut cursor = Cursor::new(&compressed);
+        let metadata = FormatV3Metadata::read_from(&mut cursor).unwrap();
+        let partial = FormatV3Writer::read_columns(
+            &mut cursor,
+            &metadata,
+            &[ColumnType::MacAddrs, ColumnType::GeoPoints],
+        )
+        .unwrap();
+        assert_eq!(
+            partial.mac_strings().unwrap(),
+            ["00:1a:2b:3c:4d:5e", "00:1a:2b:3c:4d:5f"]
+        );
+        assert_eq!(
+            partial.geo_strings().unwrap(),
+            ["35.6895, 139.6917", "35.6896, 139.6918"]
+        );
+    }
+
+    #[test]
+    fn test_epoch_and_uptime_columns() {
+        let text = "[    5.000100] ts=1705312245 up\n[   12.5] ts=1705312245123 down\n";
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(text)
+            .unwrap();
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+
+        let mut cursor = Cursor::new(&compressed);
+        let metadata = FormatV3Metadata::read_from(&mut cursor).unwrap();
+        assert_eq!(
+            metadata.get_column(ColumnType::Epochs).unwrap().row_count,
+            2
+        );
+        let partial = FormatV3Writer::read_columns(
+            &mut cursor,
+            &metadata,
+            &[ColumnType::Epochs, ColumnType::Uptimes],
+        )
+        .unwrap();
+        assert_eq!(
+            partial.epoch_strings().unwrap(),
+            ["1705312245", "1705312245123"]
+        );
+        assert_eq!(
+            partial.uptime_strings().unwrap(),
+            ["[    5.000100]", "[   12.5]"]
+        );
+    }
+
+    #[test]
+    fn test_ansi_codes_column() {
+        let text = "\x1b[1;33mWARN\x1b[0m disk 91%\n\x1b[1;31mERROR\x1b[0m disk 99%\n";
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(text)
+            .unwrap();
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+
+        let mut cursor = Cursor::new(&compressed);
+        let metadata = FormatV3Metadata::read_from(&mut cursor).unwrap();
+        let ansi = metadata.get_column(ColumnType::AnsiCodes).unwrap();
+        assert_eq!(ansi.row_count, 4);
+        let partial =
+            FormatV3Writer::read_columns(&mut cursor, &metadata, &[ColumnType::LogLevels]).unwrap();
+        assert_eq!(partial.log_level_strings().unwrap(), ["WARN", "ERROR"]);
+
+        // Colors can be dropped from an existing archive without re-encoding
+        let mut editor = FormatV3Editor::new(compressed).unwrap();
+        editor
+            .rewrite_column(ColumnType::AnsiCodes, |_, _| Some(String::new()))
+            .unwrap();
+        assert_eq!(
+            FormatV3Writer::decompress(editor.as_bytes()).unwrap(),
+            "WARN disk 91%\nERROR disk 99%\n"
+        );
+    }
+
+    #[test]
+    fn test_provenance_column() {
+        let mut batch = SourceBatch::new();
+        batch.add(
+            "web-1.log",
+            "2024-01-15 10:00:00 INFO up\n2024-01-15 10:00:05 ERROR down\n",
+        );
+        batch.add("web-2.log", "2024-01-15 10:00:01 WARN slow");
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress_batch(&batch)
+            .unwrap();
+        assert_eq!(
+            FormatV3Writer::decompress(&compressed).unwrap(),
+            batch.text()
+        );
+
+        #[cfg(feature = "query")]
+        {
+            let engine = crate::QueryEngine::from_reader(Cursor::new(&compressed)).unwrap();
+            assert_eq!(
+                engine.select_column("provenance").unwrap(),
+                ["web-1.log:1", "web-1.log:2", "web-2.log:1"]
+            );
+            let errors = engine
+                .query(&["provenance"], "log_levels", crate::Op::Eq, "ERROR")
+                .unwrap();
+            assert_eq!(errors.rows[0].values["provenance"], "web-1.log:2");
+        }
+
+        // Survives compaction; plain archives have none
+        let compacted = FormatV3Editor::new(compressed).unwrap().compact().unwrap();
+        let provenance = FormatV3Writer::read_provenance(&compacted)
+            .unwrap()
+            .unwrap();
+        assert_eq!(provenance, *batch.provenance());
+        let plain = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(batch.text())
+            .unwrap();
+        assert!(FormatV3Writer::read_provenance(&plain).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_raw_bytes_column() {
+        let mut input = b"2024-01-15 10:30:45 ERROR bad frame ".to_vec();
+        input.extend_from_slice(&[0x80, 0x81, 0xFE]);
+        input.extend_from_slice(
+            b" from 192.168.1.1\n2024-01-15 10:30:46 INFO ok \xEF\xBF\xBD\xC0\n",
+        );
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress_bytes(&input)
+            .unwrap();
+        assert_eq!(
+            FormatV3Writer::decompress_bytes(&compressed).unwrap(),
+            input
+        );
+
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap();
+        assert_eq!(metadata.header.original_length as usize, input.len());
+        assert_eq!(
+            metadata.get_column(ColumnType::RawBytes).unwrap().row_count,
+            2
+        );
+        assert_eq!(metadata.get_column(ColumnType::IPv4).unwrap().row_count, 1);
+
+        // Compaction keeps the runs attached to their placeholders
+        let editor = FormatV3Editor::new(compressed).unwrap();
+        let compacted = editor.compact().unwrap();
+        assert_eq!(FormatV3Writer::decompress_bytes(&compacted).unwrap(), input);
+    }
+
+    #[test]
+    fn test_windows_log_roundtrip() {
+        let text = "2024-01-15 10:30:45 WARN \\\\fs01\\share\\app.log rotated\r\n".repeat(20);
+        let writer = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_options(CompressOptions::new().with_verify_roundtrip(true));
+        let mut utf16 = crate::UTF16LE_BOM.to_vec();
+        text.encode_utf16()
+            .for_each(|u| utf16.extend_from_slice(&u.to_le_bytes()));
+
+        let compressed = writer.compress_bytes(&utf16).unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap();
+        assert_eq!(metadata.header.original_length as usize, utf16.len());
+        assert_eq!(
+            metadata.header.source_profile(),
+            SourceProfile {
+                crlf: true,
+                utf16le: true
+            }
+        );
+        assert_eq!(
+            metadata.get_column(ColumnType::Paths).unwrap().row_count,
+            20
+        );
+        assert_eq!(
+            FormatV3Writer::decompress_bytes(&compressed).unwrap(),
+            utf16
+        );
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+    }
+
+    #[test]
+    fn test_literal_braces_roundtrip() {
+        let text = r#"{"id": 1, "tags": {}, "msg": "{0} of {1}", "nested": {{"a": 2}}}
+println!("{} {:?} {0} {name}", x, y); // 10.0.0.1
+{12a {{ }} {} {"#
+            .repeat(10);
+        let writer = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_options(CompressOptions::new().with_verify_roundtrip(true));
+        let compressed = writer.compress(&text).unwrap();
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+    }
+
+    #[test]
+    fn test_zstd_window_record() {
+        let text = "2024-01-15 10:30:45 INFO request from 10.0.0.1 took 12ms\n".repeat(200);
+        let zstd = crate::ZstdParams::new()
+            .with_long_distance_matching(true)
+            .with_window_log(Some(29))
+            .with_workers(if cfg!(feature = "zstdmt") { 2 } else { 0 });
+        let compressed = FormatV3Writer::new(CompressionLevel::Balanced)
+            .with_options(CompressOptions::new().with_zstd(zstd))
+            .compress(&text)
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap();
+        assert_eq!(
+            metadata.extension(HeaderExtension::ZSTD_WINDOW),
+            Some(&[29][..])
+        );
+        assert!(metadata.columns.iter().all(|c| c.window_log == 29));
+        assert_eq!(metadata.producer().unwrap().settings["window_log"], "29");
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+
+        // Windows within the decoder default need no record
+        let zstd = zstd.with_window_log(Some(20));
+        let compressed = FormatV3Writer::new(CompressionLevel::Balanced)
+            .with_options(CompressOptions::new().with_zstd(zstd))
+            .compress(&text)
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&compressed)).unwrap();
+        assert!(metadata.extension(HeaderExtension::ZSTD_WINDOW).is_none());
+
+        // Out-of-range windows fail on write; unsupported ones on read
+        assert!(FormatV3Writer::new(CompressionLevel::Fast)
+            .with_options(CompressOptions::new().with_zstd(zstd.with_window_log(Some(40))))
+            .compress(&text)
+            .is_err());
+        let mut columns = metadata.columns;
+        assert!(apply_window_log(&mut columns, &[40]).is_err());
+        assert!(apply_window_log(&mut columns, &[]).is_err());
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        // A few hundred bytes of skeleton expanding to megabytes
+        let text = "x".repeat(1 << 22);
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+        assert!(compressed.len() < 4096);
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text);
+
+        let column = DecodeLimits::new().with_max_column_bytes(1 << 20);
+        assert!(matches!(
+            FormatV3Writer::decompress_with(
+                &compressed,
+                DecodeOptions::strict().with_limits(column)
+            ),
+            Err(ALICETextError::LimitExceeded {
+                limit: 1_048_576,
+                ..
+            })
+        ));
+        let total = DecodeLimits::new().with_max_total_bytes(1 << 20);
+        assert!(matches!(
+            FormatV3Writer::decompress_with(
+                &compressed,
+                DecodeOptions::strict().with_limits(total)
+            ),
+            Err(ALICETextError::LimitExceeded { .. })
+        ));
+        let unlimited = DecodeOptions::strict().with_limits(DecodeLimits::unlimited());
+        assert_eq!(
+            FormatV3Writer::decompress_with(&compressed, unlimited).unwrap(),
+            text
+        );
+    }
+
+    #[test]
+    fn test_untrusted_profile() {
+        let text = "2024-01-15 10:30:00 INFO alice@example.com GET /index\n".repeat(50);
+        let untrusted = DecodeProfile::Untrusted.options();
+        let compressed = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress(&text)
+            .unwrap();
+        assert_eq!(
+            FormatV3Writer::decompress_with(&compressed, untrusted).unwrap(),
+            text
+        );
+
+        // Flipped column byte: lenient decoding does not look, untrusted does
+        let mut corrupted = compressed.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&corrupted)).unwrap();
+        assert!(metadata.header.column_count > 0);
+        assert!(FormatV3Metadata::read_from_with(&mut Cursor::new(&corrupted), untrusted).is_err());
+
+        // Unknown (non-critical) extension
+        let extended = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_extension(HeaderExtension::new(0x0fff, vec![1, 2, 3]))
+            .compress(&text)
+            .unwrap();
+        assert!(FormatV3Writer::decompress(&extended).is_ok());
+        assert!(FormatV3Writer::decompress_with(&extended, untrusted).is_err());
+
+        // Archive size ceiling
+        let small = untrusted.with_limits(DecodeLimits::new().with_max_archive_bytes(64));
+        assert!(matches!(
+            FormatV3Writer::decompress_with(&compressed, small),
+            Err(ALICETextError::LimitExceeded {
+                field: "archive size",
+                ..
+            })
+        ));
+
+        // The checksum covers the column data only: trailing bytes are a
+        // framing error for strict readers, not a checksum mismatch
+        let mut trailing = compressed.clone();
+        trailing.extend_from_slice(b"junk");
+        let lenient = DecodeOptions::lenient().with_verify_checksum(true);
+        assert_eq!(
+            FormatV3Writer::decompress_with(&trailing, lenient).unwrap(),
+            text
+        );
+        assert!(matches!(
+            FormatV3Writer::decompress_with(&trailing, untrusted),
+            Err(ALICETextError::DecompressionError(msg)) if msg.contains("trailing bytes")
+        ));
+
+        // v2 archives take the preset's limits
+        let v2 = crate::compress_tuned(&text, crate::CompressionMode::Fast).unwrap();
+        let limits = DecodeProfile::Untrusted
+            .limits()
+            .with_max_archive_bytes(v2.len() as u64 - 1);
+        assert!(matches!(
+            crate::TunedCompressor::default()
+                .with_decode_limits(limits)
+                .decompress(&v2),
+            Err(ALICETextError::LimitExceeded {
+                field: "archive size",
+                ..
+            })
+        ));
+
+        // Edits keep the checksum valid
+        let mut editor = FormatV3Editor::new(compressed).unwrap();
+        editor
+            .rewrite_column(ColumnType::Emails, |_, _| Some("bob@example.com".into()))
+            .unwrap();
+        let edited = editor.into_bytes();
+        assert!(FormatV3Writer::decompress_with(&edited, untrusted)
+            .unwrap()
+            .contains("bob@example.com"));
+    }
 }
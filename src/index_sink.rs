@@ -0,0 +1,289 @@
+//! Write-time full-text indexing
+//!
+//! [`IndexedWriter`] feeds external full-text engines as it compresses:
+//! every v3 block emits `(row_id, token)` postings to an [`IndexSink`]
+//! (e.g. `TantivySink` with the `tantivy` feature), so the archive never has
+//! to be decompressed again for indexing.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use crate::format_v3::FormatV3Writer;
+use crate::level::Level;
+
+/// Receiver of write-time postings
+///
+/// Row ids are archive line numbers, continuous across the blocks of one
+/// [`IndexedWriter`]; each row's postings arrive together and in row order.
+pub trait IndexSink {
+    /// Called before the postings of block `block`
+    fn begin_block(&mut self, block: u64) -> crate::Result<()> {
+        let _ = block;
+        Ok(())
+    }
+
+    /// One token occurrence on row `row_id`
+    fn posting(&mut self, row_id: u64, token: &str) -> crate::Result<()>;
+
+    /// Called after the last posting of block `block`
+    fn end_block(&mut self, block: u64) -> crate::Result<()> {
+        let _ = block;
+        Ok(())
+    }
+}
+
+impl<S: IndexSink + ?Sized> IndexSink for &mut S {
+    fn begin_block(&mut self, block: u64) -> crate::Result<()> {
+        (**self).begin_block(block)
+    }
+
+    fn posting(&mut self, row_id: u64, token: &str) -> crate::Result<()> {
+        (**self).posting(row_id, token)
+    }
+
+    fn end_block(&mut self, block: u64) -> crate::Result<()> {
+        (**self).end_block(block)
+    }
+}
+
+/// In-memory sink collecting every posting (tests, small archives)
+impl IndexSink for Vec<(u64, String)> {
+    fn posting(&mut self, row_id: u64, token: &str) -> crate::Result<()> {
+        self.push((row_id, token.to_string()));
+        Ok(())
+    }
+}
+
+/// Lowercased index tokens of one line
+///
+/// Tokens are runs of alphanumerics, `_`, `.`, `-` and `:` with leading and
+/// trailing punctuation trimmed, so IPs, versions and host:port pairs stay
+/// whole while paths and key=value pairs split into their parts.
+pub fn index_tokens(line: &str) -> impl Iterator<Item = String> + '_ {
+    line.split(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | ':')))
+        .map(|t| t.trim_matches(|c: char| matches!(c, '.' | '-' | ':')))
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// v3 writer that emits postings for each block it compresses
+pub struct IndexedWriter<S: IndexSink> {
+    writer: FormatV3Writer,
+    sink: S,
+    next_row: u64,
+    next_block: u64,
+}
+
+impl<S: IndexSink> IndexedWriter<S> {
+    pub fn new(level: impl Into<Level>, sink: S) -> Self {
+        Self::with_writer(FormatV3Writer::new(level), sink)
+    }
+
+    /// Use a configured writer (options, pattern config)
+    pub fn with_writer(writer: FormatV3Writer, sink: S) -> Self {
+        Self {
+            writer,
+            sink,
+            next_row: 0,
+            next_block: 0,
+        }
+    }
+
+    /// Compress one block and index its lines from the input text
+    pub fn compress_block(&mut self, text: &str) -> crate::Result<Vec<u8>> {
+        let compressed = self.writer.compress(text)?;
+        let block = self.next_block;
+        self.sink.begin_block(block)?;
+        for line in text.lines() {
+            for token in index_tokens(line) {
+                self.sink.posting(self.next_row, &token)?;
+            }
+            self.next_row += 1;
+        }
+        self.sink.end_block(block)?;
+        self.next_block += 1;
+        Ok(compressed)
+    }
+
+    /// Row id the next block starts at
+    pub fn next_row(&self) -> u64 {
+        self.next_row
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+}
+
+/// Tantivy adapter: one document per row (`block`, `row`, raw `token` values)
+#[cfg(feature = "tantivy")]
+pub struct TantivySink {
+    writer: tantivy::IndexWriter,
+    block_field: tantivy::schema::Field,
+    row_field: tantivy::schema::Field,
+    token_field: tantivy::schema::Field,
+    block: u64,
+    pending: Option<tantivy::TantivyDocument>,
+    pending_row: u64,
+}
+
+#[cfg(feature = "tantivy")]
+impl TantivySink {
+    /// Schema the index must be created with
+    pub fn schema() -> tantivy::schema::Schema {
+        use tantivy::schema::{FAST, INDEXED, STORED, STRING};
+        let mut builder = tantivy::schema::Schema::builder();
+        builder.add_u64_field("block", INDEXED | STORED | FAST);
+        builder.add_u64_field("row", INDEXED | STORED | FAST);
+        builder.add_text_field("token", STRING);
+        builder.build()
+    }
+
+    /// Wrap a writer of an index created with [`Self::schema`]
+    pub fn new(writer: tantivy::IndexWriter) -> crate::Result<Self> {
+        let schema = writer.index().schema();
+        let field = |name: &str| schema.get_field(name).map_err(index_error);
+        Ok(Self {
+            block_field: field("block")?,
+            row_field: field("row")?,
+            token_field: field("token")?,
+            writer,
+            block: 0,
+            pending: None,
+            pending_row: 0,
+        })
+    }
+
+    /// Commit everything indexed so far
+    pub fn commit(&mut self) -> crate::Result<u64> {
+        self.flush_row()?;
+        self.writer.commit().map_err(index_error)
+    }
+
+    pub fn into_writer(mut self) -> crate::Result<tantivy::IndexWriter> {
+        self.flush_row()?;
+        Ok(self.writer)
+    }
+
+    fn flush_row(&mut self) -> crate::Result<()> {
+        if let Some(doc) = self.pending.take() {
+            self.writer.add_document(doc).map_err(index_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tantivy")]
+impl IndexSink for TantivySink {
+    fn begin_block(&mut self, block: u64) -> crate::Result<()> {
+        self.block = block;
+        Ok(())
+    }
+
+    fn posting(&mut self, row_id: u64, token: &str) -> crate::Result<()> {
+        if self.pending.is_some() && self.pending_row != row_id {
+            self.flush_row()?;
+        }
+        let doc = self.pending.get_or_insert_with(|| {
+            let mut doc = tantivy::TantivyDocument::default();
+            doc.add_u64(self.block_field, self.block);
+            doc.add_u64(self.row_field, row_id);
+            doc
+        });
+        doc.add_text(self.token_field, token);
+        self.pending_row = row_id;
+        Ok(())
+    }
+
+    fn end_block(&mut self, _block: u64) -> crate::Result<()> {
+        self.flush_row()
+    }
+}
+
+#[cfg(feature = "tantivy")]
+fn index_error(e: impl std::fmt::Display) -> crate::ALICETextError {
+    crate::ALICETextError::EncodingError(format!("Tantivy error: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionLevel;
+
+    #[test]
+    fn test_index_tokens() {
+        let tokens: Vec<String> =
+            index_tokens("ERROR [db] conn to 10.0.0.5:5432 failed, path=/var/lib/pg.").collect();
+        assert_eq!(
+            tokens,
+            [
+                "error",
+                "db",
+                "conn",
+                "to",
+                "10.0.0.5:5432",
+                "failed",
+                "path",
+                "var",
+                "lib",
+                "pg"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_indexed_writer_postings() {
+        let mut writer = IndexedWriter::new(CompressionLevel::Fast, Vec::new());
+        let first = writer
+            .compress_block("INFO start\nERROR disk full\n")
+            .unwrap();
+        writer.compress_block("ERROR timeout").unwrap();
+        assert_eq!(writer.next_row(), 3);
+        assert_eq!(
+            FormatV3Writer::decompress(&first).unwrap(),
+            "INFO start\nERROR disk full\n"
+        );
+
+        let errors: Vec<u64> = writer
+            .sink()
+            .iter()
+            .filter(|(_, t)| t == "error")
+            .map(|(row, _)| *row)
+            .collect();
+        assert_eq!(errors, [1, 2]);
+    }
+
+    #[cfg(feature = "tantivy")]
+    #[test]
+    fn test_tantivy_sink() {
+        use tantivy::collector::Count;
+        use tantivy::query::TermQuery;
+        use tantivy::schema::IndexRecordOption;
+
+        let index = tantivy::Index::create_in_ram(TantivySink::schema());
+        let sink = TantivySink::new(index.writer(15_000_000).unwrap()).unwrap();
+        let mut writer = IndexedWriter::new(CompressionLevel::Fast, sink);
+        writer
+            .compress_block("INFO up\nERROR refused 10.0.0.1\nERROR timeout\n")
+            .unwrap();
+        writer.sink_mut().commit().unwrap();
+
+        let searcher = index.reader().unwrap().searcher();
+        let token = index.schema().get_field("token").unwrap();
+        let count = |text: &str| {
+            let term = tantivy::Term::from_field_text(token, text);
+            let query = TermQuery::new(term, IndexRecordOption::Basic);
+            searcher.search(&query, &Count).unwrap()
+        };
+        assert_eq!(count("error"), 2);
+        assert_eq!(count("10.0.0.1"), 1);
+    }
+}
@@ -0,0 +1,547 @@
+//! Kubernetes / container log preset
+//!
+//! Generic patterns split `web-7fb96c846b-x5n2k` into numbers, hex runs and
+//! literal text, which costs ratio and makes pods impossible to query.
+//! [`PatternConfig::kubernetes`] enables opt-in patterns that keep container
+//! metadata whole, each in its own column:
+//!
+//! | Pattern | Column | Example | Stored as |
+//! |---------|--------|---------|-----------|
+//! | [`PatternType::Cri`] | `cri_prefixes` | `2024-01-15T10:30:45.123456789Z stdout F ` | ns since epoch (delta) + format byte |
+//! | [`PatternType::Klog`] | `klog_headers` | `I0115 10:30:45.123456   12345 main.go:42]` | severity, date/time, pid, file id, line |
+//! | [`PatternType::ContainerId`] | `container_ids` | `containerd://` + 64 hex | runtime byte + 32 bytes |
+//! | [`PatternType::Namespace`] | `k8s_namespaces` | `namespace=kube-system`, `"namespace":"prod"` | key form + name id |
+//! | [`PatternType::Pod`] | `k8s_pods` | `web-7fb96c846b-x5n2k` | prefix id + packed 5-character suffix |
+//!
+//! Pod names are recognized by the random suffix Kubernetes appends (five
+//! characters from `bcdfghjklmnpqrstvwxz2456789`), so a hyphenated word that
+//! happens to end that way (`sidecar-http2`) lands in the pod column too;
+//! it still restores exactly. Namespaces are only recognized after a
+//! `namespace` key. Matches that would not restore exactly go to `others`.
+//!
+//! The query engine returns namespace names without their key.
+//!
+//! [`PatternConfig::kubernetes`]: crate::PatternConfig::kubernetes
+//! [`PatternType::Cri`]: crate::TunedPatternType::Cri
+//! [`PatternType::Klog`]: crate::TunedPatternType::Klog
+//! [`PatternType::ContainerId`]: crate::TunedPatternType::ContainerId
+//! [`PatternType::Namespace`]: crate::TunedPatternType::Namespace
+//! [`PatternType::Pod`]: crate::TunedPatternType::Pod
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::tuned_pattern_learner::PatternType;
+
+/// Opt-in patterns enabled by [`crate::PatternConfig::with_kubernetes`], in priority order
+pub const KUBERNETES_PATTERNS: [PatternType; 5] = [
+    PatternType::Cri,
+    PatternType::Klog,
+    PatternType::ContainerId,
+    PatternType::Namespace,
+    PatternType::Pod,
+];
+
+/// Characters of the random pod name suffix (apimachinery `rand.String`)
+const SUFFIX_ALPHABET: &[u8; 27] = b"bcdfghjklmnpqrstvwxz2456789";
+
+/// Length of the random pod name suffix
+const SUFFIX_LEN: usize = 5;
+
+/// Every Kubernetes column of a payload
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KubernetesColumns {
+    pub cri_prefixes: CriColumn,
+    pub klog_headers: KlogColumn,
+    pub container_ids: Vec<ContainerId>,
+    pub namespaces: NamespaceColumn,
+    pub pods: PodColumn,
+}
+
+impl KubernetesColumns {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.cri_prefixes.is_empty()
+            && self.klog_headers.headers.is_empty()
+            && self.container_ids.is_empty()
+            && self.namespaces.values.is_empty()
+            && self.pods.pods.is_empty()
+    }
+}
+
+// ── Dictionary ─────────────────────────────────────────────────
+
+/// Distinct strings, each stored once and referenced by id
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dictionary {
+    pub entries: Vec<String>,
+    #[serde(skip)]
+    ids: HashMap<String, u32>,
+}
+
+impl Dictionary {
+    /// Dictionary over decoded entries
+    #[must_use]
+    pub fn from_entries(entries: Vec<String>) -> Self {
+        Self {
+            entries,
+            ids: HashMap::new(),
+        }
+    }
+
+    /// Id of `value`, adding it if new
+    pub fn intern(&mut self, value: &str) -> u32 {
+        if self.ids.len() != self.entries.len() {
+            self.ids = (0..)
+                .zip(&self.entries)
+                .map(|(id, e)| (e.clone(), id))
+                .collect();
+        }
+        if let Some(&id) = self.ids.get(value) {
+            return id;
+        }
+        let id = self.entries.len() as u32;
+        self.entries.push(value.to_string());
+        self.ids.insert(value.to_string(), id);
+        id
+    }
+
+    #[must_use]
+    pub fn get(&self, id: u32) -> Option<&str> {
+        self.entries.get(id as usize).map(String::as_str)
+    }
+}
+
+// ── CRI Prefix ─────────────────────────────────────────────────
+
+/// CRI log line prefixes (`<RFC 3339 UTC> <stream> <F|P> `), delta-encoded
+///
+/// Format byte: bits 0-3 fraction digits, bit 4 `stderr`, bit 5 partial (`P`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CriColumn {
+    /// Nanoseconds since the epoch, each relative to the previous entry
+    pub deltas: Vec<i64>,
+    pub formats: Vec<u8>,
+    #[serde(skip)]
+    pub(crate) last: i64,
+}
+
+impl CriColumn {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Add a prefix; `None` if it would not restore exactly
+    pub fn add(&mut self, text: &str) -> Option<usize> {
+        let (nanos, format) = parse_cri(text)?;
+        self.deltas.push(nanos - self.last);
+        self.formats.push(format);
+        self.last = nanos;
+        Some(self.deltas.len() - 1)
+    }
+
+    /// Absolute values for [`Self::get`]
+    #[must_use]
+    pub fn prepare_for_read(&self) -> Vec<i64> {
+        self.deltas
+            .iter()
+            .scan(0i64, |acc, &d| {
+                *acc += d;
+                Some(*acc)
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn get(&self, idx: usize, prefix_sums: &[i64]) -> Option<String> {
+        format_cri(*prefix_sums.get(idx)?, *self.formats.get(idx)?)
+    }
+
+    /// Every prefix, in order
+    #[must_use]
+    pub fn strings(&self) -> Vec<String> {
+        let sums = self.prepare_for_read();
+        (0..self.len()).filter_map(|i| self.get(i, &sums)).collect()
+    }
+}
+
+fn parse_cri(text: &str) -> Option<(i64, u8)> {
+    let mut parts = text.split(' ');
+    let (timestamp, stream, tag) = (parts.next()?, parts.next()?, parts.next()?);
+    let timestamp = timestamp.strip_suffix('Z')?;
+    let digits = timestamp.split_once('.').map_or(0, |(_, f)| f.len());
+    let nanos = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()?
+        .and_utc()
+        .timestamp_nanos_opt()?;
+    let format =
+        u8::try_from(digits).ok()? | u8::from(stream == "stderr") << 4 | u8::from(tag == "P") << 5;
+    (format_cri(nanos, format)? == text).then_some((nanos, format))
+}
+
+fn format_cri(nanos: i64, format: u8) -> Option<String> {
+    let digits = u32::from(format & 0x0F);
+    let subsec = nanos.rem_euclid(1_000_000_000) as u32;
+    let time = DateTime::from_timestamp(nanos.div_euclid(1_000_000_000), subsec)?;
+    let mut out = time.format("%Y-%m-%dT%H:%M:%S").to_string();
+    if digits > 0 {
+        let fraction = subsec / 10u32.checked_pow(9u32.checked_sub(digits)?)?;
+        let _ = write!(out, ".{fraction:0width$}", width = digits as usize);
+    }
+    let stream = if format & 0x10 == 0 {
+        "stdout"
+    } else {
+        "stderr"
+    };
+    let tag = if format & 0x20 == 0 { 'F' } else { 'P' };
+    let _ = write!(out, "Z {stream} {tag} ");
+    Some(out)
+}
+
+// ── klog Header ────────────────────────────────────────────────
+
+/// Parsed klog header `Lmmdd hh:mm:ss.uuuuuu threadid file:line]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KlogHeader {
+    /// `I`, `W`, `E` or `F`
+    pub severity: u8,
+    /// `mmdd * 86_400_000_000 + µs since midnight`
+    pub when: u64,
+    /// Spaces before the thread id (klog pads it to 7 columns)
+    pub gap: u8,
+    pub pid: u32,
+    /// Id in [`KlogColumn::files`]
+    pub file: u32,
+    pub line: u32,
+}
+
+/// klog headers with source file names in a dictionary
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KlogColumn {
+    pub files: Dictionary,
+    pub headers: Vec<KlogHeader>,
+}
+
+const MICROS_PER_DAY: u64 = 86_400_000_000;
+
+impl KlogColumn {
+    /// Add a header; `None` if it would not restore exactly
+    pub fn add(&mut self, text: &str) -> Option<usize> {
+        let bytes = text.as_bytes();
+        let severity = *bytes.first()?;
+        let mmdd: u64 = text.get(1..5)?.parse().ok()?;
+        let time = text.get(6..21)?;
+        let micros = parse_clock(time)?;
+        let rest = text.get(21..)?;
+        let after_gap = rest.trim_start_matches(' ');
+        let gap = u8::try_from(rest.len() - after_gap.len()).ok()?;
+        let (pid, location) = after_gap.split_once(' ')?;
+        let (file, line) = location.strip_suffix(']')?.rsplit_once(':')?;
+        let mut header = KlogHeader {
+            severity,
+            when: mmdd * MICROS_PER_DAY + micros,
+            gap,
+            pid: pid.parse().ok()?,
+            file: 0,
+            line: line.parse().ok()?,
+        };
+        if format_klog(&header, file) != text {
+            return None;
+        }
+        header.file = self.files.intern(file);
+        self.headers.push(header);
+        Some(self.headers.len() - 1)
+    }
+
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<String> {
+        let header = self.headers.get(idx)?;
+        Some(format_klog(header, self.files.get(header.file)?))
+    }
+
+    /// Every header, in order
+    #[must_use]
+    pub fn strings(&self) -> Vec<String> {
+        (0..self.headers.len())
+            .filter_map(|i| self.get(i))
+            .collect()
+    }
+}
+
+/// `hh:mm:ss.uuuuuu` to µs since midnight
+fn parse_clock(time: &str) -> Option<u64> {
+    let (hms, micros) = time.split_once('.')?;
+    let mut parts = hms.split(':').map(str::parse::<u64>);
+    let (h, m, s) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    Some(((h * 60 + m) * 60 + s) * 1_000_000 + micros.parse::<u64>().ok()?)
+}
+
+fn format_klog(header: &KlogHeader, file: &str) -> String {
+    let (mmdd, micros) = (header.when / MICROS_PER_DAY, header.when % MICROS_PER_DAY);
+    let secs = micros / 1_000_000;
+    format!(
+        "{}{mmdd:04} {:02}:{:02}:{:02}.{:06}{:gap$}{} {file}:{}]",
+        char::from(header.severity),
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        micros % 1_000_000,
+        "",
+        header.pid,
+        header.line,
+        gap = usize::from(header.gap),
+    )
+}
+
+// ── Container ID ───────────────────────────────────────────────
+
+/// Runtime URI schemes; index 0 is a bare id
+const RUNTIMES: [&str; 4] = ["", "containerd://", "docker://", "cri-o://"];
+
+/// 64-hex-digit container id with its runtime scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerId {
+    /// Index into the runtime schemes (0: bare, 1: `containerd://`,
+    /// 2: `docker://`, 3: `cri-o://`)
+    pub runtime: u8,
+    pub id: [u8; 32],
+}
+
+impl ContainerId {
+    /// Parse `[runtime://]<64 lower-case hex>`
+    #[must_use]
+    pub fn parse(text: &str) -> Option<Self> {
+        let (runtime, hex) = RUNTIMES
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, scheme)| text.strip_prefix(scheme).map(|hex| (i as u8, hex)))?;
+        if hex.len() != 64 || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            return None;
+        }
+        let mut id = [0u8; 32];
+        for (byte, pair) in id.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+        }
+        Some(Self { runtime, id })
+    }
+}
+
+impl std::fmt::Display for ContainerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(RUNTIMES.get(usize::from(self.runtime)).unwrap_or(&""))?;
+        self.id.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+// ── Namespace ──────────────────────────────────────────────────
+
+/// Key spellings before a namespace name, and what follows it
+const NAMESPACE_KEYS: [(&str, &str); 5] = [
+    ("namespace=", ""),
+    ("namespace: ", ""),
+    ("namespace:", ""),
+    ("\"namespace\": \"", "\""),
+    ("\"namespace\":\"", "\""),
+];
+
+/// Namespace names in a dictionary, each value with its key spelling
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceColumn {
+    pub names: Dictionary,
+    /// (index into the key spellings, name id)
+    pub values: Vec<(u8, u32)>,
+}
+
+impl NamespaceColumn {
+    /// Add `namespace=<name>` (or a JSON / YAML spelling)
+    pub fn add(&mut self, text: &str) -> Option<usize> {
+        let (key, name) = NAMESPACE_KEYS
+            .iter()
+            .enumerate()
+            .find_map(|(i, (pre, post))| {
+                let name = text.strip_prefix(pre)?.strip_suffix(post)?;
+                let valid = !name.is_empty()
+                    && name
+                        .bytes()
+                        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+                valid.then_some((i as u8, name))
+            })?;
+        let id = self.names.intern(name);
+        self.values.push((key, id));
+        Some(self.values.len() - 1)
+    }
+
+    /// Original text (key and name)
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<String> {
+        let &(key, id) = self.values.get(idx)?;
+        let (pre, post) = NAMESPACE_KEYS.get(usize::from(key))?;
+        Some(format!("{pre}{}{post}", self.names.get(id)?))
+    }
+
+    /// Namespace name alone
+    #[must_use]
+    pub fn name(&self, idx: usize) -> Option<&str> {
+        self.names.get(self.values.get(idx)?.1)
+    }
+
+    /// Every name, in order
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        (0..self.values.len())
+            .filter_map(|i| self.name(i).map(str::to_string))
+            .collect()
+    }
+}
+
+// ── Pod Name ───────────────────────────────────────────────────
+
+/// Pod names as (prefix id, packed random suffix)
+///
+/// The prefix (`web-7fb96c846b`) repeats for every replica of a workload
+/// revision; the suffix packs into 24 bits (27^5 values).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PodColumn {
+    pub prefixes: Dictionary,
+    pub pods: Vec<(u32, u32)>,
+}
+
+impl PodColumn {
+    /// Add `<prefix>-<suffix>`
+    pub fn add(&mut self, text: &str) -> Option<usize> {
+        let (prefix, suffix) = text.rsplit_once('-')?;
+        if prefix.is_empty() || suffix.len() != SUFFIX_LEN {
+            return None;
+        }
+        let packed = suffix.bytes().try_fold(0u32, |acc, b| {
+            let digit = SUFFIX_ALPHABET.iter().position(|&c| c == b)?;
+            Some(acc * 27 + digit as u32)
+        })?;
+        let id = self.prefixes.intern(prefix);
+        self.pods.push((id, packed));
+        Some(self.pods.len() - 1)
+    }
+
+    #[must_use]
+    pub fn get(&self, idx: usize) -> Option<String> {
+        let &(id, mut packed) = self.pods.get(idx)?;
+        let mut suffix = [0u8; SUFFIX_LEN];
+        for byte in suffix.iter_mut().rev() {
+            *byte = SUFFIX_ALPHABET[(packed % 27) as usize];
+            packed /= 27;
+        }
+        Some(format!(
+            "{}-{}",
+            self.prefixes.get(id)?,
+            std::str::from_utf8(&suffix).ok()?
+        ))
+    }
+
+    /// Every pod name, in order
+    #[must_use]
+    pub fn strings(&self) -> Vec<String> {
+        (0..self.pods.len()).filter_map(|i| self.get(i)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "query")]
+    use crate::format_v3::{ColumnType, FormatV3Writer};
+    #[cfg(feature = "query")]
+    use crate::query_engine::QueryEngine;
+    #[cfg(feature = "query")]
+    use crate::CompressionLevel;
+    use crate::{ColumnarEncoder, PatternConfig};
+
+    const LOG: &str = "\
+2024-01-15T10:30:45.123456789Z stdout F I0115 10:30:45.123456   12345 controller.go:42] synced pod=web-7fb96c846b-x5n2k namespace=prod
+2024-01-15T10:30:45.2Z stderr P E0115 10:30:45.200000       7 main.go:9] restart containerd://0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef
+2024-01-15T10:30:46Z stdout F {\"pod\":\"web-7fb96c846b-q2w4z\",\"namespace\":\"kube-system\"}
+";
+
+    #[test]
+    fn test_kubernetes_columns_roundtrip() {
+        let encoder = ColumnarEncoder::with_config(PatternConfig::kubernetes()).unwrap();
+        let payload = encoder.encode(LOG);
+        let k8s = &payload.k8s;
+        assert_eq!(k8s.cri_prefixes.len(), 3);
+        assert_eq!(k8s.klog_headers.headers.len(), 2);
+        assert_eq!(k8s.container_ids.len(), 1);
+        assert_eq!(k8s.namespaces.names(), ["prod", "kube-system"]);
+        assert_eq!(
+            k8s.pods.strings(),
+            ["web-7fb96c846b-x5n2k", "web-7fb96c846b-q2w4z"]
+        );
+        // Replicas share one prefix entry
+        assert_eq!(k8s.pods.prefixes.entries, ["web-7fb96c846b"]);
+        assert_eq!(payload.restore(), LOG);
+
+        let v2 = crate::columnar_encoder::ColumnarPayload::from_v2_bytes(
+            &payload.to_v2_bytes().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(v2.restore(), LOG);
+
+        // Opt-in: the default config leaves pods to the generic patterns
+        assert!(ColumnarEncoder::new().encode(LOG).k8s.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "query")]
+    fn test_kubernetes_v3_query() {
+        let archive = FormatV3Writer::new(CompressionLevel::Balanced)
+            .with_pattern_config(PatternConfig::kubernetes())
+            .unwrap()
+            .compress(LOG)
+            .unwrap();
+        assert_eq!(FormatV3Writer::decompress(&archive).unwrap(), LOG);
+
+        let engine = QueryEngine::from_reader(archive.as_slice()).unwrap();
+        assert!(engine.has_column(ColumnType::K8sPods.name()));
+        assert_eq!(
+            engine.select_column("k8s_namespaces").unwrap(),
+            ["prod", "kube-system"]
+        );
+        assert_eq!(
+            engine
+                .matching_lines(
+                    "k8s_pods",
+                    crate::query_engine::Op::Eq,
+                    "web-7fb96c846b-q2w4z"
+                )
+                .unwrap(),
+            [2]
+        );
+    }
+
+    #[test]
+    fn test_inexact_values_rejected() {
+        assert!(CriColumn::default()
+            .add("2024-01-15T10:30:45.1234567891Z stdout F ")
+            .is_none());
+        assert!(KlogColumn::default()
+            .add("I0115 10:30:45.123456 012 a.go:1]")
+            .is_none());
+        assert!(PodColumn::default().add("web-abcde").is_none());
+        assert!(ContainerId::parse("docker://0123").is_none());
+    }
+}
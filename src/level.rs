@@ -0,0 +1,207 @@
+//! Compression level — one setting for the v2 and v3 writers
+//!
+//! [`Level`] is either a preset or an exact zstd level. [`TunedCompressor`]
+//! and [`FormatV3Writer`] both take it (as do `compress_tuned`,
+//! `compress_v3`, `compress_against` and the appenders), and the older
+//! [`CompressionMode`] / [`CompressionLevel`] convert into it, so existing
+//! call sites keep compiling. A preset means the same zstd level in both
+//! formats:
+//!
+//! | Level | zstd | v2 pipeline | v3 header |
+//! |-------|------|-------------|-----------|
+//! | `Fast` | 3 | columnar | `Fast` |
+//! | `Balanced` | 10 | columnar | `Balanced` |
+//! | `Best` | 19 | + arithmetic-coded entropy stage | `Best` |
+//! | `Max` | 22 | + context-mixing skeleton | `Best` |
+//! | `Zstd(n)` | `n` | columnar | `Balanced` |
+//!
+//! `CompressionMode` and `CompressionLevel` remain the tags stored in v2 and
+//! v3 headers. The exact level of a v3 archive is in its
+//! [`Producer`](crate::Producer) settings, which is what re-encoding (prune,
+//! compact, views) and [`FormatV3Editor`](crate::FormatV3Editor) reuse.
+//!
+//! ```rust,ignore
+//! use alice_text::{FormatV3Writer, Level, TunedCompressor};
+//!
+//! let v2 = TunedCompressor::new(Level::Zstd(15)).compress(&text)?;
+//! let v3 = FormatV3Writer::new("best".parse::<Level>()?).compress(&text)?;
+//! ```
+//!
+//! [`TunedCompressor`]: crate::TunedCompressor
+//! [`FormatV3Writer`]: crate::FormatV3Writer
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::format_v3::CompressionLevel;
+use crate::tuned_compressor::CompressionMode;
+use crate::ALICETextError;
+
+/// Compression level: a preset or an exact zstd level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Level {
+    Fast,
+    #[default]
+    Balanced,
+    Best,
+    /// `Best` plus context-mixing skeleton coding in v2 (slow; for cold
+    /// archives); zstd 22 in v3
+    Max,
+    /// Exact zstd level (clamped to the library's range), no extra stages
+    Zstd(i32),
+}
+
+impl Level {
+    /// Preset names accepted by [`Level::from_str`]
+    pub const NAMES: [&'static str; 4] = ["fast", "balanced", "best", "max"];
+
+    /// Zstd level the columns / payload are compressed with
+    #[must_use]
+    pub fn zstd_level(self) -> i32 {
+        match self {
+            Self::Fast => 3,
+            Self::Balanced => 10,
+            Self::Best => 19,
+            Self::Max => 22,
+            Self::Zstd(level) => {
+                let range = zstd::compression_level_range();
+                level.clamp(*range.start(), *range.end())
+            }
+        }
+    }
+
+    /// v2 pipeline (and header tag) for this level
+    #[must_use]
+    pub const fn mode(self) -> CompressionMode {
+        match self {
+            Self::Fast => CompressionMode::Fast,
+            Self::Balanced | Self::Zstd(_) => CompressionMode::Balanced,
+            Self::Best => CompressionMode::Best,
+            Self::Max => CompressionMode::Max,
+        }
+    }
+
+    /// v3 header tag for this level
+    #[must_use]
+    pub const fn v3_level(self) -> CompressionLevel {
+        match self {
+            Self::Fast => CompressionLevel::Fast,
+            Self::Balanced | Self::Zstd(_) => CompressionLevel::Balanced,
+            Self::Best | Self::Max => CompressionLevel::Best,
+        }
+    }
+}
+
+impl From<CompressionMode> for Level {
+    fn from(mode: CompressionMode) -> Self {
+        match mode {
+            CompressionMode::Fast => Self::Fast,
+            CompressionMode::Balanced => Self::Balanced,
+            CompressionMode::Best => Self::Best,
+            CompressionMode::Max => Self::Max,
+        }
+    }
+}
+
+impl From<CompressionLevel> for Level {
+    fn from(level: CompressionLevel) -> Self {
+        match level {
+            CompressionLevel::Fast => Self::Fast,
+            CompressionLevel::Balanced => Self::Balanced,
+            CompressionLevel::Best => Self::Best,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Zstd(level) => write!(f, "{level}"),
+            preset => f.write_str(Self::NAMES[preset.mode() as usize]),
+        }
+    }
+}
+
+/// A preset name (`fast`, `balanced`, `best`, `max`, any case) or a zstd
+/// level (`19`, `-5`)
+impl FromStr for Level {
+    type Err = ALICETextError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let preset = match s.to_lowercase().as_str() {
+            "fast" => Some(Self::Fast),
+            "balanced" => Some(Self::Balanced),
+            "best" => Some(Self::Best),
+            "max" => Some(Self::Max),
+            _ => None,
+        };
+        let range = zstd::compression_level_range();
+        preset
+            .or_else(|| {
+                s.parse()
+                    .ok()
+                    .filter(|level| range.contains(level))
+                    .map(Self::Zstd)
+            })
+            .ok_or_else(|| {
+                ALICETextError::EncodingError(format!(
+                    "Unknown level {s:?} (fast, balanced, best, max or a zstd level {}..={})",
+                    range.start(),
+                    range.end()
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress_v3, decompress_tuned, decompress_v3, FormatV3Metadata, TunedCompressor};
+
+    #[test]
+    fn test_parse_and_map() {
+        for name in Level::NAMES {
+            let level: Level = name.parse().unwrap();
+            assert_eq!(level.to_string(), name);
+        }
+        assert_eq!("BEST".parse::<Level>().unwrap(), Level::Best);
+        assert_eq!("7".parse::<Level>().unwrap(), Level::Zstd(7));
+        assert_eq!(Level::Zstd(7).to_string(), "7");
+        assert!("turbo".parse::<Level>().is_err());
+        assert!("99".parse::<Level>().is_err());
+        assert_eq!(
+            Level::Zstd(99).zstd_level(),
+            *zstd::compression_level_range().end()
+        );
+
+        // The old enums name the same presets and zstd levels
+        assert_eq!(Level::from(CompressionMode::Max), Level::Max);
+        assert_eq!(Level::from(CompressionLevel::Best).zstd_level(), 19);
+        assert_eq!(Level::from(CompressionMode::Best).zstd_level(), 19);
+        assert_eq!(Level::Max.v3_level(), CompressionLevel::Best);
+        assert_eq!(Level::Zstd(5).mode(), CompressionMode::Balanced);
+    }
+
+    #[test]
+    fn test_both_writers_take_exact_levels() {
+        let text = "2024-01-15 10:30:45 INFO request from 10.0.0.1 took 12ms\n".repeat(200);
+
+        let mut compressor = TunedCompressor::new(Level::Zstd(1));
+        let v2 = compressor.compress(&text).unwrap();
+        assert_eq!(compressor.level(), Level::Zstd(1));
+        assert_eq!(decompress_tuned(&v2).unwrap(), text);
+
+        let v3 = compress_v3(&text, Level::Zstd(1)).unwrap();
+        assert_eq!(decompress_v3(&v3).unwrap(), text);
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&v3)).unwrap();
+        assert_eq!(
+            metadata.header.compression_level,
+            CompressionLevel::Balanced as u8
+        );
+        assert_eq!(metadata.level(), Level::Zstd(1));
+        assert_eq!(metadata.producer().unwrap().settings["zstd_level"], "1");
+    }
+}
@@ -35,6 +35,16 @@
 //! let decompressed = alice.decompress(&compressed).unwrap();
 //! assert_eq!(text, decompressed);
 //! ```
+//!
+//! ## Thread safety
+//!
+//! [`ALICEText`], [`TunedCompressor`], [`FormatV3Writer`], `QueryEngine` and
+//! [`DialogueCompressor`] are `Send + Sync` (checked at compile time), and
+//! their compress / decompress / query methods take `&self`: share one
+//! instance behind an `Arc` instead of a `Mutex`. `compress(&mut self)` only
+//! exists to keep [`TunedCompressor::last_stats`]; from several threads use
+//! [`TunedCompressor::compress_with_stats`] /
+//! [`ALICEText::compress_with_stats`], which return the statistics instead.
 
 #![allow(
     clippy::cast_possible_truncation,
@@ -49,80 +59,222 @@
     clippy::too_many_lines
 )]
 
-// --- Global Allocator: mimalloc (Microsoft's high-performance allocator) ---
-#[cfg(not(target_env = "msvc"))]
-use mimalloc::MiMalloc;
-
-#[cfg(not(target_env = "msvc"))]
-#[global_allocator]
-static GLOBAL: MiMalloc = MiMalloc;
-
 pub mod arithmetic_coder;
+pub mod context_mixing;
 pub mod entropy_estimator;
 pub mod exception_decoder;
 pub mod exception_encoder;
 pub mod pattern_learner;
 
+// Non-overlapping match ranges shared by both pattern learners
+pub mod coverage;
+
+// Pluggable tokenization for token / exception statistics
+pub mod tokenizer;
+
+// Order-N token prediction for n-gram exception encoding
+pub mod ngram_model;
+
 // Tuned (optimized) modules
 pub mod columnar_encoder;
 pub mod tuned_compressor;
 pub mod tuned_pattern_learner;
 
+// Third-party value columns
+pub mod column_plugin;
+
+// Kubernetes / container log preset
+pub mod kubernetes;
+
 // Format v3 and Query Engine
+#[cfg(feature = "query")]
+pub mod analyze;
+#[cfg(feature = "query")]
+pub mod column_cache;
+pub mod column_codec;
+pub mod filter;
 pub mod format_v3;
+#[cfg(feature = "query")]
 pub mod query_engine;
+pub mod row_set;
+pub mod saved_view;
 
 // Game dialogue compression and localization
 pub mod dialogue;
 
+// Source classification (syslog / JSON / access log / prose) and profile choice
+pub mod classifier;
+
 // Unicode normalization
 pub mod unicode_norm;
 
-pub use arithmetic_coder::{ArithmeticDecoder, ArithmeticEncoder};
+// Differential compression against a reference snapshot
+pub mod delta;
+
+// Predicate-based row deletion (retention policies)
+pub mod retention;
+
+// Metrics hooks (emission behind the `metrics` feature)
+pub mod telemetry;
+
+// CRLF / UTF-16LE input normalization
+pub mod source_profile;
+
+// Per-line source provenance for merged archives
+pub mod provenance;
+
+// Write-time postings for external full-text engines (Tantivy adapter behind `tantivy`)
+pub mod index_sink;
+
+// Stable line ids kept across prune, compact, split and merge
+pub mod line_index;
+
+// Shareable trained model (.atxm sidecar)
+pub mod model;
+
+// Bounded multi-threaded block compression pipeline
+pub mod pool;
+
+// Archive compatibility policy and version gate
+pub mod stability;
+
+// Producing build and settings recorded in every v3 archive
+pub mod producer;
+
+// Atomic file output (temp file + fsync + rename)
+pub mod safe_write;
+
+// Block-flushed compression of unbounded input (stdin tail)
+pub mod stream;
+
+// Config file defaults (level, profile, patterns, redaction)
+#[cfg(feature = "config")]
+pub mod config;
+
+// Compression level shared by the v2 and v3 writers
+pub mod level;
+
+// Zstd long-distance matching, window and worker options
+pub mod zstd_params;
+
+pub use arithmetic_coder::{AdaptiveModel, ArithmeticDecoder, ArithmeticEncoder, EscapeModel};
+pub use column_plugin::{ColumnPlugin, PluginColumn, PluginSchema, MAX_PLUGINS};
+pub use context_mixing::ContextMixer;
 pub use entropy_estimator::{EntropyEstimate, EntropyEstimator};
 pub use exception_decoder::ExceptionDecoder;
 pub use exception_encoder::{EncodedText, EncodingMode, ExceptionEncoder, ExceptionHeader};
+pub use kubernetes::{
+    ContainerId, CriColumn, KlogColumn, KlogHeader, KubernetesColumns, NamespaceColumn, PodColumn,
+    KUBERNETES_PATTERNS,
+};
+pub use ngram_model::{NGramEncoding, NGramModel, DEFAULT_NGRAM_ORDER, MAX_NGRAM_ORDER};
 pub use pattern_learner::{
     LearnedPattern, PatternDatabase, PatternLearner, PatternMatch, PatternType,
 };
+pub use tokenizer::{BytePairTokenizer, Tokenizer, UnicodeWordTokenizer, WhitespaceTokenizer};
 
 // Tuned (optimized) exports
-pub use columnar_encoder::{ColumnarEncoder, ColumnarPayload, LogLevel, TimestampColumn};
+pub use columnar_encoder::{
+    ByteSizeColumn, ColumnarEncoder, ColumnarPayload, DurationColumn, GeoPoint, HostColumn,
+    LogLevel, NumericTimeColumn, TimestampColumn, HTTP_METHODS,
+};
 pub use tuned_compressor::{
-    compress_tuned, decompress_tuned, CompressionMode, TunedCompressor, TunedHeader, TunedStats,
-    TUNED_VERSION,
+    compress_tuned, decompress_tuned, BlockRoute, BlockStrategy, CompressionMode, LineBatch,
+    RoutingStats, TunedCompressor, TunedHeader, TunedStats, DEFAULT_ROUTING_BLOCK,
+    LINE_DICTIONARY_MAX, STORED_THRESHOLD, TUNED_VERSION,
 };
 pub use tuned_pattern_learner::{
-    OwnedMatch, PatternType as TunedPatternType, TunedMatch, TunedPatternLearner,
+    strip_ansi, OwnedMatch, PatternConfig, PatternExclusion, PatternGuard, PatternLimits,
+    PatternType as TunedPatternType, TunedMatch, TunedPatternLearner, DEFAULT_MAX_LINE_LEN,
 };
 
 // Format v3 and Query Engine exports
+#[cfg(feature = "query")]
+pub use analyze::{AnalyzeReport, ColumnReport, Suggestion};
+#[cfg(feature = "query")]
+pub use column_cache::{CacheKey, ColumnCache, CACHE_EXT, DEFAULT_CACHE_SIZE};
+pub use column_codec::ColumnEncoding;
+pub use filter::{parse_filter, parse_negation, parse_relative_time, Op};
 pub use format_v3::{
-    ColumnEntry, ColumnType, CompressionLevel, FormatV3Header, FormatV3Metadata, FormatV3Writer,
-    PartialPayload, FORMAT_V3_VERSION,
+    compress_v3, decompress_v3, ColumnEntry, ColumnSummary, ColumnType, CompressionLevel,
+    DecodeLimits, DecodeOptions, DecodeProfile, DroppedColumns, FormatV3Editor, FormatV3Header,
+    FormatV3Metadata, FormatV3Writer, HeaderExtension, PartialPayload, RowMap, SchemaDiff,
+    TypeDifference, FORMAT_V3_VERSION,
 };
+#[cfg(feature = "mmap")]
+pub use query_engine::MmapSource;
+#[cfg(feature = "query")]
 pub use query_engine::{
-    compress_v3, decompress_v3, BufferSource, ColumnStats, FileStats, MmapSource, Op, QueryBuilder,
-    QueryEngine, QueryResult, QueryRow, QuerySource,
+    BufferSource, ColumnStats, FileStats, LineMatch, QueryBuilder, QueryEngine, QueryResult,
+    QueryRow, QuerySource, SamplingStrategy, SliceSource,
+};
+pub use row_set::RowSet;
+pub use saved_view::{embed_views, embed_views_with_plugins, SavedView, VIEWS_EXT};
+
+pub use delta::{
+    compress_against, compress_against_with_stats, decompress_with_reference,
+    decompress_with_reference_limited, resolve_reference, DeltaStats, DELTA_MAGIC, DELTA_VERSION,
 };
 
+pub use retention::{delete_rows, delete_rows_with_plugins, RetentionStats, RowView};
+
+pub use provenance::{LineOrigin, ProvenanceColumn, SourceBatch};
+
+#[cfg(feature = "tantivy")]
+pub use index_sink::TantivySink;
+pub use index_sink::{index_tokens, IndexSink, IndexedWriter};
+
+pub use line_index::{
+    merge_archives, merge_archives_with_plugins, split_archive, split_archive_with_plugins,
+    LineIdColumn,
+};
+
+pub use model::{TextModel, MODEL_EXT, MODEL_MAGIC, MODEL_VERSION};
+
+pub use pool::{CompressedBlock, CompressorPool, PoolConfig, PoolSender};
+
+pub use producer::{pattern_set_hash, Producer};
+
+pub use stability::{archive_version, decompress_any, READABLE_MAJORS, WRITTEN_VERSIONS};
+
+pub use safe_write::{write_atomic, SafeFileWriter};
+
+pub use stream::{decompress_stream, FlushPolicy, StreamCompressor, STREAM_MAGIC};
+
+#[cfg(feature = "config")]
+pub use config::{Config, ConfigLoader, Profile, RedactionPolicy, CONFIG_FILE};
+
+pub use level::Level;
+
+pub use zstd_params::ZstdParams;
+
+pub use classifier::{classify, Classification, SourceKind};
+pub use source_profile::{RawSegment, SourceProfile, RAW_PLACEHOLDER, UTF16LE_BOM};
+
 pub use unicode_norm::{
     is_ascii_only, is_combining, is_normalized, strip_accents, to_nfc, to_nfd, NormForm,
 };
 
 pub use dialogue::{
     DeltaTable, DialogueCompressionMode, DialogueCompressor, DialogueEntry, DialogueTable,
-    LocaleId, LocalizationTable, RubyAnnotation, SpeakerDictionary,
+    DialogueTableBuilder, DialogueViolation, DuplicatePolicy, LocaleId, LocalizationTable,
+    MarkupSegment, MarkupTag, RubyAnnotation, SearchHit, SearchQuery, SegmentedText,
+    SpeakerDictionary, TableDiff, TagStyle,
 };
 
+use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
+use std::sync::Arc;
 use thiserror::Error;
 
 /// ALICE-Text magic bytes
 pub const ALICE_TEXT_MAGIC: &[u8; 8] = b"ALICETXT";
 
-/// ALICE-Text version
-pub const ALICE_TEXT_VERSION: (u8, u8) = (1, 0);
+/// ALICE-Text version (v1 format)
+///
+/// 1.1 added the n-gram payload (`EncodingMode::NGram`); readers refuse a
+/// minor or payload layout newer than their own.
+pub const ALICE_TEXT_VERSION: (u8, u8) = (1, 1);
 
 /// ALICE-Text fingerprint
 pub const ALICE_TEXT_FINGERPRINT: &str = "ALICE-TXT-v1.0";
@@ -147,12 +299,156 @@ pub enum ALICETextError {
 
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
+
+    #[error("Roundtrip verification failed: {0}")]
+    VerificationFailed(String),
+
+    #[error("Limit exceeded: {field} is {value}, at most {limit} allowed")]
+    LimitExceeded {
+        field: &'static str,
+        value: u64,
+        limit: u64,
+    },
+
+    #[error("Invalid dialogue table: {0}")]
+    InvalidDialogue(#[from] dialogue::DialogueViolation),
+
+    /// A query named an unknown column or view, or a filter value or
+    /// timestamp did not parse (the caller's input, not the archive, is wrong)
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
 }
 
 pub type Result<T> = std::result::Result<T, ALICETextError>;
 
+/// Narrow a length or count to a `u32` format field (error instead of truncation)
+pub(crate) fn to_u32(value: usize, field: &'static str) -> Result<u32> {
+    u32::try_from(value).map_err(|_| ALICETextError::LimitExceeded {
+        field,
+        value: value as u64,
+        limit: u32::MAX.into(),
+    })
+}
+
+/// Narrow a length or count to a `u16` format field (error instead of truncation)
+pub(crate) fn to_u16(value: usize, field: &'static str) -> Result<u16> {
+    u16::try_from(value).map_err(|_| ALICETextError::LimitExceeded {
+        field,
+        value: value as u64,
+        limit: u16::MAX.into(),
+    })
+}
+
+/// Options shared by `TunedCompressor` (v2) and `FormatV3Writer` (v3)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompressOptions {
+    /// Decode the output right after encoding and fail instead of returning
+    /// bytes that do not reproduce the input (recommended for archival)
+    pub verify_roundtrip: bool,
+    /// Remove ANSI escape sequences before encoding (lossy: the archive
+    /// decodes to the uncolored text). By default they are kept in their
+    /// own column and restored exactly.
+    pub strip_ansi: bool,
+    /// Lines that skip pattern extraction (very long or binary lines)
+    pub pattern_limits: tuned_pattern_learner::PatternLimits,
+    /// Long-distance matching, window size and workers for the Zstd stage
+    pub zstd: zstd_params::ZstdParams,
+}
+
+impl CompressOptions {
+    /// Default options (no verification)
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            verify_roundtrip: false,
+            strip_ansi: false,
+            pattern_limits: tuned_pattern_learner::PatternLimits::new(),
+            zstd: zstd_params::ZstdParams::new(),
+        }
+    }
+
+    /// Enable or disable roundtrip verification
+    #[must_use]
+    pub const fn with_verify_roundtrip(mut self, verify: bool) -> Self {
+        self.verify_roundtrip = verify;
+        self
+    }
+
+    /// Enable or disable ANSI escape stripping
+    #[must_use]
+    pub const fn with_strip_ansi(mut self, strip: bool) -> Self {
+        self.strip_ansi = strip;
+        self
+    }
+
+    /// Set the pathological-input limits for pattern extraction
+    #[must_use]
+    pub const fn with_pattern_limits(
+        mut self,
+        pattern_limits: tuned_pattern_learner::PatternLimits,
+    ) -> Self {
+        self.pattern_limits = pattern_limits;
+        self
+    }
+
+    /// Set the Zstd long-distance matching, window and worker parameters
+    #[must_use]
+    pub const fn with_zstd(mut self, zstd: zstd_params::ZstdParams) -> Self {
+        self.zstd = zstd;
+        self
+    }
+
+    /// Input text after the lossy options are applied
+    pub(crate) fn prepare<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        if self.strip_ansi {
+            tuned_pattern_learner::strip_ansi(text)
+        } else {
+            std::borrow::Cow::Borrowed(text)
+        }
+    }
+}
+
+/// FNV-1a 64 offset basis (the seed of a fresh [`fnv1a`] hash)
+pub(crate) const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a 64 of `data`, continuing from `h` ([`FNV_OFFSET`] for a fresh hash)
+///
+/// The one hasher behind every checksum and fingerprint the formats store.
+#[inline(always)]
+pub(crate) fn fnv1a(data: &[u8], mut h: u64) -> u64 {
+    for &b in data {
+        h ^= u64::from(b);
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+/// Compile-time check that `T` is `Send + Sync` (`const _: () = assert_send_sync::<T>();`)
+pub(crate) const fn assert_send_sync<T: Send + Sync>() {}
+
+/// Compare decoded output against the original (length + FNV-1a)
+pub(crate) fn verify_roundtrip(original: &str, decoded: &str) -> Result<()> {
+    if original.len() != decoded.len() {
+        return Err(ALICETextError::VerificationFailed(format!(
+            "length mismatch: {} bytes in, {} bytes decoded",
+            original.len(),
+            decoded.len()
+        )));
+    }
+    let (expected, actual) = (
+        fnv1a(original.as_bytes(), FNV_OFFSET),
+        fnv1a(decoded.as_bytes(), FNV_OFFSET),
+    );
+    if expected != actual {
+        return Err(ALICETextError::VerificationFailed(format!(
+            "hash mismatch: {expected:016x} != {actual:016x}"
+        )));
+    }
+    Ok(())
+}
+
 /// Compression statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionStats {
     pub original_size: usize,
     pub compressed_size: usize,
@@ -191,9 +487,12 @@ impl CompressionStats {
 pub struct ALICEText {
     tuned: TunedCompressor,
     legacy_decoder: ExceptionDecoder,
+    tokenizer: Arc<dyn Tokenizer>,
     last_stats: Option<CompressionStats>,
 }
 
+const _: () = assert_send_sync::<ALICEText>();
+
 impl ALICEText {
     /// Create a new ALICE-Text instance
     #[must_use]
@@ -201,32 +500,60 @@ impl ALICEText {
         Self {
             tuned: TunedCompressor::default_balanced(),
             legacy_decoder: ExceptionDecoder::new(),
+            tokenizer: Arc::new(WhitespaceTokenizer),
             last_stats: None,
         }
     }
 
+    /// Set the output ceilings for decoding v1 and v2 archives
+    /// (default: [`DecodeLimits::new`])
+    #[must_use]
+    pub fn with_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.tuned = self.tuned.with_decode_limits(limits);
+        self.legacy_decoder = self.legacy_decoder.with_decode_limits(limits);
+        self
+    }
+
+    /// Count `token_count` in stats with `tokenizer` (default: whitespace)
+    #[must_use]
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
     /// Compress text to bytes (uses `TunedCompressor` v2)
     ///
     /// # Errors
     ///
     /// Returns an error if the underlying compression fails.
     pub fn compress(&mut self, text: &str) -> Result<Vec<u8>> {
-        let compressed = self.tuned.compress(text)?;
-
-        // Update stats from TunedCompressor
-        if let Some(stats) = self.tuned.last_stats() {
-            self.last_stats = Some(CompressionStats {
-                original_size: stats.original_size,
-                compressed_size: stats.compressed_size,
-                token_count: stats.pattern_count,
-                exception_count: 0, // Not tracked in tuned mode
-                pattern_count: stats.pattern_count,
-            });
-        }
-
+        let (compressed, stats) = self.compress_with_stats(text)?;
+        self.last_stats = Some(stats);
         Ok(compressed)
     }
 
+    /// Compress text to bytes, returning the statistics instead of keeping
+    /// them for [`Self::last_stats`]
+    ///
+    /// Takes `&self`, so one instance can serve several threads (e.g. behind
+    /// an `Arc`) without a lock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying compression fails.
+    pub fn compress_with_stats(&self, text: &str) -> Result<(Vec<u8>, CompressionStats)> {
+        let (compressed, stats) = self.tuned.compress_with_stats(text)?;
+        let stats = CompressionStats {
+            original_size: stats.original_size,
+            compressed_size: stats.compressed_size,
+            token_count: self.tokenizer.count(text),
+            // Values moved into columns are the tuned pipeline's exceptions
+            exception_count: stats.pattern_count,
+            pattern_count: stats.pattern_count,
+        };
+        Ok((compressed, stats))
+    }
+
     /// Decompress bytes to text (auto-detects v1 or v2 format)
     ///
     /// # Errors
@@ -313,6 +640,12 @@ pub fn decompress(data: &[u8]) -> Result<String> {
     alice.decompress(data)
 }
 
+#[cfg(feature = "appender")]
+pub mod appender;
+
+#[cfg(feature = "bus")]
+pub mod bus;
+
 #[cfg(feature = "ffi")]
 pub mod ffi;
 
@@ -328,6 +661,9 @@ pub mod voice_bridge;
 #[cfg(feature = "search")]
 pub mod search_bridge;
 
+#[cfg(feature = "tui")]
+pub mod viewer;
+
 #[cfg(feature = "python")]
 mod python_bindings {
     use super::*;
@@ -515,7 +851,52 @@ mod tests {
     #[test]
     fn test_magic_and_version_constants() {
         assert_eq!(ALICE_TEXT_MAGIC, b"ALICETXT");
-        assert_eq!(ALICE_TEXT_VERSION, (1, 0));
+        assert_eq!(ALICE_TEXT_VERSION, (1, 1));
         assert_eq!(ALICE_TEXT_FINGERPRINT, "ALICE-TXT-v1.0");
     }
+
+    #[test]
+    fn test_verify_roundtrip_helper() {
+        assert!(verify_roundtrip("abc", "abc").is_ok());
+        assert!(matches!(
+            verify_roundtrip("abc", "abd"),
+            Err(ALICETextError::VerificationFailed(_))
+        ));
+        assert!(matches!(
+            verify_roundtrip("abc", "ab"),
+            Err(ALICETextError::VerificationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_compress_options_verify() {
+        let options = CompressOptions::new().with_verify_roundtrip(true);
+        let text = "2024-01-15 10:30:45 INFO User logged in from 192.168.1.100\n".repeat(10);
+
+        let mut tuned = TunedCompressor::default().with_options(options);
+        let compressed = tuned.compress(&text).unwrap();
+        assert_eq!(tuned.decompress(&compressed).unwrap(), text);
+
+        let writer = FormatV3Writer::new(CompressionLevel::Fast).with_options(options);
+        let compressed = writer.compress(&text).unwrap();
+        assert_eq!(decompress_v3(&compressed).unwrap(), text);
+    }
+
+    #[test]
+    fn test_compress_options_verify_accepts_raw_fallbacks() {
+        // Zero-padded octets, padded numbers and upper-case UUIDs keep their
+        // spelling next to the typed value instead of being normalised
+        let text =
+            "connect from 010.001.001.001 port 0080 id 550E8400-E29B-41D4-A716-446655440000\n"
+                .repeat(5);
+        let options = CompressOptions::new().with_verify_roundtrip(true);
+
+        let mut tuned = TunedCompressor::default().with_options(options);
+        let compressed = tuned.compress(&text).unwrap();
+        assert_eq!(tuned.decompress(&compressed).unwrap(), text);
+
+        let writer = FormatV3Writer::new(CompressionLevel::Fast).with_options(options);
+        let compressed = writer.compress(&text).unwrap();
+        assert_eq!(decompress_v3(&compressed).unwrap(), text);
+    }
 }
@@ -0,0 +1,485 @@
+//! Line ids — stable references to archive lines across prune, split and merge
+//!
+//! Line positions shift whenever an archive is reshaped. An archive written
+//! with [`FormatV3Writer::with_line_ids`] also numbers its lines with
+//! strictly increasing `u64` ids in the optional `line_ids` column, and the
+//! archive operations keep them:
+//!
+//! - [`delete_rows`](crate::delete_rows) keeps the ids of the surviving lines
+//! - `FormatV3Editor::compact` carries them over unchanged
+//! - [`split_archive`] hands each part the ids of its lines
+//! - [`merge_archives`] concatenates them (parts must not overlap)
+//!
+//! An external system can store "archive X, line 1042" and retrieve that
+//! exact line later with [`QueryEngine::line_by_id`] or the query filter
+//! `line_ids=1042`, whichever archive the line has moved to.
+//!
+//! ```rust,ignore
+//! use alice_text::{line_index, CompressionLevel, FormatV3Writer, QueryEngine};
+//!
+//! let archive = FormatV3Writer::new(CompressionLevel::Balanced)
+//!     .with_line_ids(0)
+//!     .compress(&log)?;
+//! let parts = line_index::split_archive(&archive, 10_000)?;
+//! let engine = QueryEngine::from_reader(std::io::Cursor::new(&parts[1]))?;
+//! assert!(engine.line_by_id(10_000)?.is_some());
+//! ```
+//!
+//! [`QueryEngine::line_by_id`]: crate::QueryEngine::line_by_id
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::io::Cursor;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::column_plugin::ColumnPlugin;
+use crate::format_v3::{FormatV3Metadata, FormatV3Writer};
+use crate::provenance::{LineOrigin, ProvenanceColumn};
+use crate::source_profile::{RawSegment, SourceProfile, RAW_PLACEHOLDER};
+use crate::{ALICETextError, Result};
+
+/// One strictly increasing id per archive line
+///
+/// Stored as deltas (first id, then gaps), so sequential ids compress to
+/// almost nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineIdColumn {
+    ids: Vec<u64>,
+}
+
+impl LineIdColumn {
+    /// `count` consecutive ids starting at `first`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the last id would not fit in a `u64`.
+    pub fn sequential(first: u64, count: usize) -> Result<Self> {
+        let limit = u64::MAX - count as u64;
+        if first > limit {
+            return Err(ALICETextError::LimitExceeded {
+                field: "first line id",
+                value: first,
+                limit,
+            });
+        }
+        Ok(Self {
+            ids: (first..first + count as u64).collect(),
+        })
+    }
+
+    /// Column from explicit ids, one per line
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ids are not strictly increasing.
+    pub fn from_ids(ids: Vec<u64>) -> Result<Self> {
+        if let Some(pair) = ids.windows(2).find(|pair| pair[0] >= pair[1]) {
+            return Err(ALICETextError::EncodingError(format!(
+                "Line ids must increase: {} followed by {}",
+                pair[0], pair[1]
+            )));
+        }
+        Ok(Self { ids })
+    }
+
+    /// Inverse of [`Self::deltas`]
+    pub(crate) fn from_deltas(deltas: &[u64]) -> Result<Self> {
+        let mut ids = Vec::with_capacity(deltas.len());
+        let mut previous: Option<u64> = None;
+        for &delta in deltas {
+            let id = match previous {
+                None => Some(delta),
+                Some(_) if delta == 0 => None,
+                Some(prev) => prev.checked_add(delta),
+            }
+            .ok_or_else(|| {
+                ALICETextError::DecompressionError(
+                    "Line id column is not strictly increasing".to_string(),
+                )
+            })?;
+            ids.push(id);
+            previous = Some(id);
+        }
+        Ok(Self { ids })
+    }
+
+    /// First id, then the gap to each following id
+    pub(crate) fn deltas(&self) -> Vec<u64> {
+        let mut previous = 0;
+        self.ids
+            .iter()
+            .map(|&id| {
+                let delta = id - previous;
+                previous = id;
+                delta
+            })
+            .collect()
+    }
+
+    /// Ids in line order
+    #[inline]
+    #[must_use]
+    pub fn ids(&self) -> &[u64] {
+        &self.ids
+    }
+
+    /// Number of lines covered
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Id of archive line `line`
+    #[must_use]
+    pub fn get(&self, line: usize) -> Option<u64> {
+        self.ids.get(line).copied()
+    }
+
+    /// Archive line holding `id`
+    #[must_use]
+    pub fn position(&self, id: u64) -> Option<usize> {
+        self.ids.binary_search(&id).ok()
+    }
+
+    /// Keep the lines for which `keep(line_index)` holds
+    #[must_use]
+    pub fn retain_lines(&self, keep: impl Fn(usize) -> bool) -> Self {
+        Self {
+            ids: self
+                .ids
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| keep(*i))
+                .map(|(_, &id)| id)
+                .collect(),
+        }
+    }
+}
+
+/// A decoded archive, ready to be cut at line boundaries or joined
+struct Lines<'p> {
+    metadata: FormatV3Metadata,
+    plugins: &'p [Arc<dyn ColumnPlugin>],
+    profile: SourceProfile,
+    text: String,
+    raw: Vec<RawSegment>,
+    ids: Option<LineIdColumn>,
+    provenance: Option<ProvenanceColumn>,
+}
+
+impl<'p> Lines<'p> {
+    fn read(data: &[u8], plugins: &'p [Arc<dyn ColumnPlugin>]) -> Result<Self> {
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(data))?;
+        let (text, profile, raw) = FormatV3Writer::decode_normalized(data, plugins)?;
+        Ok(Self {
+            metadata,
+            plugins,
+            profile,
+            text,
+            raw,
+            ids: FormatV3Writer::read_line_ids(data)?,
+            provenance: FormatV3Writer::read_provenance(data)?,
+        })
+    }
+
+    fn encode(&self, text: &str, raw: &[RawSegment], lines: Range<usize>) -> Result<Vec<u8>> {
+        let in_range = |i: usize| lines.contains(&i);
+        let ids = self.ids.as_ref().map(|ids| ids.retain_lines(in_range));
+        let provenance = self.provenance.as_ref().map(|p| p.retain_lines(in_range));
+        FormatV3Writer::matching(&self.metadata, self.plugins)?.compress_normalized(
+            text,
+            self.profile,
+            raw,
+            provenance.as_ref(),
+            ids.as_ref(),
+        )
+    }
+}
+
+/// Split a v3 archive into archives of at most `max_lines` lines each
+///
+/// Every part keeps the compression level, pattern configuration, header
+/// extensions, line ids and provenance of `data`; [`merge_archives`] on the
+/// parts restores the original text and ids. An archive without lines comes
+/// back as a single part.
+///
+/// # Errors
+///
+/// Returns an error if `max_lines` is zero, `data` is not a valid v3 archive
+/// (or needs column plugins) or re-encoding fails.
+pub fn split_archive(data: &[u8], max_lines: usize) -> Result<Vec<Vec<u8>>> {
+    split_archive_with_plugins(data, max_lines, &[])
+}
+
+/// [`split_archive`] for an archive written with column plugins; every part
+/// is encoded with the same `plugins`
+///
+/// # Errors
+///
+/// Same as [`split_archive`], or `data` needs a plugin not among `plugins`.
+pub fn split_archive_with_plugins(
+    data: &[u8],
+    max_lines: usize,
+    plugins: &[Arc<dyn ColumnPlugin>],
+) -> Result<Vec<Vec<u8>>> {
+    if max_lines == 0 {
+        return Err(ALICETextError::EncodingError(
+            "Split size must be at least one line".to_string(),
+        ));
+    }
+    let source = Lines::read(data, plugins)?;
+    let lines: Vec<&str> = source.text.split_inclusive('\n').collect();
+    if lines.is_empty() {
+        return Ok(vec![data.to_vec()]);
+    }
+
+    let mut parts = Vec::with_capacity(lines.len().div_ceil(max_lines));
+    let mut raw = source.raw.iter().peekable();
+    let mut ordinal = 0u64;
+    for (chunk_index, chunk) in lines.chunks(max_lines).enumerate() {
+        let text = chunk.concat();
+        // Invalid UTF-8 runs follow their placeholders; renumber them per part
+        let placeholders = text.matches(RAW_PLACEHOLDER).count() as u64;
+        let part_raw: Vec<RawSegment> =
+            std::iter::from_fn(|| raw.next_if(|s| s.ordinal < ordinal + placeholders))
+                .map(|s| RawSegment {
+                    ordinal: s.ordinal - ordinal,
+                    bytes: s.bytes.clone(),
+                })
+                .collect();
+        ordinal += placeholders;
+
+        let start = chunk_index * max_lines;
+        parts.push(source.encode(&text, &part_raw, start..start + chunk.len())?);
+    }
+    Ok(parts)
+}
+
+/// Join v3 archives, in order, into one
+///
+/// The result takes its compression level, pattern configuration and header
+/// extensions from the first part. A part that does not end in `\n` gets one
+/// before the next part starts. Line ids and provenance are kept when every
+/// part has them; ids must keep increasing from one part to the next.
+///
+/// # Errors
+///
+/// Returns an error if `parts` is empty, a part is not a valid v3 archive,
+/// the parts mix source profiles (CRLF, UTF-16), only some parts have line
+/// ids or provenance, the line ids overlap, or re-encoding fails.
+pub fn merge_archives(parts: &[&[u8]]) -> Result<Vec<u8>> {
+    merge_archives_with_plugins(parts, &[])
+}
+
+/// [`merge_archives`] for archives written with column plugins
+///
+/// # Errors
+///
+/// Same as [`merge_archives`], or a part needs a plugin not among `plugins`.
+pub fn merge_archives_with_plugins(
+    parts: &[&[u8]],
+    plugins: &[Arc<dyn ColumnPlugin>],
+) -> Result<Vec<u8>> {
+    let mut decoded = parts.iter().map(|part| Lines::read(part, plugins));
+    let mut merged = decoded
+        .next()
+        .ok_or_else(|| ALICETextError::EncodingError("No archives to merge".to_string()))??;
+
+    for (index, part) in decoded.enumerate() {
+        let part = part?;
+        let index = index + 1;
+        if part.profile != merged.profile {
+            return Err(ALICETextError::EncodingError(format!(
+                "Archive {index} has a different source profile than archive 0"
+            )));
+        }
+        if !merged.text.is_empty() && !merged.text.ends_with('\n') {
+            merged.text.push('\n');
+        }
+
+        let ordinal = merged.text.matches(RAW_PLACEHOLDER).count() as u64;
+        merged.raw.extend(part.raw.into_iter().map(|s| RawSegment {
+            ordinal: s.ordinal + ordinal,
+            ..s
+        }));
+        merged.text.push_str(&part.text);
+
+        merged.ids = match (merged.ids.take(), part.ids) {
+            (None, None) => None,
+            (Some(mut ids), Some(next)) => {
+                if let (Some(&last), Some(&first)) = (ids.ids.last(), next.ids.first()) {
+                    if first <= last {
+                        return Err(ALICETextError::EncodingError(format!(
+                            "Line ids overlap: archive {index} starts at {first}, \
+                             the archives before it end at {last}"
+                        )));
+                    }
+                }
+                ids.ids.extend(next.ids);
+                Some(ids)
+            }
+            _ => return Err(mixed(index, "line ids")),
+        };
+
+        merged.provenance = match (merged.provenance.take(), part.provenance) {
+            (None, None) => None,
+            (Some(mut provenance), Some(next)) => {
+                // Re-key origins against the merged source dictionary
+                for (source, origin) in (0..next.len()).filter_map(|i| next.get(i)) {
+                    let source = provenance.source_id(source);
+                    provenance.lines.push(LineOrigin { source, ..origin });
+                }
+                Some(provenance)
+            }
+            _ => return Err(mixed(index, "provenance")),
+        };
+    }
+
+    let line_count = merged.text.lines().count();
+    merged.encode(&merged.text, &merged.raw, 0..line_count)
+}
+
+fn mixed(index: usize, what: &str) -> ALICETextError {
+    ALICETextError::EncodingError(format!(
+        "Archive {index} disagrees with the archives before it on having {what}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "query")]
+    use crate::retention::delete_rows;
+    #[cfg(feature = "query")]
+    use crate::{decompress_v3, BufferSource, FormatV3Editor, Op, QueryEngine};
+    use crate::{CompressionLevel, SourceBatch};
+
+    #[cfg(feature = "query")]
+    fn log(lines: usize) -> String {
+        (0..lines)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:30:{:02} INFO request {i} from 10.0.0.{i}\n",
+                    i % 60
+                )
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "query")]
+    fn engine(data: &[u8]) -> QueryEngine<BufferSource> {
+        QueryEngine::from_reader(std::io::Cursor::new(data.to_vec())).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "query")]
+    fn test_line_ids_survive_prune_split_merge() {
+        let text = log(10);
+        let archive = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_line_ids(100)
+            .compress(&text)
+            .unwrap();
+        let line = |i: usize| text.lines().nth(i).unwrap().to_string();
+        assert_eq!(engine(&archive).line_by_id(104).unwrap(), Some(line(4)));
+        assert_eq!(engine(&archive).line_by_id(99).unwrap(), None);
+
+        // Pruning shifts positions, not ids
+        let (pruned, _) = delete_rows(&archive, |row| row.line_number() < 3).unwrap();
+        let pruned_engine = engine(&pruned);
+        assert_eq!(pruned_engine.line_by_id(104).unwrap(), Some(line(4)));
+        assert_eq!(pruned_engine.line_by_id(101).unwrap(), None);
+        assert_eq!(
+            pruned_engine
+                .matching_lines("line_ids", Op::Eq, "104")
+                .unwrap(),
+            [1]
+        );
+
+        let compacted = FormatV3Editor::new(pruned.clone())
+            .unwrap()
+            .compact()
+            .unwrap();
+        assert_eq!(
+            FormatV3Writer::read_line_ids(&compacted).unwrap(),
+            FormatV3Writer::read_line_ids(&pruned).unwrap()
+        );
+
+        // Split: each part answers for its own ids
+        let parts = split_archive(&pruned, 3).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(engine(&parts[0]).line_by_id(108).unwrap(), None);
+        assert_eq!(engine(&parts[1]).line_by_id(108).unwrap(), Some(line(8)));
+        assert_eq!(
+            FormatV3Writer::read_line_ids(&parts[2])
+                .unwrap()
+                .unwrap()
+                .ids(),
+            [109]
+        );
+
+        // Merge restores text and ids
+        let refs: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        let merged = merge_archives(&refs).unwrap();
+        assert_eq!(
+            decompress_v3(&merged).unwrap(),
+            decompress_v3(&pruned).unwrap()
+        );
+        assert_eq!(
+            FormatV3Writer::read_line_ids(&merged).unwrap(),
+            FormatV3Writer::read_line_ids(&pruned).unwrap()
+        );
+
+        // Out of order or duplicated parts would make ids ambiguous
+        assert!(merge_archives(&[&parts[1], &parts[0]]).is_err());
+        assert!(merge_archives(&[&parts[0], &parts[0]]).is_err());
+        let plain = crate::compress_v3(&text, CompressionLevel::Fast).unwrap();
+        assert!(merge_archives(&[&parts[0], &plain]).is_err());
+    }
+
+    #[test]
+    fn test_split_merge_keeps_provenance_and_raw_bytes() {
+        let mut batch = SourceBatch::new();
+        batch.add("a.log", "a1\na2\n");
+        batch.add("b.log", "b1\n");
+        let archive = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress_batch(&batch)
+            .unwrap();
+        let parts = split_archive(&archive, 2).unwrap();
+        let provenance = FormatV3Writer::read_provenance(&parts[1]).unwrap().unwrap();
+        assert_eq!(provenance.describe(0).as_deref(), Some("b.log:1"));
+        let merged = merge_archives(&[&parts[1], &parts[0]]).unwrap();
+        let provenance = FormatV3Writer::read_provenance(&merged).unwrap().unwrap();
+        // Parts keep the whole source dictionary, so ids need no re-keying here
+        assert_eq!(provenance.sources, ["a.log", "b.log"]);
+        assert_eq!(provenance.describe(0).as_deref(), Some("b.log:1"));
+        assert_eq!(provenance.describe(2).as_deref(), Some("a.log:2"));
+
+        let input = b"one \xFF\ntwo\nthree \xFE\xFE\n";
+        let archive = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress_bytes(input)
+            .unwrap();
+        let parts = split_archive(&archive, 2).unwrap();
+        assert_eq!(
+            FormatV3Writer::decompress_bytes(&parts[1]).unwrap(),
+            b"three \xFE\xFE\n"
+        );
+        let refs: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        assert_eq!(
+            FormatV3Writer::decompress_bytes(&merge_archives(&refs).unwrap()).unwrap(),
+            input
+        );
+
+        assert!(split_archive(&archive, 0).is_err());
+        assert!(LineIdColumn::sequential(u64::MAX, 2).is_err());
+        assert!(LineIdColumn::from_ids(vec![3, 3]).is_err());
+    }
+}
@@ -2,9 +2,19 @@
 //!
 //! Ternary neural inference for next-token prediction in text compression.
 //! Uses 1.58-bit weights for ultra-fast context → prediction mapping.
+//!
+//! Also exports v3 archives as training data ([`TrainingExport`]): one
+//! `(template_id, values)` pair per line, or fixed-size token sequences,
+//! streamed to a callback in batches.
+
+use std::collections::HashMap;
 
 use alice_ml::{ternary_matvec, TernaryWeight};
 
+use crate::columnar_encoder::{ColumnarPayload, LinePiece};
+use crate::format_v3::{ColumnType, FormatV3Writer};
+use crate::Result;
+
 /// ML-accelerated text predictor.
 ///
 /// Uses ternary weights to predict the next byte/token distribution
@@ -81,6 +91,229 @@ impl TextPredictor {
     }
 }
 
+/// Padding token (fills the last sequence).
+pub const TOKEN_PAD: u32 = 256;
+/// Ends one variable value.
+pub const TOKEN_VALUE_END: u32 = 257;
+/// Ends one line.
+pub const TOKEN_LINE_END: u32 = 258;
+/// Token of template 0; template `n` is `TEMPLATE_TOKEN_BASE + n`.
+pub const TEMPLATE_TOKEN_BASE: u32 = 259;
+
+/// Line templates seen so far, numbered in order of first appearance.
+///
+/// A template is the line's skeleton text with one `<column>` slot per
+/// variable (e.g. `<timestamps> ERROR disk full on <paths>`). Reuse one vocab
+/// across archives to keep template ids stable for a whole training set.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVocab {
+    templates: Vec<String>,
+    ids: HashMap<String, u32>,
+}
+
+impl TemplateVocab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of templates.
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Template text for `id`.
+    pub fn get(&self, id: u32) -> Option<&str> {
+        self.templates.get(id as usize).map(String::as_str)
+    }
+
+    /// Id of `template`, if seen.
+    pub fn id(&self, template: &str) -> Option<u32> {
+        self.ids.get(template).copied()
+    }
+
+    /// All templates, indexed by id.
+    pub fn templates(&self) -> &[String] {
+        &self.templates
+    }
+
+    fn intern(&mut self, template: String) -> u32 {
+        if let Some(&id) = self.ids.get(&template) {
+            return id;
+        }
+        let id = self.templates.len() as u32;
+        self.templates.push(template.clone());
+        self.ids.insert(template, id);
+        id
+    }
+}
+
+/// One archive line as a training example.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrainingPair {
+    /// Id in the [`TemplateVocab`].
+    pub template_id: u32,
+    /// Variable values, in slot order.
+    pub values: Vec<String>,
+}
+
+impl TrainingPair {
+    /// Tokens of this line: template token, value bytes each followed by
+    /// [`TOKEN_VALUE_END`], then [`TOKEN_LINE_END`].
+    pub fn tokens(&self) -> Vec<u32> {
+        let mut tokens =
+            Vec::with_capacity(2 + self.values.iter().map(|v| v.len() + 1).sum::<usize>());
+        tokens.push(TEMPLATE_TOKEN_BASE + self.template_id);
+        for value in &self.values {
+            tokens.extend(value.bytes().map(u32::from));
+            tokens.push(TOKEN_VALUE_END);
+        }
+        tokens.push(TOKEN_LINE_END);
+        tokens
+    }
+}
+
+/// Batched training-data export from v3 archives.
+///
+/// Each callback invocation receives at most `batch_size` items; an error
+/// returned by the callback stops the export and is passed through.
+#[derive(Debug, Clone, Copy)]
+pub struct TrainingExport {
+    batch_size: usize,
+    seq_len: usize,
+}
+
+impl Default for TrainingExport {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl TrainingExport {
+    /// Export in batches of `batch_size` (at least 1), 512-token sequences.
+    pub fn new(batch_size: usize) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            seq_len: 512,
+        }
+    }
+
+    /// Length of each token sequence (at least 1).
+    pub fn with_seq_len(mut self, seq_len: usize) -> Self {
+        self.seq_len = seq_len.max(1);
+        self
+    }
+
+    /// Stream one [`TrainingPair`] per archive line; returns the line count.
+    pub fn pairs(
+        &self,
+        archive: &[u8],
+        vocab: &mut TemplateVocab,
+        mut on_batch: impl FnMut(&[TrainingPair]) -> Result<()>,
+    ) -> Result<usize> {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut count = 0;
+        for_each_line(archive, vocab, |pair| {
+            batch.push(pair);
+            count += 1;
+            if batch.len() == self.batch_size {
+                on_batch(&batch)?;
+                batch.clear();
+            }
+            Ok(())
+        })?;
+        if !batch.is_empty() {
+            on_batch(&batch)?;
+        }
+        Ok(count)
+    }
+
+    /// Stream the concatenated line tokens (see [`TrainingPair::tokens`]) cut
+    /// into `seq_len` sequences, the last one padded with [`TOKEN_PAD`];
+    /// returns the sequence count.
+    pub fn token_sequences(
+        &self,
+        archive: &[u8],
+        vocab: &mut TemplateVocab,
+        mut on_batch: impl FnMut(&[Vec<u32>]) -> Result<()>,
+    ) -> Result<usize> {
+        let mut batch: Vec<Vec<u32>> = Vec::with_capacity(self.batch_size);
+        let mut current = Vec::with_capacity(self.seq_len);
+        let mut count = 0;
+        let mut push = |sequence: Vec<u32>, batch: &mut Vec<Vec<u32>>| -> Result<()> {
+            batch.push(sequence);
+            count += 1;
+            if batch.len() == self.batch_size {
+                on_batch(batch)?;
+                batch.clear();
+            }
+            Ok(())
+        };
+
+        for_each_line(archive, vocab, |pair| {
+            for token in pair.tokens() {
+                current.push(token);
+                if current.len() == self.seq_len {
+                    let full = std::mem::replace(&mut current, Vec::with_capacity(self.seq_len));
+                    push(full, &mut batch)?;
+                }
+            }
+            Ok(())
+        })?;
+        if !current.is_empty() {
+            current.resize(self.seq_len, TOKEN_PAD);
+            push(current, &mut batch)?;
+        }
+        if !batch.is_empty() {
+            on_batch(&batch)?;
+        }
+        Ok(count)
+    }
+}
+
+/// Decode a v3 archive and hand each line to `f` as a [`TrainingPair`].
+fn for_each_line(
+    archive: &[u8],
+    vocab: &mut TemplateVocab,
+    mut f: impl FnMut(TrainingPair) -> Result<()>,
+) -> Result<()> {
+    let payload = FormatV3Writer::decode_payload(archive)?;
+    let prefix_sums = payload.prepare_for_read();
+    for pieces in ColumnarPayload::split_lines(&payload.skeleton_tokens) {
+        let mut template = String::new();
+        let mut values = Vec::new();
+        for piece in pieces {
+            match piece {
+                LinePiece::Text(text) => {
+                    template.push_str(text.strip_suffix('\n').unwrap_or(text));
+                }
+                LinePiece::Ref(idx) => {
+                    let idx = idx as usize;
+                    let name = payload
+                        .placeholder_column(idx)
+                        .map_or("value", |c| ColumnType::name(&c));
+                    template.push('<');
+                    template.push_str(name);
+                    template.push('>');
+                    values.push(
+                        payload
+                            .get_value_fast(idx, &prefix_sums)
+                            .unwrap_or_default(),
+                    );
+                }
+            }
+        }
+        f(TrainingPair {
+            template_id: vocab.intern(template),
+            values,
+        })?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,4 +337,43 @@ mod tests {
         let (top_idx, _) = predictor.predict_top(&context);
         assert_eq!(top_idx, 1); // class 1 has higher logit
     }
+
+    #[test]
+    fn test_training_export() {
+        let text = "2024-01-15 10:30:45 ERROR disk full on /dev/sda1\n\
+                    2024-01-15 10:30:46 ERROR disk full on /dev/sdb1\n\
+                    plain line\n";
+        let archive = crate::compress_v3(text, crate::CompressionLevel::Fast).unwrap();
+        let mut vocab = TemplateVocab::new();
+
+        let mut pairs = Vec::new();
+        let lines = TrainingExport::new(2)
+            .pairs(&archive, &mut vocab, |batch| {
+                assert!(batch.len() <= 2);
+                pairs.extend_from_slice(batch);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(lines, 3);
+        assert_eq!(vocab.len(), 2);
+        assert_eq!(pairs[0].template_id, pairs[1].template_id);
+        assert!(pairs[0].values.contains(&"/dev/sda1".to_string()));
+        assert_eq!(vocab.get(pairs[2].template_id), Some("plain line"));
+        assert!(pairs[2].values.is_empty());
+
+        let total: usize = pairs.iter().map(|p| p.tokens().len()).sum();
+        let mut sequences = Vec::new();
+        let count = TrainingExport::new(8)
+            .with_seq_len(16)
+            .token_sequences(&archive, &mut vocab, |batch| {
+                sequences.extend_from_slice(batch);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(count, total.div_ceil(16));
+        assert!(sequences.iter().all(|s| s.len() == 16));
+        assert_eq!(sequences[0][0], TEMPLATE_TOKEN_BASE + pairs[0].template_id);
+        // Same vocab, same ids
+        assert_eq!(vocab.len(), 2);
+    }
 }
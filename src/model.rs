@@ -0,0 +1,344 @@
+//! Model — shareable `.atxm` sidecar with the learned compression state
+//!
+//! A [`TextModel`] bundles what a fleet of writers should agree on: the
+//! pattern configuration, learned pattern statistics, and an optional Zstd
+//! dictionary trained on columnar payloads. Train it once, ship the `.atxm`
+//! file to every agent, and load it with `TunedCompressor::with_model`.
+//!
+//! Archives written with a dictionary record the model id
+//! (`TunedHeader::FLAG_DICTIONARY`); decoding them needs the same model and
+//! fails with the expected id otherwise. Archives without a dictionary
+//! decode without any model.
+//!
+//! ```text
+//! MAGIC "ALICETXM" (8) | VERSION (2) | MODEL_ID u64 | { TAG u8 | LEN u32 | DATA }*
+//! ```
+//!
+//! Sections: pattern config (Bincode), pattern stats (JSON), Zstd
+//! dictionary (raw). Unknown tags are skipped, so minor versions can add
+//! sections; a different major version is rejected. The id is FNV-1a over
+//! the config and dictionary — the parts decoding depends on — and is
+//! checked on load.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::path::Path;
+
+use crate::columnar_encoder::ColumnarEncoder;
+use crate::pattern_learner::{PatternDatabase, PatternLearner};
+use crate::tuned_pattern_learner::PatternConfig;
+use crate::{fnv1a, ALICETextError, Result, FNV_OFFSET};
+
+/// Magic bytes for model files
+pub const MODEL_MAGIC: &[u8; 8] = b"ALICETXM";
+
+/// Model format version
+pub const MODEL_VERSION: (u8, u8) = (1, 0);
+
+/// Model file extension
+pub const MODEL_EXT: &str = "atxm";
+
+/// Fixed header size (magic + version + model id)
+const HEADER_SIZE: usize = 8 + 2 + 8;
+
+/// Training texts are cut into line-aligned chunks of about this size, one
+/// dictionary sample each
+const SAMPLE_CHUNK: usize = 16 * 1024;
+
+const TAG_PATTERN_CONFIG: u8 = 1;
+const TAG_PATTERN_STATS: u8 = 2;
+const TAG_DICTIONARY: u8 = 3;
+
+/// Learned state shared between writers and readers of archives
+#[derive(Debug, Clone, Default)]
+pub struct TextModel {
+    /// Pattern extraction configuration used by writers
+    pub pattern_config: PatternConfig,
+    /// Pattern statistics from the training texts (informational)
+    pub pattern_stats: PatternDatabase,
+    /// Zstd dictionary for columnar payloads (empty = none)
+    pub dictionary: Vec<u8>,
+}
+
+impl TextModel {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Train on sample texts: pattern statistics, plus a Zstd dictionary of
+    /// at most `dictionary_size` bytes (0 = no dictionary)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exclusion regex in `config` is invalid or Zstd
+    /// cannot build a dictionary from the samples (too little data).
+    pub fn train(samples: &[&str], config: PatternConfig, dictionary_size: usize) -> Result<Self> {
+        let learner = PatternLearner::new();
+        let mut pattern_stats = PatternDatabase::new();
+        for sample in samples {
+            for m in learner.find_matches(sample) {
+                pattern_stats.add_match(m.pattern_type, &m.matched_text);
+            }
+        }
+
+        let mut dictionary = Vec::new();
+        if dictionary_size > 0 {
+            let encoder = ColumnarEncoder::with_config(config.clone())?;
+            let mut payloads = Vec::new();
+            for sample in samples {
+                for chunk in line_chunks(sample, SAMPLE_CHUNK) {
+                    payloads.push(encoder.encode(chunk).to_v2_bytes()?);
+                }
+            }
+            dictionary = zstd::dict::from_samples(&payloads, dictionary_size).map_err(|e| {
+                ALICETextError::EncodingError(format!(
+                    "Dictionary training failed (too little sample data?): {e}"
+                ))
+            })?;
+        }
+
+        Ok(Self {
+            pattern_config: config,
+            pattern_stats,
+            dictionary,
+        })
+    }
+
+    /// Whether archives written with this model need it to decode
+    #[must_use]
+    pub fn has_dictionary(&self) -> bool {
+        !self.dictionary.is_empty()
+    }
+
+    /// Model id recorded in archives (FNV-1a over config and dictionary)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern config cannot be serialized.
+    pub fn id(&self) -> Result<u64> {
+        let config = self.config_bytes()?;
+        let h = fnv1a(&config, FNV_OFFSET);
+        Ok(fnv1a(&self.dictionary, h))
+    }
+
+    fn config_bytes(&self) -> Result<Vec<u8>> {
+        bincode::serialize(&self.pattern_config)
+            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))
+    }
+
+    /// Serialize to the `.atxm` format
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a section cannot be serialized.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let sections = [
+            (TAG_PATTERN_CONFIG, self.config_bytes()?),
+            (TAG_PATTERN_STATS, self.pattern_stats.to_bytes()?),
+            (TAG_DICTIONARY, self.dictionary.clone()),
+        ];
+
+        let mut out = Vec::with_capacity(
+            HEADER_SIZE + sections.iter().map(|(_, d)| 5 + d.len()).sum::<usize>(),
+        );
+        out.extend_from_slice(MODEL_MAGIC);
+        out.push(MODEL_VERSION.0);
+        out.push(MODEL_VERSION.1);
+        out.extend_from_slice(&self.id()?.to_le_bytes());
+        for (tag, data) in sections {
+            out.push(tag);
+            out.extend_from_slice(&crate::to_u32(data.len(), "model section size")?.to_le_bytes());
+            out.extend_from_slice(&data);
+        }
+        Ok(out)
+    }
+
+    /// Parse the `.atxm` format
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on bad magic, an unsupported major version, a
+    /// truncated or corrupt section, or a model id that doesn't match the content.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(ALICETextError::DecompressionError(
+                "Model too short".to_string(),
+            ));
+        }
+        if &data[0..8] != MODEL_MAGIC {
+            return Err(ALICETextError::DecompressionError(
+                "Invalid model magic: expected ALICETXM".to_string(),
+            ));
+        }
+        if data[8] != MODEL_VERSION.0 {
+            return Err(ALICETextError::InvalidVersion(data[8], data[9]));
+        }
+        let stored_id = u64::from_le_bytes(data[10..18].try_into().unwrap_or_default());
+
+        let mut model = Self::new();
+        let truncated = || ALICETextError::DecompressionError("Model section truncated".into());
+        let mut rest = &data[HEADER_SIZE..];
+        while let Some((&tag, after)) = rest.split_first() {
+            let (len, after) = after.split_first_chunk::<4>().ok_or_else(truncated)?;
+            let (section, after) = after
+                .split_at_checked(u32::from_le_bytes(*len) as usize)
+                .ok_or_else(truncated)?;
+            match tag {
+                TAG_PATTERN_CONFIG => {
+                    model.pattern_config = bincode::deserialize(section).map_err(|e| {
+                        ALICETextError::DecompressionError(format!("Bincode error: {e}"))
+                    })?;
+                }
+                TAG_PATTERN_STATS => model.pattern_stats = PatternDatabase::from_bytes(section)?,
+                TAG_DICTIONARY => model.dictionary = section.to_vec(),
+                // Added by a newer minor version
+                _ => {}
+            }
+            rest = after;
+        }
+
+        let id = model.id()?;
+        if id != stored_id {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Model id mismatch: header {stored_id:016x}, content {id:016x}"
+            )));
+        }
+        Ok(model)
+    }
+
+    /// Write to an `.atxm` file (atomically, see [`crate::SafeFileWriter`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or the write fails.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        crate::write_atomic(path, &self.to_bytes()?)
+    }
+
+    /// Read an `.atxm` file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or isn't a valid model.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+/// Line-aligned chunks of at least `size` bytes (the last may be shorter)
+fn line_chunks(text: &str, size: usize) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        end = rest[end..].find('\n').map_or(rest.len(), |nl| end + nl + 1);
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionMode, TunedCompressor};
+
+    fn training_text(seed: usize) -> String {
+        (0..2000)
+            .map(|i| {
+                let i = i + seed;
+                format!(
+                    "2024-01-15 10:{:02}:{:02} INFO [worker-{}] GET /api/v2/users/{} status=200 bytes={}\n",
+                    i / 60 % 60,
+                    i % 60,
+                    i % 8,
+                    i * 31 % 9973,
+                    i * 17 % 4096
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_model_roundtrip_and_checks() {
+        let texts = [training_text(0), training_text(5000)];
+        let samples: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let model = TextModel::train(&samples, PatternConfig::strict(), 4096).unwrap();
+        assert!(model.has_dictionary());
+        assert!(model.pattern_stats.total_matches > 0);
+
+        let bytes = model.to_bytes().unwrap();
+        let loaded = TextModel::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.id().unwrap(), model.id().unwrap());
+        assert_eq!(loaded.pattern_config, PatternConfig::strict());
+        assert_eq!(loaded.dictionary, model.dictionary);
+
+        // Unknown sections are skipped
+        let mut extended = bytes.clone();
+        extended.extend_from_slice(&[0x7F, 2, 0, 0, 0, 0xAA, 0xBB]);
+        assert!(TextModel::from_bytes(&extended).is_ok());
+
+        // Major version, tampering and truncation are rejected
+        let mut newer = bytes.clone();
+        newer[8] = MODEL_VERSION.0 + 1;
+        assert!(matches!(
+            TextModel::from_bytes(&newer),
+            Err(ALICETextError::InvalidVersion(..))
+        ));
+        let mut tampered = bytes.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xFF;
+        assert!(TextModel::from_bytes(&tampered).is_err());
+        assert!(TextModel::from_bytes(&bytes[..bytes.len() - 3]).is_err());
+        assert!(TextModel::from_bytes(b"ALICETXT").is_err());
+    }
+
+    #[test]
+    fn test_shared_model_decodes_across_agents() {
+        let texts = [training_text(0), training_text(7000)];
+        let samples: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let model = TextModel::train(&samples, PatternConfig::default(), 8192).unwrap();
+        let shared = TextModel::from_bytes(&model.to_bytes().unwrap()).unwrap();
+
+        // A small fresh batch benefits from the dictionary
+        let batch: String = training_text(20_000)
+            .lines()
+            .take(40)
+            .map(|l| format!("{l}\n"))
+            .collect();
+        let mut writer = TunedCompressor::new(CompressionMode::Balanced)
+            .with_model(&model)
+            .unwrap();
+        let with_model = writer.compress(&batch).unwrap();
+        let without = TunedCompressor::default().compress(&batch).unwrap();
+        assert!(
+            with_model.len() < without.len(),
+            "{} vs {}",
+            with_model.len(),
+            without.len()
+        );
+        assert_eq!(
+            TunedCompressor::read_model_id(&with_model).unwrap(),
+            Some(model.id().unwrap())
+        );
+        assert_eq!(TunedCompressor::read_model_id(&without).unwrap(), None);
+
+        // Another agent with the shared model decodes it; without it, it can't
+        let reader = TunedCompressor::default().with_model(&shared).unwrap();
+        assert_eq!(reader.decompress(&with_model).unwrap(), batch);
+        assert!(TunedCompressor::default().decompress(&with_model).is_err());
+        let other = TextModel::train(&samples[..1], PatternConfig::default(), 4096).unwrap();
+        let wrong = TunedCompressor::default().with_model(&other).unwrap();
+        assert!(wrong.decompress(&with_model).is_err());
+
+        // Model-less archives still decode with a model loaded
+        assert_eq!(reader.decompress(&without).unwrap(), batch);
+    }
+}
@@ -0,0 +1,449 @@
+//! N-gram model — order-N token prediction for `EncodingMode::NGram`
+//!
+//! Text is split into segments (tokenizer tokens plus the gaps between
+//! them, so the segments concatenate back to the input). Encoder and
+//! decoder run the same adaptive model: before each segment it predicts
+//! the segment that most recently followed the longest known context of
+//! up to `order - 1` previous segments. Only mispredicted segments are
+//! stored, with their index; a decoder replaying the predictions
+//! reconstructs everything else.
+//!
+//! A model can be primed with [`NGramModel::train`] on representative text.
+//! Archives record the primed model's [`fingerprint`](NGramModel::fingerprint)
+//! and decoding them requires the same model.
+//!
+//! Payload (LZMA-compressed, little-endian):
+//!
+//! ```text
+//! ORDER u8 | SEGMENTS u32 | EXCEPTIONS u32 | EXCEPTIONS × (GAP u32 | CORRECTION)
+//! CORRECTION = 0u32 | LEN u32 | UTF-8 bytes      (segment not seen before)
+//!            | ID + 1 u32                        (known segment, by first-seen id)
+//! ```
+//!
+//! `GAP` counts correctly predicted segments since the previous exception.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::tokenizer::Tokenizer;
+use crate::{to_u32, ALICETextError, Result};
+
+/// Default model order (predict from the two previous segments)
+pub const DEFAULT_NGRAM_ORDER: usize = 3;
+
+/// Largest supported model order
+pub const MAX_NGRAM_ORDER: usize = 8;
+
+// ── FNV-1a (file-local) ───────────────────────────────────────
+#[inline(always)]
+fn fnv1a_fold(mut h: u64, data: &[u8]) -> u64 {
+    for &b in data {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Split `text` into `tokenizer` tokens and the non-empty gaps between them
+///
+/// The segments concatenate to `text`.
+#[must_use]
+pub fn segment<'a>(text: &'a str, tokenizer: &dyn Tokenizer) -> Vec<&'a str> {
+    let base = text.as_ptr() as usize;
+    let mut segments = Vec::new();
+    let mut pos = 0;
+    for token in tokenizer.tokenize(text) {
+        let start = token.as_ptr() as usize - base;
+        if start > pos {
+            segments.push(&text[pos..start]);
+        }
+        if !token.is_empty() {
+            segments.push(token);
+        }
+        pos = start + token.len();
+    }
+    if pos < text.len() {
+        segments.push(&text[pos..]);
+    }
+    segments
+}
+
+/// Adaptive order-N segment predictor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NGramModel {
+    order: usize,
+    /// Segment -> id, in first-seen order
+    ids: HashMap<String, u32>,
+    vocab: Vec<String>,
+    /// (context length, context hash) -> segment that last followed it
+    next: HashMap<(u8, u64), u32>,
+    /// Last `order - 1` segment ids
+    history: Vec<u32>,
+    fingerprint: u64,
+}
+
+impl NGramModel {
+    /// Untrained model predicting from up to `order - 1` previous segments
+    ///
+    /// `order` is clamped to `1..=MAX_NGRAM_ORDER`.
+    #[must_use]
+    pub fn new(order: usize) -> Self {
+        Self {
+            order: order.clamp(1, MAX_NGRAM_ORDER),
+            ids: HashMap::new(),
+            vocab: Vec::new(),
+            next: HashMap::new(),
+            history: Vec::new(),
+            fingerprint: 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Distinct segments seen so far
+    #[must_use]
+    pub fn vocab_len(&self) -> usize {
+        self.vocab.len()
+    }
+
+    /// Identifies the training history; 0 for an untrained model
+    #[must_use]
+    pub const fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Prime the model on `sample`, segmented with `tokenizer`
+    pub fn train(&mut self, sample: &str, tokenizer: &dyn Tokenizer) {
+        for seg in segment(sample, tokenizer) {
+            self.observe(seg);
+        }
+    }
+
+    /// Predicted next segment, if any context has been seen
+    #[must_use]
+    pub fn predict(&self) -> Option<&str> {
+        (0..self.order.min(self.history.len() + 1))
+            .rev()
+            .find_map(|k| self.next.get(&(k as u8, self.context_hash(k))))
+            .map(|&id| self.vocab[id as usize].as_str())
+    }
+
+    /// Id of a segment already in the vocabulary
+    #[must_use]
+    pub fn id_of(&self, segment: &str) -> Option<u32> {
+        self.ids.get(segment).copied()
+    }
+
+    /// Record that `segment` came next
+    pub fn observe(&mut self, segment: &str) {
+        let id = match self.ids.get(segment) {
+            Some(&id) => id,
+            None => {
+                let id = self.vocab.len() as u32;
+                self.ids.insert(segment.to_string(), id);
+                self.vocab.push(segment.to_string());
+                id
+            }
+        };
+        for k in 0..self.order.min(self.history.len() + 1) {
+            self.next.insert((k as u8, self.context_hash(k)), id);
+        }
+        if self.order > 1 {
+            if self.history.len() == self.order - 1 {
+                self.history.remove(0);
+            }
+            self.history.push(id);
+        }
+        let fp = fnv1a_fold(self.fingerprint ^ FNV_OFFSET, segment.as_bytes());
+        // Keep 0 reserved for "untrained"
+        self.fingerprint = fp.max(1);
+    }
+
+    /// Hash of the last `k` segment ids
+    fn context_hash(&self, k: usize) -> u64 {
+        self.history[self.history.len() - k..]
+            .iter()
+            .fold(FNV_OFFSET, |h, id| fnv1a_fold(h, &id.to_le_bytes()))
+    }
+
+    fn segment_by_id(&self, id: u32) -> Option<&str> {
+        self.vocab.get(id as usize).map(String::as_str)
+    }
+}
+
+impl Default for NGramModel {
+    fn default() -> Self {
+        Self::new(DEFAULT_NGRAM_ORDER)
+    }
+}
+
+/// How a mispredicted segment is restored
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Correction {
+    /// Segment not in the vocabulary yet
+    Literal(String),
+    /// Segment already seen, by vocabulary id
+    Known(u32),
+}
+
+/// A mispredicted segment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NGramException {
+    /// Segment index in the text
+    pub index: usize,
+    pub correction: Correction,
+}
+
+/// Mispredicted segments of one text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NGramEncoding {
+    pub order: u8,
+    pub segment_count: usize,
+    pub exceptions: Vec<NGramException>,
+}
+
+impl NGramEncoding {
+    /// Run `model` over `segments`, keeping only the misses
+    #[must_use]
+    pub fn encode(model: &mut NGramModel, segments: &[&str]) -> Self {
+        let mut exceptions = Vec::new();
+        for (index, &seg) in segments.iter().enumerate() {
+            if model.predict() != Some(seg) {
+                let correction = match model.id_of(seg) {
+                    Some(id) => Correction::Known(id),
+                    None => Correction::Literal(seg.to_string()),
+                };
+                exceptions.push(NGramException { index, correction });
+            }
+            model.observe(seg);
+        }
+        Self {
+            order: model.order() as u8,
+            segment_count: segments.len(),
+            exceptions,
+        }
+    }
+
+    /// Replay `model` and the stored misses to rebuild the text of
+    /// `original_length` bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exception is out of order or references an
+    /// unknown segment, a segment has no prediction, or the text does not
+    /// come out at exactly `original_length` bytes.
+    pub fn decode(&self, model: &mut NGramModel, original_length: usize) -> Result<String> {
+        let corrupt = |msg: &str| ALICETextError::DecompressionError(format!("N-gram: {msg}"));
+        // Segments are never empty
+        if self.segment_count > original_length {
+            return Err(corrupt("more segments than bytes"));
+        }
+        let mut text = String::with_capacity(original_length);
+        let mut pending = self.exceptions.iter().peekable();
+        for index in 0..self.segment_count {
+            let seg = match pending.next_if(|e| e.index == index) {
+                Some(NGramException {
+                    correction: Correction::Literal(seg),
+                    ..
+                }) => seg.clone(),
+                Some(NGramException {
+                    correction: Correction::Known(id),
+                    ..
+                }) => model
+                    .segment_by_id(*id)
+                    .ok_or_else(|| corrupt("unknown segment id"))?
+                    .to_string(),
+                None => model
+                    .predict()
+                    .ok_or_else(|| corrupt("no prediction"))?
+                    .to_string(),
+            };
+            if seg.is_empty() || seg.len() > original_length - text.len() {
+                return Err(corrupt("segment past the declared length"));
+            }
+            text.push_str(&seg);
+            model.observe(&seg);
+        }
+        if pending.next().is_some() {
+            return Err(corrupt("exception past the last segment"));
+        }
+        if text.len() != original_length {
+            return Err(corrupt("text shorter than the declared length"));
+        }
+        Ok(text)
+    }
+
+    /// Serialize to the payload layout (see module docs)
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if a count or length does not fit in `u32`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = vec![self.order];
+        out.extend_from_slice(&to_u32(self.segment_count, "n-gram segments")?.to_le_bytes());
+        out.extend_from_slice(&to_u32(self.exceptions.len(), "n-gram exceptions")?.to_le_bytes());
+        let mut next = 0;
+        for e in &self.exceptions {
+            out.extend_from_slice(&to_u32(e.index - next, "n-gram gap")?.to_le_bytes());
+            next = e.index + 1;
+            match &e.correction {
+                Correction::Literal(seg) => {
+                    out.extend_from_slice(&0u32.to_le_bytes());
+                    out.extend_from_slice(&to_u32(seg.len(), "n-gram segment")?.to_le_bytes());
+                    out.extend_from_slice(seg.as_bytes());
+                }
+                Correction::Known(id) => {
+                    let tag = id.checked_add(1).ok_or(ALICETextError::LimitExceeded {
+                        field: "n-gram vocabulary",
+                        value: u64::from(*id) + 1,
+                        limit: u64::from(u32::MAX),
+                    })?;
+                    out.extend_from_slice(&tag.to_le_bytes());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Parse the payload layout
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is truncated or not valid UTF-8.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let truncated = || ALICETextError::DecompressionError("Truncated n-gram payload".into());
+        let mut pos = 0;
+        let read_u32 = |pos: &mut usize| -> Result<u32> {
+            let bytes = data.get(*pos..*pos + 4).ok_or_else(truncated)?;
+            *pos += 4;
+            Ok(u32::from_le_bytes(
+                bytes.try_into().map_err(|_| truncated())?,
+            ))
+        };
+        let order = *data.first().ok_or_else(truncated)?;
+        pos += 1;
+        let segment_count = read_u32(&mut pos)? as usize;
+        let count = read_u32(&mut pos)? as usize;
+        // Every exception takes at least 8 bytes
+        let mut exceptions = Vec::with_capacity(count.min(data.len() / 8));
+        let mut next = 0usize;
+        for _ in 0..count {
+            let index = next + read_u32(&mut pos)? as usize;
+            next = index + 1;
+            let correction = match read_u32(&mut pos)? {
+                0 => {
+                    let len = read_u32(&mut pos)? as usize;
+                    let bytes = data.get(pos..pos + len).ok_or_else(truncated)?;
+                    pos += len;
+                    let seg = std::str::from_utf8(bytes).map_err(|e| {
+                        ALICETextError::DecompressionError(format!("UTF-8 error: {e}"))
+                    })?;
+                    Correction::Literal(seg.to_string())
+                }
+                tag => Correction::Known(tag - 1),
+            };
+            exceptions.push(NGramException { index, correction });
+        }
+        Ok(Self {
+            order,
+            segment_count,
+            exceptions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::{UnicodeWordTokenizer, WhitespaceTokenizer};
+
+    fn log_text() -> String {
+        (0..200)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:{:02}:00 INFO request served user={}\n",
+                    i % 60,
+                    i % 7
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_segment_is_lossless() {
+        let text = "  a=1, b  ログ\n";
+        for tokenizer in [
+            &WhitespaceTokenizer as &dyn Tokenizer,
+            &UnicodeWordTokenizer,
+        ] {
+            assert_eq!(segment(text, tokenizer).concat(), text);
+        }
+        assert_eq!(
+            segment(text, &WhitespaceTokenizer),
+            ["  ", "a=1,", " ", "b", "  ", "ログ", "\n"]
+        );
+    }
+
+    #[test]
+    fn test_ngram_roundtrip_and_misses() {
+        let text = log_text();
+        let segments = segment(&text, &UnicodeWordTokenizer);
+        let encoding = NGramEncoding::encode(&mut NGramModel::default(), &segments);
+        // Repeated structure is predicted; only the varying fields miss
+        assert!(encoding.exceptions.len() * 3 < segments.len());
+
+        let parsed = NGramEncoding::from_bytes(&encoding.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed, encoding);
+        assert_eq!(
+            parsed
+                .decode(&mut NGramModel::default(), text.len())
+                .unwrap(),
+            text
+        );
+        // The declared length bounds the output and must match exactly
+        let mut inflated = parsed.clone();
+        inflated.segment_count = u32::MAX as usize;
+        assert!(inflated
+            .decode(&mut NGramModel::default(), text.len())
+            .is_err());
+        assert!(parsed
+            .decode(&mut NGramModel::default(), text.len() + 1)
+            .is_err());
+        assert!(parsed
+            .decode(&mut NGramModel::default(), text.len() - 1)
+            .is_err());
+
+        // Orders 1 (most recent segment) through MAX all roundtrip
+        for order in [1, 2, MAX_NGRAM_ORDER + 5] {
+            let encoding = NGramEncoding::encode(&mut NGramModel::new(order), &segments);
+            let decoded = encoding
+                .decode(&mut NGramModel::new(order), text.len())
+                .unwrap();
+            assert_eq!(decoded, text);
+        }
+    }
+
+    #[test]
+    fn test_primed_model() {
+        let mut primed = NGramModel::default();
+        assert_eq!(primed.fingerprint(), 0);
+        primed.train(&log_text(), &WhitespaceTokenizer);
+        assert_ne!(primed.fingerprint(), 0);
+
+        let text = "2024-01-15 10:05:00 INFO request served user=3\n";
+        let segments = segment(text, &WhitespaceTokenizer);
+        let cold = NGramEncoding::encode(&mut NGramModel::default(), &segments);
+        let warm = NGramEncoding::encode(&mut primed.clone(), &segments);
+        assert!(warm.exceptions.len() < cold.exceptions.len());
+        assert_eq!(warm.decode(&mut primed.clone(), text.len()).unwrap(), text);
+    }
+}
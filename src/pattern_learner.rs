@@ -2,6 +2,7 @@
 //!
 //! Automatically extracts and learns patterns from text/logs.
 
+use crate::coverage::Coverage;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::cmp::Reverse;
@@ -227,7 +228,7 @@ impl PatternLearner {
     #[must_use]
     pub fn learn(&self, text: &str) -> PatternDatabase {
         let mut db = PatternDatabase::new();
-        let mut covered: Vec<bool> = vec![false; text.len()];
+        let mut coverage = Coverage::new();
 
         // Find all pattern matches, prioritizing by pattern type
         for (pattern_type, regex) in &self.patterns {
@@ -235,14 +236,11 @@ impl PatternLearner {
                 let start = mat.start();
                 let end = mat.end();
 
-                // Check if this region is already covered
-                if covered[start..end].iter().any(|&c| c) {
+                // Skip regions already claimed by a higher-priority pattern
+                if !coverage.claim(start, end) {
                     continue;
                 }
 
-                // Mark region as covered
-                covered[start..end].iter_mut().for_each(|c| *c = true);
-
                 db.add_match(*pattern_type, mat.as_str());
             }
         }
@@ -254,7 +252,7 @@ impl PatternLearner {
     #[must_use]
     pub fn find_matches(&self, text: &str) -> Vec<PatternMatch> {
         let mut matches = Vec::new();
-        let mut covered: Vec<bool> = vec![false; text.len()];
+        let mut coverage = Coverage::new();
         let mut pattern_index = 0;
 
         for (pattern_type, regex) in &self.patterns {
@@ -262,14 +260,11 @@ impl PatternLearner {
                 let start = mat.start();
                 let end = mat.end();
 
-                // Check if this region is already covered
-                if covered[start..end].iter().any(|&c| c) {
+                // Skip regions already claimed by a higher-priority pattern
+                if !coverage.claim(start, end) {
                     continue;
                 }
 
-                // Mark region as covered
-                covered[start..end].iter_mut().for_each(|c| *c = true);
-
                 matches.push(PatternMatch {
                     pattern_type: *pattern_type,
                     start,
@@ -0,0 +1,501 @@
+//! Pool — bounded, multi-threaded block compression pipeline
+//!
+//! The plumbing every ingest service ends up writing: lines go in through a
+//! bounded channel, a batcher cuts them into blocks of about
+//! [`PoolConfig::block_size`] bytes, worker threads compress the blocks
+//! (`TunedCompressor`, v2) and [`CompressorPool::recv`] hands them back in
+//! input order.
+//!
+//! ```text
+//! send(line) ─▶ [lines: queue_capacity] ─▶ batcher ─▶ [blocks] ─▶ workers ─▶ [done] ─▶ recv() (in order)
+//! ```
+//!
+//! Every stage is bounded, so a slow consumer stalls the workers, then the
+//! batcher, then [`PoolSender::send`] — backpressure reaches the producer
+//! instead of memory growing. [`PoolSender::try_send`] lets a producer shed
+//! load instead of blocking. Queue depths are exposed through
+//! [`CompressorPool::queue_depth`] / [`CompressorPool::blocks_in_flight`]
+//! and, with the `metrics` feature, as the `alice_text_pool_queue_depth` gauge.
+//!
+//! ```rust
+//! use alice_text::pool::{CompressorPool, PoolConfig};
+//!
+//! let pool = CompressorPool::new(PoolConfig::default());
+//! pool.send("2024-01-15 10:30:45 INFO started").unwrap();
+//! let blocks = pool.finish().unwrap();
+//! assert_eq!(blocks[0].lines, 1);
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::tuned_compressor::{CompressionMode, TunedCompressor};
+use crate::{telemetry, ALICETextError, Result};
+
+/// Pipeline sizing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Compression threads
+    pub workers: usize,
+    /// Lines that may wait for the batcher before `send` blocks
+    pub queue_capacity: usize,
+    /// Close a block once it holds this many bytes
+    pub block_size: usize,
+    /// Also close a non-empty block this long after its first line (`None` = size only)
+    pub flush_interval: Option<Duration>,
+    pub mode: CompressionMode,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            workers: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            queue_capacity: 10_000,
+            block_size: 1 << 20,
+            flush_interval: None,
+            mode: CompressionMode::Balanced,
+        }
+    }
+}
+
+/// One compressed block, a self-contained v2 archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedBlock {
+    /// Position in the output sequence (0, 1, 2, ...)
+    pub seq: u64,
+    /// Number of input lines
+    pub lines: usize,
+    /// Uncompressed size (lines plus their line breaks)
+    pub original_size: usize,
+    pub data: Vec<u8>,
+}
+
+enum Message {
+    Line(String),
+    Flush,
+}
+
+struct Job {
+    seq: u64,
+    lines: usize,
+    text: String,
+}
+
+type Done = (u64, Result<CompressedBlock>);
+
+/// Producer handle; clone it for every thread that feeds the pool
+///
+/// The pool's input closes once the pool and all senders are closed/dropped.
+#[derive(Clone)]
+pub struct PoolSender {
+    tx: SyncSender<Message>,
+    depth: Arc<AtomicUsize>,
+}
+
+impl PoolSender {
+    /// Queue a line (without its line break), blocking while the queue is full
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pipeline has shut down.
+    pub fn send(&self, line: impl Into<String>) -> Result<()> {
+        self.enqueue(Message::Line(line.into()))
+    }
+
+    /// Queue a line without blocking; hands it back if the queue is full or closed
+    ///
+    /// # Errors
+    ///
+    /// Returns `TrySendError::Full(line)` / `TrySendError::Disconnected(line)`.
+    pub fn try_send(&self, line: String) -> std::result::Result<(), TrySendError<String>> {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+        match self.tx.try_send(Message::Line(line)) {
+            Ok(()) => {
+                telemetry::record_queue_depth("lines", self.depth.load(Ordering::Relaxed));
+                Ok(())
+            }
+            Err(e) => {
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+                Err(match e {
+                    TrySendError::Full(Message::Line(line)) => TrySendError::Full(line),
+                    TrySendError::Disconnected(Message::Line(line)) => {
+                        TrySendError::Disconnected(line)
+                    }
+                    _ => unreachable!("only lines are sent here"),
+                })
+            }
+        }
+    }
+
+    /// Close the current block now, even if it is below the block size
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pipeline has shut down.
+    pub fn flush(&self) -> Result<()> {
+        self.enqueue(Message::Flush)
+    }
+
+    fn enqueue(&self, message: Message) -> Result<()> {
+        let is_line = matches!(message, Message::Line(_));
+        if is_line {
+            self.depth.fetch_add(1, Ordering::Relaxed);
+        }
+        if self.tx.send(message).is_err() {
+            if is_line {
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+            }
+            return Err(closed());
+        }
+        telemetry::record_queue_depth("lines", self.depth.load(Ordering::Relaxed));
+        Ok(())
+    }
+}
+
+fn closed() -> ALICETextError {
+    ALICETextError::EncodingError("Compressor pool is shut down".to_string())
+}
+
+/// Bounded channel → batcher → compression workers → in-order output
+pub struct CompressorPool {
+    sender: Option<PoolSender>,
+    done: Receiver<Done>,
+    /// Blocks finished out of order, waiting for their predecessors
+    reorder: BTreeMap<u64, Result<CompressedBlock>>,
+    next_seq: u64,
+    blocks_in_flight: Arc<AtomicUsize>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl CompressorPool {
+    /// Start the batcher and worker threads
+    #[must_use]
+    pub fn new(config: PoolConfig) -> Self {
+        let workers = config.workers.max(1);
+        let (line_tx, line_rx) = mpsc::sync_channel(config.queue_capacity.max(1));
+        let (job_tx, job_rx) = mpsc::sync_channel::<Job>(workers);
+        let (done_tx, done_rx) = mpsc::sync_channel(workers * 2);
+        let depth = Arc::new(AtomicUsize::new(0));
+        let blocks_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let mut threads = Vec::with_capacity(workers + 1);
+        let batcher = Batcher {
+            lines: line_rx,
+            jobs: job_tx,
+            depth: Arc::clone(&depth),
+            blocks_in_flight: Arc::clone(&blocks_in_flight),
+            block_size: config.block_size.max(1),
+            flush_interval: config.flush_interval,
+        };
+        threads.push(thread::spawn(move || batcher.run()));
+
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        for _ in 0..workers {
+            let jobs = Arc::clone(&job_rx);
+            let done = done_tx.clone();
+            let mode = config.mode;
+            threads.push(thread::spawn(move || worker(&jobs, &done, mode)));
+        }
+
+        Self {
+            sender: Some(PoolSender { tx: line_tx, depth }),
+            done: done_rx,
+            reorder: BTreeMap::new(),
+            next_seq: 0,
+            blocks_in_flight,
+            threads,
+        }
+    }
+
+    /// A producer handle for another thread
+    ///
+    /// # Panics
+    ///
+    /// Panics if called after [`Self::close`].
+    #[must_use]
+    pub fn sender(&self) -> PoolSender {
+        self.sender.clone().expect("compressor pool is closed")
+    }
+
+    /// Queue a line (see [`PoolSender::send`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool is closed.
+    pub fn send(&self, line: impl Into<String>) -> Result<()> {
+        self.sender.as_ref().ok_or_else(closed)?.send(line)
+    }
+
+    /// Stop accepting lines from this handle; once every [`PoolSender`] is
+    /// dropped too, the last partial block is flushed and the output ends
+    pub fn close(&mut self) {
+        self.sender = None;
+    }
+
+    /// Lines queued but not yet batched
+    #[must_use]
+    pub fn queue_depth(&self) -> usize {
+        self.sender
+            .as_ref()
+            .map_or(0, |s| s.depth.load(Ordering::Relaxed))
+    }
+
+    /// Blocks cut but not yet returned by [`Self::recv`]
+    #[must_use]
+    pub fn blocks_in_flight(&self) -> usize {
+        self.blocks_in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Next block in input order, waiting for it if necessary
+    ///
+    /// Returns `None` once the pool is closed and every block was returned.
+    pub fn recv(&mut self) -> Option<Result<CompressedBlock>> {
+        loop {
+            if let Some(block) = self.take_next() {
+                return Some(block);
+            }
+            let (seq, block) = self.done.recv().ok()?;
+            self.reorder.insert(seq, block);
+        }
+    }
+
+    /// Next block in input order if it is already finished
+    pub fn try_recv(&mut self) -> Option<Result<CompressedBlock>> {
+        while let Ok((seq, block)) = self.done.try_recv() {
+            self.reorder.insert(seq, block);
+        }
+        self.take_next()
+    }
+
+    /// Wait up to `timeout` for the next block in input order
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Result<CompressedBlock>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(block) = self.take_next() {
+                return Some(block);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let (seq, block) = self.done.recv_timeout(remaining).ok()?;
+            self.reorder.insert(seq, block);
+        }
+    }
+
+    fn take_next(&mut self) -> Option<Result<CompressedBlock>> {
+        let block = self.reorder.remove(&self.next_seq)?;
+        self.next_seq += 1;
+        let in_flight = self.blocks_in_flight.fetch_sub(1, Ordering::Relaxed) - 1;
+        telemetry::record_queue_depth("blocks", in_flight);
+        Some(block)
+    }
+
+    /// Close the pool and collect all remaining blocks in order
+    ///
+    /// Waits for other [`PoolSender`] clones to be dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first compression error.
+    pub fn finish(mut self) -> Result<Vec<CompressedBlock>> {
+        self.close();
+        let mut blocks = Vec::new();
+        while let Some(block) = self.recv() {
+            blocks.push(block?);
+        }
+        for handle in self.threads.drain(..) {
+            handle
+                .join()
+                .map_err(|_| ALICETextError::EncodingError("Pool thread panicked".into()))?;
+        }
+        Ok(blocks)
+    }
+}
+
+impl Iterator for CompressorPool {
+    type Item = Result<CompressedBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+/// Cuts the line stream into blocks
+struct Batcher {
+    lines: Receiver<Message>,
+    jobs: SyncSender<Job>,
+    depth: Arc<AtomicUsize>,
+    blocks_in_flight: Arc<AtomicUsize>,
+    block_size: usize,
+    flush_interval: Option<Duration>,
+}
+
+impl Batcher {
+    fn run(self) {
+        let mut text = String::new();
+        let mut lines = 0;
+        let mut seq = 0;
+        let mut started = Instant::now();
+        loop {
+            let message = match self.flush_interval {
+                Some(interval) if lines > 0 => {
+                    let remaining = interval.saturating_sub(started.elapsed());
+                    match self.lines.recv_timeout(remaining) {
+                        Ok(message) => Some(message),
+                        Err(RecvTimeoutError::Timeout) => Some(Message::Flush),
+                        Err(RecvTimeoutError::Disconnected) => None,
+                    }
+                }
+                _ => self.lines.recv().ok(),
+            };
+            let closed = message.is_none();
+            let flush = match message {
+                Some(Message::Line(line)) => {
+                    let depth = self.depth.fetch_sub(1, Ordering::Relaxed) - 1;
+                    telemetry::record_queue_depth("lines", depth);
+                    if lines == 0 {
+                        started = Instant::now();
+                    }
+                    text.push_str(&line);
+                    text.push('\n');
+                    lines += 1;
+                    text.len() >= self.block_size
+                }
+                Some(Message::Flush) | None => true,
+            };
+            if flush && lines > 0 {
+                self.blocks_in_flight.fetch_add(1, Ordering::Relaxed);
+                let job = Job {
+                    seq,
+                    lines,
+                    text: std::mem::take(&mut text),
+                };
+                if self.jobs.send(job).is_err() {
+                    return;
+                }
+                seq += 1;
+                lines = 0;
+            }
+            if closed {
+                return;
+            }
+        }
+    }
+}
+
+fn worker(jobs: &Mutex<Receiver<Job>>, done: &SyncSender<Done>, mode: CompressionMode) {
+    let mut compressor = TunedCompressor::new(mode);
+    loop {
+        // Hold the lock only while waiting for a job
+        let job = match jobs.lock() {
+            Ok(jobs) => jobs.recv(),
+            Err(_) => return,
+        };
+        let Ok(job) = job else { return };
+        let block = compressor.compress(&job.text).map(|data| CompressedBlock {
+            seq: job.seq,
+            lines: job.lines,
+            original_size: job.text.len(),
+            data,
+        });
+        if done.send((job.seq, block)).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_blocks_in_order() {
+        let pool = CompressorPool::new(PoolConfig {
+            workers: 4,
+            queue_capacity: 64,
+            block_size: 2048,
+            ..PoolConfig::default()
+        });
+        let lines: Vec<String> = (0..5000)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:{:02}:{:02} INFO request {i} from 10.0.{}.{}",
+                    i / 60 % 60,
+                    i % 60,
+                    i % 7,
+                    i % 250
+                )
+            })
+            .collect();
+
+        let sender = pool.sender();
+        let producer = {
+            let lines = lines.clone();
+            thread::spawn(move || {
+                for line in lines {
+                    sender.send(line).unwrap();
+                }
+            })
+        };
+        // Consume while producing: the bounded stages would stall the producer otherwise
+        let blocks = pool.finish().unwrap();
+        producer.join().unwrap();
+
+        assert!(blocks.len() > 10);
+        let mut text = String::new();
+        for (i, block) in blocks.iter().enumerate() {
+            assert_eq!(block.seq, i as u64);
+            let decoded = TunedCompressor::default().decompress(&block.data).unwrap();
+            assert_eq!(decoded.len(), block.original_size);
+            assert_eq!(decoded.lines().count(), block.lines);
+            text.push_str(&decoded);
+        }
+        assert_eq!(
+            text,
+            lines.iter().map(|l| format!("{l}\n")).collect::<String>()
+        );
+    }
+
+    #[test]
+    fn test_pool_flush_and_backpressure() {
+        let mut pool = CompressorPool::new(PoolConfig {
+            workers: 1,
+            queue_capacity: 2,
+            block_size: 1 << 20,
+            flush_interval: Some(Duration::from_millis(20)),
+            ..PoolConfig::default()
+        });
+
+        // The interval closes a small block without an explicit flush
+        pool.send("first").unwrap();
+        pool.send("second").unwrap();
+        let block = pool.recv_timeout(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!((block.seq, block.lines), (0, 2));
+        assert_eq!(pool.blocks_in_flight(), 0);
+
+        // An explicit flush does too
+        let sender = pool.sender();
+        sender.send("third").unwrap();
+        sender.flush().unwrap();
+        assert_eq!(pool.recv().unwrap().unwrap().lines, 1);
+
+        // A full queue hands the line back instead of blocking
+        let mut shed = 0;
+        for i in 0..10_000 {
+            if let Err(TrySendError::Full(line)) = sender.try_send(format!("line {i}")) {
+                assert_eq!(line, format!("line {i}"));
+                shed += 1;
+            }
+        }
+        assert!(pool.queue_depth() <= 2 + 1);
+        drop(sender);
+        let rest = pool.finish().unwrap();
+        assert_eq!(rest.iter().map(|b| b.lines).sum::<usize>() + shed, 10_000);
+    }
+}
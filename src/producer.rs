@@ -0,0 +1,112 @@
+//! Producer metadata — the software and settings that wrote an archive
+//!
+//! Every v3 archive carries a [`HeaderExtension::PRODUCER`] record: crate,
+//! format and zstd versions, a hash of the built-in pattern set and the
+//! writer's settings. A decode problem found years later can then be traced
+//! to the exact producer configuration (`alice-text info` prints it). The
+//! record is JSON, so fields can be added without breaking older readers;
+//! re-encoding an archive (prune, compact, split) records the new producer.
+//!
+//! ```rust,ignore
+//! let metadata = FormatV3Metadata::read_from(&mut file)?;
+//! if let Some(producer) = metadata.producer() {
+//!     println!("written by alice-text {}", producer.crate_version);
+//! }
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format_v3::HeaderExtension;
+use crate::tuned_pattern_learner;
+use crate::{fnv1a, ALICETextError, Result};
+
+/// FNV-1a of the built-in pattern names and regexes, in priority order
+///
+/// Changes whenever a pattern is added, removed, reordered or edited.
+#[must_use]
+pub fn pattern_set_hash() -> u64 {
+    tuned_pattern_learner::builtin_patterns().fold(0xcbf2_9ce4_8422_2325, |h, (name, pattern)| {
+        fnv1a(&[0], fnv1a(pattern.as_bytes(), fnv1a(name.as_bytes(), h)))
+    })
+}
+
+/// Who wrote an archive, and how
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Producer {
+    /// `alice-text` crate version
+    pub crate_version: String,
+    /// Archive format version (`"3.0"`)
+    pub format_version: String,
+    /// zstd library the columns were compressed with
+    pub zstd_version: String,
+    /// [`pattern_set_hash`] of the writing build (16 hex digits)
+    pub pattern_set: String,
+    /// Writer settings (`level`, `zstd_level`, `strip_ansi`, ...)
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+}
+
+impl Producer {
+    /// This build writing `format_version` with `settings`
+    #[must_use]
+    pub fn current(format_version: (u8, u8), settings: BTreeMap<String, String>) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            format_version: format!("{}.{}", format_version.0, format_version.1),
+            zstd_version: zstd::zstd_safe::version_string().to_string(),
+            pattern_set: format!("{:016x}", pattern_set_hash()),
+            settings,
+        }
+    }
+
+    pub(crate) fn to_extension(&self) -> Result<HeaderExtension> {
+        let data = serde_json::to_vec(self)
+            .map_err(|e| ALICETextError::EncodingError(format!("Producer: {e}")))?;
+        Ok(HeaderExtension::new(HeaderExtension::PRODUCER, data))
+    }
+
+    pub(crate) fn from_extension(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data)
+            .map_err(|e| ALICETextError::DecompressionError(format!("Producer: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressOptions, CompressionLevel, FormatV3Metadata, FormatV3Writer};
+
+    #[test]
+    fn test_producer_recorded() {
+        let archive = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_options(CompressOptions::new().with_strip_ansi(true))
+            .compress("2024-01-15 10:30:45 INFO ok\n")
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&archive)).unwrap();
+        let producer = metadata.producer().unwrap();
+        assert_eq!(producer.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(producer.format_version, "3.1");
+        assert!(producer.zstd_version.starts_with("1."));
+        assert_eq!(producer.pattern_set, format!("{:016x}", pattern_set_hash()));
+        assert_eq!(producer.settings["level"], "fast");
+        assert_eq!(producer.settings["zstd_level"], "3");
+        assert_eq!(producer.settings["strip_ansi"], "true");
+
+        // One record, even when the writer is handed an old one
+        let writer = FormatV3Writer::matching(&metadata, &[]).unwrap();
+        let again = writer.compress("x\n").unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&again)).unwrap();
+        let records = metadata
+            .extensions
+            .iter()
+            .filter(|e| e.tag == HeaderExtension::PRODUCER)
+            .count();
+        assert_eq!(records, 1);
+        assert_eq!(metadata.producer().unwrap().settings["strip_ansi"], "false");
+    }
+}
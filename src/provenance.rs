@@ -0,0 +1,297 @@
+//! Provenance — per-line origin (source, byte offset, line number) for merged archives
+//!
+//! When several inputs are merged into one v3 archive, the optional
+//! `provenance` column records for every archive line which source it came
+//! from (dictionary-encoded) and where it started in that source. It is
+//! populated by [`SourceBatch`] / `FormatV3Writer::compress_batch`, the
+//! `ArchiveAppender::append_line_from` API and the CLI `merge` command, and
+//! is selectable in queries as `provenance` (`source:line`).
+//!
+//! ```rust,ignore
+//! use alice_text::{CompressionLevel, FormatV3Writer, SourceBatch};
+//!
+//! let mut batch = SourceBatch::new();
+//! batch.add("web-1.log", &web1);
+//! batch.add("web-2.log", &web2);
+//! let archive = FormatV3Writer::new(CompressionLevel::Balanced).compress_batch(&batch)?;
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+use serde::{Deserialize, Serialize};
+
+/// Where one archive line came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineOrigin {
+    /// Index into [`ProvenanceColumn::sources`]
+    pub source: u32,
+    /// Byte offset of the line in its source
+    pub byte_offset: u64,
+    /// 1-based line number in its source
+    pub line_number: u64,
+}
+
+/// Source dictionary plus one [`LineOrigin`] per archive line
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvenanceColumn {
+    /// Source names (file paths, stream ids), referenced by `LineOrigin::source`
+    pub sources: Vec<String>,
+    /// Origin of each archive line, in order
+    pub lines: Vec<LineOrigin>,
+}
+
+impl ProvenanceColumn {
+    /// Number of lines covered
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Dictionary id of `source`, added if new
+    pub fn source_id(&mut self, source: &str) -> u32 {
+        match self.sources.iter().position(|s| s == source) {
+            Some(id) => id as u32,
+            None => {
+                self.sources.push(source.to_string());
+                (self.sources.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Source name and origin of archive line `line`
+    #[must_use]
+    pub fn get(&self, line: usize) -> Option<(&str, LineOrigin)> {
+        let origin = *self.lines.get(line)?;
+        let source = self.sources.get(origin.source as usize)?;
+        Some((source.as_str(), origin))
+    }
+
+    /// `source:line_number` for archive line `line` (the query-engine value)
+    #[must_use]
+    pub fn describe(&self, line: usize) -> Option<String> {
+        self.get(line)
+            .map(|(source, origin)| format!("{source}:{}", origin.line_number))
+    }
+
+    /// Keep the lines for which `keep(line_index)` holds (dictionary unchanged)
+    #[must_use]
+    pub fn retain_lines(&self, keep: impl Fn(usize) -> bool) -> Self {
+        Self {
+            sources: self.sources.clone(),
+            lines: self
+                .lines
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| keep(*i))
+                .map(|(_, origin)| *origin)
+                .collect(),
+        }
+    }
+}
+
+/// Sources whose next position a [`SourceBatch`] remembers
+///
+/// Past this, positions of sources not in the current batch are forgotten;
+/// such a source restarts at offset 0, line 1 if it is added again.
+pub const MAX_TRACKED_SOURCES: usize = 4096;
+
+/// Several named inputs concatenated into one text, with line provenance
+///
+/// Every input is terminated with `\n` so lines never span sources. Adding
+/// the same source again continues its offsets and line numbers, also across
+/// [`Self::clear`] (used by the appender between rotations), for up to
+/// [`MAX_TRACKED_SOURCES`] sources.
+#[derive(Debug, Clone, Default)]
+pub struct SourceBatch {
+    text: String,
+    provenance: ProvenanceColumn,
+    /// Next (byte offset, line number) per source name
+    positions: HashMap<String, (u64, u64)>,
+}
+
+impl SourceBatch {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the lines of `text`, recording `source` as their origin
+    pub fn add(&mut self, source: &str, text: &str) {
+        let (id, mut position) = self.start(source);
+        for line in text.split_inclusive('\n') {
+            self.push_line(id, &mut position, line, line.len());
+        }
+        self.finish(source, position);
+    }
+
+    /// [`Self::add`] reading `reader` a line at a time (the input is never
+    /// held as a whole); invalid UTF-8 is replaced with `U+FFFD`, offsets
+    /// stay in source bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns the reader's I/O error; lines read before it stay in the batch.
+    pub fn add_reader(&mut self, source: &str, mut reader: impl BufRead) -> io::Result<()> {
+        let (id, mut position) = self.start(source);
+        let mut line = Vec::new();
+        let result = loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break Ok(()),
+                Ok(len) => self.push_line(id, &mut position, &String::from_utf8_lossy(&line), len),
+                Err(e) => break Err(e),
+            }
+        };
+        self.finish(source, position);
+        result
+    }
+
+    /// Dictionary id and next (byte offset, line number) of `source`
+    fn start(&mut self, source: &str) -> (u32, (u64, u64)) {
+        if self.positions.len() >= MAX_TRACKED_SOURCES && !self.positions.contains_key(source) {
+            let current = &self.provenance.sources;
+            self.positions.retain(|name, _| current.contains(name));
+        }
+        let id = self.provenance.source_id(source);
+        (id, self.positions.get(source).copied().unwrap_or((0, 1)))
+    }
+
+    /// Append one line that took `source_len` bytes in its source
+    fn push_line(&mut self, id: u32, position: &mut (u64, u64), line: &str, source_len: usize) {
+        let (offset, line_number) = position;
+        self.provenance.lines.push(LineOrigin {
+            source: id,
+            byte_offset: *offset,
+            line_number: *line_number,
+        });
+        self.text.push_str(line);
+        *offset += source_len as u64;
+        *line_number += 1;
+    }
+
+    fn finish(&mut self, source: &str, position: (u64, u64)) {
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+        self.positions.insert(source.to_string(), position);
+    }
+
+    /// Merged text (what gets compressed)
+    #[inline]
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Line origins for [`Self::text`]
+    #[inline]
+    #[must_use]
+    pub const fn provenance(&self) -> &ProvenanceColumn {
+        &self.provenance
+    }
+
+    /// Merged text size in bytes
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Drop the buffered lines, keeping per-source positions
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.provenance = ProvenanceColumn::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_records_origins() {
+        let mut batch = SourceBatch::new();
+        batch.add("a.log", "one\ntwo\n");
+        batch.add("b.log", "alpha\nbeta");
+        batch.add("a.log", "three\n");
+
+        assert_eq!(batch.text(), "one\ntwo\nalpha\nbeta\nthree\n");
+        let provenance = batch.provenance();
+        assert_eq!(provenance.sources, ["a.log", "b.log"]);
+        assert_eq!(provenance.len(), batch.text().lines().count());
+        assert_eq!(
+            provenance.get(3),
+            Some((
+                "b.log",
+                LineOrigin {
+                    source: 1,
+                    byte_offset: 6,
+                    line_number: 2
+                }
+            ))
+        );
+        // Same source continues where it left off
+        assert_eq!(provenance.describe(4).as_deref(), Some("a.log:3"));
+        assert_eq!(provenance.lines[4].byte_offset, 8);
+
+        let kept = provenance.retain_lines(|i| i != 0);
+        assert_eq!(kept.describe(0).as_deref(), Some("a.log:2"));
+
+        batch.clear();
+        batch.add("a.log", "four\n");
+        assert_eq!(batch.provenance().describe(0).as_deref(), Some("a.log:4"));
+    }
+
+    #[test]
+    fn test_add_reader_matches_add() {
+        let text = "one\ntwo\nthree";
+        let mut added = SourceBatch::new();
+        added.add("a.log", text);
+        let mut read = SourceBatch::new();
+        read.add_reader("a.log", text.as_bytes()).unwrap();
+        assert_eq!(read.text(), added.text());
+        assert_eq!(read.provenance(), added.provenance());
+
+        // Offsets count source bytes, not the replacement character
+        read.add_reader("b.log", &b"\xff\xfe bad\nok\n"[..])
+            .unwrap();
+        assert_eq!(read.provenance().describe(4).as_deref(), Some("b.log:2"));
+        assert_eq!(read.provenance().lines[4].byte_offset, 7);
+    }
+
+    #[test]
+    fn test_positions_are_capped() {
+        let mut batch = SourceBatch::new();
+        for i in 0..MAX_TRACKED_SOURCES {
+            batch.add(&format!("{i}.log"), "x\n");
+        }
+        batch.clear();
+        batch.add("0.log", "y\n");
+        assert_eq!(batch.provenance().describe(0).as_deref(), Some("0.log:2"));
+
+        // A new source past the cap forgets the ones not in this batch
+        batch.add("new.log", "z\n");
+        assert_eq!(batch.positions.len(), 2);
+        batch.add("1.log", "w\n");
+        assert_eq!(batch.provenance().describe(2).as_deref(), Some("1.log:1"));
+        batch.add("0.log", "v\n");
+        assert_eq!(batch.provenance().describe(3).as_deref(), Some("0.log:3"));
+    }
+}
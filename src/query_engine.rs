@@ -2,8 +2,12 @@
 //!
 //! Optimizations:
 //! - **Typed Filtering**: Compares raw primitives (u8, u32, f64) instead of Strings
-//! - **Parallel Decompression**: Uses Rayon to fetch columns simultaneously
-//! - **Memory Mapping**: Uses mmap for zero-copy random access
+//! - **Parallel Decompression** (`parallel` feature): Uses Rayon to fetch
+//!   columns simultaneously, on the global pool or a bounded one
+//!   (`QueryEngine::with_threads`)
+//! - **Memory Mapping** (`mmap` feature): Uses mmap for zero-copy random access
+//! - **Column Cache** (optional): decoded columns persist on disk across
+//!   processes ([`QueryEngine::with_cache`])
 //!
 //! ## Example
 //!
@@ -16,23 +20,50 @@
 //! // Filter without String allocation
 //! let errors = engine.filter_op("log_levels", Op::Eq, "ERROR")?;
 //!
+//! // Filters on several columns combine on line numbers
+//! let lines = engine.filter_multi(&[("log_levels", Op::Eq, "ERROR"), ("ipv4", Op::StartsWith, "10.")])?;
+//!
 //! // Parallel column fetch
 //! let result = engine.query(&["timestamps", "ipv4"], "log_levels", Op::Eq, "ERROR")?;
 //! ```
 
-use crate::columnar_encoder::LogLevel;
+use crate::classifier::SourceKind;
+use crate::column_cache::{CacheKey, ColumnCache};
+use crate::column_codec::{
+    column_slice, decode_column, decode_runs, decode_values, ColumnEncoding, DedupColumn,
+    EncodableValue,
+};
+use crate::column_plugin::ColumnPlugin;
+use crate::columnar_encoder::{
+    format_mac, parse_byte_size, parse_duration, ColumnarPayload, LinePiece, LogLevel,
+    SkeletonToken, TimestampColumn, HTTP_METHODS,
+};
+use crate::filter::parse_timestamp_ms;
+pub use crate::filter::{parse_filter, parse_negation, parse_relative_time, Op};
+pub use crate::format_v3::{compress_v3, decompress_v3};
 use crate::format_v3::{
-    ColumnType, CompressionLevel, FormatV3Metadata, FormatV3Writer, PartialPayload,
+    ColumnEntry, ColumnSummary, ColumnType, DecodeOptions, FormatV3Metadata, FormatV3Writer,
+    PartialPayload, RowMap,
 };
-use crate::{ALICETextError, Result};
-use chrono::NaiveDateTime;
+use crate::kubernetes::{CriColumn, KlogColumn, NamespaceColumn, PodColumn};
+use crate::line_index::LineIdColumn;
+use crate::row_set::RowSet;
+use crate::saved_view::{self, SavedView};
+use crate::{telemetry, ALICETextError, Result};
+#[cfg(feature = "mmap")]
 use memmap2::Mmap;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+#[cfg(feature = "mmap")]
 use std::fs::File;
 use std::io::{Cursor, Read};
+#[cfg(feature = "mmap")]
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
 /// Query result row
 #[derive(Debug, Clone)]
@@ -40,6 +71,17 @@ pub struct QueryRow {
     pub values: HashMap<String, String>,
 }
 
+/// Matched row paired with its reconstructed source line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineMatch {
+    /// 0-based line number in the archive
+    pub line_number: usize,
+    /// Original line (without trailing `\n`)
+    pub line: String,
+    /// Selected column values for this row
+    pub values: HashMap<String, String>,
+}
+
 /// Query result set
 #[derive(Debug)]
 pub struct QueryResult {
@@ -92,22 +134,33 @@ impl QueryResult {
     }
 }
 
-/// Comparison operators
-#[derive(Debug, Clone, Copy)]
-pub enum Op {
-    Eq,
-    Ne,
-    Lt,
-    Le,
-    Gt,
-    Ge,
-    Contains,
-    StartsWith,
-    EndsWith,
+/// Line selection for [`QueryEngine::sample`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SamplingStrategy {
+    /// First `n` lines
+    #[default]
+    Head,
+    /// `n` lines evenly spaced across the archive
+    Uniform,
+    /// Round-robin across log levels, so rare levels (ERROR, FATAL) are represented
+    PerLevel,
+}
+
+impl SamplingStrategy {
+    /// Parse `head`, `uniform` or `per-level` (case-insensitive)
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "head" => Some(Self::Head),
+            "uniform" => Some(Self::Uniform),
+            "per-level" | "level" => Some(Self::PerLevel),
+            _ => None,
+        }
+    }
 }
 
 /// Column statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnStats {
     pub name: String,
     pub col_type: ColumnType,
@@ -116,7 +169,7 @@ pub struct ColumnStats {
 }
 
 /// File statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileStats {
     pub original_size: u64,
     pub compressed_size: u64,
@@ -124,12 +177,106 @@ pub struct FileStats {
     pub row_count: u64,
     pub column_count: usize,
     pub columns: Vec<ColumnStats>,
+    /// Compress-time min/max/quantiles (timestamps, ipv4, numbers)
+    #[serde(default)]
+    pub summaries: Vec<ColumnSummary>,
+    /// Input bytes held in value columns (`None` for older archives)
+    #[serde(default)]
+    pub value_bytes: Option<u64>,
+    /// `value_bytes / original_size`: the share of the input stored as
+    /// typed values rather than skeleton text
+    #[serde(default)]
+    pub exception_rate: Option<f64>,
+    /// Detected source kind (`None` unless the writer classified the input)
+    #[serde(default)]
+    pub source_kind: Option<SourceKind>,
 }
 
 /// Query Engine with Memory Mapping (Optimized)
 pub struct QueryEngine<S: QuerySource> {
     source: S,
     metadata: FormatV3Metadata,
+    /// On-disk column cache and this archive's content hash
+    cache: Option<(ColumnCache, u64)>,
+    /// Views added by the caller (e.g. from a sidecar), over the archive's own
+    views: Vec<SavedView>,
+    /// Column plugins the archive was written with
+    plugins: Vec<Arc<dyn ColumnPlugin>>,
+    /// Skeleton and placeholder map split into lines, built by the first
+    /// [`Self::lines`] call
+    line_starts: OnceLock<LineStarts>,
+    /// Pool for parallel column reads (`None`: rayon's global pool)
+    #[cfg(feature = "parallel")]
+    threads: Option<Arc<ThreadPool>>,
+}
+
+/// An archive's skeleton and placeholder map, with where each line starts
+///
+/// Paging through an archive (the viewer) fetches a few lines at a time;
+/// keeping this around means only the value columns are decoded per fetch.
+struct LineStarts {
+    skeleton: Vec<SkeletonToken>,
+    placeholder_map: Vec<(u8, u32)>,
+    /// `(token, byte offset)` of each line's first piece
+    starts: Vec<(usize, usize)>,
+}
+
+impl LineStarts {
+    /// Index the lines of `structure` as [`ColumnarPayload::split_lines`] splits them
+    fn new(structure: ColumnarPayload) -> Self {
+        let mut starts = Vec::new();
+        let mut open = false;
+        for (t, token) in structure.skeleton_tokens.iter().enumerate() {
+            match token {
+                SkeletonToken::Text(text) => {
+                    let mut offset = 0;
+                    for piece in text.split_inclusive('\n') {
+                        if !open {
+                            starts.push((t, offset));
+                        }
+                        offset += piece.len();
+                        open = !piece.ends_with('\n');
+                    }
+                }
+                SkeletonToken::Ref(_) => {
+                    if !open {
+                        starts.push((t, 0));
+                        open = true;
+                    }
+                }
+            }
+        }
+        Self {
+            skeleton: structure.skeleton_tokens,
+            placeholder_map: structure.placeholder_map,
+            starts,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Pieces of line `n` (`n < self.len()`), trailing `\n` included
+    fn line(&self, n: usize) -> Vec<LinePiece<'_>> {
+        let (first, mut offset) = self.starts[n];
+        let mut pieces = Vec::new();
+        for token in &self.skeleton[first..] {
+            match token {
+                SkeletonToken::Text(text) => {
+                    for piece in text[offset..].split_inclusive('\n') {
+                        pieces.push(LinePiece::Text(piece));
+                        if piece.ends_with('\n') {
+                            return pieces;
+                        }
+                    }
+                }
+                SkeletonToken::Ref(idx) => pieces.push(LinePiece::Ref(*idx)),
+            }
+            offset = 0;
+        }
+        pieces
+    }
 }
 
 /// Trait for different data sources
@@ -138,10 +285,12 @@ pub trait QuerySource: Send + Sync {
 }
 
 /// Memory-mapped file source (zero-copy)
+#[cfg(feature = "mmap")]
 pub struct MmapSource {
     mmap: Arc<Mmap>,
 }
 
+#[cfg(feature = "mmap")]
 impl QuerySource for MmapSource {
     fn as_slice(&self) -> &[u8] {
         &self.mmap[..]
@@ -159,6 +308,24 @@ impl QuerySource for BufferSource {
     }
 }
 
+/// Borrowed buffer source (zero-copy, e.g. an archive held in a cache)
+pub struct SliceSource<'a> {
+    data: &'a [u8],
+}
+
+impl QuerySource for SliceSource<'_> {
+    fn as_slice(&self) -> &[u8] {
+        self.data
+    }
+}
+
+// Queries take `&self`: one engine serves every thread of a server
+const _: () = crate::assert_send_sync::<QueryEngine<BufferSource>>();
+const _: () = crate::assert_send_sync::<QueryEngine<SliceSource<'static>>>();
+#[cfg(feature = "mmap")]
+const _: () = crate::assert_send_sync::<QueryEngine<MmapSource>>();
+
+#[cfg(feature = "mmap")]
 impl QueryEngine<MmapSource> {
     /// Open a file with memory mapping for maximum speed
     ///
@@ -168,15 +335,8 @@ impl QueryEngine<MmapSource> {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = File::open(path.as_ref()).map_err(ALICETextError::Io)?;
         let mmap = unsafe { Mmap::map(&file).map_err(ALICETextError::Io)? };
-
-        let mut cursor = Cursor::new(&mmap[..]);
-        let metadata = FormatV3Metadata::read_from(&mut cursor)?;
-
-        Ok(Self {
-            source: MmapSource {
-                mmap: Arc::new(mmap),
-            },
-            metadata,
+        Self::from_source(MmapSource {
+            mmap: Arc::new(mmap),
         })
     }
 }
@@ -190,24 +350,200 @@ impl QueryEngine<BufferSource> {
     pub fn from_reader<R: Read>(mut reader: R) -> Result<Self> {
         let mut data = Vec::new();
         reader.read_to_end(&mut data)?;
+        Self::from_source(BufferSource {
+            data: Arc::new(data),
+        })
+    }
 
-        let mut cursor = Cursor::new(&data[..]);
-        let metadata = FormatV3Metadata::read_from(&mut cursor)?;
-
-        Ok(Self {
-            source: BufferSource {
+    /// Copy at most [`DecodeLimits::max_archive_bytes`](crate::DecodeLimits)
+    /// from `reader` into memory and parse it with `options`
+    ///
+    /// For archives from untrusted sources (see
+    /// [`DecodeProfile::Untrusted`](crate::DecodeProfile)): the buffer is
+    /// private to the engine, so the bytes checked are the bytes queried.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if the input is larger than the limit, or an
+    /// error if reading or parsing fails.
+    pub fn from_reader_with<R: Read>(reader: R, options: impl Into<DecodeOptions>) -> Result<Self> {
+        let options = options.into();
+        let limit = options.limits.max_archive_bytes;
+        let mut data = Vec::new();
+        reader
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut data)?;
+        options.limits.check_archive(data.len() as u64)?;
+        Self::from_source_with(
+            BufferSource {
                 data: Arc::new(data),
             },
-            metadata,
-        })
+            options,
+        )
+    }
+}
+
+impl<'a> QueryEngine<SliceSource<'a>> {
+    /// Query an archive the caller already holds in memory, without copying it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if parsing the metadata fails.
+    pub fn from_slice(data: &'a [u8]) -> Result<Self> {
+        Self::from_source(SliceSource { data })
     }
 }
 
 impl<S: QuerySource> QueryEngine<S> {
+    /// Engine over any source (reads the metadata only)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if parsing the metadata fails.
+    pub fn from_source(source: S) -> Result<Self> {
+        Self::from_source_with(source, DecodeOptions::default())
+    }
+
+    /// Engine over any source, parsed with `options`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if parsing the metadata fails or `options` reject the archive.
+    pub fn from_source_with(source: S, options: impl Into<DecodeOptions>) -> Result<Self> {
+        let metadata =
+            FormatV3Metadata::read_from_with(&mut Cursor::new(source.as_slice()), options.into())?;
+        Ok(Self {
+            source,
+            metadata,
+            cache: None,
+            views: Vec::new(),
+            plugins: Vec::new(),
+            line_starts: OnceLock::new(),
+            #[cfg(feature = "parallel")]
+            threads: None,
+        })
+    }
+
+    /// Serve decoded columns from (and store them in) an on-disk cache
+    ///
+    /// Hashes the archive once to key its entries.
+    #[must_use]
+    pub fn with_cache(mut self, cache: ColumnCache) -> Self {
+        let file_hash = ColumnCache::file_hash(self.source.as_slice());
+        self.cache = Some((cache, file_hash));
+        self
+    }
+
+    /// Read columns in parallel on a dedicated pool of `threads` threads
+    /// (at least one) instead of rayon's global pool, which has one thread
+    /// per core
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the threads cannot be spawned.
+    #[cfg(feature = "parallel")]
+    pub fn with_threads(self, threads: usize) -> Result<Self> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads.max(1))
+            .thread_name(|i| format!("alice-query-{i}"))
+            .build()
+            .map_err(|e| ALICETextError::Io(std::io::Error::other(e)))?;
+        Ok(self.with_thread_pool(Arc::new(pool)))
+    }
+
+    /// Read columns in parallel on `pool` (e.g. one shared by several engines)
+    #[cfg(feature = "parallel")]
+    #[must_use]
+    pub fn with_thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.threads = Some(pool);
+        self
+    }
+
+    /// Run `op` on this engine's pool (or the global one)
+    #[cfg(feature = "parallel")]
+    fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.threads {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Fetch several columns, in parallel with the `parallel` feature
+    fn read_raw_columns(&self, col_types: &[ColumnType]) -> Result<Vec<PartialPayload>> {
+        #[cfg(feature = "parallel")]
+        return self.install(|| {
+            col_types
+                .par_iter()
+                .map(|&ct| self.read_raw_column(ct))
+                .collect()
+        });
+        #[cfg(not(feature = "parallel"))]
+        col_types
+            .iter()
+            .map(|&ct| self.read_raw_column(ct))
+            .collect()
+    }
+
+    /// The attached column cache, if any
+    #[must_use]
+    pub fn cache(&self) -> Option<&ColumnCache> {
+        self.cache.as_ref().map(|(cache, _)| cache)
+    }
+
+    /// Add saved views (e.g. [`read_sidecar`](crate::saved_view::read_sidecar)),
+    /// replacing archive views of the same name
+    #[must_use]
+    pub fn with_views(mut self, views: Vec<SavedView>) -> Self {
+        self.views = saved_view::merge(std::mem::take(&mut self.views), &views);
+        self
+    }
+
+    /// Decode the archive's plugin column with `plugins` (see
+    /// [`FormatV3Writer::decompress_with_plugins`]); without them, reads that
+    /// need plugin values fail
+    #[must_use]
+    pub fn with_plugins(mut self, plugins: &[Arc<dyn ColumnPlugin>]) -> Self {
+        self.plugins = plugins.to_vec();
+        self
+    }
+
+    /// Saved views: the archive's, then those added with [`Self::with_views`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive's view record does not deserialize.
+    pub fn views(&self) -> Result<Vec<SavedView>> {
+        Ok(saved_view::merge(self.metadata.saved_views()?, &self.views))
+    }
+
+    /// Run the saved view `name`: lines matching its filters, with its
+    /// selected columns (see [`Self::query_at`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no view has that name, a filter does not parse or
+    /// names an unknown column, or decompression fails.
+    pub fn view(&self, name: &str) -> Result<Vec<LineMatch>> {
+        let view = self
+            .views()?
+            .into_iter()
+            .find(|v| v.name == name)
+            .ok_or_else(|| ALICETextError::InvalidQuery(format!("Unknown view: {name}")))?;
+        let (required, excluded) = view.conditions()?;
+        let select: Vec<&str> = view.select.iter().map(String::as_str).collect();
+        self.query_at(&select, &self.filter_except(&required, &excluded)?)
+    }
+
+    /// Parsed header and column directory
+    pub(crate) const fn metadata(&self) -> &FormatV3Metadata {
+        &self.metadata
+    }
+
     /// Get file statistics (header only - O(1))
     pub fn stats(&self) -> FileStats {
         let compressed_size = self.metadata.compressed_size();
         let original_size = self.metadata.header.original_length;
+        let value_bytes = self.metadata.value_bytes();
 
         FileStats {
             original_size,
@@ -230,6 +566,16 @@ impl<S: QuerySource> QueryEngine<S> {
                     compressed_size: c.compressed_size,
                 })
                 .collect(),
+            summaries: self.metadata.summaries.clone(),
+            value_bytes,
+            exception_rate: value_bytes.map(|bytes| {
+                if original_size > 0 {
+                    bytes as f64 / original_size as f64
+                } else {
+                    0.0
+                }
+            }),
+            source_kind: self.metadata.source_kind(),
         }
     }
 
@@ -259,21 +605,21 @@ impl<S: QuerySource> QueryEngine<S> {
 
     /// Read multiple columns (parallel decompression)
     ///
+    /// Row `i` holds value `i` of each column, which is not the same line
+    /// once lines lack a value or carry several; [`Self::select_rows`] lines
+    /// the columns up by line.
+    ///
     /// # Errors
     ///
     /// Returns an error if any column name is unknown or decompression fails.
     pub fn select_columns(&self, names: &[&str]) -> Result<QueryResult> {
+        let timer = telemetry::Timer::start();
         let col_types: Vec<ColumnType> = names
             .iter()
             .map(|n| self.name_to_type(n))
             .collect::<Result<Vec<_>>>()?;
 
-        // Parallel column fetch using Rayon
-        let partials: Result<Vec<PartialPayload>> = col_types
-            .par_iter()
-            .map(|&ct| self.read_raw_column(ct))
-            .collect();
-        let partials = partials?;
+        let partials = self.read_raw_columns(&col_types)?;
 
         // Get max row count
         let max_rows = col_types
@@ -301,67 +647,358 @@ impl<S: QuerySource> QueryEngine<S> {
             result.rows.push(row);
         }
 
+        telemetry::record_query(&timer, "select", result.len());
+        Ok(result)
+    }
+
+    /// Read multiple columns, one result row per line
+    ///
+    /// Row `i` holds the first value of each column found on line `i` (lines
+    /// without any give an empty row). Also reads the skeleton and the
+    /// placeholder map to map values to lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any column name is unknown or decompression fails.
+    pub fn select_rows(&self, names: &[&str]) -> Result<QueryResult> {
+        let timer = telemetry::Timer::start();
+        let rows: Vec<QueryRow> = self
+            .values_by_line(names, None)?
+            .into_iter()
+            .map(|(_, row)| row)
+            .collect();
+        let result = QueryResult {
+            columns: names.iter().map(std::string::ToString::to_string).collect(),
+            rows,
+        };
+        telemetry::record_query(&timer, "select_rows", result.len());
         Ok(result)
     }
 
+    /// Lines matching every `(column, op, value)` filter (AND)
+    ///
+    /// Unlike [`Self::filter_op`], whose indices are positions within one
+    /// column, these are line numbers, so filters on different columns
+    /// combine correctly when some lines lack a field or carry several. The
+    /// skeleton and placeholder map are read once for all filters; the line
+    /// sets are intersected smallest first, stopping as soon as one is
+    /// empty. No filters match every line.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column name is unknown, a filter value cannot be
+    /// parsed, or decompression fails.
+    pub fn filter_multi(&self, filters: &[(&str, Op, &str)]) -> Result<RowSet> {
+        let timer = telemetry::Timer::start();
+        for &(column, _, _) in filters {
+            self.name_to_type(column)?;
+        }
+        let structure =
+            self.read_payload(|c| matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap))?;
+        let lines = ColumnarPayload::split_lines(&structure.skeleton_tokens);
+        if filters.is_empty() {
+            telemetry::record_query(&timer, "filter_multi", lines.len());
+            return Ok(RowSet::full(lines.len()));
+        }
+
+        let mut sets = Vec::with_capacity(filters.len());
+        for &(column, op, value) in filters {
+            let indices = self.scan_column(column, op, value)?;
+            let set =
+                Self::indices_to_lines(&structure, &lines, self.name_to_type(column)?, indices);
+            // Later filters cannot bring lines back
+            if set.is_empty() {
+                telemetry::record_query(&timer, "filter_multi", 0);
+                return Ok(set);
+            }
+            sets.push(set);
+        }
+        sets.sort_by_key(RowSet::len);
+        let mut sets = sets.into_iter();
+        let mut rows = sets.next().unwrap_or_default();
+        for set in sets {
+            rows = &rows & &set;
+            if rows.is_empty() {
+                break;
+            }
+        }
+        telemetry::record_query(&timer, "filter_multi", rows.len());
+        Ok(rows)
+    }
+
+    /// Lines matching every `filters` entry and none of `excluded` (NOT)
+    ///
+    /// Negation is per line: excluding `log_levels=ERROR` drops every line
+    /// with an ERROR level and keeps lines without a level, whereas the
+    /// value filter `log_levels!=ERROR` only keeps lines that have some
+    /// other level.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column name is unknown, a filter value cannot be
+    /// parsed, or decompression fails.
+    pub fn filter_except(
+        &self,
+        filters: &[(&str, Op, &str)],
+        excluded: &[(&str, Op, &str)],
+    ) -> Result<RowSet> {
+        for &(column, _, _) in excluded {
+            self.name_to_type(column)?;
+        }
+        let mut rows = self.filter_multi(filters)?;
+        for &(column, op, value) in excluded {
+            if rows.is_empty() {
+                break;
+            }
+            rows = &rows - &self.matching_lines(column, op, value)?;
+        }
+        Ok(rows)
+    }
+
+    /// Lines matching every filter (see [`Self::filter_multi`]), with their
+    /// text and the first value of each selected column on them
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column name is unknown, a filter value cannot be
+    /// parsed, or decompression fails.
+    pub fn query_rows(
+        &self,
+        select_columns: &[&str],
+        filters: &[(&str, Op, &str)],
+    ) -> Result<Vec<LineMatch>> {
+        let line_numbers = self.filter_multi(filters)?;
+        self.query_at(select_columns, &line_numbers)
+    }
+
+    /// Lines in `rows` (e.g. a combination of [`Self::filter_multi`] results),
+    /// with their text and the first value of each selected column on them
+    ///
+    /// Line numbers past the end of the archive are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a column name is unknown or decompression fails.
+    pub fn query_at(&self, select_columns: &[&str], rows: &RowSet) -> Result<Vec<LineMatch>> {
+        let timer = telemetry::Timer::start();
+        if rows.is_empty() {
+            telemetry::record_query(&timer, "query_rows", 0);
+            return Ok(Vec::new());
+        }
+        let values = self.values_by_line(select_columns, Some(rows))?;
+        let line_numbers: Vec<usize> = values.iter().map(|&(line, _)| line).collect();
+        let rendered = self.lines(&line_numbers)?;
+        let matches: Vec<LineMatch> = values
+            .into_iter()
+            .zip(rendered)
+            .map(|((line_number, row), line)| LineMatch {
+                line_number,
+                line,
+                values: row.values,
+            })
+            .collect();
+
+        telemetry::record_query(&timer, "query_rows", matches.len());
+        Ok(matches)
+    }
+
+    /// First value of each of `names` on every line in `lines` (all lines if `None`)
+    fn values_by_line(
+        &self,
+        names: &[&str],
+        lines: Option<&RowSet>,
+    ) -> Result<Vec<(usize, QueryRow)>> {
+        let col_types: Vec<ColumnType> = names
+            .iter()
+            .map(|n| self.name_to_type(n))
+            .collect::<Result<Vec<_>>>()?;
+        let partial =
+            FormatV3Writer::read_columns_by_row_with(&self.metadata, &col_types, |entry| {
+                self.column_bytes(entry)
+            })?;
+        let no_rows = RowMap::default();
+        let row_map = partial.rows.as_ref().unwrap_or(&no_rows);
+
+        let all: RowSet;
+        let lines = if let Some(lines) = lines {
+            lines
+        } else {
+            all = RowSet::full(row_map.len());
+            &all
+        };
+        Ok(lines
+            .iter()
+            .take_while(|&line| line < row_map.len())
+            .map(|line| {
+                let values = names
+                    .iter()
+                    .zip(&col_types)
+                    .filter_map(|(name, &col_type)| {
+                        let idx = row_map.values(line, col_type).next()?;
+                        let value = self.get_value_at(&partial, col_type, idx)?;
+                        Some(((*name).to_string(), value))
+                    })
+                    .collect();
+                (line, QueryRow { values })
+            })
+            .collect())
+    }
+
     /// Optimized filter: Scans raw primitives without String allocation
     ///
+    /// The indices are positions within `column`, not line numbers: index `i`
+    /// is the `i`-th value of the column wherever it sits. Use
+    /// [`Self::filter_multi`] to combine filters on several columns.
+    ///
     /// # Errors
     ///
     /// Returns an error if the column name is unknown, the value cannot be parsed, or decompression fails.
-    pub fn filter_op(&self, column: &str, op: Op, value: &str) -> Result<Vec<usize>> {
+    pub fn filter_op(&self, column: &str, op: Op, value: &str) -> Result<RowSet> {
+        let timer = telemetry::Timer::start();
+        let indices: RowSet = self.scan_column(column, op, value)?.into_iter().collect();
+        telemetry::record_query(&timer, "filter", indices.len());
+        Ok(indices)
+    }
+
+    /// Typed scan behind `filter_op` / `query`
+    fn scan_column(&self, column: &str, op: Op, value: &str) -> Result<Vec<usize>> {
         let col_type = self.name_to_type(column)?;
-        let partial = self.read_raw_column(col_type)?;
 
-        // Typed comparison dispatch - no String allocations in hot loop!
-        #[allow(clippy::option_if_let_else)]
+        // Substring operators compare the text form, whatever the column type
+        if matches!(op, Op::Contains | Op::StartsWith | Op::EndsWith) {
+            let partial = self.read_raw_column(col_type)?;
+            let strings = self.partial_to_strings(&partial, col_type)?;
+            return Ok(self.scan_strings(&strings, op, value));
+        }
+
+        if matches!(op, Op::Between) && op.operands(value).count() != 2 {
+            return Err(ALICETextError::InvalidQuery(format!(
+                "Range filters need low..high, got {value}"
+            )));
+        }
+
+        // Subnet filters compare masked addresses
+        if matches!(op, Op::InCidr) {
+            return match col_type {
+                ColumnType::IPv4 => {
+                    let block =
+                        parse_cidr::<std::net::Ipv4Addr>(value, 32, |a| u32::from(a).into())?;
+                    let bytes = self.column_block(col_type)?;
+                    self.scan_values::<u32>(col_type, bytes.as_deref(), |addrs| {
+                        Ok(scan_cidr(addrs, block))
+                    })
+                }
+                ColumnType::IPv6 => {
+                    let block = parse_cidr::<std::net::Ipv6Addr>(value, 128, u128::from)?;
+                    let bytes = self.column_block(col_type)?;
+                    self.scan_values::<u128>(col_type, bytes.as_deref(), |addrs| {
+                        Ok(scan_cidr(addrs, block))
+                    })
+                }
+                _ => Err(ALICETextError::InvalidQuery(format!(
+                    "CIDR filters need an ipv4 or ipv6 column, not {column}"
+                ))),
+            };
+        }
+
+        // Fixed-width columns are scanned in place in the decompressed block
+        // - no decoding, no String allocations in the hot loop
+        if matches!(
+            col_type,
+            ColumnType::LogLevels
+                | ColumnType::IPv4
+                | ColumnType::IPv6
+                | ColumnType::Numbers
+                | ColumnType::UUIDs
+                | ColumnType::HttpStatuses
+                | ColumnType::Pids
+                | ColumnType::Tids
+        ) {
+            let bytes = self.column_block(col_type)?;
+            let block = bytes.as_deref();
+            return match col_type {
+                ColumnType::LogLevels => self.scan_values::<u8>(col_type, block, |levels| {
+                    self.scan_typed(levels, op, value, |v| Ok(LogLevel::parse_level(v) as u8))
+                }),
+                ColumnType::IPv4 => self.scan_values::<u32>(col_type, block, |addrs| {
+                    self.scan_typed(addrs, op, value, |v| self.parse_ipv4(v))
+                }),
+                ColumnType::IPv6 => self.scan_values::<u128>(col_type, block, |addrs| {
+                    self.scan_typed(addrs, op, value, |v| self.parse_ipv6(v))
+                }),
+                ColumnType::Numbers => {
+                    let targets = op
+                        .operands(value)
+                        .map(|v| {
+                            v.parse::<f64>().map_err(|_| {
+                                ALICETextError::InvalidQuery(format!("Invalid number: {v}"))
+                            })
+                        })
+                        .collect::<Result<Vec<f64>>>()?;
+                    // Encoded as the values' bits; a `&[u64]` is a `&[f64]` in place
+                    self.scan_values::<u64>(col_type, block, |bits| {
+                        Ok(bits.map_or_else(Vec::new, |bits| {
+                            self.scan_f64(bytemuck::cast_slice(bits), op, &targets)
+                        }))
+                    })
+                }
+                ColumnType::UUIDs => self.scan_values::<u128>(col_type, block, |uuids| {
+                    self.scan_typed(uuids, op, value, |v| self.parse_uuid(v))
+                }),
+                ColumnType::HttpStatuses => self.scan_values::<u16>(col_type, block, |statuses| {
+                    self.scan_typed(statuses, op, value, |v| {
+                        v.parse::<u16>().map_err(|_| {
+                            ALICETextError::InvalidQuery(format!("Invalid HTTP status: {v}"))
+                        })
+                    })
+                }),
+                _ => self.scan_values::<u32>(col_type, block, |ids| {
+                    self.scan_typed(ids, op, value, |v| {
+                        v.parse::<u32>().map_err(|_| {
+                            ALICETextError::InvalidQuery(format!(
+                                "Invalid process or thread id: {v}"
+                            ))
+                        })
+                    })
+                }),
+            };
+        }
+
+        let partial = self.read_raw_column(col_type)?;
         match col_type {
-            ColumnType::LogLevels => {
-                let target = LogLevel::parse_level(value) as u8;
-                partial.log_levels.as_ref().map_or_else(
-                    || Ok(Vec::new()),
-                    |data| Ok(self.scan_primitive(data, op, target)),
-                )
-            }
-            ColumnType::IPv4 => {
-                let target = self.parse_ipv4(value)?;
-                partial.ipv4_addrs.as_ref().map_or_else(
-                    || Ok(Vec::new()),
-                    |data| Ok(self.scan_primitive(data, op, target)),
-                )
-            }
-            ColumnType::IPv6 => {
-                let target = self.parse_ipv6(value)?;
-                partial.ipv6_addrs.as_ref().map_or_else(
-                    || Ok(Vec::new()),
-                    |data| Ok(self.scan_primitive(data, op, target)),
-                )
+            ColumnType::LineIds => {
+                let ids = partial.line_ids.as_ref().map(LineIdColumn::ids);
+                self.scan_typed(ids, op, value, |v| {
+                    v.parse::<u64>()
+                        .map_err(|_| ALICETextError::InvalidQuery(format!("Invalid line id: {v}")))
+                })
             }
-            ColumnType::Numbers => {
-                let target = value.parse::<f64>().unwrap_or(0.0);
-                partial.numbers.as_ref().map_or_else(
-                    || Ok(Vec::new()),
-                    |data| Ok(self.scan_f64(data, op, target)),
-                )
+            ColumnType::Durations => {
+                let nanos = partial.durations.as_ref().map(|d| d.nanos.as_slice());
+                self.scan_typed(nanos, op, value, |v| {
+                    parse_duration(v).map(|(ns, _)| ns).ok_or_else(|| {
+                        ALICETextError::InvalidQuery(format!("Invalid duration: {v}"))
+                    })
+                })
             }
-            ColumnType::UUIDs => {
-                let target = self.parse_uuid(value)?;
-                partial.uuids.as_ref().map_or_else(
-                    || Ok(Vec::new()),
-                    |data| Ok(self.scan_primitive(data, op, target)),
-                )
+            ColumnType::ByteSizes => {
+                let bytes = partial.byte_sizes.as_ref().map(|s| s.bytes.as_slice());
+                self.scan_typed(bytes, op, value, |v| {
+                    parse_byte_size(v).map(|(b, _)| b).ok_or_else(|| {
+                        ALICETextError::InvalidQuery(format!("Invalid byte size: {v}"))
+                    })
+                })
             }
             ColumnType::Timestamps => {
                 // Typed timestamp filtering: parse query ONCE, compare as i64
-                let target_ms = self.parse_query_timestamp(value)?;
-                partial.timestamps.as_ref().map_or_else(
-                    || Ok(Vec::new()),
-                    |ts_col| {
-                        // Get prefix sums (absolute timestamps in ms) - pure numeric computation
-                        let timestamps_i64 = ts_col.prepare_for_read();
-                        Ok(self.scan_primitive(&timestamps_i64, op, target_ms))
-                    },
-                )
+                // Prefix sums (absolute timestamps in ms) - pure numeric computation
+                let timestamps_i64 = partial
+                    .timestamps
+                    .as_ref()
+                    .map(TimestampColumn::prepare_for_read);
+                self.scan_typed(timestamps_i64.as_deref(), op, value, |v| {
+                    self.parse_query_timestamp(v)
+                })
             }
             _ => {
                 // Fallback for string types (emails, urls, paths, etc.)
@@ -371,6 +1008,47 @@ impl<S: QuerySource> QueryEngine<S> {
         }
     }
 
+    /// Scan an integer column block in its encoding: a plain block in place;
+    /// for `Dict` / `Rle` blocks each distinct value or run is tested once
+    /// and the rows whose index is in the matching set are kept
+    fn scan_values<T: EncodableValue + bytemuck::Pod>(
+        &self,
+        col_type: ColumnType,
+        block: Option<&[u8]>,
+        scan: impl FnOnce(Option<&[T]>) -> Result<Vec<usize>>,
+    ) -> Result<Vec<usize>> {
+        let (Some(block), Some(entry)) = (block, self.metadata.get_column(col_type)) else {
+            return scan(None);
+        };
+        let rows = entry.row_count as usize;
+        match entry.encoding {
+            ColumnEncoding::Plain => scan(Some(&column_slice::<T>(block)?)),
+            ColumnEncoding::Dict => {
+                let column: DedupColumn<T> = decode_column(block)?;
+                Ok(column.rows_at(&scan(Some(&column.values))?))
+            }
+            ColumnEncoding::Rle => {
+                let column = decode_runs::<T>(block, rows)?;
+                Ok(column.rows_at(&scan(Some(&column.values))?))
+            }
+            ColumnEncoding::Delta | ColumnEncoding::Shuffle => {
+                scan(Some(&decode_values(block, entry.encoding, rows)?))
+            }
+        }
+    }
+
+    /// Parse every operand of `op` / `value`, then scan `data` (no column: no matches)
+    fn scan_typed<T: PartialOrd + Copy>(
+        &self,
+        data: Option<&[T]>,
+        op: Op,
+        value: &str,
+        parse: impl Fn(&str) -> Result<T>,
+    ) -> Result<Vec<usize>> {
+        let targets = op.operands(value).map(parse).collect::<Result<Vec<T>>>()?;
+        Ok(data.map_or_else(Vec::new, |data| self.scan_primitive(data, op, &targets)))
+    }
+
     /// Filter with closure (legacy compatibility)
     ///
     /// # Errors
@@ -389,17 +1067,25 @@ impl<S: QuerySource> QueryEngine<S> {
             .collect())
     }
 
-    /// Select values at specific indices
+    /// Select values at specific positions (e.g. a [`Self::filter_op`] result)
     ///
     /// # Errors
     ///
-    /// Returns an error if the column name is unknown or decompression fails.
-    pub fn select_at(&self, column: &str, indices: &[usize]) -> Result<Vec<String>> {
+    /// Returns an error if the column name is unknown, an index is past the
+    /// end of the column, or decompression fails.
+    pub fn select_at(&self, column: &str, indices: &RowSet) -> Result<Vec<String>> {
         let all_values = self.select_column(column)?;
-        Ok(indices
+        indices
             .iter()
-            .filter_map(|&i| all_values.get(i).cloned())
-            .collect())
+            .map(|i| {
+                all_values.get(i).cloned().ok_or_else(|| {
+                    ALICETextError::InvalidQuery(format!(
+                        "Index {i} is past the end of column '{column}' ({} values)",
+                        all_values.len()
+                    ))
+                })
+            })
+            .collect()
     }
 
     /// Full query: filter on one column, select from others (parallel)
@@ -415,10 +1101,13 @@ impl<S: QuerySource> QueryEngine<S> {
         op: Op,
         filter_value: &str,
     ) -> Result<QueryResult> {
+        let timer = telemetry::Timer::start();
+
         // Step 1: Filter using typed scan (fast, no String allocation)
-        let indices = self.filter_op(filter_column, op, filter_value)?;
+        let indices = self.scan_column(filter_column, op, filter_value)?;
 
         if indices.is_empty() {
+            telemetry::record_query(&timer, "query", 0);
             return Ok(QueryResult {
                 columns: select_columns
                     .iter()
@@ -428,19 +1117,106 @@ impl<S: QuerySource> QueryEngine<S> {
             });
         }
 
-        // Step 2: Fetch selected columns in PARALLEL
+        let filter_type = self.name_to_type(filter_column)?;
+        let structure =
+            self.read_payload(|c| matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap))?;
+        let result = self.materialize(select_columns, filter_type, &indices, &structure)?;
+        telemetry::record_query(&timer, "query", result.len());
+        Ok(result)
+    }
+
+    /// Like [`Self::query`], but also reconstructs the full line of each match
+    ///
+    /// Only the skeleton, the placeholder map, the filter column, the selected
+    /// columns and the columns referenced by the matched lines are read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any column name is unknown, the filter value cannot be parsed,
+    /// or decompression fails.
+    pub fn query_with_lines(
+        &self,
+        select_columns: &[&str],
+        filter_column: &str,
+        op: Op,
+        filter_value: &str,
+    ) -> Result<Vec<LineMatch>> {
+        let timer = telemetry::Timer::start();
+        let filter_type = self.name_to_type(filter_column)?;
+        let indices = self.scan_column(filter_column, op, filter_value)?;
+        if indices.is_empty() {
+            telemetry::record_query(&timer, "query_with_lines", 0);
+            return Ok(Vec::new());
+        }
+        let mut structure =
+            self.read_payload(|c| matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap))?;
+        let result = self.materialize(select_columns, filter_type, &indices, &structure)?;
+        let skeleton = std::mem::take(&mut structure.skeleton_tokens);
+        let lines = ColumnarPayload::split_lines(&skeleton);
+
+        // Provenance and line ids are indexed by line; every other column by value position
+        let value_lines = if filter_type.is_per_line() {
+            (0..lines.len()).collect()
+        } else {
+            Self::value_lines(&structure, &lines, filter_type)
+        };
+        let (line_numbers, rows): (Vec<usize>, Vec<QueryRow>) = indices
+            .iter()
+            .zip(result.rows)
+            .filter_map(|(&idx, row)| {
+                value_lines
+                    .get(idx)
+                    .filter(|&&line| line < lines.len())
+                    .map(|&line| (line, row))
+            })
+            .unzip();
+
+        let rendered =
+            self.render_lines(&structure.placeholder_map, None, &lines, &line_numbers)?;
+        let matches: Vec<LineMatch> = line_numbers
+            .into_iter()
+            .zip(rendered)
+            .zip(rows)
+            .map(|((line_number, line), row)| LineMatch {
+                line_number,
+                line,
+                values: row.values,
+            })
+            .collect();
+
+        telemetry::record_query(&timer, "query_with_lines", matches.len());
+        Ok(matches)
+    }
+
+    /// Values of `select_columns` on the rows of the given `filter_type` values (parallel column fetch)
+    ///
+    /// The filter column contributes the matching value itself, every other
+    /// column its first value on the same row ([`RowMap`]), so the columns
+    /// line up even when lines lack a value or carry several.
+    fn materialize(
+        &self,
+        select_columns: &[&str],
+        filter_type: ColumnType,
+        indices: &[usize],
+        structure: &ColumnarPayload,
+    ) -> Result<QueryResult> {
         let col_types: Vec<ColumnType> = select_columns
             .iter()
             .map(|n| self.name_to_type(n))
             .collect::<Result<Vec<_>>>()?;
 
-        let partials: Result<Vec<PartialPayload>> = col_types
-            .par_iter()
-            .map(|&ct| self.read_raw_column(ct))
-            .collect();
-        let partials = partials?;
+        let partials = self.read_raw_columns(&col_types)?;
+
+        let mut mapped = col_types.clone();
+        mapped.push(filter_type);
+        let row_map = RowMap::build(structure, &mapped);
+        let filter_len = self
+            .metadata
+            .get_column(filter_type)
+            .map_or(0, |entry| entry.row_count as usize);
+        let filter_rows = row_map.value_rows(filter_type, filter_len)?;
 
-        // Step 3: Materialize only matching rows (pinpoint extraction)
+        // Materialize only matching rows (pinpoint extraction)
         let mut result = QueryResult {
             columns: select_columns
                 .iter()
@@ -449,28 +1225,426 @@ impl<S: QuerySource> QueryEngine<S> {
             rows: Vec::with_capacity(indices.len()),
         };
 
-        for &idx in &indices {
+        for &idx in indices {
+            let line = filter_rows.get(idx).copied().flatten();
             let mut row = QueryRow {
                 values: HashMap::new(),
             };
             for (j, name) in select_columns.iter().enumerate() {
-                if let Some(val) = self.get_value_at(&partials[j], col_types[j], idx) {
+                let value_idx = if col_types[j] == filter_type {
+                    Some(idx)
+                } else {
+                    line.and_then(|line| row_map.values(line, col_types[j]).next())
+                };
+                if let Some(val) =
+                    value_idx.and_then(|v| self.get_value_at(&partials[j], col_types[j], v))
+                {
                     row.values.insert(name.to_string(), val);
                 }
             }
             result.rows.push(row);
         }
-
         Ok(result)
     }
 
+    /// Line number of every value in column `col_type` (`usize::MAX` if unreferenced)
+    fn value_lines(
+        structure: &ColumnarPayload,
+        lines: &[Vec<LinePiece<'_>>],
+        col_type: ColumnType,
+    ) -> Vec<usize> {
+        let mut value_lines = Vec::new();
+        for (line, pieces) in lines.iter().enumerate() {
+            for piece in pieces {
+                if let LinePiece::Ref(idx) = piece {
+                    if structure.placeholder_column(*idx as usize) == Some(col_type) {
+                        let col_idx = structure.placeholder_map[*idx as usize].1 as usize;
+                        if value_lines.len() <= col_idx {
+                            value_lines.resize(col_idx + 1, usize::MAX);
+                        }
+                        value_lines[col_idx] = line;
+                    }
+                }
+            }
+        }
+        value_lines
+    }
+
     /// Decompress entire file
     ///
     /// # Errors
     ///
     /// Returns an error if decompression fails.
     pub fn decompress_all(&self) -> Result<String> {
-        FormatV3Writer::decompress(self.source.as_slice())
+        FormatV3Writer::decompress_with_plugins(self.source.as_slice(), &self.plugins)
+    }
+
+    /// Reconstruct up to `n` representative lines (without trailing `\n`)
+    ///
+    /// Only the skeleton, the placeholder map and the columns referenced by
+    /// the chosen lines are decompressed (plus `log_levels` for
+    /// [`SamplingStrategy::PerLevel`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or decompressing a column fails.
+    pub fn sample(&self, n: usize, strategy: SamplingStrategy) -> Result<Vec<String>> {
+        let timer = telemetry::Timer::start();
+        let per_level = strategy == SamplingStrategy::PerLevel;
+        let mut structure = self.read_payload(|c| {
+            matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap)
+                || (per_level && c == ColumnType::LogLevels)
+        })?;
+        let skeleton = std::mem::take(&mut structure.skeleton_tokens);
+        let lines = ColumnarPayload::split_lines(&skeleton);
+        let n = n.min(lines.len());
+
+        let chosen: Vec<usize> = match strategy {
+            SamplingStrategy::Head => (0..n).collect(),
+            SamplingStrategy::Uniform => (0..n).map(|i| i * lines.len() / n).collect(),
+            SamplingStrategy::PerLevel => Self::round_robin_levels(&structure, &lines, n),
+        };
+        let levels = per_level.then(|| std::mem::take(&mut structure.log_levels));
+        let sample = self.render_lines(&structure.placeholder_map, levels, &lines, &chosen)?;
+
+        telemetry::record_query(&timer, "sample", sample.len());
+        Ok(sample)
+    }
+
+    /// Number of lines in the archive
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or decompressing the skeleton fails.
+    pub fn line_count(&self) -> Result<usize> {
+        if let Some(index) = self.line_starts.get() {
+            return Ok(index.len());
+        }
+        let structure = self.read_payload(|c| c == ColumnType::Skeleton)?;
+        Ok(ColumnarPayload::split_lines(&structure.skeleton_tokens).len())
+    }
+
+    /// Reconstruct the given lines (0-based, without trailing `\n`)
+    ///
+    /// Out-of-range line numbers are skipped. Like [`Self::sample`], only the
+    /// columns referenced by the requested lines are decompressed; the
+    /// skeleton and placeholder map are decoded by the first call and kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or decompressing a column fails.
+    pub fn lines(&self, line_numbers: &[usize]) -> Result<Vec<String>> {
+        let timer = telemetry::Timer::start();
+        let index = self.line_starts()?;
+        let lines: Vec<Vec<LinePiece<'_>>> = line_numbers
+            .iter()
+            .filter(|&&i| i < index.len())
+            .map(|&i| index.line(i))
+            .collect();
+        let chosen: Vec<usize> = (0..lines.len()).collect();
+        let rendered = self.render_lines(&index.placeholder_map, None, &lines, &chosen)?;
+
+        telemetry::record_query(&timer, "lines", rendered.len());
+        Ok(rendered)
+    }
+
+    /// Reconstruct the line with stable id `id` (archives written with line ids)
+    ///
+    /// Returns `None` if no line of this archive has the id, e.g. because it
+    /// was pruned or split into another archive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or decompressing a column fails.
+    pub fn line_by_id(&self, id: u64) -> Result<Option<String>> {
+        let partial = self.read_raw_column(ColumnType::LineIds)?;
+        let Some(line) = partial.line_ids.and_then(|ids| ids.position(id)) else {
+            return Ok(None);
+        };
+        Ok(self.lines(&[line])?.pop())
+    }
+
+    /// Line numbers (ascending, deduplicated) of the rows matching `column op value`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column name is unknown, the filter value cannot be parsed,
+    /// or decompression fails.
+    pub fn matching_lines(&self, column: &str, op: Op, value: &str) -> Result<RowSet> {
+        let timer = telemetry::Timer::start();
+        let col_type = self.name_to_type(column)?;
+        let indices = self.scan_column(column, op, value)?;
+        if indices.is_empty() {
+            telemetry::record_query(&timer, "matching_lines", 0);
+            return Ok(RowSet::new());
+        }
+
+        let structure =
+            self.read_payload(|c| matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap))?;
+        let lines = ColumnarPayload::split_lines(&structure.skeleton_tokens);
+        let line_numbers = Self::indices_to_lines(&structure, &lines, col_type, indices);
+
+        telemetry::record_query(&timer, "matching_lines", line_numbers.len());
+        Ok(line_numbers)
+    }
+
+    /// The cached [`LineStarts`], decoding the structure columns on first use
+    fn line_starts(&self) -> Result<&LineStarts> {
+        if let Some(index) = self.line_starts.get() {
+            return Ok(index);
+        }
+        let structure =
+            self.read_payload(|c| matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap))?;
+        // A racing call may have won; either index is the same
+        Ok(self.line_starts.get_or_init(|| LineStarts::new(structure)))
+    }
+
+    /// Lines holding the values at `indices` of `col_type`
+    fn indices_to_lines(
+        structure: &ColumnarPayload,
+        lines: &[Vec<LinePiece<'_>>],
+        col_type: ColumnType,
+        indices: Vec<usize>,
+    ) -> RowSet {
+        if col_type.is_per_line() {
+            return indices
+                .into_iter()
+                .filter(|&line| line < lines.len())
+                .collect();
+        }
+        let value_lines = Self::value_lines(structure, lines, col_type);
+        indices
+            .iter()
+            .filter_map(|&idx| value_lines.get(idx).copied())
+            .filter(|&line| line < lines.len())
+            .collect()
+    }
+
+    /// Rows (ascending, deduplicated) holding a `column` value equal to `value`
+    ///
+    /// Values compare as [`Self::select_column`] returns them, so unlike the
+    /// typed filters this works for every column. The value positions are
+    /// mapped back to rows through the placeholder map; only `column`, the
+    /// skeleton and the placeholder map are decompressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column name is unknown or decompression fails.
+    pub fn rows_with_value(&self, column: &str, value: &str) -> Result<RowSet> {
+        let timer = telemetry::Timer::start();
+        let col_type = self.name_to_type(column)?;
+        let indices: Vec<usize> = self
+            .select_column(column)?
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| *v == value)
+            .map(|(i, _)| i)
+            .collect();
+        if indices.is_empty() {
+            telemetry::record_query(&timer, "rows_with_value", 0);
+            return Ok(RowSet::new());
+        }
+
+        let structure =
+            self.read_payload(|c| matches!(c, ColumnType::Skeleton | ColumnType::PlaceholderMap))?;
+        let lines = ColumnarPayload::split_lines(&structure.skeleton_tokens);
+        let rows = Self::indices_to_lines(&structure, &lines, col_type, indices);
+
+        telemetry::record_query(&timer, "rows_with_value", rows.len());
+        Ok(rows)
+    }
+
+    /// The `k` most frequent `column` values with their counts (ties by value)
+    ///
+    /// Counts every value on the lines in `lines` (e.g. a [`Self::filter_multi`]
+    /// result), or in the whole column if `None`. Primitive and dictionary
+    /// columns (levels, addresses, numbers, statuses, ids, hosts, pods,
+    /// namespaces) are counted by their encoded value and only the top `k`
+    /// are formatted; other columns are counted as text.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the column name is unknown or decompression fails.
+    pub fn top_k(
+        &self,
+        column: &str,
+        k: usize,
+        lines: Option<&RowSet>,
+    ) -> Result<Vec<(String, usize)>> {
+        let timer = telemetry::Timer::start();
+        let col_type = self.name_to_type(column)?;
+        let (partial, positions): (PartialPayload, Option<Vec<usize>>) = match lines {
+            None => (self.read_raw_column(col_type)?, None),
+            Some(lines) => {
+                let partial =
+                    FormatV3Writer::read_columns_by_row_with(&self.metadata, &[col_type], |e| {
+                        self.column_bytes(e)
+                    })?;
+                let positions = partial.rows.as_ref().map_or_else(Vec::new, |rows| {
+                    lines
+                        .iter()
+                        .take_while(|&line| line < rows.len())
+                        .flat_map(|line| rows.values(line, col_type))
+                        .collect()
+                });
+                (partial, Some(positions))
+            }
+        };
+
+        let top: Vec<(String, usize)> = if let Some(keys) = value_keys(&partial, col_type) {
+            // Count encoded values, remembering where each first occurs
+            let mut counts: HashMap<u128, (usize, usize)> = HashMap::new();
+            let mut count = |idx: usize| {
+                if let Some(&key) = keys.get(idx) {
+                    counts.entry(key).or_insert((0, idx)).0 += 1;
+                }
+            };
+            match &positions {
+                Some(positions) => positions.iter().copied().for_each(&mut count),
+                None => (0..keys.len()).for_each(&mut count),
+            }
+            let mut counts: Vec<(u128, usize, usize)> = counts
+                .into_iter()
+                .map(|(key, (n, first))| (key, n, first))
+                .collect();
+            counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            counts
+                .into_iter()
+                .take(k)
+                .filter_map(|(_, n, first)| {
+                    Some((self.get_value_at(&partial, col_type, first)?, n))
+                })
+                .collect()
+        } else {
+            let strings = self.partial_to_strings(&partial, col_type)?;
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            let mut count = |idx: usize| {
+                if let Some(value) = strings.get(idx) {
+                    *counts.entry(value).or_default() += 1;
+                }
+            };
+            match &positions {
+                Some(positions) => positions.iter().copied().for_each(&mut count),
+                None => (0..strings.len()).for_each(&mut count),
+            }
+            let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+            counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            counts
+                .into_iter()
+                .take(k)
+                .map(|(value, n)| (value.to_string(), n))
+                .collect()
+        };
+
+        telemetry::record_query(&timer, "top_k", top.len());
+        Ok(top)
+    }
+
+    /// Reconstruct the `chosen` skeleton lines (without trailing `\n`)
+    ///
+    /// `structure` holds the skeleton-side columns already read (plus
+    /// `log_levels` when `levels_loaded`); only the value columns the chosen
+    /// lines refer to are read.
+    fn render_lines(
+        &self,
+        placeholder_map: &[(u8, u32)],
+        log_levels: Option<Vec<u8>>,
+        lines: &[Vec<LinePiece<'_>>],
+        chosen: &[usize],
+    ) -> Result<Vec<String>> {
+        // Fetch only the value columns the chosen lines refer to
+        let mut needed: Vec<ColumnType> = Vec::new();
+        for piece in chosen.iter().flat_map(|&i| &lines[i]) {
+            if let LinePiece::Ref(idx) = piece {
+                if let Some(col) = placeholder_map
+                    .get(*idx as usize)
+                    .and_then(|&(tag, _)| ColumnType::from_placeholder_tag(tag))
+                {
+                    if !needed.contains(&col) {
+                        needed.push(col);
+                    }
+                }
+            }
+        }
+        let levels_loaded = log_levels.is_some();
+        let mut values = self.read_payload(|c| {
+            (needed.contains(&c) && !(levels_loaded && c == ColumnType::LogLevels))
+                || c == ColumnType::Spellings
+        })?;
+        if let Some(levels) = log_levels {
+            values.log_levels = levels;
+        }
+
+        let prefix_sums = values.prepare_for_read();
+        Ok(chosen
+            .iter()
+            .map(|&i| {
+                let mut line = String::new();
+                for piece in &lines[i] {
+                    match piece {
+                        LinePiece::Text(text) => line.push_str(text),
+                        LinePiece::Ref(idx) => {
+                            let idx = *idx as usize;
+                            if let Some(value) = placeholder_map
+                                .get(idx)
+                                .and_then(|&entry| values.value_for(idx, entry, &prefix_sums))
+                            {
+                                line.push_str(&value);
+                            }
+                        }
+                    }
+                }
+                if line.ends_with('\n') {
+                    line.pop();
+                }
+                line
+            })
+            .collect())
+    }
+
+    /// Pick `n` line indices, cycling through log levels (unleveled lines last)
+    fn round_robin_levels(
+        structure: &ColumnarPayload,
+        lines: &[Vec<LinePiece<'_>>],
+        n: usize,
+    ) -> Vec<usize> {
+        let mut groups: Vec<(u8, Vec<usize>)> = Vec::new();
+        for (i, pieces) in lines.iter().enumerate() {
+            let level = pieces
+                .iter()
+                .find_map(|piece| match piece {
+                    LinePiece::Ref(idx)
+                        if structure.placeholder_column(*idx as usize)
+                            == Some(ColumnType::LogLevels) =>
+                    {
+                        let (_, col_idx) = structure.placeholder_map[*idx as usize];
+                        structure.log_levels.get(col_idx as usize).copied()
+                    }
+                    _ => None,
+                })
+                .unwrap_or(u8::MAX);
+            match groups.iter_mut().find(|(l, _)| *l == level) {
+                Some((_, members)) => members.push(i),
+                None => groups.push((level, vec![i])),
+            }
+        }
+        groups.sort_by_key(|(level, _)| *level);
+
+        let mut chosen = Vec::with_capacity(n);
+        for round in 0.. {
+            if chosen.len() == n {
+                break;
+            }
+            for (_, members) in &groups {
+                if let Some(&i) = members.get(round) {
+                    if chosen.len() < n {
+                        chosen.push(i);
+                    }
+                }
+            }
+        }
+        chosen.sort_unstable();
+        chosen
     }
 
     // === Private: Typed Scanners ===
@@ -479,10 +1653,13 @@ impl<S: QuerySource> QueryEngine<S> {
     /// Compiles down to SIMD instructions in release mode
     #[inline]
     #[allow(clippy::unused_self)]
-    fn scan_primitive<T>(&self, data: &[T], op: Op, target: T) -> Vec<usize>
+    fn scan_primitive<T>(&self, data: &[T], op: Op, targets: &[T]) -> Vec<usize>
     where
         T: PartialOrd + Copy,
     {
+        let Some(&target) = targets.first() else {
+            return Vec::new();
+        };
         data.iter()
             .enumerate()
             .filter(|(_, &v)| match op {
@@ -492,7 +1669,10 @@ impl<S: QuerySource> QueryEngine<S> {
                 Op::Le => v <= target,
                 Op::Gt => v > target,
                 Op::Ge => v >= target,
-                Op::Contains | Op::StartsWith | Op::EndsWith => false,
+                Op::In => targets.contains(&v),
+                Op::NotIn => !targets.contains(&v),
+                Op::Between => targets.len() == 2 && targets[0] <= v && v <= targets[1],
+                Op::Contains | Op::StartsWith | Op::EndsWith | Op::InCidr => false,
             })
             .map(|(i, _)| i)
             .collect()
@@ -501,17 +1681,24 @@ impl<S: QuerySource> QueryEngine<S> {
     /// Scanner for f64 (needs special handling for NaN)
     #[inline]
     #[allow(clippy::unused_self)]
-    fn scan_f64(&self, data: &[f64], op: Op, target: f64) -> Vec<usize> {
+    fn scan_f64(&self, data: &[f64], op: Op, targets: &[f64]) -> Vec<usize> {
+        let Some(&target) = targets.first() else {
+            return Vec::new();
+        };
+        let listed = |v: f64| targets.iter().any(|t| (v - t).abs() < f64::EPSILON);
         data.iter()
             .enumerate()
             .filter(|(_, &v)| match op {
                 Op::Eq => (v - target).abs() < f64::EPSILON,
                 Op::Ne => (v - target).abs() >= f64::EPSILON,
+                Op::In => listed(v),
+                Op::NotIn => !listed(v),
+                Op::Between => targets.len() == 2 && targets[0] <= v && v <= targets[1],
                 Op::Lt => v < target,
                 Op::Le => v <= target,
                 Op::Gt => v > target,
                 Op::Ge => v >= target,
-                Op::Contains | Op::StartsWith | Op::EndsWith => false,
+                Op::Contains | Op::StartsWith | Op::EndsWith | Op::InCidr => false,
             })
             .map(|(i, _)| i)
             .collect()
@@ -521,6 +1708,7 @@ impl<S: QuerySource> QueryEngine<S> {
     #[inline]
     #[allow(clippy::unused_self)]
     fn scan_strings(&self, data: &[String], op: Op, target: &str) -> Vec<usize> {
+        let targets: Vec<&str> = op.operands(target).collect();
         data.iter()
             .enumerate()
             .filter(|(_, v)| match op {
@@ -533,6 +1721,12 @@ impl<S: QuerySource> QueryEngine<S> {
                 Op::Contains => v.contains(target),
                 Op::StartsWith => v.starts_with(target),
                 Op::EndsWith => v.ends_with(target),
+                Op::In => targets.contains(&v.as_str()),
+                Op::NotIn => !targets.contains(&v.as_str()),
+                Op::Between => {
+                    targets.len() == 2 && targets[0] <= v.as_str() && v.as_str() <= targets[1]
+                }
+                Op::InCidr => false,
             })
             .map(|(i, _)| i)
             .collect()
@@ -540,9 +1734,50 @@ impl<S: QuerySource> QueryEngine<S> {
 
     // === Private: Column Reading ===
 
+    /// Decompressed block of `col_type` (`None` if the archive has no such column)
+    fn column_block(&self, col_type: ColumnType) -> Result<Option<Vec<u8>>> {
+        self.metadata
+            .get_column(col_type)
+            .map(|entry| self.column_bytes(entry))
+            .transpose()
+    }
+
     fn read_raw_column(&self, col_type: ColumnType) -> Result<PartialPayload> {
-        let mut cursor = Cursor::new(self.source.as_slice());
-        FormatV3Writer::read_columns(&mut cursor, &self.metadata, &[col_type])
+        FormatV3Writer::read_columns_with(&self.metadata, &[col_type], |entry| {
+            self.column_bytes(entry)
+        })
+    }
+
+    fn read_payload(&self, wanted: impl Fn(ColumnType) -> bool) -> Result<ColumnarPayload> {
+        FormatV3Writer::read_payload_with(&self.metadata, &self.plugins, wanted, |entry| {
+            self.column_bytes(entry)
+        })
+    }
+
+    /// Decompressed bytes of one column block, through the cache if attached
+    pub(crate) fn column_bytes(&self, entry: &ColumnEntry) -> Result<Vec<u8>> {
+        let read = || {
+            FormatV3Writer::read_column_bytes(
+                &mut Cursor::new(self.source.as_slice()),
+                entry,
+                self.metadata.limits.max_column_bytes,
+            )
+        };
+        let Some((cache, file_hash)) = &self.cache else {
+            return read();
+        };
+        let key = CacheKey {
+            file_hash: *file_hash,
+            column: entry.col_type,
+            row_group: 0,
+        };
+        if let Some(bytes) = cache.get(&key) {
+            return Ok(bytes);
+        }
+        let bytes = read()?;
+        // A cache that can't be written only costs speed
+        let _ = cache.put(&key, &bytes);
+        Ok(bytes)
     }
 
     #[allow(clippy::unused_self)]
@@ -566,7 +1801,26 @@ impl<S: QuerySource> QueryEngine<S> {
             "others" => Ok(ColumnType::Others),
             "placeholder_map" => Ok(ColumnType::PlaceholderMap),
             "timestamps_raw" => Ok(ColumnType::TimestampsRaw),
-            _ => Err(ALICETextError::DecompressionError(format!(
+            "epochs" => Ok(ColumnType::Epochs),
+            "uptimes" => Ok(ColumnType::Uptimes),
+            "ansi_codes" => Ok(ColumnType::AnsiCodes),
+            "mac_addrs" => Ok(ColumnType::MacAddrs),
+            "geo_points" => Ok(ColumnType::GeoPoints),
+            "cri_prefixes" => Ok(ColumnType::CriPrefixes),
+            "klog_headers" => Ok(ColumnType::KlogHeaders),
+            "container_ids" => Ok(ColumnType::ContainerIds),
+            "k8s_namespaces" => Ok(ColumnType::K8sNamespaces),
+            "k8s_pods" => Ok(ColumnType::K8sPods),
+            "http_methods" => Ok(ColumnType::HttpMethods),
+            "http_statuses" => Ok(ColumnType::HttpStatuses),
+            "durations" => Ok(ColumnType::Durations),
+            "byte_sizes" => Ok(ColumnType::ByteSizes),
+            "hosts" => Ok(ColumnType::Hosts),
+            "pids" => Ok(ColumnType::Pids),
+            "tids" => Ok(ColumnType::Tids),
+            "provenance" => Ok(ColumnType::Provenance),
+            "line_ids" => Ok(ColumnType::LineIds),
+            _ => Err(ALICETextError::InvalidQuery(format!(
                 "Unknown column: {name}"
             ))),
         }
@@ -578,56 +1832,34 @@ impl<S: QuerySource> QueryEngine<S> {
     fn parse_ipv4(&self, s: &str) -> Result<u32> {
         s.parse::<std::net::Ipv4Addr>()
             .map(u32::from)
-            .map_err(|_| ALICETextError::DecompressionError(format!("Invalid IPv4: {s}")))
+            .map_err(|_| ALICETextError::InvalidQuery(format!("Invalid IPv4: {s}")))
     }
 
     #[allow(clippy::unused_self)]
     fn parse_ipv6(&self, s: &str) -> Result<u128> {
         s.parse::<std::net::Ipv6Addr>()
             .map(u128::from)
-            .map_err(|_| ALICETextError::DecompressionError(format!("Invalid IPv6: {s}")))
+            .map_err(|_| ALICETextError::InvalidQuery(format!("Invalid IPv6: {s}")))
     }
 
     #[allow(clippy::unused_self)]
     fn parse_uuid(&self, s: &str) -> Result<u128> {
         let hex: String = s.chars().filter(char::is_ascii_hexdigit).collect();
         if hex.len() != 32 {
-            return Err(ALICETextError::DecompressionError(format!(
-                "Invalid UUID: {s}"
-            )));
+            return Err(ALICETextError::InvalidQuery(format!("Invalid UUID: {s}")));
         }
         u128::from_str_radix(&hex, 16)
-            .map_err(|_| ALICETextError::DecompressionError(format!("Invalid UUID hex: {s}")))
+            .map_err(|_| ALICETextError::InvalidQuery(format!("Invalid UUID hex: {s}")))
     }
 
     /// Parse query timestamp string to Unix milliseconds (i64)
-    /// Supports multiple formats: "YYYY-MM-DD HH:MM:SS", "YYYY-MM-DDTHH:MM:SS", etc.
+    ///
+    /// Relative expressions (`now-1h`, `-15m`) count from the current UTC
+    /// time; log timestamps without a zone compare as UTC.
     #[allow(clippy::unused_self)]
     fn parse_query_timestamp(&self, s: &str) -> Result<i64> {
-        // Try common formats
-        let formats = [
-            "%Y-%m-%d %H:%M:%S",    // 2024-01-15 10:30:45
-            "%Y-%m-%dT%H:%M:%S",    // 2024-01-15T10:30:45
-            "%Y-%m-%d %H:%M:%S%.f", // 2024-01-15 10:30:45.123
-            "%Y-%m-%dT%H:%M:%S%.f", // 2024-01-15T10:30:45.123
-            "%Y-%m-%d",             // 2024-01-15 (assumes 00:00:00)
-        ];
-
-        for fmt in &formats {
-            if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
-                return Ok(dt.and_utc().timestamp_millis());
-            }
-        }
-
-        // Try date-only format with time defaulting to 00:00:00
-        if let Ok(date) = chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d") {
-            let dt = date.and_hms_opt(0, 0, 0).unwrap();
-            return Ok(dt.and_utc().timestamp_millis());
-        }
-
-        Err(ALICETextError::DecompressionError(format!(
-            "Invalid timestamp format: {s}. Expected YYYY-MM-DD HH:MM:SS"
-        )))
+        parse_relative_time(s, chrono::Utc::now().timestamp_millis())
+            .map_or_else(|| parse_timestamp_ms(s), Ok)
     }
 
     // === Private: Value Extraction ===
@@ -691,6 +1923,81 @@ impl<S: QuerySource> QueryEngine<S> {
             ColumnType::Emails => partial.emails.clone().unwrap_or_default(),
             ColumnType::URLs => partial.urls.clone().unwrap_or_default(),
             ColumnType::Paths => partial.paths.clone().unwrap_or_default(),
+            ColumnType::Epochs => partial.epoch_strings().unwrap_or_default(),
+            ColumnType::Uptimes => partial.uptime_strings().unwrap_or_default(),
+            ColumnType::AnsiCodes => partial.ansi_codes.clone().unwrap_or_default(),
+            ColumnType::MacAddrs => partial.mac_strings().unwrap_or_default(),
+            ColumnType::GeoPoints => partial.geo_strings().unwrap_or_default(),
+            ColumnType::CriPrefixes => partial
+                .cri_prefixes
+                .as_ref()
+                .map(CriColumn::strings)
+                .unwrap_or_default(),
+            ColumnType::KlogHeaders => partial
+                .klog_headers
+                .as_ref()
+                .map(KlogColumn::strings)
+                .unwrap_or_default(),
+            ColumnType::ContainerIds => partial
+                .container_ids
+                .as_ref()
+                .map(|ids| ids.iter().map(ToString::to_string).collect())
+                .unwrap_or_default(),
+            ColumnType::K8sNamespaces => partial
+                .k8s_namespaces
+                .as_ref()
+                .map(NamespaceColumn::names)
+                .unwrap_or_default(),
+            ColumnType::K8sPods => partial
+                .k8s_pods
+                .as_ref()
+                .map(PodColumn::strings)
+                .unwrap_or_default(),
+            ColumnType::HttpMethods => partial
+                .http_methods
+                .as_ref()
+                .map(|methods| methods.iter().filter_map(|&m| method_name(m)).collect())
+                .unwrap_or_default(),
+            ColumnType::HttpStatuses => partial
+                .http_statuses
+                .as_ref()
+                .map(|statuses| statuses.iter().map(ToString::to_string).collect())
+                .unwrap_or_default(),
+            ColumnType::Durations => partial
+                .durations
+                .as_ref()
+                .map(|d| (0..d.len()).filter_map(|i| d.get(i)).collect())
+                .unwrap_or_default(),
+            ColumnType::ByteSizes => partial
+                .byte_sizes
+                .as_ref()
+                .map(|s| (0..s.len()).filter_map(|i| s.get(i)).collect())
+                .unwrap_or_default(),
+            ColumnType::Hosts => partial
+                .hosts
+                .as_ref()
+                .map(|h| (0..h.len()).filter_map(|i| h.get(i)).collect())
+                .unwrap_or_default(),
+            ColumnType::Pids => partial
+                .pids
+                .as_ref()
+                .map(|ids| ids.iter().map(ToString::to_string).collect())
+                .unwrap_or_default(),
+            ColumnType::Tids => partial
+                .tids
+                .as_ref()
+                .map(|ids| ids.iter().map(ToString::to_string).collect())
+                .unwrap_or_default(),
+            ColumnType::Provenance => partial
+                .provenance
+                .as_ref()
+                .map(|p| (0..p.len()).filter_map(|i| p.describe(i)).collect())
+                .unwrap_or_default(),
+            ColumnType::LineIds => partial
+                .line_ids
+                .as_ref()
+                .map(|ids| ids.ids().iter().map(ToString::to_string).collect())
+                .unwrap_or_default(),
             _ => Vec::new(),
         })
     }
@@ -752,18 +2059,158 @@ impl<S: QuerySource> QueryEngine<S> {
             ColumnType::Emails => partial.emails.as_ref()?.get(index).cloned(),
             ColumnType::URLs => partial.urls.as_ref()?.get(index).cloned(),
             ColumnType::Paths => partial.paths.as_ref()?.get(index).cloned(),
+            ColumnType::Epochs => {
+                let epochs = partial.epochs.as_ref()?;
+                epochs.get_epoch(index, &epochs.prepare_for_read())
+            }
+            ColumnType::Uptimes => {
+                let uptimes = partial.uptimes.as_ref()?;
+                uptimes.get_uptime(index, &uptimes.prepare_for_read())
+            }
+            ColumnType::AnsiCodes => partial.ansi_codes.as_ref()?.get(index).cloned(),
+            ColumnType::MacAddrs => partial
+                .mac_addrs
+                .as_ref()?
+                .get(index)
+                .map(|&m| format_mac(m)),
+            ColumnType::GeoPoints => partial
+                .geo_points
+                .as_ref()?
+                .get(index)
+                .map(ToString::to_string),
+            ColumnType::CriPrefixes => {
+                let cri = partial.cri_prefixes.as_ref()?;
+                cri.get(index, &cri.prepare_for_read())
+            }
+            ColumnType::KlogHeaders => partial.klog_headers.as_ref()?.get(index),
+            ColumnType::ContainerIds => partial
+                .container_ids
+                .as_ref()?
+                .get(index)
+                .map(ToString::to_string),
+            ColumnType::K8sNamespaces => partial
+                .k8s_namespaces
+                .as_ref()?
+                .name(index)
+                .map(str::to_string),
+            ColumnType::K8sPods => partial.k8s_pods.as_ref()?.get(index),
+            ColumnType::HttpMethods => method_name(*partial.http_methods.as_ref()?.get(index)?),
+            ColumnType::HttpStatuses => partial
+                .http_statuses
+                .as_ref()?
+                .get(index)
+                .map(ToString::to_string),
+            ColumnType::Durations => partial.durations.as_ref()?.get(index),
+            ColumnType::ByteSizes => partial.byte_sizes.as_ref()?.get(index),
+            ColumnType::Hosts => partial.hosts.as_ref()?.get(index),
+            ColumnType::Pids => partial.pids.as_ref()?.get(index).map(ToString::to_string),
+            ColumnType::Tids => partial.tids.as_ref()?.get(index).map(ToString::to_string),
+            ColumnType::Provenance => partial.provenance.as_ref()?.describe(index),
+            ColumnType::LineIds => partial
+                .line_ids
+                .as_ref()?
+                .get(index)
+                .map(|id| id.to_string()),
             _ => None,
         }
     }
 }
 
+/// `address/prefix` as `(network, mask)` over `bits`-bit addresses
+fn parse_cidr<A: std::str::FromStr>(
+    value: &str,
+    bits: u32,
+    to_int: impl Fn(A) -> u128,
+) -> Result<(u128, u128)> {
+    let invalid = || ALICETextError::InvalidQuery(format!("Invalid CIDR block: {value}"));
+    let (addr, prefix) = value.trim().split_once('/').ok_or_else(invalid)?;
+    let addr = to_int(addr.parse::<A>().map_err(|_| invalid())?);
+    let prefix: u32 = prefix.parse().map_err(|_| invalid())?;
+    if prefix > bits {
+        return Err(invalid());
+    }
+    let mask = u128::MAX
+        .checked_shl(128 - prefix)
+        .unwrap_or(0)
+        .checked_shr(128 - bits)
+        .unwrap_or(0);
+    Ok((addr & mask, mask))
+}
+
+/// Positions of addresses inside `(network, mask)` (no column: no matches)
+fn scan_cidr<T: Copy + Into<u128>>(
+    data: Option<&[T]>,
+    (network, mask): (u128, u128),
+) -> Vec<usize> {
+    data.unwrap_or_default()
+        .iter()
+        .enumerate()
+        .filter(|(_, &addr)| addr.into() & mask == network)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Encoded value at each position of a primitive or dictionary column, in
+/// value order (`None` for columns counted as text)
+fn value_keys(partial: &PartialPayload, col_type: ColumnType) -> Option<Vec<u128>> {
+    fn widen<T: Copy + Into<u128>>(values: Option<&Vec<T>>) -> Option<Vec<u128>> {
+        values.map(|v| v.iter().map(|&x| x.into()).collect())
+    }
+    match col_type {
+        ColumnType::LogLevels => widen(partial.log_levels.as_ref()),
+        ColumnType::IPv4 => widen(partial.ipv4_addrs.as_ref()),
+        ColumnType::IPv6 => widen(partial.ipv6_addrs.as_ref()),
+        ColumnType::UUIDs => widen(partial.uuids.as_ref()),
+        ColumnType::MacAddrs => widen(partial.mac_addrs.as_ref()),
+        ColumnType::HttpMethods => widen(partial.http_methods.as_ref()),
+        ColumnType::HttpStatuses => widen(partial.http_statuses.as_ref()),
+        ColumnType::Pids => widen(partial.pids.as_ref()),
+        ColumnType::Tids => widen(partial.tids.as_ref()),
+        // Sign-flipped bits order floats like numbers
+        ColumnType::Numbers => partial.numbers.as_ref().map(|numbers| {
+            numbers
+                .iter()
+                .map(|n| {
+                    let bits = n.to_bits();
+                    u128::from(if bits >> 63 == 1 {
+                        !bits
+                    } else {
+                        bits | 1 << 63
+                    })
+                })
+                .collect()
+        }),
+        ColumnType::LineIds => partial
+            .line_ids
+            .as_ref()
+            .map(|ids| ids.ids().iter().map(|&id| u128::from(id)).collect()),
+        ColumnType::Hosts => widen(partial.hosts.as_ref().map(|h| &h.hosts)),
+        ColumnType::K8sNamespaces => partial
+            .k8s_namespaces
+            .as_ref()
+            .map(|ns| ns.values.iter().map(|&(_, id)| u128::from(id)).collect()),
+        ColumnType::K8sPods => partial.k8s_pods.as_ref().map(|pods| {
+            pods.pods
+                .iter()
+                .map(|&(prefix, suffix)| u128::from(prefix) << 32 | u128::from(suffix))
+                .collect()
+        }),
+        _ => None,
+    }
+}
+
+/// Name of an `http_methods` value
+fn method_name(method: u8) -> Option<String> {
+    HTTP_METHODS
+        .get(usize::from(method))
+        .map(|&m| m.to_string())
+}
+
 /// Query builder for fluent API
 pub struct QueryBuilder<'a, S: QuerySource> {
     engine: &'a QueryEngine<S>,
     select_cols: Vec<String>,
-    filter_col: Option<String>,
-    filter_op: Option<Op>,
-    filter_value: Option<String>,
+    filters: Vec<(String, Op, String)>,
 }
 
 impl<'a, S: QuerySource> QueryBuilder<'a, S> {
@@ -771,9 +2218,7 @@ impl<'a, S: QuerySource> QueryBuilder<'a, S> {
         Self {
             engine,
             select_cols: Vec::new(),
-            filter_col: None,
-            filter_op: None,
-            filter_value: None,
+            filters: Vec::new(),
         }
     }
 
@@ -786,14 +2231,17 @@ impl<'a, S: QuerySource> QueryBuilder<'a, S> {
         self
     }
 
+    /// Add a filter; several filters must all match the same line
     #[must_use]
     pub fn filter(mut self, column: &str, op: Op, value: &str) -> Self {
-        self.filter_col = Some(column.to_string());
-        self.filter_op = Some(op);
-        self.filter_value = Some(value.to_string());
+        self.filters
+            .push((column.to_string(), op, value.to_string()));
         self
     }
 
+    /// One filter runs [`QueryEngine::query`] (a row per matching value),
+    /// several [`QueryEngine::query_rows`] (a row per matching line)
+    ///
     /// # Errors
     ///
     /// Returns an error if the query fails due to unknown columns or decompression errors.
@@ -804,38 +2252,31 @@ impl<'a, S: QuerySource> QueryBuilder<'a, S> {
             .map(std::string::String::as_str)
             .collect();
 
-        if let (Some(filter_col), Some(op), Some(filter_value)) =
-            (self.filter_col, self.filter_op, self.filter_value)
-        {
-            self.engine
-                .query(&select_refs, &filter_col, op, &filter_value)
-        } else {
-            self.engine.select_columns(&select_refs)
+        let filters: Vec<(&str, Op, &str)> = self
+            .filters
+            .iter()
+            .map(|(column, op, value)| (column.as_str(), *op, value.as_str()))
+            .collect();
+        match filters[..] {
+            [] => self.engine.select_columns(&select_refs),
+            [(column, op, value)] => self.engine.query(&select_refs, column, op, value),
+            _ => Ok(QueryResult {
+                columns: self.select_cols.clone(),
+                rows: self
+                    .engine
+                    .query_rows(&select_refs, &filters)?
+                    .into_iter()
+                    .map(|m| QueryRow { values: m.values })
+                    .collect(),
+            }),
         }
     }
 }
 
-/// Convenience function to compress text with v3 format
-///
-/// # Errors
-///
-/// Returns an error if Bincode serialization or Zstd compression fails.
-pub fn compress_v3(text: &str, level: CompressionLevel) -> Result<Vec<u8>> {
-    FormatV3Writer::new(level).compress(text)
-}
-
-/// Convenience function to decompress v3 format
-///
-/// # Errors
-///
-/// Returns an error if decompression fails.
-pub fn decompress_v3(data: &[u8]) -> Result<String> {
-    FormatV3Writer::decompress(data)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::format_v3::CompressionLevel;
     use std::io::Cursor;
 
     fn create_test_data() -> Vec<u8> {
@@ -856,6 +2297,22 @@ mod tests {
         assert!(stats.original_size > 0);
         assert!(stats.column_count > 0);
         assert_eq!(stats.row_count, 5);
+        let rate = stats.exception_rate.unwrap();
+        assert!(rate > 0.0 && rate < 1.0, "{rate}");
+        assert!(stats.value_bytes.unwrap() < stats.original_size);
+    }
+
+    #[test]
+    fn test_query_engine_stats_json() {
+        let data = create_test_data();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        let stats = engine.stats();
+        let json = serde_json::to_string(&stats).unwrap();
+        let parsed: FileStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.row_count, stats.row_count);
+        assert_eq!(parsed.columns.len(), stats.columns.len());
+        assert_eq!(parsed.columns[0].col_type, stats.columns[0].col_type);
     }
 
     #[test]
@@ -879,6 +2336,18 @@ mod tests {
         assert!(levels.contains(&"INFO".to_string()));
         assert!(levels.contains(&"ERROR".to_string()));
         assert!(levels.contains(&"WARN".to_string()));
+
+        let errors = engine.filter_op("log_levels", Op::Eq, "ERROR").unwrap();
+        assert_eq!(
+            engine.select_at("log_levels", &errors).unwrap(),
+            ["ERROR", "ERROR"]
+        );
+        // An index past the column is an error, not a shorter result
+        let past: RowSet = [0, 5].into_iter().collect();
+        assert!(matches!(
+            engine.select_at("log_levels", &past),
+            Err(ALICETextError::InvalidQuery(_))
+        ));
     }
 
     #[test]
@@ -901,6 +2370,542 @@ mod tests {
         assert_eq!(indices.len(), 1);
     }
 
+    #[test]
+    fn test_typed_filter_http_status() {
+        let text =
+            "\"GET / HTTP/1.1\" 200 5\n\"POST /login HTTP/1.1\" 503 0\n\"GET /x HTTP/1.1\" 404 9\n";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        // Compared as u16, not as text
+        assert_eq!(
+            engine
+                .matching_lines("http_statuses", Op::Ge, "500")
+                .unwrap(),
+            [1]
+        );
+        let result = engine
+            .query(&["http_methods"], "http_statuses", Op::Lt, "500")
+            .unwrap();
+        assert_eq!(result.column_values("http_methods"), ["GET", "GET"]);
+        assert!(engine.filter_op("http_statuses", Op::Eq, "OK").is_err());
+    }
+
+    #[test]
+    fn test_typed_filter_duration() {
+        let text = "GET /a took 123ms\nGET /b took 1.5s\nGET /c took 2m30s\n";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        assert_eq!(
+            engine.select_column("durations").unwrap(),
+            ["123ms", "1.5s", "2m30s"]
+        );
+        // Compared in ns across units
+        assert_eq!(
+            engine.matching_lines("durations", Op::Gt, "500ms").unwrap(),
+            [1, 2]
+        );
+        assert!(engine.filter_op("durations", Op::Gt, "fast").is_err());
+    }
+
+    #[test]
+    fn test_typed_filter_byte_size() {
+        let text = "flushed 300KB\ncompacted 1.5GiB\nwrote 2GB\n";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        assert_eq!(
+            engine.select_column("byte_sizes").unwrap(),
+            ["300KB", "1.5GiB", "2GB"]
+        );
+        // Compared in bytes across decimal and binary units
+        assert_eq!(
+            engine.matching_lines("byte_sizes", Op::Gt, "1GB").unwrap(),
+            [1, 2]
+        );
+        assert_eq!(
+            engine
+                .matching_lines("byte_sizes", Op::Gt, "1.75GiB")
+                .unwrap(),
+            [2]
+        );
+        assert!(engine.filter_op("byte_sizes", Op::Gt, "huge").is_err());
+    }
+
+    #[test]
+    fn test_filter_hosts() {
+        let text = "\
+GET from web-01.prod.example.com
+GET from web-02.prod.example.com
+GET from db.staging.example.com
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        assert_eq!(
+            engine
+                .matching_lines("hosts", Op::Eq, "web-02.prod.example.com")
+                .unwrap(),
+            [1]
+        );
+        assert_eq!(
+            engine
+                .matching_lines("hosts", Op::EndsWith, ".prod.example.com")
+                .unwrap(),
+            [0, 1]
+        );
+    }
+
+    #[test]
+    fn test_typed_filter_pid() {
+        let text = "\
+sshd[8842]: session opened
+cron[77]: job started pid=77 tid=78
+sshd[8842]: session closed
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        assert_eq!(
+            engine.select_column("pids").unwrap(),
+            ["8842", "77", "77", "8842"]
+        );
+        assert_eq!(
+            engine.matching_lines("pids", Op::Eq, "8842").unwrap(),
+            [0, 2]
+        );
+        assert_eq!(engine.matching_lines("tids", Op::Eq, "78").unwrap(), [1]);
+        // Ids stay out of the numbers column
+        assert!(engine.select_column("numbers").unwrap().is_empty());
+        assert!(engine.filter_op("pids", Op::Eq, "sshd").is_err());
+        assert_eq!(FormatV3Writer::decompress(&data).unwrap(), text);
+    }
+
+    #[test]
+    fn test_rows_with_value() {
+        let text = "\
+2024-01-15 10:30:45 INFO login from 10.0.0.1 as alice@example.com
+2024-01-15 10:30:46 WARN retry from 10.0.0.2
+2024-01-15 10:30:47 INFO logout from 10.0.0.1 as alice@example.com
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        assert_eq!(engine.rows_with_value("ipv4", "10.0.0.1").unwrap(), [0, 2]);
+        assert_eq!(
+            engine
+                .rows_with_value("emails", "alice@example.com")
+                .unwrap(),
+            [0, 2]
+        );
+        assert_eq!(engine.rows_with_value("log_levels", "WARN").unwrap(), [1]);
+        assert!(engine
+            .rows_with_value("ipv4", "10.0.0.9")
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            engine.rows_with_value("no_such_column", "x"),
+            Err(ALICETextError::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            engine.filter_op("numbers", Op::Gt, "many"),
+            Err(ALICETextError::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            engine.filter_op("timestamps", Op::Gt, "yesterday-ish"),
+            Err(ALICETextError::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_query_aligns_rows() {
+        // Values are not one per line: line 0 has no address, line 2 two
+        let text = "\
+2024-01-15 10:30:45 INFO startup complete
+2024-01-15 10:30:46 ERROR refused 10.0.0.1
+2024-01-15 10:30:47 WARN proxy 10.0.0.2 via 10.0.0.3
+2024-01-15 10:30:48 ERROR refused 10.0.0.4
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        let result = engine
+            .query(&["ipv4", "log_levels"], "log_levels", Op::Eq, "ERROR")
+            .unwrap();
+        assert_eq!(result.column_values("ipv4"), ["10.0.0.1", "10.0.0.4"]);
+        let result = engine
+            .query(&["log_levels"], "ipv4", Op::Eq, "10.0.0.3")
+            .unwrap();
+        assert_eq!(result.column_values("log_levels"), ["WARN"]);
+
+        let rows = engine.select_rows(&["log_levels", "ipv4"]).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(!rows.rows[0].values.contains_key("ipv4"));
+        assert_eq!(rows.rows[2].values["ipv4"], "10.0.0.2");
+        assert_eq!(rows.rows[3].values["log_levels"], "ERROR");
+
+        let partial = FormatV3Writer::read_columns_by_row(
+            &mut Cursor::new(&data),
+            engine.metadata(),
+            &[ColumnType::IPv4],
+        )
+        .unwrap();
+        let row_map = partial.rows.unwrap();
+        assert_eq!(
+            row_map.values(2, ColumnType::IPv4).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(
+            row_map.value_rows(ColumnType::IPv4, 4).unwrap(),
+            [Some(1), Some(2), Some(2), Some(3)]
+        );
+        // A map pointing past the column is corrupt, not a reason to grow
+        assert!(row_map.value_rows(ColumnType::IPv4, 3).is_err());
+    }
+
+    #[test]
+    fn test_filter_multi_with_missing_fields() {
+        // Not every line has a level or an address
+        let text = "\
+connection from 10.0.0.7
+2024-01-15 10:30:46 ERROR disk full
+2024-01-15 10:30:47 ERROR refused 10.0.0.7
+2024-01-15 10:30:48 INFO accepted 10.0.0.8
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        // Column positions: the two ERRORs are levels 0 and 1, 10.0.0.7 is address 0 and 1
+        assert_eq!(
+            engine.filter_op("log_levels", Op::Eq, "ERROR").unwrap(),
+            [0, 1]
+        );
+        assert_eq!(
+            engine.filter_op("ipv4", Op::Eq, "10.0.0.7").unwrap(),
+            [0, 1]
+        );
+
+        // Line numbers: only line 2 has both
+        let filters = [
+            ("log_levels", Op::Eq, "ERROR"),
+            ("ipv4", Op::Eq, "10.0.0.7"),
+        ];
+        assert_eq!(engine.filter_multi(&filters).unwrap(), [2]);
+        assert_eq!(engine.filter_multi(&filters[1..]).unwrap(), [0, 2]);
+        assert_eq!(engine.filter_multi(&[]).unwrap(), [0, 1, 2, 3]);
+        // Substring operators work on typed columns through their text form
+        assert_eq!(
+            engine
+                .filter_multi(&[
+                    ("ipv4", Op::StartsWith, "10."),
+                    ("log_levels", Op::Ne, "ERROR"),
+                ])
+                .unwrap(),
+            [3]
+        );
+        assert!(engine
+            .filter_multi(&[("log_levels", Op::Eq, "FATAL"), ("nope", Op::Eq, "x")])
+            .is_err());
+
+        let matches = engine.query_rows(&["timestamps"], &filters).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_number, 2);
+        assert_eq!(
+            matches[0].line,
+            "2024-01-15 10:30:47 ERROR refused 10.0.0.7"
+        );
+        assert_eq!(matches[0].values["timestamps"], "2024-01-15 10:30:47");
+
+        let result = QueryBuilder::new(&engine)
+            .select(&["ipv4"])
+            .filter("log_levels", Op::Eq, "ERROR")
+            .filter("ipv4", Op::Ne, "10.0.0.8")
+            .execute()
+            .unwrap();
+        assert_eq!(result.column_values("ipv4"), ["10.0.0.7"]);
+    }
+
+    #[test]
+    fn test_in_and_not_filters() {
+        let text = "\
+connection from 10.0.0.7
+2024-01-15 10:30:46 ERROR disk full
+2024-01-15 10:30:47 FATAL refused 10.0.0.7
+2024-01-15 10:30:48 INFO accepted 10.0.0.8
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        assert_eq!(
+            engine
+                .matching_lines("log_levels", Op::In, "ERROR, FATAL")
+                .unwrap(),
+            [1, 2]
+        );
+        assert_eq!(
+            engine
+                .matching_lines("ipv4", Op::NotIn, "10.0.0.7,10.0.0.9")
+                .unwrap(),
+            [3]
+        );
+        assert!(engine
+            .matching_lines("ipv4", Op::In, "10.0.0.7,nope")
+            .is_err());
+
+        // NOT is per line: line 0 has no level, so it is not an ERROR line
+        let errors = [("log_levels", Op::Eq, "ERROR")];
+        assert_eq!(engine.filter_except(&[], &errors).unwrap(), [0, 2, 3]);
+        assert_eq!(
+            engine
+                .filter_except(
+                    &[("ipv4", Op::Eq, "10.0.0.7")],
+                    &[("log_levels", Op::Eq, "FATAL")]
+                )
+                .unwrap(),
+            [0]
+        );
+
+        assert!(matches!(
+            parse_filter("log_levels in ERROR,FATAL"),
+            Some(("log_levels", Op::In, "ERROR,FATAL"))
+        ));
+        assert!(matches!(
+            parse_filter("ipv4 NOT IN 10.0.0.7"),
+            Some(("ipv4", Op::NotIn, "10.0.0.7"))
+        ));
+        assert!(matches!(
+            parse_filter("messages=logged in today"),
+            Some(("messages", Op::Eq, "logged in today"))
+        ));
+        assert_eq!(
+            parse_negation("not log_levels=ERROR"),
+            (true, "log_levels=ERROR")
+        );
+        assert_eq!(parse_negation("!ipv4=10.0.0.7"), (true, "ipv4=10.0.0.7"));
+        assert_eq!(parse_negation("notes=x"), (false, "notes=x"));
+    }
+
+    #[test]
+    fn test_cidr_filter() {
+        let text = "\
+2024-01-15 10:30:45 INFO from 10.1.2.3
+2024-01-15 10:30:46 INFO from 192.168.1.20
+2024-01-15 10:30:47 INFO from 10.255.0.1 via 2001:0db8:85a3:0000:0000:8a2e:0370:7334
+2024-01-15 10:30:48 INFO from 11.0.0.1 via 2001:0db9:0000:0000:0000:0000:0000:0001
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        assert_eq!(
+            engine
+                .matching_lines("ipv4", Op::InCidr, "10.0.0.0/8")
+                .unwrap(),
+            [0, 2]
+        );
+        assert_eq!(
+            engine
+                .matching_lines("ipv4", Op::InCidr, "192.168.1.16/28")
+                .unwrap(),
+            [1]
+        );
+        assert_eq!(
+            engine
+                .filter_op("ipv4", Op::InCidr, "0.0.0.0/0")
+                .unwrap()
+                .len(),
+            4
+        );
+        assert_eq!(
+            engine
+                .matching_lines("ipv6", Op::InCidr, "2001:db8::/32")
+                .unwrap(),
+            [2]
+        );
+        // Expanded addresses stay typed and come back as written
+        assert_eq!(
+            engine
+                .filter_op("ipv6", Op::Eq, "2001:db9::1")
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(engine.lines(&[3]).unwrap(), [text.lines().nth(3).unwrap()]);
+        assert_eq!(engine.decompress_all().unwrap(), text);
+        for bad in ["10.0.0.0", "10.0.0.0/33", "10.0.0/8"] {
+            assert!(matches!(
+                engine.filter_op("ipv4", Op::InCidr, bad),
+                Err(ALICETextError::InvalidQuery(_))
+            ));
+        }
+        assert!(engine
+            .filter_op("log_levels", Op::InCidr, "10.0.0.0/8")
+            .is_err());
+        assert!(matches!(
+            parse_filter("ipv4 within 10.0.0.0/8"),
+            Some(("ipv4", Op::InCidr, "10.0.0.0/8"))
+        ));
+    }
+
+    #[test]
+    fn test_numbers_byte_shuffle() {
+        // A random walk printed at full precision: the high bytes of
+        // neighbouring values repeat, the low bytes are noise
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut load = 40.0;
+        let loads: Vec<f64> = (0..4000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                load += (seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+                load
+            })
+            .collect();
+        let text: String = loads.iter().map(|v| format!("gauge load {v}\n")).collect();
+        let data = compress_v3(&text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+        let entry = engine.metadata.get_column(ColumnType::Numbers).unwrap();
+        assert_eq!(entry.encoding, ColumnEncoding::Shuffle);
+        let bits: Vec<u64> = loads.iter().map(|v| v.to_bits()).collect();
+        let plain =
+            zstd::stream::encode_all(Cursor::new(crate::column_codec::encode_column(&bits)), 3)
+                .unwrap();
+        assert!((entry.compressed_size as usize) < plain.len());
+        assert_eq!(engine.decompress_all().unwrap(), text);
+
+        let expected: Vec<usize> = (0..loads.len()).filter(|&i| loads[i] > 40.0).collect();
+        assert_eq!(
+            engine.filter_op("numbers", Op::Gt, "40").unwrap().to_vec(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_column_encodings() {
+        // uuids: four values in scrambled order; pids: sequential
+        let text: String = (0..2000usize)
+            .map(|i| {
+                format!(
+                    "worker[{}]: req 550e8400-e29b-41d4-a716-4466554400{:02}\n",
+                    1000 + i,
+                    i * 7919 % 13 % 4
+                )
+            })
+            .collect();
+        let data = compress_v3(&text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+        let encoding = |col_type| engine.metadata.get_column(col_type).unwrap().encoding;
+        assert_eq!(encoding(ColumnType::UUIDs), ColumnEncoding::Dict);
+        assert_eq!(encoding(ColumnType::Pids), ColumnEncoding::Delta);
+        assert_eq!(engine.decompress_all().unwrap(), text);
+
+        // Dictionary columns: the filter tests the distinct values, then selects rows by index
+        let uuid = "550e8400-e29b-41d4-a716-446655440002";
+        let hits = engine.filter_op("uuids", Op::Eq, uuid).unwrap();
+        let expected: Vec<usize> = (0..2000).filter(|i| i * 7919 % 13 % 4 == 2).collect();
+        assert_eq!(hits.to_vec(), expected);
+        let misses = engine.filter_op("uuids", Op::Ne, uuid).unwrap();
+        assert_eq!(hits.len() + misses.len(), 2000);
+        assert_eq!(engine.select_column("uuids").unwrap()[expected[0]], uuid);
+
+        assert_eq!(engine.filter_op("pids", Op::Eq, "2500").unwrap(), [1500]);
+        assert_eq!(
+            engine.filter_op("pids", Op::Between, "1000..1002").unwrap(),
+            [0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_between_and_relative_time() {
+        let text = "\
+2024-01-15 10:30:45 INFO GET /a 200
+2024-01-15 10:31:45 ERROR GET /b 503
+2024-01-15 10:32:45 ERROR GET /c 404
+2024-01-15 10:33:45 INFO GET /d 201
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        let range = "2024-01-15 10:31:00..2024-01-15 10:32:45";
+        assert_eq!(
+            engine
+                .matching_lines("timestamps", Op::Between, range)
+                .unwrap(),
+            [1, 2]
+        );
+        assert!(engine
+            .filter_op("timestamps", Op::Between, "2024-01-15 10:31:00")
+            .is_err());
+
+        // Relative bounds count from now: 2024 is long gone
+        assert!(engine
+            .matching_lines("timestamps", Op::Ge, "now-1h")
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            engine
+                .matching_lines("timestamps", Op::Between, "-36500d..now")
+                .unwrap()
+                .len(),
+            4
+        );
+
+        assert!(matches!(
+            parse_filter("timestamps between now-2h..now"),
+            Some(("timestamps", Op::Between, "now-2h..now"))
+        ));
+
+        let now = 1_705_314_645_000;
+        assert_eq!(parse_relative_time("now", now), Some(now));
+        assert_eq!(parse_relative_time("now-1h", now), Some(now - 3_600_000));
+        assert_eq!(parse_relative_time("-15m", now), Some(now - 900_000));
+        assert_eq!(parse_relative_time("now+1d2h", now), Some(now + 93_600_000));
+        assert_eq!(parse_relative_time("+1h", now), None);
+        assert_eq!(parse_relative_time("2024-01-15", now), None);
+        assert_eq!(parse_relative_time("nowhere", now), None);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let text = "\
+2024-01-15 10:30:45 INFO 10.0.0.1 \"GET /a HTTP/1.1\" 200
+2024-01-15 10:30:46 ERROR 10.0.0.2 \"GET /b HTTP/1.1\" 503
+2024-01-15 10:30:47 ERROR 10.0.0.2 \"GET /b HTTP/1.1\" 503
+2024-01-15 10:30:48 INFO 10.0.0.3 \"GET /c HTTP/1.1\" 200
+2024-01-15 10:30:49 ERROR 10.0.0.1 \"GET /b HTTP/1.1\" 404
+2024-01-15 10:30:50 INFO 10.0.0.2 \"GET /a HTTP/1.1\" 200
+";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+        let top = |column, k, lines| engine.top_k(column, k, lines).unwrap();
+        let pairs = |p: &[(&str, usize)]| -> Vec<(String, usize)> {
+            p.iter().map(|&(v, n)| (v.to_string(), n)).collect()
+        };
+
+        // Ties break by value
+        assert_eq!(
+            top("ipv4", 2, None),
+            pairs(&[("10.0.0.2", 3), ("10.0.0.1", 2)])
+        );
+        assert_eq!(top("paths", 1, None), pairs(&[("/b", 3)]));
+        assert_eq!(top("log_levels", 10, None).len(), 2);
+
+        let errors = engine
+            .filter_multi(&[("log_levels", Op::Eq, "ERROR")])
+            .unwrap();
+        assert_eq!(
+            top("ipv4", 5, Some(&errors)),
+            pairs(&[("10.0.0.2", 2), ("10.0.0.1", 1)])
+        );
+        assert_eq!(
+            top("http_statuses", 5, Some(&errors)),
+            pairs(&[("503", 2), ("404", 1)])
+        );
+        assert!(top("ipv4", 5, Some(&RowSet::new())).is_empty());
+        assert!(engine.top_k("nope", 5, None).is_err());
+    }
+
     #[test]
     fn test_parallel_query() {
         let data = create_test_data();
@@ -915,6 +2920,57 @@ mod tests {
         for row in &result.rows {
             assert_eq!(row.values.get("log_levels").unwrap(), "ERROR");
         }
+
+        // Same answer on a dedicated single-thread pool
+        #[cfg(feature = "parallel")]
+        {
+            let engine = engine.with_threads(1).unwrap();
+            let bounded = engine
+                .query(&["log_levels", "ipv4"], "log_levels", Op::Eq, "ERROR")
+                .unwrap();
+            assert!(bounded
+                .rows
+                .iter()
+                .map(|r| &r.values)
+                .eq(result.rows.iter().map(|r| &r.values)));
+            assert_eq!(engine.install(rayon::current_num_threads), 1);
+        }
+    }
+
+    #[test]
+    fn test_from_slice_borrows() {
+        let data = create_test_data();
+        let engine = QueryEngine::from_slice(&data).unwrap();
+        assert_eq!(engine.source.as_slice().as_ptr(), data.as_ptr());
+        assert_eq!(
+            engine
+                .filter_op("log_levels", Op::Eq, "ERROR")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(QueryEngine::from_slice(&data[..4]).is_err());
+    }
+
+    #[test]
+    fn test_from_reader_untrusted() {
+        let data = create_test_data();
+        let untrusted = crate::DecodeProfile::Untrusted.options();
+        let engine = QueryEngine::from_reader_with(Cursor::new(&data), untrusted).unwrap();
+        assert_eq!(engine.metadata.limits, untrusted.limits);
+        assert_eq!(
+            engine
+                .filter_op("log_levels", Op::Eq, "ERROR")
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let small = untrusted.with_limits(untrusted.limits.with_max_archive_bytes(16));
+        assert!(matches!(
+            QueryEngine::from_reader_with(Cursor::new(&data), small),
+            Err(ALICETextError::LimitExceeded { limit: 16, .. })
+        ));
     }
 
     #[test]
@@ -940,4 +2996,134 @@ mod tests {
         let decompressed = engine.decompress_all().unwrap();
         assert_eq!(text, decompressed);
     }
+
+    #[test]
+    fn test_sample_head_and_uniform() {
+        let data = create_test_data();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+        let text = engine.decompress_all().unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(
+            engine.sample(2, SamplingStrategy::Head).unwrap(),
+            lines[..2]
+        );
+        assert_eq!(
+            engine.sample(3, SamplingStrategy::Uniform).unwrap(),
+            [lines[0], lines[1], lines[3]]
+        );
+        assert_eq!(engine.sample(100, SamplingStrategy::Head).unwrap(), lines);
+        assert!(engine
+            .sample(0, SamplingStrategy::Uniform)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_query_with_lines() {
+        let data = create_test_data();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+        let text = engine.decompress_all().unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        let matches = engine
+            .query_with_lines(&["ipv4"], "log_levels", Op::Eq, "ERROR")
+            .unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].line_number, 1);
+        assert_eq!(matches[0].line, lines[1]);
+        assert_eq!(matches[0].values["ipv4"], "192.168.1.2");
+        assert_eq!(matches[1].line, lines[4]);
+        assert_eq!(matches[1].values["ipv4"], "192.168.1.5");
+
+        // Same row join as query()
+        let rows = engine
+            .query(&["ipv4"], "log_levels", Op::Eq, "ERROR")
+            .unwrap();
+        assert_eq!(rows.column_values("ipv4"), ["192.168.1.2", "192.168.1.5"]);
+
+        let by_ip = engine
+            .query_with_lines(&["log_levels"], "ipv4", Op::Eq, "192.168.1.3")
+            .unwrap();
+        assert_eq!(by_ip.len(), 1);
+        assert_eq!(by_ip[0].line, lines[2]);
+        assert_eq!(by_ip[0].values["log_levels"], "WARN");
+
+        assert!(engine
+            .query_with_lines(&["ipv4"], "log_levels", Op::Eq, "FATAL")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lines_and_matching_lines() {
+        let data = create_test_data();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+        let text = engine.decompress_all().unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(engine.line_count().unwrap(), lines.len());
+        assert_eq!(engine.lines(&[4, 0, 99]).unwrap(), [lines[4], lines[0]]);
+        assert_eq!(
+            engine
+                .matching_lines("log_levels", Op::Eq, "ERROR")
+                .unwrap(),
+            [1, 4]
+        );
+        assert_eq!(
+            engine
+                .matching_lines("ipv4", Op::Eq, "192.168.1.3")
+                .unwrap(),
+            [2]
+        );
+        assert!(engine
+            .matching_lines("log_levels", Op::Eq, "FATAL")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lines_cached_index() {
+        let text = "\n192.168.1.1 up\n2024-01-15 10:30:00 ERROR x\n\nINFO 10.0.0.2\ntrailing 42";
+        let data = compress_v3(text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+        let expected: Vec<&str> = text.lines().collect();
+
+        assert!(engine.line_starts.get().is_none());
+        let all: Vec<usize> = (0..expected.len()).collect();
+        assert_eq!(engine.lines(&all).unwrap(), expected);
+        assert_eq!(engine.line_starts.get().unwrap().len(), expected.len());
+        assert_eq!(engine.line_count().unwrap(), expected.len());
+        // Later fetches reuse the index
+        assert_eq!(
+            engine.lines(&[5, 1, 9]).unwrap(),
+            [expected[5], expected[1]]
+        );
+    }
+
+    #[test]
+    fn test_sample_per_level() {
+        let mut text = String::new();
+        for i in 0..50 {
+            text.push_str(&format!("2024-01-15 10:30:{:02} INFO ok {i}\n", i % 60));
+        }
+        text.push_str("2024-01-15 10:31:00 ERROR disk full\n");
+        text.push_str("plain line without level");
+        let data = compress_v3(&text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+
+        let sample = engine.sample(3, SamplingStrategy::PerLevel).unwrap();
+        assert_eq!(
+            sample,
+            [
+                "2024-01-15 10:30:00 INFO ok 0",
+                "2024-01-15 10:31:00 ERROR disk full",
+                "plain line without level",
+            ]
+        );
+        assert_eq!(
+            SamplingStrategy::from_name("per_level"),
+            Some(SamplingStrategy::PerLevel)
+        );
+    }
 }
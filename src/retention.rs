@@ -0,0 +1,372 @@
+//! Retention — predicate-based row deletion on v3 archives
+//!
+//! A row is one line of the original text. [`delete_rows`] walks the
+//! skeleton, hands each line and the column values extracted from it to a
+//! predicate, and re-encodes the surviving lines into a fresh archive with
+//! the original level and header extensions, so every column and the skeleton lose
+//! the matching entries together.
+//!
+//! ```rust,ignore
+//! use alice_text::retention::{delete_rows, ipv4_in_range, older_than};
+//!
+//! let cutoff = chrono::Utc::now().timestamp_millis() - 90 * 86_400_000;
+//! let (pruned, stats) = delete_rows(&archive, older_than(cutoff))?;
+//! let (pruned, _) = delete_rows(&pruned, ipv4_in_range("10.0.0.0".parse()?, 8))?;
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::column_plugin::ColumnPlugin;
+use crate::columnar_encoder::{ColumnarPayload, LinePiece};
+use crate::filter::parse_timestamp_ms;
+use crate::format_v3::{FormatV3Metadata, FormatV3Writer};
+use crate::provenance::{LineOrigin, ProvenanceColumn};
+use crate::source_profile::{RawSegment, RAW_PLACEHOLDER};
+use crate::Result;
+
+/// One line of the archive plus the column values found on it
+#[derive(Debug, Clone, Default)]
+pub struct RowView {
+    line_number: usize,
+    line: String,
+    fields: Vec<(&'static str, String)>,
+    origin: Option<(String, LineOrigin)>,
+}
+
+impl RowView {
+    /// Zero-based line index in the original text
+    #[must_use]
+    pub const fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// Full line text (including the trailing `\n`, if any)
+    #[must_use]
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Values of a column on this line, in order (query-engine column names)
+    pub fn values<'a>(&'a self, column: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(name, _)| *name == column)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// First value of a column on this line
+    #[must_use]
+    pub fn first<'a>(&'a self, column: &'a str) -> Option<&'a str> {
+        self.values(column).next()
+    }
+
+    /// First parseable timestamp on this line, as Unix milliseconds
+    #[must_use]
+    pub fn timestamp_ms(&self) -> Option<i64> {
+        self.fields
+            .iter()
+            .filter(|(name, _)| matches!(*name, "timestamps" | "timestamps_raw"))
+            .find_map(|(_, value)| parse_timestamp_ms(value).ok())
+    }
+
+    /// IPv4 addresses on this line
+    pub fn ipv4_addrs(&self) -> impl Iterator<Item = Ipv4Addr> + '_ {
+        self.values("ipv4").filter_map(|v| v.parse().ok())
+    }
+
+    /// Source name and position of this line (archives with a provenance column)
+    #[must_use]
+    pub fn origin(&self) -> Option<(&str, LineOrigin)> {
+        self.origin
+            .as_ref()
+            .map(|(source, origin)| (source.as_str(), *origin))
+    }
+}
+
+/// Row deletion statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionStats {
+    /// Rows in the input archive
+    pub rows_before: usize,
+    /// Rows removed by the predicate
+    pub rows_removed: usize,
+    /// Original text bytes removed
+    pub bytes_removed: usize,
+}
+
+/// Split a payload into rows (lines) with their column values and origins
+fn rows(payload: &ColumnarPayload, provenance: Option<&ProvenanceColumn>) -> Vec<RowView> {
+    let prefix_sums = payload.prepare_for_read();
+    ColumnarPayload::split_lines(&payload.skeleton_tokens)
+        .into_iter()
+        .enumerate()
+        .map(|(line_number, pieces)| {
+            let mut row = RowView {
+                line_number,
+                origin: provenance
+                    .and_then(|p| p.get(line_number))
+                    .map(|(source, origin)| (source.to_string(), origin)),
+                ..RowView::default()
+            };
+            for piece in pieces {
+                match piece {
+                    LinePiece::Text(text) => row.line.push_str(text),
+                    LinePiece::Ref(idx) => {
+                        let idx = idx as usize;
+                        if let Some(value) = payload.get_value_fast(idx, &prefix_sums) {
+                            row.line.push_str(&value);
+                            if let Some(column) = payload.placeholder_column(idx) {
+                                row.fields.push((column.name(), value));
+                            }
+                        }
+                    }
+                }
+            }
+            row
+        })
+        .collect()
+}
+
+/// Produce a new v3 archive without the rows matching `predicate`
+///
+/// The provenance and line ids of the surviving rows are kept, as are the
+/// archive's level, saved views and other header extensions.
+///
+/// # Errors
+///
+/// Returns an error if `data` is not a valid v3 archive or re-encoding fails.
+pub fn delete_rows<F>(data: &[u8], predicate: F) -> Result<(Vec<u8>, RetentionStats)>
+where
+    F: Fn(&RowView) -> bool,
+{
+    delete_rows_with_plugins(data, &[], predicate)
+}
+
+/// [`delete_rows`] for an archive written with column plugins; the pruned
+/// archive is encoded with the same `plugins`
+///
+/// # Errors
+///
+/// Same as [`delete_rows`], or `data` needs a plugin not among `plugins`.
+pub fn delete_rows_with_plugins<F>(
+    data: &[u8],
+    plugins: &[Arc<dyn ColumnPlugin>],
+    predicate: F,
+) -> Result<(Vec<u8>, RetentionStats)>
+where
+    F: Fn(&RowView) -> bool,
+{
+    let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(data))?;
+    let payload = FormatV3Writer::decode_payload(data, plugins)?;
+    let provenance = FormatV3Writer::read_provenance(data)?;
+    let rows = rows(&payload, provenance.as_ref());
+
+    let mut stats = RetentionStats {
+        rows_before: rows.len(),
+        ..RetentionStats::default()
+    };
+    // The header's length is a claim: reserve no more than the decode limit
+    let mut kept = String::with_capacity(
+        metadata
+            .header
+            .original_length
+            .min(metadata.limits.max_total_bytes) as usize,
+    );
+    // Invalid UTF-8 runs follow their placeholders; removed placeholders shift the rest
+    let mut raw = payload.raw_bytes.iter().peekable();
+    let mut kept_raw: Vec<RawSegment> = Vec::new();
+    let mut kept_lines = Vec::with_capacity(rows.len());
+    let (mut ordinal, mut removed_placeholders) = (0u64, 0u64);
+    for row in &rows {
+        let placeholders = row.line.matches(RAW_PLACEHOLDER).count() as u64;
+        let row_raw = std::iter::from_fn(|| raw.next_if(|s| s.ordinal < ordinal + placeholders));
+        if predicate(row) {
+            stats.rows_removed += 1;
+            stats.bytes_removed += row.line.len();
+            row_raw.for_each(drop);
+            removed_placeholders += placeholders;
+        } else {
+            kept.push_str(&row.line);
+            kept_lines.push(row.line_number);
+            kept_raw.extend(row_raw.map(|s| RawSegment {
+                ordinal: s.ordinal - removed_placeholders,
+                bytes: s.bytes.clone(),
+            }));
+        }
+        ordinal += placeholders;
+    }
+
+    let was_kept = |i: usize| kept_lines.binary_search(&i).is_ok();
+    let kept_provenance = provenance.map(|p| p.retain_lines(was_kept));
+    let kept_ids = FormatV3Writer::read_line_ids(data)?.map(|ids| ids.retain_lines(was_kept));
+
+    let pruned = FormatV3Writer::matching(&metadata, plugins)?.compress_normalized(
+        &kept,
+        metadata.header.source_profile(),
+        &kept_raw,
+        kept_provenance.as_ref(),
+        kept_ids.as_ref(),
+    )?;
+    Ok((pruned, stats))
+}
+
+/// Predicate: row timestamp earlier than `cutoff_ms` (rows without a timestamp are kept)
+pub fn older_than(cutoff_ms: i64) -> impl Fn(&RowView) -> bool {
+    move |row| row.timestamp_ms().is_some_and(|ts| ts < cutoff_ms)
+}
+
+/// Predicate: row merged from source `name` (rows without provenance are kept)
+pub fn from_source(name: &str) -> impl Fn(&RowView) -> bool + '_ {
+    move |row| row.origin().is_some_and(|(source, _)| source == name)
+}
+
+/// Predicate: any IPv4 address on the row inside `network/prefix_len`
+pub fn ipv4_in_range(network: Ipv4Addr, prefix_len: u8) -> impl Fn(&RowView) -> bool {
+    let mask = u32::MAX
+        .checked_shl(32 - u32::from(prefix_len.min(32)))
+        .unwrap_or(0);
+    let network = u32::from(network) & mask;
+    move |row| row.ipv4_addrs().any(|ip| u32::from(ip) & mask == network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format_v3::{CompressionLevel, HeaderExtension};
+    use crate::saved_view::SavedView;
+    use crate::{compress_v3, decompress_v3, Level};
+
+    const LOG: &str = "2024-01-10 08:00:00 INFO boot from 192.168.1.10\n\
+                       2024-03-01 09:15:00 WARN slow from 10.1.2.3\n\
+                       no timestamp here\n\
+                       2024-04-20 12:00:00 ERROR crash from 192.168.1.11\n\
+                       2024-04-21 12:00:00 INFO ok from 10.200.0.1";
+
+    fn lines_without(skip: &[usize]) -> String {
+        LOG.split_inclusive('\n')
+            .enumerate()
+            .filter(|(i, _)| !skip.contains(i))
+            .map(|(_, l)| l)
+            .collect()
+    }
+
+    #[test]
+    fn test_delete_older_than() {
+        let data = compress_v3(LOG, CompressionLevel::Fast).unwrap();
+        let cutoff = parse_timestamp_ms("2024-04-01 00:00:00").unwrap();
+
+        let (pruned, stats) = delete_rows(&data, older_than(cutoff)).unwrap();
+        assert_eq!(stats.rows_before, 5);
+        assert_eq!(stats.rows_removed, 2);
+        assert_eq!(decompress_v3(&pruned).unwrap(), lines_without(&[0, 1]));
+
+        // Columns shrink along with the skeleton
+        #[cfg(feature = "query")]
+        {
+            let engine = crate::QueryEngine::from_reader(std::io::Cursor::new(&pruned)).unwrap();
+            assert_eq!(
+                engine.select_column("log_levels").unwrap(),
+                ["ERROR", "INFO"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_delete_ipv4_range() {
+        let data = compress_v3(LOG, CompressionLevel::Balanced).unwrap();
+        let (pruned, stats) =
+            delete_rows(&data, ipv4_in_range(Ipv4Addr::new(10, 0, 0, 0), 8)).unwrap();
+        assert_eq!(stats.rows_removed, 2);
+        assert_eq!(
+            stats.bytes_removed,
+            LOG.len() - lines_without(&[1, 4]).len()
+        );
+        assert_eq!(decompress_v3(&pruned).unwrap(), lines_without(&[1, 4]));
+
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&pruned)).unwrap();
+        assert_eq!(
+            metadata.header.compression_level,
+            CompressionLevel::Balanced as u8
+        );
+    }
+
+    #[test]
+    fn test_delete_keeps_raw_bytes() {
+        let input = b"2024-01-10 08:00:00 INFO a \xFF\n\
+                      2024-03-01 09:15:00 WARN b \xFE\xFE\n\
+                      2024-04-20 12:00:00 ERROR c \xC0\n";
+        let data = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress_bytes(input)
+            .unwrap();
+        let (pruned, stats) = delete_rows(&data, |row| row.line_number() == 1).unwrap();
+        assert_eq!(stats.rows_removed, 1);
+        assert_eq!(
+            FormatV3Writer::decompress_bytes(&pruned).unwrap(),
+            b"2024-01-10 08:00:00 INFO a \xFF\n2024-04-20 12:00:00 ERROR c \xC0\n"
+        );
+    }
+
+    #[test]
+    fn test_delete_from_source() {
+        let mut batch = crate::SourceBatch::new();
+        batch.add(
+            "a.log",
+            "2024-01-10 08:00:00 INFO a1\n2024-01-10 08:00:01 INFO a2\n",
+        );
+        batch.add("b.log", "2024-01-10 08:00:02 WARN b1\n");
+        batch.add("a.log", "2024-01-10 08:00:03 INFO a3\n");
+        let data = FormatV3Writer::new(CompressionLevel::Fast)
+            .compress_batch(&batch)
+            .unwrap();
+
+        let (pruned, stats) = delete_rows(&data, from_source("b.log")).unwrap();
+        assert_eq!(stats.rows_removed, 1);
+        let provenance = FormatV3Writer::read_provenance(&pruned).unwrap().unwrap();
+        assert_eq!(provenance.len(), 3);
+        assert_eq!(provenance.describe(2).as_deref(), Some("a.log:3"));
+    }
+
+    #[test]
+    fn test_row_view_fields() {
+        let data = compress_v3(LOG, CompressionLevel::Fast).unwrap();
+        let (_, stats) = delete_rows(&data, |row| {
+            row.line_number() == 3
+                && row.first("log_levels") == Some("ERROR")
+                && row.values("ipv4").eq(["192.168.1.11"])
+        })
+        .unwrap();
+        assert_eq!(stats.rows_removed, 1);
+
+        let (unchanged, stats) = delete_rows(&data, |_| false).unwrap();
+        assert_eq!(stats.rows_removed, 0);
+        assert_eq!(decompress_v3(&unchanged).unwrap(), LOG);
+    }
+
+    #[test]
+    fn test_delete_keeps_level_and_extensions() {
+        let data = FormatV3Writer::new(Level::Zstd(7))
+            .with_detected_profile(LOG)
+            .unwrap()
+            .with_views(&[SavedView::new("errors").filter("log_levels=ERROR")])
+            .unwrap()
+            .with_extension(HeaderExtension::new(0x0123, b"custom".to_vec()))
+            .compress(LOG)
+            .unwrap();
+        let before = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&data)).unwrap();
+
+        let (pruned, _) = delete_rows(&data, |row| row.line_number() == 2).unwrap();
+        let after = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&pruned)).unwrap();
+        assert_eq!(after.level(), Level::Zstd(7));
+        assert!(after.source_kind().is_some());
+        assert_eq!(after.source_kind(), before.source_kind());
+        assert_eq!(after.saved_views().unwrap(), before.saved_views().unwrap());
+        assert_eq!(after.extension(0x0123), Some(&b"custom"[..]));
+        assert_eq!(decompress_v3(&pruned).unwrap(), lines_without(&[2]));
+    }
+}
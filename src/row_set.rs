@@ -0,0 +1,457 @@
+//! Row sets — compressed bitmaps of line numbers / value positions
+//!
+//! Filters return a [`RowSet`] instead of a `Vec<usize>`. Like a roaring
+//! bitmap, the set is split into chunks of 65 536 rows keyed by the high
+//! bits; a chunk holding at most [`ARRAY_MAX`] rows is a sorted `u16` array,
+//! a denser one is a 8 KiB bitmap. A million matching rows take about 128 KiB
+//! instead of 8 MB, and union / intersection / difference work a chunk (or a
+//! 64-bit word) at a time.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Sub};
+
+/// Rows a chunk holds before it switches from an array to a bitmap
+pub const ARRAY_MAX: usize = 4096;
+
+/// Rows per chunk
+const CHUNK_BITS: u32 = 16;
+const CHUNK_WORDS: usize = (1 << CHUNK_BITS) / 64;
+
+/// Rows of one chunk (low 16 bits)
+#[derive(Clone, PartialEq, Eq)]
+enum Chunk {
+    /// Ascending, at most [`ARRAY_MAX`] entries
+    Array(Vec<u16>),
+    /// One bit per row, with its population count
+    Bitmap(Box<[u64; CHUNK_WORDS]>, usize),
+}
+
+impl Chunk {
+    fn len(&self) -> usize {
+        match self {
+            Self::Array(values) => values.len(),
+            Self::Bitmap(_, len) => *len,
+        }
+    }
+
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Self::Array(values) => values.binary_search(&low).is_ok(),
+            Self::Bitmap(words, _) => words[usize::from(low) / 64] >> (low % 64) & 1 == 1,
+        }
+    }
+
+    /// Add `low` in place; returns whether it was new
+    fn insert(&mut self, low: u16) -> bool {
+        match self {
+            Self::Array(values) => {
+                let Err(at) = values.binary_search(&low) else {
+                    return false;
+                };
+                values.insert(at, low);
+                if values.len() > ARRAY_MAX {
+                    *self = Self::Bitmap(self.words(), ARRAY_MAX + 1);
+                }
+            }
+            Self::Bitmap(words, len) => {
+                let (word, bit) = (usize::from(low) / 64, 1 << (low % 64));
+                if words[word] & bit != 0 {
+                    return false;
+                }
+                words[word] |= bit;
+                *len += 1;
+            }
+        }
+        true
+    }
+
+    fn words(&self) -> Box<[u64; CHUNK_WORDS]> {
+        match self {
+            Self::Array(values) => {
+                let mut words = Box::new([0u64; CHUNK_WORDS]);
+                for &low in values {
+                    words[usize::from(low) / 64] |= 1 << (low % 64);
+                }
+                words
+            }
+            Self::Bitmap(words, _) => words.clone(),
+        }
+    }
+
+    /// Smallest representation of `words`; `None` if empty
+    fn from_words(words: Box<[u64; CHUNK_WORDS]>) -> Option<Self> {
+        let len = words.iter().map(|w| w.count_ones() as usize).sum();
+        match len {
+            0 => None,
+            len if len <= ARRAY_MAX => Some(Self::Array(bits(&words).collect())),
+            len => Some(Self::Bitmap(words, len)),
+        }
+    }
+
+    /// Chunk of ascending, deduplicated `values`; `None` if empty
+    fn from_sorted(values: Vec<u16>) -> Option<Self> {
+        if values.len() <= ARRAY_MAX {
+            return (!values.is_empty()).then_some(Self::Array(values));
+        }
+        Self::from_words(Self::Array(values).words())
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Self::Array(values) => Box::new(values.iter().copied()),
+            Self::Bitmap(words, _) => Box::new(bits(words)),
+        }
+    }
+
+    fn and(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Array(a), Self::Array(b)) => Self::from_sorted(merge(a, b, |x, y| x && y)),
+            (Self::Array(a), bitmap) | (bitmap, Self::Array(a)) => {
+                Self::from_sorted(a.iter().copied().filter(|&v| bitmap.contains(v)).collect())
+            }
+            (Self::Bitmap(a, _), Self::Bitmap(b, _)) => {
+                Self::from_words(zip_words(a, b, |x, y| x & y))
+            }
+        }
+    }
+
+    fn or(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Array(a), Self::Array(b)) => Self::from_sorted(merge(a, b, |x, y| x || y)),
+            _ => Self::from_words(zip_words(&self.words(), &other.words(), |x, y| x | y)),
+        }
+    }
+
+    fn and_not(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Array(a), Self::Array(b)) => Self::from_sorted(merge(a, b, |x, y| x && !y)),
+            (Self::Array(a), bitmap) => {
+                Self::from_sorted(a.iter().copied().filter(|&v| !bitmap.contains(v)).collect())
+            }
+            _ => Self::from_words(zip_words(&self.words(), &other.words(), |x, y| x & !y)),
+        }
+    }
+}
+
+/// Set bits of a chunk bitmap, ascending
+fn bits(words: &[u64; CHUNK_WORDS]) -> impl Iterator<Item = u16> + '_ {
+    words.iter().enumerate().flat_map(|(i, &word)| {
+        let mut rest = word;
+        std::iter::from_fn(move || {
+            (rest != 0).then(|| {
+                let bit = rest.trailing_zeros();
+                rest &= rest - 1;
+                (i * 64) as u16 + bit as u16
+            })
+        })
+    })
+}
+
+fn zip_words(
+    a: &[u64; CHUNK_WORDS],
+    b: &[u64; CHUNK_WORDS],
+    op: impl Fn(u64, u64) -> u64,
+) -> Box<[u64; CHUNK_WORDS]> {
+    let mut out = Box::new([0u64; CHUNK_WORDS]);
+    for ((o, &x), &y) in out.iter_mut().zip(a.iter()).zip(b.iter()) {
+        *o = op(x, y);
+    }
+    out
+}
+
+/// Sorted merge of two ascending arrays, keeping values for which
+/// `keep(in_a, in_b)` holds
+fn merge(a: &[u16], b: &[u16], keep: impl Fn(bool, bool) -> bool) -> Vec<u16> {
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let (value, in_a, in_b) = match (a.get(i), b.get(j)) {
+            (Some(&x), Some(&y)) if x == y => (x, true, true),
+            (Some(&x), Some(&y)) if x < y => (x, true, false),
+            (Some(&x), None) => (x, true, false),
+            (_, Some(&y)) => (y, false, true),
+            (None, None) => unreachable!(),
+        };
+        i += usize::from(in_a);
+        j += usize::from(in_b);
+        if keep(in_a, in_b) {
+            out.push(value);
+        }
+    }
+    out
+}
+
+/// Compressed set of row numbers (line numbers or value positions)
+///
+/// Iteration is ascending. Build one by collecting row numbers (in any
+/// order) or with [`RowSet::full`]; combine with `&`, `|` and `-` or the
+/// named methods.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct RowSet {
+    /// (high bits, non-empty chunk), ascending by key
+    chunks: Vec<(u64, Chunk)>,
+}
+
+impl RowSet {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rows `0..count`
+    #[must_use]
+    pub fn full(count: usize) -> Self {
+        (0..count).collect()
+    }
+
+    /// Number of rows
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.chunks.iter().map(|(_, chunk)| chunk.len()).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    #[must_use]
+    pub fn contains(&self, row: usize) -> bool {
+        let (key, low) = split(row);
+        self.chunks
+            .binary_search_by_key(&key, |(k, _)| *k)
+            .is_ok_and(|i| self.chunks[i].1.contains(low))
+    }
+
+    /// Add `row`; returns whether it was new
+    pub fn insert(&mut self, row: usize) -> bool {
+        let (key, low) = split(row);
+        let i = match self.chunks.binary_search_by_key(&key, |(k, _)| *k) {
+            Ok(i) => i,
+            Err(i) => {
+                self.chunks.insert(i, (key, Chunk::Array(vec![low])));
+                return true;
+            }
+        };
+        self.chunks[i].1.insert(low)
+    }
+
+    /// Smallest row
+    #[must_use]
+    pub fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+
+    /// Rows, ascending
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks.iter().flat_map(|(key, chunk)| {
+            let base = (key << CHUNK_BITS) as usize;
+            chunk.iter().map(move |low| base | usize::from(low))
+        })
+    }
+
+    /// Rows, ascending, as a vector
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+
+    /// Rows in both sets
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, Chunk::and, false, false)
+    }
+
+    /// Rows in either set
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, Chunk::or, true, true)
+    }
+
+    /// Rows of `self` not in `other`
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, Chunk::and_not, true, false)
+    }
+
+    /// Rows of `0..count` not in this set (negation)
+    #[must_use]
+    pub fn complement(&self, count: usize) -> Self {
+        Self::full(count).difference(self)
+    }
+
+    /// Walk both chunk lists in key order; chunks present on one side only
+    /// are kept when `keep_left` / `keep_right` says so
+    fn combine(
+        &self,
+        other: &Self,
+        op: fn(&Chunk, &Chunk) -> Option<Chunk>,
+        keep_left: bool,
+        keep_right: bool,
+    ) -> Self {
+        let mut chunks = Vec::new();
+        let (mut a, mut b) = (
+            self.chunks.iter().peekable(),
+            other.chunks.iter().peekable(),
+        );
+        loop {
+            match (a.peek(), b.peek()) {
+                (Some((ka, ca)), Some((kb, cb))) if ka == kb => {
+                    if let Some(chunk) = op(ca, cb) {
+                        chunks.push((*ka, chunk));
+                    }
+                    a.next();
+                    b.next();
+                }
+                (Some((ka, _)), Some((kb, _))) if ka < kb => {
+                    chunks.extend(a.next().filter(|_| keep_left).cloned());
+                }
+                (Some(_), None) => chunks.extend(a.next().filter(|_| keep_left).cloned()),
+                (_, Some(_)) => chunks.extend(b.next().filter(|_| keep_right).cloned()),
+                (None, None) => break,
+            }
+        }
+        Self { chunks }
+    }
+}
+
+fn split(row: usize) -> (u64, u16) {
+    ((row as u64) >> CHUNK_BITS, row as u16)
+}
+
+impl FromIterator<usize> for RowSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut rows: Vec<usize> = iter.into_iter().collect();
+        if !rows.windows(2).all(|w| w[0] < w[1]) {
+            rows.sort_unstable();
+            rows.dedup();
+        }
+        let mut chunks = Vec::new();
+        for group in rows.chunk_by(|&a, &b| split(a).0 == split(b).0) {
+            let key = split(group[0]).0;
+            let values = group.iter().map(|&row| split(row).1).collect();
+            chunks.extend(Chunk::from_sorted(values).map(|chunk| (key, chunk)));
+        }
+        Self { chunks }
+    }
+}
+
+impl<'a> IntoIterator for &'a RowSet {
+    type Item = usize;
+    type IntoIter = Box<dyn Iterator<Item = usize> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl BitAnd for &RowSet {
+    type Output = RowSet;
+
+    fn bitand(self, other: &RowSet) -> RowSet {
+        self.intersection(other)
+    }
+}
+
+impl BitOr for &RowSet {
+    type Output = RowSet;
+
+    fn bitor(self, other: &RowSet) -> RowSet {
+        self.union(other)
+    }
+}
+
+impl Sub for &RowSet {
+    type Output = RowSet;
+
+    fn sub(self, other: &RowSet) -> RowSet {
+        self.difference(other)
+    }
+}
+
+impl fmt::Debug for RowSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<const N: usize> PartialEq<[usize; N]> for RowSet {
+    fn eq(&self, other: &[usize; N]) -> bool {
+        self.len() == N && self.iter().eq(other.iter().copied())
+    }
+}
+
+impl From<RowSet> for Vec<usize> {
+    fn from(rows: RowSet) -> Self {
+        rows.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_operations() {
+        let a: RowSet = [5, 1, 3, 70_000, 1].into_iter().collect();
+        let b: RowSet = [3, 4, 5, 200_000].into_iter().collect();
+        assert_eq!(a, [1, 3, 5, 70_000]);
+        assert!(a.contains(70_000) && !a.contains(2));
+        assert_eq!(&a & &b, [3, 5]);
+        assert_eq!(&a | &b, [1, 3, 4, 5, 70_000, 200_000]);
+        assert_eq!(&a - &b, [1, 70_000]);
+        assert_eq!(b.complement(7), [0, 1, 2, 6]);
+        assert_eq!(a.first(), Some(1));
+        assert!(RowSet::new().first().is_none());
+
+        let mut c = RowSet::new();
+        assert!(c.insert(9) && c.insert(2) && !c.insert(9));
+        assert_eq!(c, [2, 9]);
+    }
+
+    #[test]
+    fn test_dense_chunks_become_bitmaps() {
+        let evens: RowSet = (0..1_000_000).step_by(2).collect();
+        assert_eq!(evens.len(), 500_000);
+        assert!(evens
+            .chunks
+            .iter()
+            .all(|(_, chunk)| matches!(chunk, Chunk::Bitmap(..))));
+
+        let all = RowSet::full(1_000_000);
+        let odds = all.difference(&evens);
+        assert_eq!(odds.len(), 500_000);
+        assert!(odds.iter().all(|row| row % 2 == 1));
+        assert!((&odds & &evens).is_empty());
+        assert_eq!(&odds | &evens, all);
+
+        // A sparse intersection shrinks back to arrays
+        let few: RowSet = [10, 11, 999_998].into_iter().collect();
+        let hit = &few & &evens;
+        assert_eq!(hit, [10, 999_998]);
+        assert!(hit
+            .chunks
+            .iter()
+            .all(|(_, chunk)| matches!(chunk, Chunk::Array(_))));
+    }
+
+    #[test]
+    fn test_insert_in_place() {
+        let mut set = RowSet::new();
+        // Descending, across chunks and past the array limit
+        for i in (0..ARRAY_MAX + 10).rev() {
+            assert!(set.insert(i * 3));
+        }
+        assert!(set.insert(200_000) && !set.insert(200_000) && !set.insert(3));
+        let expected: RowSet = (0..(ARRAY_MAX + 10) * 3)
+            .step_by(3)
+            .chain([200_000])
+            .collect();
+        assert_eq!(set, expected);
+        assert!(matches!(set.chunks[0].1, Chunk::Bitmap(..)));
+        assert!(set.insert(1) && set.contains(1) && set.len() == expected.len() + 1);
+    }
+}
@@ -0,0 +1,217 @@
+//! Safe write — crash- and concurrency-safe file output
+//!
+//! [`SafeFileWriter`] never leaves a partially written file at the target
+//! path: bytes go to a uniquely named temp file in the same directory,
+//! which is fsynced and then renamed over the target in one step. Readers
+//! (and concurrent writers) see either the old file or the complete new
+//! one. With [`SafeFileWriter::with_dir_sync`] the directory entry is
+//! fsynced as well, so the rename itself survives a power loss (Unix).
+//!
+//! Every file-producing path in the crate and CLIs goes through it:
+//! `FormatV3Editor::save`, `TextModel::save`, the appender, the column
+//! cache and `alice-text` / `alice-textd` output. The CLI's `--no-fsync`
+//! flag (and [`SafeFileWriter::fast`]) keeps the atomic rename but skips
+//! fsync for speed.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Result;
+
+/// Distinguishes temp files of concurrent writes from one process
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Atomic file writer (temp file + fsync + rename)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeFileWriter {
+    fsync: bool,
+    dir_sync: bool,
+}
+
+impl Default for SafeFileWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SafeFileWriter {
+    /// Atomic rename with fsync of the file (directory not synced)
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            fsync: true,
+            dir_sync: false,
+        }
+    }
+
+    /// Atomic rename without any fsync (fast; a crash may lose the write
+    /// but never leaves a partial file)
+    #[must_use]
+    pub const fn fast() -> Self {
+        Self {
+            fsync: false,
+            dir_sync: false,
+        }
+    }
+
+    /// Fsync the temp file before renaming it
+    #[must_use]
+    pub const fn with_fsync(mut self, fsync: bool) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Fsync the parent directory after the rename (no-op outside Unix)
+    #[must_use]
+    pub const fn with_dir_sync(mut self, dir_sync: bool) -> Self {
+        self.dir_sync = dir_sync;
+        self
+    }
+
+    /// Whether written files are fsynced
+    #[inline]
+    #[must_use]
+    pub const fn fsync_enabled(&self) -> bool {
+        self.fsync
+    }
+
+    /// Replace `path` with `bytes`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temp file cannot be created, written or
+    /// synced, or the rename fails; the temp file is removed on failure.
+    pub fn write(&self, path: impl AsRef<Path>, bytes: &[u8]) -> Result<()> {
+        let path = path.as_ref();
+        let tmp = temp_path(path);
+        let result = (|| -> Result<()> {
+            let mut file = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+            file.write_all(bytes)?;
+            if self.fsync {
+                file.sync_all()?;
+            }
+            drop(file);
+            fs::rename(&tmp, path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+            return result;
+        }
+        if self.dir_sync {
+            sync_dir(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// `.{name}.{pid}-{n}.tmp` next to `path`
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(|| "output".into(), |n| n.to_string_lossy());
+    let n = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{name}.{}-{n}.tmp", std::process::id()))
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Write `bytes` to `path` with the default [`SafeFileWriter`]
+///
+/// # Errors
+///
+/// See [`SafeFileWriter::write`].
+pub fn write_atomic(path: impl AsRef<Path>, bytes: &[u8]) -> Result<()> {
+    SafeFileWriter::new().write(path, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("alice-safe-write-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn leftovers(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".tmp")
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_atomic_replace() {
+        let dir = temp_dir("replace");
+        let path = dir.join("out.atxt");
+        write_atomic(&path, b"first").unwrap();
+        SafeFileWriter::fast()
+            .with_dir_sync(true)
+            .write(&path, b"second version")
+            .unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second version");
+        assert_eq!(leftovers(&dir), 0);
+
+        // Failed write: no temp file left, target untouched
+        assert!(write_atomic(dir.join("missing/out.atxt"), b"x").is_err());
+        assert!(SafeFileWriter::new().write(&dir, b"x").is_err());
+        assert_eq!(leftovers(&dir), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_writers() {
+        let dir = temp_dir("concurrent");
+        let path = Arc::new(dir.join("shared.atxt"));
+        let handles: Vec<_> = (0..8u8)
+            .map(|i| {
+                let path = Arc::clone(&path);
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        SafeFileWriter::fast()
+                            .write(path.as_ref(), &vec![i; 4096])
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        // Whole content of exactly one writer, never a mix
+        let data = fs::read(path.as_ref()).unwrap();
+        assert_eq!(data.len(), 4096);
+        assert!(data.iter().all(|&b| b == data[0]));
+        assert_eq!(leftovers(&dir), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
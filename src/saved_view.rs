@@ -0,0 +1,318 @@
+//! Saved views — named queries shipped alongside an archive
+//!
+//! A [`SavedView`] is a column selection plus filter expressions in the
+//! `query --where` syntax (`log_levels in ERROR,FATAL`, `not hosts~.staging.`).
+//! Views are stored either inside the archive, in the
+//! [`HeaderExtension::SAVED_VIEWS`] record ([`FormatV3Writer::with_views`],
+//! [`embed_views`]), or in a JSON sidecar next to it (`server.atxv`,
+//! [`write_sidecar`]). [`QueryEngine::view`] runs one by name; views added
+//! with [`QueryEngine::with_views`] replace archive views of the same name.
+//!
+//! ```rust,ignore
+//! use alice_text::{saved_view, QueryEngine, SavedView};
+//!
+//! let view = SavedView::new("errors_by_host")
+//!     .select(&["hosts", "timestamps"])
+//!     .filter("log_levels in ERROR,FATAL");
+//! saved_view::write_sidecar(&saved_view::sidecar_path("server.atxt"), &[view])?;
+//!
+//! let engine = QueryEngine::open("server.atxt")?
+//!     .with_views(saved_view::read_sidecar("server.atxv")?);
+//! let matches = engine.view("errors_by_host")?;
+//! ```
+//!
+//! [`HeaderExtension::SAVED_VIEWS`]: crate::HeaderExtension::SAVED_VIEWS
+//! [`FormatV3Writer::with_views`]: crate::FormatV3Writer::with_views
+//! [`QueryEngine::view`]: crate::QueryEngine::view
+//! [`QueryEngine::with_views`]: crate::QueryEngine::with_views
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::column_plugin::ColumnPlugin;
+use crate::filter::{parse_filter, parse_negation, Op};
+use crate::format_v3::{FormatV3Metadata, FormatV3Writer, HeaderExtension};
+use crate::{ALICETextError, Result};
+
+/// Extension of the JSON view sidecar (`server.atxt` → `server.atxv`)
+pub const VIEWS_EXT: &str = "atxv";
+
+/// `(column, op, value)` filters borrowed from a view
+pub type Conditions<'a> = Vec<(&'a str, Op, &'a str)>;
+
+/// A named query: selected columns and `--where` filter expressions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedView {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Columns to select (may be empty: lines only)
+    #[serde(default)]
+    pub select: Vec<String>,
+    /// Filter expressions, all of which must hold on a line; a `not ` prefix
+    /// excludes matching lines
+    #[serde(default)]
+    pub filters: Vec<String>,
+}
+
+impl SavedView {
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn describe(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    #[must_use]
+    pub fn select(mut self, columns: &[&str]) -> Self {
+        self.select = columns.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Add a filter expression (`log_levels=ERROR`, `not ipv4 within 10.0.0.0/8`)
+    #[must_use]
+    pub fn filter(mut self, expr: &str) -> Self {
+        self.filters.push(expr.to_string());
+        self
+    }
+
+    /// Parsed filters: (required, excluded)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an expression does not parse.
+    pub fn conditions(&self) -> Result<(Conditions<'_>, Conditions<'_>)> {
+        let mut required = Vec::new();
+        let mut excluded = Vec::new();
+        for expr in &self.filters {
+            let (negated, condition) = parse_negation(expr);
+            let filter = parse_filter(condition).ok_or_else(|| {
+                ALICETextError::InvalidQuery(format!(
+                    "Invalid filter in view {:?}: {expr}",
+                    self.name
+                ))
+            })?;
+            if negated {
+                excluded.push(filter);
+            } else {
+                required.push(filter);
+            }
+        }
+        Ok((required, excluded))
+    }
+}
+
+/// Check names (non-empty, unique) and filter syntax
+///
+/// # Errors
+///
+/// Returns an error naming the first invalid view.
+pub fn validate(views: &[SavedView]) -> Result<()> {
+    let mut names = HashSet::new();
+    for view in views {
+        if view.name.trim().is_empty() {
+            return Err(ALICETextError::EncodingError(
+                "Saved view without a name".to_string(),
+            ));
+        }
+        if !names.insert(view.name.as_str()) {
+            return Err(ALICETextError::EncodingError(format!(
+                "Saved view {:?} defined twice",
+                view.name
+            )));
+        }
+        view.conditions()?;
+    }
+    Ok(())
+}
+
+/// `views` with `overrides` replacing entries of the same name (others appended)
+#[must_use]
+pub fn merge(views: Vec<SavedView>, overrides: &[SavedView]) -> Vec<SavedView> {
+    let mut merged: Vec<SavedView> = views
+        .into_iter()
+        .filter(|v| overrides.iter().all(|o| o.name != v.name))
+        .collect();
+    merged.extend_from_slice(overrides);
+    merged
+}
+
+/// Header extension record holding `views`
+pub(crate) fn to_extension(views: &[SavedView]) -> Result<HeaderExtension> {
+    validate(views)?;
+    let data = serde_json::to_vec(views)
+        .map_err(|e| ALICETextError::EncodingError(format!("Saved views: {e}")))?;
+    Ok(HeaderExtension::new(HeaderExtension::SAVED_VIEWS, data))
+}
+
+/// Views of every [`HeaderExtension::SAVED_VIEWS`] record, in order
+pub(crate) fn from_metadata(metadata: &FormatV3Metadata) -> Result<Vec<SavedView>> {
+    let mut views = Vec::new();
+    for ext in &metadata.extensions {
+        if ext.tag == HeaderExtension::SAVED_VIEWS {
+            let record: Vec<SavedView> = serde_json::from_slice(&ext.data)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Saved views: {e}")))?;
+            views.extend(record);
+        }
+    }
+    Ok(views)
+}
+
+/// Re-encode a v3 archive with `views` as its saved views (replacing any
+/// it had); content, provenance and line ids are kept
+///
+/// # Errors
+///
+/// Returns an error if a view is invalid or decoding / encoding fails.
+pub fn embed_views(data: &[u8], views: &[SavedView]) -> Result<Vec<u8>> {
+    embed_views_with_plugins(data, views, &[])
+}
+
+/// [`embed_views`] for an archive written with column plugins
+///
+/// # Errors
+///
+/// Same as [`embed_views`], or the archive needs a plugin not among `plugins`.
+pub fn embed_views_with_plugins(
+    data: &[u8],
+    views: &[SavedView],
+    plugins: &[Arc<dyn ColumnPlugin>],
+) -> Result<Vec<u8>> {
+    let mut metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(data))?;
+    metadata
+        .extensions
+        .retain(|e| e.tag != HeaderExtension::SAVED_VIEWS);
+    let (text, profile, raw) = FormatV3Writer::decode_normalized(data, plugins)?;
+    let provenance = FormatV3Writer::read_provenance(data)?;
+    let line_ids = FormatV3Writer::read_line_ids(data)?;
+    FormatV3Writer::matching(&metadata, plugins)?
+        .with_views(views)?
+        .compress_normalized(&text, profile, &raw, provenance.as_ref(), line_ids.as_ref())
+}
+
+/// Sidecar path for `archive` (`server.atxt` → `server.atxv`)
+#[must_use]
+pub fn sidecar_path(archive: impl AsRef<Path>) -> PathBuf {
+    archive.as_ref().with_extension(VIEWS_EXT)
+}
+
+/// Read a JSON view sidecar
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or holds invalid views.
+pub fn read_sidecar(path: impl AsRef<Path>) -> Result<Vec<SavedView>> {
+    let json = std::fs::read_to_string(path)?;
+    let views: Vec<SavedView> = serde_json::from_str(&json)
+        .map_err(|e| ALICETextError::DecompressionError(format!("Saved views: {e}")))?;
+    validate(&views)?;
+    Ok(views)
+}
+
+/// Write a JSON view sidecar (atomically)
+///
+/// # Errors
+///
+/// Returns an error if a view is invalid or the file cannot be written.
+pub fn write_sidecar(path: impl AsRef<Path>, views: &[SavedView]) -> Result<()> {
+    validate(views)?;
+    let json = serde_json::to_string_pretty(views)
+        .map_err(|e| ALICETextError::EncodingError(format!("Saved views: {e}")))?;
+    crate::write_atomic(path, json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionLevel;
+    #[cfg(feature = "query")]
+    use crate::QueryEngine;
+
+    const LOG: &str = "\
+2024-01-15 10:30:45 INFO web-1 ok 10.0.0.1
+2024-01-15 10:30:46 ERROR web-2 timeout 10.0.0.2
+2024-01-15 10:30:47 FATAL web-1 crash 192.168.0.9
+";
+
+    fn errors() -> SavedView {
+        SavedView::new("errors")
+            .describe("Errors outside the office network")
+            .select(&["log_levels", "ipv4"])
+            .filter("log_levels in ERROR,FATAL")
+            .filter("not ipv4 within 192.168.0.0/16")
+    }
+
+    #[test]
+    #[cfg(feature = "query")]
+    fn test_views_in_archive_and_sidecar() {
+        let archive = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_views(&[errors()])
+            .unwrap()
+            .compress(LOG)
+            .unwrap();
+        let engine = QueryEngine::from_reader(archive.as_slice()).unwrap();
+        assert_eq!(engine.views().unwrap(), [errors()]);
+        let matches = engine.view("errors").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_number, 1);
+        assert_eq!(matches[0].values["ipv4"], "10.0.0.2");
+        assert!(engine.view("nope").is_err());
+
+        // A sidecar view of the same name wins
+        let dir = tempfile::tempdir().unwrap();
+        let sidecar = sidecar_path(dir.path().join("server.atxt"));
+        assert_eq!(sidecar, dir.path().join("server.atxv"));
+        let all = SavedView::new("errors").filter("log_levels!=INFO");
+        write_sidecar(&sidecar, std::slice::from_ref(&all)).unwrap();
+        let engine = engine.with_views(read_sidecar(&sidecar).unwrap());
+        assert_eq!(engine.views().unwrap(), [all]);
+        assert_eq!(engine.view("errors").unwrap().len(), 2);
+
+        // Embedding replaces the archive's views and keeps the text
+        let embedded = embed_views(&archive, &[SavedView::new("everything")]).unwrap();
+        assert_eq!(FormatV3Writer::decompress(&embedded).unwrap(), LOG);
+        let engine = QueryEngine::from_reader(embedded.as_slice()).unwrap();
+        assert_eq!(engine.views().unwrap(), [SavedView::new("everything")]);
+        assert_eq!(engine.view("everything").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_view_record_is_json() {
+        let ext = to_extension(&[errors()]).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Vec<SavedView>>(&ext.data).unwrap(),
+            [errors()]
+        );
+
+        // Fields missing from an older record take their defaults
+        let archive = FormatV3Writer::new(CompressionLevel::Fast)
+            .with_extension(HeaderExtension::new(
+                HeaderExtension::SAVED_VIEWS,
+                br#"[{"name":"all"}]"#.to_vec(),
+            ))
+            .compress(LOG)
+            .unwrap();
+        let metadata = FormatV3Metadata::read_from(&mut std::io::Cursor::new(&archive)).unwrap();
+        assert_eq!(from_metadata(&metadata).unwrap(), [SavedView::new("all")]);
+    }
+
+    #[test]
+    fn test_invalid_views_rejected() {
+        assert!(validate(&[SavedView::new("")]).is_err());
+        assert!(validate(&[errors(), errors()]).is_err());
+        assert!(validate(&[SavedView::new("x").filter("no operator")]).is_err());
+    }
+}
@@ -2,8 +2,15 @@
 //!
 //! FM-Index search on compressed text — O(|pattern|) search without full decompression.
 //!
+//! Write-time postings for external engines ([`IndexedWriter`]) live in
+//! [`index_sink`](crate::index_sink), which needs no ALICE-Search; they are
+//! re-exported here.
+//!
 //! Author: Moroya Sakamoto
 
+#[cfg(feature = "tantivy")]
+pub use crate::index_sink::TantivySink;
+pub use crate::index_sink::{index_tokens, IndexSink, IndexedWriter};
 use crate::ALICEText;
 use alice_search::FmIndex;
 
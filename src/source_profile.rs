@@ -0,0 +1,289 @@
+//! Source profile — line endings and input encoding restored on decode
+//!
+//! Windows server logs arrive with CRLF line endings, often as UTF-16LE
+//! with a byte-order mark. The encoders work on LF-only UTF-8, so the input
+//! is normalized first and what was changed is recorded in two header flag
+//! bits (`TunedHeader::flags`, `FormatV3Header::flags`); decoding puts it back
+//! byte for byte.
+//!
+//! - CRLF is normalized only when *every* line break is CRLF, so mixed
+//!   input is stored verbatim and still round-trips exactly.
+//! - UTF-16LE input must start with the `FF FE` BOM and be valid UTF-16;
+//!   anything else is read as UTF-8.
+//! - Bytes that are not valid UTF-8 are cut out as [`RawSegment`]s and
+//!   replaced by one `U+FFFD` each, so pattern extraction only ever sees the
+//!   valid regions. The segments are stored in their own column and spliced
+//!   back by `decompress_bytes`; `decompress` returns the text with the
+//!   `U+FFFD` placeholders.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+/// UTF-16LE byte-order mark
+pub const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+
+/// Placeholder left in the text for each run of invalid UTF-8
+pub const RAW_PLACEHOLDER: char = char::REPLACEMENT_CHARACTER;
+
+/// Run of input bytes that are not valid UTF-8, kept verbatim
+///
+/// `ordinal` counts `U+FFFD` characters in the text (placeholders and genuine
+/// ones alike), so a segment stays attached to its placeholder when edits
+/// change the length of the surrounding text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawSegment {
+    /// Index of the placeholder among all `U+FFFD`s in the text
+    pub ordinal: u64,
+    /// Original bytes
+    pub bytes: Vec<u8>,
+}
+
+/// How the original input differs from the LF-only UTF-8 text that is encoded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceProfile {
+    /// Every line break was CRLF
+    pub crlf: bool,
+    /// Input was UTF-16LE with a BOM
+    pub utf16le: bool,
+}
+
+impl SourceProfile {
+    /// Header flag: restore CRLF line endings
+    pub const FLAG_CRLF: u8 = 0x02;
+    /// Header flag: re-encode as UTF-16LE with BOM
+    pub const FLAG_UTF16LE: u8 = 0x04;
+
+    #[must_use]
+    pub const fn from_flags(flags: u8) -> Self {
+        Self {
+            crlf: flags & Self::FLAG_CRLF != 0,
+            utf16le: flags & Self::FLAG_UTF16LE != 0,
+        }
+    }
+
+    #[must_use]
+    pub const fn flags(self) -> u8 {
+        (if self.crlf { Self::FLAG_CRLF } else { 0 })
+            | (if self.utf16le { Self::FLAG_UTF16LE } else { 0 })
+    }
+
+    /// Normalize CRLF line endings to LF (only when every line break is CRLF)
+    #[must_use]
+    pub fn normalize(text: &str) -> (Cow<'_, str>, Self) {
+        let line_breaks = text.bytes().filter(|&b| b == b'\n').count();
+        let crlf = line_breaks > 0 && text.matches("\r\n").count() == line_breaks;
+        let profile = Self {
+            crlf,
+            utf16le: false,
+        };
+        if crlf {
+            (Cow::Owned(text.replace("\r\n", "\n")), profile)
+        } else {
+            (Cow::Borrowed(text), profile)
+        }
+    }
+
+    /// Decode raw input (UTF-8, or UTF-16LE with BOM) and normalize line endings
+    ///
+    /// Never fails: invalid UTF-8 comes back as [`RawSegment`]s.
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> (String, Self, Vec<RawSegment>) {
+        if let Some(text) = bytes.strip_prefix(&UTF16LE_BOM).and_then(decode_utf16le) {
+            let (normalized, profile) = Self::normalize(&text);
+            let profile = Self {
+                utf16le: true,
+                ..profile
+            };
+            return (normalized.into_owned(), profile, Vec::new());
+        }
+
+        let (text, raw) = split_utf8(bytes);
+        let (normalized, profile) = Self::normalize(&text);
+        (normalized.into_owned(), profile, raw)
+    }
+
+    /// Restore line endings (the text form returned by `decompress`)
+    #[must_use]
+    pub fn restore_text(self, text: String) -> String {
+        if self.crlf {
+            text.replace('\n', "\r\n")
+        } else {
+            text
+        }
+    }
+
+    /// Restore line endings, invalid UTF-8 runs and input encoding (the original bytes)
+    #[must_use]
+    pub fn encode(self, text: String, raw: &[RawSegment]) -> Vec<u8> {
+        let text = self.restore_text(text);
+        if self.utf16le {
+            let mut bytes = Vec::with_capacity(2 + text.len() * 2);
+            bytes.extend_from_slice(&UTF16LE_BOM);
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            }
+            return bytes;
+        }
+        if raw.is_empty() {
+            return text.into_bytes();
+        }
+
+        // Raw runs never contain ASCII, so restoring CRLF first can't split one
+        let mut bytes = Vec::with_capacity(text.len() + raw_len(raw));
+        let mut segments = raw.iter().peekable();
+        let mut rest = text.as_str();
+        let mut ordinal = 0u64;
+        while let Some(pos) = rest.find(RAW_PLACEHOLDER) {
+            let end = pos + RAW_PLACEHOLDER.len_utf8();
+            bytes.extend_from_slice(&rest.as_bytes()[..pos]);
+            match segments.next_if(|s| s.ordinal == ordinal) {
+                Some(segment) => bytes.extend_from_slice(&segment.bytes),
+                None => bytes.extend_from_slice(&rest.as_bytes()[pos..end]),
+            }
+            ordinal += 1;
+            rest = &rest[end..];
+        }
+        bytes.extend_from_slice(rest.as_bytes());
+        bytes
+    }
+
+    /// Size of [`Self::encode`]'s output for normalized `text`, without building it
+    #[must_use]
+    pub fn restored_len(self, text: &str, raw: &[RawSegment]) -> usize {
+        let added_cr = if self.crlf {
+            text.bytes().filter(|&b| b == b'\n').count()
+        } else {
+            0
+        };
+        if self.utf16le {
+            UTF16LE_BOM.len() + (text.encode_utf16().count() + added_cr) * 2
+        } else {
+            text.len() + added_cr + raw_len(raw) - raw.len() * RAW_PLACEHOLDER.len_utf8()
+        }
+    }
+}
+
+/// BOM-less UTF-16LE payload to text (`None` if it isn't valid UTF-16)
+fn decode_utf16le(units: &[u8]) -> Option<String> {
+    if !units.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = units
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .ok()
+}
+
+/// Split bytes into text (one placeholder per invalid run) and the invalid runs
+fn split_utf8(bytes: &[u8]) -> (String, Vec<RawSegment>) {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), Vec::new());
+    }
+
+    let mut text = String::with_capacity(bytes.len());
+    let mut raw: Vec<RawSegment> = Vec::new();
+    let mut ordinal = 0u64;
+    for chunk in bytes.utf8_chunks() {
+        let valid = chunk.valid();
+        ordinal += valid.matches(RAW_PLACEHOLDER).count() as u64;
+        text.push_str(valid);
+
+        let invalid = chunk.invalid();
+        if invalid.is_empty() {
+            continue;
+        }
+        match raw.last_mut() {
+            // Every chunk but the last ends in an invalid sequence, so an
+            // empty valid part means this run continues the previous one
+            Some(last) if valid.is_empty() => last.bytes.extend_from_slice(invalid),
+            _ => {
+                raw.push(RawSegment {
+                    ordinal,
+                    bytes: invalid.to_vec(),
+                });
+                text.push(RAW_PLACEHOLDER);
+                ordinal += 1;
+            }
+        }
+    }
+    (text, raw)
+}
+
+/// Total bytes held by raw segments
+fn raw_len(raw: &[RawSegment]) -> usize {
+    raw.iter().map(|s| s.bytes.len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        let mut bytes = UTF16LE_BOM.to_vec();
+        text.encode_utf16()
+            .for_each(|u| bytes.extend_from_slice(&u.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn test_crlf_normalize_restore() {
+        let text = "line one\r\nline\rtwo\r\r\n";
+        let (normalized, profile) = SourceProfile::normalize(text);
+        assert!(profile.crlf);
+        assert_eq!(normalized, "line one\nline\rtwo\r\n");
+        assert_eq!(profile.restored_len(&normalized, &[]), text.len());
+        assert_eq!(profile.restore_text(normalized.into_owned()), text);
+
+        // Mixed endings are left alone
+        let (mixed, profile) = SourceProfile::normalize("a\r\nb\nc");
+        assert!(!profile.crlf);
+        assert!(matches!(mixed, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_utf16le_roundtrip() {
+        let original = utf16le("2024-01-15 イベント C:\\Windows\\System32 🚀\r\n");
+        let (text, profile, raw) = SourceProfile::decode(&original);
+        assert!(raw.is_empty());
+        assert_eq!(
+            profile,
+            SourceProfile {
+                crlf: true,
+                utf16le: true
+            }
+        );
+        assert_eq!(text, "2024-01-15 イベント C:\\Windows\\System32 🚀\n");
+        assert_eq!(profile.restored_len(&text, &raw), original.len());
+        assert_eq!(profile.encode(text, &raw), original);
+        assert_eq!(SourceProfile::from_flags(profile.flags()), profile);
+    }
+
+    #[test]
+    fn test_invalid_utf8_preserved() {
+        let original = b"ok \xC3\x28 bad\xFF\xFE\xFD \xEF\xBF\xBD genuine\r\ntail\x80\r\n";
+        let (text, profile, raw) = SourceProfile::decode(original);
+        assert!(profile.crlf);
+        assert_eq!(
+            text,
+            "ok \u{FFFD}( bad\u{FFFD} \u{FFFD} genuine\ntail\u{FFFD}\n"
+        );
+        // The genuine U+FFFD (ordinal 2) has no segment
+        assert_eq!(raw.iter().map(|s| s.ordinal).collect::<Vec<_>>(), [0, 1, 3]);
+        assert_eq!(raw[1].bytes, [0xFF, 0xFE, 0xFD]);
+        assert_eq!(profile.restored_len(&text, &raw), original.len());
+        assert_eq!(profile.encode(text, &raw), original);
+
+        // Not valid UTF-16 after a BOM: kept as bytes instead
+        for original in [&[0xFF, 0xFE, 0x00, 0xD8][..], &[0xFF, 0xFE, 0x41]] {
+            let (text, profile, raw) = SourceProfile::decode(original);
+            assert!(!profile.utf16le);
+            assert_eq!(profile.encode(text, &raw), original);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Stability — archive compatibility policy and version gate
+//!
+//! `.atxt` files are archival: logs compressed today must decompress with
+//! any later release. The rules:
+//!
+//! 1. **Nothing is dropped.** Every major version a release has written stays
+//!    in [`READABLE_MAJORS`]; the range only grows.
+//! 2. **Minor versions only add.** Within a major, new data arrives as flag
+//!    bits, header extension tags or column types that older readers of the
+//!    same major skip (v3: `DecodeOptions::lenient`). Anything an old reader
+//!    would decode *wrongly* must be marked critical (`HeaderExtension::CRITICAL`
+//!    or a header flag, which readers refuse when unknown), come with a minor
+//!    bump, or bump the major. v1 and v2 have no skippable records, so their
+//!    readers refuse a minor newer than their own; v2 minors also change the
+//!    payload so that older readers fail on it (the 2.1 payload marker).
+//! 3. **Written bytes are frozen.** Changing how an existing version lays out
+//!    its bytes requires a new version (or a new flag bit) — never a silent
+//!    change under the same number.
+//! 4. **Every written version has a golden file.** `tests/fixtures` holds
+//!    committed archives, one or more per entry of [`WRITTEN_VERSIONS`] plus
+//!    older layouts; `tests/golden.rs` decodes each byte-exactly and fails if
+//!    a written version has no fixture. Fixtures are never regenerated.
+//!
+//! Bumping a format version therefore means: add it to [`WRITTEN_VERSIONS`]
+//! (a compile-time check keeps it inside [`READABLE_MAJORS`]), teach
+//! [`decompress_any`] to read it, and commit a fixture written by it.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::ops::RangeInclusive;
+
+use crate::format_v3::{FormatV3Writer, FORMAT_V3_VERSION};
+use crate::tuned_compressor::{TunedCompressor, TUNED_VERSION};
+use crate::{ALICETextError, Result, ALICE_TEXT_MAGIC, ALICE_TEXT_VERSION};
+
+/// Major versions this release decodes (v1 legacy LZMA, v2 tuned, v3 columnar)
+pub const READABLE_MAJORS: RangeInclusive<u8> = 1..=3;
+
+/// Versions this release writes, one per format
+pub const WRITTEN_VERSIONS: [(u8, u8); 3] = [ALICE_TEXT_VERSION, TUNED_VERSION, FORMAT_V3_VERSION];
+
+// Version gate: a writer may not emit a major its own readers reject
+const _: () = {
+    let mut i = 0;
+    while i < WRITTEN_VERSIONS.len() {
+        let major = WRITTEN_VERSIONS[i].0;
+        assert!(
+            major >= *READABLE_MAJORS.start() && major <= *READABLE_MAJORS.end(),
+            "WRITTEN_VERSIONS contains a major outside READABLE_MAJORS"
+        );
+        i += 1;
+    }
+};
+
+/// Magic-checked `(major, minor)` version of an `.atxt` archive
+///
+/// # Errors
+///
+/// Returns an error if the data is too short, the magic is wrong, or the
+/// major version is outside [`READABLE_MAJORS`].
+pub fn archive_version(data: &[u8]) -> Result<(u8, u8)> {
+    if data.len() < 10 {
+        return Err(ALICETextError::DecompressionError(
+            "Data too short".to_string(),
+        ));
+    }
+    if &data[0..8] != ALICE_TEXT_MAGIC {
+        return Err(ALICETextError::InvalidMagic);
+    }
+    let version = (data[8], data[9]);
+    if !READABLE_MAJORS.contains(&version.0) {
+        return Err(ALICETextError::InvalidVersion(version.0, version.1));
+    }
+    Ok(version)
+}
+
+/// Decompress an archive of any readable version to its exact original bytes
+///
+/// v2 archives that need an external model (`TunedCompressor::with_model`)
+/// cannot be decoded here.
+///
+/// # Errors
+///
+/// Returns an error if [`archive_version`] rejects the data or decoding fails.
+pub fn decompress_any(data: &[u8]) -> Result<Vec<u8>> {
+    match archive_version(data)? {
+        (1, _) => crate::decompress(data).map(String::into_bytes),
+        (2, _) => TunedCompressor::default().decompress_bytes(data),
+        (3, _) => FormatV3Writer::decompress_bytes(data),
+        (major, minor) => Err(ALICETextError::InvalidVersion(major, minor)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_gate() {
+        let text = "2024-01-15 10:30:00 INFO request from 10.0.0.1\n".repeat(20);
+        let v2 = TunedCompressor::default().compress(&text).unwrap();
+        let v3 = crate::compress_v3(&text, crate::CompressionLevel::Fast).unwrap();
+        assert_eq!(archive_version(&v2).unwrap(), TUNED_VERSION);
+        assert_eq!(archive_version(&v3).unwrap(), FORMAT_V3_VERSION);
+        assert_eq!(decompress_any(&v3).unwrap(), text.as_bytes());
+
+        let mut future = v3;
+        future[8] = READABLE_MAJORS.end() + 1;
+        assert!(matches!(
+            decompress_any(&future),
+            Err(ALICETextError::InvalidVersion(4, _))
+        ));
+        assert!(matches!(
+            archive_version(b"NOTATXT!\x03\x00"),
+            Err(ALICETextError::InvalidMagic)
+        ));
+        assert!(archive_version(b"ALICE").is_err());
+    }
+}
@@ -0,0 +1,376 @@
+//! Stream — block-flushed compression of never-ending input
+//!
+//! `journalctl -f | alice-text compress -` never reaches end of input, so a
+//! single archive would only be written on exit. [`StreamCompressor`]
+//! instead cuts the input into blocks at line boundaries and appends each
+//! block as a complete v2 archive as soon as the [`FlushPolicy`] size or
+//! time limit is hit:
+//!
+//! ```text
+//! MAGIC "ALICESTM" (8) | VERSION (2) | { LEN u32 LE | ARCHIVE (LEN) }*
+//! ```
+//!
+//! Blocks are independent, so everything up to the last flushed block
+//! survives a crash; a torn trailing block is dropped by
+//! [`DecodeOptions::lenient`] and rejected by [`DecodeOptions::strict`].
+//! The writer's `flush` is called after every block — wrap a `File` whose
+//! `flush` calls `sync_data` to make each block durable.
+//!
+//! ```rust
+//! use alice_text::stream::{decompress_stream, FlushPolicy, StreamCompressor};
+//! use alice_text::TunedCompressor;
+//!
+//! let policy = FlushPolicy::default().with_max_bytes(64);
+//! let mut stream = StreamCompressor::new(Vec::new(), TunedCompressor::default())
+//!     .unwrap()
+//!     .with_policy(policy);
+//! for i in 0..10 {
+//!     stream.push(format!("2024-01-15 10:30:0{i} INFO tick {i}\n").as_bytes()).unwrap();
+//! }
+//! assert!(stream.blocks_written() > 1);
+//! let data = stream.finish().unwrap();
+//! assert!(decompress_stream(&data).unwrap().ends_with(b"tick 9\n"));
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::format_v3::DecodeOptions;
+use crate::tuned_compressor::TunedCompressor;
+use crate::{to_u32, ALICETextError, Result};
+
+/// Magic bytes for block streams
+pub const STREAM_MAGIC: &[u8; 8] = b"ALICESTM";
+
+/// Stream format version
+pub const STREAM_VERSION: (u8, u8) = (1, 0);
+
+/// Fixed header size (magic + version)
+const HEADER_SIZE: usize = 8 + 2;
+
+/// When to close the current block and append it to the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlushPolicy {
+    /// Flush once the oldest buffered byte is this old
+    pub interval: Option<Duration>,
+    /// Flush once this many bytes are buffered
+    pub max_bytes: Option<usize>,
+}
+
+impl FlushPolicy {
+    /// Set the time limit
+    #[must_use]
+    pub const fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Set the size limit
+    #[must_use]
+    pub const fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+}
+
+/// Incremental compressor appending one archive per flushed block
+pub struct StreamCompressor<W: Write> {
+    writer: W,
+    compressor: TunedCompressor,
+    policy: FlushPolicy,
+    buffer: Vec<u8>,
+    buffer_started: Option<Instant>,
+    blocks: u64,
+}
+
+impl<W: Write> StreamCompressor<W> {
+    /// Start a stream on `writer` (the stream header is written immediately)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be written.
+    pub fn new(mut writer: W, compressor: TunedCompressor) -> Result<Self> {
+        writer.write_all(STREAM_MAGIC)?;
+        writer.write_all(&[STREAM_VERSION.0, STREAM_VERSION.1])?;
+        writer.flush()?;
+        Ok(Self {
+            writer,
+            compressor,
+            policy: FlushPolicy::default(),
+            buffer: Vec::new(),
+            buffer_started: None,
+            blocks: 0,
+        })
+    }
+
+    /// Set the flush policy (default: only [`Self::finish`] writes a block)
+    #[must_use]
+    pub fn with_policy(mut self, policy: FlushPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Bytes currently buffered (not yet written)
+    #[inline]
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Blocks appended so far
+    #[inline]
+    pub fn blocks_written(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Buffer `bytes`, flushing if the policy limit is reached
+    ///
+    /// Returns whether a block was written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the write fails.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<bool> {
+        if bytes.is_empty() {
+            return Ok(false);
+        }
+        if self.buffer_started.is_none() {
+            self.buffer_started = Some(Instant::now());
+        }
+        self.buffer.extend_from_slice(bytes);
+        let mut flushed = false;
+        while self.should_flush() && self.flush_block()? {
+            flushed = true;
+        }
+        Ok(flushed)
+    }
+
+    /// Flush if the policy limit is reached (call periodically while idle)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the write fails.
+    pub fn poll(&mut self) -> Result<bool> {
+        if self.should_flush() {
+            self.flush_block()
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Time until the interval limit expires for the buffered data
+    ///
+    /// `None` if nothing is buffered or no interval is set.
+    pub fn time_to_flush(&self) -> Option<Duration> {
+        let interval = self.policy.interval?;
+        let started = self.buffer_started?;
+        Some(interval.saturating_sub(started.elapsed()))
+    }
+
+    /// Whether the buffer has hit the size or age limit
+    pub fn should_flush(&self) -> bool {
+        self.policy
+            .max_bytes
+            .is_some_and(|max| self.buffer.len() >= max)
+            || self.time_to_flush().is_some_and(|left| left.is_zero())
+    }
+
+    /// Write complete buffered lines (up to the size limit) as one block
+    ///
+    /// A line longer than the size limit becomes a block of its own; a
+    /// trailing partial line stays buffered unless it alone exceeds the
+    /// size limit. Returns whether a block was written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the write fails; the buffer is
+    /// kept on failure.
+    pub fn flush_block(&mut self) -> Result<bool> {
+        let max = self.policy.max_bytes.unwrap_or(usize::MAX);
+        let limit = self.buffer.len().min(max);
+        let newline = self.buffer[..limit]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .or_else(|| self.buffer.iter().position(|&b| b == b'\n'));
+        let cut = match newline {
+            Some(newline) => newline + 1,
+            None if self.buffer.len() >= max => self.buffer.len(),
+            None => {
+                // Partial line only: wait for its end, restart the clock
+                self.buffer_started = (!self.buffer.is_empty()).then(Instant::now);
+                return Ok(false);
+            }
+        };
+        self.write_block(cut)?;
+        Ok(true)
+    }
+
+    /// Write everything buffered (including a partial last line) and
+    /// return the writer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if compression or the write fails.
+    pub fn finish(mut self) -> Result<W> {
+        if !self.buffer.is_empty() {
+            self.write_block(self.buffer.len())?;
+        }
+        Ok(self.writer)
+    }
+
+    fn write_block(&mut self, cut: usize) -> Result<()> {
+        let archive = self.compressor.compress_bytes(&self.buffer[..cut])?;
+        let len = to_u32(archive.len(), "stream block size")?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&archive)?;
+        self.writer.flush()?;
+
+        self.blocks += 1;
+        self.buffer.drain(..cut);
+        self.buffer_started = (!self.buffer.is_empty()).then(Instant::now);
+        Ok(())
+    }
+}
+
+/// Whether `data` starts with the block stream magic
+#[inline]
+pub fn is_stream(data: &[u8]) -> bool {
+    data.starts_with(STREAM_MAGIC)
+}
+
+/// Decompress a block stream (lenient: a torn trailing block is dropped)
+///
+/// # Errors
+///
+/// Returns an error if the header is invalid or a complete block fails to
+/// decode.
+pub fn decompress_stream(data: &[u8]) -> Result<Vec<u8>> {
+    decompress_stream_with(&TunedCompressor::default(), data, DecodeOptions::lenient())
+}
+
+/// Decompress a block stream with `compressor` (e.g. one holding the
+/// stream's model)
+///
+/// # Errors
+///
+/// Returns an error if the header is invalid, a block fails to decode, or
+/// (strict) the last block is truncated.
+pub fn decompress_stream_with(
+    compressor: &TunedCompressor,
+    data: &[u8],
+    options: DecodeOptions,
+) -> Result<Vec<u8>> {
+    if data.len() < HEADER_SIZE {
+        return Err(ALICETextError::DecompressionError(
+            "Data too short".to_string(),
+        ));
+    }
+    if !is_stream(data) {
+        return Err(ALICETextError::InvalidMagic);
+    }
+    if data[8] != STREAM_VERSION.0 {
+        return Err(ALICETextError::InvalidVersion(data[8], data[9]));
+    }
+
+    let mut output = Vec::new();
+    let mut rest = &data[HEADER_SIZE..];
+    while !rest.is_empty() {
+        let block = rest
+            .get(..4)
+            .map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)
+            .and_then(|len| rest.get(4..4 + len));
+        let Some(block) = block else {
+            if options.strict {
+                return Err(ALICETextError::DecompressionError(
+                    "Truncated stream block".to_string(),
+                ));
+            }
+            break;
+        };
+        output.extend_from_slice(&compressor.decompress_bytes(block)?);
+        options.limits.check_total(output.len() as u64)?;
+        rest = &rest[4 + block.len()..];
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_lines(n: usize) -> String {
+        (0..n)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:{:02}:{:02} INFO worker {i} ok\n",
+                    i / 60,
+                    i % 60
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_size_flush_roundtrip() {
+        let text = log_lines(500);
+        let mut stream = StreamCompressor::new(Vec::new(), TunedCompressor::default())
+            .unwrap()
+            .with_policy(FlushPolicy::default().with_max_bytes(4096));
+        for chunk in text.as_bytes().chunks(777) {
+            stream.push(chunk).unwrap();
+            assert!(stream.buffered_len() < 4096);
+        }
+        // One large write is still cut into size-limited blocks
+        stream.push(text.as_bytes()).unwrap();
+        assert!(stream.blocks_written() >= 8);
+        let text = text.repeat(2);
+        let data = stream.finish().unwrap();
+        assert_eq!(decompress_stream(&data).unwrap(), text.as_bytes());
+        assert_eq!(
+            decompress_stream_with(&TunedCompressor::default(), &data, DecodeOptions::strict())
+                .unwrap(),
+            text.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_interval_flush_and_torn_tail() {
+        let mut stream = StreamCompressor::new(Vec::new(), TunedCompressor::default())
+            .unwrap()
+            .with_policy(FlushPolicy::default().with_interval(Duration::from_millis(20)));
+        stream.push(b"first line\npartial").unwrap();
+        assert!(!stream.poll().unwrap());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(stream.poll().unwrap());
+        assert_eq!(stream.buffered_len(), b"partial".len());
+        // A partial line alone is not flushed by time
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!stream.poll().unwrap());
+
+        stream.push(b" done\n").unwrap();
+        let data = stream.finish().unwrap();
+        assert_eq!(
+            decompress_stream(&data).unwrap(),
+            b"first line\npartial done\n"
+        );
+
+        // Crash mid-write: the completed first block survives
+        let first_len = u32::from_le_bytes(data[10..14].try_into().unwrap()) as usize;
+        let torn = &data[..data.len() - 3];
+        assert_eq!(decompress_stream(torn).unwrap(), b"first line\n");
+        assert!(
+            decompress_stream_with(&TunedCompressor::default(), torn, DecodeOptions::strict())
+                .is_err()
+        );
+        assert_eq!(
+            decompress_stream(&data[..14 + first_len]).unwrap(),
+            b"first line\n"
+        );
+        assert!(matches!(
+            decompress_stream(b"ALICETXT\x02\x00"),
+            Err(ALICETextError::InvalidMagic)
+        ));
+    }
+}
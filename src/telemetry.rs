@@ -0,0 +1,266 @@
+//! Telemetry — `metrics` facade hooks for compression and query paths
+//!
+//! Feature-gated emission: `#[cfg(feature = "metrics")]`
+//!
+//! With the feature enabled, `TunedCompressor`, `FormatV3Writer`,
+//! `QueryEngine` and `CompressorPool` report through whatever `metrics` recorder the host
+//! installs (e.g. `metrics-exporter-prometheus`). Without it every hook
+//! compiles to nothing.
+//!
+//! | Metric | Kind | Labels |
+//! |--------|------|--------|
+//! | `alice_text_bytes_in_total` | counter | `op`, `format` |
+//! | `alice_text_bytes_out_total` | counter | `op`, `format` |
+//! | `alice_text_compression_ratio` | histogram | `format` |
+//! | `alice_text_compress_duration_seconds` | histogram | `format` |
+//! | `alice_text_decompress_duration_seconds` | histogram | `format` |
+//! | `alice_text_query_duration_seconds` | histogram | `kind` |
+//! | `alice_text_query_rows_total` | counter | `kind` |
+//! | `alice_text_pool_queue_depth` | gauge | `stage` (`lines`, `blocks`) |
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+pub const BYTES_IN: &str = "alice_text_bytes_in_total";
+pub const BYTES_OUT: &str = "alice_text_bytes_out_total";
+pub const COMPRESSION_RATIO: &str = "alice_text_compression_ratio";
+pub const COMPRESS_DURATION: &str = "alice_text_compress_duration_seconds";
+pub const DECOMPRESS_DURATION: &str = "alice_text_decompress_duration_seconds";
+pub const QUERY_DURATION: &str = "alice_text_query_duration_seconds";
+pub const QUERY_ROWS: &str = "alice_text_query_rows_total";
+pub const POOL_QUEUE_DEPTH: &str = "alice_text_pool_queue_depth";
+
+/// Register units and descriptions with the installed recorder
+///
+/// Optional; call once after installing the exporter so `# HELP` lines are populated.
+#[cfg(feature = "metrics")]
+pub fn describe_metrics() {
+    use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+
+    describe_counter!(BYTES_IN, Unit::Bytes, "Input bytes processed");
+    describe_counter!(BYTES_OUT, Unit::Bytes, "Output bytes produced");
+    describe_histogram!(
+        COMPRESSION_RATIO,
+        Unit::Count,
+        "Compressed size / original size"
+    );
+    describe_histogram!(COMPRESS_DURATION, Unit::Seconds, "Compression latency");
+    describe_histogram!(DECOMPRESS_DURATION, Unit::Seconds, "Decompression latency");
+    describe_histogram!(QUERY_DURATION, Unit::Seconds, "Query latency");
+    describe_counter!(QUERY_ROWS, Unit::Count, "Rows returned by queries");
+    describe_gauge!(
+        POOL_QUEUE_DEPTH,
+        Unit::Count,
+        "Lines or blocks waiting in the compressor pool"
+    );
+}
+
+/// Latency timer (zero-sized when the feature is disabled)
+pub(crate) struct Timer {
+    #[cfg(feature = "metrics")]
+    start: std::time::Instant,
+}
+
+impl Timer {
+    #[inline(always)]
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+/// Record a successful compression
+#[inline(always)]
+#[allow(unused_variables)]
+pub(crate) fn record_compress(
+    timer: &Timer,
+    format: &'static str,
+    bytes_in: usize,
+    bytes_out: usize,
+) {
+    #[cfg(feature = "metrics")]
+    {
+        let secs = timer.start.elapsed().as_secs_f64();
+        metrics::counter!(BYTES_IN, "op" => "compress", "format" => format)
+            .increment(bytes_in as u64);
+        metrics::counter!(BYTES_OUT, "op" => "compress", "format" => format)
+            .increment(bytes_out as u64);
+        if bytes_in > 0 {
+            metrics::histogram!(COMPRESSION_RATIO, "format" => format)
+                .record(bytes_out as f64 / bytes_in as f64);
+        }
+        metrics::histogram!(COMPRESS_DURATION, "format" => format).record(secs);
+    }
+}
+
+/// Record a successful decompression
+#[inline(always)]
+#[allow(unused_variables)]
+pub(crate) fn record_decompress(
+    timer: &Timer,
+    format: &'static str,
+    bytes_in: usize,
+    bytes_out: usize,
+) {
+    #[cfg(feature = "metrics")]
+    {
+        let secs = timer.start.elapsed().as_secs_f64();
+        metrics::counter!(BYTES_IN, "op" => "decompress", "format" => format)
+            .increment(bytes_in as u64);
+        metrics::counter!(BYTES_OUT, "op" => "decompress", "format" => format)
+            .increment(bytes_out as u64);
+        metrics::histogram!(DECOMPRESS_DURATION, "format" => format).record(secs);
+    }
+}
+
+/// Record a successful query
+#[cfg(feature = "query")]
+#[inline(always)]
+#[allow(unused_variables)]
+pub(crate) fn record_query(timer: &Timer, kind: &'static str, rows: usize) {
+    #[cfg(feature = "metrics")]
+    {
+        let secs = timer.start.elapsed().as_secs_f64();
+        metrics::histogram!(QUERY_DURATION, "kind" => kind).record(secs);
+        metrics::counter!(QUERY_ROWS, "kind" => kind).increment(rows as u64);
+    }
+}
+
+/// Record the current depth of a compressor pool queue
+#[inline(always)]
+#[allow(unused_variables)]
+pub(crate) fn record_queue_depth(stage: &'static str, depth: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!(POOL_QUEUE_DEPTH, "stage" => stage).set(depth as f64);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::TunedCompressor;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Sums counters and counts histogram samples per metric name
+    #[derive(Default)]
+    struct CaptureRecorder {
+        values: Arc<Mutex<HashMap<String, f64>>>,
+    }
+
+    struct Handle {
+        name: String,
+        counts_samples: bool,
+        values: Arc<Mutex<HashMap<String, f64>>>,
+    }
+
+    impl Handle {
+        fn add(&self, v: f64) {
+            *self
+                .values
+                .lock()
+                .unwrap()
+                .entry(self.name.clone())
+                .or_default() += v;
+        }
+    }
+
+    impl CounterFn for Handle {
+        fn increment(&self, value: u64) {
+            self.add(value as f64);
+        }
+
+        fn absolute(&self, _value: u64) {}
+    }
+
+    impl HistogramFn for Handle {
+        fn record(&self, _value: f64) {
+            self.add(if self.counts_samples { 1.0 } else { 0.0 });
+        }
+    }
+
+    impl CaptureRecorder {
+        fn handle(&self, key: &Key, counts_samples: bool) -> Arc<Handle> {
+            Arc::new(Handle {
+                name: key.name().to_string(),
+                counts_samples,
+                values: Arc::clone(&self.values),
+            })
+        }
+
+        fn get(&self, name: &str) -> f64 {
+            self.values
+                .lock()
+                .unwrap()
+                .get(name)
+                .copied()
+                .unwrap_or_default()
+        }
+    }
+
+    impl Recorder for CaptureRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.handle(key, false))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.handle(key, true))
+        }
+    }
+
+    const TEXT: &str = "2024-01-15 10:30:45 INFO ok from 192.168.1.1\n\
+                        2024-01-15 10:30:46 ERROR failed from 192.168.1.2\n";
+
+    #[test]
+    fn test_compress_metrics() {
+        let recorder = CaptureRecorder::default();
+        let compressed_len = metrics::with_local_recorder(&recorder, || {
+            let compressed = TunedCompressor::default().compress(TEXT).unwrap();
+            TunedCompressor::default().decompress(&compressed).unwrap();
+            compressed.len()
+        });
+
+        let total = (TEXT.len() + compressed_len) as f64;
+        assert_eq!(recorder.get(BYTES_IN), total);
+        assert_eq!(recorder.get(BYTES_OUT), total);
+        assert_eq!(recorder.get(COMPRESS_DURATION), 1.0);
+        assert_eq!(recorder.get(DECOMPRESS_DURATION), 1.0);
+        assert_eq!(recorder.get(COMPRESSION_RATIO), 1.0);
+    }
+
+    #[test]
+    #[cfg(feature = "query")]
+    fn test_query_metrics() {
+        use crate::{compress_v3, decompress_v3, CompressionLevel, Op, QueryEngine};
+        use std::io::Cursor;
+
+        let recorder = CaptureRecorder::default();
+        metrics::with_local_recorder(&recorder, || {
+            let data = compress_v3(TEXT, CompressionLevel::Fast).unwrap();
+            let engine = QueryEngine::from_reader(Cursor::new(&data)).unwrap();
+            let result = engine
+                .query(&["ipv4"], "log_levels", Op::Eq, "ERROR")
+                .unwrap();
+            assert_eq!(result.len(), 1);
+            decompress_v3(&data).unwrap();
+        });
+
+        assert_eq!(recorder.get(QUERY_DURATION), 1.0);
+        assert_eq!(recorder.get(QUERY_ROWS), 1.0);
+        assert_eq!(recorder.get(COMPRESS_DURATION), 1.0);
+        assert_eq!(recorder.get(DECOMPRESS_DURATION), 1.0);
+    }
+}
@@ -0,0 +1,289 @@
+//! Tokenizers — how text is split into tokens for exception statistics
+//!
+//! Token and exception counts are only meaningful if "token" fits the
+//! text: whitespace splitting sees a whole Japanese sentence as one token
+//! and `key=value,key=value` as one as well. [`Tokenizer`] is the extension
+//! point used by [`ExceptionEncoder`](crate::ExceptionEncoder) and
+//! [`ALICEText`](crate::ALICEText) stats; implement it for custom schemes.
+//!
+//! | Tokenizer | Splits on |
+//! |-----------|-----------|
+//! | [`WhitespaceTokenizer`] | Unicode whitespace (the original behaviour) |
+//! | [`UnicodeWordTokenizer`] | Word boundaries; one token per ideograph / hiragana |
+//! | [`BytePairTokenizer`] | Merges learned from a sample (BPE over characters) |
+//!
+//! Tokens are always slices of the input, in order, and never contain
+//! whitespace.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Splits text into tokens
+pub trait Tokenizer: Send + Sync {
+    /// Tokens of `text`, in order
+    fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str>;
+
+    /// Number of tokens (override when counting is cheaper than collecting)
+    fn count(&self, text: &str) -> usize {
+        self.tokenize(text).len()
+    }
+}
+
+// ── Whitespace ─────────────────────────────────────────────────
+
+/// Runs of non-whitespace characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.split_whitespace().collect()
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+}
+
+// ── Unicode Word ───────────────────────────────────────────────
+
+/// Word tokens in the spirit of UAX #29
+///
+/// - Letters, digits and `_` form words; `.`, `'` and `’` inside a word
+///   stay in it (`192.168.1.1`, `can't`), as does `,` between digits
+/// - Every Han ideograph and hiragana character is its own token, since
+///   CJK text has no spaces; katakana runs (`サーバー`) are one token
+/// - Punctuation and symbols are not tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UnicodeWordTokenizer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Word,
+    Katakana,
+    /// Han ideograph or hiragana
+    Ideograph,
+    Other,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        match c {
+            '\u{3040}'..='\u{309F}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FA1F}' => Self::Ideograph,
+            '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => {
+                Self::Katakana
+            }
+            c if c.is_alphanumeric() || c == '_' => Self::Word,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl Tokenizer for UnicodeWordTokenizer {
+    fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let (start, c) = chars[i];
+            let class = CharClass::of(c);
+            let mut j = i + 1;
+            match class {
+                CharClass::Other => {
+                    i = j;
+                    continue;
+                }
+                CharClass::Ideograph => {}
+                CharClass::Katakana => {
+                    while j < chars.len() && CharClass::of(chars[j].1) == CharClass::Katakana {
+                        j += 1;
+                    }
+                }
+                CharClass::Word => loop {
+                    if j < chars.len() && CharClass::of(chars[j].1) == CharClass::Word {
+                        j += 1;
+                        continue;
+                    }
+                    // A mid-word separator followed by more of the word
+                    let joins = j + 1 < chars.len()
+                        && CharClass::of(chars[j + 1].1) == CharClass::Word
+                        && match chars[j].1 {
+                            '.' | '\'' | '\u{2019}' => true,
+                            ',' => {
+                                chars[j - 1].1.is_ascii_digit() && chars[j + 1].1.is_ascii_digit()
+                            }
+                            _ => false,
+                        };
+                    if !joins {
+                        break;
+                    }
+                    j += 2;
+                },
+            }
+            let end = chars.get(j).map_or(text.len(), |&(pos, _)| pos);
+            tokens.push(&text[start..end]);
+            i = j;
+        }
+        tokens
+    }
+}
+
+// ── Byte-Pair ──────────────────────────────────────────────────
+
+/// Byte-pair encoding over characters, trained on a sample
+///
+/// Each whitespace-separated piece starts as single characters; learned
+/// merges are applied lowest rank first, as in BPE. Merging characters
+/// rather than bytes keeps every token valid UTF-8.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BytePairTokenizer {
+    merges: Vec<(String, String)>,
+    ranks: HashMap<(String, String), usize>,
+}
+
+impl BytePairTokenizer {
+    /// Learn up to `max_merges` merges from `sample`
+    ///
+    /// Stops early once no pair occurs at least twice; ties go to the
+    /// lexicographically smallest pair so training is deterministic.
+    #[must_use]
+    pub fn train(sample: &str, max_merges: usize) -> Self {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for piece in sample.split_whitespace() {
+            *counts.entry(piece).or_insert(0) += 1;
+        }
+        let mut words: Vec<(&str, usize, Vec<Range<usize>>)> = counts
+            .into_iter()
+            .map(|(piece, n)| (piece, n, char_ranges(piece)))
+            .collect();
+
+        let mut merges = Vec::new();
+        while merges.len() < max_merges {
+            let mut pairs: HashMap<(&str, &str), usize> = HashMap::new();
+            for (piece, n, symbols) in &words {
+                for pair in symbols.windows(2) {
+                    let key = (&piece[pair[0].clone()], &piece[pair[1].clone()]);
+                    *pairs.entry(key).or_insert(0) += n;
+                }
+            }
+            let Some((best, count)) = pairs
+                .into_iter()
+                .max_by(|(a, x), (b, y)| x.cmp(y).then_with(|| b.cmp(a)))
+            else {
+                break;
+            };
+            if count < 2 {
+                break;
+            }
+            let best = (best.0.to_string(), best.1.to_string());
+            for (piece, _, symbols) in &mut words {
+                merge_all(piece, symbols, &best);
+            }
+            merges.push(best);
+        }
+        Self::from_merges(merges)
+    }
+
+    /// Tokenizer from a merge list saved with [`Self::merges`]
+    #[must_use]
+    pub fn from_merges(merges: Vec<(String, String)>) -> Self {
+        let ranks = merges
+            .iter()
+            .enumerate()
+            .map(|(rank, pair)| (pair.clone(), rank))
+            .collect();
+        Self { merges, ranks }
+    }
+
+    /// Learned merges, highest priority first
+    #[must_use]
+    pub fn merges(&self) -> &[(String, String)] {
+        &self.merges
+    }
+
+    fn rank(&self, left: &str, right: &str) -> Option<usize> {
+        self.ranks
+            .get(&(left.to_string(), right.to_string()))
+            .copied()
+    }
+}
+
+impl Tokenizer for BytePairTokenizer {
+    fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut tokens = Vec::new();
+        for piece in text.split_whitespace() {
+            let mut symbols = char_ranges(piece);
+            loop {
+                let best = symbols
+                    .windows(2)
+                    .enumerate()
+                    .filter_map(|(i, pair)| {
+                        self.rank(&piece[pair[0].clone()], &piece[pair[1].clone()])
+                            .map(|rank| (rank, i))
+                    })
+                    .min();
+                let Some((_, i)) = best else { break };
+                symbols[i].end = symbols[i + 1].end;
+                symbols.remove(i + 1);
+            }
+            tokens.extend(symbols.into_iter().map(|r| &piece[r]));
+        }
+        tokens
+    }
+}
+
+fn char_ranges(piece: &str) -> Vec<Range<usize>> {
+    piece
+        .char_indices()
+        .map(|(i, c)| i..i + c.len_utf8())
+        .collect()
+}
+
+/// Merge every left-to-right occurrence of `pair` in `symbols`
+fn merge_all(piece: &str, symbols: &mut Vec<Range<usize>>, pair: &(String, String)) {
+    let mut i = 0;
+    while i + 1 < symbols.len() {
+        if piece[symbols[i].clone()] == pair.0 && piece[symbols[i + 1].clone()] == pair.1 {
+            symbols[i].end = symbols[i + 1].end;
+            symbols.remove(i + 1);
+        }
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenizers() {
+        let log = "user_id=42 from 192.168.1.100, can't retry";
+        assert_eq!(WhitespaceTokenizer.count(log), 5);
+        assert_eq!(
+            UnicodeWordTokenizer.tokenize(log),
+            ["user_id", "42", "from", "192.168.1.100", "can't", "retry"]
+        );
+
+        let ja = "サーバーに接続しました。";
+        assert_eq!(WhitespaceTokenizer.count(ja), 1);
+        assert_eq!(
+            UnicodeWordTokenizer.tokenize(ja),
+            ["サーバー", "に", "接", "続", "し", "ま", "し", "た"]
+        );
+
+        let bpe = BytePairTokenizer::train("lower lowest low low newer", 10);
+        assert_eq!(bpe.merges()[0], ("l".to_string(), "o".to_string()));
+        assert_eq!(bpe.tokenize("low lowly"), ["low", "low", "l", "y"]);
+        let reloaded = BytePairTokenizer::from_merges(bpe.merges().to_vec());
+        assert_eq!(reloaded.tokenize("lowest"), bpe.tokenize("lowest"));
+        // Tokens are slices of the input
+        assert_eq!(bpe.tokenize("low lowly").concat(), "lowlowly");
+    }
+}
@@ -4,13 +4,51 @@
 //! - Bincode for fast binary serialization (vs JSON)
 //! - Zstd for fast compression with dictionary support
 //! - Columnar data layout for better compression ratios
-
-use crate::columnar_encoder::{ColumnarEncoder, ColumnarPayload};
-use crate::{ALICETextError, Result, ALICE_TEXT_MAGIC};
+//!
+//! With [`TunedCompressor::with_block_routing`] the text is split into
+//! line-aligned blocks and `EntropyEstimator::route_block` picks a
+//! [`BlockStrategy`] (columnar, dedup, raw-zstd, stored) for each; the choices
+//! are recorded per block in the archive and reported in [`TunedStats::routing`].
+//!
+//! In [`CompressionMode::Best`] the placeholder-type and log-level streams are
+//! arithmetic-coded with order-1 adaptive models ahead of the Zstd payload
+//! (`TunedHeader::FLAG_ENTROPY`); these short, skewed streams code to a
+//! fraction of what Zstd gets out of their Bincode form. [`CompressionMode::Max`]
+//! additionally codes the skeleton with the order-N context-mixing model
+//! (`context_mixing`) — several times slower, for cold archives.
+
+use crate::arithmetic_coder::{decode_order1, encode_order1};
+use crate::columnar_encoder::{ColumnarEncoder, ColumnarPayload, SkeletonToken};
+use crate::context_mixing::{self, ContextMixer};
+use crate::entropy_estimator::EntropyEstimator;
+use crate::format_v3::DecodeLimits;
+use crate::level::Level;
+use crate::model::TextModel;
+use crate::source_profile::{RawSegment, SourceProfile};
+use crate::tuned_pattern_learner::PatternConfig;
+use crate::zstd_params;
+use crate::{
+    telemetry, to_u32, verify_roundtrip, ALICETextError, CompressOptions, Result, ALICE_TEXT_MAGIC,
+};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 /// Tuned compressor version
-pub const TUNED_VERSION: (u8, u8) = (2, 0);
+///
+/// 2.1 columnar payloads start with a marker 2.0 readers fail on and carry
+/// the columns added since 2.0 in a tagged section (see `ColumnarPayload`).
+pub const TUNED_VERSION: (u8, u8) = (2, 1);
+
+/// Default block size for block routing (blocks end at the next line break)
+pub const DEFAULT_ROUTING_BLOCK: usize = 1 << 20;
+
+/// Inputs shorter than this are always stored raw (framing would outweigh any gain)
+pub const STORED_THRESHOLD: usize = 64;
+
+/// Upper bound for the dictionary [`TunedCompressor::compress_lines`] trains
+/// on a batch (about a tenth of the batch size below that)
+pub const LINE_DICTIONARY_MAX: usize = 64 * 1024;
 
 /// Compression mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
@@ -23,15 +61,20 @@ pub enum CompressionMode {
     Balanced = 1,
     /// Best compression (zstd level 15-22)
     Best = 2,
+    /// Best plus context-mixing skeleton coding (slow; for cold archives)
+    Max = 3,
 }
 
 impl CompressionMode {
-    const fn zstd_level(self) -> i32 {
-        match self {
-            Self::Fast => 3,
-            Self::Balanced => 10,
-            Self::Best => 19,
-        }
+    /// Zstd level of this preset (see [`Level`])
+    #[must_use]
+    pub fn zstd_level(self) -> i32 {
+        Level::from(self).zstd_level()
+    }
+
+    /// Whether the arithmetic-coding entropy stage is used
+    const fn entropy_coded(self) -> bool {
+        matches!(self, Self::Best | Self::Max)
     }
 }
 
@@ -46,22 +89,86 @@ pub struct TunedHeader {
     pub pattern_count: u32,
     /// Skeleton length
     pub skeleton_length: u32,
+    /// Block flags (`FLAG_STORED`, ...)
+    pub flags: u8,
+    /// Size of the Bincode `PatternConfig` preceding the body (0 = default config)
+    pub config_size: u32,
+    /// Zstd window log decoders must accept (0 = within their default; see
+    /// [`ZstdParams::required_window_log`](crate::ZstdParams::required_window_log))
+    pub window_log: u8,
 }
 
 impl TunedHeader {
     /// Header size in bytes (fixed)
     pub const SIZE: usize = 24;
 
+    /// Payload is the raw UTF-8 text (no columnar encoding, no Zstd)
+    pub const FLAG_STORED: u8 = 0x01;
+
+    /// Restore CRLF line endings on decode
+    pub const FLAG_CRLF: u8 = SourceProfile::FLAG_CRLF;
+
+    /// Original input was UTF-16LE with BOM
+    pub const FLAG_UTF16LE: u8 = SourceProfile::FLAG_UTF16LE;
+
+    /// Body is a sequence of routed blocks (see [`BlockStrategy`])
+    pub const FLAG_ROUTED: u8 = 0x08;
+
+    /// Columnar bodies carry arithmetic-coded streams (`CompressionMode::Best`/`Max`)
+    pub const FLAG_ENTROPY: u8 = 0x10;
+
+    /// Columnar bodies are Zstd-compressed with a model dictionary; the
+    /// model id (u64 LE) follows the pattern config
+    pub const FLAG_DICTIONARY: u8 = 0x20;
+
+    /// Every flag this reader understands; archives with any other bit set are refused
+    pub const KNOWN_FLAGS: u8 = Self::FLAG_STORED
+        | Self::FLAG_CRLF
+        | Self::FLAG_UTF16LE
+        | Self::FLAG_ROUTED
+        | Self::FLAG_ENTROPY
+        | Self::FLAG_DICTIONARY;
+
+    /// Whether the payload is a stored (uncompressed) block
+    #[inline]
+    #[must_use]
+    pub const fn is_stored(&self) -> bool {
+        self.flags & Self::FLAG_STORED != 0
+    }
+
+    /// Whether columnar bodies start with an entropy-coded stream section
+    #[inline]
+    #[must_use]
+    pub const fn is_entropy_coded(&self) -> bool {
+        self.flags & Self::FLAG_ENTROPY != 0
+    }
+
+    /// Whether decoding needs the model recorded after the pattern config
+    #[inline]
+    #[must_use]
+    pub const fn uses_dictionary(&self) -> bool {
+        self.flags & Self::FLAG_DICTIONARY != 0
+    }
+
+    /// Whether the payload is a routed block container
+    #[inline]
+    #[must_use]
+    pub const fn is_routed(&self) -> bool {
+        self.flags & Self::FLAG_ROUTED != 0
+    }
+
     #[must_use]
     pub fn to_bytes(&self) -> [u8; Self::SIZE] {
         let mut bytes = [0u8; Self::SIZE];
 
         bytes[0..8].copy_from_slice(&self.original_length.to_le_bytes());
         bytes[8] = self.mode as u8;
-        bytes[9..12].fill(0); // reserved
+        bytes[9] = self.flags;
+        bytes[10] = self.window_log;
+        bytes[11] = 0; // reserved
         bytes[12..16].copy_from_slice(&self.pattern_count.to_le_bytes());
         bytes[16..20].copy_from_slice(&self.skeleton_length.to_le_bytes());
-        bytes[20..24].fill(0); // reserved
+        bytes[20..24].copy_from_slice(&self.config_size.to_le_bytes());
 
         bytes
     }
@@ -84,16 +191,94 @@ impl TunedHeader {
                 0 => CompressionMode::Fast,
                 1 => CompressionMode::Balanced,
                 2 => CompressionMode::Best,
+                3 => CompressionMode::Max,
                 _ => CompressionMode::Balanced,
             },
             pattern_count: u32::from_le_bytes(bytes[12..16].try_into().map_err(|_| to_err())?),
             skeleton_length: u32::from_le_bytes(bytes[16..20].try_into().map_err(|_| to_err())?),
+            flags: bytes[9],
+            config_size: u32::from_le_bytes(bytes[20..24].try_into().map_err(|_| to_err())?),
+            window_log: bytes[10],
         })
     }
 }
 
+/// Encoding of one block in a routed archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum BlockStrategy {
+    /// Pattern extraction + columnar payload + Zstd
+    Columnar = 0,
+    /// Unique-line dictionary + per-line indices + Zstd
+    Dedup = 1,
+    /// Zstd over the plain text
+    RawZstd = 2,
+    /// Plain text
+    Stored = 3,
+}
+
+impl BlockStrategy {
+    pub const ALL: [Self; 4] = [Self::Columnar, Self::Dedup, Self::RawZstd, Self::Stored];
+
+    #[must_use]
+    pub const fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::Columnar),
+            1 => Some(Self::Dedup),
+            2 => Some(Self::RawZstd),
+            3 => Some(Self::Stored),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Columnar => "columnar",
+            Self::Dedup => "dedup",
+            Self::RawZstd => "raw-zstd",
+            Self::Stored => "stored",
+        }
+    }
+}
+
+/// Routing decision for one block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRoute {
+    pub strategy: BlockStrategy,
+    pub original_size: usize,
+    pub compressed_size: usize,
+}
+
+/// Per-block strategy choices of a routed compression
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingStats {
+    pub blocks: Vec<BlockRoute>,
+}
+
+impl RoutingStats {
+    /// Number of blocks routed to `strategy`
+    #[must_use]
+    pub fn count(&self, strategy: BlockStrategy) -> usize {
+        self.blocks
+            .iter()
+            .filter(|b| b.strategy == strategy)
+            .count()
+    }
+
+    /// Input bytes routed to `strategy`
+    #[must_use]
+    pub fn bytes(&self, strategy: BlockStrategy) -> usize {
+        self.blocks
+            .iter()
+            .filter(|b| b.strategy == strategy)
+            .map(|b| b.original_size)
+            .sum()
+    }
+}
+
 /// Compression statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TunedStats {
     pub original_size: usize,
     pub compressed_size: usize,
@@ -101,6 +286,54 @@ pub struct TunedStats {
     pub pattern_count: usize,
     pub compression_ratio: f64,
     pub space_savings: f64,
+    /// Input bytes captured into typed value columns (the rest is skeleton text)
+    #[serde(default)]
+    pub value_bytes: usize,
+    /// `value_bytes / original_size`: the share of the input that is
+    /// exceptions to the skeleton (0 for stored blocks)
+    #[serde(default)]
+    pub exception_rate: f64,
+    /// Block routing choices (`None` unless block routing is enabled and used)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingStats>,
+}
+
+/// Output of [`TunedCompressor::encode_block`] / [`TunedCompressor::encode_routed`]
+struct EncodedBody {
+    body: Vec<u8>,
+    pattern_count: usize,
+    /// Skeleton token count
+    skeleton_size: usize,
+    config_size: usize,
+    /// Input bytes moved into value columns
+    value_bytes: usize,
+}
+
+/// Individually decodable records and the state they share
+/// (see [`TunedCompressor::compress_lines`])
+#[derive(Debug, Clone, Default)]
+pub struct LineBatch {
+    /// Shared state: an `.atxm` model (pattern config and dictionary)
+    pub state: Vec<u8>,
+    /// One Zstd frame per input line, in input order
+    pub frames: Vec<Vec<u8>>,
+}
+
+impl LineBatch {
+    /// Parse the shared state (load it with [`TunedCompressor::with_model`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state is not a valid model.
+    pub fn model(&self) -> Result<TextModel> {
+        TextModel::from_bytes(&self.state)
+    }
+
+    /// Total size of the frames (state excluded)
+    #[must_use]
+    pub fn frames_size(&self) -> usize {
+        self.frames.iter().map(Vec::len).sum()
+    }
 }
 
 /// Tuned Compressor
@@ -108,21 +341,122 @@ pub struct TunedStats {
 /// High-performance compressor using columnar layout + Zstd.
 pub struct TunedCompressor {
     encoder: ColumnarEncoder,
-    mode: CompressionMode,
+    level: Level,
+    options: CompressOptions,
+    /// Block size for block routing (`None` = single columnar block)
+    routing_block: Option<usize>,
+    /// Model id and Zstd dictionary for columnar payloads (see [`TextModel`])
+    dictionary: Option<(u64, Vec<u8>)>,
+    /// Output ceilings when decoding
+    limits: DecodeLimits,
     last_stats: Option<TunedStats>,
 }
 
+const _: () = crate::assert_send_sync::<TunedCompressor>();
+
 impl TunedCompressor {
-    /// Create a new tuned compressor
+    /// Create a new tuned compressor (a [`Level`], or a [`CompressionMode`] preset)
     #[must_use]
-    pub fn new(mode: CompressionMode) -> Self {
+    pub fn new(level: impl Into<Level>) -> Self {
         Self {
             encoder: ColumnarEncoder::new(),
-            mode,
+            level: level.into(),
+            options: CompressOptions::default(),
+            routing_block: None,
+            dictionary: None,
+            limits: DecodeLimits::new(),
             last_stats: None,
         }
     }
 
+    /// Split the input into line-aligned blocks of about `block_size` bytes
+    /// and let the entropy estimator pick each block's [`BlockStrategy`]
+    #[must_use]
+    pub const fn with_block_routing(mut self, block_size: usize) -> Self {
+        self.routing_block = Some(if block_size == 0 { 1 } else { block_size });
+        self
+    }
+
+    /// Set compression options
+    #[must_use]
+    pub const fn with_options(mut self, options: CompressOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the output ceilings for decoding (default: [`DecodeLimits::new`])
+    #[must_use]
+    pub const fn with_decode_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Use a custom pattern configuration (recorded in the archive header)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exclusion regex is invalid.
+    pub fn with_pattern_config(mut self, config: PatternConfig) -> Result<Self> {
+        self.encoder = ColumnarEncoder::with_config(config)?;
+        Ok(self)
+    }
+
+    /// Use a shared model: its pattern configuration, and its dictionary for
+    /// writing and for reading archives that were written with it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exclusion regex in the model's config is invalid.
+    pub fn with_model(self, model: &TextModel) -> Result<Self> {
+        let mut this = self.with_pattern_config(model.pattern_config.clone())?;
+        this.dictionary = if model.has_dictionary() {
+            Some((model.id()?, model.dictionary.clone()))
+        } else {
+            None
+        };
+        Ok(this)
+    }
+
+    /// Model id a v2 archive needs for decoding (`None` if it needs no model)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a v2 archive or the header is corrupt.
+    pub fn read_model_id(data: &[u8]) -> Result<Option<u64>> {
+        let (header, body) = Self::split_frame(data)?;
+        if header.is_stored() {
+            return Ok(None);
+        }
+        let (_, body) = Self::split_config(&header, body)?;
+        Self::split_model(&header, body).map(|(id, _)| id)
+    }
+
+    /// Pattern configuration recorded in a v2 archive (default if none)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a v2 archive or the config block is corrupt.
+    pub fn read_pattern_config(data: &[u8]) -> Result<PatternConfig> {
+        let (header, body) = Self::split_frame(data)?;
+        Self::split_config(&header, body).map(|(config, _)| config)
+    }
+
+    /// Per-block strategies recorded in a v2 archive (empty unless routed)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a v2 archive or the block table is corrupt.
+    pub fn block_strategies(data: &[u8]) -> Result<Vec<BlockStrategy>> {
+        let (header, body) = Self::split_frame(data)?;
+        if !header.is_routed() || header.is_stored() {
+            return Ok(Vec::new());
+        }
+        let (_, body) = Self::split_config(&header, body)?;
+        let (_, body) = Self::split_model(&header, body)?;
+        let (blocks, _) = split_routed(body)?;
+        Ok(blocks.into_iter().map(|(strategy, _)| strategy).collect())
+    }
+
     /// Create with default balanced mode
     #[must_use]
     pub fn default_balanced() -> Self {
@@ -141,66 +475,438 @@ impl TunedCompressor {
         Self::new(CompressionMode::Best)
     }
 
+    /// Create with maximum (context-mixing) compression
+    #[must_use]
+    pub fn max() -> Self {
+        Self::new(CompressionMode::Max)
+    }
+
     /// Compress text to bytes
     ///
     /// # Errors
     ///
-    /// Returns an error if Bincode serialization or Zstd compression fails.
+    /// Returns an error if Bincode serialization or Zstd compression fails, or
+    /// (with `verify_roundtrip`) if the output does not decode back to `text`.
     pub fn compress(&mut self, text: &str) -> Result<Vec<u8>> {
-        let original_size = text.len();
+        let (output, stats) = self.compress_with_stats(text)?;
+        self.last_stats = Some(stats);
+        Ok(output)
+    }
 
-        // Step 1: Extract patterns and create columnar payload
-        let payload = self.encoder.encode(text);
-        let pattern_count = payload.placeholder_map.len();
-        let skeleton_size = payload.skeleton_tokens.len();
+    /// Compress text to bytes, returning the statistics instead of keeping
+    /// them for [`Self::last_stats`]
+    ///
+    /// Takes `&self`, so one compressor can serve several threads (e.g.
+    /// behind an `Arc`) without a lock.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::compress`].
+    pub fn compress_with_stats(&self, text: &str) -> Result<(Vec<u8>, TunedStats)> {
+        let text = self.options.prepare(text);
+        let (normalized, profile) = SourceProfile::normalize(&text);
+        self.compress_normalized(&normalized, profile, &[])
+    }
 
-        // Step 2: Serialize payload with Bincode
-        let serialized = bincode::serialize(&payload)
-            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+    /// Compress each line to its own frame, sharing a dictionary across all of them
+    ///
+    /// For message queues and similar stores where every record must stay
+    /// individually decodable: the dictionary carries the corpus-level
+    /// redundancy that a single short record cannot. It is the model's when one
+    /// is set ([`Self::with_model`]), otherwise trained on the batch itself
+    /// (none if the batch is too small). Decode a frame with
+    /// [`Self::decompress_line`] after loading [`LineBatch::model`].
+    ///
+    /// Frames hold the record text as is; per-record columnar payloads cost
+    /// more in framing than pattern extraction saves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Zstd compression or model serialization fails.
+    pub fn compress_lines<'a, I>(&self, lines: I) -> Result<LineBatch>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let lines: Vec<&str> = lines.into_iter().collect();
+        let dictionary = match &self.dictionary {
+            Some((_, dictionary)) => dictionary.clone(),
+            None => {
+                let total: usize = lines.iter().map(|l| l.len()).sum();
+                // Zstd rejects too few or too small samples: frames go without
+                zstd::dict::from_samples(&lines, (total / 10).min(LINE_DICTIONARY_MAX))
+                    .unwrap_or_default()
+            }
+        };
 
-        // Step 3: Compress with Zstd
-        let compressed =
-            zstd::stream::encode_all(std::io::Cursor::new(&serialized), self.mode.zstd_level())
-                .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")))?;
+        let zstd_error =
+            |e: std::io::Error| ALICETextError::EncodingError(format!("Zstd error: {e}"));
+        let mut compressor = self
+            .options
+            .zstd
+            .compressor(self.level.zstd_level(), &dictionary)?;
+        let frames = lines
+            .iter()
+            .map(|line| compressor.compress(line.as_bytes()).map_err(zstd_error))
+            .collect::<Result<Vec<_>>>()?;
+
+        let model = TextModel {
+            pattern_config: self.encoder.pattern_config().clone(),
+            dictionary,
+            ..TextModel::default()
+        };
+        Ok(LineBatch {
+            state: model.to_bytes()?,
+            frames,
+        })
+    }
+
+    /// Decode one frame of a [`LineBatch`] (the batch's model must be loaded)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is corrupt, was written with a different
+    /// dictionary, is not UTF-8, or decodes past the
+    /// [`DecodeLimits::max_total_bytes`] ceiling.
+    pub fn decompress_line(&self, frame: &[u8]) -> Result<String> {
+        let dictionary = self.dictionary.as_ref().map_or(&[][..], |(_, d)| d);
+        let out = zstd_params::decompress(frame, dictionary, 0, self.limits.max_total_bytes)?;
+        utf8(out)
+    }
+
+    /// Compress arbitrary bytes: UTF-8 (invalid runs kept verbatim), or UTF-16LE with BOM
+    ///
+    /// [`Self::decompress_bytes`] returns the exact input bytes (see [`SourceProfile`]).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::compress`].
+    pub fn compress_bytes(&mut self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let (output, stats) = self.compress_bytes_with_stats(bytes)?;
+        self.last_stats = Some(stats);
+        Ok(output)
+    }
+
+    /// [`Self::compress_bytes`] through `&self`, returning the statistics
+    /// (see [`Self::compress_with_stats`])
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::compress`].
+    pub fn compress_bytes_with_stats(&self, bytes: &[u8]) -> Result<(Vec<u8>, TunedStats)> {
+        let (text, profile, raw) = SourceProfile::decode(bytes);
+        match self.options.prepare(&text) {
+            Cow::Borrowed(text) => self.compress_normalized(text, profile, &raw),
+            // Stripping moved the placeholders; the (lossy) output keeps U+FFFD
+            Cow::Owned(text) => self.compress_normalized(&text, profile, &[]),
+        }
+    }
+
+    /// Compress LF-only UTF-8 text, recording `profile` in the header flags and
+    /// `raw` (invalid UTF-8 runs behind placeholders) in the payload column section
+    fn compress_normalized(
+        &self,
+        text: &str,
+        profile: SourceProfile,
+        raw: &[RawSegment],
+    ) -> Result<(Vec<u8>, TunedStats)> {
+        let timer = telemetry::Timer::start();
+        let original_size = profile.restored_len(text, raw);
+        self.options.zstd.validate()?;
+
+        // Step 1-3: Columnar encode + Bincode + Zstd (skipped for tiny inputs;
+        // raw byte runs have no place in a stored block)
+        let mut routing = None;
+        let encoded = if text.len() < STORED_THRESHOLD && raw.is_empty() {
+            None
+        } else if let Some(block_size) = self.routing_block {
+            let (encoded, stats) = self.encode_routed(text, raw, block_size)?;
+            routing = Some(stats);
+            Some(encoded)
+        } else {
+            Some(self.encode_block(text, raw)?)
+        };
+        let routed_flag = if routing.is_some() {
+            TunedHeader::FLAG_ROUTED
+        } else {
+            0
+        };
+        let entropy_flag = if self.level.mode().entropy_coded() {
+            TunedHeader::FLAG_ENTROPY
+        } else {
+            0
+        };
+        let dictionary_flag = if self.dictionary.is_some() {
+            TunedHeader::FLAG_DICTIONARY
+        } else {
+            0
+        };
+
+        // Fall back to a stored block when compression doesn't help
+        let (header, body) = match &encoded {
+            Some(encoded) if encoded.body.len() < text.len() || !raw.is_empty() => (
+                TunedHeader {
+                    original_length: original_size as u64,
+                    mode: self.level.mode(),
+                    // Informational counts (routed totals may pass u32): saturate
+                    pattern_count: u32::try_from(encoded.pattern_count).unwrap_or(u32::MAX),
+                    skeleton_length: u32::try_from(encoded.skeleton_size).unwrap_or(u32::MAX),
+                    flags: profile.flags() | routed_flag | entropy_flag | dictionary_flag,
+                    config_size: to_u32(encoded.config_size, "v2 pattern config size")?,
+                    window_log: self.options.zstd.required_window_log().unwrap_or(0),
+                },
+                encoded.body.as_slice(),
+            ),
+            _ => (
+                TunedHeader {
+                    original_length: original_size as u64,
+                    mode: self.level.mode(),
+                    pattern_count: 0,
+                    skeleton_length: 0,
+                    flags: TunedHeader::FLAG_STORED | profile.flags(),
+                    config_size: 0,
+                    window_log: 0,
+                },
+                text.as_bytes(),
+            ),
+        };
+
+        if header.is_stored() {
+            routing = None;
+        }
+        let value_bytes = match &encoded {
+            Some(encoded) if !header.is_stored() => encoded.value_bytes,
+            _ => 0,
+        };
 
         // Step 4: Build final output
-        // Format: MAGIC (8) + VERSION (2) + HEADER (24) + COMPRESSED_DATA
-        let mut output = Vec::with_capacity(8 + 2 + TunedHeader::SIZE + compressed.len());
+        // Format: MAGIC (8) + VERSION (2) + HEADER (24) + BODY
+        let mut output = Vec::with_capacity(8 + 2 + TunedHeader::SIZE + body.len());
 
         // Magic bytes
         output.extend_from_slice(ALICE_TEXT_MAGIC);
 
-        // Version (2.0 for tuned format)
+        // Version (2.x for tuned format)
         output.push(TUNED_VERSION.0);
         output.push(TUNED_VERSION.1);
 
         // Header
-        let header = TunedHeader {
-            original_length: original_size as u64,
-            mode: self.mode,
-            pattern_count: pattern_count as u32,
-            skeleton_length: skeleton_size as u32,
-        };
         output.extend_from_slice(&header.to_bytes());
 
-        // Compressed data
-        output.extend_from_slice(&compressed);
+        // Compressed data (or raw text for stored blocks)
+        output.extend_from_slice(body);
+
+        // Step 5: Optional self-check before handing the bytes out
+        if self.options.verify_roundtrip {
+            let (decoded, _) = self
+                .decode_normalized(&header, body)
+                .map_err(|e| ALICETextError::VerificationFailed(e.to_string()))?;
+            verify_roundtrip(text, &decoded)?;
+        }
 
         let compressed_size = output.len();
 
         // Update stats
-        let inv_original = 1.0 / original_size as f64;
-        let ratio = compressed_size as f64 * inv_original;
-        self.last_stats = Some(TunedStats {
+        let ratio = if original_size == 0 {
+            0.0
+        } else {
+            compressed_size as f64 / original_size as f64
+        };
+        let stats = TunedStats {
             original_size,
             compressed_size,
-            skeleton_size,
-            pattern_count,
+            skeleton_size: header.skeleton_length as usize,
+            pattern_count: header.pattern_count as usize,
             compression_ratio: ratio,
             space_savings: 1.0 - ratio,
-        });
+            value_bytes,
+            exception_rate: if original_size == 0 {
+                0.0
+            } else {
+                value_bytes as f64 / original_size as f64
+            },
+            routing,
+        };
 
-        Ok(output)
+        telemetry::record_compress(&timer, "v2", original_size, compressed_size);
+        Ok((output, stats))
+    }
+
+    /// Columnar encode + Bincode + Zstd, preceded by the pattern config if not default
+    fn encode_block(&self, text: &str, raw: &[RawSegment]) -> Result<EncodedBody> {
+        // Step 1: Extract patterns and create columnar payload
+        let mut payload = self
+            .encoder
+            .encode_limited(text, self.options.pattern_limits);
+        payload.raw_bytes = raw.to_vec();
+        let pattern_count = payload.placeholder_map.len();
+        let skeleton_size = payload.skeleton_tokens.len();
+        let value_bytes = text.len().saturating_sub(payload.literal_bytes());
+
+        // Step 2-3: Serialize payload with Bincode, compress with Zstd
+        let compressed = self.encode_columnar(payload)?;
+
+        let (body, config_size) = self.prefix_config(compressed)?;
+        Ok(EncodedBody {
+            body,
+            pattern_count,
+            skeleton_size,
+            config_size,
+            value_bytes,
+        })
+    }
+
+    /// Routed container: block count, then per block strategy (u8), body
+    /// length (u32 LE) and body; invalid UTF-8 runs follow as a Bincode trailer
+    ///
+    /// Returns the body (value bytes from columnar blocks only) and the routing stats.
+    fn encode_routed(
+        &self,
+        text: &str,
+        raw: &[RawSegment],
+        block_size: usize,
+    ) -> Result<(EncodedBody, RoutingStats)> {
+        let estimator = EntropyEstimator::new();
+        let blocks = split_blocks(text, block_size);
+        let mut stats = RoutingStats::default();
+        let (mut pattern_count, mut skeleton_size, mut value_bytes) = (0, 0, 0);
+
+        let mut body = Vec::with_capacity(text.len() / 4);
+        body.extend_from_slice(&to_u32(blocks.len(), "v2 block count")?.to_le_bytes());
+        for block in blocks {
+            let strategy = estimator.route_block(block, self.level);
+            let data = match strategy {
+                BlockStrategy::Columnar => {
+                    let payload = self
+                        .encoder
+                        .encode_limited(block, self.options.pattern_limits);
+                    pattern_count += payload.placeholder_map.len();
+                    skeleton_size += payload.skeleton_tokens.len();
+                    value_bytes += block.len().saturating_sub(payload.literal_bytes());
+                    self.encode_columnar(payload)?
+                }
+                BlockStrategy::Dedup => self.zstd(&dedup_table(block)?)?,
+                BlockStrategy::RawZstd => self.zstd(block.as_bytes())?,
+                BlockStrategy::Stored => block.as_bytes().to_vec(),
+            };
+            body.push(strategy as u8);
+            body.extend_from_slice(&to_u32(data.len(), "v2 block size")?.to_le_bytes());
+            body.extend_from_slice(&data);
+            stats.blocks.push(BlockRoute {
+                strategy,
+                original_size: block.len(),
+                compressed_size: data.len(),
+            });
+        }
+        if !raw.is_empty() {
+            let trailer = bincode::serialize(raw)
+                .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+            body.extend_from_slice(&trailer);
+        }
+
+        let (body, config_size) = self.prefix_config(body)?;
+        let encoded = EncodedBody {
+            body,
+            pattern_count,
+            skeleton_size,
+            config_size,
+            value_bytes,
+        };
+        Ok((encoded, stats))
+    }
+
+    /// Zstd-compressed payload; in `Best`/`Max` mode preceded by the
+    /// entropy-coded streams (length as u32 LE, then Bincode [`EntropyStreams`])
+    fn encode_columnar(&self, mut payload: ColumnarPayload) -> Result<Vec<u8>> {
+        // Placeholder map entries index their column as u32
+        to_u32(payload.placeholder_map.len(), "v2 placeholder count")?;
+        if !self.level.mode().entropy_coded() {
+            return self.zstd_columnar(&payload.to_v2_bytes()?);
+        }
+        let streams = bincode::serialize(&EntropyStreams::take(&mut payload, self.level.mode()))
+            .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?;
+        let mut body = Vec::with_capacity(4 + streams.len());
+        body.extend_from_slice(&to_u32(streams.len(), "v2 entropy stream size")?.to_le_bytes());
+        body.extend_from_slice(&streams);
+        body.extend_from_slice(&self.zstd_columnar(&payload.to_v2_bytes()?)?);
+        Ok(body)
+    }
+
+    /// Zstd at this compressor's level and [`CompressOptions::zstd`] parameters
+    fn zstd(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.options.zstd.compress(data, self.level.zstd_level())
+    }
+
+    /// Zstd for columnar payloads, with the model dictionary if one is set
+    fn zstd_columnar(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let Some((_, dictionary)) = &self.dictionary else {
+            return self.zstd(data);
+        };
+        self.options
+            .zstd
+            .compressor(self.level.zstd_level(), dictionary)?
+            .compress(data)
+            .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")))
+    }
+
+    /// Inverse of [`Self::zstd_columnar`]
+    fn unzstd_columnar(&self, header: &TunedHeader, data: &[u8]) -> Result<Vec<u8>> {
+        if !header.uses_dictionary() {
+            return zstd_params::decompress(
+                data,
+                &[],
+                header.window_log,
+                self.limits.max_total_bytes,
+            );
+        }
+        let Some((_, dictionary)) = &self.dictionary else {
+            return Err(ALICETextError::DecompressionError(
+                "Archive needs a model dictionary".to_string(),
+            ));
+        };
+        zstd_params::decompress(
+            data,
+            dictionary,
+            header.window_log,
+            self.limits.max_total_bytes,
+        )
+    }
+
+    /// Inverse of [`Self::encode_columnar`]
+    fn decode_columnar(&self, header: &TunedHeader, data: &[u8]) -> Result<ColumnarPayload> {
+        if !header.is_entropy_coded() {
+            return ColumnarPayload::from_v2_bytes(&self.unzstd_columnar(header, data)?);
+        }
+        let corrupt = || ALICETextError::DecompressionError("Entropy section truncated".into());
+        let (len, rest) = data.split_first_chunk::<4>().ok_or_else(corrupt)?;
+        let (streams, rest) = rest
+            .split_at_checked(u32::from_le_bytes(*len) as usize)
+            .ok_or_else(corrupt)?;
+        let streams: EntropyStreams = bincode::deserialize(streams)
+            .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        let mut payload = ColumnarPayload::from_v2_bytes(&self.unzstd_columnar(header, rest)?)?;
+        streams.restore(&mut payload, self.limits)?;
+        Ok(payload)
+    }
+
+    /// Prepend the pattern config if not default, then the model id if a
+    /// dictionary is in use; returns (body, config size)
+    fn prefix_config(&self, compressed: Vec<u8>) -> Result<(Vec<u8>, usize)> {
+        let config = self.encoder.pattern_config();
+        if config.is_default() && self.dictionary.is_none() {
+            return Ok((compressed, 0));
+        }
+        let mut body = if config.is_default() {
+            Vec::with_capacity(8 + compressed.len())
+        } else {
+            bincode::serialize(config)
+                .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))?
+        };
+        let config_size = body.len();
+        if let Some((id, _)) = &self.dictionary {
+            body.extend_from_slice(&id.to_le_bytes());
+        }
+        body.extend_from_slice(&compressed);
+        Ok((body, config_size))
     }
 
     /// Decompress bytes to text
@@ -210,6 +916,32 @@ impl TunedCompressor {
     /// Returns an error if the data is too short, magic is invalid, version is legacy,
     /// or Zstd/Bincode decompression fails.
     pub fn decompress(&self, data: &[u8]) -> Result<String> {
+        let timer = telemetry::Timer::start();
+        self.limits.check_archive(data.len() as u64)?;
+        let (header, body) = Self::split_frame(data)?;
+        let text = self.decode_body(&header, body)?;
+        telemetry::record_decompress(&timer, "v2", data.len(), text.len());
+        Ok(text)
+    }
+
+    /// Decompress to the exact original input bytes (invalid UTF-8 spliced back,
+    /// UTF-16LE archives re-encoded)
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::decompress`].
+    pub fn decompress_bytes(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let timer = telemetry::Timer::start();
+        self.limits.check_archive(data.len() as u64)?;
+        let (header, body) = Self::split_frame(data)?;
+        let (text, raw) = self.decode_normalized(&header, body)?;
+        let bytes = SourceProfile::from_flags(header.flags).encode(text, &raw);
+        telemetry::record_decompress(&timer, "v2", data.len(), bytes.len());
+        Ok(bytes)
+    }
+
+    /// Validate magic/version and split into header and body
+    fn split_frame(data: &[u8]) -> Result<(TunedHeader, &[u8])> {
         // Minimum size check
         let min_size = 8 + 2 + TunedHeader::SIZE;
         if data.len() < min_size {
@@ -230,23 +962,134 @@ impl TunedCompressor {
                 "Legacy format - use ALICEText instead".to_string(),
             ));
         }
+        // v2 has no skippable records: a newer minor may hold data this build would drop
+        if version.0 == TUNED_VERSION.0 && version.1 > TUNED_VERSION.1 {
+            return Err(ALICETextError::InvalidVersion(version.0, version.1));
+        }
 
         // Parse header
-        let _header = TunedHeader::from_bytes(&data[10..10 + TunedHeader::SIZE])?;
+        let header = TunedHeader::from_bytes(&data[10..10 + TunedHeader::SIZE])?;
+        let unknown = header.flags & !TunedHeader::KNOWN_FLAGS;
+        if unknown != 0 {
+            return Err(ALICETextError::DecompressionError(format!(
+                "Unknown v2 header flags {unknown:#04x}"
+            )));
+        }
 
         // Get compressed data
-        let compressed_data = &data[10 + TunedHeader::SIZE..];
-
-        // Decompress with Zstd
-        let decompressed = zstd::stream::decode_all(std::io::Cursor::new(compressed_data))
-            .map_err(|e| ALICETextError::DecompressionError(format!("Zstd error: {e}")))?;
+        Ok((header, &data[10 + TunedHeader::SIZE..]))
+    }
 
-        // Deserialize with Bincode
-        let payload: ColumnarPayload = bincode::deserialize(&decompressed)
+    /// Split the optional pattern config off the front of a body
+    fn split_config<'a>(header: &TunedHeader, body: &'a [u8]) -> Result<(PatternConfig, &'a [u8])> {
+        if header.config_size == 0 {
+            return Ok((PatternConfig::default(), body));
+        }
+        let (config_bytes, rest) = body
+            .split_at_checked(header.config_size as usize)
+            .ok_or_else(|| {
+                ALICETextError::DecompressionError("Pattern config truncated".to_string())
+            })?;
+        let config = bincode::deserialize(config_bytes)
             .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+        Ok((config, rest))
+    }
+
+    /// Split the model id off the front of a (config-stripped) body
+    fn split_model<'a>(header: &TunedHeader, body: &'a [u8]) -> Result<(Option<u64>, &'a [u8])> {
+        if !header.uses_dictionary() {
+            return Ok((None, body));
+        }
+        let (id, rest) = body
+            .split_first_chunk::<8>()
+            .ok_or_else(|| ALICETextError::DecompressionError("Model id truncated".to_string()))?;
+        Ok((Some(u64::from_le_bytes(*id)), rest))
+    }
+
+    /// Decode the block following the header, line endings restored
+    fn decode_body(&self, header: &TunedHeader, body: &[u8]) -> Result<String> {
+        let (text, _) = self.decode_normalized(header, body)?;
+        Ok(SourceProfile::from_flags(header.flags).restore_text(text))
+    }
+
+    /// Decode the block following the header to the LF-only text that was encoded,
+    /// plus the invalid UTF-8 runs behind its placeholders
+    fn decode_normalized(
+        &self,
+        header: &TunedHeader,
+        body: &[u8],
+    ) -> Result<(String, Vec<RawSegment>)> {
+        // Stored block: payload is the original text
+        if header.is_stored() {
+            return std::str::from_utf8(body)
+                .map(|text| (text.to_string(), Vec::new()))
+                .map_err(|e| ALICETextError::DecompressionError(format!("UTF-8 error: {e}")));
+        }
+
+        // Pattern config is informational for decoding (values are stored verbatim)
+        let (_, body) = Self::split_config(header, body)?;
+
+        // A dictionary archive decodes only with the model it was written with
+        let (model_id, body) = Self::split_model(header, body)?;
+        if let Some(id) = model_id {
+            if self.dictionary.as_ref().map(|(own, _)| *own) != Some(id) {
+                return Err(ALICETextError::DecompressionError(format!(
+                    "Archive needs model {id:016x}"
+                )));
+            }
+        }
+
+        if header.is_routed() {
+            return self.decode_routed(header, body);
+        }
+
+        // Decompress with Zstd, deserialize with Bincode (payload, then the column section)
+        let mut payload = self.decode_columnar(header, body)?;
+        let raw = std::mem::take(&mut payload.raw_bytes);
 
         // Restore text
-        Ok(self.encoder.decode(&payload))
+        Ok((self.encoder.decode(&payload), raw))
+    }
+
+    /// Decode a routed block container (see [`Self::encode_routed`])
+    fn decode_routed(
+        &self,
+        header: &TunedHeader,
+        body: &[u8],
+    ) -> Result<(String, Vec<RawSegment>)> {
+        let (blocks, trailer) = split_routed(body)?;
+        let mut text = String::new();
+        for (strategy, data) in blocks {
+            // What the remaining blocks may still produce
+            let budget = self
+                .limits
+                .max_total_bytes
+                .saturating_sub(text.len() as u64);
+            match strategy {
+                BlockStrategy::Columnar => {
+                    let payload = self.decode_columnar(header, data)?;
+                    text.push_str(&self.encoder.decode(&payload));
+                }
+                BlockStrategy::Dedup => {
+                    text.push_str(&decode_dedup(data, header.window_log, budget)?);
+                }
+                BlockStrategy::RawZstd => text.push_str(&utf8(zstd_params::decompress(
+                    data,
+                    &[],
+                    header.window_log,
+                    budget,
+                )?)?),
+                BlockStrategy::Stored => text.push_str(&utf8(data.to_vec())?),
+            }
+            self.limits.check_total(text.len() as u64)?;
+        }
+        let raw = if trailer.is_empty() {
+            Vec::new()
+        } else {
+            bincode::deserialize(trailer)
+                .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?
+        };
+        Ok((text, raw))
     }
 
     /// Get last compression statistics
@@ -255,15 +1098,32 @@ impl TunedCompressor {
         self.last_stats.as_ref()
     }
 
-    /// Get compression mode
+    /// Get compression mode (the pipeline; [`Level::Zstd`] runs `Balanced`)
     #[must_use]
     pub const fn mode(&self) -> CompressionMode {
-        self.mode
+        self.level.mode()
     }
 
     /// Set compression mode
-    pub const fn set_mode(&mut self, mode: CompressionMode) {
-        self.mode = mode;
+    pub fn set_mode(&mut self, mode: CompressionMode) {
+        self.level = mode.into();
+    }
+
+    /// Get compression level
+    #[must_use]
+    pub const fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Set compression level
+    pub const fn set_level(&mut self, level: Level) {
+        self.level = level;
+    }
+
+    /// Get compression options
+    #[must_use]
+    pub const fn options(&self) -> CompressOptions {
+        self.options
     }
 
     /// Verify compressed data without full decompression
@@ -318,13 +1178,237 @@ impl Default for TunedCompressor {
     }
 }
 
+/// Placeholder-type and log-level streams, order-1 arithmetic-coded, and
+/// (`Max` only) the skeleton, context-mixing coded
+///
+/// Column indices in the placeholder map count up per column type, so only
+/// the types are coded; a map that doesn't follow that order stays in the
+/// payload (`placeholder_count` 0). Likewise the skeleton is coded as bytes
+/// with [`SKELETON_REF`] for each placeholder when its refs count up from 0.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EntropyStreams {
+    placeholder_count: u32,
+    placeholder_types: Vec<u8>,
+    log_level_count: u32,
+    log_levels: Vec<u8>,
+    skeleton_len: u64,
+    skeleton: Vec<u8>,
+}
+
+/// Placeholder marker in the coded skeleton (never occurs in UTF-8)
+const SKELETON_REF: u8 = 0xFF;
+
+impl EntropyStreams {
+    /// Move the streams out of `payload` and code them
+    fn take(payload: &mut ColumnarPayload, mode: CompressionMode) -> Self {
+        let mut streams = Self::default();
+
+        if mode == CompressionMode::Max {
+            if let Some(bytes) = skeleton_bytes(&payload.skeleton_tokens) {
+                payload.skeleton_tokens.clear();
+                streams.skeleton_len = bytes.len() as u64;
+                streams.skeleton = context_mixing::encode(&bytes, ContextMixer::DEFAULT_ORDER);
+            }
+        }
+
+        let mut next = [0u32; 256];
+        let sequential = payload.placeholder_map.iter().all(|&(col_type, col_idx)| {
+            let expected = &mut next[col_type as usize];
+            *expected += 1;
+            *expected - 1 == col_idx
+        });
+        if sequential {
+            let types: Vec<u8> = std::mem::take(&mut payload.placeholder_map)
+                .into_iter()
+                .map(|(col_type, _)| col_type)
+                .collect();
+            streams.placeholder_count = types.len() as u32;
+            streams.placeholder_types = encode_order1(&types);
+        }
+
+        let levels = std::mem::take(&mut payload.log_levels);
+        streams.log_level_count = levels.len() as u32;
+        streams.log_levels = encode_order1(&levels);
+        streams
+    }
+
+    /// Decode the streams back into `payload`, holding the declared counts
+    /// to `limits`
+    fn restore(&self, payload: &mut ColumnarPayload, limits: DecodeLimits) -> Result<()> {
+        let corrupt = |what: &str| {
+            ALICETextError::DecompressionError(format!("Entropy-coded {what} truncated"))
+        };
+        limits.check_total(
+            u64::from(self.placeholder_count)
+                .saturating_add(u64::from(self.log_level_count))
+                .saturating_add(self.skeleton_len),
+        )?;
+        if self.placeholder_count > 0 {
+            let types = decode_order1(&self.placeholder_types, self.placeholder_count as usize)
+                .ok_or_else(|| corrupt("placeholder map"))?;
+            let mut next = [0u32; 256];
+            payload.placeholder_map = types
+                .into_iter()
+                .map(|col_type| {
+                    let col_idx = &mut next[col_type as usize];
+                    *col_idx += 1;
+                    (col_type, *col_idx - 1)
+                })
+                .collect();
+        }
+        payload.log_levels = decode_order1(&self.log_levels, self.log_level_count as usize)
+            .ok_or_else(|| corrupt("log levels"))?;
+        if self.skeleton_len > 0 {
+            let bytes = context_mixing::decode(&self.skeleton, self.skeleton_len as usize)
+                .ok_or_else(|| corrupt("skeleton"))?;
+            payload.skeleton_tokens = skeleton_tokens(&bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Skeleton as bytes with [`SKELETON_REF`] per placeholder (`None` unless refs count up from 0)
+fn skeleton_bytes(tokens: &[SkeletonToken]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut next = 0;
+    for token in tokens {
+        match token {
+            SkeletonToken::Text(text) => bytes.extend_from_slice(text.as_bytes()),
+            SkeletonToken::Ref(idx) if *idx == next => {
+                bytes.push(SKELETON_REF);
+                next += 1;
+            }
+            SkeletonToken::Ref(_) => return None,
+        }
+    }
+    Some(bytes)
+}
+
+/// Inverse of [`skeleton_bytes`]
+fn skeleton_tokens(bytes: &[u8]) -> Result<Vec<SkeletonToken>> {
+    let mut tokens = Vec::new();
+    let mut next = 0;
+    for (i, text) in bytes.split(|&b| b == SKELETON_REF).enumerate() {
+        if i > 0 {
+            tokens.push(SkeletonToken::Ref(next));
+            next += 1;
+        }
+        if !text.is_empty() {
+            tokens.push(SkeletonToken::Text(utf8(text.to_vec())?));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Zstd at the level's zstd level
+pub(crate) fn zstd_encode(data: &[u8], level: Level) -> Result<Vec<u8>> {
+    zstd::stream::encode_all(data, level.zstd_level())
+        .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")))
+}
+
+fn utf8(bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes)
+        .map_err(|e| ALICETextError::DecompressionError(format!("UTF-8 error: {e}")))
+}
+
+/// Unique lines (first-occurrence order) plus one index per line, Zstd-compressed
+pub(crate) fn encode_dedup(block: &str, level: Level) -> Result<Vec<u8>> {
+    zstd_encode(&dedup_table(block)?, level)
+}
+
+/// Unique lines (first-occurrence order) plus one index per line, Bincode-serialized
+fn dedup_table(block: &str) -> Result<Vec<u8>> {
+    let mut unique: Vec<&str> = Vec::new();
+    let mut ids: HashMap<&str, u32> = HashMap::new();
+    let indices: Vec<u32> = block
+        .split_inclusive('\n')
+        .map(|line| {
+            *ids.entry(line).or_insert_with(|| {
+                unique.push(line);
+                (unique.len() - 1) as u32
+            })
+        })
+        .collect();
+    bincode::serialize(&(unique, indices))
+        .map_err(|e| ALICETextError::EncodingError(format!("Bincode error: {e}")))
+}
+
+/// Inverse of [`encode_dedup`]; the expanded text may be at most `max_bytes`
+/// (a few indices to one long line expand far beyond the frame)
+fn decode_dedup(data: &[u8], window_log: u8, max_bytes: u64) -> Result<String> {
+    let (unique, indices): (Vec<String>, Vec<u32>) =
+        bincode::deserialize(&zstd_params::decompress(data, &[], window_log, max_bytes)?)
+            .map_err(|e| ALICETextError::DecompressionError(format!("Bincode error: {e}")))?;
+    let lines = indices
+        .into_iter()
+        .map(|i| {
+            unique.get(i as usize).map(String::as_str).ok_or_else(|| {
+                ALICETextError::DecompressionError(format!("Dedup index out of range: {i}"))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let size: u64 = lines.iter().map(|line| line.len() as u64).sum();
+    if size > max_bytes {
+        return Err(ALICETextError::LimitExceeded {
+            field: "decoded dedup block",
+            value: size,
+            limit: max_bytes,
+        });
+    }
+    Ok(lines.concat())
+}
+
+/// Line-aligned blocks of at least `block_size` bytes (the last may be shorter)
+fn split_blocks(text: &str, block_size: usize) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = (start + block_size).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+        end = text[end..].find('\n').map_or(text.len(), |nl| end + nl + 1);
+        blocks.push(&text[start..end]);
+        start = end;
+    }
+    blocks
+}
+
+/// (strategy, body) pairs of a routed container plus the raw-segment trailer
+type RoutedBlocks<'a> = (Vec<(BlockStrategy, &'a [u8])>, &'a [u8]);
+
+/// Parse a routed container into (strategy, body) pairs and the trailer
+fn split_routed(body: &[u8]) -> Result<RoutedBlocks<'_>> {
+    let corrupt = |what: &str| ALICETextError::DecompressionError(format!("Routed block {what}"));
+    let (count, mut rest) = body
+        .split_first_chunk::<4>()
+        .ok_or_else(|| corrupt("table truncated"))?;
+    let count = u32::from_le_bytes(*count) as usize;
+    let mut blocks = Vec::with_capacity(count.min(rest.len() / 5));
+    for _ in 0..count {
+        let (&tag, after) = rest
+            .split_first()
+            .ok_or_else(|| corrupt("table truncated"))?;
+        let strategy = BlockStrategy::from_u8(tag).ok_or_else(|| corrupt("strategy unknown"))?;
+        let (len, after) = after
+            .split_first_chunk::<4>()
+            .ok_or_else(|| corrupt("table truncated"))?;
+        let (data, after) = after
+            .split_at_checked(u32::from_le_bytes(*len) as usize)
+            .ok_or_else(|| corrupt("body truncated"))?;
+        blocks.push((strategy, data));
+        rest = after;
+    }
+    Ok((blocks, rest))
+}
+
 /// Convenience function to compress with tuned compressor
 ///
 /// # Errors
 ///
 /// Returns an error if Bincode serialization or Zstd compression fails.
-pub fn compress_tuned(text: &str, mode: CompressionMode) -> Result<Vec<u8>> {
-    let mut compressor = TunedCompressor::new(mode);
+pub fn compress_tuned(text: &str, level: impl Into<Level>) -> Result<Vec<u8>> {
+    let mut compressor = TunedCompressor::new(level);
     compressor.compress(text)
 }
 
@@ -415,12 +1499,13 @@ mod tests {
     #[test]
     fn test_header_parsing() {
         let mut compressor = TunedCompressor::default();
-        let text = "2024-01-15 INFO Test 192.168.1.1";
+        let text = "2024-01-15 INFO Test 192.168.1.1\n".repeat(20);
 
-        let compressed = compressor.compress(text).unwrap();
+        let compressed = compressor.compress(&text).unwrap();
         let header = compressor.read_header(&compressed).unwrap();
 
         assert_eq!(header.original_length, text.len() as u64);
+        assert!(!header.is_stored());
         assert!(header.pattern_count > 0);
     }
 
@@ -491,12 +1576,456 @@ mod tests {
             mode: CompressionMode::Best,
             pattern_count: 42,
             skeleton_length: 999,
+            flags: TunedHeader::FLAG_STORED,
+            config_size: 77,
+            window_log: 29,
         };
         let bytes = header.to_bytes();
         let restored = TunedHeader::from_bytes(&bytes).unwrap();
         assert_eq!(restored.original_length, 123_456_789);
         assert_eq!(restored.pattern_count, 42);
         assert_eq!(restored.skeleton_length, 999);
+        assert_eq!(restored.config_size, 77);
+        assert_eq!(restored.window_log, 29);
+        assert!(restored.is_stored());
+    }
+
+    #[test]
+    fn test_zstd_params_roundtrip() {
+        let text = "2024-01-15 10:30:45 INFO request from 10.0.0.1 took 12ms\n".repeat(200);
+        let zstd = crate::ZstdParams::new()
+            .with_long_distance_matching(true)
+            .with_window_log(Some(28))
+            .with_workers(if cfg!(feature = "zstdmt") { 2 } else { 0 });
+        for routing in [None, Some(1024)] {
+            let mut compressor = TunedCompressor::new(CompressionMode::Balanced).with_options(
+                CompressOptions::new()
+                    .with_zstd(zstd)
+                    .with_verify_roundtrip(true),
+            );
+            if let Some(block_size) = routing {
+                compressor = compressor.with_block_routing(block_size);
+            }
+            let compressed = compressor.compress(&text).unwrap();
+            assert_eq!(compressor.read_header(&compressed).unwrap().window_log, 28);
+            assert_eq!(decompress_tuned(&compressed).unwrap(), text);
+        }
+
+        // A window this build cannot open is refused before decoding
+        let mut compressed = compress_tuned(&text, Level::Balanced).unwrap();
+        compressed[10 + 10] = 40;
+        assert!(decompress_tuned(&compressed).is_err());
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let text = "x".repeat(1 << 22);
+        let small = DecodeLimits::new().with_max_total_bytes(1 << 20);
+        for routing in [None, Some(1 << 20)] {
+            let mut compressor = TunedCompressor::new(CompressionMode::Fast);
+            if let Some(block_size) = routing {
+                compressor = compressor.with_block_routing(block_size);
+            }
+            let compressed = compressor.compress(&text).unwrap();
+            assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+            let limited = compressor.with_decode_limits(small);
+            assert!(matches!(
+                limited.decompress(&compressed),
+                Err(ALICETextError::LimitExceeded { .. })
+            ));
+        }
+
+        // Dedup indices repeating one long line
+        let line = format!("{}\n", "y".repeat(1 << 16));
+        let data = encode_dedup(&line.repeat(64), Level::Fast).unwrap();
+        assert_eq!(decode_dedup(&data, 0, u64::MAX).unwrap().len(), 65_537 * 64);
+        assert!(decode_dedup(&data, 0, 1 << 20).is_err());
+
+        // Line frames are bounded the same way
+        let compressor = TunedCompressor::default();
+        let batch = compressor.compress_lines([text.as_str()]).unwrap();
+        assert_eq!(compressor.decompress_line(&batch.frames[0]).unwrap(), text);
+        assert!(matches!(
+            compressor
+                .with_decode_limits(small)
+                .decompress_line(&batch.frames[0]),
+            Err(ALICETextError::LimitExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pattern_config_recorded() {
+        let text = "release 1.2.3.4.5 build a1b2c3 from 10.0.0.1 took 42 ms\n".repeat(20);
+        let mut compressor = TunedCompressor::default()
+            .with_pattern_config(PatternConfig::strict())
+            .unwrap();
+
+        let compressed = compressor.compress(&text).unwrap();
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+        assert_eq!(
+            TunedCompressor::read_pattern_config(&compressed).unwrap(),
+            PatternConfig::strict()
+        );
+
+        // Any decoder handles it; default archives carry no config
+        let plain = TunedCompressor::default().compress(&text).unwrap();
+        assert_eq!(
+            TunedCompressor::default().decompress(&compressed).unwrap(),
+            text
+        );
+        assert!(TunedCompressor::read_pattern_config(&plain)
+            .unwrap()
+            .is_default());
+    }
+
+    #[test]
+    fn test_epoch_section_roundtrip() {
+        let text: String = (0..50)
+            .map(|i| format!("[{:5}.{:06}] tick at {}\n", i, i * 37, 1_705_312_245 + i))
+            .collect();
+        let mut compressor = TunedCompressor::default();
+        let compressed = compressor.compress(&text).unwrap();
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+        assert_eq!((compressed[8], compressed[9]), TUNED_VERSION);
+
+        // What a 2.0 reader does: Zstd, then a bare Bincode payload
+        let body = zstd::decode_all(&compressed[10 + TunedHeader::SIZE..]).unwrap();
+        assert!(bincode::deserialize::<ColumnarPayload>(&body).is_err());
+
+        // A newer minor is refused rather than decoded without its additions
+        let mut newer = compressed;
+        newer[9] = TUNED_VERSION.1 + 1;
+        assert!(matches!(
+            compressor.decompress(&newer),
+            Err(ALICETextError::InvalidVersion(2, _))
+        ));
+    }
+
+    #[test]
+    fn test_ansi_roundtrip_and_strip() {
+        let text = "\x1b[32mINFO\x1b[0m request 42 ok\n\x1b[31mERROR\x1b[0m request 43 failed\n"
+            .repeat(20);
+        let mut compressor = TunedCompressor::default();
+        let compressed = compressor.compress(&text).unwrap();
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+
+        let mut lossy = TunedCompressor::default().with_options(
+            CompressOptions::new()
+                .with_strip_ansi(true)
+                .with_verify_roundtrip(true),
+        );
+        let stripped = lossy.compress(&text).unwrap();
+        assert_eq!(
+            lossy.decompress(&stripped).unwrap(),
+            "INFO request 42 ok\nERROR request 43 failed\n".repeat(20)
+        );
+    }
+
+    #[test]
+    fn test_binary_safe_roundtrip() {
+        let mut input = Vec::new();
+        for i in 0..40 {
+            input.extend_from_slice(format!("2024-01-15 10:30:{i:02} INFO id=").as_bytes());
+            input.extend_from_slice(&[0xFF, 0xC0, i]);
+            input.extend_from_slice(b" from 10.0.0.1\n");
+        }
+        let mut compressor = TunedCompressor::default()
+            .with_options(CompressOptions::new().with_verify_roundtrip(true));
+        let compressed = compressor.compress_bytes(&input).unwrap();
+        assert_eq!(compressor.decompress_bytes(&compressed).unwrap(), input);
+        assert_eq!(compressor.last_stats().unwrap().original_size, input.len());
+
+        // Text view: one U+FFFD per invalid run, patterns still extracted around it
+        let text = compressor.decompress(&compressed).unwrap();
+        assert!(text.starts_with("2024-01-15 10:30:00 INFO id=\u{FFFD}\0 from 10.0.0.1\n"));
+
+        // Tiny inputs with invalid bytes can't use a stored block
+        let compressed = compressor.compress_bytes(b"x\xFFy").unwrap();
+        assert_eq!(compressor.decompress_bytes(&compressed).unwrap(), b"x\xFFy");
+    }
+
+    #[test]
+    fn test_best_mode_entropy_stage() {
+        let levels = ["INFO", "INFO", "DEBUG", "INFO", "WARN", "ERROR", "INFO"];
+        let text: String = (0..700)
+            .map(|i| {
+                format!(
+                    "2024-01-15 10:30:{:02} {} request from 10.0.0.{} took {}ms\n",
+                    i % 60,
+                    levels[i % levels.len()],
+                    i % 200,
+                    i * 7 % 400
+                )
+            })
+            .collect();
+
+        let mut compressor = TunedCompressor::new(CompressionMode::Best)
+            .with_options(CompressOptions::new().with_verify_roundtrip(true));
+        let compressed = compressor.compress(&text).unwrap();
+        assert!(compressor
+            .read_header(&compressed)
+            .unwrap()
+            .is_entropy_coded());
+        assert_eq!(
+            TunedCompressor::default().decompress(&compressed).unwrap(),
+            text
+        );
+        let balanced = TunedCompressor::default().compress(&text).unwrap();
+        assert!(!TunedCompressor::default()
+            .read_header(&balanced)
+            .unwrap()
+            .is_entropy_coded());
+
+        // The coded streams beat Zstd over the same streams in Bincode form
+        let mut payload = ColumnarEncoder::new().encode(&text);
+        let plain = bincode::serialize(&(&payload.placeholder_map, &payload.log_levels)).unwrap();
+        let zstd_size = zstd_encode(&plain, Level::Best).unwrap().len();
+        let streams = EntropyStreams::take(&mut payload, CompressionMode::Best);
+        assert!(payload.placeholder_map.is_empty() && payload.log_levels.is_empty());
+        let coded_size = bincode::serialize(&streams).unwrap().len();
+        assert!(coded_size < zstd_size, "{coded_size} vs {zstd_size}");
+
+        // Declared counts are held to the decode limits before decoding
+        let forged = EntropyStreams {
+            log_level_count: u32::MAX,
+            ..EntropyStreams::default()
+        };
+        assert!(matches!(
+            forged.restore(
+                &mut ColumnarEncoder::new().encode(""),
+                DecodeLimits::new().with_max_total_bytes(1 << 20)
+            ),
+            Err(ALICETextError::LimitExceeded { .. })
+        ));
+
+        // Routed columnar blocks use the same stage
+        let mut compressor = TunedCompressor::new(CompressionMode::Best).with_block_routing(8192);
+        let compressed = compressor.compress(&text).unwrap();
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+    }
+
+    #[test]
+    fn test_max_mode_skeleton_coding() {
+        let text: String = (0..600)
+            .map(|i| match i % 3 {
+                0 => format!(
+                    "2024-01-15 10:30:{:02} INFO GET /api/users/{i} 200\n",
+                    i % 60
+                ),
+                1 => format!("2024-01-15 10:30:{:02} DEBUG cache miss key={i}\n", i % 60),
+                _ => format!("2024-01-15 10:30:{:02} WARN slow query {i}ms\n", i % 60),
+            })
+            .collect();
+
+        let mut compressor =
+            TunedCompressor::max().with_options(CompressOptions::new().with_verify_roundtrip(true));
+        let max = compressor.compress(&text).unwrap();
+        let header = compressor.read_header(&max).unwrap();
+        assert_eq!(header.mode, CompressionMode::Max);
+        assert!(header.is_entropy_coded());
+        assert_eq!(TunedCompressor::default().decompress(&max).unwrap(), text);
+
+        let best = TunedCompressor::best().compress(&text).unwrap();
+        assert!(max.len() < best.len(), "{} vs {}", max.len(), best.len());
+
+        // Skeleton byte form: refs must count up from 0
+        let tokens = vec![
+            SkeletonToken::Ref(0),
+            SkeletonToken::Text(" a\n".into()),
+            SkeletonToken::Ref(1),
+        ];
+        let bytes = skeleton_bytes(&tokens).unwrap();
+        assert_eq!(bytes, b"\xFF a\n\xFF");
+        assert_eq!(skeleton_tokens(&bytes).unwrap(), tokens);
+        assert!(skeleton_bytes(&[SkeletonToken::Ref(1)]).is_none());
+    }
+
+    #[test]
+    fn test_block_routing_roundtrip() {
+        let mut text = String::new();
+        for i in 0..200 {
+            text.push_str(&format!(
+                "2024-01-15 10:{:02}:{:02} INFO request {i} from 10.0.{}.{} took {}ms\n",
+                i / 60,
+                i % 60,
+                i % 7,
+                i % 251,
+                i * 37 % 1000
+            ));
+        }
+        text.push_str(&"heartbeat ok\nheartbeat ok\nqueue drained\n".repeat(300));
+        // A few long random lines in random order: dedup beats plain zstd
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let records: Vec<String> = (0..8)
+            .map(|_| {
+                (0..200)
+                    .map(|_| char::from(b'!' + (next() % 94) as u8))
+                    .chain(std::iter::once('\n'))
+                    .collect()
+            })
+            .collect();
+        for _ in 0..400 {
+            text.push_str(&records[(next() % 8) as usize]);
+        }
+
+        let mut compressor = TunedCompressor::default()
+            .with_block_routing(16 * 1024)
+            .with_options(CompressOptions::new().with_verify_roundtrip(true));
+        let compressed = compressor.compress(&text).unwrap();
+        assert!(compressor.read_header(&compressed).unwrap().is_routed());
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+
+        let routing = compressor.last_stats().unwrap().routing.clone().unwrap();
+        let sizes: usize = routing.blocks.iter().map(|b| b.original_size).sum();
+        assert_eq!(sizes, text.len());
+        assert_eq!(
+            TunedCompressor::block_strategies(&compressed).unwrap(),
+            routing
+                .blocks
+                .iter()
+                .map(|b| b.strategy)
+                .collect::<Vec<_>>()
+        );
+        assert!(routing.count(BlockStrategy::RawZstd) >= 1);
+        assert!(routing.count(BlockStrategy::Dedup) >= 1);
+        assert_eq!(
+            BlockStrategy::ALL
+                .iter()
+                .map(|&s| routing.count(s))
+                .sum::<usize>(),
+            routing.blocks.len()
+        );
+
+        // Unrouted archives report no strategies
+        let plain = TunedCompressor::default().compress(&text).unwrap();
+        assert!(TunedCompressor::block_strategies(&plain)
+            .unwrap()
+            .is_empty());
+
+        // Invalid UTF-8 and CRLF survive routing
+        let mut input = text.replace('\n', "\r\n").into_bytes();
+        input.extend_from_slice(b"tail \xFF\xFE\r\n");
+        let compressed = compressor.compress_bytes(&input).unwrap();
+        assert_eq!(compressor.decompress_bytes(&compressed).unwrap(), input);
+    }
+
+    #[test]
+    fn test_compress_lines_shared_state() {
+        let records: Vec<String> = (0..500)
+            .map(|i| {
+                format!(
+                    r#"{{"ts":"2024-01-15T10:{:02}:{:02}Z","level":"INFO","service":"checkout","order":{},"status":"paid"}}"#,
+                    i / 60 % 60,
+                    i % 60,
+                    100_000 + i * 7
+                )
+            })
+            .collect();
+        let writer = TunedCompressor::default();
+        let batch = writer
+            .compress_lines(records.iter().map(String::as_str))
+            .unwrap();
+        assert_eq!(batch.frames.len(), records.len());
+
+        // The shared dictionary beats compressing each record on its own
+        let independent: usize = records
+            .iter()
+            .map(|r| zstd_encode(r.as_bytes(), Level::Balanced).unwrap().len())
+            .sum();
+        assert!(
+            batch.frames_size() * 2 < independent,
+            "{} vs {independent}",
+            batch.frames_size()
+        );
+
+        // Any single record decodes with the state alone
+        let reader = TunedCompressor::default()
+            .with_model(&batch.model().unwrap())
+            .unwrap();
+        for i in [499, 0, 250] {
+            assert_eq!(
+                reader.decompress_line(&batch.frames[i]).unwrap(),
+                records[i]
+            );
+        }
+        assert!(TunedCompressor::default()
+            .decompress_line(&batch.frames[0])
+            .is_err());
+
+        // A compressor with the model loaded reuses its dictionary
+        let reuse = reader.compress_lines(["late record"]).unwrap();
+        assert_eq!(
+            reuse.model().unwrap().dictionary,
+            batch.model().unwrap().dictionary
+        );
+        assert_eq!(
+            reader.decompress_line(&reuse.frames[0]).unwrap(),
+            "late record"
+        );
+
+        // Too small to train a dictionary: frames still decode
+        let tiny = writer.compress_lines(["a", "", "b\nc"]).unwrap();
+        assert!(!tiny.model().unwrap().has_dictionary());
+        assert_eq!(writer.decompress_line(&tiny.frames[1]).unwrap(), "");
+        assert_eq!(writer.decompress_line(&tiny.frames[2]).unwrap(), "b\nc");
+    }
+
+    #[test]
+    fn test_windows_log_roundtrip() {
+        let text =
+            "2024-01-15 10:30:45 ERROR C:\\Windows\\System32\\svchost.exe stopped\r\n".repeat(30);
+        let mut compressor = TunedCompressor::default()
+            .with_options(CompressOptions::new().with_verify_roundtrip(true));
+        let compressed = compressor.compress(&text).unwrap();
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+        let header = TunedHeader::from_bytes(&compressed[10..]).unwrap();
+        assert_eq!(
+            header.flags & TunedHeader::FLAG_CRLF,
+            TunedHeader::FLAG_CRLF
+        );
+        assert_eq!(header.original_length as usize, text.len());
+        // 2.0 readers ignore the flags: the 2.1 payload makes them fail instead
+        // of returning LF-only text
+        let body = zstd::decode_all(&compressed[10 + TunedHeader::SIZE..]).unwrap();
+        assert!(bincode::deserialize::<ColumnarPayload>(&body).is_err());
+        // Flags this build does not know are refused, not ignored
+        let mut unknown = compressed.clone();
+        unknown[10 + 9] |= 0x80;
+        assert!(compressor.decompress(&unknown).is_err());
+
+        // UTF-16LE with BOM: bytes round-trip exactly, text decodes to UTF-8
+        let mut utf16 = crate::UTF16LE_BOM.to_vec();
+        text.encode_utf16()
+            .for_each(|u| utf16.extend_from_slice(&u.to_le_bytes()));
+        let compressed = compressor.compress_bytes(&utf16).unwrap();
+        assert!(compressed.len() < text.len());
+        assert_eq!(compressor.decompress_bytes(&compressed).unwrap(), utf16);
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+    }
+
+    #[test]
+    fn test_small_input_stored() {
+        let mut compressor = TunedCompressor::default();
+        let text = "short message, 30 bytes long!!";
+
+        let compressed = compressor.compress(text).unwrap();
+        assert_eq!(compressed.len(), 8 + 2 + TunedHeader::SIZE + text.len());
+        assert!(compressor.read_header(&compressed).unwrap().is_stored());
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+
+        // Incompressible input above the threshold also falls back to stored
+        let noise: String = (0..200u32)
+            .map(|i| char::from(b'!' + (i.wrapping_mul(2_654_435_761) >> 26) as u8))
+            .collect();
+        let compressed = compressor.compress(&noise).unwrap();
+        assert!(compressed.len() <= 8 + 2 + TunedHeader::SIZE + noise.len());
+        assert_eq!(compressor.decompress(&compressed).unwrap(), noise);
     }
 
     #[test]
@@ -585,5 +2114,53 @@ mod tests {
         assert_eq!(stats.original_size, text.len());
         assert!(stats.compressed_size > 0);
         assert!(stats.compression_ratio > 0.0);
+        // Stored block: nothing went into value columns
+        assert_eq!((stats.value_bytes, stats.exception_rate), (0, 0.0));
+    }
+
+    #[test]
+    fn test_exception_rate() {
+        let line = "2024-01-15 10:30:45 INFO login ok from 192.168.1.100\n";
+        let text = line.repeat(50);
+        let literal = "  login ok from \n".len() * 50;
+
+        let mut compressor = TunedCompressor::default();
+        compressor.compress(&text).unwrap();
+        let stats = compressor.last_stats().unwrap();
+        assert_eq!(stats.value_bytes, text.len() - literal);
+        let rate = stats.exception_rate;
+        assert!((rate - stats.value_bytes as f64 / text.len() as f64).abs() < 1e-12);
+
+        // Routed: only blocks that went columnar contribute
+        let mut routed = TunedCompressor::default().with_block_routing(DEFAULT_ROUTING_BLOCK);
+        routed.compress(&text).unwrap();
+        assert!(routed.last_stats().unwrap().value_bytes <= stats.value_bytes);
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        let text = "2024-01-15 10:30:45 INFO login ok from 192.168.1.100\n".repeat(50);
+        let mut exclusive = TunedCompressor::default();
+        let expected = exclusive.compress(&text).unwrap();
+
+        // One compressor behind an Arc, no lock
+        let shared = std::sync::Arc::new(TunedCompressor::default());
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let shared = std::sync::Arc::clone(&shared);
+                let (text, expected) = (&text, &expected);
+                scope.spawn(move || {
+                    let (compressed, stats) = shared.compress_with_stats(text).unwrap();
+                    assert_eq!(&compressed, expected);
+                    assert_eq!(stats.compressed_size, compressed.len());
+                    assert_eq!(shared.decompress(&compressed).unwrap(), *text);
+                });
+            }
+        });
+        assert!(shared.last_stats().is_none());
+        assert_eq!(
+            exclusive.last_stats().unwrap().compressed_size,
+            expected.len()
+        );
     }
 }
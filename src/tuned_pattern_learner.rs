@@ -1,12 +1,27 @@
 //! Tuned Pattern Learner - Fused Regex Implementation
 //!
 //! Uses a single combined regex for O(N) pattern extraction instead of O(N×M).
+//! The default regex is compiled once per process and shared by every learner;
+//! a [`PatternConfig`] (priority, disabled patterns, context guards, exclusion
+//! rules) compiles its own.
+//!
+//! With the `dfa` feature the alternation runs on a multi-pattern lazy DFA
+//! (`regex-automata` hybrid) instead: states are built on demand, so
+//! construction skips most of the up-front compile, and every search is
+//! linear in the input. Haystacks the DFA gives up on (`\b` next to
+//! non-ASCII text) are rerun on the capturing regex, compiled on first need.
 
+use crate::columnar_encoder::{ColumnarPayload, SkeletonToken};
+use crate::coverage::Coverage;
+use crate::kubernetes::KUBERNETES_PATTERNS;
+use crate::{ALICETextError, Result};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, OnceLock};
 
 /// Pattern types (same as original, but optimized for u8 storage)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -25,6 +40,40 @@ pub enum PatternType {
     Hex = 10,
     Email = 11,
     Custom = 12,
+    /// Unix epoch seconds/millis (`1705312245`, `1705312245123`)
+    Epoch = 13,
+    /// Bracketed uptime seconds, dmesg style (`[ 1234.5678]`)
+    Uptime = 14,
+    /// ANSI escape sequence (`\x1b[31m`)
+    Ansi = 15,
+    /// MAC address (`aa:bb:cc:dd:ee:ff`, `AA-BB-CC-DD-EE-FF`)
+    Mac = 16,
+    /// Latitude/longitude pair (`35.6895, 139.6917`)
+    Geo = 17,
+    /// Kubernetes pod name with its random suffix (`web-7fb96c846b-x5n2k`)
+    Pod = 18,
+    /// Kubernetes namespace after its key (`namespace=kube-system`)
+    Namespace = 19,
+    /// 64-hex-digit container id (`containerd://3f4e...`)
+    ContainerId = 20,
+    /// klog header (`I0115 10:30:45.123456   12345 main.go:42]`)
+    Klog = 21,
+    /// CRI log line prefix (`2024-01-15T10:30:45.123456789Z stdout F `)
+    Cri = 22,
+    /// HTTP request method in a request line or `method` field (`"GET /`)
+    HttpMethod = 23,
+    /// HTTP status code after a request line or `status` key (`HTTP/1.1" 404`)
+    HttpStatus = 24,
+    /// Duration / latency with its unit (`123ms`, `4.5s`, `2m30s`)
+    Duration = 25,
+    /// Byte size with its unit (`300KB`, `1.5GiB`, `512B`)
+    ByteSize = 26,
+    /// Hostname / FQDN with at least three labels (`web-01.prod.example.com`)
+    Hostname = 27,
+    /// Process id after a `pid` key or in `prog[1234]:` (syslog)
+    Pid = 28,
+    /// Thread id after a `tid` / `thread_id` key
+    Tid = 29,
 }
 
 impl PatternType {
@@ -52,6 +101,23 @@ impl PatternType {
             9 => Self::Number,
             10 => Self::Hex,
             11 => Self::Email,
+            13 => Self::Epoch,
+            14 => Self::Uptime,
+            15 => Self::Ansi,
+            16 => Self::Mac,
+            17 => Self::Geo,
+            18 => Self::Pod,
+            19 => Self::Namespace,
+            20 => Self::ContainerId,
+            21 => Self::Klog,
+            22 => Self::Cri,
+            23 => Self::HttpMethod,
+            24 => Self::HttpStatus,
+            25 => Self::Duration,
+            26 => Self::ByteSize,
+            27 => Self::Hostname,
+            28 => Self::Pid,
+            29 => Self::Tid,
             _ => Self::Custom,
         }
     }
@@ -93,8 +159,21 @@ struct PatternDef {
     pattern_type: PatternType,
 }
 
+/// Built-in pattern `(name, regex)` pairs in priority order
+pub(crate) fn builtin_patterns() -> impl Iterator<Item = (&'static str, &'static str)> {
+    PATTERNS.iter().map(|def| (def.name, def.pattern))
+}
+
+/// ANSI escape sequences: CSI (`ESC [ ... m`), OSC (`ESC ] ... BEL`) and two-byte escapes
+const ANSI_PATTERN: &str = r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[@-_])";
+
 /// All pattern definitions (ordered by priority - most specific first)
 const PATTERNS: &[PatternDef] = &[
+    PatternDef {
+        name: "ANSI",
+        pattern: ANSI_PATTERN,
+        pattern_type: PatternType::Ansi,
+    },
     PatternDef {
         name: "TIMESTAMP",
         pattern: r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:?\d{2})?",
@@ -115,16 +194,50 @@ const PATTERNS: &[PatternDef] = &[
         pattern: r#"https?://[^\s<>"']+"#,
         pattern_type: PatternType::URL,
     },
+    // Three labels minimum and a lower-case alphabetic TLD, so `app.log`,
+    // `1.2.3` and `com.example.Foo` stay out. Labels are kept loose (a
+    // trailing `-` is allowed): the RFC form nests a group under `{2,}` and
+    // nearly doubles the fused regex's build time.
+    PatternDef {
+        name: "HOSTNAME",
+        pattern: r"(?-u:\b)(?:[A-Za-z0-9][A-Za-z0-9-]*\.){2,}[a-z]+(?-u:\b)",
+        pattern_type: PatternType::Hostname,
+    },
     PatternDef {
         name: "IPV6",
         pattern: r"(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}",
         pattern_type: PatternType::IPv6,
     },
+    PatternDef {
+        name: "MAC",
+        pattern: r"\b[0-9a-fA-F]{2}(?::[0-9a-fA-F]{2}){5}\b|\b[0-9a-fA-F]{2}(?:-[0-9a-fA-F]{2}){5}\b",
+        pattern_type: PatternType::Mac,
+    },
     PatternDef {
         name: "IPV4",
         pattern: r"(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)",
         pattern_type: PatternType::IPv4,
     },
+    PatternDef {
+        name: "HTTPMETHOD",
+        pattern: r#""(?:GET|HEAD|POST|PUT|DELETE|CONNECT|OPTIONS|TRACE|PATCH) |"method": ?"(?:GET|HEAD|POST|PUT|DELETE|CONNECT|OPTIONS|TRACE|PATCH)"|\bmethod=(?:GET|HEAD|POST|PUT|DELETE|CONNECT|OPTIONS|TRACE|PATCH)\b"#,
+        pattern_type: PatternType::HttpMethod,
+    },
+    PatternDef {
+        name: "HTTPSTATUS",
+        pattern: r#"HTTP/\d(?:\.\d)?" [1-5]\d{2}\b|"(?:status|status_code)": ?[1-5]\d{2}\b|\b(?:status|status_code)[=:] ?[1-5]\d{2}\b"#,
+        pattern_type: PatternType::HttpStatus,
+    },
+    PatternDef {
+        name: "PID",
+        pattern: r#"(?-u:\b)pid[=: ] ?\d+(?-u:\b)|"pid": ?\d+|[A-Za-z0-9_-]\[\d+\]:"#,
+        pattern_type: PatternType::Pid,
+    },
+    PatternDef {
+        name: "TID",
+        pattern: r#"(?-u:\b)(?:tid|thread_id)[=: ] ?\d+(?-u:\b)|"(?:tid|thread_id)": ?\d+"#,
+        pattern_type: PatternType::Tid,
+    },
     PatternDef {
         name: "DATE",
         pattern: r"\d{4}-\d{2}-\d{2}",
@@ -137,7 +250,7 @@ const PATTERNS: &[PatternDef] = &[
     },
     PatternDef {
         name: "PATH",
-        pattern: r"(?:/[a-zA-Z0-9._-]+)+/?",
+        pattern: r"(?:/[a-zA-Z0-9._-]+)+/?|\b[A-Za-z]:\\(?:[a-zA-Z0-9._$~-]+\\?)*|\\\\[a-zA-Z0-9._$-]+(?:\\[a-zA-Z0-9._$~-]+)+",
         pattern_type: PatternType::Path,
     },
     PatternDef {
@@ -150,6 +263,33 @@ const PATTERNS: &[PatternDef] = &[
         pattern: r"(?:DEBUG|INFO|WARN(?:ING)?|ERROR|FATAL|TRACE|CRITICAL)",
         pattern_type: PatternType::LogLevel,
     },
+    PatternDef {
+        name: "GEO",
+        pattern: r"-?(?:90|[0-8]?\d)\.\d{4,7}, ?-?(?:180|1[0-7]\d|\d?\d)\.\d{4,7}\b",
+        pattern_type: PatternType::Geo,
+    },
+    // ASCII word boundaries: a Unicode `\b` here pushes the fused regex off
+    // its lazy DFA and roughly doubles scan time
+    PatternDef {
+        name: "DURATION",
+        pattern: r"(?-u:\b)(?:\d+(?:\.\d+)?(?:ns|us|\x{b5}s|ms|s|m|h))+(?-u:\b)",
+        pattern_type: PatternType::Duration,
+    },
+    PatternDef {
+        name: "BYTESIZE",
+        pattern: r"(?-u:\b)\d+(?:\.\d+)?(?:[KMGTP]i?B|kB|B)(?-u:\b)",
+        pattern_type: PatternType::ByteSize,
+    },
+    PatternDef {
+        name: "UPTIME",
+        pattern: r"\[ *\d+\.\d{1,9}\]",
+        pattern_type: PatternType::Uptime,
+    },
+    PatternDef {
+        name: "EPOCH",
+        pattern: r"\b1\d{9}(?:\d{3})?(?:\.\d{1,6})?\b",
+        pattern_type: PatternType::Epoch,
+    },
     PatternDef {
         name: "NUMBER",
         pattern: r"\d+(?:\.\d+)?",
@@ -157,138 +297,666 @@ const PATTERNS: &[PatternDef] = &[
     },
 ];
 
+/// Opt-in pattern definitions, only used when listed in [`PatternConfig::priority`]
+/// (see [`crate::kubernetes`])
+const OPT_IN_PATTERNS: &[PatternDef] = &[
+    PatternDef {
+        name: "CRI",
+        pattern: r"\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d{1,9})?Z (?:stdout|stderr) [FP] ",
+        pattern_type: PatternType::Cri,
+    },
+    PatternDef {
+        name: "KLOG",
+        pattern: r"\b[IWEF]\d{4} \d{2}:\d{2}:\d{2}\.\d{6} +\d+ [A-Za-z0-9_.-]+:\d+\]",
+        pattern_type: PatternType::Klog,
+    },
+    PatternDef {
+        name: "CONTAINERID",
+        pattern: r"(?:(?:containerd|docker|cri-o)://)?\b[0-9a-f]{64}\b",
+        pattern_type: PatternType::ContainerId,
+    },
+    PatternDef {
+        name: "NAMESPACE",
+        pattern: r#""namespace": ?"[a-z0-9](?:[-a-z0-9]*[a-z0-9])?"|\bnamespace(?:=|: ?)[a-z0-9](?:[-a-z0-9]*[a-z0-9])?\b"#,
+        pattern_type: PatternType::Namespace,
+    },
+    PatternDef {
+        name: "POD",
+        pattern: r"\b[a-z0-9](?:[-a-z0-9]*[a-z0-9])?-[bcdfghjklmnpqrstvwxz2456789]{5}\b",
+        pattern_type: PatternType::Pod,
+    },
+];
+
+/// Reject a match when the character just before/after it is in a set
+///
+/// Stands in for lookaround, which the `regex` crate does not support:
+/// e.g. IPv4 not followed by `.` keeps `1.2.3.4.5` out of the IPv4 column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternGuard {
+    pub pattern_type: PatternType,
+    /// Characters that must not immediately precede the match
+    pub not_preceded_by: String,
+    /// Characters that must not immediately follow the match
+    pub not_followed_by: String,
+}
+
+/// Reject matches of a pattern whose text matches a regex
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatternExclusion {
+    pub pattern_type: PatternType,
+    pub regex: String,
+}
+
+/// Pattern extraction configuration
+///
+/// Rejected matches stay in the skeleton as literal text, so any
+/// configuration decodes losslessly; the effective config is stored in the
+/// archive so re-encoding (retention, compaction) can reuse it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatternConfig {
+    /// Patterns tried first, in order (unlisted patterns follow in default order)
+    ///
+    /// Opt-in patterns (the Kubernetes set) are only tried when listed here.
+    pub priority: Vec<PatternType>,
+    /// Patterns never extracted
+    pub disabled: Vec<PatternType>,
+    pub guards: Vec<PatternGuard>,
+    pub exclusions: Vec<PatternExclusion>,
+}
+
+impl PatternConfig {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Guards for common misclassifications: dotted version strings
+    /// (`1.2.3.4.5`, `v10.2.3.4`) as IPv4, and digits inside bare hex ids
+    /// or identifiers (`a1b2c3`, `build42`) as numbers
+    #[must_use]
+    pub fn strict() -> Self {
+        Self::new()
+            .with_guard(PatternType::IPv4, ".vV", ".")
+            .with_guard(
+                PatternType::Number,
+                "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_.",
+                "_",
+            )
+    }
+
+    /// Kubernetes / container log preset: [`Self::with_kubernetes`] on the defaults
+    #[must_use]
+    pub fn kubernetes() -> Self {
+        Self::new().with_kubernetes()
+    }
+
+    /// Prose / dialogue preset: numbers, hex, durations and byte sizes stay
+    /// in the text, where a few scattered values compress better than columns
+    #[must_use]
+    pub fn prose() -> Self {
+        Self::new()
+            .disable(PatternType::Number)
+            .disable(PatternType::Hex)
+            .disable(PatternType::Duration)
+            .disable(PatternType::ByteSize)
+    }
+
+    /// Try the Kubernetes patterns (CRI prefix, klog header, container id,
+    /// namespace, pod name) before all others
+    #[must_use]
+    pub fn with_kubernetes(mut self) -> Self {
+        let mut priority = KUBERNETES_PATTERNS.to_vec();
+        priority.extend(
+            self.priority
+                .into_iter()
+                .filter(|p| !KUBERNETES_PATTERNS.contains(p)),
+        );
+        self.priority = priority;
+        self
+    }
+
+    /// Whether this is the built-in behaviour
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Set the priority order
+    #[must_use]
+    pub fn with_priority(mut self, priority: Vec<PatternType>) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Disable a pattern
+    #[must_use]
+    pub fn disable(mut self, pattern_type: PatternType) -> Self {
+        self.disabled.push(pattern_type);
+        self
+    }
+
+    /// Add a context guard
+    #[must_use]
+    pub fn with_guard(
+        mut self,
+        pattern_type: PatternType,
+        not_preceded_by: &str,
+        not_followed_by: &str,
+    ) -> Self {
+        self.guards.push(PatternGuard {
+            pattern_type,
+            not_preceded_by: not_preceded_by.to_string(),
+            not_followed_by: not_followed_by.to_string(),
+        });
+        self
+    }
+
+    /// Add an exclusion rule (regex is validated when the learner is built)
+    #[must_use]
+    pub fn exclude(mut self, pattern_type: PatternType, regex: &str) -> Self {
+        self.exclusions.push(PatternExclusion {
+            pattern_type,
+            regex: regex.to_string(),
+        });
+        self
+    }
+}
+
+/// Default [`PatternLimits::max_line_len`] (1 MiB)
+pub const DEFAULT_MAX_LINE_LEN: usize = 1 << 20;
+
+/// Guards against pathological input
+///
+/// Lines over the length limit and (with `skip_binary`) lines containing a
+/// NUL byte skip pattern extraction and stay in the skeleton as literal
+/// text, so one multi-megabyte or binary line cannot stall ingestion.
+/// Encoder-side only: the archive decodes the same either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatternLimits {
+    /// Longest line (bytes, excluding the newline) patterns are extracted from
+    pub max_line_len: Option<usize>,
+    /// Skip lines containing NUL bytes
+    pub skip_binary: bool,
+}
+
+impl PatternLimits {
+    /// Default limits: 1 MiB lines, binary lines skipped
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_line_len: Some(DEFAULT_MAX_LINE_LEN),
+            skip_binary: true,
+        }
+    }
+
+    /// No limits: extract patterns from every line
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_line_len: None,
+            skip_binary: false,
+        }
+    }
+
+    /// Set the line length limit (`None` for no limit)
+    #[must_use]
+    pub const fn with_max_line_len(mut self, max_line_len: Option<usize>) -> Self {
+        self.max_line_len = max_line_len;
+        self
+    }
+
+    /// Enable or disable skipping binary lines
+    #[must_use]
+    pub const fn with_skip_binary(mut self, skip_binary: bool) -> Self {
+        self.skip_binary = skip_binary;
+        self
+    }
+
+    /// Whether patterns are extracted from `line` (without its newline)
+    #[inline]
+    #[must_use]
+    pub fn allows(&self, line: &str) -> bool {
+        self.max_line_len.is_none_or(|max| line.len() <= max)
+            && !(self.skip_binary && line.as_bytes().contains(&0))
+    }
+
+    /// Byte ranges of `text` to scan: runs of allowed lines
+    pub(crate) fn segments(&self, text: &str) -> SmallVec<[(usize, usize); 4]> {
+        let mut segments = SmallVec::new();
+        if *self == Self::unlimited() {
+            segments.push((0, text.len()));
+            return segments;
+        }
+        let (mut start, mut pos) = (0, 0);
+        for line in text.split_inclusive('\n') {
+            let end = pos + line.len();
+            if !self.allows(line.strip_suffix('\n').unwrap_or(line)) {
+                if start < pos {
+                    segments.push((start, pos));
+                }
+                start = end;
+            }
+            pos = end;
+        }
+        if start < pos {
+            segments.push((start, pos));
+        }
+        segments
+    }
+}
+
+impl Default for PatternLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A [`PatternConfig`] with its fused and exclusion regexes compiled
+struct CompiledPatterns {
+    config: PatternConfig,
+    #[cfg(not(feature = "dfa"))]
+    fused_regex: Regex,
+    #[cfg(feature = "dfa")]
+    fused_dfa: dfa::FusedDfa,
+    /// Enabled patterns in priority order (matches the fused alternation)
+    patterns: Vec<&'static PatternDef>,
+    exclusions: Vec<(PatternType, Regex)>,
+}
+
+impl CompiledPatterns {
+    fn compile(config: PatternConfig) -> Result<Self> {
+        let mut patterns: Vec<&'static PatternDef> = Vec::with_capacity(PATTERNS.len());
+        for pattern_type in &config.priority {
+            let mut defs = PATTERNS.iter().chain(OPT_IN_PATTERNS);
+            if let Some(p) = defs.find(|p| p.pattern_type == *pattern_type) {
+                if !patterns.iter().any(|q| std::ptr::eq(*q, p)) {
+                    patterns.push(p);
+                }
+            }
+        }
+        for p in PATTERNS {
+            if !patterns.iter().any(|q| std::ptr::eq(*q, p)) {
+                patterns.push(p);
+            }
+        }
+        patterns.retain(|p| !config.disabled.contains(&p.pattern_type));
+
+        // Build fused regex: (?P<TIMESTAMP>...)|(?P<UUID>...)|...
+        // An empty alternation never matches, so everything disabled is valid.
+        let expr = if patterns.is_empty() {
+            r"[^\s\S]".to_string()
+        } else {
+            patterns
+                .iter()
+                .map(|p| format!("(?P<{}>{})", p.name, p.pattern))
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+        #[cfg(not(feature = "dfa"))]
+        let fused_regex = Regex::new(&expr)
+            .map_err(|e| ALICETextError::EncodingError(format!("Invalid fused regex: {e}")))?;
+        #[cfg(feature = "dfa")]
+        let fused_dfa = dfa::FusedDfa::new(&patterns, expr)?;
+
+        let exclusions = config
+            .exclusions
+            .iter()
+            .map(|x| {
+                Regex::new(&x.regex)
+                    .map(|re| (x.pattern_type, re))
+                    .map_err(|e| {
+                        ALICETextError::EncodingError(format!(
+                            "Invalid exclusion regex {:?}: {e}",
+                            x.regex
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            config,
+            #[cfg(not(feature = "dfa"))]
+            fused_regex,
+            #[cfg(feature = "dfa")]
+            fused_dfa,
+            patterns,
+            exclusions,
+        })
+    }
+
+    /// Call `f(pattern_index, start, end)` for each fused match, left to right
+    fn for_each_match(&self, text: &str, f: impl FnMut(usize, usize, usize)) {
+        #[cfg(not(feature = "dfa"))]
+        regex_matches(&self.fused_regex, &self.patterns, text, f);
+        #[cfg(feature = "dfa")]
+        self.fused_dfa.for_each_match(&self.patterns, text, f);
+    }
+
+    /// Whether guards and exclusions allow a match at `start..end`
+    fn accepts(&self, pattern_type: PatternType, text: &str, start: usize, end: usize) -> bool {
+        let guarded = self
+            .config
+            .guards
+            .iter()
+            .filter(|g| g.pattern_type == pattern_type)
+            .any(|g| {
+                text[..start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| g.not_preceded_by.contains(c))
+                    || text[end..]
+                        .chars()
+                        .next()
+                        .is_some_and(|c| g.not_followed_by.contains(c))
+            });
+        !guarded
+            && !self
+                .exclusions
+                .iter()
+                .any(|(t, re)| *t == pattern_type && re.is_match(&text[start..end]))
+    }
+}
+
+/// Run the capturing fused regex, reporting which alternative matched
+fn regex_matches(
+    regex: &Regex,
+    patterns: &[&'static PatternDef],
+    text: &str,
+    mut f: impl FnMut(usize, usize, usize),
+) {
+    for caps in regex.captures_iter(text) {
+        let found = patterns
+            .iter()
+            .enumerate()
+            .find_map(|(i, p)| caps.name(p.name).map(|m| (i, m)));
+        if let Some((i, m)) = found {
+            f(i, m.start(), m.end());
+        }
+    }
+}
+
+#[cfg(feature = "dfa")]
+mod dfa {
+    use super::{regex_matches, PatternDef};
+    use crate::{ALICETextError, Result};
+    use regex::Regex;
+    use regex_automata::hybrid::{self, regex::Cache};
+    use regex_automata::util::iter::Searcher;
+    use regex_automata::util::pool::Pool;
+    use regex_automata::Input;
+    use std::panic::{RefUnwindSafe, UnwindSafe};
+    use std::sync::{Arc, OnceLock};
+
+    type CacheFn = Box<dyn Fn() -> Cache + Send + Sync + UnwindSafe + RefUnwindSafe>;
+
+    /// One lazy DFA over all enabled patterns (pattern id = priority index)
+    ///
+    /// Leftmost-first across pattern ids matches the fused alternation, so
+    /// results are identical to the regex path.
+    pub(super) struct FusedDfa {
+        dfa: Arc<hybrid::regex::Regex>,
+        caches: Pool<Cache, CacheFn>,
+        /// Fused alternation source for the fallback regex
+        pub(super) expr: String,
+        fallback: OnceLock<Regex>,
+    }
+
+    impl FusedDfa {
+        pub(super) fn new(patterns: &[&'static PatternDef], expr: String) -> Result<Self> {
+            let sources: Vec<&str> = patterns.iter().map(|p| p.pattern).collect();
+            let dfa = hybrid::regex::Builder::new()
+                .dfa(hybrid::dfa::Config::new().unicode_word_boundary(true))
+                .build_many(&sources)
+                .map_err(|e| ALICETextError::EncodingError(format!("Invalid fused DFA: {e}")))?;
+            let dfa = Arc::new(dfa);
+            let for_cache = Arc::clone(&dfa);
+            let create: CacheFn = Box::new(move || for_cache.create_cache());
+            Ok(Self {
+                dfa,
+                caches: Pool::new(create),
+                expr,
+                fallback: OnceLock::new(),
+            })
+        }
+
+        pub(super) fn for_each_match(
+            &self,
+            patterns: &[&'static PatternDef],
+            text: &str,
+            mut f: impl FnMut(usize, usize, usize),
+        ) {
+            let mut spans = Vec::new();
+            let mut cache = self.caches.get();
+            let mut searcher = Searcher::new(Input::new(text));
+            loop {
+                match searcher.try_advance(|input| self.dfa.try_search(&mut cache, input)) {
+                    Ok(Some(m)) => spans.push((m.pattern().as_usize(), m.start(), m.end())),
+                    Ok(None) => break,
+                    Err(_) => {
+                        // Quit byte (Unicode word boundary): rerun the whole text
+                        let regex = self.fallback.get_or_init(|| {
+                            Regex::new(&self.expr).expect("fused DFA built from the same patterns")
+                        });
+                        regex_matches(regex, patterns, text, f);
+                        return;
+                    }
+                }
+            }
+            for (i, start, end) in spans {
+                f(i, start, end);
+            }
+        }
+    }
+}
+
+/// Value part of a match whose pattern includes context
+///
+/// The HTTP and process / thread id patterns match surrounding tokens
+/// (`"GET `, `HTTP/1.1" 200`, `pid=`, `sshd[…]:`) to avoid taking every
+/// number; the context stays literal text.
+fn value_span(pattern_type: PatternType, matched: &str) -> (usize, usize) {
+    match pattern_type {
+        PatternType::HttpMethod => {
+            let start = matched.find(|c: char| c.is_ascii_uppercase()).unwrap_or(0);
+            let len = matched[start..]
+                .find(|c: char| !c.is_ascii_uppercase())
+                .unwrap_or(matched.len() - start);
+            (start, start + len)
+        }
+        PatternType::HttpStatus => (matched.len() - 3, matched.len()),
+        PatternType::Pid | PatternType::Tid => {
+            let start = matched.find(|c: char| c.is_ascii_digit()).unwrap_or(0);
+            let len = matched[start..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(matched.len() - start);
+            (start, start + len)
+        }
+        _ => (0, matched.len()),
+    }
+}
+
+/// Append skeleton literal text, escaping `{` as `{{`
+fn push_escaped(skeleton: &mut String, literal: &str) {
+    let mut parts = literal.split('{');
+    if let Some(first) = parts.next() {
+        skeleton.push_str(first);
+    }
+    for part in parts {
+        skeleton.push_str("{{");
+        skeleton.push_str(part);
+    }
+}
+
+/// Process-wide default patterns, compiled on first use
+static DEFAULT_PATTERNS: OnceLock<Arc<CompiledPatterns>> = OnceLock::new();
+
+/// Get (compiling on first call) the shared default patterns
+///
+/// # Panics
+///
+/// Panics if the built-in fused regex fails to compile (should never happen with valid patterns).
+fn default_patterns() -> &'static Arc<CompiledPatterns> {
+    DEFAULT_PATTERNS.get_or_init(|| {
+        Arc::new(CompiledPatterns::compile(PatternConfig::default()).expect("Invalid fused regex"))
+    })
+}
+
 /// Tuned Pattern Learner with Fused Regex
 ///
 /// Uses a single combined regex for O(N) pattern extraction.
+/// Default construction is free after the first instance (or [`TunedPatternLearner::warm_up`]).
+#[derive(Clone)]
 pub struct TunedPatternLearner {
-    /// Combined regex with named capture groups
-    fused_regex: Regex,
-    /// Mapping from group name to pattern type
-    group_map: HashMap<&'static str, PatternType>,
-    /// Group names in order (for iteration)
-    group_names: Vec<&'static str>,
+    /// Combined regex with named capture groups, plus guards/exclusions (shared)
+    compiled: Arc<CompiledPatterns>,
 }
 
 impl TunedPatternLearner {
     /// Create a new tuned pattern learner
     ///
+    /// The first call in a process compiles the fused regex; later calls reuse it.
+    ///
     /// # Panics
     ///
     /// Panics if the built-in fused regex fails to compile (should never happen with valid patterns).
     #[must_use]
     pub fn new() -> Self {
-        // Build fused regex: (?P<TIMESTAMP>...)|(?P<UUID>...)|...
-        let expr = PATTERNS
-            .iter()
-            .map(|p| format!("(?P<{}>{})", p.name, p.pattern))
-            .collect::<Vec<_>>()
-            .join("|");
-
-        let fused_regex = Regex::new(&expr).expect("Invalid fused regex");
-
-        let mut group_map = HashMap::new();
-        let mut group_names = Vec::new();
-
-        for p in PATTERNS {
-            group_map.insert(p.name, p.pattern_type);
-            group_names.push(p.name);
+        Self {
+            compiled: Arc::clone(default_patterns()),
         }
+    }
 
-        Self {
-            fused_regex,
-            group_map,
-            group_names,
+    /// Create a learner with a custom pattern configuration
+    ///
+    /// A default config shares the process-wide regex.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an exclusion regex is invalid.
+    pub fn with_config(config: PatternConfig) -> Result<Self> {
+        if config.is_default() {
+            return Ok(Self::new());
         }
+        Ok(Self {
+            compiled: Arc::new(CompiledPatterns::compile(config)?),
+        })
+    }
+
+    /// Effective pattern configuration
+    #[must_use]
+    pub fn config(&self) -> &PatternConfig {
+        &self.compiled.config
+    }
+
+    /// Compile the shared fused regex now instead of on first use
+    ///
+    /// Call at service startup to keep the compile cost off the first request.
+    pub fn warm_up() {
+        let _ = default_patterns();
     }
 
     /// Find all matches in text with zero-copy (single pass)
     ///
     /// Returns matches in a `SmallVec` to avoid heap allocation for small match counts.
+    /// Applies the default [`PatternLimits`].
     #[must_use]
     pub fn find_matches<'a>(&self, text: &'a str) -> SmallVec<[TunedMatch<'a>; 32]> {
+        self.find_matches_limited(text, PatternLimits::default())
+    }
+
+    /// Find all matches, skipping lines rejected by `limits`
+    #[must_use]
+    pub fn find_matches_limited<'a>(
+        &self,
+        text: &'a str,
+        limits: PatternLimits,
+    ) -> SmallVec<[TunedMatch<'a>; 32]> {
+        self.find_unclaimed(text, limits, &mut Coverage::new())
+    }
+
+    /// [`Self::find_matches_limited`] outside ranges already in `coverage`
+    /// (claimed by column plugins); claims what it matches
+    pub(crate) fn find_unclaimed<'a>(
+        &self,
+        text: &'a str,
+        limits: PatternLimits,
+        coverage: &mut Coverage,
+    ) -> SmallVec<[TunedMatch<'a>; 32]> {
         let mut matches = SmallVec::new();
-        let mut covered = vec![false; text.len()];
-
-        for caps in self.fused_regex.captures_iter(text) {
-            // Find which named group matched
-            for &name in &self.group_names {
-                if let Some(m) = caps.name(name) {
-                    let start = m.start();
-                    let end = m.end();
-
-                    // Skip if region already covered by higher-priority pattern
-                    if covered[start..end].iter().any(|&c| c) {
-                        continue;
-                    }
 
-                    // Mark as covered
-                    for c in &mut covered[start..end] {
-                        *c = true;
-                    }
+        // Matches never span a newline, so scanning runs of allowed lines
+        // finds exactly what a whole-text scan finds on those lines
+        let compiled = &*self.compiled;
+        for (from, to) in limits.segments(text) {
+            compiled.for_each_match(&text[from..to], |i, start, end| {
+                let pattern_type = compiled.patterns[i].pattern_type;
+                let (value_start, value_end) =
+                    value_span(pattern_type, &text[from + start..from + end]);
+                let (start, end) = (from + start + value_start, from + start + value_end);
 
-                    matches.push(TunedMatch {
-                        pattern_type: self.group_map[name],
-                        start,
-                        end,
-                        matched_text: Cow::Borrowed(m.as_str()),
-                    });
-                    break;
+                // Rejected by a guard or exclusion: leave as literal text
+                if !compiled.accepts(pattern_type, text, start, end) {
+                    return;
                 }
-            }
+
+                // Skip if region already covered by higher-priority pattern
+                if !coverage.claim(start, end) {
+                    return;
+                }
+
+                matches.push(TunedMatch {
+                    pattern_type,
+                    start,
+                    end,
+                    matched_text: Cow::Borrowed(&text[start..end]),
+                });
+            });
         }
 
-        // Sort by position
-        matches.sort_by_key(|m: &TunedMatch| m.start);
         matches
     }
 
     /// Create skeleton text with placeholders and extract matches
     ///
     /// Returns (skeleton, matches) where skeleton has {0}, {1}, etc. placeholders.
+    /// Literal `{` in the text is escaped as `{{`, so input that already
+    /// contains `{0}` (format strings, JSON) restores exactly.
+    /// Applies the default [`PatternLimits`].
     #[must_use]
     pub fn extract_skeleton<'a>(&self, text: &'a str) -> (String, SmallVec<[TunedMatch<'a>; 32]>) {
-        let matches = self.find_matches(text);
-
-        if matches.is_empty() {
-            return (text.to_string(), matches);
-        }
-
-        // Build skeleton with capacity hint
-        let mut skeleton = String::with_capacity(text.len());
-        let mut last_end = 0;
-
-        for (i, m) in matches.iter().enumerate() {
-            // Add text before match
-            skeleton.push_str(&text[last_end..m.start]);
-            // Add placeholder
-            skeleton.push('{');
-            // Efficient integer formatting for small numbers
-            if i < 10 {
-                skeleton.push((b'0' + i as u8) as char);
-            } else {
-                skeleton.push_str(&i.to_string());
-            }
-            skeleton.push('}');
-            last_end = m.end;
-        }
-
-        // Add remaining text
-        skeleton.push_str(&text[last_end..]);
+        self.extract_skeleton_limited(text, PatternLimits::default())
+    }
 
+    /// Create skeleton text, skipping lines rejected by `limits`
+    #[must_use]
+    pub fn extract_skeleton_limited<'a>(
+        &self,
+        text: &'a str,
+        limits: PatternLimits,
+    ) -> (String, SmallVec<[TunedMatch<'a>; 32]>) {
+        let matches = self.find_matches_limited(text, limits);
+        let skeleton = build_skeleton(text, matches.iter().map(|m| (m.start, m.end)));
         (skeleton, matches)
     }
 
     /// Restore text from skeleton and matches
+    ///
+    /// Placeholders without a match are kept as written.
     #[must_use]
     pub fn restore_text(&self, skeleton: &str, matches: &[OwnedMatch]) -> String {
-        let mut result = skeleton.to_string();
-
-        // Replace in reverse order to maintain positions
-        for (i, m) in matches.iter().enumerate().rev() {
-            let placeholder = format!("{{{i}}}");
-            result = result.replace(&placeholder, &m.matched_text);
+        let mut result = String::with_capacity(skeleton.len());
+        for token in ColumnarPayload::parse_skeleton(skeleton) {
+            match token {
+                SkeletonToken::Text(text) => result.push_str(&text),
+                SkeletonToken::Ref(i) => match matches.get(i as usize) {
+                    Some(m) => result.push_str(&m.matched_text),
+                    None => {
+                        let _ = write!(result, "{{{i}}}");
+                    }
+                },
+            }
         }
-
         result
     }
 
@@ -309,6 +977,51 @@ impl Default for TunedPatternLearner {
     }
 }
 
+/// Skeleton of `text` with the ascending, disjoint `spans` replaced by
+/// `{0}`, `{1}`, ... and literal `{` escaped as `{{`
+pub(crate) fn build_skeleton(text: &str, spans: impl Iterator<Item = (usize, usize)>) -> String {
+    // Build skeleton with capacity hint
+    let mut skeleton = String::with_capacity(text.len());
+    let mut last_end = 0;
+
+    for (i, (start, end)) in spans.enumerate() {
+        // Add text before match
+        push_escaped(&mut skeleton, &text[last_end..start]);
+        // Add placeholder
+        skeleton.push('{');
+        // Efficient integer formatting for small numbers
+        if i < 10 {
+            skeleton.push((b'0' + i as u8) as char);
+        } else {
+            skeleton.push_str(&i.to_string());
+        }
+        skeleton.push('}');
+        last_end = end;
+    }
+
+    // Add remaining text
+    push_escaped(&mut skeleton, &text[last_end..]);
+    skeleton
+}
+
+/// Remove ANSI escape sequences (colors, cursor movement) from text
+///
+/// Lossy; see `CompressOptions::strip_ansi`. Borrows when there is nothing to strip.
+///
+/// # Panics
+///
+/// Panics if the built-in ANSI regex fails to compile (should never happen).
+#[must_use]
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    static ANSI_REGEX: OnceLock<Regex> = OnceLock::new();
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+    ANSI_REGEX
+        .get_or_init(|| Regex::new(ANSI_PATTERN).expect("Invalid ANSI regex"))
+        .replace_all(text, "")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -372,6 +1085,152 @@ mod tests {
         assert_eq!(matches[0].pattern_type, PatternType::Timestamp);
     }
 
+    #[test]
+    fn test_epoch_and_uptime_detection() {
+        let learner = TunedPatternLearner::new();
+        let text = "[ 1234.567890] ts=1705312245 ms=1705312245123 t=1705312245.5 id=17053122450";
+
+        let matches = learner.find_matches(text);
+        let found: Vec<(PatternType, &str)> = matches
+            .iter()
+            .map(|m| (m.pattern_type, m.matched_text.as_ref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (PatternType::Uptime, "[ 1234.567890]"),
+                (PatternType::Epoch, "1705312245"),
+                (PatternType::Epoch, "1705312245123"),
+                (PatternType::Epoch, "1705312245.5"),
+                (PatternType::Number, "17053122450"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mac_and_geo_detection() {
+        let learner = TunedPatternLearner::new();
+        let text = "lease 00:1a:2b:3c:4d:5e AA-BB-CC-DD-EE-FF at 35.6895, 139.6917 \
+                    -33.8688,151.2093 took 1.5, 2.5 ms id00:1a:2b:3c:4d:5e";
+
+        let matches = learner.find_matches(text);
+        let found: Vec<(PatternType, &str)> = matches
+            .iter()
+            .filter(|m| matches!(m.pattern_type, PatternType::Mac | PatternType::Geo))
+            .map(|m| (m.pattern_type, m.matched_text.as_ref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (PatternType::Mac, "00:1a:2b:3c:4d:5e"),
+                (PatternType::Mac, "AA-BB-CC-DD-EE-FF"),
+                (PatternType::Geo, "35.6895, 139.6917"),
+                (PatternType::Geo, "-33.8688,151.2093"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_http_detection() {
+        let learner = TunedPatternLearner::new();
+        let text = r#"10.0.0.1 - - "GET /index.html HTTP/1.1" 404 512 {"method":"POST","status": 503} method=PUT status=201 took 200 ms GETTER"#;
+
+        let matches = learner.find_matches(text);
+        let found: Vec<(PatternType, &str)> = matches
+            .iter()
+            .filter(|m| {
+                matches!(
+                    m.pattern_type,
+                    PatternType::HttpMethod | PatternType::HttpStatus
+                )
+            })
+            .map(|m| (m.pattern_type, m.matched_text.as_ref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (PatternType::HttpMethod, "GET"),
+                (PatternType::HttpStatus, "404"),
+                (PatternType::HttpMethod, "POST"),
+                (PatternType::HttpStatus, "503"),
+                (PatternType::HttpMethod, "PUT"),
+                (PatternType::HttpStatus, "201"),
+            ]
+        );
+        // A bare three-digit number stays a number
+        assert!(matches
+            .iter()
+            .any(|m| m.pattern_type == PatternType::Number && m.matched_text == "200"));
+    }
+
+    #[test]
+    fn test_pid_tid_detection() {
+        let learner = TunedPatternLearner::new();
+        let text = r#"sshd[8842]: accepted pid=12 tid=13 {"pid": 7, "thread_id": 9} [pid 4:tid 5] [12345] took 99"#;
+
+        let matches = learner.find_matches(text);
+        let found: Vec<(PatternType, &str)> = matches
+            .iter()
+            .filter(|m| matches!(m.pattern_type, PatternType::Pid | PatternType::Tid))
+            .map(|m| (m.pattern_type, m.matched_text.as_ref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (PatternType::Pid, "8842"),
+                (PatternType::Pid, "12"),
+                (PatternType::Tid, "13"),
+                (PatternType::Pid, "7"),
+                (PatternType::Tid, "9"),
+                (PatternType::Pid, "4"),
+                (PatternType::Tid, "5"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ansi_detection_and_strip() {
+        let learner = TunedPatternLearner::new();
+        let text = "\x1b[1;31mERROR\x1b[0m code 42 \x1b]0;title\x07done";
+
+        let matches = learner.find_matches(text);
+        let ansi: Vec<&str> = matches
+            .iter()
+            .filter(|m| m.pattern_type == PatternType::Ansi)
+            .map(|m| m.matched_text.as_ref())
+            .collect();
+        assert_eq!(ansi, ["\x1b[1;31m", "\x1b[0m", "\x1b]0;title\x07"]);
+        // Color parameters no longer leak into the number column
+        assert_eq!(
+            matches
+                .iter()
+                .filter(|m| m.pattern_type == PatternType::Number)
+                .count(),
+            1
+        );
+
+        assert_eq!(strip_ansi(text), "ERROR code 42 done");
+        assert!(matches!(strip_ansi("plain"), Cow::Borrowed("plain")));
+    }
+
+    #[test]
+    fn test_windows_paths() {
+        let learner = TunedPatternLearner::new();
+        let text =
+            r"Faulting module C:\Windows\System32\ntdll.dll, share \\fs01\logs\app, drive D:\";
+
+        let matches = learner.find_matches(text);
+        let paths: Vec<&str> = matches
+            .iter()
+            .filter(|m| m.pattern_type == PatternType::Path)
+            .map(|m| m.matched_text.as_ref())
+            .collect();
+        assert_eq!(
+            paths,
+            [r"C:\Windows\System32\ntdll.dll", r"\\fs01\logs\app", r"D:\"]
+        );
+    }
+
     #[test]
     fn test_large_text_performance() {
         let learner = TunedPatternLearner::new();
@@ -391,7 +1250,7 @@ mod tests {
 
     #[test]
     fn test_pattern_type_u8_roundtrip() {
-        for i in 0..=12u8 {
+        for i in 0..=15u8 {
             let pt = PatternType::from_u8(i);
             assert_eq!(pt.as_u8(), i);
         }
@@ -453,4 +1312,151 @@ mod tests {
             .map(|m| m.pattern_type)
             .any(|x| x == PatternType::IPv6));
     }
+
+    #[test]
+    fn test_shared_regex() {
+        TunedPatternLearner::warm_up();
+        let a = TunedPatternLearner::new();
+        let b = TunedPatternLearner::default();
+        assert!(Arc::ptr_eq(&a.compiled, &b.compiled));
+
+        let text = "2024-01-15 10:30:45 WARN disk /var/log at 91.5%";
+        let ma: Vec<_> = a
+            .find_matches(text)
+            .iter()
+            .map(|m| m.pattern_type)
+            .collect();
+        let mb: Vec<_> = b
+            .find_matches(text)
+            .iter()
+            .map(|m| m.pattern_type)
+            .collect();
+        assert_eq!(ma, mb);
+    }
+
+    #[test]
+    fn test_pattern_config_guards() {
+        let text = "release 1.2.3.4.5 build a1b2c3 from 10.0.0.1 took 42 ms";
+        let types = |learner: &TunedPatternLearner| -> Vec<(PatternType, String)> {
+            learner
+                .find_matches(text)
+                .iter()
+                .map(|m| (m.pattern_type, m.matched_text.to_string()))
+                .collect()
+        };
+
+        let default = types(&TunedPatternLearner::new());
+        assert!(default.contains(&(PatternType::IPv4, "1.2.3.4".to_string())));
+        assert!(default.contains(&(PatternType::Number, "1".to_string())));
+
+        let strict = types(&TunedPatternLearner::with_config(PatternConfig::strict()).unwrap());
+        assert_eq!(
+            strict,
+            [
+                (PatternType::IPv4, "10.0.0.1".to_string()),
+                (PatternType::Number, "42".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pattern_config_priority_and_exclusions() {
+        let text = "at 2024-01-15 10:30:45 from 127.0.0.1 and 10.1.1.1";
+
+        // Disabling the timestamp lets DATE and TIME take over
+        let learner =
+            TunedPatternLearner::with_config(PatternConfig::new().disable(PatternType::Timestamp))
+                .unwrap();
+        let types: Vec<_> = learner
+            .find_matches(text)
+            .iter()
+            .map(|m| m.pattern_type)
+            .collect();
+        assert_eq!(
+            types,
+            [
+                PatternType::Date,
+                PatternType::Time,
+                PatternType::IPv4,
+                PatternType::IPv4
+            ]
+        );
+
+        // Priority: NUMBER ahead of IPV4 splits addresses into numbers
+        let learner = TunedPatternLearner::with_config(
+            PatternConfig::new().with_priority(vec![PatternType::Number]),
+        )
+        .unwrap();
+        assert!(learner
+            .find_matches("from 10.1.1.1")
+            .iter()
+            .all(|m| m.pattern_type == PatternType::Number));
+
+        // Exclusion: loopback addresses stay literal
+        let learner = TunedPatternLearner::with_config(
+            PatternConfig::new().exclude(PatternType::IPv4, r"^127\."),
+        )
+        .unwrap();
+        let ips: Vec<_> = learner
+            .find_matches(text)
+            .iter()
+            .filter(|m| m.pattern_type == PatternType::IPv4)
+            .map(|m| m.matched_text.to_string())
+            .collect();
+        assert_eq!(ips, ["10.1.1.1"]);
+        assert_eq!(learner.config().exclusions.len(), 1);
+
+        assert!(TunedPatternLearner::with_config(
+            PatternConfig::new().exclude(PatternType::IPv4, "(")
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "dfa")]
+    #[test]
+    fn test_dfa_matches_regex() {
+        let compiled = default_patterns();
+        let text = "2024-01-15T10:30:45Z ERROR /var/log/app.log 0xdeadbeef 1705312245 \
+                    user@example.com https://example.com/a?b=1 ::1 \
+                    fe80:0000:0000:0000:0202:b3ff:fe1e:8329 [ 12.345] 42.5\n";
+        // ASCII runs on the DFA; `\b` next to kana forces the regex fallback
+        for text in [text.to_string(), format!("ログ{text}ログ 1705312245ログ")] {
+            let mut dfa = Vec::new();
+            compiled.for_each_match(&text, |i, s, e| dfa.push((i, s, e)));
+            let mut re = Vec::new();
+            let expr = Regex::new(&compiled.fused_dfa.expr).unwrap();
+            regex_matches(&expr, &compiled.patterns, &text, |i, s, e| {
+                re.push((i, s, e))
+            });
+            assert_eq!(dfa, re);
+            assert!(dfa.len() > 10);
+        }
+    }
+
+    #[test]
+    fn test_pattern_limits() {
+        let learner = TunedPatternLearner::new();
+        let long = format!("id={}", "9".repeat(64));
+        let text = format!("a 10.0.0.1\n{long}\nbin \0 42\nb 10.0.0.2");
+        let limits = PatternLimits::new().with_max_line_len(Some(32));
+        let found: Vec<_> = learner
+            .find_matches_limited(&text, limits)
+            .iter()
+            .map(|m| m.matched_text.to_string())
+            .collect();
+        assert_eq!(found, ["10.0.0.1", "10.0.0.2"]);
+
+        // Skipped lines stay literal in the skeleton
+        let (skeleton, _) = learner.extract_skeleton_limited(&text, limits);
+        assert_eq!(skeleton, format!("a {{0}}\n{long}\nbin \0 42\nb {{1}}"));
+
+        // Unlimited and the default limits see every line here
+        assert_eq!(
+            learner
+                .find_matches_limited(&text, PatternLimits::unlimited())
+                .len(),
+            4
+        );
+        assert_eq!(learner.find_matches(&text).len(), 3);
+    }
 }
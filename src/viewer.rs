@@ -0,0 +1,535 @@
+//! Viewer — interactive terminal browser for v3 archives
+//!
+//! Feature-gated: `#[cfg(feature = "tui")]`
+//!
+//! Backs `alice-text view`. Lines are reconstructed a screen at a time via
+//! [`QueryEngine::lines`], so opening a large archive decodes the skeleton
+//! and only the value columns the visible lines refer to. Filters run on the
+//! typed columns through [`QueryEngine::matching_lines`] and never render the
+//! lines they drop.
+//!
+//! | Key | Action |
+//! |-----|--------|
+//! | `j`/`k`, arrows | move one line |
+//! | `PgDn`/`Space`, `PgUp`/`b` | move one screen |
+//! | `g`/`Home`, `G`/`End` | first / last line |
+//! | `f` | filter (`log_levels=ERROR`, `timestamps>=2024-01-15 10:30`; join with `&`) |
+//! | `F` | clear filter |
+//! | `/`, `n`, `N` | search text, next / previous match |
+//! | `t` | jump to the first line at or after a timestamp |
+//! | `:` | jump to a line number |
+//! | `q`, `Ctrl-C` | quit |
+//!
+//! [`Viewer`] holds all state and is driven by [`Viewer::handle_key`] and
+//! [`Viewer::draw`]; [`run`] wires it to the terminal.
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use crate::query_engine::{parse_filter, parse_negation, Op, QueryEngine, QuerySource};
+use crate::{ALICETextError, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::Paragraph;
+use ratatui::Frame;
+use std::collections::HashMap;
+
+/// Decoded lines kept before the line cache is dropped
+const LINE_CACHE_LIMIT: usize = 50_000;
+
+/// Lines decoded per step while searching
+const SEARCH_CHUNK: usize = 4096;
+
+/// Input the bottom line is reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    /// Filter expression(s), `&`-separated
+    Filter,
+    /// Text to search for
+    Search,
+    /// Timestamp to jump to
+    Time,
+    /// 1-based line number to jump to
+    Line,
+}
+
+impl Prompt {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Filter => "filter: ",
+            Self::Search => "/",
+            Self::Time => "time: ",
+            Self::Line => ":",
+        }
+    }
+}
+
+/// Interactive view over one archive
+pub struct Viewer<S: QuerySource> {
+    engine: QueryEngine<S>,
+    title: String,
+    total: usize,
+    /// Filter expression and its matching line numbers (ascending)
+    filtered: Option<(String, Vec<usize>)>,
+    /// Selected row (index into the current view)
+    cursor: usize,
+    /// First visible row
+    top: usize,
+    /// Rows that fit on screen (updated on draw)
+    height: usize,
+    cache: HashMap<usize, String>,
+    search: Option<String>,
+    prompt: Option<(Prompt, String)>,
+    status: String,
+}
+
+impl<S: QuerySource> Viewer<S> {
+    /// Open a view over `engine`; `title` is shown in the status bar
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the skeleton cannot be read.
+    pub fn new(engine: QueryEngine<S>, title: impl Into<String>) -> Result<Self> {
+        let total = engine.line_count()?;
+        Ok(Self {
+            engine,
+            title: title.into(),
+            total,
+            filtered: None,
+            cursor: 0,
+            top: 0,
+            height: 20,
+            cache: HashMap::new(),
+            search: None,
+            prompt: None,
+            status: String::new(),
+        })
+    }
+
+    /// Rows in the current view (all lines, or the filter matches)
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.filtered
+            .as_ref()
+            .map_or(self.total, |(_, lines)| lines.len())
+    }
+
+    /// True if the current view has no rows
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Archive line number (0-based) of the selected row
+    #[must_use]
+    pub fn selected_line(&self) -> Option<usize> {
+        self.line_at(self.cursor)
+    }
+
+    /// Status message from the last action (errors, "not found", ...)
+    #[must_use]
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn line_at(&self, row: usize) -> Option<usize> {
+        match &self.filtered {
+            Some((_, lines)) => lines.get(row).copied(),
+            None => (row < self.total).then_some(row),
+        }
+    }
+
+    /// Move the selection by `delta` rows (clamped to the view)
+    pub fn move_by(&mut self, delta: isize) {
+        self.move_to(self.cursor.saturating_add_signed(delta));
+    }
+
+    /// Select `row` (clamped to the view) and scroll it into sight
+    pub fn move_to(&mut self, row: usize) {
+        self.cursor = row.min(self.len().saturating_sub(1));
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        } else if self.cursor >= self.top + self.height {
+            self.top = self.cursor + 1 - self.height;
+        }
+    }
+
+    /// Show only lines matching every `&`-separated condition
+    /// (`column=value`, `column>=value`, `not column=value`, ... as in `query --where`)
+    ///
+    /// The selection stays on the nearest matching line. Returns the number of matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a condition does not parse, names an unknown column,
+    /// or decompression fails.
+    pub fn set_filter(&mut self, expr: &str) -> Result<usize> {
+        let mut required = Vec::new();
+        let mut excluded = Vec::new();
+        for condition in expr.split('&') {
+            let (negated, condition) = parse_negation(condition);
+            let filter = parse_filter(condition).ok_or_else(|| {
+                ALICETextError::EncodingError(format!("Invalid filter: {condition}"))
+            })?;
+            if negated {
+                excluded.push(filter);
+            } else {
+                required.push(filter);
+            }
+        }
+        let lines = self.engine.filter_except(&required, &excluded)?.to_vec();
+        let anchor = self.selected_line().unwrap_or(0);
+        let row = lines.partition_point(|&line| line < anchor);
+        let count = lines.len();
+        self.filtered = Some((expr.trim().to_string(), lines));
+        self.top = 0;
+        self.move_to(row);
+        Ok(count)
+    }
+
+    /// Drop the filter, keeping the selected line
+    pub fn clear_filter(&mut self) {
+        let line = self.selected_line().unwrap_or(0);
+        self.filtered = None;
+        self.move_to(line);
+    }
+
+    /// Set the search text and select its next occurrence
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decompression fails.
+    pub fn search(&mut self, text: &str) -> Result<bool> {
+        self.search = (!text.is_empty()).then(|| text.to_string());
+        self.search_next(true)
+    }
+
+    /// Select the next (or previous) row containing the search text, wrapping around
+    ///
+    /// Returns `false` (selection unchanged) if no row in the view contains it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decompression fails.
+    pub fn search_next(&mut self, forward: bool) -> Result<bool> {
+        let (Some(text), len) = (self.search.clone(), self.len()) else {
+            return Ok(false);
+        };
+        // Every row once, starting next to the cursor
+        let order: Vec<usize> = (1..=len)
+            .map(|step| {
+                if forward {
+                    (self.cursor + step) % len
+                } else {
+                    (self.cursor + len - step) % len
+                }
+            })
+            .collect();
+        for chunk in order.chunks(SEARCH_CHUNK) {
+            let lines: Vec<usize> = chunk.iter().filter_map(|&row| self.line_at(row)).collect();
+            self.fetch(&lines)?;
+            let found = chunk.iter().find(|&&row| {
+                self.line_at(row)
+                    .and_then(|line| self.cache.get(&line))
+                    .is_some_and(|line| line.contains(&text))
+            });
+            if let Some(&row) = found {
+                self.move_to(row);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Select the first line whose timestamp is at or after `timestamp`
+    ///
+    /// With a filter active, the first matching row at or after that line.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive has no timestamps column, `timestamp`
+    /// does not parse, or decompression fails.
+    pub fn jump_to_time(&mut self, timestamp: &str) -> Result<bool> {
+        let lines = self
+            .engine
+            .matching_lines("timestamps", Op::Ge, timestamp)?;
+        let Some(first) = lines.first() else {
+            return Ok(false);
+        };
+        let row = match &self.filtered {
+            Some((_, view)) => view.partition_point(|&line| line < first),
+            None => first,
+        };
+        if row >= self.len() {
+            return Ok(false);
+        }
+        self.move_to(row);
+        Ok(true)
+    }
+
+    /// Handle one key press; returns `true` when the viewer should exit
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some((kind, mut input)) = self.prompt.take() {
+            match key.code {
+                KeyCode::Enter => self.submit(kind, &input),
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.prompt = Some((kind, input));
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.prompt = Some((kind, input));
+                }
+                _ => self.prompt = Some((kind, input)),
+            }
+            return false;
+        }
+
+        self.status.clear();
+        let page = isize::try_from(self.height.max(1)).unwrap_or(isize::MAX);
+        match key.code {
+            KeyCode::Char('q') => return true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Char('j') | KeyCode::Down => self.move_by(1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_by(-1),
+            KeyCode::Char(' ') | KeyCode::PageDown => self.move_by(page),
+            KeyCode::Char('b') | KeyCode::PageUp => self.move_by(-page),
+            KeyCode::Char('g') | KeyCode::Home => self.move_to(0),
+            KeyCode::Char('G') | KeyCode::End => self.move_to(usize::MAX),
+            KeyCode::Char('f') => self.prompt = Some((Prompt::Filter, String::new())),
+            KeyCode::Char('F') => self.clear_filter(),
+            KeyCode::Char('/') => self.prompt = Some((Prompt::Search, String::new())),
+            KeyCode::Char('t') => self.prompt = Some((Prompt::Time, String::new())),
+            KeyCode::Char(':') => self.prompt = Some((Prompt::Line, String::new())),
+            KeyCode::Char(c @ ('n' | 'N')) => {
+                let found = self.search_next(c == 'n');
+                self.report(found, "Pattern not found");
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Run a completed prompt
+    fn submit(&mut self, kind: Prompt, input: &str) {
+        match kind {
+            Prompt::Filter if input.trim().is_empty() => self.clear_filter(),
+            Prompt::Filter => match self.set_filter(input) {
+                Ok(count) => self.status = format!("{count} matching lines"),
+                Err(e) => self.status = e.to_string(),
+            },
+            Prompt::Search => {
+                let found = self.search(input);
+                self.report(found, "Pattern not found");
+            }
+            Prompt::Time => {
+                let found = self.jump_to_time(input.trim());
+                self.report(found, "No line at or after that time");
+            }
+            Prompt::Line => match input.trim().parse::<usize>() {
+                Ok(number) => {
+                    let line = number.saturating_sub(1);
+                    let row = match &self.filtered {
+                        Some((_, view)) => view.partition_point(|&l| l < line),
+                        None => line,
+                    };
+                    self.move_to(row);
+                }
+                Err(_) => self.status = format!("Not a line number: {input}"),
+            },
+        }
+    }
+
+    fn report(&mut self, outcome: Result<bool>, not_found: &str) {
+        match outcome {
+            Ok(true) => {}
+            Ok(false) => self.status = not_found.to_string(),
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    /// Decode the given archive lines into the cache (missing ones only)
+    fn fetch(&mut self, lines: &[usize]) -> Result<()> {
+        let missing: Vec<usize> = lines
+            .iter()
+            .copied()
+            .filter(|line| !self.cache.contains_key(line))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        if self.cache.len() + missing.len() > LINE_CACHE_LIMIT {
+            self.cache.clear();
+        }
+        let text = self.engine.lines(&missing)?;
+        self.cache.extend(missing.into_iter().zip(text));
+        Ok(())
+    }
+
+    /// Render the visible lines and the status bar
+    pub fn draw(&mut self, frame: &mut Frame<'_>) {
+        let [body, bar] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        self.height = usize::from(body.height).max(1);
+        self.move_to(self.cursor);
+
+        // Decode the screen plus one screen either side, so scrolling stays smooth
+        let end = (self.top + self.height).min(self.len());
+        let prefetch: Vec<usize> = (self.top.saturating_sub(self.height)
+            ..(end + self.height).min(self.len()))
+            .filter_map(|row| self.line_at(row))
+            .collect();
+        if let Err(e) = self.fetch(&prefetch) {
+            self.status = e.to_string();
+        }
+
+        let width = self.total.max(1).to_string().len();
+        let rows: Vec<Line<'_>> = (self.top..end)
+            .filter_map(|row| self.line_at(row).map(|line| (row, line)))
+            .map(|(row, line)| {
+                let text = self.cache.get(&line).map_or("", String::as_str);
+                let rendered = Line::raw(format!("{:>width$} │ {text}", line + 1));
+                if row == self.cursor {
+                    rendered.style(Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    rendered
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(rows), body);
+
+        let status = match &self.prompt {
+            Some((kind, input)) => format!("{}{input}", kind.label()),
+            None => {
+                let mut parts = vec![
+                    self.title.clone(),
+                    format!("{}/{}", (self.cursor + 1).min(self.len()), self.len()),
+                ];
+                if let Some((expr, _)) = &self.filtered {
+                    parts.push(format!("[{expr}]"));
+                }
+                if let Some(text) = &self.search {
+                    parts.push(format!("/{text}"));
+                }
+                if !self.status.is_empty() {
+                    parts.push(self.status.clone());
+                }
+                parts.join("  ")
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(status).style(Style::new().add_modifier(Modifier::REVERSED)),
+            bar,
+        );
+    }
+}
+
+/// Take over the terminal and run `viewer` until the user quits
+///
+/// # Errors
+///
+/// Returns an error if the terminal cannot be drawn to or read from.
+pub fn run<S: QuerySource>(mut viewer: Viewer<S>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = (|| -> Result<()> {
+        loop {
+            terminal.draw(|frame| viewer.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && viewer.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress_v3, CompressionLevel};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use std::io::Cursor;
+
+    fn viewer() -> Viewer<crate::BufferSource> {
+        let mut text = String::new();
+        for i in 0..300 {
+            let level = if i % 50 == 7 { "ERROR" } else { "INFO" };
+            text.push_str(&format!(
+                "2024-01-15 10:{:02}:{:02} {level} request {i} from 10.0.0.{}\n",
+                i / 60,
+                i % 60,
+                i % 200
+            ));
+        }
+        let data = compress_v3(&text, CompressionLevel::Fast).unwrap();
+        let engine = QueryEngine::from_reader(Cursor::new(data)).unwrap();
+        Viewer::new(engine, "test.atxt").unwrap()
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_viewer_navigation_filter_search() {
+        let mut viewer = viewer();
+        assert_eq!(viewer.len(), 300);
+        viewer.move_by(-5);
+        assert_eq!(viewer.selected_line(), Some(0));
+        viewer.move_to(usize::MAX);
+        assert_eq!(viewer.selected_line(), Some(299));
+
+        assert!(viewer.jump_to_time("2024-01-15 10:02:00").unwrap());
+        assert_eq!(viewer.selected_line(), Some(120));
+
+        // Filter keeps the nearest match selected
+        assert_eq!(viewer.set_filter("log_levels=ERROR").unwrap(), 6);
+        assert_eq!(viewer.selected_line(), Some(157));
+        assert_eq!(
+            viewer
+                .set_filter("log_levels=ERROR & timestamps<2024-01-15 10:01:00")
+                .unwrap(),
+            2
+        );
+        assert!(viewer.set_filter("no operator").is_err());
+
+        viewer.clear_filter();
+        assert!(viewer.search("request 42 ").unwrap());
+        assert_eq!(viewer.selected_line(), Some(42));
+        assert!(!viewer.search("not in the archive").unwrap());
+        assert_eq!(viewer.selected_line(), Some(42));
+
+        // Prompt driven through keys
+        for code in [KeyCode::Char(':'), KeyCode::Char('1'), KeyCode::Char('0')] {
+            viewer.handle_key(key(code));
+        }
+        assert!(!viewer.handle_key(key(KeyCode::Enter)));
+        assert_eq!(viewer.selected_line(), Some(9));
+        assert!(viewer.handle_key(key(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn test_viewer_draw() {
+        let mut viewer = viewer();
+        let mut terminal = Terminal::new(TestBackend::new(60, 6)).unwrap();
+        viewer.move_to(7);
+        terminal.draw(|frame| viewer.draw(frame)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let row = |y: u16| -> String {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect()
+        };
+        // Five body rows scrolled so row 7 (line 8) is the last one
+        assert!(row(0).starts_with("  4 │ 2024-01-15 10:00:03 INFO request 3"));
+        assert!(row(4).starts_with("  8 │ 2024-01-15 10:00:07 ERROR request 7"));
+        assert!(row(5).starts_with("test.atxt  8/300"));
+    }
+}
@@ -25,7 +25,19 @@
 //!                               v
 //!                     ALICE-Voice VoiceCodec (select layer)
 //! ```
+//!
+//! # Voice manifests
+//!
+//! [`VoiceManifest`] maps every `(locale, dialogue id)` to the synthesized
+//! audio asset it plays. Asset ids embed the entry's content hash, so after a
+//! localization update [`VoiceManifest::diff`] against the previous manifest
+//! lists exactly the lines that need re-synthesis.
+
+use std::collections::{BTreeMap, HashSet};
 
+use serde::{Deserialize, Serialize};
+
+use crate::dialogue::{DialogueEntry, DialogueTable, LocaleId, LocalizationTable};
 use crate::{ALICEText, EncodingMode};
 use alice_voice::{VoiceCodecConfig, VoiceQuality};
 
@@ -149,6 +161,153 @@ pub fn codec_config_from_hints(hints: &SpeechHints) -> VoiceCodecConfig {
     VoiceCodecConfig::for_quality(hints.suggested_quality)
 }
 
+/// Synthesized-audio asset for one dialogue line.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceAsset {
+    /// Locale the line is played in.
+    pub locale: LocaleId,
+    /// Dialogue entry id.
+    pub id: u32,
+    /// Locale whose text is spoken (the base locale for lines without a delta).
+    pub source_locale: LocaleId,
+    /// Speaker index in the table's `SpeakerDictionary`.
+    pub speaker: u16,
+    /// `DialogueEntry::content_hash` of the spoken entry.
+    pub content_hash: u64,
+    /// Asset identifier: `{source_locale}/{id}-{content_hash:016x}`.
+    pub asset_id: String,
+}
+
+impl VoiceAsset {
+    fn new(locale: LocaleId, source_locale: LocaleId, entry: &DialogueEntry) -> Self {
+        let content_hash = entry.content_hash();
+        Self {
+            locale,
+            id: entry.id,
+            source_locale,
+            speaker: entry.speaker,
+            content_hash,
+            asset_id: format!("{}/{}-{content_hash:016x}", source_locale.0, entry.id),
+        }
+    }
+}
+
+/// Dialogue-to-voice manifest, ordered by `(locale, id)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceManifest {
+    pub assets: Vec<VoiceAsset>,
+}
+
+/// Lines whose audio must be (re)synthesized or dropped.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ManifestDiff {
+    /// New `(locale, id)` pairs.
+    pub added: Vec<VoiceAsset>,
+    /// Pairs whose spoken content changed.
+    pub changed: Vec<VoiceAsset>,
+    /// Pairs no longer in the manifest.
+    pub removed: Vec<VoiceAsset>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Distinct assets to synthesize (a base line shared by several locales once).
+    pub fn to_synthesize(&self) -> Vec<&VoiceAsset> {
+        let mut seen = HashSet::new();
+        self.added
+            .iter()
+            .chain(&self.changed)
+            .filter(|asset| seen.insert(asset.asset_id.as_str()))
+            .collect()
+    }
+}
+
+impl VoiceManifest {
+    /// Manifest for a single-locale table.
+    pub fn from_table(table: &DialogueTable, locale: LocaleId) -> Self {
+        let mut assets: Vec<VoiceAsset> = table
+            .iter()
+            .map(|entry| VoiceAsset::new(locale, locale, entry))
+            .collect();
+        assets.sort_by_key(|a| a.id);
+        Self { assets }
+    }
+
+    /// Manifest for every locale; lines without a delta reuse the base asset.
+    pub fn from_localization(table: &LocalizationTable) -> Self {
+        let mut locales = table.available_locales();
+        locales.sort_by_key(|l| l.0);
+        let mut assets = Vec::new();
+        for locale in locales {
+            let delta = table.locale_deltas.get(&locale);
+            for base in table.base_table.iter() {
+                let asset = match delta.and_then(|d| d.get(base.id)) {
+                    Some(entry) if locale != table.base_locale => {
+                        VoiceAsset::new(locale, locale, entry)
+                    }
+                    _ => VoiceAsset::new(locale, table.base_locale, base),
+                };
+                assets.push(asset);
+            }
+        }
+        assets.sort_by_key(|a| (a.locale.0, a.id));
+        Self { assets }
+    }
+
+    /// Asset played for `id` in `locale`.
+    pub fn get(&self, locale: LocaleId, id: u32) -> Option<&VoiceAsset> {
+        self.assets
+            .binary_search_by_key(&(locale.0, id), |a| (a.locale.0, a.id))
+            .ok()
+            .map(|i| &self.assets[i])
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Changes relative to `previous` (by content hash).
+    pub fn diff(&self, previous: &Self) -> ManifestDiff {
+        let key = |a: &VoiceAsset| (a.locale.0, a.id);
+        let before: BTreeMap<_, &VoiceAsset> =
+            previous.assets.iter().map(|a| (key(a), a)).collect();
+        let after: BTreeMap<_, &VoiceAsset> = self.assets.iter().map(|a| (key(a), a)).collect();
+
+        let mut diff = ManifestDiff::default();
+        for (k, asset) in &after {
+            match before.get(k) {
+                None => diff.added.push((*asset).clone()),
+                Some(old) if old.content_hash != asset.content_hash => {
+                    diff.changed.push((*asset).clone());
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .iter()
+            .filter(|(k, _)| !after.contains_key(k))
+            .map(|(_, a)| (*a).clone())
+            .collect();
+        diff
+    }
+
+    /// Serialize as pretty JSON.
+    pub fn to_json(&self) -> crate::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,4 +359,46 @@ mod tests {
         assert_eq!(config.sample_rate, 16000);
         assert_eq!(config.lpc_order, 10);
     }
+
+    fn entry(id: u32, text: &str) -> DialogueEntry {
+        DialogueEntry {
+            id,
+            speaker: 0,
+            text: text.to_string(),
+            ruby: None,
+        }
+    }
+
+    #[test]
+    fn test_voice_manifest_diff() {
+        let mut table = LocalizationTable::new(LocaleId::JA);
+        table.base_table.add(entry(0, "おはよう"));
+        table.base_table.add(entry(1, "さようなら"));
+        table.add_delta(LocaleId::EN, entry(0, "Good morning"));
+        let before = VoiceManifest::from_localization(&table);
+        assert_eq!(before.len(), 4);
+
+        // Untranslated EN line plays the JA asset
+        let fallback = before.get(LocaleId::EN, 1).unwrap();
+        assert_eq!(fallback.source_locale, LocaleId::JA);
+        assert_eq!(
+            fallback.asset_id,
+            before.get(LocaleId::JA, 1).unwrap().asset_id
+        );
+
+        table.add_delta(LocaleId::EN, entry(0, "Morning!"));
+        table.add_delta(LocaleId::EN, entry(1, "Goodbye"));
+        let after = VoiceManifest::from_localization(&table);
+        let diff = after.diff(&before);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 2);
+        assert!(diff
+            .to_synthesize()
+            .iter()
+            .all(|a| a.locale == LocaleId::EN));
+        assert!(after.diff(&after).is_empty());
+
+        let json = after.to_json().unwrap();
+        assert_eq!(VoiceManifest::from_json(&json).unwrap(), after);
+    }
 }
@@ -0,0 +1,279 @@
+//! Zstd parameters — long-distance matching, window size and workers
+//!
+//! [`ZstdParams`] travels in [`CompressOptions::zstd`](crate::CompressOptions)
+//! and applies to the payload / column compression of both writers. For
+//! multi-gigabyte inputs, long-distance matching and a larger window find
+//! repeats far apart (e.g. the same stack trace an hour later) that the
+//! level's default window misses; workers split compression across threads.
+//!
+//! A decoder only accepts windows up to 2^27 bytes unless told otherwise, so
+//! a larger `window_log` is recorded in the archive: the critical
+//! [`HeaderExtension::ZSTD_WINDOW`](crate::HeaderExtension::ZSTD_WINDOW)
+//! record in v3 (so v3 readers that predate it refuse the archive instead of
+//! failing mid-column), and byte 10 of the v2.1
+//! [`TunedHeader`](crate::TunedHeader). v2 readers before 2.1 ignore that
+//! byte; they fail on the 2.1 payload marker, and on the frame itself, since
+//! their zstd decoder keeps the default limit. Readers check the recorded
+//! window against [`ZstdParams::WINDOW_LOG_MAX`] before decoding anything.
+//!
+//! ```rust,ignore
+//! use alice_text::{CompressOptions, FormatV3Writer, Level, ZstdParams};
+//!
+//! let zstd = ZstdParams::new()
+//!     .with_long_distance_matching(true)
+//!     .with_window_log(Some(30))
+//!     .with_workers(4);
+//! let archive = FormatV3Writer::new(Level::Best)
+//!     .with_options(CompressOptions::new().with_zstd(zstd))
+//!     .compress(&text)?;
+//! ```
+//!
+//! License: BSL 1.1
+//! Author: Moroya Sakamoto
+
+use std::io::Read;
+
+use zstd::stream::raw::CParameter;
+
+use crate::{ALICETextError, Result};
+
+/// Zstd encoder parameters beyond the level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZstdParams {
+    /// Long-distance matching (finds repeats across the whole window)
+    pub long_distance_matching: bool,
+    /// Window size as a power of two (`None`: chosen by the level)
+    pub window_log: Option<u32>,
+    /// Compression threads (0: compress on the calling thread; more needs
+    /// the `zstdmt` feature)
+    pub workers: u32,
+}
+
+impl ZstdParams {
+    /// Smallest window log zstd accepts
+    pub const WINDOW_LOG_MIN: u32 = 10;
+
+    /// Largest window log this build can encode and decode
+    pub const WINDOW_LOG_MAX: u32 = if cfg!(target_pointer_width = "64") {
+        31
+    } else {
+        30
+    };
+
+    /// Largest window log a decoder accepts without being told
+    pub const WINDOW_LOG_DEFAULT_LIMIT: u32 = 27;
+
+    /// Level defaults only
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            long_distance_matching: false,
+            window_log: None,
+            workers: 0,
+        }
+    }
+
+    /// Enable or disable long-distance matching
+    #[must_use]
+    pub const fn with_long_distance_matching(mut self, enabled: bool) -> Self {
+        self.long_distance_matching = enabled;
+        self
+    }
+
+    /// Set the window log (`None`: chosen by the level)
+    #[must_use]
+    pub const fn with_window_log(mut self, window_log: Option<u32>) -> Self {
+        self.window_log = window_log;
+        self
+    }
+
+    /// Set the number of compression threads
+    #[must_use]
+    pub const fn with_workers(mut self, workers: u32) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Whether every parameter is left to the level
+    #[must_use]
+    pub const fn is_default(&self) -> bool {
+        !self.long_distance_matching && self.window_log.is_none() && self.workers == 0
+    }
+
+    /// Window log readers must be told to accept (`None` if within their default)
+    #[must_use]
+    pub fn required_window_log(&self) -> Option<u8> {
+        self.window_log
+            .filter(|&log| log > Self::WINDOW_LOG_DEFAULT_LIMIT)
+            .and_then(|log| u8::try_from(log).ok())
+    }
+
+    /// # Errors
+    ///
+    /// Returns `EncodingError` if the window log is outside
+    /// [`Self::WINDOW_LOG_MIN`]..=[`Self::WINDOW_LOG_MAX`], or workers are
+    /// requested in a build without the `zstdmt` feature.
+    pub fn validate(&self) -> Result<()> {
+        if self.workers > 0 && !cfg!(feature = "zstdmt") {
+            return Err(ALICETextError::EncodingError(
+                "Zstd workers need the `zstdmt` feature".to_string(),
+            ));
+        }
+        match self.window_log {
+            Some(log) if !(Self::WINDOW_LOG_MIN..=Self::WINDOW_LOG_MAX).contains(&log) => {
+                Err(ALICETextError::EncodingError(format!(
+                    "Zstd window log {log} is outside {}..={}",
+                    Self::WINDOW_LOG_MIN,
+                    Self::WINDOW_LOG_MAX
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Bulk compressor at `level` with these parameters (and `dictionary`, if not empty)
+    pub(crate) fn compressor(
+        &self,
+        level: i32,
+        dictionary: &[u8],
+    ) -> Result<zstd::bulk::Compressor<'static>> {
+        self.validate()?;
+        let zstd_error =
+            |e: std::io::Error| ALICETextError::EncodingError(format!("Zstd error: {e}"));
+        let mut compressor =
+            zstd::bulk::Compressor::with_dictionary(level, dictionary).map_err(zstd_error)?;
+        if self.long_distance_matching {
+            compressor
+                .set_parameter(CParameter::EnableLongDistanceMatching(true))
+                .map_err(zstd_error)?;
+        }
+        if let Some(log) = self.window_log {
+            compressor
+                .set_parameter(CParameter::WindowLog(log))
+                .map_err(zstd_error)?;
+        }
+        if self.workers > 0 {
+            compressor
+                .set_parameter(CParameter::NbWorkers(self.workers))
+                .map_err(zstd_error)?;
+        }
+        Ok(compressor)
+    }
+
+    /// Compress `data` at `level` (default parameters: a plain streaming frame)
+    pub(crate) fn compress(&self, data: &[u8], level: i32) -> Result<Vec<u8>> {
+        if self.is_default() {
+            return zstd::stream::encode_all(data, level)
+                .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")));
+        }
+        self.compressor(level, &[])?
+            .compress(data)
+            .map_err(|e| ALICETextError::EncodingError(format!("Zstd error: {e}")))
+    }
+}
+
+/// Reject a recorded window log this build cannot decode
+///
+/// # Errors
+///
+/// Returns `DecompressionError` if `window_log` exceeds [`ZstdParams::WINDOW_LOG_MAX`].
+pub fn check_window_log(window_log: u8) -> Result<()> {
+    if u32::from(window_log) > ZstdParams::WINDOW_LOG_MAX {
+        return Err(ALICETextError::DecompressionError(format!(
+            "Archive needs a 2^{window_log} byte zstd window; this build decodes up to 2^{}",
+            ZstdParams::WINDOW_LOG_MAX
+        )));
+    }
+    Ok(())
+}
+
+/// Decompress a zstd frame, accepting windows up to 2^`window_log` bytes
+/// (0: the decoder default), with `dictionary` if not empty
+///
+/// Stops with `LimitExceeded` once the output passes `max_bytes` (see
+/// [`DecodeLimits`](crate::DecodeLimits)).
+pub(crate) fn decompress(
+    data: &[u8],
+    dictionary: &[u8],
+    window_log: u8,
+    max_bytes: u64,
+) -> Result<Vec<u8>> {
+    check_window_log(window_log)?;
+    let zstd_error =
+        |e: std::io::Error| ALICETextError::DecompressionError(format!("Zstd error: {e}"));
+    let mut decoder =
+        zstd::stream::read::Decoder::with_dictionary(data, dictionary).map_err(zstd_error)?;
+    if window_log > 0 {
+        decoder
+            .window_log_max(u32::from(window_log))
+            .map_err(zstd_error)?;
+    }
+    let mut out = Vec::new();
+    decoder
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut out)
+        .map_err(zstd_error)?;
+    if out.len() as u64 > max_bytes {
+        return Err(ALICETextError::LimitExceeded {
+            field: "decoded zstd frame",
+            value: out.len() as u64,
+            limit: max_bytes,
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_and_required_window() {
+        assert!(ZstdParams::new().is_default());
+        assert!(ZstdParams::new()
+            .with_window_log(Some(9))
+            .validate()
+            .is_err());
+        assert!(ZstdParams::new()
+            .with_window_log(Some(40))
+            .validate()
+            .is_err());
+        assert_eq!(
+            ZstdParams::new()
+                .with_window_log(Some(24))
+                .required_window_log(),
+            None
+        );
+        assert_eq!(
+            ZstdParams::new()
+                .with_window_log(Some(29))
+                .required_window_log(),
+            Some(29)
+        );
+        assert!(check_window_log(40).is_err());
+        assert_eq!(
+            ZstdParams::new().with_workers(2).validate().is_ok(),
+            cfg!(feature = "zstdmt")
+        );
+    }
+
+    #[test]
+    fn test_large_window_needs_decoder_limit() {
+        // A streaming frame of unknown size declares the full window, so a
+        // small input is enough to need the larger decoder limit
+        let data: Vec<u8> = (0..1u32 << 14)
+            .flat_map(|i| i.wrapping_mul(2_654_435_761).to_le_bytes())
+            .collect();
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 1).unwrap();
+        encoder.set_parameter(CParameter::WindowLog(28)).unwrap();
+        std::io::Write::write_all(&mut encoder, &data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert!(decompress(&compressed, &[], 0, u64::MAX).is_err());
+        assert_eq!(decompress(&compressed, &[], 28, u64::MAX).unwrap(), data);
+        assert!(matches!(
+            decompress(&compressed, &[], 28, 1 << 10),
+            Err(ALICETextError::LimitExceeded { limit: 1024, .. })
+        ));
+    }
+}
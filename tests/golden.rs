@@ -0,0 +1,86 @@
+//! Golden-file compatibility suite
+//!
+//! Every archive in `tests/fixtures` was written by a past release and is
+//! committed once, never regenerated. Each must still decode to its source
+//! byte-exactly; see `alice_text::stability` for the policy.
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use alice_text::stability::{archive_version, decompress_any, WRITTEN_VERSIONS};
+use alice_text::{DecodeOptions, FormatV3Metadata, FormatV3Writer};
+
+/// (archive, source it was written from)
+const FIXTURES: &[(&str, &str)] = &[
+    ("v1_0_prose.atxt", "prose.txt"),
+    ("v1_1_prose.atxt", "prose.txt"),
+    ("v1_1_prose_ngram.atxt", "prose.txt"),
+    ("v2_0_service.atxt", "service.log"),
+    ("v2_0_small.atxt", "small.txt"),
+    ("v2_0_crlf_binary.atxt", "crlf_binary.log"),
+    ("v2_1_service.atxt", "service.log"),
+    ("v2_1_crlf_binary.atxt", "crlf_binary.log"),
+    ("v3_0_service.atxt", "service.log"),
+    ("v3_0_service_inline_skeleton.atxt", "service.log"),
+    ("v3_0_crlf_binary.atxt", "crlf_binary.log"),
+    ("v3_1_service.atxt", "service.log"),
+    ("v3_1_crlf_binary.atxt", "crlf_binary.log"),
+];
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+#[test]
+fn golden_archives_decode_byte_exactly() {
+    for (archive, source) in FIXTURES {
+        let data = fixture(archive);
+        let decoded = decompress_any(&data).unwrap_or_else(|e| panic!("{archive}: {e}"));
+        assert!(
+            decoded == fixture(source),
+            "{archive} does not match {source}"
+        );
+    }
+}
+
+#[test]
+fn golden_archive_names_match_versions() {
+    for (archive, _) in FIXTURES {
+        let (major, minor) = archive_version(&fixture(archive)).unwrap();
+        assert!(
+            archive.starts_with(&format!("v{major}_{minor}_")),
+            "{archive} holds version {major}.{minor}"
+        );
+    }
+}
+
+#[test]
+fn every_written_version_has_a_fixture() {
+    for (major, minor) in WRITTEN_VERSIONS {
+        let prefix = format!("v{major}_{minor}_");
+        assert!(
+            FIXTURES
+                .iter()
+                .any(|(archive, _)| archive.starts_with(&prefix)),
+            "version {major}.{minor} is written but has no golden fixture; \
+             commit one to tests/fixtures"
+        );
+    }
+}
+
+#[test]
+fn golden_v3_archives_pass_strict_decoding() {
+    for (archive, _) in FIXTURES.iter().filter(|(a, _)| a.starts_with("v3_")) {
+        let data = fixture(archive);
+        let metadata = FormatV3Metadata::read_from(&mut Cursor::new(&data)).unwrap();
+        assert!(metadata.unknown_columns.is_empty(), "{archive}");
+        assert!(
+            FormatV3Writer::decompress_with(&data, DecodeOptions::strict()).is_ok(),
+            "{archive}"
+        );
+    }
+}
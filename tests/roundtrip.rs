@@ -0,0 +1,307 @@
+//! Byte-exact round-trip matrix
+//!
+//! Adversarial representatives for every pattern type: values a typed column
+//! could normalise (zero padding, case, offsets, precision, out-of-range
+//! fields). Each line must be detected as its pattern and come back byte for
+//! byte from every writer; values a column cannot hold exactly are kept as
+//! raw text.
+
+use alice_text::{
+    compress_v3, decompress_v3, CompressionLevel, FormatV3Writer, Level, PatternConfig,
+    TunedCompressor, TunedPatternLearner, TunedPatternType as P,
+};
+
+/// (pattern, lines containing a value of that pattern)
+const MATRIX: &[(P, &[&str])] = &[
+    (
+        P::Timestamp,
+        &[
+            "2024-01-15 10:30:45 plain",
+            "2024-01-15T10:30:45+0900 offset without colon",
+            "2024-01-15T10:30:45-05:30 half-hour offset",
+            "2024-01-15T10:30:45+14:00 largest offset",
+            "2024-01-15T10:30:45-00:00 negative zero offset",
+            "2024-01-15 10:30:45+09:00 space and offset",
+            "2024-01-15T10:30:45.000Z zero millis",
+            "2024-01-15T10:30:45.123456789Z nanos",
+            "2024-01-15 10:30:46.5 one fraction digit",
+            "2024-02-30 10:30:45 impossible date",
+            "2024-12-31 23:59:60 leap second",
+        ],
+    ),
+    (
+        P::Date,
+        &[
+            "on 2024-02-29 leap day",
+            "on 1969-12-31 before epoch",
+            "on 0000-01-01 year zero",
+            "on 2024-13-45 out of range",
+        ],
+    ),
+    (
+        P::Time,
+        &[
+            "at 10:30:45 plain",
+            "at 10:30:45.000 zero millis",
+            "at 23:59:59.9999 sub-millisecond",
+            "at 23:59:60 leap second",
+            "at 25:61:61 out of range",
+        ],
+    ),
+    (
+        P::IPv4,
+        &[
+            "from 192.168.1.100 plain",
+            "from 010.001.001.001 zero padded",
+            "from 1.2.3.04 one padded octet",
+            "version 1.2.3.4 release",
+            "from 0.0.0.0 any",
+        ],
+    ),
+    (
+        P::IPv6,
+        &[
+            "via 2001:db8:85a3:1:2:8a2e:370:7334 canonical",
+            "via 2001:0db8:0000:0000:0000:0000:0000:0001 zero padded",
+            "via 2001:0DB8:85A3:0001:0002:8A2E:0370:7334 upper case",
+            "via 0:0:0:0:0:0:0:0 unspecified",
+        ],
+    ),
+    (
+        P::UUID,
+        &[
+            "id 550e8400-e29b-41d4-a716-446655440000 lower",
+            "id 550E8400-E29B-41D4-A716-446655440000 upper",
+            "id 550e8400-E29B-41d4-a716-446655440000 mixed",
+        ],
+    ),
+    (
+        P::LogLevel,
+        &["INFO plain", "WARNING long form", "CRITICAL x"],
+    ),
+    (
+        P::Path,
+        &[
+            "open /var/log/../app.log",
+            "open C:\\Windows\\System32\\ntdll.dll",
+        ],
+    ),
+    (P::URL, &["get https://example.com/a?b=c&d=%20#frag"]),
+    (
+        P::Number,
+        &[
+            "n 007 octal-looking",
+            "n 00 zeros",
+            "n 1.50 trailing zero",
+            "n 0.0 zero fraction",
+            "n 9007199254740993 past f64 integers",
+            "n 123456789012345678901234567890 past i64",
+            "n 3.14159265358979323846 past f64 precision",
+            "n 5 plain",
+        ],
+    ),
+    (P::Hex, &["at 0x00FF padded", "at 0xdeadBEEF mixed case"]),
+    (P::Email, &["to Alice.Smith+tag@Example.COM x"]),
+    (
+        P::Epoch,
+        &[
+            "at 1705312245 seconds",
+            "at 1705312245.000100 padded fraction",
+            "at 1705312245123 millis",
+        ],
+    ),
+    (
+        P::Uptime,
+        &[
+            "[    0.000000] boot",
+            "[ 1234.5] short fraction",
+            "[12345678.123456789] nanos",
+        ],
+    ),
+    (P::Ansi, &["\x1b[31mred\x1b[0m", "\x1b[0;1;31mbold red"]),
+    (
+        P::Mac,
+        &[
+            "mac aa:bb:cc:dd:ee:ff lower",
+            "mac AA-BB-CC-DD-EE-FF upper dashes",
+            "mac aa:BB:cc:dd:ee:ff mixed case",
+        ],
+    ),
+    (
+        P::Geo,
+        &[
+            "at 35.6895, 139.6917 tokyo",
+            "at -0.0000, -0.0000 negative zero",
+            "at 35.68950, 139.69170 trailing zeros",
+            "at -33.8688197, 151.2092955 seven digits",
+        ],
+    ),
+    (
+        P::Pod,
+        &[
+            "pod web-7fb96c846b-x5n2k ready",
+            "pod api-5d8f7-bcdfg ready",
+        ],
+    ),
+    (
+        P::Namespace,
+        &["namespace=kube-system x", "\"namespace\": \"default\" x"],
+    ),
+    (
+        P::ContainerId,
+        &[
+            "containerd://3f4e5d6c7b8a99887766554433221100aabbccddeeff00112233445566778899 up",
+            "id 3f4e5d6c7b8a99887766554433221100aabbccddeeff00112233445566778899 bare",
+        ],
+    ),
+    (
+        P::Klog,
+        &[
+            "I0115 10:30:45.123456   12345 main.go:42] started",
+            "E0230 10:30:45.123456 1 x.go:1] impossible date",
+            "F0101 00:00:00.000000 000 x.go:01] zero padded",
+        ],
+    ),
+    (
+        P::Cri,
+        &[
+            "2024-01-15T10:30:45.123456789Z stdout F hello",
+            "2024-01-15T10:30:45Z stderr P no fraction",
+            "2024-01-15T10:30:45.100Z stdout F trailing zeros",
+        ],
+    ),
+    (
+        P::HttpMethod,
+        &["\"GET / HTTP/1.1\" 200 x", "method=PATCH x"],
+    ),
+    (
+        P::HttpStatus,
+        &[
+            "\"GET / HTTP/1.1\" 404 x",
+            "\"status\": 200 x",
+            "status=599 x",
+        ],
+    ),
+    (
+        P::Duration,
+        &[
+            "took 123ms x",
+            "took 1m60s overflowing seconds",
+            "took 1h0m0s zero components",
+            "took 1.50s trailing zero",
+            "took 00ms zero padded",
+            "took 5\u{b5}s micro sign",
+        ],
+    ),
+    (
+        P::ByteSize,
+        &[
+            "size 300KB x",
+            "size 1.50KB trailing zero",
+            "size 007MB zero padded",
+            "size 0.5B half a byte",
+        ],
+    ),
+    (
+        P::Hostname,
+        &[
+            "host web-01.prod.example.com x",
+            "host Web-01.PROD.example.com mixed case",
+        ],
+    ),
+    (
+        P::Pid,
+        &[
+            "pid=4242 x",
+            "pid=0123 zero padded",
+            "pid=99999999999 past u32",
+            "sshd[0042]: zero padded",
+        ],
+    ),
+    (P::Tid, &["tid=00 x", "thread_id: 4294967296 past u32"]),
+];
+
+fn config() -> PatternConfig {
+    PatternConfig::new().with_kubernetes()
+}
+
+/// Every writer under test (built once: pattern compilation dominates)
+struct Writers {
+    v2: Vec<TunedCompressor>,
+    v3: FormatV3Writer,
+}
+
+impl Writers {
+    fn new(v2_levels: &[Level]) -> Self {
+        Self {
+            v2: v2_levels
+                .iter()
+                .map(|&level| {
+                    TunedCompressor::new(level)
+                        .with_pattern_config(config())
+                        .unwrap()
+                })
+                .collect(),
+            v3: FormatV3Writer::new(CompressionLevel::Fast)
+                .with_pattern_config(config())
+                .unwrap(),
+        }
+    }
+
+    /// Every writer must restore `text` exactly
+    fn assert_roundtrip(&mut self, text: &str) {
+        for compressor in &mut self.v2 {
+            let compressed = compressor.compress(text).unwrap();
+            assert_eq!(compressor.decompress(&compressed).unwrap(), text, "v2");
+        }
+        let compressed = self.v3.compress(text).unwrap();
+        assert_eq!(FormatV3Writer::decompress(&compressed).unwrap(), text, "v3");
+        // Default patterns too
+        assert_eq!(
+            decompress_v3(&compress_v3(text, CompressionLevel::Fast).unwrap()).unwrap(),
+            text
+        );
+    }
+}
+
+#[test]
+fn representatives_are_detected() {
+    let learner = TunedPatternLearner::with_config(config()).unwrap();
+    for (pattern, lines) in MATRIX {
+        for line in *lines {
+            assert!(
+                learner
+                    .find_matches(line)
+                    .iter()
+                    .any(|m| m.pattern_type == *pattern),
+                "{line:?} is not detected as {pattern:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn every_pattern_roundtrips_byte_exactly() {
+    // Zstd 22 contexts make `Level::Max` slow per call; the mixed document covers it
+    let mut writers = Writers::new(&[Level::Fast, Level::Balanced]);
+    for (_, lines) in MATRIX {
+        for line in *lines {
+            // Alone, without a trailing newline, and repeated (delta columns)
+            writers.assert_roundtrip(line);
+            writers.assert_roundtrip(&format!("{line}\n").repeat(3));
+        }
+    }
+}
+
+#[test]
+fn mixed_document_roundtrips_byte_exactly() {
+    let lines: Vec<&str> = MATRIX
+        .iter()
+        .flat_map(|(_, lines)| *lines)
+        .copied()
+        .collect();
+    let document = lines.join("\n");
+    let mut writers = Writers::new(&[Level::Fast, Level::Balanced, Level::Max]);
+    writers.assert_roundtrip(&document);
+    writers.assert_roundtrip(&format!("{document}\r\n").repeat(20));
+}